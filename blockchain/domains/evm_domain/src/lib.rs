@@ -1,6 +1,8 @@
-use runtime::Tx;
+use runtime::{DomainProof, Tx};
 use serde::{Deserialize, Serialize};
 
+pub use runtime::{CrossDomainPacket, LightClientHeader};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EvmDomainConfig {
     pub chain_id: String,
@@ -10,30 +12,19 @@ pub struct EvmDomainConfig {
     pub token_model: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CrossDomainPacket {
-    pub src_domain: String,
-    pub dst_domain: String,
-    pub sequence: u64,
-    pub payload: serde_json::Value,
-    pub timeout_height: u64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LightClientHeader {
-    pub state_root: [u8; 32],
-    pub validator_set_hash: [u8; 32],
-    pub height: u64,
-}
-
 pub fn validate_batch(txs: &[Tx]) -> bool {
     // Placeholder: enforce EVM-specific rules
     !txs.is_empty()
 }
 
-pub fn verify_packet(packet: &CrossDomainPacket, header: &LightClientHeader) -> bool {
-    // Simplified: ensure packet hasn't timed out and header height progresses.
-    header.height <= packet.timeout_height && packet.sequence > 0
+/// Whether `proof` shows `packet` committed under `header`'s state root.
+/// Timeouts are checked against the Kova height when the packet is received.
+pub fn verify_packet(
+    packet: &CrossDomainPacket,
+    header: &LightClientHeader,
+    proof: &DomainProof,
+) -> bool {
+    runtime::verify_packet_proof(packet, header, proof)
 }
 
 #[macro_export]
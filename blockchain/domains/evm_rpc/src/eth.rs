@@ -0,0 +1,182 @@
+//! Translation between Ethereum JSON-RPC shapes and the node's EVM domain
+//! views. Domains have no block hashes of their own, so blocks are named by
+//! a hash derived from their height.
+
+use std::fmt::LowerHex;
+
+use alloy_primitives::{keccak256, U256};
+use anyhow::Context;
+use serde_json::{json, Value};
+
+/// Hex quantity as JSON-RPC encodes numbers.
+pub fn quantity(n: impl LowerHex) -> String {
+    format!("0x{n:x}")
+}
+
+pub fn parse_quantity(s: &str) -> anyhow::Result<u64> {
+    let digits = s
+        .strip_prefix("0x")
+        .context("quantity must be 0x-prefixed")?;
+    u64::from_str_radix(digits, 16).with_context(|| format!("invalid quantity {s}"))
+}
+
+/// Height a block tag names; `None` for the tip.
+pub fn parse_block(tag: Option<&Value>) -> anyhow::Result<Option<u64>> {
+    match tag.and_then(Value::as_str) {
+        None | Some("latest" | "pending" | "safe" | "finalized") => Ok(None),
+        Some("earliest") => Ok(Some(0)),
+        Some(n) => parse_quantity(n).map(Some),
+    }
+}
+
+/// Call payload the node runs for an `eth_call` or `eth_estimateGas`
+/// transaction object.
+pub fn call_payload(call: &Value) -> anyhow::Result<Value> {
+    let value = match call["value"].as_str() {
+        Some(v) => U256::from_str_radix(v.trim_start_matches("0x"), 16)
+            .with_context(|| format!("invalid value {v}"))?,
+        None => U256::ZERO,
+    };
+    let input = call["input"].as_str().or(call["data"].as_str());
+    Ok(json!({
+        "from": call["from"],
+        "to": call["to"],
+        "input": input,
+        "value": value.to_string(),
+    }))
+}
+
+pub fn block_hash(height: u64) -> String {
+    keccak256(height.to_be_bytes()).to_string()
+}
+
+pub fn tx_hash(raw: &[u8]) -> String {
+    keccak256(raw).to_string()
+}
+
+/// Balance from the node's decimal string.
+pub fn balance(decimal: &str) -> anyhow::Result<String> {
+    let balance = U256::from_str_radix(decimal, 10).context("invalid balance")?;
+    Ok(quantity(balance))
+}
+
+/// An Ethereum log from one in the node's `/evm/logs` view.
+pub fn log(log: &Value) -> Value {
+    let height = log["block_height"].as_u64().unwrap_or_default();
+    json!({
+        "address": log["address"],
+        "topics": log["topics"],
+        "data": log["data"],
+        "blockNumber": quantity(height),
+        "blockHash": block_hash(height),
+        "transactionHash": log["tx_hash"],
+        "transactionIndex": "0x0",
+        "logIndex": quantity(log["log_index"].as_u64().unwrap_or_default()),
+        "removed": false,
+    })
+}
+
+/// An Ethereum receipt from the node's `/evm/tx/:hash` view. Logs are
+/// indexed within the tx.
+pub fn receipt(view: &Value) -> Value {
+    let trace = &view["trace"];
+    let height = view["block_height"].as_u64().unwrap_or_default();
+    let gas_used = quantity(view["gas_used"].as_u64().unwrap_or_default());
+    let logs: Vec<Value> = trace["logs"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            log(&json!({
+                "address": entry["address"],
+                "topics": entry["topics"],
+                "data": entry["data"],
+                "block_height": height,
+                "tx_hash": trace["tx_hash"],
+                "log_index": index,
+            }))
+        })
+        .collect();
+    json!({
+        "transactionHash": trace["tx_hash"],
+        "transactionIndex": "0x0",
+        "blockNumber": quantity(height),
+        "blockHash": block_hash(height),
+        "from": trace["from"],
+        "to": trace["to"],
+        "contractAddress": trace["contract_address"],
+        "gasUsed": gas_used,
+        "cumulativeGasUsed": gas_used,
+        "effectiveGasPrice": "0x0",
+        "status": if trace["status"] == "success" { "0x1" } else { "0x0" },
+        "logs": logs,
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "type": "0x0",
+    })
+}
+
+/// A header-only block; wallets read it to detect fee markets.
+pub fn block(height: u64) -> Value {
+    json!({
+        "number": quantity(height),
+        "hash": block_hash(height),
+        "parentHash": block_hash(height.saturating_sub(1)),
+        "timestamp": "0x0",
+        "gasLimit": quantity(30_000_000u64),
+        "gasUsed": "0x0",
+        "baseFeePerGas": "0x0",
+        "transactions": [],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_objects_become_node_payloads() {
+        let call = json!({
+            "from": "0x0000000000000000000000000000000000000001",
+            "to": "0x0000000000000000000000000000000000000002",
+            "data": "0x1234",
+            "value": "0x10",
+        });
+        let payload = call_payload(&call).unwrap();
+        assert_eq!(payload["input"], "0x1234");
+        assert_eq!(payload["value"], "16");
+        assert!(call_payload(&json!({ "value": "0xzz" })).is_err());
+    }
+
+    #[test]
+    fn block_tags_and_quantities_parse() {
+        assert_eq!(parse_block(None).unwrap(), None);
+        assert_eq!(parse_block(Some(&json!("latest"))).unwrap(), None);
+        assert_eq!(parse_block(Some(&json!("earliest"))).unwrap(), Some(0));
+        assert_eq!(parse_block(Some(&json!("0x1f"))).unwrap(), Some(31));
+        assert!(parse_quantity("31").is_err());
+        assert_eq!(balance("255").unwrap(), "0xff");
+    }
+
+    #[test]
+    fn receipts_report_status_and_index_their_logs() {
+        let view = json!({
+            "block_height": 3,
+            "gas_used": 21000,
+            "trace": {
+                "tx_hash": "0xaa",
+                "status": "revert",
+                "logs": [
+                    { "address": "0x01", "topics": [], "data": "0x" },
+                    { "address": "0x01", "topics": [], "data": "0x" },
+                ],
+            },
+        });
+        let receipt = receipt(&view);
+        assert_eq!(receipt["status"], "0x0");
+        assert_eq!(receipt["blockNumber"], "0x3");
+        assert_eq!(receipt["logs"][1]["logIndex"], "0x1");
+        assert_eq!(receipt["logs"][1]["blockHash"], block_hash(3));
+    }
+}
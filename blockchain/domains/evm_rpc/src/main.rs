@@ -0,0 +1,396 @@
+//! Ethereum JSON-RPC for one EVM domain, so wallets and Ethereum tooling
+//! work against it. Signed txs are relayed to the node as `DomainExecute`
+//! calls carrying the raw tx, signed and paid for by the relayer key; reads
+//! go to the node's `/domain/:id/evm` views.
+
+use std::{env, net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+use ed25519_dalek::SigningKey;
+use runtime::{address_from_pubkey, Address, DomainCall};
+use sdk_rust::{build_domain_execute_signed, KovaClient};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{net::TcpListener, sync::Mutex};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+mod eth;
+
+/// Kova gas limit of the tx relaying a signed EVM tx.
+const DEFAULT_RELAY_GAS_LIMIT: u64 = 1_000_000;
+/// JSON-RPC error code for reverted calls, as Ethereum clients use it.
+const EXECUTION_ERROR: i64 = 3;
+const INVALID_REQUEST: i64 = -32600;
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Clone)]
+struct AppState {
+    client: KovaClient,
+    http: reqwest::Client,
+    rpc: String,
+    domain_id: Uuid,
+    /// Kova chain id the relayed txs are signed for.
+    chain_id: String,
+    evm_chain_id: u64,
+    relay_gas_limit: u64,
+    relayer: Arc<SigningKey>,
+    /// Next nonce of the relayer; `None` until read from the node, and again
+    /// after a rejected send.
+    relayer_nonce: Arc<Mutex<Option<u64>>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn params(err: anyhow::Error) -> Self {
+        Self::new(INVALID_PARAMS, format!("{err:#}"))
+    }
+
+    fn server(err: impl std::fmt::Display) -> Self {
+        Self::new(SERVER_ERROR, err.to_string())
+    }
+}
+
+type RpcResult = Result<Value, RpcError>;
+
+fn param(params: &[Value], index: usize) -> Result<&Value, RpcError> {
+    params
+        .get(index)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("missing param {index}")))
+}
+
+fn str_param(params: &[Value], index: usize) -> Result<&str, RpcError> {
+    param(params, index)?
+        .as_str()
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("param {index} must be a string")))
+}
+
+impl AppState {
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}/domain/{}/evm{path}",
+            self.rpc.trim_end_matches('/'),
+            self.domain_id
+        )
+    }
+
+    /// GETs a node view; `None` when the node has nothing under `path`.
+    async fn get(&self, path: &str) -> Result<Option<Value>, RpcError> {
+        let res = self
+            .http
+            .get(self.url(path))
+            .send()
+            .await
+            .map_err(RpcError::server)?;
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !res.status().is_success() {
+            let status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            return Err(RpcError::new(INVALID_PARAMS, format!("{status}: {body}")));
+        }
+        res.json().await.map(Some).map_err(RpcError::server)
+    }
+
+    async fn account(&self, address: &str) -> Result<Value, RpcError> {
+        self.get(&format!("/account/{address}"))
+            .await?
+            .ok_or_else(|| RpcError::server("evm domain not found"))
+    }
+
+    /// Runs an `eth_call` transaction object on the node, failing with the
+    /// revert data when the call doesn't succeed.
+    async fn simulate(&self, call: &Value) -> Result<Value, RpcError> {
+        let payload = eth::call_payload(call).map_err(RpcError::params)?;
+        let res = self
+            .http
+            .post(self.url("/call"))
+            .json(&payload)
+            .send()
+            .await
+            .map_err(RpcError::server)?;
+        if !res.status().is_success() {
+            let body = res.text().await.unwrap_or_default();
+            return Err(RpcError::new(EXECUTION_ERROR, body));
+        }
+        let result: Value = res.json().await.map_err(RpcError::server)?;
+        if result["status"] != "success" {
+            let reason = result["halt_reason"]
+                .as_str()
+                .unwrap_or("execution reverted");
+            return Err(RpcError {
+                code: EXECUTION_ERROR,
+                message: reason.to_string(),
+                data: Some(result["output"].clone()),
+            });
+        }
+        Ok(result)
+    }
+
+    async fn height(&self) -> Result<u64, RpcError> {
+        let status = self.client.status().await.map_err(RpcError::server)?;
+        Ok(status.height.saturating_sub(1))
+    }
+
+    async fn send_raw(&self, raw: &str) -> RpcResult {
+        let raw = hex::decode(raw.trim_start_matches("0x"))
+            .map_err(|err| RpcError::new(INVALID_PARAMS, format!("invalid raw tx: {err}")))?;
+        let hash = eth::tx_hash(&raw);
+        let call = DomainCall {
+            domain_id: self.domain_id,
+            payload: Value::Null,
+            raw,
+            max_gas: None,
+        };
+        let mut next = self.relayer_nonce.lock().await;
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => {
+                let relayer: Address =
+                    address_from_pubkey(&self.relayer.verifying_key().to_bytes());
+                self.client
+                    .get_nonce(&relayer)
+                    .await
+                    .map_err(RpcError::server)?
+            }
+        };
+        let tx = build_domain_execute_signed(
+            &self.chain_id,
+            call,
+            self.relayer.as_ref(),
+            nonce,
+            self.relay_gas_limit,
+        )
+        .map_err(RpcError::server)?;
+        match self.client.send_tx(&tx).await {
+            Ok(_) => {
+                *next = Some(nonce + 1);
+                Ok(json!(hash))
+            }
+            Err(err) => {
+                warn!("relaying evm tx {hash} failed: {err}");
+                *next = None;
+                Err(RpcError::server(err))
+            }
+        }
+    }
+
+    async fn logs(&self, filter: &Value) -> RpcResult {
+        let tip = self.height().await?;
+        let from = eth::parse_block(filter.get("fromBlock"))
+            .map_err(RpcError::params)?
+            .unwrap_or(tip);
+        let to = eth::parse_block(filter.get("toBlock"))
+            .map_err(RpcError::params)?
+            .unwrap_or(tip);
+        let mut query = format!("/logs?from_block={from}&to_block={to}");
+        if let Some(address) = filter["address"].as_str() {
+            query.push_str(&format!("&address={address}"));
+        }
+        if let Some(topic0) = filter["topics"][0].as_str() {
+            query.push_str(&format!("&topic0={topic0}"));
+        }
+        let logs = self.get(&query).await?.unwrap_or_else(|| json!([]));
+        // Lists of addresses are matched here rather than by the node.
+        let addresses: Option<Vec<String>> = filter["address"].as_array().map(|list| {
+            list.iter()
+                .filter_map(|a| a.as_str().map(str::to_lowercase))
+                .collect()
+        });
+        let logs: Vec<Value> = logs
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter(|log| {
+                addresses.as_ref().is_none_or(|list| {
+                    let address = log["address"].as_str().unwrap_or_default();
+                    list.contains(&address.to_lowercase())
+                })
+            })
+            .map(eth::log)
+            .collect();
+        Ok(json!(logs))
+    }
+
+    async fn dispatch(&self, method: &str, params: &[Value]) -> RpcResult {
+        match method {
+            "eth_chainId" => Ok(json!(eth::quantity(self.evm_chain_id))),
+            "net_version" => Ok(json!(self.evm_chain_id.to_string())),
+            "web3_clientVersion" => Ok(json!(concat!("kova-evm-rpc/", env!("CARGO_PKG_VERSION")))),
+            "eth_blockNumber" => Ok(json!(eth::quantity(self.height().await?))),
+            "eth_gasPrice" | "eth_maxPriorityFeePerGas" => Ok(json!("0x0")),
+            "eth_getBalance" => {
+                let account = self.account(str_param(params, 0)?).await?;
+                let balance = account["balance"].as_str().unwrap_or("0");
+                eth::balance(balance)
+                    .map(|b| json!(b))
+                    .map_err(RpcError::server)
+            }
+            "eth_getTransactionCount" => {
+                let account = self.account(str_param(params, 0)?).await?;
+                Ok(json!(eth::quantity(account["nonce"].as_u64().unwrap_or(0))))
+            }
+            "eth_getCode" => {
+                let account = self.account(str_param(params, 0)?).await?;
+                Ok(account["code"].clone())
+            }
+            "eth_call" => {
+                let result = self.simulate(param(params, 0)?).await?;
+                Ok(result["output"].clone())
+            }
+            "eth_estimateGas" => {
+                let result = self.simulate(param(params, 0)?).await?;
+                Ok(json!(eth::quantity(
+                    result["gas_used"].as_u64().unwrap_or_default()
+                )))
+            }
+            "eth_sendRawTransaction" => self.send_raw(str_param(params, 0)?).await,
+            "eth_getTransactionReceipt" => {
+                let hash = str_param(params, 0)?;
+                let view = self.get(&format!("/tx/{hash}")).await?;
+                Ok(view.as_ref().map(eth::receipt).unwrap_or(Value::Null))
+            }
+            "eth_getLogs" => self.logs(param(params, 0)?).await,
+            "eth_getBlockByNumber" => {
+                let height = match eth::parse_block(params.first()).map_err(RpcError::params)? {
+                    Some(height) => height,
+                    None => self.height().await?,
+                };
+                Ok(eth::block(height))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("method {method} not supported"),
+            )),
+        }
+    }
+
+    async fn handle(&self, request: Value) -> Value {
+        let request: RpcRequest = match serde_json::from_value(request) {
+            Ok(request) => request,
+            Err(err) => {
+                return response(
+                    Value::Null,
+                    Err(RpcError::new(INVALID_REQUEST, err.to_string())),
+                )
+            }
+        };
+        let result = self.dispatch(&request.method, &request.params).await;
+        response(request.id, result)
+    }
+}
+
+fn response(id: Value, result: RpcResult) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": err.code, "message": err.message, "data": err.data },
+        }),
+    }
+}
+
+/// Serves single requests and batches alike.
+async fn serve_rpc(State(state): State<AppState>, Json(body): Json<Value>) -> Json<Value> {
+    match body {
+        Value::Array(requests) => {
+            let mut responses = Vec::with_capacity(requests.len());
+            for request in requests {
+                responses.push(state.handle(request).await);
+            }
+            Json(Value::Array(responses))
+        }
+        request => Json(state.handle(request).await),
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+    let rpc = env::var("NODE_RPC").unwrap_or_else(|_| "http://validator1:8545".into());
+    let chain_id = env::var("CHAIN_ID").unwrap_or_else(|_| "kova-devnet".into());
+    let domain_id: Uuid = env::var("EVM_DOMAIN")
+        .map_err(|_| anyhow::anyhow!("EVM_DOMAIN env var (domain uuid) required"))?
+        .parse()?;
+    let relay_gas_limit = env::var("RELAY_GAS_LIMIT")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_RELAY_GAS_LIMIT);
+
+    let sk_hex = env::var("RELAYER_SK")
+        .map_err(|_| anyhow::anyhow!("RELAYER_SK env var (hex ed25519 key) required"))?;
+    let sk_bytes = hex::decode(sk_hex.trim_start_matches("0x"))?;
+    let relayer = SigningKey::from_bytes(
+        sk_bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("RELAYER_SK must be 32 bytes"))?,
+    );
+
+    let mut state = AppState {
+        client: KovaClient::new(rpc.clone()),
+        http: reqwest::Client::new(),
+        rpc,
+        domain_id,
+        chain_id,
+        evm_chain_id: 0,
+        relay_gas_limit,
+        relayer: Arc::new(relayer),
+        relayer_nonce: Arc::new(Mutex::new(None)),
+    };
+    let domain = state
+        .get("")
+        .await
+        .map_err(|err| anyhow::anyhow!("cannot read evm domain: {}", err.message))?
+        .ok_or_else(|| anyhow::anyhow!("{domain_id} is not an evm domain"))?;
+    state.evm_chain_id = domain["chain_id"]
+        .as_u64()
+        .ok_or_else(|| anyhow::anyhow!("node returned no evm chain id"))?;
+
+    let app = Router::new()
+        .route("/", post(serve_rpc))
+        .route("/healthz", get(|| async { "ok" }))
+        .with_state(state.clone());
+
+    let addr: SocketAddr = env::var("EVM_RPC_ADDR")
+        .unwrap_or_else(|_| "0.0.0.0:8546".into())
+        .parse()?;
+    info!(
+        "serving evm domain {} (chain id {}) on {}",
+        domain_id, state.evm_chain_id, addr
+    );
+    let listener = TcpListener::bind(addr).await?;
+    axum::serve(listener, app.into_make_service()).await?;
+    Ok(())
+}
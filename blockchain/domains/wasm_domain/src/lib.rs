@@ -1,3 +1,4 @@
+use runtime::WasmLimits;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,8 +8,9 @@ pub struct WasmDomainConfig {
     pub da_mode: String,
 }
 
-pub fn validate_module(_wasm_bytes: &[u8]) -> bool {
-    // Placeholder: ensure module meets policies
-    true
+/// Whether a domain with default limits would accept `wasm_bytes` for
+/// deployment.
+pub fn validate_module(wasm_bytes: &[u8]) -> bool {
+    WasmLimits::default().validate(wasm_bytes).is_ok()
 }
 
@@ -0,0 +1,17 @@
+-- Blocks rolled back by a chain reorganization, kept for audit.
+
+CREATE TABLE IF NOT EXISTS orphaned_blocks (
+    id BIGSERIAL PRIMARY KEY,
+    height BIGINT NOT NULL,
+    hash BYTEA NOT NULL,
+    parent_hash BYTEA NOT NULL,
+    state_root BYTEA NOT NULL,
+    tx_count INT NOT NULL,
+    tx_hashes BYTEA[] NOT NULL DEFAULT '{}',
+    -- Height the indexed chain was rolled back to.
+    fork_height BIGINT NOT NULL,
+    orphaned_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS idx_orphaned_blocks_height ON orphaned_blocks (height DESC);
+CREATE INDEX IF NOT EXISTS idx_orphaned_blocks_hash ON orphaned_blocks (hash);
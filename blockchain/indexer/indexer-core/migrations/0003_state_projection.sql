@@ -0,0 +1,31 @@
+-- Account and staking state re-derived by replaying blocks through the
+-- runtime. A row holds the value from `height` until the next row for the
+-- same key; the current value is the row with the greatest height.
+
+CREATE TABLE IF NOT EXISTS balances (
+    address BYTEA NOT NULL,
+    height BIGINT NOT NULL,
+    balance NUMERIC(39, 0) NOT NULL,
+    nonce BIGINT NOT NULL,
+    PRIMARY KEY (address, height)
+);
+
+CREATE INDEX IF NOT EXISTS idx_balances_height ON balances (height);
+
+CREATE TABLE IF NOT EXISTS staking_positions (
+    -- validator, delegation, exit or unbonding.
+    kind TEXT NOT NULL,
+    owner BYTEA NOT NULL,
+    -- Nil for unbondings not tied to a validator.
+    validator_id UUID NOT NULL,
+    -- Only non-zero for unbondings.
+    release_height BIGINT NOT NULL,
+    height BIGINT NOT NULL,
+    -- 0 once the position is closed.
+    amount NUMERIC(39, 0) NOT NULL,
+    PRIMARY KEY (kind, owner, validator_id, release_height, height)
+);
+
+CREATE INDEX IF NOT EXISTS idx_staking_positions_owner ON staking_positions (owner, height DESC);
+CREATE INDEX IF NOT EXISTS idx_staking_positions_validator ON staking_positions (validator_id, height DESC);
+CREATE INDEX IF NOT EXISTS idx_staking_positions_height ON staking_positions (height);
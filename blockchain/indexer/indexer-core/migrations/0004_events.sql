@@ -0,0 +1,18 @@
+-- Structured runtime events, recorded while replaying blocks for the state
+-- projection. Block-level events (rewards, the exit queue) have no tx.
+
+CREATE TABLE IF NOT EXISTS events (
+    id BIGSERIAL PRIMARY KEY,
+    block_height BIGINT NOT NULL REFERENCES blocks (height) ON DELETE CASCADE,
+    tx_id BIGINT REFERENCES transactions (id) ON DELETE CASCADE,
+    -- Order of the event within its block.
+    position INT NOT NULL,
+    kind TEXT NOT NULL,
+    -- String values: hex addresses and hashes, UUIDs, decimal amounts.
+    attributes JSONB NOT NULL DEFAULT '{}',
+    UNIQUE (block_height, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_kind ON events (kind, block_height);
+CREATE INDEX IF NOT EXISTS idx_events_tx ON events (tx_id);
+CREATE INDEX IF NOT EXISTS idx_events_attributes ON events USING GIN (attributes);
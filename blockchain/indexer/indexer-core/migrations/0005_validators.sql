@@ -0,0 +1,18 @@
+-- Validator commission and profile, re-derived by the state projection. A
+-- row holds the values from `height` until the next row for the same
+-- validator. Stake lives in `staking_positions`.
+
+CREATE TABLE IF NOT EXISTS validators (
+    validator_id UUID NOT NULL,
+    height BIGINT NOT NULL,
+    owner BYTEA NOT NULL,
+    status TEXT NOT NULL,
+    commission_rate SMALLINT NOT NULL,
+    moniker TEXT NOT NULL,
+    website TEXT NOT NULL,
+    details TEXT NOT NULL,
+    PRIMARY KEY (validator_id, height)
+);
+
+CREATE INDEX IF NOT EXISTS idx_validators_owner ON validators (owner, height DESC);
+CREATE INDEX IF NOT EXISTS idx_validators_height ON validators (height);
@@ -0,0 +1,8 @@
+-- Treasury pool balance, re-derived by the state projection. A row holds the
+-- balance from `height` until the next row. Payouts are the
+-- `treasury_spend` rows in `events`.
+
+CREATE TABLE IF NOT EXISTS treasury_balances (
+    height BIGINT PRIMARY KEY,
+    balance NUMERIC(39, 0) NOT NULL
+);
@@ -0,0 +1,28 @@
+-- User-issued assets and their holders, re-derived by the state projection.
+-- A row holds the value from `height` until the next row for the same key.
+
+CREATE TABLE IF NOT EXISTS assets (
+    asset_id UUID NOT NULL,
+    height BIGINT NOT NULL,
+    symbol TEXT NOT NULL,
+    decimals SMALLINT NOT NULL,
+    issuer BYTEA NOT NULL,
+    supply NUMERIC(39, 0) NOT NULL,
+    -- Null for uncapped assets.
+    max_supply NUMERIC(39, 0),
+    PRIMARY KEY (asset_id, height)
+);
+
+CREATE INDEX IF NOT EXISTS idx_assets_height ON assets (height);
+
+CREATE TABLE IF NOT EXISTS asset_holders (
+    asset_id UUID NOT NULL,
+    holder BYTEA NOT NULL,
+    height BIGINT NOT NULL,
+    -- 0 once the holder has emptied its balance.
+    balance NUMERIC(39, 0) NOT NULL,
+    PRIMARY KEY (asset_id, holder, height)
+);
+
+CREATE INDEX IF NOT EXISTS idx_asset_holders_holder ON asset_holders (holder, height DESC);
+CREATE INDEX IF NOT EXISTS idx_asset_holders_height ON asset_holders (height);
@@ -0,0 +1,6 @@
+-- Privacy actions name the pool they touched; rows from before pools could
+-- be chosen all went to the default pool.
+
+ALTER TABLE privacy_actions ADD COLUMN IF NOT EXISTS pool TEXT NOT NULL DEFAULT 'shielded';
+
+CREATE INDEX IF NOT EXISTS idx_privacy_actions_pool ON privacy_actions (pool, action);
@@ -0,0 +1,5 @@
+-- Batch commits carry the roots their rollup proof attests to. Rows from
+-- before proofs were required have none.
+
+ALTER TABLE rollup_batches ADD COLUMN IF NOT EXISTS state_root BYTEA;
+ALTER TABLE rollup_batches ADD COLUMN IF NOT EXISTS da_root BYTEA;
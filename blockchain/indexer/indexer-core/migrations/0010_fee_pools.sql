@@ -0,0 +1,10 @@
+-- Fee pool balances, re-derived by the state projection. A row holds the
+-- balances from `height` until the next row.
+
+CREATE TABLE IF NOT EXISTS fee_pools (
+    height BIGINT PRIMARY KEY,
+    l1_gas NUMERIC(39, 0) NOT NULL,
+    da NUMERIC(39, 0) NOT NULL,
+    sequencer NUMERIC(39, 0) NOT NULL,
+    treasury NUMERIC(39, 0) NOT NULL
+);
@@ -0,0 +1,32 @@
+use anyhow::Context;
+use indexer_core::{events, explorer, graphql};
+use sqlx::postgres::PgPoolOptions;
+use std::env;
+use tracing::info;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt().with_env_filter("info").init();
+    let database_url =
+        env::var("DATABASE_URL").context("DATABASE_URL env var is required for indexer-api")?;
+    let api_addr = env::var("API_ADDR").unwrap_or_else(|_| "0.0.0.0:8090".to_string());
+    let max_conn: u32 = env::var("DB_POOL_SIZE")
+        .unwrap_or_else(|_| "5".to_string())
+        .parse()
+        .unwrap_or(5);
+
+    // The indexer owns the schema and its migrations; this only reads.
+    let pool = PgPoolOptions::new()
+        .max_connections(max_conn)
+        .connect(&database_url)
+        .await?;
+    let app = graphql::router(graphql::schema(pool.clone()))
+        .merge(events::router(pool.clone()))
+        .merge(explorer::router(pool));
+    let listener = tokio::net::TcpListener::bind(&api_addr)
+        .await
+        .with_context(|| format!("binding api listener on {api_addr}"))?;
+    info!("serving graphql on http://{api_addr}/graphql, events on /events and the explorer on /explorer");
+    axum::serve(listener, app.into_make_service()).await?;
+    Ok(())
+}
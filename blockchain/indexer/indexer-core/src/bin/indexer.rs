@@ -1,7 +1,9 @@
 use anyhow::Context;
-use indexer_core::{BlockSink, PostgresSink};
+use indexer_core::projection::Projection;
+use indexer_core::{BlockSink, ForkDetected, PostgresSink};
+use metrics::Metrics;
 use reqwest::StatusCode;
-use runtime::Block;
+use runtime::{devnet_genesis, hash_block, Block, GenesisConfig};
 use std::env;
 use tokio::time::{sleep, Duration};
 use tracing::{error, info, warn};
@@ -25,24 +27,61 @@ async fn main() -> anyhow::Result<()> {
         .parse()
         .unwrap_or(5);
 
+    let metrics_addr =
+        env::var("METRICS_ADDR").unwrap_or_else(|_| "0.0.0.0:9102".to_string());
+
     info!(
         "starting indexer rpc_url={} start_height={} poll_ms={}",
         rpc_url, start_height, poll_ms
     );
 
+    let metrics = Metrics::new(&[("service", "indexer")]);
+    let listener = tokio::net::TcpListener::bind(&metrics_addr)
+        .await
+        .with_context(|| format!("binding metrics listener on {metrics_addr}"))?;
+    let app = metrics::router(metrics.clone());
+    tokio::spawn(async move {
+        if let Err(err) = axum::serve(listener, app.into_make_service()).await {
+            error!("metrics server stopped: {err}");
+        }
+    });
+
     let client = reqwest::Client::new();
     let mut sink = PostgresSink::connect(&database_url, max_conn).await?;
+    let project = env::var("STATE_PROJECTION")
+        .map_or(true, |v| v != "0" && v.to_lowercase() != "false");
+    if project && start_height == 0 {
+        sink = sink.with_projection(Projection::new(load_genesis()?).await?);
+    } else if project {
+        warn!("state projection disabled: it needs indexing from START_HEIGHT=0");
+    }
     let mut height = start_height;
 
     loop {
         match fetch_block(&client, &rpc_url, height).await {
             Ok(Some(block)) => {
                 info!("ingesting block height={}", height);
+                let produced_at = block.header.timestamp;
                 if let Err(err) = sink.ingest_block(block).await {
-                    error!("failed to ingest block {}: {err}", height);
+                    if err.downcast_ref::<ForkDetected>().is_some() {
+                        match rewind(&client, &rpc_url, &mut sink, height, start_height).await {
+                            Ok(fork) => {
+                                metrics.reorgs.inc();
+                                height = fork + 1;
+                                continue;
+                            }
+                            Err(err) => error!("failed to roll back reorg at {}: {err}", height),
+                        }
+                    } else {
+                        error!("failed to ingest block {}: {err}", height);
+                    }
                     sleep(Duration::from_millis(poll_ms)).await;
                     continue;
                 }
+                metrics.block_height.set(height as i64);
+                metrics
+                    .ingestion_lag_ms
+                    .set(now_millis().saturating_sub(produced_at) as i64);
                 height += 1;
             }
             Ok(None) => {
@@ -56,6 +95,52 @@ async fn main() -> anyhow::Result<()> {
     }
 }
 
+/// The genesis the node was started with; both default to devnet.
+fn load_genesis() -> anyhow::Result<GenesisConfig> {
+    let Ok(path) = env::var("GENESIS_PATH") else {
+        return Ok(devnet_genesis());
+    };
+    let contents =
+        std::fs::read_to_string(&path).with_context(|| format!("reading genesis {path}"))?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Walks back from `height` to the highest block the node and the sink agree
+/// on, rolls the sink back to it and returns its height.
+async fn rewind(
+    client: &reqwest::Client,
+    rpc_url: &str,
+    sink: &mut PostgresSink,
+    height: u64,
+    start_height: u64,
+) -> anyhow::Result<u64> {
+    let mut fork = height;
+    loop {
+        if fork <= start_height {
+            anyhow::bail!(
+                "chain reorganized below the first indexed block; \
+                 reindex from an earlier START_HEIGHT"
+            );
+        }
+        fork -= 1;
+        let stored = sink.block_hash(fork).await?;
+        let canonical = fetch_block(client, rpc_url, fork).await?.map(|b| hash_block(&b));
+        if stored.is_some() && stored == canonical {
+            break;
+        }
+    }
+    let orphaned = sink.rollback_to(fork).await?;
+    warn!("reorg: rolled back {orphaned} blocks above height {fork}");
+    Ok(fork)
+}
+
 async fn fetch_block(
     client: &reqwest::Client,
     rpc_url: &str,
@@ -0,0 +1,131 @@
+//! `GET /events` over the runtime events recorded by the state projection,
+//! oldest first. Filters: `kind`, `address` (hex; matches any attribute,
+//! so a sender, recipient, validator or proposer alike) and `from_height`.
+//! Pages continue from the `next` cursor of the previous one via `after`.
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct EventQuery {
+    pub kind: Option<String>,
+    pub address: Option<String>,
+    pub from_height: Option<i64>,
+    pub after: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventItem {
+    pub id: i64,
+    pub block_height: i64,
+    /// Hex; absent for events of the block itself.
+    pub tx_hash: Option<String>,
+    pub position: i32,
+    pub kind: String,
+    pub attributes: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventPage {
+    pub events: Vec<EventItem>,
+    /// Pass as `after` for the next page; absent on the last one.
+    pub next: Option<i64>,
+}
+
+pub fn router(pool: Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/events", get(list_events))
+        .with_state(pool)
+}
+
+async fn list_events(
+    State(pool): State<Pool<Postgres>>,
+    Query(q): Query<EventQuery>,
+) -> Result<Json<EventPage>, (StatusCode, String)> {
+    let limit = q.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {MAX_PAGE_SIZE}"),
+        ));
+    }
+    let address = q
+        .address
+        .as_deref()
+        .map(address_path)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT e.id, e.block_height, t.tx_hash AS "tx_hash?", e.position, e.kind, e.attributes
+        FROM events e
+        LEFT JOIN transactions t ON t.id = e.tx_id
+        WHERE ($1::TEXT IS NULL OR e.kind = $1)
+            AND ($2::TEXT IS NULL OR e.attributes @? $2::TEXT::JSONPATH)
+            AND ($3::BIGINT IS NULL OR e.block_height >= $3)
+            AND ($4::BIGINT IS NULL OR e.id > $4)
+        ORDER BY e.id
+        LIMIT $5
+        "#,
+        q.kind,
+        address,
+        q.from_height,
+        q.after,
+        limit + 1
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let mut events: Vec<EventItem> = rows
+        .into_iter()
+        .map(|r| EventItem {
+            id: r.id,
+            block_height: r.block_height,
+            tx_hash: r.tx_hash.map(hex::encode),
+            position: r.position,
+            kind: r.kind,
+            attributes: r.attributes,
+        })
+        .collect();
+    let next = if events.len() as i64 > limit {
+        events.truncate(limit as usize);
+        events.last().map(|e| e.id)
+    } else {
+        None
+    };
+    Ok(Json(EventPage { events, next }))
+}
+
+/// JSON path matching any attribute equal to `address`. Only validated hex
+/// goes into the path, and the GIN index on `attributes` serves it.
+fn address_path(address: &str) -> anyhow::Result<String> {
+    let clean = address.strip_prefix("0x").unwrap_or(address);
+    let bytes = hex::decode(clean).map_err(|_| anyhow::anyhow!("invalid hex: {address}"))?;
+    if bytes.len() != 32 {
+        anyhow::bail!("expected 32 bytes: {address}");
+    }
+    Ok(format!(r#"$.* ? (@ == "{}")"#, hex::encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_path_normalizes_hex() {
+        let upper = format!("0x{}", "AB".repeat(32));
+        let expected = format!(r#"$.* ? (@ == "{}")"#, "ab".repeat(32));
+        assert_eq!(address_path(&upper).unwrap(), expected);
+        assert!(address_path("abcd").is_err());
+        assert!(address_path(r#"" || true"#).is_err());
+    }
+}
@@ -0,0 +1,397 @@
+//! REST endpoints for the block explorer under `/explorer`, so a UI needs
+//! no SQL access:
+//!
+//! - `GET /explorer/blocks`: latest blocks, newest first.
+//! - `GET /explorer/fee-pools`: fee pool totals after each block, newest
+//!   first.
+//! - `GET /explorer/accounts/richest`: largest balances.
+//! - `GET /explorer/validators`: signed and proposed blocks and uptime over
+//!   the last `window` blocks.
+//! - `GET /explorer/tx-volume`: txs per UTC day for the last `days` days of
+//!   chain time.
+//!
+//! Block lists take `limit` and continue from the previous page's `next`
+//! via `before`.
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+const DEFAULT_UPTIME_WINDOW: i64 = 1_000;
+const MAX_UPTIME_WINDOW: i64 = 100_000;
+const DEFAULT_VOLUME_DAYS: i64 = 30;
+const MAX_VOLUME_DAYS: i64 = 365;
+const DAY_MS: i64 = 86_400_000;
+
+type ApiError = (StatusCode, String);
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PageQuery {
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LimitQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UptimeQuery {
+    pub window: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct VolumeQuery {
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockSummary {
+    pub height: i64,
+    pub hash: String,
+    pub timestamp_ms: i64,
+    pub proposer: String,
+    pub gas_used: i64,
+    pub gas_limit: i64,
+    /// Decimal string, like every amount here.
+    pub base_fee: String,
+    pub tx_count: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockPage {
+    pub blocks: Vec<BlockSummary>,
+    /// Pass as `before` for the next page; absent on the last one.
+    pub next: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeePoolTotals {
+    pub height: i64,
+    pub l1_gas: String,
+    pub da: String,
+    pub sequencer: String,
+    pub treasury: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeePoolPage {
+    pub blocks: Vec<FeePoolTotals>,
+    pub next: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RichAccount {
+    pub address: String,
+    pub balance: String,
+    pub nonce: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidatorStats {
+    pub validator_id: Uuid,
+    pub owner: String,
+    pub moniker: String,
+    pub status: String,
+    /// Blocks in the window whose liveness report names the validator as
+    /// a QC signer.
+    pub signed_blocks: i64,
+    /// Blocks in the window that carried a liveness report.
+    pub reported_blocks: i64,
+    pub proposed_blocks: i64,
+    /// Views the validator led that timed out without a proposal.
+    pub missed_proposals: i64,
+    /// `signed_blocks` over `reported_blocks`; absent without reports.
+    pub uptime_bps: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyVolume {
+    /// `YYYY-MM-DD`, UTC.
+    pub day: String,
+    pub tx_count: i64,
+    pub failed_tx_count: i64,
+    pub active_senders: i64,
+}
+
+pub fn router(pool: Pool<Postgres>) -> Router {
+    Router::new()
+        .route("/explorer/blocks", get(blocks))
+        .route("/explorer/fee-pools", get(fee_pools))
+        .route("/explorer/accounts/richest", get(richest_accounts))
+        .route("/explorer/validators", get(validators))
+        .route("/explorer/tx-volume", get(tx_volume))
+        .with_state(pool)
+}
+
+async fn blocks(
+    State(pool): State<Pool<Postgres>>,
+    Query(q): Query<PageQuery>,
+) -> Result<Json<BlockPage>, ApiError> {
+    let limit = bounded("limit", q.limit, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE)?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT height, hash, timestamp_ms, proposer, gas_used, gas_limit,
+            base_fee::TEXT AS "base_fee!", tx_count
+        FROM blocks
+        WHERE $1::BIGINT IS NULL OR height < $1
+        ORDER BY height DESC
+        LIMIT $2
+        "#,
+        q.before,
+        limit + 1
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(internal)?;
+
+    let mut blocks: Vec<BlockSummary> = rows
+        .into_iter()
+        .map(|r| BlockSummary {
+            height: r.height,
+            hash: hex::encode(r.hash),
+            timestamp_ms: r.timestamp_ms,
+            proposer: hex::encode(r.proposer),
+            gas_used: r.gas_used,
+            gas_limit: r.gas_limit,
+            base_fee: r.base_fee,
+            tx_count: r.tx_count,
+        })
+        .collect();
+    let next = next_page(&mut blocks, limit, |b| b.height);
+    Ok(Json(BlockPage { blocks, next }))
+}
+
+async fn fee_pools(
+    State(pool): State<Pool<Postgres>>,
+    Query(q): Query<PageQuery>,
+) -> Result<Json<FeePoolPage>, ApiError> {
+    let limit = bounded("limit", q.limit, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE)?;
+    // `fee_pools` only has rows for blocks that changed a pool; each block
+    // reports the latest row at or below it.
+    let rows = sqlx::query!(
+        r#"
+        SELECT b.height,
+            COALESCE(f.l1_gas, 0)::TEXT AS "l1_gas!",
+            COALESCE(f.da, 0)::TEXT AS "da!",
+            COALESCE(f.sequencer, 0)::TEXT AS "sequencer!",
+            COALESCE(f.treasury, 0)::TEXT AS "treasury!"
+        FROM blocks b
+        LEFT JOIN LATERAL (
+            SELECT l1_gas, da, sequencer, treasury
+            FROM fee_pools
+            WHERE height <= b.height
+            ORDER BY height DESC
+            LIMIT 1
+        ) f ON TRUE
+        WHERE $1::BIGINT IS NULL OR b.height < $1
+        ORDER BY b.height DESC
+        LIMIT $2
+        "#,
+        q.before,
+        limit + 1
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(internal)?;
+
+    let mut blocks: Vec<FeePoolTotals> = rows
+        .into_iter()
+        .map(|r| FeePoolTotals {
+            height: r.height,
+            l1_gas: r.l1_gas,
+            da: r.da,
+            sequencer: r.sequencer,
+            treasury: r.treasury,
+        })
+        .collect();
+    let next = next_page(&mut blocks, limit, |b| b.height);
+    Ok(Json(FeePoolPage { blocks, next }))
+}
+
+async fn richest_accounts(
+    State(pool): State<Pool<Postgres>>,
+    Query(q): Query<LimitQuery>,
+) -> Result<Json<Vec<RichAccount>>, ApiError> {
+    let limit = bounded("limit", q.limit, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE)?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT address AS "address!", balance::TEXT AS "balance!", nonce AS "nonce!"
+        FROM (
+            SELECT DISTINCT ON (address) address, balance, nonce
+            FROM balances
+            ORDER BY address, height DESC
+        ) latest
+        WHERE balance > 0
+        ORDER BY latest.balance DESC, address
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(internal)?;
+
+    Ok(Json(
+        rows.into_iter()
+            .map(|r| RichAccount {
+                address: hex::encode(r.address),
+                balance: r.balance,
+                nonce: r.nonce,
+            })
+            .collect(),
+    ))
+}
+
+async fn validators(
+    State(pool): State<Pool<Postgres>>,
+    Query(q): Query<UptimeQuery>,
+) -> Result<Json<Vec<ValidatorStats>>, ApiError> {
+    let window = bounded("window", q.window, DEFAULT_UPTIME_WINDOW, MAX_UPTIME_WINDOW)?;
+    // Liveness reports are what proposers put in `consensus_metadata`; see
+    // `runtime::LivenessReport`.
+    let rows = sqlx::query!(
+        r#"
+        WITH recent AS (
+            SELECT proposer, consensus_metadata -> 'liveness' AS liveness
+            FROM blocks
+            WHERE height > (SELECT COALESCE(MAX(height), -1) FROM blocks) - $1
+        ),
+        current AS (
+            SELECT DISTINCT ON (validator_id) validator_id, owner, status, moniker
+            FROM validators
+            ORDER BY validator_id, height DESC
+        )
+        SELECT c.validator_id AS "validator_id!", c.owner AS "owner!", c.status AS "status!",
+            c.moniker AS "moniker!",
+            (SELECT COUNT(*) FROM recent r
+                WHERE r.liveness -> 'signers' ? c.validator_id::TEXT) AS "signed_blocks!",
+            (SELECT COUNT(*) FROM recent r
+                WHERE jsonb_typeof(r.liveness -> 'signers') = 'array') AS "reported_blocks!",
+            (SELECT COUNT(*) FROM recent r WHERE r.proposer = c.owner) AS "proposed_blocks!",
+            (SELECT COUNT(*) FROM recent r,
+                jsonb_array_elements(COALESCE(r.liveness -> 'missed_proposals', '[]')) m
+                WHERE m ->> 1 = c.validator_id::TEXT) AS "missed_proposals!"
+        FROM current c
+        ORDER BY c.validator_id
+        "#,
+        window
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(internal)?;
+
+    Ok(Json(
+        rows.into_iter()
+            .map(|r| ValidatorStats {
+                validator_id: r.validator_id,
+                owner: hex::encode(r.owner),
+                moniker: r.moniker,
+                status: r.status,
+                signed_blocks: r.signed_blocks,
+                reported_blocks: r.reported_blocks,
+                proposed_blocks: r.proposed_blocks,
+                missed_proposals: r.missed_proposals,
+                uptime_bps: uptime_bps(r.signed_blocks, r.reported_blocks),
+            })
+            .collect(),
+    ))
+}
+
+async fn tx_volume(
+    State(pool): State<Pool<Postgres>>,
+    Query(q): Query<VolumeQuery>,
+) -> Result<Json<Vec<DailyVolume>>, ApiError> {
+    let days = bounded("days", q.days, DEFAULT_VOLUME_DAYS, MAX_VOLUME_DAYS)?;
+    // Counted back from the latest block rather than the wall clock, so a
+    // stalled devnet still shows its last days.
+    let rows = sqlx::query!(
+        r#"
+        SELECT to_char(to_timestamp(b.timestamp_ms / 1000.0) AT TIME ZONE 'UTC', 'YYYY-MM-DD')
+                AS "day!",
+            COUNT(t.id) AS "tx_count!",
+            COUNT(t.id) FILTER (WHERE NOT t.success) AS "failed_tx_count!",
+            COUNT(DISTINCT t.sender) AS "active_senders!"
+        FROM blocks b
+        JOIN transactions t ON t.block_height = b.height
+        WHERE b.timestamp_ms > (SELECT MAX(timestamp_ms) FROM blocks) - $1 * $2
+        GROUP BY 1
+        ORDER BY 1 DESC
+        "#,
+        days,
+        DAY_MS
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(internal)?;
+
+    Ok(Json(
+        rows.into_iter()
+            .map(|r| DailyVolume {
+                day: r.day,
+                tx_count: r.tx_count,
+                failed_tx_count: r.failed_tx_count,
+                active_senders: r.active_senders,
+            })
+            .collect(),
+    ))
+}
+
+fn bounded(name: &str, value: Option<i64>, default: i64, max: i64) -> Result<i64, ApiError> {
+    let value = value.unwrap_or(default);
+    if !(1..=max).contains(&value) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("{name} must be between 1 and {max}"),
+        ));
+    }
+    Ok(value)
+}
+
+/// Trims a page fetched with one extra row and returns the cursor for the
+/// next one, if there is more.
+fn next_page<T>(rows: &mut Vec<T>, limit: i64, cursor: impl Fn(&T) -> i64) -> Option<i64> {
+    if rows.len() as i64 <= limit {
+        return None;
+    }
+    rows.truncate(limit as usize);
+    rows.last().map(cursor)
+}
+
+fn uptime_bps(signed: i64, reported: i64) -> Option<u32> {
+    if reported <= 0 {
+        return None;
+    }
+    let signed = signed.clamp(0, reported);
+    u32::try_from(signed * 10_000 / reported).ok()
+}
+
+fn internal(err: sqlx::Error) -> ApiError {
+    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_and_uptime() {
+        let mut rows = vec![30, 29, 28];
+        assert_eq!(next_page(&mut rows, 2, |h| *h), Some(29));
+        assert_eq!(rows, vec![30, 29]);
+        assert_eq!(next_page(&mut rows, 2, |h| *h), None);
+
+        assert_eq!(uptime_bps(3, 4), Some(7_500));
+        assert_eq!(uptime_bps(0, 0), None);
+        assert_eq!(bounded("limit", None, 20, 100).unwrap(), 20);
+        assert!(bounded("limit", Some(0), 20, 100).is_err());
+        assert!(bounded("limit", Some(101), 20, 100).is_err());
+    }
+}
@@ -0,0 +1,985 @@
+//! Read-only GraphQL API over the indexer's Postgres schema, served by the
+//! `indexer-api` binary. Lists are newest first and paginated with
+//! `first`/`after`, where cursors come from a previous page's edges.
+
+use async_graphql::connection::{Connection, Edge};
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, InputObject, Json, Object, Result, Schema,
+};
+use axum::extract::State;
+use axum::response::{Html, IntoResponse};
+use axum::routing::get;
+use axum::Router;
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+pub type IndexerSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+const DEFAULT_PAGE_SIZE: i64 = 20;
+const MAX_PAGE_SIZE: i64 = 100;
+
+pub fn schema(pool: Pool<Postgres>) -> IndexerSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(pool)
+        .limit_depth(8)
+        .finish()
+}
+
+/// `POST /graphql` runs queries; `GET /graphql` serves GraphiQL.
+pub fn router(schema: IndexerSchema) -> Router {
+    Router::new()
+        .route("/graphql", get(graphiql).post(graphql))
+        .route("/health", get(|| async { "ok" }))
+        .with_state(schema)
+}
+
+async fn graphql(
+    State(schema): State<IndexerSchema>,
+    axum::Json(request): axum::Json<async_graphql::Request>,
+) -> axum::Json<async_graphql::Response> {
+    axum::Json(schema.execute(request).await)
+}
+
+async fn graphiql() -> impl IntoResponse {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+fn pool<'a>(ctx: &Context<'a>) -> &'a Pool<Postgres> {
+    ctx.data_unchecked::<Pool<Postgres>>()
+}
+
+fn page_size(first: Option<i32>) -> Result<i64> {
+    match first.map(i64::from) {
+        None => Ok(DEFAULT_PAGE_SIZE),
+        Some(n) if (1..=MAX_PAGE_SIZE).contains(&n) => Ok(n),
+        Some(_) => Err(format!("first must be between 1 and {MAX_PAGE_SIZE}").into()),
+    }
+}
+
+fn parse_cursor(after: Option<String>) -> Result<Option<i64>> {
+    after
+        .map(|c| c.parse().map_err(|_| "invalid cursor".into()))
+        .transpose()
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>> {
+    let clean = value.strip_prefix("0x").unwrap_or(value);
+    let bytes = hex::decode(clean).map_err(|_| format!("invalid hex: {value}"))?;
+    if bytes.len() != 32 {
+        return Err(format!("expected 32 bytes: {value}").into());
+    }
+    Ok(bytes)
+}
+
+/// Builds a page from up to `limit + 1` rows fetched after `after`.
+fn page<T: async_graphql::OutputType>(
+    mut rows: Vec<T>,
+    limit: i64,
+    after: Option<i64>,
+    cursor: impl Fn(&T) -> i64,
+) -> Connection<String, T> {
+    let has_next = rows.len() as i64 > limit;
+    rows.truncate(limit as usize);
+    let mut connection = Connection::new(after.is_some(), has_next);
+    connection.edges.extend(
+        rows.into_iter()
+            .map(|row| Edge::new(cursor(&row).to_string(), row)),
+    );
+    connection
+}
+
+#[derive(Default, InputObject)]
+pub struct TransactionFilter {
+    /// Hex address of the sender.
+    pub sender: Option<String>,
+    /// e.g. `transfer`, `governance_vote`.
+    pub payload_type: Option<String>,
+    pub from_height: Option<i64>,
+    pub to_height: Option<i64>,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn block(
+        &self,
+        ctx: &Context<'_>,
+        height: Option<i64>,
+        hash: Option<String>,
+    ) -> Result<Option<Block>> {
+        let hash = hash.as_deref().map(parse_hex).transpose()?;
+        if height.is_none() && hash.is_none() {
+            return Err("block needs a height or a hash".into());
+        }
+        let block = sqlx::query_as!(
+            Block,
+            r#"
+            SELECT height, hash, parent_hash, timestamp_ms, proposer, state_root,
+                gas_used, gas_limit, base_fee::TEXT AS "base_fee!", tx_count
+            FROM blocks
+            WHERE ($1::BIGINT IS NULL OR height = $1) AND ($2::BYTEA IS NULL OR hash = $2)
+            "#,
+            height,
+            hash
+        )
+        .fetch_optional(pool(ctx))
+        .await?;
+        Ok(block)
+    }
+
+    async fn blocks(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Connection<String, Block>> {
+        let limit = page_size(first)?;
+        let after = parse_cursor(after)?;
+        let rows = sqlx::query_as!(
+            Block,
+            r#"
+            SELECT height, hash, parent_hash, timestamp_ms, proposer, state_root,
+                gas_used, gas_limit, base_fee::TEXT AS "base_fee!", tx_count
+            FROM blocks
+            WHERE $1::BIGINT IS NULL OR height < $1
+            ORDER BY height DESC
+            LIMIT $2
+            "#,
+            after,
+            limit + 1
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(page(rows, limit, after, |b| b.height))
+    }
+
+    async fn transaction(&self, ctx: &Context<'_>, hash: String) -> Result<Option<Transaction>> {
+        let hash = parse_hex(&hash)?;
+        let tx = sqlx::query_as!(
+            Transaction,
+            r#"
+            SELECT id, tx_hash, block_height, position, sender, nonce, gas_limit,
+                gas_price::TEXT, max_fee::TEXT, max_priority_fee::TEXT,
+                payload_type, payload, success, events
+            FROM transactions
+            WHERE tx_hash = $1
+            "#,
+            hash
+        )
+        .fetch_optional(pool(ctx))
+        .await?;
+        Ok(tx)
+    }
+
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<TransactionFilter>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Connection<String, Transaction>> {
+        let filter = filter.unwrap_or_default();
+        let sender = filter.sender.as_deref().map(parse_hex).transpose()?;
+        transactions(
+            pool(ctx),
+            TxQuery {
+                sender,
+                payload_type: filter.payload_type,
+                from_height: filter.from_height,
+                to_height: filter.to_height,
+            },
+            first,
+            after,
+        )
+        .await
+    }
+
+    async fn account(&self, ctx: &Context<'_>, address: String) -> Result<Option<Account>> {
+        let address = parse_hex(&address)?;
+        let account = sqlx::query_as!(
+            Account,
+            r#"
+            SELECT address, first_seen_height, last_seen_height, tx_count
+            FROM accounts
+            WHERE address = $1
+            "#,
+            address
+        )
+        .fetch_optional(pool(ctx))
+        .await?;
+        Ok(account)
+    }
+
+    /// Proposals that have been voted on, approved or executed, most
+    /// recently active first.
+    async fn proposals(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Connection<String, Proposal>> {
+        let limit = page_size(first)?;
+        let after = parse_cursor(after)?;
+        let rows = sqlx::query_as!(
+            Proposal,
+            r#"
+            SELECT proposal_id AS "id!", MAX(id) AS "last_event_id!",
+                bool_or(kind = 'bridge_approve') AS "bridge_approved!",
+                bool_or(kind = 'execute') AS "executed!"
+            FROM governance_events
+            WHERE proposal_id IS NOT NULL
+            GROUP BY proposal_id
+            HAVING $1::BIGINT IS NULL OR MAX(id) < $1
+            ORDER BY MAX(id) DESC
+            LIMIT $2
+            "#,
+            after,
+            limit + 1
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(page(rows, limit, after, |p| p.last_event_id))
+    }
+
+    async fn proposal(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<Proposal>> {
+        let proposal = sqlx::query_as!(
+            Proposal,
+            r#"
+            SELECT proposal_id AS "id!", MAX(id) AS "last_event_id!",
+                bool_or(kind = 'bridge_approve') AS "bridge_approved!",
+                bool_or(kind = 'execute') AS "executed!"
+            FROM governance_events
+            WHERE proposal_id = $1
+            GROUP BY proposal_id
+            "#,
+            id
+        )
+        .fetch_optional(pool(ctx))
+        .await?;
+        Ok(proposal)
+    }
+
+    /// Every validator with its current commission and profile.
+    async fn validators(&self, ctx: &Context<'_>) -> Result<Vec<ValidatorProfile>> {
+        let validators = sqlx::query_as!(
+            ValidatorProfile,
+            r#"
+            SELECT DISTINCT ON (validator_id)
+                validator_id, height, owner, status, commission_rate, moniker, website, details
+            FROM validators
+            ORDER BY validator_id, height DESC
+            "#
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(validators)
+    }
+
+    /// A validator's commission and profile after each change, newest first.
+    async fn validator_history(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+    ) -> Result<Vec<ValidatorProfile>> {
+        let history = sqlx::query_as!(
+            ValidatorProfile,
+            r#"
+            SELECT validator_id, height, owner, status, commission_rate, moniker, website, details
+            FROM validators
+            WHERE validator_id = $1
+            ORDER BY height DESC
+            "#,
+            id
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(history)
+    }
+
+    /// Treasury pool balance after each block that changed it, with the
+    /// block's payouts.
+    async fn treasury_history(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Connection<String, TreasuryBalance>> {
+        let limit = page_size(first)?;
+        let after = parse_cursor(after)?;
+        let rows = sqlx::query_as!(
+            TreasuryBalance,
+            r#"
+            SELECT height, balance::TEXT AS "balance!"
+            FROM treasury_balances
+            WHERE $1::BIGINT IS NULL OR height < $1
+            ORDER BY height DESC
+            LIMIT $2
+            "#,
+            after,
+            limit + 1
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(page(rows, limit, after, |t| t.height))
+    }
+
+    /// Every asset with its current supply.
+    async fn assets(&self, ctx: &Context<'_>) -> Result<Vec<AssetInfo>> {
+        let assets = sqlx::query_as!(
+            AssetInfo,
+            r#"
+            SELECT DISTINCT ON (asset_id)
+                asset_id, height, symbol, decimals, issuer, supply::TEXT AS "supply!",
+                max_supply::TEXT
+            FROM assets
+            ORDER BY asset_id, height DESC
+            "#
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(assets)
+    }
+
+    async fn asset(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<AssetInfo>> {
+        let asset = sqlx::query_as!(
+            AssetInfo,
+            r#"
+            SELECT asset_id, height, symbol, decimals, issuer, supply::TEXT AS "supply!",
+                max_supply::TEXT
+            FROM assets
+            WHERE asset_id = $1
+            ORDER BY height DESC
+            LIMIT 1
+            "#,
+            id
+        )
+        .fetch_optional(pool(ctx))
+        .await?;
+        Ok(asset)
+    }
+
+    async fn domain(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<Domain>> {
+        let domain = sqlx::query_as!(
+            Domain,
+            r#"
+            SELECT domain_id, kind, security_model, risk_params
+            FROM domains
+            WHERE domain_id = $1
+            "#,
+            id
+        )
+        .fetch_optional(pool(ctx))
+        .await?;
+        Ok(domain)
+    }
+}
+
+struct TxQuery {
+    sender: Option<Vec<u8>>,
+    payload_type: Option<String>,
+    from_height: Option<i64>,
+    to_height: Option<i64>,
+}
+
+async fn transactions(
+    pool: &Pool<Postgres>,
+    query: TxQuery,
+    first: Option<i32>,
+    after: Option<String>,
+) -> Result<Connection<String, Transaction>> {
+    let limit = page_size(first)?;
+    let after = parse_cursor(after)?;
+    // Ids follow chain order: blocks are ingested in height order and
+    // rolled-back rows are deleted rather than reused.
+    let rows = sqlx::query_as!(
+        Transaction,
+        r#"
+        SELECT id, tx_hash, block_height, position, sender, nonce, gas_limit,
+            gas_price::TEXT, max_fee::TEXT, max_priority_fee::TEXT,
+            payload_type, payload, success, events
+        FROM transactions
+        WHERE ($1::BYTEA IS NULL OR sender = $1)
+            AND ($2::TEXT IS NULL OR payload_type = $2)
+            AND ($3::BIGINT IS NULL OR block_height >= $3)
+            AND ($4::BIGINT IS NULL OR block_height <= $4)
+            AND ($5::BIGINT IS NULL OR id < $5)
+        ORDER BY id DESC
+        LIMIT $6
+        "#,
+        query.sender,
+        query.payload_type,
+        query.from_height,
+        query.to_height,
+        after,
+        limit + 1
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(page(rows, limit, after, |tx| tx.id))
+}
+
+pub struct Block {
+    height: i64,
+    hash: Vec<u8>,
+    parent_hash: Vec<u8>,
+    timestamp_ms: i64,
+    proposer: Vec<u8>,
+    state_root: Vec<u8>,
+    gas_used: i64,
+    gas_limit: i64,
+    base_fee: String,
+    tx_count: i32,
+}
+
+#[Object]
+impl Block {
+    async fn height(&self) -> i64 {
+        self.height
+    }
+
+    async fn hash(&self) -> String {
+        hex::encode(&self.hash)
+    }
+
+    async fn parent_hash(&self) -> String {
+        hex::encode(&self.parent_hash)
+    }
+
+    async fn timestamp_ms(&self) -> i64 {
+        self.timestamp_ms
+    }
+
+    async fn proposer(&self) -> String {
+        hex::encode(&self.proposer)
+    }
+
+    async fn state_root(&self) -> String {
+        hex::encode(&self.state_root)
+    }
+
+    async fn gas_used(&self) -> i64 {
+        self.gas_used
+    }
+
+    async fn gas_limit(&self) -> i64 {
+        self.gas_limit
+    }
+
+    /// Decimal string; fees don't fit a GraphQL `Int`.
+    async fn base_fee(&self) -> &str {
+        &self.base_fee
+    }
+
+    async fn tx_count(&self) -> i32 {
+        self.tx_count
+    }
+
+    async fn transactions(&self, ctx: &Context<'_>) -> Result<Vec<Transaction>> {
+        let txs = sqlx::query_as!(
+            Transaction,
+            r#"
+            SELECT id, tx_hash, block_height, position, sender, nonce, gas_limit,
+                gas_price::TEXT, max_fee::TEXT, max_priority_fee::TEXT,
+                payload_type, payload, success, events
+            FROM transactions
+            WHERE block_height = $1
+            ORDER BY position
+            "#,
+            self.height
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(txs)
+    }
+}
+
+pub struct Transaction {
+    id: i64,
+    tx_hash: Vec<u8>,
+    block_height: i64,
+    position: i32,
+    sender: Vec<u8>,
+    nonce: i64,
+    gas_limit: i64,
+    gas_price: Option<String>,
+    max_fee: Option<String>,
+    max_priority_fee: Option<String>,
+    payload_type: String,
+    payload: serde_json::Value,
+    success: bool,
+    events: Vec<String>,
+}
+
+#[Object]
+impl Transaction {
+    async fn hash(&self) -> String {
+        hex::encode(&self.tx_hash)
+    }
+
+    async fn block_height(&self) -> i64 {
+        self.block_height
+    }
+
+    async fn position(&self) -> i32 {
+        self.position
+    }
+
+    async fn sender(&self) -> String {
+        hex::encode(&self.sender)
+    }
+
+    async fn nonce(&self) -> i64 {
+        self.nonce
+    }
+
+    async fn gas_limit(&self) -> i64 {
+        self.gas_limit
+    }
+
+    async fn gas_price(&self) -> Option<&str> {
+        self.gas_price.as_deref()
+    }
+
+    async fn max_fee(&self) -> Option<&str> {
+        self.max_fee.as_deref()
+    }
+
+    async fn max_priority_fee(&self) -> Option<&str> {
+        self.max_priority_fee.as_deref()
+    }
+
+    async fn payload_type(&self) -> &str {
+        &self.payload_type
+    }
+
+    async fn payload(&self) -> Json<&serde_json::Value> {
+        Json(&self.payload)
+    }
+
+    async fn success(&self) -> bool {
+        self.success
+    }
+
+    async fn events(&self) -> &[String] {
+        &self.events
+    }
+}
+
+pub struct Account {
+    address: Vec<u8>,
+    first_seen_height: i64,
+    last_seen_height: i64,
+    tx_count: i64,
+}
+
+#[Object]
+impl Account {
+    async fn address(&self) -> String {
+        hex::encode(&self.address)
+    }
+
+    async fn first_seen_height(&self) -> i64 {
+        self.first_seen_height
+    }
+
+    async fn last_seen_height(&self) -> i64 {
+        self.last_seen_height
+    }
+
+    async fn tx_count(&self) -> i64 {
+        self.tx_count
+    }
+
+    /// Transactions sent by this account.
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        payload_type: Option<String>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Connection<String, Transaction>> {
+        let query = TxQuery {
+            sender: Some(self.address.clone()),
+            payload_type,
+            from_height: None,
+            to_height: None,
+        };
+        transactions(pool(ctx), query, first, after).await
+    }
+}
+
+pub struct Proposal {
+    id: Uuid,
+    last_event_id: i64,
+    bridge_approved: bool,
+    executed: bool,
+}
+
+#[Object]
+impl Proposal {
+    async fn id(&self) -> Uuid {
+        self.id
+    }
+
+    async fn bridge_approved(&self) -> bool {
+        self.bridge_approved
+    }
+
+    async fn executed(&self) -> bool {
+        self.executed
+    }
+
+    async fn votes(&self, ctx: &Context<'_>) -> Result<Vec<Vote>> {
+        let votes = sqlx::query_as!(
+            Vote,
+            r#"
+            SELECT t.sender AS voter, g.support AS "support!", g.weight::TEXT AS weight,
+                t.block_height, t.tx_hash
+            FROM governance_events g
+            JOIN transactions t ON t.id = g.tx_id
+            WHERE g.proposal_id = $1 AND g.kind = 'vote'
+            ORDER BY g.id
+            "#,
+            self.id
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(votes)
+    }
+}
+
+pub struct Vote {
+    voter: Vec<u8>,
+    support: bool,
+    weight: Option<String>,
+    block_height: i64,
+    tx_hash: Vec<u8>,
+}
+
+#[Object]
+impl Vote {
+    async fn voter(&self) -> String {
+        hex::encode(&self.voter)
+    }
+
+    async fn support(&self) -> bool {
+        self.support
+    }
+
+    async fn weight(&self) -> Option<&str> {
+        self.weight.as_deref()
+    }
+
+    async fn block_height(&self) -> i64 {
+        self.block_height
+    }
+
+    async fn tx_hash(&self) -> String {
+        hex::encode(&self.tx_hash)
+    }
+}
+
+pub struct ValidatorProfile {
+    validator_id: Uuid,
+    height: i64,
+    owner: Vec<u8>,
+    status: String,
+    commission_rate: i16,
+    moniker: String,
+    website: String,
+    details: String,
+}
+
+#[Object]
+impl ValidatorProfile {
+    async fn id(&self) -> Uuid {
+        self.validator_id
+    }
+
+    /// Height from which these values apply.
+    async fn height(&self) -> i64 {
+        self.height
+    }
+
+    async fn owner(&self) -> String {
+        hex::encode(&self.owner)
+    }
+
+    async fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Percent of delegator rewards kept by the validator.
+    async fn commission_rate(&self) -> i16 {
+        self.commission_rate
+    }
+
+    async fn moniker(&self) -> &str {
+        &self.moniker
+    }
+
+    async fn website(&self) -> &str {
+        &self.website
+    }
+
+    async fn details(&self) -> &str {
+        &self.details
+    }
+}
+
+pub struct TreasuryBalance {
+    height: i64,
+    balance: String,
+}
+
+#[Object]
+impl TreasuryBalance {
+    async fn height(&self) -> i64 {
+        self.height
+    }
+
+    /// Decimal string.
+    async fn balance(&self) -> &str {
+        &self.balance
+    }
+
+    /// Executed `treasury_spend` proposals paid out in this block.
+    async fn payouts(&self, ctx: &Context<'_>) -> Result<Vec<TreasuryPayout>> {
+        let payouts = sqlx::query_as!(
+            TreasuryPayout,
+            r#"
+            SELECT t.tx_hash AS "tx_hash?",
+                e.attributes->>'proposal_id' AS proposal_id,
+                e.attributes->>'recipient' AS "recipient!",
+                e.attributes->>'amount' AS "amount!"
+            FROM events e
+            LEFT JOIN transactions t ON t.id = e.tx_id
+            WHERE e.block_height = $1 AND e.kind = 'treasury_spend'
+            ORDER BY e.position
+            "#,
+            self.height
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(payouts)
+    }
+}
+
+pub struct TreasuryPayout {
+    tx_hash: Option<Vec<u8>>,
+    proposal_id: Option<String>,
+    recipient: String,
+    amount: String,
+}
+
+#[Object]
+impl TreasuryPayout {
+    async fn tx_hash(&self) -> Option<String> {
+        self.tx_hash.as_ref().map(hex::encode)
+    }
+
+    async fn proposal_id(&self) -> Option<&str> {
+        self.proposal_id.as_deref()
+    }
+
+    async fn recipient(&self) -> &str {
+        &self.recipient
+    }
+
+    /// Decimal string.
+    async fn amount(&self) -> &str {
+        &self.amount
+    }
+}
+
+pub struct Domain {
+    domain_id: Uuid,
+    kind: String,
+    security_model: String,
+    risk_params: serde_json::Value,
+}
+
+#[Object]
+impl Domain {
+    async fn id(&self) -> Uuid {
+        self.domain_id
+    }
+
+    async fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    async fn security_model(&self) -> &str {
+        &self.security_model
+    }
+
+    async fn risk_params(&self) -> Json<&serde_json::Value> {
+        Json(&self.risk_params)
+    }
+
+    /// Rollup batches committed for this domain.
+    async fn batches(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> Result<Connection<String, Batch>> {
+        let limit = page_size(first)?;
+        let after = parse_cursor(after)?;
+        let rows = sqlx::query_as!(
+            Batch,
+            r#"
+            SELECT b.id, b.blob_id, b.block_height, t.tx_hash, t.sender
+            FROM rollup_batches b
+            JOIN transactions t ON t.id = b.tx_id
+            WHERE b.domain_id = $1 AND ($2::BIGINT IS NULL OR b.id < $2)
+            ORDER BY b.id DESC
+            LIMIT $3
+            "#,
+            self.domain_id,
+            after,
+            limit + 1
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(page(rows, limit, after, |b| b.id))
+    }
+}
+
+pub struct Batch {
+    id: i64,
+    blob_id: String,
+    block_height: i64,
+    tx_hash: Vec<u8>,
+    sender: Vec<u8>,
+}
+
+#[Object]
+impl Batch {
+    async fn blob_id(&self) -> &str {
+        &self.blob_id
+    }
+
+    async fn block_height(&self) -> i64 {
+        self.block_height
+    }
+
+    async fn tx_hash(&self) -> String {
+        hex::encode(&self.tx_hash)
+    }
+
+    /// The sequencer that committed the batch.
+    async fn sender(&self) -> String {
+        hex::encode(&self.sender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_hold_one_extra_row_to_detect_more() {
+        let connection = page(vec![5_i64, 4, 3], 2, Some(6), |n| *n);
+        assert!(connection.has_previous_page);
+        assert!(connection.has_next_page);
+        let cursors: Vec<&str> = connection.edges.iter().map(|e| e.cursor.as_str()).collect();
+        assert_eq!(cursors, vec!["5", "4"]);
+
+        assert!(page_size(Some(0)).is_err());
+        assert!(page_size(Some(101)).is_err());
+        assert!(parse_cursor(Some("x".into())).is_err());
+        assert!(parse_hex("0x1234").is_err());
+    }
+}
+
+pub struct AssetInfo {
+    asset_id: Uuid,
+    height: i64,
+    symbol: String,
+    decimals: i16,
+    issuer: Vec<u8>,
+    supply: String,
+    max_supply: Option<String>,
+}
+
+#[Object]
+impl AssetInfo {
+    async fn id(&self) -> Uuid {
+        self.asset_id
+    }
+
+    /// Height from which these values apply.
+    async fn height(&self) -> i64 {
+        self.height
+    }
+
+    async fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    async fn decimals(&self) -> i16 {
+        self.decimals
+    }
+
+    async fn issuer(&self) -> String {
+        hex::encode(&self.issuer)
+    }
+
+    /// Decimal string.
+    async fn supply(&self) -> &str {
+        &self.supply
+    }
+
+    /// Decimal string; `null` for an uncapped asset.
+    async fn max_supply(&self) -> Option<&str> {
+        self.max_supply.as_deref()
+    }
+
+    /// Current holders, largest balance first.
+    async fn holders(&self, ctx: &Context<'_>) -> Result<Vec<AssetHolder>> {
+        let holders = sqlx::query_as!(
+            AssetHolder,
+            r#"
+            SELECT holder AS "holder!", height AS "height!", balance::TEXT AS "balance!"
+            FROM (
+                SELECT DISTINCT ON (holder) holder, height, balance
+                FROM asset_holders
+                WHERE asset_id = $1
+                ORDER BY holder, height DESC
+            ) latest
+            WHERE balance > 0
+            ORDER BY latest.balance DESC, holder
+            "#,
+            self.asset_id
+        )
+        .fetch_all(pool(ctx))
+        .await?;
+        Ok(holders)
+    }
+}
+
+pub struct AssetHolder {
+    holder: Vec<u8>,
+    height: i64,
+    balance: String,
+}
+
+#[Object]
+impl AssetHolder {
+    async fn holder(&self) -> String {
+        hex::encode(&self.holder)
+    }
+
+    /// Height of the holder's last balance change.
+    async fn height(&self) -> i64 {
+        self.height
+    }
+
+    /// Decimal string.
+    async fn balance(&self) -> &str {
+        &self.balance
+    }
+}
@@ -1,35 +1,100 @@
+pub mod events;
+pub mod explorer;
+pub mod graphql;
+pub mod projection;
+
 use async_trait::async_trait;
 use bigdecimal::BigDecimal;
 use serde_json;
-use runtime::{derive_sender, hash_block, Block, Tx, TxPayload};
+use projection::{Projection, StateChanges};
+use runtime::{address_from_pubkey, hash_block, tx_hash, Block, Hash, Tx, TxPayload};
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
-use tracing::info;
+use state::DEFAULT_PRIVACY_POOL;
+use std::fmt;
+use tracing::{info, warn};
 use uuid::Uuid;
 
+/// Returned by `ingest_block` when the block doesn't extend the indexed chain:
+/// its parent or the block already stored at its height has another hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForkDetected {
+    pub height: u64,
+}
+
+impl fmt::Display for ForkDetected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "block at height {} does not extend the indexed chain", self.height)
+    }
+}
+
+impl std::error::Error for ForkDetected {}
+
 /// Generic sink for block ingestion.
 #[async_trait]
 pub trait BlockSink {
+    /// Stores `block`, failing with `ForkDetected` when it doesn't build on
+    /// the stored chain. Re-ingesting a stored block is a no-op.
     async fn ingest_block(&mut self, block: Block) -> anyhow::Result<()>;
+
+    async fn block_hash(&self, height: u64) -> anyhow::Result<Option<Hash>>;
+
+    /// Drops every block above `height` along with its txs and events,
+    /// recording the dropped blocks as orphaned. Returns how many there were.
+    async fn rollback_to(&mut self, height: u64) -> anyhow::Result<u64>;
 }
 
 /// In-memory sink for tests and smoke runs.
 #[derive(Default)]
 pub struct InMemorySink {
     pub blocks: Vec<Block>,
+    pub orphaned: Vec<Block>,
+}
+
+impl InMemorySink {
+    fn get(&self, height: u64) -> Option<&Block> {
+        self.blocks.iter().find(|b| b.header.height == height)
+    }
 }
 
 #[async_trait]
 impl BlockSink for InMemorySink {
     async fn ingest_block(&mut self, block: Block) -> anyhow::Result<()> {
-        info!("ingesting block {}", block.header.height);
+        let height = block.header.height;
+        if let Some(existing) = self.get(height) {
+            if hash_block(existing) == hash_block(&block) {
+                return Ok(());
+            }
+            return Err(ForkDetected { height }.into());
+        }
+        let parent = height.checked_sub(1).and_then(|h| self.get(h));
+        if parent.is_some_and(|p| hash_block(p) != block.header.parent_hash) {
+            return Err(ForkDetected { height }.into());
+        }
+        info!("ingesting block {}", height);
         self.blocks.push(block);
         Ok(())
     }
+
+    async fn block_hash(&self, height: u64) -> anyhow::Result<Option<Hash>> {
+        Ok(self.get(height).map(hash_block))
+    }
+
+    async fn rollback_to(&mut self, height: u64) -> anyhow::Result<u64> {
+        let (kept, orphaned): (Vec<Block>, Vec<Block>) = self
+            .blocks
+            .drain(..)
+            .partition(|b| b.header.height <= height);
+        self.blocks = kept;
+        let count = orphaned.len() as u64;
+        self.orphaned.extend(orphaned);
+        Ok(count)
+    }
 }
 
 /// Postgres-backed sink that runs migrations and stores blocks/txs.
 pub struct PostgresSink {
     pool: Pool<Postgres>,
+    projection: Option<Projection>,
 }
 
 impl PostgresSink {
@@ -39,12 +104,61 @@ impl PostgresSink {
             .connect(database_url)
             .await?;
         sqlx::migrate!().run(&pool).await?;
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            projection: None,
+        })
+    }
+
+    /// Keeps `balances`, `staking_positions`, `asset_holders` and `events` up
+    /// to date by replaying every block through `projection`, so ingestion
+    /// has to start at genesis.
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = Some(projection);
+        self
     }
 
     pub fn pool(&self) -> &Pool<Postgres> {
         &self.pool
     }
+
+    /// Commits `tx` along with the state `block` changed, if a projection is
+    /// kept and hasn't seen the block yet.
+    async fn commit_with_state(
+        &mut self,
+        mut tx: sqlx::Transaction<'_, Postgres>,
+        block: &Block,
+    ) -> anyhow::Result<()> {
+        let Some(projection) = self
+            .projection
+            .as_mut()
+            .filter(|p| block.header.height >= p.next_height())
+        else {
+            tx.commit().await?;
+            return Ok(());
+        };
+        let changes = projection.apply(block).await?;
+        let written = async {
+            record_state(&mut tx, &changes).await?;
+            tx.commit().await?;
+            anyhow::Ok(())
+        }
+        .await;
+        if written.is_err() {
+            projection.undo().await?;
+        }
+        written
+    }
+}
+
+async fn stored_hash(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    height: i64,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let hash = sqlx::query_scalar!("SELECT hash FROM blocks WHERE height = $1", height)
+        .fetch_optional(&mut **tx)
+        .await?;
+    Ok(hash)
 }
 
 #[async_trait]
@@ -53,6 +167,19 @@ impl BlockSink for PostgresSink {
         let mut tx = self.pool.begin().await?;
         let block_hash = hash_block(&block);
         let height = i64::try_from(block.header.height)?;
+        if let Some(existing) = stored_hash(&mut tx, height).await? {
+            if existing == block_hash {
+                // Stored blocks are replayed on restart to rebuild the projection.
+                return self.commit_with_state(tx, &block).await;
+            }
+            return Err(ForkDetected { height: block.header.height }.into());
+        }
+        if height > 0 {
+            let parent = stored_hash(&mut tx, height - 1).await?;
+            if parent.is_some_and(|p| p != block.header.parent_hash) {
+                return Err(ForkDetected { height: block.header.height }.into());
+            }
+        }
         let timestamp = i64::try_from(block.header.timestamp)?;
         let gas_used = i64::try_from(block.header.gas_used)?;
         let gas_limit = i64::try_from(block.header.gas_limit)?;
@@ -74,21 +201,6 @@ impl BlockSink for PostgresSink {
                 da_blobs, consensus_metadata
             )
             VALUES ($1,$2,$3,$4,$5,$6,$7,$8,$9,$10,$11,$12,$13,$14,$15)
-            ON CONFLICT (height) DO UPDATE SET
-                hash = EXCLUDED.hash,
-                parent_hash = EXCLUDED.parent_hash,
-                timestamp_ms = EXCLUDED.timestamp_ms,
-                proposer = EXCLUDED.proposer,
-                state_root = EXCLUDED.state_root,
-                l1_tx_root = EXCLUDED.l1_tx_root,
-                da_root = EXCLUDED.da_root,
-                domain_roots = EXCLUDED.domain_roots,
-                gas_used = EXCLUDED.gas_used,
-                gas_limit = EXCLUDED.gas_limit,
-                base_fee = EXCLUDED.base_fee,
-                tx_count = EXCLUDED.tx_count,
-                da_blobs = EXCLUDED.da_blobs,
-                consensus_metadata = EXCLUDED.consensus_metadata
             "#,
             height,
             block_hash.to_vec(),
@@ -113,8 +225,90 @@ impl BlockSink for PostgresSink {
             ingest_tx(&mut tx, tx_obj, height, position as i32, block.header.height).await?;
         }
 
+        self.commit_with_state(tx, &block).await
+    }
+
+    async fn block_hash(&self, height: u64) -> anyhow::Result<Option<Hash>> {
+        let hash = sqlx::query_scalar!(
+            "SELECT hash FROM blocks WHERE height = $1",
+            i64::try_from(height)?
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(hash.and_then(|h| Hash::try_from(h.as_slice()).ok()))
+    }
+
+    async fn rollback_to(&mut self, height: u64) -> anyhow::Result<u64> {
+        let fork_height = i64::try_from(height)?;
+        let mut tx = self.pool.begin().await?;
+        let orphaned = sqlx::query!(
+            r#"
+            INSERT INTO orphaned_blocks (
+                height, hash, parent_hash, state_root, tx_count, tx_hashes, fork_height
+            )
+            SELECT b.height, b.hash, b.parent_hash, b.state_root, b.tx_count,
+                COALESCE(array_agg(t.tx_hash ORDER BY t.position)
+                    FILTER (WHERE t.tx_hash IS NOT NULL), '{}'),
+                $1
+            FROM blocks b
+            LEFT JOIN transactions t ON t.block_height = b.height
+            WHERE b.height > $1
+            GROUP BY b.height
+            "#,
+            fork_height
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // Batches, governance and privacy events go with their txs, runtime
+        // events with their blocks.
+        sqlx::query!("DELETE FROM transactions WHERE block_height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM blocks WHERE height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
+        // Activity counters aren't unwound; only the heights are kept consistent.
+        sqlx::query!("DELETE FROM accounts WHERE first_seen_height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            "UPDATE accounts SET last_seen_height = $1 WHERE last_seen_height > $1",
+            fork_height
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM balances WHERE height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM staking_positions WHERE height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM validators WHERE height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM treasury_balances WHERE height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM fee_pools WHERE height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM assets WHERE height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM asset_holders WHERE height > $1", fork_height)
+            .execute(&mut *tx)
+            .await?;
         tx.commit().await?;
-        Ok(())
+        if let Some(projection) = self.projection.as_mut() {
+            projection.rollback_to(height).await?;
+        }
+
+        if orphaned > 0 {
+            warn!("rolled back {orphaned} blocks above height {height}");
+        }
+        Ok(orphaned)
     }
 }
 
@@ -126,7 +320,7 @@ async fn ingest_tx(
     block_height_u64: u64,
 ) -> anyhow::Result<()> {
     let tx_hash = tx_hash(raw_tx);
-    let sender = derive_sender(&raw_tx.signature);
+    let sender = address_from_pubkey(&raw_tx.public_key);
     let payload_kind = payload_kind(&raw_tx.payload);
     let payload = serde_json::to_value(&raw_tx.payload)?;
     let events = payload_events(&raw_tx.payload);
@@ -186,10 +380,17 @@ async fn handle_payload(
 ) -> anyhow::Result<()> {
     let height = i64::try_from(block_height)?;
     match payload {
-        TxPayload::Transfer { to, .. } => {
+        TxPayload::Transfer { to, .. }
+        | TxPayload::AssetMint { to, .. }
+        | TxPayload::AssetTransfer { to, .. }
+        | TxPayload::VestingCreate {
+            beneficiary: to, ..
+        } => {
             touch_account(tx, to, height).await?;
         }
-        TxPayload::Delegate { validator, .. } | TxPayload::Undelegate { validator, .. } => {
+        TxPayload::Delegate { validator, .. }
+        | TxPayload::Undelegate { validator, .. }
+        | TxPayload::ClaimRewards { validator } => {
             touch_account(tx, validator, height).await?;
         }
         TxPayload::DomainCreate { domain_id, params } => {
@@ -198,16 +399,24 @@ async fn handle_payload(
         TxPayload::DomainConfigUpdate { domain_id, params } => {
             upsert_domain(tx, domain_id, params.clone()).await?;
         }
-        TxPayload::RollupBatchCommit { domain_id, blob_id } => {
+        TxPayload::RollupBatchCommit {
+            domain_id,
+            blob_id,
+            state_root,
+            da_root,
+            ..
+        } => {
             sqlx::query!(
                 r#"
-                INSERT INTO rollup_batches (domain_id, blob_id, block_height, tx_id)
-                VALUES ($1,$2,$3,$4)
+                INSERT INTO rollup_batches (domain_id, blob_id, block_height, tx_id, state_root, da_root)
+                VALUES ($1,$2,$3,$4,$5,$6)
                 "#,
                 domain_id,
                 blob_id,
                 height,
-                tx_id
+                tx_id,
+                state_root.to_vec(),
+                da_root.to_vec()
             )
             .execute(&mut **tx)
             .await?;
@@ -261,43 +470,246 @@ async fn handle_payload(
             .execute(&mut **tx)
             .await?;
         }
-        TxPayload::PrivacyDeposit { commitment, .. } => {
+        TxPayload::GovernanceCancel { proposal_id } => {
+            sqlx::query!(
+                r#"
+                INSERT INTO governance_events (tx_id, kind, proposal_id)
+                VALUES ($1,'cancel',$2)
+                "#,
+                tx_id,
+                proposal_id
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        TxPayload::PrivacyDeposit {
+            commitment, pool, ..
+        } => {
             sqlx::query!(
                 r#"
-                INSERT INTO privacy_actions (tx_id, action, commitment)
-                VALUES ($1,'deposit',$2)
+                INSERT INTO privacy_actions (tx_id, action, commitment, pool)
+                VALUES ($1,'deposit',$2,$3)
                 "#,
                 tx_id,
-                commitment.to_vec()
+                commitment.to_vec(),
+                pool.as_deref().unwrap_or(DEFAULT_PRIVACY_POOL)
             )
             .execute(&mut **tx)
             .await?;
         }
-        TxPayload::PrivacyWithdraw { nullifier, recipient, .. } => {
+        TxPayload::PrivacyWithdraw {
+            nullifier,
+            recipient,
+            pool,
+            relayer,
+            ..
+        } => {
             sqlx::query!(
                 r#"
-                INSERT INTO privacy_actions (tx_id, action, nullifier, recipient)
-                VALUES ($1,'withdraw',$2,$3)
+                INSERT INTO privacy_actions (tx_id, action, nullifier, recipient, pool)
+                VALUES ($1,'withdraw',$2,$3,$4)
                 "#,
                 tx_id,
                 nullifier.to_vec(),
-                recipient.to_vec()
+                recipient.to_vec(),
+                pool.as_deref().unwrap_or(DEFAULT_PRIVACY_POOL)
             )
             .execute(&mut **tx)
             .await?;
             touch_account(tx, recipient, height).await?;
+            if let Some(relayer) = relayer {
+                touch_account(tx, relayer, height).await?;
+            }
         }
-        TxPayload::RollupBridgeDeposit { .. }
+        TxPayload::DomainPause { .. }
+        | TxPayload::DomainResume { .. }
+        | TxPayload::DomainRetire { .. }
+        | TxPayload::RollupBridgeDeposit { .. }
+        | TxPayload::RollupBridgeBurn { .. }
         | TxPayload::RollupBridgeWithdraw { .. }
+        | TxPayload::ForcedWithdraw { .. }
+        | TxPayload::RollupBatchClaim { .. }
+        | TxPayload::FraudBisect { .. }
+        | TxPayload::FraudRespond { .. }
+        | TxPayload::FraudDefend { .. }
+        | TxPayload::SequencerRegister { .. }
+        | TxPayload::SequencerExit { .. }
+        | TxPayload::PreconfirmationChallenge { .. }
+        | TxPayload::PreconfirmationFulfill { .. }
+        | TxPayload::ForceInclude { .. }
+        | TxPayload::ForceIncludeProve { .. }
+        | TxPayload::LightClientCreate { .. }
+        | TxPayload::LightClientUpdate { .. }
+        | TxPayload::LightClientPacket { .. }
         | TxPayload::Stake { .. }
         | TxPayload::Unstake { .. }
         | TxPayload::SystemUpgrade { .. }
+        | TxPayload::RegisterBlsKey { .. }
+        | TxPayload::ValidatorEdit { .. }
+        | TxPayload::Unjail
+        | TxPayload::AssetCreate { .. }
+        | TxPayload::MultisigCreate { .. }
+        | TxPayload::MultisigSubmit { .. }
+        | TxPayload::MultisigApprove { .. }
+        | TxPayload::MultisigExecute { .. }
+        | TxPayload::Schedule { .. }
+        | TxPayload::ScheduleCancel { .. }
+        | TxPayload::DomainInboxProcess { .. }
+        | TxPayload::CrossDomainRefund { .. }
+        | TxPayload::SubmitEvidence { .. }
         | TxPayload::Delegate { .. }
         | TxPayload::Undelegate { .. } => { /* already handled or no-op */ }
     }
     Ok(())
 }
 
+async fn record_state(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    changes: &StateChanges,
+) -> anyhow::Result<()> {
+    let height = i64::try_from(changes.height)?;
+    // Replays after a restart write the same rows again.
+    for (address, entry) in &changes.balances {
+        sqlx::query!(
+            r#"
+            INSERT INTO balances (address, height, balance, nonce)
+            VALUES ($1,$2,$3,$4)
+            ON CONFLICT DO NOTHING
+            "#,
+            address.to_vec(),
+            height,
+            BigDecimal::from(entry.balance),
+            i64::try_from(entry.nonce)?
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    for (key, amount) in &changes.positions {
+        sqlx::query!(
+            r#"
+            INSERT INTO staking_positions (
+                kind, owner, validator_id, release_height, height, amount
+            )
+            VALUES ($1,$2,$3,$4,$5,$6)
+            ON CONFLICT DO NOTHING
+            "#,
+            key.kind.as_str(),
+            key.owner.to_vec(),
+            key.validator_id,
+            i64::try_from(key.release_height)?,
+            height,
+            BigDecimal::from(*amount)
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    for (id, entry) in &changes.validators {
+        sqlx::query!(
+            r#"
+            INSERT INTO validators (
+                validator_id, height, owner, status, commission_rate, moniker, website, details
+            )
+            VALUES ($1,$2,$3,$4,$5,$6,$7,$8)
+            ON CONFLICT DO NOTHING
+            "#,
+            id,
+            height,
+            entry.owner.to_vec(),
+            entry.status,
+            i16::from(entry.commission_rate),
+            entry.description.moniker,
+            entry.description.website,
+            entry.description.details
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    if let Some(balance) = changes.treasury {
+        sqlx::query!(
+            r#"
+            INSERT INTO treasury_balances (height, balance)
+            VALUES ($1,$2)
+            ON CONFLICT DO NOTHING
+            "#,
+            height,
+            BigDecimal::from(balance)
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    if let Some(pools) = &changes.fee_pools {
+        sqlx::query!(
+            r#"
+            INSERT INTO fee_pools (height, l1_gas, da, sequencer, treasury)
+            VALUES ($1,$2,$3,$4,$5)
+            ON CONFLICT DO NOTHING
+            "#,
+            height,
+            BigDecimal::from(pools.l1_gas),
+            BigDecimal::from(pools.da),
+            BigDecimal::from(pools.sequencer),
+            BigDecimal::from(pools.treasury)
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    for (id, entry) in &changes.assets {
+        sqlx::query!(
+            r#"
+            INSERT INTO assets (asset_id, height, symbol, decimals, issuer, supply, max_supply)
+            VALUES ($1,$2,$3,$4,$5,$6,$7)
+            ON CONFLICT DO NOTHING
+            "#,
+            id,
+            height,
+            entry.symbol,
+            i16::from(entry.decimals),
+            entry.issuer.to_vec(),
+            BigDecimal::from(entry.supply),
+            entry.max_supply.map(BigDecimal::from)
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    for ((asset_id, holder), balance) in &changes.asset_balances {
+        sqlx::query!(
+            r#"
+            INSERT INTO asset_holders (asset_id, holder, height, balance)
+            VALUES ($1,$2,$3,$4)
+            ON CONFLICT DO NOTHING
+            "#,
+            asset_id,
+            holder.to_vec(),
+            height,
+            BigDecimal::from(*balance)
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    for (index, (tx_position, event)) in changes.events.iter().enumerate() {
+        let tx_position = tx_position.map(i32::try_from).transpose()?;
+        sqlx::query!(
+            r#"
+            INSERT INTO events (block_height, tx_id, position, kind, attributes)
+            VALUES (
+                $1,
+                (SELECT id FROM transactions WHERE block_height = $1 AND position = $2),
+                $3, $4, $5
+            )
+            ON CONFLICT DO NOTHING
+            "#,
+            height,
+            tx_position,
+            i32::try_from(index)?,
+            event.kind,
+            serde_json::to_value(&event.attributes)?
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
 async fn touch_account(
     tx: &mut sqlx::Transaction<'_, Postgres>,
     address: &[u8; 32],
@@ -350,18 +762,54 @@ fn payload_kind(payload: &TxPayload) -> &'static str {
         TxPayload::Unstake { .. } => "unstake",
         TxPayload::Delegate { .. } => "delegate",
         TxPayload::Undelegate { .. } => "undelegate",
+        TxPayload::ClaimRewards { .. } => "claim_rewards",
+        TxPayload::ValidatorEdit { .. } => "validator_edit",
+        TxPayload::Unjail => "unjail",
         TxPayload::DomainCreate { .. } => "domain_create",
         TxPayload::DomainConfigUpdate { .. } => "domain_config_update",
+        TxPayload::DomainPause { .. } => "domain_pause",
+        TxPayload::DomainResume { .. } => "domain_resume",
+        TxPayload::DomainRetire { .. } => "domain_retire",
         TxPayload::RollupBatchCommit { .. } => "rollup_batch_commit",
         TxPayload::RollupBridgeDeposit { .. } => "rollup_bridge_deposit",
+        TxPayload::RollupBridgeBurn { .. } => "rollup_bridge_burn",
         TxPayload::RollupBridgeWithdraw { .. } => "rollup_bridge_withdraw",
         TxPayload::GovernanceProposal { .. } => "governance_proposal",
         TxPayload::GovernanceVote { .. } => "governance_vote",
         TxPayload::GovernanceBridgeApprove { .. } => "governance_bridge_approve",
         TxPayload::GovernanceExecute { .. } => "governance_execute",
+        TxPayload::GovernanceCancel { .. } => "governance_cancel",
         TxPayload::PrivacyDeposit { .. } => "privacy_deposit",
         TxPayload::PrivacyWithdraw { .. } => "privacy_withdraw",
         TxPayload::SystemUpgrade { .. } => "system_upgrade",
+        TxPayload::RegisterBlsKey { .. } => "register_bls_key",
+        TxPayload::DomainInboxProcess { .. } => "domain_inbox_process",
+        TxPayload::CrossDomainRefund { .. } => "cross_domain_refund",
+        TxPayload::SubmitEvidence { .. } => "submit_evidence",
+        TxPayload::AssetCreate { .. } => "asset_create",
+        TxPayload::AssetMint { .. } => "asset_mint",
+        TxPayload::AssetTransfer { .. } => "asset_transfer",
+        TxPayload::MultisigCreate { .. } => "multisig_create",
+        TxPayload::MultisigSubmit { .. } => "multisig_submit",
+        TxPayload::MultisigApprove { .. } => "multisig_approve",
+        TxPayload::MultisigExecute { .. } => "multisig_execute",
+        TxPayload::Schedule { .. } => "schedule",
+        TxPayload::ScheduleCancel { .. } => "schedule_cancel",
+        TxPayload::VestingCreate { .. } => "vesting_create",
+        TxPayload::ForcedWithdraw { .. } => "forced_withdraw",
+        TxPayload::RollupBatchClaim { .. } => "rollup_batch_claim",
+        TxPayload::FraudBisect { .. } => "fraud_bisect",
+        TxPayload::FraudRespond { .. } => "fraud_respond",
+        TxPayload::FraudDefend { .. } => "fraud_defend",
+        TxPayload::SequencerRegister { .. } => "sequencer_register",
+        TxPayload::SequencerExit { .. } => "sequencer_exit",
+        TxPayload::PreconfirmationChallenge { .. } => "preconfirmation_challenge",
+        TxPayload::PreconfirmationFulfill { .. } => "preconfirmation_fulfill",
+        TxPayload::ForceInclude { .. } => "force_include",
+        TxPayload::ForceIncludeProve { .. } => "force_include_prove",
+        TxPayload::LightClientCreate { .. } => "light_client_create",
+        TxPayload::LightClientUpdate { .. } => "light_client_update",
+        TxPayload::LightClientPacket { .. } => "light_client_packet",
     }
 }
 
@@ -369,11 +817,6 @@ fn payload_events(payload: &TxPayload) -> Vec<String> {
     vec![payload_kind(payload).to_string()]
 }
 
-fn tx_hash(tx: &Tx) -> [u8; 32] {
-    let bytes = bincode::serialize(tx).unwrap_or_default();
-    *blake3::hash(&bytes).as_bytes()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -395,5 +838,53 @@ mod tests {
             "domain_create"
         );
     }
+
+    fn block(height: u64, parent_hash: Hash, timestamp: u64) -> Block {
+        Block {
+            header: runtime::BlockHeader {
+                parent_hash,
+                height,
+                timestamp,
+                proposer_id: [0u8; 32],
+                state_root: [0u8; 32],
+                l1_tx_root: [0u8; 32],
+                da_commitment: None,
+                domain_roots: vec![],
+                gas_used: 0,
+                gas_limit: 0,
+                base_fee: 0,
+                snapshot_root: None,
+                protocol_version: 0,
+                consensus_metadata: serde_json::json!({}),
+            },
+            transactions: vec![],
+            da_blobs: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn forks_are_detected_and_rolled_back() {
+        let mut sink = InMemorySink::default();
+        let b0 = block(0, [0u8; 32], 0);
+        let b1 = block(1, hash_block(&b0), 1);
+        let b2 = block(2, hash_block(&b1), 2);
+        for b in [&b0, &b1, &b2, &b2] {
+            sink.ingest_block(b.clone()).await.unwrap();
+        }
+        assert_eq!(sink.blocks.len(), 3);
+
+        let other1 = block(1, hash_block(&b0), 10);
+        let other2 = block(2, hash_block(&other1), 11);
+        for b in [&other1, &other2] {
+            let err = sink.ingest_block(b.clone()).await.unwrap_err();
+            assert!(err.downcast_ref::<ForkDetected>().is_some());
+        }
+
+        assert_eq!(sink.rollback_to(0).await.unwrap(), 2);
+        sink.ingest_block(other1.clone()).await.unwrap();
+        sink.ingest_block(other2).await.unwrap();
+        assert_eq!(sink.block_hash(1).await.unwrap(), Some(hash_block(&other1)));
+        assert_eq!(sink.orphaned.len(), 2);
+    }
 }
 
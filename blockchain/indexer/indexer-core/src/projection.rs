@@ -0,0 +1,543 @@
+//! Account and staking state derived by replaying indexed blocks through the
+//! runtime from genesis, so balances, stakes, delegations, unbondings and
+//! validator profiles follow exactly the rules the chain applies, including
+//! failed txs, gas fees, rewards and the exit queue. The fee pools and
+//! user-issued assets are tracked the same way.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use runtime::{
+    apply_block, from_genesis, Address, Block, DomainCheckpoint, Event, ExecutionContext,
+    GenesisConfig,
+};
+use state::{ChainState, FeePools, InMemoryStateStore, StateStore, ValidatorDescription};
+use uuid::Uuid;
+
+/// How far back a reorg can roll the projection; matches the node's own
+/// reorg limit.
+pub const MAX_ROLLBACK_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PositionKind {
+    Validator,
+    Delegation,
+    /// Unstake waiting in the exit queue.
+    Exit,
+    Unbonding,
+}
+
+impl PositionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PositionKind::Validator => "validator",
+            PositionKind::Delegation => "delegation",
+            PositionKind::Exit => "exit",
+            PositionKind::Unbonding => "unbonding",
+        }
+    }
+}
+
+/// Identifies a staking position across heights. Amounts of entries sharing
+/// a key are summed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PositionKey {
+    pub kind: PositionKind,
+    pub owner: Address,
+    /// Nil for unbondings not tied to a validator.
+    pub validator_id: Uuid,
+    /// Only set for unbondings.
+    pub release_height: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceEntry {
+    pub balance: u128,
+    pub nonce: u64,
+}
+
+/// A validator's status, commission and profile; its stake is a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorEntry {
+    pub owner: Address,
+    pub status: String,
+    pub commission_rate: u8,
+    pub description: ValidatorDescription,
+}
+
+/// An asset's registry entry; holdings are reported separately.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetEntry {
+    pub symbol: String,
+    pub decimals: u8,
+    pub issuer: Address,
+    pub supply: u128,
+    pub max_supply: Option<u128>,
+}
+
+/// What a block changed. Positions that closed are reported with amount 0.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StateChanges {
+    pub height: u64,
+    pub balances: Vec<(Address, BalanceEntry)>,
+    pub positions: Vec<(PositionKey, u128)>,
+    /// Validators are never removed, so only new and edited ones appear.
+    pub validators: Vec<(Uuid, ValidatorEntry)>,
+    /// Treasury pool balance, when the block changed it.
+    pub treasury: Option<u128>,
+    /// Every fee pool, when the block changed any of them.
+    pub fee_pools: Option<FeePools>,
+    /// Assets are never removed, so only new and re-supplied ones appear.
+    pub assets: Vec<(Uuid, AssetEntry)>,
+    /// Holdings keyed by asset and holder; emptied ones are reported as 0.
+    pub asset_balances: Vec<((Uuid, Address), u128)>,
+    /// Events in block order, with the position of the tx that emitted them;
+    /// `None` for those of the block itself.
+    pub events: Vec<(Option<usize>, Event)>,
+}
+
+struct Checkpoint {
+    chain: ChainState,
+    domains: DomainCheckpoint,
+}
+
+pub struct Projection {
+    ctx: ExecutionContext<InMemoryStateStore>,
+    next_height: u64,
+    /// State before each of the most recent blocks, oldest first.
+    checkpoints: VecDeque<(u64, Checkpoint)>,
+}
+
+impl Projection {
+    pub async fn new(genesis: GenesisConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            ctx: from_genesis(genesis).await?,
+            next_height: 0,
+            checkpoints: VecDeque::new(),
+        })
+    }
+
+    /// Height of the next block to apply.
+    pub fn next_height(&self) -> u64 {
+        self.next_height
+    }
+
+    /// Applies `block`, which must be the next one, and returns what it
+    /// changed. The first block's changes include the genesis allocations.
+    pub async fn apply(&mut self, block: &Block) -> anyhow::Result<StateChanges> {
+        let height = block.header.height;
+        if height != self.next_height {
+            anyhow::bail!(
+                "state projection expects block {} but got {height}",
+                self.next_height
+            );
+        }
+        let before = self.ctx.state.get_chain_state().await?;
+        let checkpoint = Checkpoint {
+            chain: before.clone(),
+            domains: self.ctx.domains.checkpoint(),
+        };
+        let mut result = match apply_block(&self.ctx, block).await {
+            Ok(result) => result,
+            Err(err) => {
+                self.restore(checkpoint).await?;
+                return Err(err.context(format!("replaying block {height}")));
+            }
+        };
+        if block.header.state_root != [0u8; 32] && block.header.state_root != result.state_root {
+            self.restore(checkpoint).await?;
+            anyhow::bail!("state projection diverged from the chain at height {height}");
+        }
+        let after = self.ctx.state.get_chain_state().await?;
+
+        self.checkpoints.push_back((height, checkpoint));
+        if self.checkpoints.len() > MAX_ROLLBACK_DEPTH {
+            self.checkpoints.pop_front();
+        }
+        self.next_height = height + 1;
+
+        let mut events: Vec<(Option<usize>, Event)> = Vec::new();
+        for (position, receipt) in result.receipts.iter_mut().enumerate() {
+            events.extend(receipt.events.drain(..).map(|e| (Some(position), e)));
+        }
+        // `result.events` lists the tx events again before the block's own.
+        let block_events = result.events.split_off(events.len());
+        events.extend(block_events.into_iter().map(|e| (None, e)));
+
+        let base = if height == 0 {
+            ChainState::default()
+        } else {
+            before
+        };
+        let validators_before = validators(&base);
+        let assets_before = assets(&base);
+        Ok(StateChanges {
+            height,
+            balances: changed(
+                &balances(&base),
+                &balances(&after),
+                BalanceEntry {
+                    balance: 0,
+                    nonce: 0,
+                },
+            ),
+            positions: changed(&positions(&base), &positions(&after), 0),
+            validators: validators(&after)
+                .into_iter()
+                .filter(|(id, entry)| validators_before.get(id) != Some(entry))
+                .collect(),
+            treasury: (after.fee_pools.treasury != base.fee_pools.treasury)
+                .then_some(after.fee_pools.treasury),
+            fee_pools: (after.fee_pools != base.fee_pools).then(|| after.fee_pools.clone()),
+            assets: assets(&after)
+                .into_iter()
+                .filter(|(id, entry)| assets_before.get(id) != Some(entry))
+                .collect(),
+            asset_balances: changed(&asset_balances(&base), &asset_balances(&after), 0),
+            events,
+        })
+    }
+
+    /// Puts the projection back to its state after block `height`.
+    pub async fn rollback_to(&mut self, height: u64) -> anyhow::Result<()> {
+        let target = height + 1;
+        if target >= self.next_height {
+            return Ok(());
+        }
+        let Some(index) = self.checkpoints.iter().position(|(h, _)| *h == target) else {
+            anyhow::bail!(
+                "state projection can only roll back {MAX_ROLLBACK_DEPTH} blocks; \
+                 reindex from genesis"
+            );
+        };
+        let (_, checkpoint) = self
+            .checkpoints
+            .drain(index..)
+            .next()
+            .expect("checkpoint at index");
+        self.restore(checkpoint).await?;
+        self.next_height = target;
+        Ok(())
+    }
+
+    /// Reverts the last block applied.
+    pub async fn undo(&mut self) -> anyhow::Result<()> {
+        let Some((height, checkpoint)) = self.checkpoints.pop_back() else {
+            anyhow::bail!("state projection has no block to undo");
+        };
+        self.restore(checkpoint).await?;
+        self.next_height = height;
+        Ok(())
+    }
+
+    async fn restore(&self, checkpoint: Checkpoint) -> anyhow::Result<()> {
+        self.ctx.domains.restore(checkpoint.domains);
+        self.ctx.state.put_chain_state(checkpoint.chain).await
+    }
+}
+
+fn balances(chain: &ChainState) -> BTreeMap<Address, BalanceEntry> {
+    chain
+        .accounts
+        .values()
+        .map(|a| {
+            let entry = BalanceEntry {
+                balance: a.balance_x,
+                nonce: a.nonce,
+            };
+            (a.address, entry)
+        })
+        .collect()
+}
+
+fn validators(chain: &ChainState) -> BTreeMap<Uuid, ValidatorEntry> {
+    chain
+        .validators
+        .values()
+        .map(|v| {
+            let entry = ValidatorEntry {
+                owner: v.owner,
+                status: format!("{:?}", v.status),
+                commission_rate: v.commission_rate,
+                description: v.description.clone(),
+            };
+            (v.id, entry)
+        })
+        .collect()
+}
+
+fn assets(chain: &ChainState) -> BTreeMap<Uuid, AssetEntry> {
+    chain
+        .assets
+        .values()
+        .map(|a| {
+            let entry = AssetEntry {
+                symbol: a.symbol.clone(),
+                decimals: a.decimals,
+                issuer: a.issuer,
+                supply: a.supply,
+                max_supply: a.max_supply,
+            };
+            (a.id, entry)
+        })
+        .collect()
+}
+
+fn asset_balances(chain: &ChainState) -> BTreeMap<(Uuid, Address), u128> {
+    chain
+        .accounts
+        .values()
+        .flat_map(|a| {
+            a.assets
+                .iter()
+                .map(|(asset_id, balance)| ((*asset_id, a.address), *balance))
+        })
+        .collect()
+}
+
+fn positions(chain: &ChainState) -> BTreeMap<PositionKey, u128> {
+    let mut positions = BTreeMap::new();
+    let mut add = |kind, owner, validator_id, release_height, amount: u128| {
+        let key = PositionKey {
+            kind,
+            owner,
+            validator_id,
+            release_height,
+        };
+        let total: &mut u128 = positions.entry(key).or_default();
+        *total = total.saturating_add(amount);
+    };
+    for v in chain.validators.values() {
+        add(PositionKind::Validator, v.owner, v.id, 0, v.stake);
+    }
+    for d in chain.delegations.values() {
+        add(
+            PositionKind::Delegation,
+            d.delegator,
+            d.validator_id,
+            0,
+            d.stake,
+        );
+    }
+    for exit in &chain.exit_queue {
+        add(
+            PositionKind::Exit,
+            exit.owner,
+            exit.validator_id,
+            0,
+            exit.amount,
+        );
+    }
+    for u in &chain.pending_unbonds {
+        let validator_id = u.validator_id.unwrap_or(Uuid::nil());
+        add(
+            PositionKind::Unbonding,
+            u.owner,
+            validator_id,
+            u.release_height,
+            u.amount,
+        );
+    }
+    positions
+}
+
+/// Entries of `after` that differ from `before`, plus `removed` for keys
+/// that are gone.
+fn changed<K: Ord + Copy, V: PartialEq + Copy>(
+    before: &BTreeMap<K, V>,
+    after: &BTreeMap<K, V>,
+    removed: V,
+) -> Vec<(K, V)> {
+    let mut changes: Vec<(K, V)> = after
+        .iter()
+        .filter(|(k, v)| before.get(k) != Some(v))
+        .map(|(k, v)| (*k, *v))
+        .collect();
+    changes.extend(
+        before
+            .keys()
+            .filter(|k| !after.contains_key(k))
+            .map(|k| (*k, removed)),
+    );
+    changes.sort_by_key(|a| a.0);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::SigningKey;
+    use runtime::{
+        address_from_pubkey, devnet_genesis, sign_bytes, tx_signing_bytes, BlockHeader, Tx,
+        TxPayload,
+    };
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn address(seed: u8) -> Address {
+        address_from_pubkey(&key(seed).verifying_key().to_bytes())
+    }
+
+    fn tx(nonce: u64, payload: TxPayload) -> Tx {
+        let mut tx = Tx {
+            chain_id: "kova-devnet".into(),
+            nonce,
+            gas_limit: 100_000,
+            max_fee: None,
+            max_priority_fee: None,
+            gas_price: Some(1),
+            payload,
+            public_key: key(1).verifying_key().to_bytes().to_vec(),
+            signature: vec![],
+        };
+        tx.signature = sign_bytes(&key(1), &tx_signing_bytes(&tx).unwrap());
+        tx
+    }
+
+    fn block(height: u64, transactions: Vec<Tx>) -> Block {
+        Block {
+            header: BlockHeader {
+                parent_hash: [0u8; 32],
+                height,
+                timestamp: 0,
+                proposer_id: [0u8; 32],
+                state_root: [0u8; 32],
+                l1_tx_root: [0u8; 32],
+                da_commitment: None,
+                domain_roots: vec![],
+                gas_used: 0,
+                gas_limit: 30_000_000,
+                base_fee: 1,
+                snapshot_root: None,
+                protocol_version: 0,
+                consensus_metadata: serde_json::json!({}),
+            },
+            transactions,
+            da_blobs: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_balances_and_stakes_and_rolls_back() {
+        let mut genesis = devnet_genesis();
+        genesis.initial_accounts = vec![(address(1), 1_000_000)];
+        let mut projection = Projection::new(genesis).await.unwrap();
+
+        let transfer = TxPayload::Transfer {
+            to: address(2),
+            amount: 100,
+        };
+        let changes = projection
+            .apply(&block(0, vec![tx(0, transfer)]))
+            .await
+            .unwrap();
+        let sender = BalanceEntry {
+            balance: 1_000_000 - 100 - 21_000,
+            nonce: 1,
+        };
+        assert!(changes.balances.contains(&(address(1), sender)));
+        let recipient = BalanceEntry {
+            balance: 100,
+            nonce: 0,
+        };
+        assert!(changes.balances.contains(&(address(2), recipient)));
+        // The burned share of the gas fee.
+        assert_eq!(changes.treasury, Some(21_000 * 30 / 100));
+        let pools = changes.fee_pools.as_ref().unwrap();
+        assert_eq!(pools.treasury, 21_000 * 30 / 100);
+        let (position, event) = &changes.events[0];
+        assert_eq!(*position, Some(0));
+        assert_eq!(event.kind, "transfer");
+
+        let stake = tx(1, TxPayload::Stake { amount: 50_000 });
+        let changes = projection.apply(&block(1, vec![stake])).await.unwrap();
+        let (position, amount) = changes.positions[0];
+        assert_eq!(position.kind, PositionKind::Validator);
+        assert_eq!(position.owner, address(1));
+        assert_eq!(amount, 50_000);
+
+        assert!(projection.apply(&block(3, vec![])).await.is_err());
+        projection.rollback_to(0).await.unwrap();
+        assert_eq!(projection.next_height(), 1);
+        let unfunded = tx(1, TxPayload::Stake { amount: 5_000_000 });
+        let changes = projection.apply(&block(1, vec![unfunded])).await.unwrap();
+        // Failed, so only the gas is charged.
+        assert!(changes.positions.is_empty());
+        assert_eq!(changes.balances.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn records_new_and_edited_validators() {
+        let mut genesis = devnet_genesis();
+        genesis.initial_accounts = vec![(address(1), 1_000_000)];
+        let mut projection = Projection::new(genesis).await.unwrap();
+
+        let stake = tx(0, TxPayload::Stake { amount: 50_000 });
+        let changes = projection.apply(&block(0, vec![stake])).await.unwrap();
+        let (id, entry) = &changes.validators[0];
+        assert_eq!(entry.owner, address(1));
+        assert_eq!(entry.description, ValidatorDescription::default());
+
+        let edit = TxPayload::ValidatorEdit {
+            commission_rate: Some(1),
+            moniker: Some("alpha".into()),
+            website: None,
+            details: None,
+        };
+        let changes = projection
+            .apply(&block(1, vec![tx(1, edit)]))
+            .await
+            .unwrap();
+        assert_eq!(changes.validators.len(), 1);
+        let (edited, entry) = &changes.validators[0];
+        assert_eq!(edited, id);
+        assert_eq!(entry.commission_rate, 1);
+        assert_eq!(entry.description.moniker, "alpha");
+
+        let changes = projection.apply(&block(2, vec![])).await.unwrap();
+        assert!(changes.validators.is_empty());
+    }
+
+    #[tokio::test]
+    async fn records_assets_and_holders() {
+        let mut genesis = devnet_genesis();
+        genesis.initial_accounts = vec![(address(1), 1_000_000)];
+        let mut projection = Projection::new(genesis).await.unwrap();
+
+        let create = TxPayload::AssetCreate {
+            symbol: "USDK".into(),
+            decimals: 6,
+            max_supply: None,
+        };
+        let id = state::asset_id(&address(1), 0);
+        let mint = TxPayload::AssetMint {
+            asset_id: id,
+            to: address(1),
+            amount: 500,
+        };
+        let changes = projection
+            .apply(&block(0, vec![tx(0, create), tx(1, mint)]))
+            .await
+            .unwrap();
+        let (created, entry) = &changes.assets[0];
+        assert_eq!(*created, id);
+        assert_eq!(entry.symbol, "USDK");
+        assert_eq!(entry.supply, 500);
+        assert_eq!(changes.asset_balances, vec![((id, address(1)), 500)]);
+
+        let transfer = TxPayload::AssetTransfer {
+            asset_id: id,
+            to: address(2),
+            amount: 500,
+        };
+        let changes = projection
+            .apply(&block(1, vec![tx(2, transfer)]))
+            .await
+            .unwrap();
+        // Supply is unchanged; the sender's emptied holding is reported as 0.
+        assert!(changes.assets.is_empty());
+        assert!(changes.asset_balances.contains(&((id, address(1)), 0)));
+        assert!(changes.asset_balances.contains(&((id, address(2)), 500)));
+    }
+}
@@ -0,0 +1,60 @@
+//! Mix nodes a route can be drawn from, as published to clients and
+//! gateways, e.g. `{"nodes": [{"public_key": "<hex>", "url": "http://mix1:8050"}]}`.
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::sphinx::{NodeKey, MAX_HOPS};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MixNode {
+    #[serde(serialize_with = "to_hex", deserialize_with = "from_hex")]
+    pub public_key: NodeKey,
+    /// Base URL; packets are posted to `{url}/relay`.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Directory {
+    pub nodes: Vec<MixNode>,
+}
+
+impl Directory {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn get(&self, public_key: &NodeKey) -> Option<&MixNode> {
+        self.nodes.iter().find(|n| n.public_key == *public_key)
+    }
+
+    /// `hops` distinct nodes in random order.
+    pub fn route(&self, hops: usize) -> anyhow::Result<Vec<MixNode>> {
+        if hops == 0 || hops > MAX_HOPS {
+            anyhow::bail!("route must have between 1 and {MAX_HOPS} hops");
+        }
+        if hops > self.nodes.len() {
+            anyhow::bail!(
+                "directory has {} nodes, route needs {hops}",
+                self.nodes.len()
+            );
+        }
+        Ok(self
+            .nodes
+            .choose_multiple(&mut rand::thread_rng(), hops)
+            .cloned()
+            .collect())
+    }
+}
+
+fn to_hex<S: Serializer>(key: &NodeKey, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex::encode(key))
+}
+
+fn from_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NodeKey, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    hex::decode(value.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| serde::de::Error::custom("public_key must be 32 bytes of hex"))
+}
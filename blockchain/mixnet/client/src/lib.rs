@@ -1,9 +1,34 @@
+//! Client side of the mixnet: wraps a request in a Sphinx packet routed
+//! through random mix nodes, so the node it reaches can't tell who sent it.
+
+pub mod directory;
+pub mod sphinx;
+
 use reqwest::Client;
 use tracing::info;
 
-pub async fn send_via_mixnet(endpoint: &str, payload: &[u8]) -> anyhow::Result<()> {
-    info!("mixnet stub sending to {}", endpoint);
-    let _ = Client::new().post(endpoint).body(payload.to_vec()).send().await?;
+pub use directory::{Directory, MixNode};
+pub use sphinx::{build_packet, process, Hop, NodeKey, Packet, Processed, MAX_HOPS};
+
+/// Sends `payload` to `endpoint` through `hops` nodes of `directory`. The
+/// exit node posts it as JSON; nothing comes back, so callers look for the
+/// effect (e.g. a tx receipt) instead.
+pub async fn send_via_mixnet(
+    directory: &Directory,
+    hops: usize,
+    endpoint: &str,
+    payload: &[u8],
+) -> anyhow::Result<()> {
+    let route = directory.route(hops)?;
+    let keys: Vec<NodeKey> = route.iter().map(|n| n.public_key).collect();
+    let packet = build_packet(&keys, endpoint, payload)?;
+    let entry = &route[0];
+    info!("sending mixnet packet through {hops} hops");
+    Client::new()
+        .post(format!("{}/relay", entry.url.trim_end_matches('/')))
+        .body(packet.to_bytes())
+        .send()
+        .await?
+        .error_for_status()?;
     Ok(())
 }
-
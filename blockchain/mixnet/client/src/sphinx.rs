@@ -0,0 +1,379 @@
+//! Sphinx-style packets. The sender picks a route of mix nodes and wraps the
+//! payload in one layer per hop; each node can only peel its own layer,
+//! which tells it the next hop and nothing else. Every packet has the same
+//! size whatever the route length and payload, and the group element is
+//! re-blinded at every hop, so packets can't be linked across hops.
+//!
+//! Per hop, x25519 against the node's key gives a shared secret, from which
+//! blake3 derives a header stream key, a header MAC key, a payload stream key
+//! and the blinding factor for the next hop. Only the exit node learns the
+//! destination, which travels inside the payload with a MAC that shows it
+//! wasn't tampered with on the way.
+
+use rand::RngCore;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Longest route a packet can carry.
+pub const MAX_HOPS: usize = 5;
+/// Routing block for one hop: a flag, the next node's key and its MAC.
+const HOP_LEN: usize = 1 + 32 + 32;
+const BETA_LEN: usize = MAX_HOPS * HOP_LEN;
+/// Size of every payload, padding included.
+pub const PAYLOAD_LEN: usize = 16 * 1024;
+pub const PACKET_LEN: usize = 32 + BETA_LEN + 32 + PAYLOAD_LEN;
+/// What the payload holds besides the body: MAC, destination and lengths.
+const PAYLOAD_OVERHEAD: usize = 32 + 2 + 4;
+
+const FORWARD: u8 = 0;
+const EXIT: u8 = 1;
+
+const HEADER_STREAM_CONTEXT: &str = "kova mixnet header stream v1";
+const HEADER_MAC_CONTEXT: &str = "kova mixnet header mac v1";
+const PAYLOAD_STREAM_CONTEXT: &str = "kova mixnet payload stream v1";
+const PAYLOAD_MAC_CONTEXT: &str = "kova mixnet payload mac v1";
+const BLINDING_CONTEXT: &str = "kova mixnet blinding v1";
+const REPLAY_CONTEXT: &str = "kova mixnet replay v1";
+
+/// A mix node's public key, which is also its id in the directory.
+pub type NodeKey = [u8; 32];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// Blinded ephemeral key for the node the packet is sent to.
+    alpha: [u8; 32],
+    /// Encrypted routing blocks.
+    beta: Vec<u8>,
+    /// MAC over `beta` under the node's header MAC key.
+    gamma: [u8; 32],
+    payload: Vec<u8>,
+}
+
+impl Packet {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(PACKET_LEN);
+        out.extend_from_slice(&self.alpha);
+        out.extend_from_slice(&self.beta);
+        out.extend_from_slice(&self.gamma);
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.len() != PACKET_LEN {
+            anyhow::bail!("packet is {} bytes, expected {PACKET_LEN}", bytes.len());
+        }
+        let (alpha, rest) = bytes.split_at(32);
+        let (beta, rest) = rest.split_at(BETA_LEN);
+        let (gamma, payload) = rest.split_at(32);
+        Ok(Self {
+            alpha: alpha.try_into()?,
+            beta: beta.to_vec(),
+            gamma: gamma.try_into()?,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+/// What a node does with a packet after peeling its layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hop {
+    Forward { next: NodeKey, packet: Packet },
+    Exit { destination: String, body: Vec<u8> },
+}
+
+/// A processed packet. `replay_tag` is the same for every copy of a packet
+/// at this node; nodes drop packets whose tag they've seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Processed {
+    pub replay_tag: [u8; 32],
+    pub hop: Hop,
+}
+
+struct HopKeys {
+    header_stream: [u8; 32],
+    header_mac: [u8; 32],
+    payload_stream: [u8; 32],
+    payload_mac: [u8; 32],
+}
+
+impl HopKeys {
+    fn derive(shared: &[u8; 32]) -> Self {
+        Self {
+            header_stream: blake3::derive_key(HEADER_STREAM_CONTEXT, shared),
+            header_mac: blake3::derive_key(HEADER_MAC_CONTEXT, shared),
+            payload_stream: blake3::derive_key(PAYLOAD_STREAM_CONTEXT, shared),
+            payload_mac: blake3::derive_key(PAYLOAD_MAC_CONTEXT, shared),
+        }
+    }
+}
+
+fn keystream(key: &[u8; 32], len: usize) -> Vec<u8> {
+    let mut out = vec![0u8; len];
+    blake3::Hasher::new_keyed(key).finalize_xof().fill(&mut out);
+    out
+}
+
+fn xor(data: &mut [u8], stream: &[u8]) {
+    for (byte, key) in data.iter_mut().zip(stream) {
+        *byte ^= key;
+    }
+}
+
+fn mac(key: &[u8; 32], data: &[u8]) -> [u8; 32] {
+    *blake3::keyed_hash(key, data).as_bytes()
+}
+
+fn blinding(alpha: &[u8; 32], shared: &[u8; 32]) -> StaticSecret {
+    let mut material = [0u8; 64];
+    material[..32].copy_from_slice(alpha);
+    material[32..].copy_from_slice(shared);
+    StaticSecret::from(blake3::derive_key(BLINDING_CONTEXT, &material))
+}
+
+fn dh(secret: &StaticSecret, public: &[u8; 32]) -> anyhow::Result<[u8; 32]> {
+    let shared = secret.diffie_hellman(&PublicKey::from(*public));
+    if !shared.was_contributory() {
+        anyhow::bail!("invalid mixnet key");
+    }
+    Ok(shared.to_bytes())
+}
+
+/// Largest body that fits a packet sent to `destination`.
+pub fn max_body_len(destination: &str) -> usize {
+    PAYLOAD_LEN.saturating_sub(PAYLOAD_OVERHEAD + destination.len())
+}
+
+/// Wraps `body` for delivery to `destination` by the last node of `route`.
+pub fn build_packet(route: &[NodeKey], destination: &str, body: &[u8]) -> anyhow::Result<Packet> {
+    if route.is_empty() || route.len() > MAX_HOPS {
+        anyhow::bail!("route must have between 1 and {MAX_HOPS} hops");
+    }
+    let destination_len = u16::try_from(destination.len())?;
+    if body.len() > max_body_len(destination) {
+        anyhow::bail!(
+            "body of {} bytes exceeds the {} a packet carries",
+            body.len(),
+            max_body_len(destination)
+        );
+    }
+    let mut rng = rand::thread_rng();
+    let mut seed = [0u8; 32];
+    rng.fill_bytes(&mut seed);
+    let ephemeral = StaticSecret::from(seed);
+
+    // The shared secret with every hop, and the blinded key each one sees.
+    let mut alphas = Vec::with_capacity(route.len());
+    let mut keys = Vec::with_capacity(route.len());
+    let mut blinds: Vec<StaticSecret> = Vec::with_capacity(route.len());
+    let mut alpha = PublicKey::from(&ephemeral).to_bytes();
+    for node in route {
+        let mut shared = dh(&ephemeral, node)?;
+        for blind in &blinds {
+            shared = dh(blind, &shared)?;
+        }
+        let blind = blinding(&alpha, &shared);
+        alphas.push(alpha);
+        keys.push(HopKeys::derive(&shared));
+        alpha = dh(&blind, &alpha)?;
+        blinds.push(blind);
+    }
+
+    // Filler keeps the header the same length, and its MACs valid, as each
+    // hop shifts out its block and pads the end.
+    let mut filler = Vec::with_capacity((route.len() - 1) * HOP_LEN);
+    for hop in &keys[..route.len() - 1] {
+        filler.extend_from_slice(&[0u8; HOP_LEN]);
+        let stream = keystream(&hop.header_stream, BETA_LEN + HOP_LEN);
+        let start = BETA_LEN + HOP_LEN - filler.len();
+        xor(&mut filler, &stream[start..]);
+    }
+
+    let exit = &keys[route.len() - 1];
+    let mut beta = vec![0u8; BETA_LEN - filler.len()];
+    beta[0] = EXIT;
+    rng.fill_bytes(&mut beta[HOP_LEN..]);
+    let stream = keystream(&exit.header_stream, beta.len());
+    xor(&mut beta, &stream);
+    beta.extend_from_slice(&filler);
+    let mut gamma = mac(&exit.header_mac, &beta);
+    for (i, hop) in keys.iter().enumerate().rev().skip(1) {
+        let mut next = Vec::with_capacity(BETA_LEN);
+        next.push(FORWARD);
+        next.extend_from_slice(&route[i + 1]);
+        next.extend_from_slice(&gamma);
+        next.extend_from_slice(&beta[..BETA_LEN - HOP_LEN]);
+        xor(&mut next, &keystream(&hop.header_stream, BETA_LEN));
+        beta = next;
+        gamma = mac(&hop.header_mac, &beta);
+    }
+
+    let mut contents = Vec::with_capacity(PAYLOAD_LEN - 32);
+    contents.extend_from_slice(&destination_len.to_le_bytes());
+    contents.extend_from_slice(destination.as_bytes());
+    contents.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    contents.extend_from_slice(body);
+    contents.resize(PAYLOAD_LEN - 32, 0);
+    let mut payload = mac(&exit.payload_mac, &contents).to_vec();
+    payload.extend_from_slice(&contents);
+    for hop in keys.iter().rev() {
+        xor(&mut payload, &keystream(&hop.payload_stream, PAYLOAD_LEN));
+    }
+
+    Ok(Packet {
+        alpha: alphas[0],
+        beta,
+        gamma,
+        payload,
+    })
+}
+
+/// Peels the layer of `packet` meant for the node holding `secret`.
+pub fn process(secret: &StaticSecret, packet: &Packet) -> anyhow::Result<Processed> {
+    if packet.beta.len() != BETA_LEN || packet.payload.len() != PAYLOAD_LEN {
+        anyhow::bail!("malformed packet");
+    }
+    let shared = dh(secret, &packet.alpha)?;
+    let keys = HopKeys::derive(&shared);
+    if blake3::keyed_hash(&keys.header_mac, &packet.beta) != blake3::Hash::from(packet.gamma) {
+        anyhow::bail!("packet header MAC mismatch");
+    }
+    let replay_tag = blake3::derive_key(REPLAY_CONTEXT, &shared);
+
+    let mut routing = packet.beta.clone();
+    routing.extend_from_slice(&[0u8; HOP_LEN]);
+    xor(
+        &mut routing,
+        &keystream(&keys.header_stream, BETA_LEN + HOP_LEN),
+    );
+    let mut payload = packet.payload.clone();
+    xor(&mut payload, &keystream(&keys.payload_stream, PAYLOAD_LEN));
+
+    let hop = match routing[0] {
+        FORWARD => {
+            let next: NodeKey = routing[1..33].try_into()?;
+            let gamma: [u8; 32] = routing[33..HOP_LEN].try_into()?;
+            let alpha = dh(&blinding(&packet.alpha, &shared), &packet.alpha)?;
+            Hop::Forward {
+                next,
+                packet: Packet {
+                    alpha,
+                    beta: routing[HOP_LEN..].to_vec(),
+                    gamma,
+                    payload,
+                },
+            }
+        }
+        EXIT => {
+            let (tag, contents) = payload.split_at(32);
+            if blake3::keyed_hash(&keys.payload_mac, contents)
+                != blake3::Hash::from(<[u8; 32]>::try_from(tag)?)
+            {
+                anyhow::bail!("packet payload MAC mismatch");
+            }
+            let (destination, body) = parse_contents(contents)?;
+            Hop::Exit { destination, body }
+        }
+        flag => anyhow::bail!("unknown routing flag {flag}"),
+    };
+    Ok(Processed { replay_tag, hop })
+}
+
+fn parse_contents(contents: &[u8]) -> anyhow::Result<(String, Vec<u8>)> {
+    let take = |from: usize, len: usize| {
+        contents
+            .get(from..from + len)
+            .ok_or_else(|| anyhow::anyhow!("truncated payload"))
+    };
+    let destination_len = u16::from_le_bytes(take(0, 2)?.try_into()?) as usize;
+    let destination = String::from_utf8(take(2, destination_len)?.to_vec())?;
+    let body_at = 2 + destination_len;
+    let body_len = u32::from_le_bytes(take(body_at, 4)?.try_into()?) as usize;
+    let body = take(body_at + 4, body_len)?.to_vec();
+    Ok((destination, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nodes(count: u8) -> Vec<(StaticSecret, NodeKey)> {
+        (1..=count)
+            .map(|i| {
+                let secret = StaticSecret::from([i; 32]);
+                let key = PublicKey::from(&secret).to_bytes();
+                (secret, key)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn each_hop_peels_one_layer_until_the_exit() {
+        for hops in 1..=MAX_HOPS as u8 {
+            let nodes = nodes(hops);
+            let route: Vec<NodeKey> = nodes.iter().map(|(_, key)| *key).collect();
+            let body = b"{\"tx\":{}}".to_vec();
+            let mut packet = build_packet(&route, "http://node:8545/send_raw_tx", &body).unwrap();
+            assert_eq!(packet.to_bytes().len(), PACKET_LEN);
+
+            for (i, (secret, _)) in nodes.iter().enumerate() {
+                let packet_bytes = packet.to_bytes();
+                let processed =
+                    process(secret, &Packet::from_bytes(&packet_bytes).unwrap()).unwrap();
+                match processed.hop {
+                    Hop::Forward {
+                        next,
+                        packet: inner,
+                    } => {
+                        assert_eq!(next, route[i + 1]);
+                        // Nothing carries over unchanged from one hop to the next.
+                        assert_ne!(inner.alpha, packet.alpha);
+                        assert_ne!(inner.payload, packet.payload);
+                        packet = inner;
+                    }
+                    Hop::Exit {
+                        destination,
+                        body: delivered,
+                    } => {
+                        assert_eq!(i, nodes.len() - 1);
+                        assert_eq!(destination, "http://node:8545/send_raw_tx");
+                        assert_eq!(delivered, body);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_and_misrouted_packets_are_rejected() {
+        let nodes = nodes(3);
+        let route: Vec<NodeKey> = nodes.iter().map(|(_, key)| *key).collect();
+        let packet = build_packet(&route, "http://node", b"tx").unwrap();
+
+        let first = process(&nodes[0].0, &packet).unwrap();
+        assert_eq!(
+            process(&nodes[0].0, &packet).unwrap().replay_tag,
+            first.replay_tag
+        );
+        assert!(process(&nodes[1].0, &packet).is_err());
+
+        let mut header = packet.clone();
+        header.beta[7] ^= 1;
+        assert!(process(&nodes[0].0, &header).is_err());
+
+        // A flipped payload bit passes the mixes but not the exit.
+        let mut body = packet.clone();
+        body.payload[100] ^= 1;
+        let mut current = body;
+        for (secret, _) in &nodes[..2] {
+            match process(secret, &current).unwrap().hop {
+                Hop::Forward { packet, .. } => current = packet,
+                Hop::Exit { .. } => panic!("exit before the last hop"),
+            }
+        }
+        assert!(process(&nodes[2].0, &current).is_err());
+
+        assert!(build_packet(&[], "http://node", b"tx").is_err());
+        let too_long = vec![0u8; max_body_len("http://node") + 1];
+        assert!(build_packet(&route, "http://node", &too_long).is_err());
+    }
+}
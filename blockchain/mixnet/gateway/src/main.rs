@@ -1,26 +1,140 @@
-use axum::{routing::post, Json, Router};
-use serde::{Deserialize, Serialize};
-use tracing::info;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct RelayRequest {
-    endpoint: String,
-    payload: String,
+//! A mix node. `POST /relay` takes a Sphinx packet, peels the layer sealed
+//! to this node's key and, after a random delay, forwards the rest to the
+//! next node from the directory or, at the exit, posts the payload to its
+//! destination. Packets seen before are dropped.
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use std::{env, fs};
+
+use anyhow::Context;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use mixnet_client::{process, Directory, Hop, Packet};
+use rand::Rng;
+use serde::Serialize;
+use tracing::{info, warn};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+const DEFAULT_MAX_DELAY_MS: u64 = 500;
+
+#[derive(Clone)]
+struct AppState {
+    secret: Arc<StaticSecret>,
+    directory: Arc<Directory>,
+    /// Replay tags of every packet processed since startup.
+    seen: Arc<Mutex<HashSet<[u8; 32]>>>,
+    http: reqwest::Client,
+    max_delay: Duration,
 }
 
-async fn relay(Json(req): Json<RelayRequest>) -> Json<&'static str> {
-    info!("relaying to {} via mixnet stub", req.endpoint);
-    Json("relayed")
+#[derive(Serialize)]
+struct NodeInfo {
+    public_key: String,
+}
+
+async fn node_info(State(state): State<AppState>) -> Json<NodeInfo> {
+    Json(NodeInfo {
+        public_key: hex::encode(PublicKey::from(state.secret.as_ref()).to_bytes()),
+    })
+}
+
+async fn relay(State(state): State<AppState>, body: Bytes) -> (StatusCode, &'static str) {
+    let processed = match Packet::from_bytes(&body).and_then(|p| process(&state.secret, &p)) {
+        Ok(processed) => processed,
+        Err(err) => {
+            warn!("dropping mixnet packet: {err}");
+            return (StatusCode::BAD_REQUEST, "invalid packet");
+        }
+    };
+    if !state.seen.lock().unwrap().insert(processed.replay_tag) {
+        return (StatusCode::BAD_REQUEST, "replayed packet");
+    }
+    let delay = state.max_delay.mul_f64(rand::thread_rng().gen::<f64>());
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        if let Err(err) = forward(&state, processed.hop).await {
+            warn!("mixnet forward failed: {err}");
+        }
+    });
+    (StatusCode::ACCEPTED, "accepted")
+}
+
+async fn forward(state: &AppState, hop: Hop) -> anyhow::Result<()> {
+    match hop {
+        Hop::Forward { next, packet } => {
+            let node = state
+                .directory
+                .get(&next)
+                .with_context(|| format!("next hop {} not in directory", hex::encode(next)))?;
+            state
+                .http
+                .post(format!("{}/relay", node.url.trim_end_matches('/')))
+                .body(packet.to_bytes())
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        Hop::Exit { destination, body } => {
+            state
+                .http
+                .post(&destination)
+                .header(header::CONTENT_TYPE.as_str(), "application/json")
+                .body(body)
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+    }
+    Ok(())
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().with_env_filter("info").init();
-    let app = Router::new().route("/relay", post(relay));
-    info!("mixnet gateway listening on 0.0.0.0:8050");
-    axum::Server::bind(&"0.0.0.0:8050".parse().unwrap())
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
-}
+    let sk_hex = env::var("MIXNET_SK").context("MIXNET_SK env var (hex x25519 key) required")?;
+    let secret: [u8; 32] = hex::decode(sk_hex.trim_start_matches("0x"))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("MIXNET_SK must be 32 bytes"))?;
+    let directory_path = env::var("MIXNET_DIRECTORY").unwrap_or_else(|_| "mixnet.json".into());
+    let directory = Directory::from_json(
+        &fs::read_to_string(&directory_path)
+            .with_context(|| format!("reading mixnet directory {directory_path}"))?,
+    )?;
+    let max_delay_ms = env::var("MIXNET_MAX_DELAY_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_DELAY_MS);
+
+    let secret = StaticSecret::from(secret);
+    info!(
+        "mix node {} with {} nodes in its directory",
+        hex::encode(PublicKey::from(&secret).to_bytes()),
+        directory.nodes.len()
+    );
+    let state = AppState {
+        secret: Arc::new(secret),
+        directory: Arc::new(directory),
+        seen: Arc::new(Mutex::new(HashSet::new())),
+        http: reqwest::Client::new(),
+        max_delay: Duration::from_millis(max_delay_ms),
+    };
+    let app = Router::new()
+        .route("/relay", post(relay))
+        .route("/info", get(node_info))
+        .route("/health", get(|| async { "ok" }))
+        .with_state(state);
 
+    let addr: SocketAddr = env::var("MIXNET_LISTEN")
+        .unwrap_or_else(|_| "0.0.0.0:8050".into())
+        .parse()?;
+    info!("mixnet gateway listening on {addr}");
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app.into_make_service()).await?;
+    Ok(())
+}
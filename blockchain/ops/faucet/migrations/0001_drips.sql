@@ -0,0 +1,22 @@
+-- Every funding request the faucet answered, throttled and failed ones
+-- included, so users can see why a request was refused.
+
+CREATE TABLE IF NOT EXISTS drips (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- Hex, without 0x.
+    address TEXT NOT NULL,
+    ip TEXT NOT NULL,
+    -- Decimal string; amounts don't fit an INTEGER.
+    amount TEXT NOT NULL,
+    -- sent, throttled or failed.
+    outcome TEXT NOT NULL,
+    -- Hex; only for sent drips.
+    tx_hash TEXT,
+    -- Why the drip was throttled or failed.
+    reason TEXT,
+    retry_after_secs INTEGER,
+    created_at_ms INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_drips_address ON drips (address, id DESC);
+CREATE INDEX IF NOT EXISTS idx_drips_created ON drips (created_at_ms);
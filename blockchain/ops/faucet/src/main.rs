@@ -1,17 +1,51 @@
-use std::{env, net::SocketAddr, sync::Arc};
+mod policy;
+mod store;
 
-use axum::{extract::State, routing::{get, post}, Json, Router};
+use std::{
+    env,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
 use ed25519_dalek::SigningKey;
+use policy::{Cooldowns, DripPolicy};
+use runtime::{Hash, TxPayload};
+use sdk_rust::{tx_hash, KovaClient, Wallet};
 use serde::{Deserialize, Serialize};
-use sdk_rust::build_transfer_signed;
+use store::{now_ms, DripRecord, FaucetStore, Outcome};
+use tokio::sync::{mpsc, oneshot};
 use tracing::{info, warn};
 
+const TRANSFER_GAS: u64 = 21_000;
+/// Requests waiting for the sender task; more are refused.
+const QUEUE_CAPACITY: usize = 256;
+const DEFAULT_HISTORY_LIMIT: i64 = 20;
+const MAX_HISTORY_LIMIT: i64 = 100;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// A transfer for the sender task, which owns the faucet's nonce.
+struct Drip {
+    to: [u8; 32],
+    amount: u128,
+    sent: oneshot::Sender<anyhow::Result<Hash>>,
+}
+
 #[derive(Clone)]
 struct AppState {
-    rpc: String,
-    chain_id: String,
-    default_amount: u128,
-    signing_key: Arc<SigningKey>,
+    policy: Arc<DripPolicy>,
+    cooldowns: Arc<Mutex<Cooldowns>>,
+    drips: mpsc::Sender<Drip>,
+    store: FaucetStore,
+    client: KovaClient,
+    account: [u8; 32],
 }
 
 #[derive(Debug, Deserialize)]
@@ -19,58 +53,204 @@ struct FundRequest {
     address: String,
     #[serde(default)]
     amount: Option<u128>,
-    #[serde(default)]
-    nonce: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
 struct FundResponse {
-    status: u16,
-    message: String,
+    tx_hash: String,
+    amount: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct DripConfig {
+    default_amount: String,
+    max_amount: String,
+    address_cooldown_secs: u64,
+    ip_cooldown_secs: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    account: String,
+    /// Absent when the node can't be reached.
+    balance: Option<String>,
+    /// Default-sized drips the balance still covers.
+    drips_left: Option<String>,
+    queue_depth: usize,
+    queue_capacity: usize,
+    drips_last_day: u64,
+    sent_last_day: String,
+    drip: DripConfig,
 }
 
 fn parse_address(hex_addr: &str) -> anyhow::Result<[u8; 32]> {
-    let mut out = [0u8; 32];
     let cleaned = hex_addr.trim_start_matches("0x");
-    let bytes = hex::decode(cleaned)?;
-    for (i, b) in bytes.iter().take(32).enumerate() {
-        out[i] = *b;
-    }
-    Ok(out)
+    hex::decode(cleaned)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("address must be 32 bytes"))
+}
+
+fn error(status: StatusCode, message: impl ToString) -> Response {
+    (status, message.to_string()).into_response()
 }
 
 async fn fund(
     State(state): State<AppState>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
     Json(req): Json<FundRequest>,
-) -> Result<Json<FundResponse>, (axum::http::StatusCode, String)> {
-    let amount = req.amount.unwrap_or(state.default_amount);
-    let nonce = req.nonce.unwrap_or(0);
-    let addr = parse_address(&req.address)
-        .map_err(|e| (axum::http::StatusCode::BAD_REQUEST, e.to_string()))?;
-
-    let tx = build_transfer_signed(&state.chain_id, addr, amount, &state.signing_key, nonce)
-        .map_err(|e| (axum::http::StatusCode::BAD_REQUEST, e.to_string()))?;
-
-    let client = reqwest::Client::new();
-    let url = format!("{}/send_raw_tx", state.rpc.trim_end_matches('/'));
-    let res = client
-        .post(&url)
-        .json(&serde_json::json!({ "tx": tx }))
-        .send()
-        .await
-        .map_err(|e| (axum::http::StatusCode::BAD_GATEWAY, e.to_string()))?;
-
-    let status = res.status();
-    let body = res.text().await.unwrap_or_default();
-    if !status.is_success() {
-        warn!("faucet send_raw_tx failed: {} {}", status, body);
-        return Err((axum::http::StatusCode::BAD_GATEWAY, body));
+) -> Response {
+    let to = match parse_address(&req.address) {
+        Ok(to) => to,
+        Err(err) => return error(StatusCode::BAD_REQUEST, err),
+    };
+    let amount = match state.policy.amount(req.amount) {
+        Ok(amount) => amount,
+        Err(err) => return error(StatusCode::BAD_REQUEST, err),
+    };
+    let ip = client.ip();
+    let reserved = state
+        .cooldowns
+        .lock()
+        .unwrap()
+        .reserve(&state.policy, to, ip, Instant::now());
+    if let Err(throttled) = reserved {
+        let secs = throttled.retry_after.as_secs().max(1);
+        let mut record = DripRecord::new(&to, amount, Outcome::Throttled);
+        record.reason = Some(throttled.reason.to_string());
+        record.retry_after_secs = Some(secs);
+        log_drip(&state, &record, &ip.to_string()).await;
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, secs.to_string())],
+            format!("{}; retry in {secs}s", throttled.reason),
+        )
+            .into_response();
+    }
+
+    let result = drip(&state, to, amount).await;
+    match result {
+        Ok(hash) => {
+            let mut record = DripRecord::new(&to, amount, Outcome::Sent);
+            record.tx_hash = Some(hex::encode(hash));
+            log_drip(&state, &record, &ip.to_string()).await;
+            Json(FundResponse {
+                tx_hash: hex::encode(hash),
+                amount: amount.to_string(),
+            })
+            .into_response()
+        }
+        Err((status, message)) => {
+            state.cooldowns.lock().unwrap().release(&to, &ip);
+            let mut record = DripRecord::new(&to, amount, Outcome::Failed);
+            record.reason = Some(message.clone());
+            log_drip(&state, &record, &ip.to_string()).await;
+            error(status, message)
+        }
     }
+}
+
+/// A drip that can't be logged is still answered.
+async fn log_drip(state: &AppState, record: &DripRecord, ip: &str) {
+    if let Err(err) = state.store.record(record, ip).await {
+        warn!("failed to record drip to {}: {err}", record.address);
+    }
+}
 
-    Ok(Json(FundResponse {
-        status: status.as_u16(),
-        message: if body.is_empty() { "sent".into() } else { body },
-    }))
+async fn status(State(state): State<AppState>) -> Response {
+    let balance = match state.client.get_balance(&state.account).await {
+        Ok(balance) => Some(balance),
+        Err(err) => {
+            warn!("faucet balance lookup failed: {err}");
+            None
+        }
+    };
+    let (drips_last_day, sent_last_day) = match state.store.sent_since(now_ms() - DAY_MS).await {
+        Ok(sent) => sent,
+        Err(err) => return error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    };
+    let policy = &state.policy;
+    let queue_capacity = state.drips.max_capacity();
+    Json(StatusResponse {
+        account: hex::encode(state.account),
+        balance: balance.map(|b| b.to_string()),
+        drips_left: balance.map(|b| (b / policy.default_amount).to_string()),
+        queue_depth: queue_capacity - state.drips.capacity(),
+        queue_capacity,
+        drips_last_day,
+        sent_last_day: sent_last_day.to_string(),
+        drip: DripConfig {
+            default_amount: policy.default_amount.to_string(),
+            max_amount: policy.max_amount.to_string(),
+            address_cooldown_secs: policy.address_cooldown.as_secs(),
+            ip_cooldown_secs: policy.ip_cooldown.as_secs(),
+        },
+    })
+    .into_response()
+}
+
+async fn history(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Query(q): Query<HistoryQuery>,
+) -> Response {
+    let address = match parse_address(&address) {
+        Ok(address) => address,
+        Err(err) => return error(StatusCode::BAD_REQUEST, err),
+    };
+    let limit = q.limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+    if !(1..=MAX_HISTORY_LIMIT).contains(&limit) {
+        return error(
+            StatusCode::BAD_REQUEST,
+            format!("limit must be between 1 and {MAX_HISTORY_LIMIT}"),
+        );
+    }
+    match state.store.history(&address, limit).await {
+        Ok(records) => Json(records).into_response(),
+        Err(err) => error(StatusCode::INTERNAL_SERVER_ERROR, err),
+    }
+}
+
+async fn drip(state: &AppState, to: [u8; 32], amount: u128) -> Result<Hash, (StatusCode, String)> {
+    let (sent, receipt) = oneshot::channel();
+    state
+        .drips
+        .try_send(Drip { to, amount, sent })
+        .map_err(|_| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "faucet is busy".to_string(),
+            )
+        })?;
+    match receipt.await {
+        Ok(Ok(hash)) => Ok(hash),
+        Ok(Err(err)) => Err((StatusCode::BAD_GATEWAY, err.to_string())),
+        Err(_) => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "faucet sender stopped".to_string(),
+        )),
+    }
+}
+
+/// Sends drips one at a time so each gets the next nonce. The wallet fetches
+/// the nonce from the node once and drops it when the node refuses a tx, so
+/// a rejected or externally spent nonce is refetched on the next drip.
+async fn run_sender(wallet: Wallet<SigningKey>, mut drips: mpsc::Receiver<Drip>) {
+    while let Some(drip) = drips.recv().await {
+        let payload = TxPayload::Transfer {
+            to: drip.to,
+            amount: drip.amount,
+        };
+        let result = wallet.send(payload, TRANSFER_GAS).await;
+        if let Err(err) = &result {
+            warn!("faucet transfer to {} failed: {err}", hex::encode(drip.to));
+        }
+        let _ = drip.sent.send(result.map(|tx| tx_hash(&tx)));
+    }
 }
 
 #[tokio::main]
@@ -78,10 +258,7 @@ async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
     let rpc = env::var("RPC_URL").unwrap_or_else(|_| "http://validator1:8545".into());
     let chain_id = env::var("CHAIN_ID").unwrap_or_else(|_| "kova-devnet".into());
-    let default_amount = env::var("FAUCET_AMOUNT")
-        .ok()
-        .and_then(|v| v.parse::<u128>().ok())
-        .unwrap_or(100_000);
+    let policy = DripPolicy::from_env();
 
     let sk_hex = env::var("FAUCET_SK")
         .map_err(|_| anyhow::anyhow!("FAUCET_SK env var (hex ed25519 key) required"))?;
@@ -93,24 +270,46 @@ async fn main() -> anyhow::Result<()> {
             .map_err(|_| anyhow::anyhow!("FAUCET_SK must be 32 bytes"))?,
     );
 
+    let db_url = env::var("FAUCET_DB").unwrap_or_else(|_| "sqlite://faucet.db".into());
+    let store = FaucetStore::open(&db_url).await?;
+
+    let client = KovaClient::new(rpc);
+    let wallet = Wallet::new(client.clone(), chain_id, signing_key);
+    let account = wallet.address();
+    info!(
+        "faucet account {} drips {} (max {})",
+        hex::encode(wallet.address()),
+        policy.default_amount,
+        policy.max_amount
+    );
+    let (drips, queue) = mpsc::channel(QUEUE_CAPACITY);
+    tokio::spawn(run_sender(wallet, queue));
+
     let state = AppState {
-        rpc,
-        chain_id,
-        default_amount,
-        signing_key: Arc::new(signing_key),
+        policy: Arc::new(policy),
+        cooldowns: Arc::new(Mutex::new(Cooldowns::default())),
+        drips,
+        store,
+        client,
+        account,
     };
 
     let app = Router::new()
         .route("/healthz", get(|| async { "ok" }))
         .route("/fund", post(fund))
+        .route("/status", get(status))
+        .route("/history/:address", get(history))
         .with_state(state);
 
     let addr: SocketAddr = env::var("FAUCET_LISTEN")
         .unwrap_or_else(|_| "0.0.0.0:8080".into())
         .parse()?;
     info!("starting faucet on {}", addr);
-    axum::Server::bind(&addr)
-        .serve(app.into_make_service())
-        .await?;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
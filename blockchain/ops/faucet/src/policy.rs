@@ -0,0 +1,182 @@
+//! Who may be funded, how much and how often. Every address and every client
+//! IP has to wait out a cooldown between drips.
+
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+const DEFAULT_AMOUNT: u128 = 100_000;
+const DEFAULT_ADDRESS_COOLDOWN: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_IP_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+/// Past this many tracked clients, expired entries are dropped.
+const MAX_TRACKED: usize = 100_000;
+
+#[derive(Debug, Clone)]
+pub struct DripPolicy {
+    /// Sent when a request names no amount.
+    pub default_amount: u128,
+    /// Largest amount a request may ask for.
+    pub max_amount: u128,
+    pub address_cooldown: Duration,
+    pub ip_cooldown: Duration,
+}
+
+impl Default for DripPolicy {
+    fn default() -> Self {
+        Self {
+            default_amount: DEFAULT_AMOUNT,
+            max_amount: DEFAULT_AMOUNT,
+            address_cooldown: DEFAULT_ADDRESS_COOLDOWN,
+            ip_cooldown: DEFAULT_IP_COOLDOWN,
+        }
+    }
+}
+
+impl DripPolicy {
+    /// Reads `FAUCET_AMOUNT`, `FAUCET_MAX_AMOUNT` (defaults to the drip
+    /// amount), `FAUCET_ADDRESS_COOLDOWN_SECS` and `FAUCET_IP_COOLDOWN_SECS`.
+    /// Unset or invalid values keep the defaults.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let amount = |key: &str| {
+            env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<u128>().ok())
+                .filter(|v| *v > 0)
+        };
+        let secs = |key: &str| {
+            env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_secs)
+        };
+        let default_amount = amount("FAUCET_AMOUNT").unwrap_or(defaults.default_amount);
+        Self {
+            default_amount,
+            max_amount: amount("FAUCET_MAX_AMOUNT")
+                .unwrap_or(default_amount)
+                .max(default_amount),
+            address_cooldown: secs("FAUCET_ADDRESS_COOLDOWN_SECS")
+                .unwrap_or(defaults.address_cooldown),
+            ip_cooldown: secs("FAUCET_IP_COOLDOWN_SECS").unwrap_or(defaults.ip_cooldown),
+        }
+    }
+
+    pub fn amount(&self, requested: Option<u128>) -> anyhow::Result<u128> {
+        let amount = requested.unwrap_or(self.default_amount);
+        if amount == 0 || amount > self.max_amount {
+            anyhow::bail!("amount must be between 1 and {}", self.max_amount);
+        }
+        Ok(amount)
+    }
+}
+
+/// Why a request was refused and when it may be retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttled {
+    pub reason: &'static str,
+    pub retry_after: Duration,
+}
+
+/// Last drip per address and per IP.
+#[derive(Default)]
+pub struct Cooldowns {
+    addresses: HashMap<[u8; 32], Instant>,
+    ips: HashMap<IpAddr, Instant>,
+}
+
+impl Cooldowns {
+    /// Records a drip to `address` for `ip` at `now`, unless either is still
+    /// cooling down. Call `release` if the drip then fails.
+    pub fn reserve(
+        &mut self,
+        policy: &DripPolicy,
+        address: [u8; 32],
+        ip: IpAddr,
+        now: Instant,
+    ) -> Result<(), Throttled> {
+        if self.addresses.len() + self.ips.len() >= MAX_TRACKED {
+            self.addresses
+                .retain(|_, at| now.saturating_duration_since(*at) < policy.address_cooldown);
+            self.ips
+                .retain(|_, at| now.saturating_duration_since(*at) < policy.ip_cooldown);
+        }
+        let remaining = |last: Option<&Instant>, cooldown: Duration| {
+            last.map(|at| cooldown.saturating_sub(now.saturating_duration_since(*at)))
+                .filter(|wait| !wait.is_zero())
+        };
+        if let Some(retry_after) = remaining(self.addresses.get(&address), policy.address_cooldown)
+        {
+            return Err(Throttled {
+                reason: "address was funded recently",
+                retry_after,
+            });
+        }
+        if let Some(retry_after) = remaining(self.ips.get(&ip), policy.ip_cooldown) {
+            return Err(Throttled {
+                reason: "too many requests from this IP",
+                retry_after,
+            });
+        }
+        self.addresses.insert(address, now);
+        self.ips.insert(ip, now);
+        Ok(())
+    }
+
+    /// Undoes a reservation whose drip was never sent.
+    pub fn release(&mut self, address: &[u8; 32], ip: &IpAddr) {
+        self.addresses.remove(address);
+        self.ips.remove(ip);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses_and_ips_cool_down_separately() {
+        let policy = DripPolicy {
+            address_cooldown: Duration::from_secs(100),
+            ip_cooldown: Duration::from_secs(10),
+            ..DripPolicy::default()
+        };
+        let mut cooldowns = Cooldowns::default();
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let other_ip: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(cooldowns.reserve(&policy, [1; 32], ip, start).is_ok());
+        let throttled = cooldowns
+            .reserve(&policy, [1; 32], other_ip, start + Duration::from_secs(40))
+            .unwrap_err();
+        assert_eq!(throttled.retry_after, Duration::from_secs(60));
+        let throttled = cooldowns
+            .reserve(&policy, [2; 32], ip, start + Duration::from_secs(4))
+            .unwrap_err();
+        assert_eq!(throttled.retry_after, Duration::from_secs(6));
+        assert!(cooldowns
+            .reserve(&policy, [2; 32], ip, start + Duration::from_secs(10))
+            .is_ok());
+
+        // A failed drip doesn't count against either.
+        cooldowns.release(&[2; 32], &ip);
+        assert!(cooldowns
+            .reserve(&policy, [2; 32], ip, start + Duration::from_secs(11))
+            .is_ok());
+    }
+
+    #[test]
+    fn amounts_are_capped() {
+        let policy = DripPolicy {
+            default_amount: 10,
+            max_amount: 50,
+            ..DripPolicy::default()
+        };
+        assert_eq!(policy.amount(None).unwrap(), 10);
+        assert_eq!(policy.amount(Some(50)).unwrap(), 50);
+        assert!(policy.amount(Some(51)).is_err());
+        assert!(policy.amount(Some(0)).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Sqlite log of the faucet's drips, behind `/history/:address` and the
+//! depletion figures in `/status`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Sent,
+    Throttled,
+    Failed,
+}
+
+impl Outcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Outcome::Sent => "sent",
+            Outcome::Throttled => "throttled",
+            Outcome::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "sent" => Ok(Outcome::Sent),
+            "throttled" => Ok(Outcome::Throttled),
+            "failed" => Ok(Outcome::Failed),
+            other => anyhow::bail!("unknown drip outcome {other}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DripRecord {
+    pub address: String,
+    /// Decimal string.
+    pub amount: String,
+    pub outcome: Outcome,
+    pub tx_hash: Option<String>,
+    pub reason: Option<String>,
+    pub retry_after_secs: Option<u64>,
+    pub created_at_ms: i64,
+}
+
+impl DripRecord {
+    pub fn new(address: &[u8; 32], amount: u128, outcome: Outcome) -> Self {
+        Self {
+            address: hex::encode(address),
+            amount: amount.to_string(),
+            outcome,
+            tx_hash: None,
+            reason: None,
+            retry_after_secs: None,
+            created_at_ms: now_ms(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FaucetStore {
+    pool: SqlitePool,
+}
+
+impl FaucetStore {
+    /// Opens the database at `url` (e.g. `sqlite://faucet.db`), creating it
+    /// if needed, and applies the migrations.
+    pub async fn open(url: &str) -> anyhow::Result<Self> {
+        let options = url.parse::<SqliteConnectOptions>()?.create_if_missing(true);
+        // Sqlite takes one writer at a time anyway.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await?;
+        sqlx::migrate!().run(&pool).await?;
+        Ok(Self { pool })
+    }
+
+    /// `ip` is kept for operators; history doesn't return it.
+    pub async fn record(&self, record: &DripRecord, ip: &str) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO drips (
+                address, ip, amount, outcome, tx_hash, reason, retry_after_secs, created_at_ms
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&record.address)
+        .bind(ip)
+        .bind(&record.amount)
+        .bind(record.outcome.as_str())
+        .bind(&record.tx_hash)
+        .bind(&record.reason)
+        .bind(record.retry_after_secs.map(i64::try_from).transpose()?)
+        .bind(record.created_at_ms)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Up to `limit` requests for `address`, newest first.
+    pub async fn history(&self, address: &[u8; 32], limit: i64) -> anyhow::Result<Vec<DripRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT address, amount, outcome, tx_hash, reason, retry_after_secs, created_at_ms
+            FROM drips
+            WHERE address = ?
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(hex::encode(address))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                let retry_after: Option<i64> = row.try_get("retry_after_secs")?;
+                Ok(DripRecord {
+                    address: row.try_get("address")?,
+                    amount: row.try_get("amount")?,
+                    outcome: Outcome::parse(row.try_get("outcome")?)?,
+                    tx_hash: row.try_get("tx_hash")?,
+                    reason: row.try_get("reason")?,
+                    retry_after_secs: retry_after.map(u64::try_from).transpose()?,
+                    created_at_ms: row.try_get("created_at_ms")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Drips sent since `since_ms` and their total amount.
+    pub async fn sent_since(&self, since_ms: i64) -> anyhow::Result<(u64, u128)> {
+        let amounts: Vec<String> = sqlx::query_scalar(
+            "SELECT amount FROM drips WHERE outcome = 'sent' AND created_at_ms >= ?",
+        )
+        .bind(since_ms)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut total = 0u128;
+        for amount in &amounts {
+            total = total.saturating_add(amount.parse()?);
+        }
+        Ok((amounts.len() as u64, total))
+    }
+}
+
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn records_history_and_sent_totals() {
+        let store = FaucetStore::open("sqlite::memory:").await.unwrap();
+        let mut sent = DripRecord::new(&[1; 32], 500, Outcome::Sent);
+        sent.tx_hash = Some("ab".repeat(32));
+        store.record(&sent, "10.0.0.1").await.unwrap();
+        let mut throttled = DripRecord::new(&[1; 32], 500, Outcome::Throttled);
+        throttled.reason = Some("address was funded recently".into());
+        throttled.retry_after_secs = Some(60);
+        store.record(&throttled, "10.0.0.1").await.unwrap();
+        let other = DripRecord::new(&[2; 32], 700, Outcome::Sent);
+        store.record(&other, "10.0.0.2").await.unwrap();
+
+        let history = store.history(&[1; 32], 10).await.unwrap();
+        assert_eq!(history, vec![throttled, sent.clone()]);
+        assert_eq!(store.history(&[1; 32], 1).await.unwrap().len(), 1);
+        assert_eq!(store.sent_since(0).await.unwrap(), (2, 1_200));
+        assert_eq!(store.sent_since(now_ms() + 1_000).await.unwrap(), (0, 0));
+    }
+}
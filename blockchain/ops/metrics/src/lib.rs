@@ -0,0 +1,153 @@
+//! Prometheus metrics shared by the node, the sequencer API and the indexer.
+//! Every service registers the same families under its own constant labels,
+//! updates the ones it knows about and serves them at `/metrics`.
+
+use axum::{http::header, routing::get, Router};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::collections::HashMap;
+
+const NAMESPACE: &str = "kova";
+const PROOF_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0];
+
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    pub block_height: IntGauge,
+    pub mempool_depth: IntGauge,
+    pub consensus_view: IntGauge,
+    pub da_sampling_failures: IntCounter,
+    /// Times the canonical chain switched to another branch.
+    pub reorgs: IntCounter,
+    pub zk_proof_seconds: Histogram,
+    /// Block proof jobs waiting for a worker.
+    pub zk_proof_queue_depth: IntGauge,
+    /// Milliseconds between a block's timestamp and its ingestion.
+    pub ingestion_lag_ms: IntGauge,
+    /// Compressed batch bytes the sequencer posted to DA.
+    pub da_batch_bytes: IntCounter,
+}
+
+impl Metrics {
+    /// `labels` are attached to every series, e.g. `[("service", "node")]`.
+    pub fn new(labels: &[(&str, &str)]) -> Self {
+        let labels: HashMap<String, String> = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let registry = Registry::new_custom(Some(NAMESPACE.into()), Some(labels))
+            .expect("valid metric labels");
+        let metrics = Self {
+            block_height: IntGauge::with_opts(Opts::new(
+                "block_height",
+                "Height of the latest applied or ingested block",
+            ))
+            .unwrap(),
+            mempool_depth: IntGauge::with_opts(Opts::new(
+                "mempool_depth",
+                "Transactions waiting for inclusion",
+            ))
+            .unwrap(),
+            consensus_view: IntGauge::with_opts(Opts::new(
+                "consensus_view",
+                "Current consensus view",
+            ))
+            .unwrap(),
+            da_sampling_failures: IntCounter::with_opts(Opts::new(
+                "da_sampling_failures_total",
+                "Blocks rejected because DA sampling failed",
+            ))
+            .unwrap(),
+            reorgs: IntCounter::with_opts(Opts::new(
+                "reorgs_total",
+                "Canonical chain switches to another branch",
+            ))
+            .unwrap(),
+            zk_proof_seconds: Histogram::with_opts(
+                HistogramOpts::new("zk_proof_seconds", "Time spent generating zk proofs")
+                    .buckets(PROOF_BUCKETS.to_vec()),
+            )
+            .unwrap(),
+            zk_proof_queue_depth: IntGauge::with_opts(Opts::new(
+                "zk_proof_queue_depth",
+                "Block proof jobs waiting for a worker",
+            ))
+            .unwrap(),
+            ingestion_lag_ms: IntGauge::with_opts(Opts::new(
+                "ingestion_lag_ms",
+                "Delay between block production and indexer ingestion",
+            ))
+            .unwrap(),
+            da_batch_bytes: IntCounter::with_opts(Opts::new(
+                "da_batch_bytes_total",
+                "Compressed batch bytes posted to DA",
+            ))
+            .unwrap(),
+            registry,
+        };
+        let collectors: [Box<dyn prometheus::core::Collector>; 9] = [
+            Box::new(metrics.block_height.clone()),
+            Box::new(metrics.mempool_depth.clone()),
+            Box::new(metrics.consensus_view.clone()),
+            Box::new(metrics.da_sampling_failures.clone()),
+            Box::new(metrics.reorgs.clone()),
+            Box::new(metrics.zk_proof_seconds.clone()),
+            Box::new(metrics.zk_proof_queue_depth.clone()),
+            Box::new(metrics.ingestion_lag_ms.clone()),
+            Box::new(metrics.da_batch_bytes.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+        metrics
+    }
+
+    /// Text exposition format.
+    pub fn encode(&self) -> String {
+        let mut buf = Vec::new();
+        // Encoding into a Vec only fails on malformed families, which the
+        // typed constructors above rule out.
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buf);
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+/// `GET /metrics`, ready to be merged into a service router.
+pub fn router(metrics: Metrics) -> Router {
+    Router::new().route(
+        "/metrics",
+        get(move || {
+            let metrics = metrics.clone();
+            async move {
+                (
+                    [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+                    metrics.encode(),
+                )
+            }
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_families_with_service_labels() {
+        let metrics = Metrics::new(&[("service", "node"), ("chain", "kova-devnet")]);
+        metrics.block_height.set(42);
+        metrics.da_sampling_failures.inc();
+        metrics.zk_proof_seconds.observe(1.5);
+
+        let text = metrics.encode();
+        assert!(text.contains(r#"kova_block_height{chain="kova-devnet",service="node"} 42"#));
+        assert!(text
+            .contains(r#"kova_da_sampling_failures_total{chain="kova-devnet",service="node"} 1"#));
+        assert!(text.contains("kova_zk_proof_seconds_count"));
+        assert!(text.contains("# TYPE kova_mempool_depth gauge"));
+    }
+}
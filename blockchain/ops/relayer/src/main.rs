@@ -0,0 +1,267 @@
+//! Relays packets between a Kova node and an external EVM chain running the
+//! Kova packet module. Inbound, it keeps the chain's light client on Kova
+//! up to date and delivers the chain's packets with their proofs. Outbound,
+//! it carries the messages Kova domains address to the client, acks among
+//! them, to the external chain once their domain root is committed.
+//!
+//! The external chain is reached over JSON-RPC:
+//! - `kova_latestHeader` returns `{header, signatures, next_validators}`,
+//!   the latest header with its validators' signatures;
+//! - `kova_packets [from_height]` returns `[{height, packet, proof}]`, the
+//!   packets committed at or after `from_height`, in order;
+//! - `kova_recvMessage [{message, proof, root}]` takes a Kova message with
+//!   its proof against the sending domain's committed root.
+
+use std::{env, time::Duration};
+
+use anyhow::Context;
+use ed25519_dalek::SigningKey;
+use runtime::{
+    client_domain_id, CrossDomainMessage, CrossDomainPacket, DomainProof, Hash, HeaderSignature,
+    LightClientHeader, Tx, ValidatorSet,
+};
+use sdk_rust::{build_light_client_packet_signed, build_light_client_update_signed, KovaClient};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+const INCLUSION_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+struct SignedHeader {
+    header: LightClientHeader,
+    signatures: Vec<HeaderSignature>,
+    next_validators: Option<ValidatorSet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PacketItem {
+    height: u64,
+    packet: CrossDomainPacket,
+    proof: DomainProof,
+}
+
+#[derive(Debug, Deserialize)]
+struct OutboxPage {
+    root: Hash,
+    root_committed: bool,
+    items: Vec<OutboxItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OutboxItem {
+    index: usize,
+    message: CrossDomainMessage,
+    proof: Option<DomainProof>,
+}
+
+struct Relayer {
+    kova: KovaClient,
+    kova_rpc: String,
+    chain_id: String,
+    external_rpc: String,
+    client_id: String,
+    signing_key: SigningKey,
+    http: reqwest::Client,
+    /// Domains whose outboxes are watched for messages to the client.
+    domains: Vec<Uuid>,
+    client_height: u64,
+    /// External height from which packets are still to be delivered.
+    packet_height: u64,
+    /// Next outbox index to look at, per watched domain.
+    outbox_cursors: Vec<usize>,
+}
+
+impl Relayer {
+    async fn external<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<T> {
+        let body = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let res: serde_json::Value = self
+            .http
+            .post(&self.external_rpc)
+            .json(&body)
+            .send()
+            .await?
+            .json()
+            .await?;
+        if let Some(err) = res.get("error") {
+            anyhow::bail!("{method} failed: {err}");
+        }
+        serde_json::from_value(res.get("result").cloned().unwrap_or_default())
+            .with_context(|| format!("decoding {method} result"))
+    }
+
+    /// Submits `build(nonce)` and waits for it; the error is the failure
+    /// the receipt records.
+    async fn submit(&self, build: impl FnOnce(u64) -> anyhow::Result<Tx>) -> anyhow::Result<()> {
+        let address = runtime::address_from_pubkey(&self.signing_key.verifying_key().to_bytes());
+        let tx = build(self.kova.get_nonce(&address).await?)?;
+        let hash = self.kova.send_tx(&tx).await?;
+        let receipt = self.kova.wait_for_inclusion(&hash, INCLUSION_TIMEOUT).await?;
+        if !receipt.success {
+            let err = receipt.outcome.error.unwrap_or_else(|| "tx failed".into());
+            anyhow::bail!(err);
+        }
+        Ok(())
+    }
+
+    async fn update_client(&mut self) -> anyhow::Result<()> {
+        let signed: SignedHeader = self.external("kova_latestHeader", json!([])).await?;
+        let height = signed.header.height;
+        if height <= self.client_height {
+            return Ok(());
+        }
+        let result = self
+            .submit(|nonce| {
+                build_light_client_update_signed(
+                    &self.chain_id,
+                    self.client_id.clone(),
+                    signed.header,
+                    signed.signatures,
+                    signed.next_validators,
+                    &self.signing_key,
+                    nonce,
+                )
+            })
+            .await;
+        match result {
+            Ok(()) => info!(height, "updated light client"),
+            Err(err) if err.to_string().contains("not newer") => {}
+            Err(err) => return Err(err),
+        }
+        self.client_height = height;
+        Ok(())
+    }
+
+    async fn deliver_packets(&mut self) -> anyhow::Result<()> {
+        let items: Vec<PacketItem> = self
+            .external("kova_packets", json!([self.packet_height]))
+            .await?;
+        let mut delivered = None;
+        for item in items {
+            // Headers are final, so once the client has one every packet
+            // committed up to it can be delivered.
+            if item.height > self.client_height {
+                break;
+            }
+            let sequence = item.packet.sequence;
+            let result = self
+                .submit(|nonce| {
+                    build_light_client_packet_signed(
+                        &self.chain_id,
+                        self.client_id.clone(),
+                        item.height,
+                        item.packet,
+                        item.proof,
+                        &self.signing_key,
+                        nonce,
+                    )
+                })
+                .await;
+            match result {
+                Ok(()) => info!(sequence, "delivered packet"),
+                Err(err) if err.to_string().contains("already relayed") => {}
+                // Later packets would be out of order; retry from this
+                // height next round.
+                Err(err) => return Err(err),
+            }
+            self.packet_height = item.height;
+            delivered = Some(item.height);
+        }
+        if let Some(height) = delivered {
+            self.packet_height = height + 1;
+        }
+        Ok(())
+    }
+
+    async fn forward_messages(&mut self) -> anyhow::Result<()> {
+        let client_domain = client_domain_id(&self.client_id);
+        for (i, domain) in self.domains.clone().into_iter().enumerate() {
+            let url = format!(
+                "{}/domain/{domain}/outbox?from={}&proof=true",
+                self.kova_rpc, self.outbox_cursors[i]
+            );
+            let page: OutboxPage = self.http.get(&url).send().await?.json().await?;
+            if !page.root_committed {
+                continue;
+            }
+            for item in page.items {
+                if item.message.to == client_domain {
+                    let params = json!([{
+                        "message": item.message,
+                        "proof": item.proof,
+                        "root": page.root,
+                    }]);
+                    self.external::<serde_json::Value>("kova_recvMessage", params)
+                        .await?;
+                    info!(%domain, nonce = item.message.nonce, "forwarded message");
+                }
+                self.outbox_cursors[i] = item.index + 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn env_required(name: &str) -> anyhow::Result<String> {
+    env::var(name).map_err(|_| anyhow::anyhow!("{name} env var required"))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+    let kova_rpc = env::var("KOVA_RPC").unwrap_or_else(|_| "http://localhost:7000".into());
+    let chain_id = env::var("CHAIN_ID").unwrap_or_else(|_| "kova-devnet".into());
+    let external_rpc = env_required("EXTERNAL_RPC")?;
+    let client_id = env_required("CLIENT_ID")?;
+    let domains = env::var("RELAY_DOMAINS")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|id| !id.trim().is_empty())
+        .map(|id| Uuid::parse_str(id.trim()).context("invalid domain id in RELAY_DOMAINS"))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let poll = env::var("RELAY_POLL_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2_000);
+
+    let sk_hex = env_required("RELAYER_SK")?;
+    let sk_bytes = hex::decode(sk_hex.trim_start_matches("0x"))?;
+    let signing_key = SigningKey::from_bytes(
+        sk_bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("RELAYER_SK must be 32 bytes"))?,
+    );
+
+    let mut relayer = Relayer {
+        kova: KovaClient::new(kova_rpc.clone()),
+        kova_rpc: kova_rpc.trim_end_matches('/').to_string(),
+        chain_id,
+        external_rpc,
+        client_id,
+        signing_key,
+        http: reqwest::Client::new(),
+        outbox_cursors: vec![0; domains.len()],
+        domains,
+        client_height: 0,
+        packet_height: 0,
+    };
+    info!(client = %relayer.client_id, "starting relayer");
+    loop {
+        if let Err(err) = relayer.update_client().await {
+            warn!("light client update failed: {err:#}");
+        }
+        if let Err(err) = relayer.deliver_packets().await {
+            warn!("packet delivery failed: {err:#}");
+        }
+        if let Err(err) = relayer.forward_messages().await {
+            warn!("message forwarding failed: {err:#}");
+        }
+        tokio::time::sleep(Duration::from_millis(poll)).await;
+    }
+}
@@ -1,12 +1,13 @@
 use async_trait::async_trait;
-use blake3;
+use runtime::bls::{bls_aggregate, bls_verify, bls_verify_aggregate, BlsSecretKey};
 use runtime::{
-    address_from_pubkey, hash_block, sign_bytes, verify_signature_bytes, Block, BlockHeader, Hash,
-    Tx,
+    address_from_pubkey, hash_block, legacy_signatures_accepted, sign_bytes, sign_in_domain,
+    verify_in_domain, verify_signature_bytes, vote_messages, vote_signing_bytes, Address, Block,
+    BlockHeader, DoubleSignEvidence, Hash, LivenessReport, SigningDomain, Tx,
 };
 use serde::{Deserialize, Serialize};
 use state::Validator;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 use tokio::time::{self, Duration};
 use uuid::Uuid;
@@ -16,6 +17,20 @@ pub struct SignedProposal {
     pub block: Block,
     pub public_key: Vec<u8>,
     pub signature: Vec<u8>,
+    /// Highest QC known to the proposer; justifies extending its block.
+    #[serde(default)]
+    pub justify: Option<QuorumCertificate>,
+    /// Leader-schedule reference checked at gossip validation time.
+    #[serde(default)]
+    pub leader_proof: Option<LeaderProof>,
+}
+
+/// Claim that the proposer leads `view` under the stake-weighted schedule of
+/// the validator set hashing to `validator_set_hash`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LeaderProof {
+    pub view: u64,
+    pub validator_set_hash: Hash,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +39,8 @@ pub struct SignedVote {
     pub view: u64,
     pub voter: Validator,
     pub signature: Vec<u8>,
+    /// BLS signature over the same message, aggregated into the QC.
+    pub bls_signature: Vec<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +48,9 @@ pub struct SlashEvidence {
     pub validator_id: Uuid,
     pub reason: String,
     pub height: u64,
+    /// Signed conflicting messages, submitted to the runtime for slashing.
+    #[serde(default)]
+    pub proof: Option<DoubleSignEvidence>,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -39,22 +59,30 @@ pub struct ConsensusMetrics {
     pub locked_qc: bool,
     pub pending_qc: bool,
     pub commit_queue_depth: usize,
+    pub committed_height: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuorumCertificate {
     pub block_id: Hash,
     pub view: u64,
-    pub signatures: Vec<Vec<u8>>,
+    /// Aggregated BLS signature of `voters` over `(block_id, view)`.
+    pub aggregate_signature: Vec<u8>,
     pub voters: Vec<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsensusState {
     pub view: u64,
+    /// Height of the last committed block.
     pub height: u64,
+    /// QC on the second block of the highest two-chain; proposals must
+    /// extend it unless they carry a newer QC.
     pub locked_qc: Option<QuorumCertificate>,
+    /// Highest QC seen (the prepare QC in chained HotStuff).
     pub pending_qc: Option<QuorumCertificate>,
+    pub last_voted_view: Option<u64>,
+    pub last_committed: Option<Hash>,
 }
 
 #[async_trait]
@@ -69,6 +97,12 @@ pub trait ConsensusEngine: Send + Sync {
     fn pop_commit(&self) -> Option<Hash>;
     fn leader_for_view(&self, view: u64) -> Option<Validator>;
     fn current_view(&self) -> u64;
+    fn high_qc(&self) -> Option<QuorumCertificate>;
+    /// Drains evidence recorded since the last call.
+    fn take_evidence(&self) -> Vec<SlashEvidence>;
+    /// Voters of the high QC plus the leaders of views that timed out since
+    /// the last call, for the next proposal. `None` before the first QC.
+    fn liveness_report(&self) -> Option<LivenessReport>;
 }
 
 pub fn build_block(header: BlockHeader, txs: Vec<Tx>, da_blobs: Vec<String>) -> Block {
@@ -87,15 +121,32 @@ pub struct HotStuffEngine {
 
 #[derive(Debug)]
 struct HotStuffInner {
+    /// Signing domain for proposals, votes and QCs.
+    chain_id: String,
+    /// Protocol version of the chain's last applied block, which decides
+    /// whether untagged signatures still verify.
+    protocol_version: u32,
     state: ConsensusState,
     pending_blocks: HashMap<Hash, Block>,
     block_tree: HashMap<Hash, Block>,
+    qcs: HashMap<Hash, QuorumCertificate>,
+    committed: HashSet<Hash>,
     votes: HashMap<(Hash, u64), VoteTally>,
     validators: Vec<Validator>,
     commit_queue: VecDeque<Hash>,
     total_stake: u128,
+    seen_proposals: HashMap<(Address, u64), SignedProposal>,
+    seen_votes: HashMap<(Uuid, u64), SignedVote>,
+    evidence: Vec<SlashEvidence>,
+    /// `(view, leader)` for views that timed out without a proposal, not yet
+    /// reported.
+    missed_proposals: Vec<(u64, Uuid)>,
 }
 
+/// Views behind the current one for which signed messages are retained to
+/// detect equivocation.
+const EQUIVOCATION_WINDOW_VIEWS: u64 = 256;
+
 #[derive(Debug, Default, Clone)]
 struct VoteTally {
     stake: u128,
@@ -104,20 +155,30 @@ struct VoteTally {
 }
 
 impl HotStuffEngine {
-    pub fn new(validators: Vec<Validator>) -> Self {
+    pub fn new(chain_id: impl Into<String>, validators: Vec<Validator>) -> Self {
         let inner = HotStuffInner {
+            chain_id: chain_id.into(),
+            protocol_version: 0,
             state: ConsensusState {
                 view: 0,
                 height: 0,
                 locked_qc: None,
                 pending_qc: None,
+                last_voted_view: None,
+                last_committed: None,
             },
             pending_blocks: HashMap::new(),
             block_tree: HashMap::new(),
+            qcs: HashMap::new(),
+            committed: HashSet::new(),
             votes: HashMap::new(),
             total_stake: validators.iter().map(|v| v.stake).sum(),
             validators,
             commit_queue: VecDeque::new(),
+            seen_proposals: HashMap::new(),
+            seen_votes: HashMap::new(),
+            evidence: Vec::new(),
+            missed_proposals: Vec::new(),
         };
         Self {
             inner: Arc::new(Mutex::new(inner)),
@@ -125,6 +186,69 @@ impl HotStuffEngine {
         }
     }
 
+    /// Follows the chain's protocol version; the node calls it at startup
+    /// and after every applied block.
+    pub fn set_protocol_version(&self, version: u32) {
+        self.inner.lock().unwrap().protocol_version = version;
+    }
+
+    /// Whether untagged signatures verify under the chain's current version.
+    pub fn accepts_legacy_signatures(&self) -> bool {
+        self.inner.lock().unwrap().accept_legacy()
+    }
+
+    pub fn leader_proof(&self, view: u64) -> LeaderProof {
+        let guard = self.inner.lock().unwrap();
+        LeaderProof {
+            view,
+            validator_set_hash: validator_set_hash(&guard.validators),
+        }
+    }
+
+    /// Stateless admission check for gossiped proposals: the proposal must be
+    /// signed by the leader its proof names for the view in the block.
+    pub fn check_leader_proof(&self, proposal: &SignedProposal) -> anyhow::Result<()> {
+        let Some(proof) = proposal.leader_proof.as_ref() else {
+            anyhow::bail!("proposal carries no leader proof");
+        };
+        if proposal_view(&proposal.block) != Some(proof.view) {
+            anyhow::bail!("leader proof view does not match block");
+        }
+        let guard = self.inner.lock().unwrap();
+        verify_proposal(
+            &guard.chain_id,
+            proposal,
+            hash_block(&proposal.block),
+            guard.accept_legacy(),
+        )?;
+        if proof.validator_set_hash != validator_set_hash(&guard.validators) {
+            anyhow::bail!("leader proof references an unknown validator set");
+        }
+        match guard.leader_for_view(proof.view) {
+            Some(leader) if leader.owner == proposal.block.header.proposer_id => Ok(()),
+            _ => anyhow::bail!("proposer is not the leader for view {}", proof.view),
+        }
+    }
+
+    /// Stateless admission check for gossiped votes: the voter must be in
+    /// the current validator set and both its signatures must verify.
+    pub fn check_vote_signature(&self, vote: &SignedVote) -> anyhow::Result<()> {
+        let guard = self.inner.lock().unwrap();
+        verify_vote(
+            &guard.chain_id,
+            vote,
+            &guard.validators,
+            guard.accept_legacy(),
+        )
+        .map(|_| ())
+    }
+
+    /// Whether `pubkey` belongs to a member of the current validator set.
+    pub fn is_validator_key(&self, pubkey: &[u8]) -> bool {
+        let guard = self.inner.lock().unwrap();
+        guard.validators.iter().any(|v| v.pubkey == pubkey)
+    }
+
     pub async fn run_timeouts(self) {
         let mut interval = time::interval(self.timeout);
         loop {
@@ -136,6 +260,10 @@ impl HotStuffEngine {
 }
 
 impl HotStuffInner {
+    fn accept_legacy(&self) -> bool {
+        legacy_signatures_accepted(self.protocol_version)
+    }
+
     fn quorum_threshold(&self) -> u128 {
         (self.total_stake * 2) / 3 + 1
     }
@@ -153,6 +281,217 @@ impl HotStuffInner {
         }
         self.validators.first().cloned()
     }
+
+    fn parent_of(&self, block_id: &Hash) -> Option<Hash> {
+        self.block_tree
+            .get(block_id)
+            .map(|b| b.header.parent_hash)
+            .filter(|h| self.block_tree.contains_key(h))
+    }
+
+    fn extends(&self, block_id: &Hash, ancestor: &Hash) -> bool {
+        let mut cursor = Some(*block_id);
+        while let Some(id) = cursor {
+            if &id == ancestor {
+                return true;
+            }
+            cursor = self.parent_of(&id);
+        }
+        false
+    }
+
+    /// HotStuff `safeNode`: the block must extend the locked block, or the
+    /// proposal must carry a QC newer than the lock on the block's parent.
+    fn safe_node(&self, block: &Block, justify: Option<&QuorumCertificate>) -> bool {
+        let Some(locked) = self.state.locked_qc.as_ref() else {
+            return true;
+        };
+        self.extends(&hash_block(block), &locked.block_id)
+            || justify
+                .is_some_and(|qc| qc.view > locked.view && qc.block_id == block.header.parent_hash)
+    }
+
+    fn verify_qc(&self, qc: &QuorumCertificate) -> anyhow::Result<()> {
+        let messages = vote_messages(&self.chain_id, &qc.block_id, qc.view, self.accept_legacy())?;
+        let mut seen = HashSet::new();
+        let mut stake = 0u128;
+        let mut pubkeys = Vec::with_capacity(qc.voters.len());
+        for voter in &qc.voters {
+            if !seen.insert(*voter) {
+                anyhow::bail!("duplicate voter in qc");
+            }
+            let validator = self
+                .validators
+                .iter()
+                .find(|v| &v.id == voter)
+                .ok_or_else(|| anyhow::anyhow!("qc voter not in validator set"))?;
+            pubkeys.push(validator.bls_pubkey.clone());
+            stake = stake.saturating_add(validator.stake);
+        }
+        if stake < self.quorum_threshold() {
+            anyhow::bail!("qc below quorum threshold");
+        }
+        let mut result = Ok(());
+        for msg in &messages {
+            result = bls_verify_aggregate(&pubkeys, &qc.aggregate_signature, msg);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Chained HotStuff update on a new QC for `b2`: the QC is the prepare QC
+    /// for `b2`, the pre-commit (lock) for its parent `b1` and the commit for
+    /// the grandparent `b0` once all three form a direct chain.
+    fn update(&mut self, qc: QuorumCertificate) -> anyhow::Result<()> {
+        let high_view = self.state.pending_qc.as_ref().map(|q| q.view);
+        if high_view.is_none_or(|v| qc.view > v) {
+            self.state.pending_qc = Some(qc.clone());
+        }
+        let b2 = qc.block_id;
+        self.qcs.insert(b2, qc);
+
+        let Some(b1) = self.parent_of(&b2) else {
+            return Ok(());
+        };
+        let Some(b1_qc) = self.qcs.get(&b1).cloned() else {
+            return Ok(());
+        };
+        let locked_view = self.state.locked_qc.as_ref().map(|q| q.view);
+        if locked_view.is_none_or(|v| b1_qc.view > v) {
+            self.state.locked_qc = Some(b1_qc);
+        }
+
+        let Some(b0) = self.parent_of(&b1) else {
+            return Ok(());
+        };
+        if self.qcs.contains_key(&b0) && !self.committed.contains(&b0) {
+            self.commit(b0)?;
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self, block_id: Hash) -> anyhow::Result<()> {
+        if let Some(last) = self.state.last_committed {
+            if !self.extends(&block_id, &last) {
+                anyhow::bail!("refusing to commit block conflicting with committed chain");
+            }
+        }
+        let mut chain = Vec::new();
+        let mut cursor = Some(block_id);
+        while let Some(id) = cursor {
+            if self.committed.contains(&id) {
+                break;
+            }
+            chain.push(id);
+            cursor = self.parent_of(&id);
+        }
+        for id in chain.into_iter().rev() {
+            self.committed.insert(id);
+            self.pending_blocks.remove(&id);
+            if let Some(block) = self.block_tree.get(&id) {
+                self.state.height = block.header.height;
+            }
+            self.commit_queue.push_back(id);
+        }
+        self.state.last_committed = Some(block_id);
+        Ok(())
+    }
+
+    /// Remembers the proposal and fails with recorded evidence if the same
+    /// proposer already signed a different block for this view.
+    fn observe_proposal(
+        &mut self,
+        proposal: &SignedProposal,
+        view: u64,
+        validator_id: Uuid,
+    ) -> anyhow::Result<()> {
+        let key = (proposal.block.header.proposer_id, view);
+        let Some(prev) = self.seen_proposals.get(&key) else {
+            self.seen_proposals.insert(key, proposal.clone());
+            return Ok(());
+        };
+        if hash_block(&prev.block) == hash_block(&proposal.block) {
+            return Ok(());
+        }
+        let proof = DoubleSignEvidence::Proposal {
+            public_key: proposal.public_key.clone(),
+            first: Box::new(prev.block.clone()),
+            first_signature: prev.signature.clone(),
+            second: Box::new(proposal.block.clone()),
+            second_signature: proposal.signature.clone(),
+        };
+        self.evidence.push(SlashEvidence {
+            validator_id,
+            reason: "double proposal".into(),
+            height: proposal.block.header.height,
+            proof: Some(proof),
+        });
+        anyhow::bail!("equivocating proposal for view {view}")
+    }
+
+    fn observe_vote(&mut self, vote: &SignedVote) -> anyhow::Result<()> {
+        let key = (vote.voter.id, vote.view);
+        let Some(prev) = self.seen_votes.get(&key) else {
+            self.seen_votes.insert(key, vote.clone());
+            return Ok(());
+        };
+        if prev.block_id == vote.block_id {
+            return Ok(());
+        }
+        let proof = DoubleSignEvidence::Vote {
+            public_key: vote.voter.pubkey.clone(),
+            view: vote.view,
+            first: (prev.block_id, prev.signature.clone()),
+            second: (vote.block_id, vote.signature.clone()),
+        };
+        let height = self
+            .block_tree
+            .get(&vote.block_id)
+            .map(|b| b.header.height)
+            .unwrap_or(self.state.height);
+        self.evidence.push(SlashEvidence {
+            validator_id: vote.voter.id,
+            reason: "double vote".into(),
+            height,
+            proof: Some(proof),
+        });
+        anyhow::bail!("equivocating vote for view {}", vote.view)
+    }
+
+    fn prune_seen(&mut self) {
+        let floor = self.state.view.saturating_sub(EQUIVOCATION_WINDOW_VIEWS);
+        self.seen_proposals.retain(|(_, view), _| *view >= floor);
+        self.seen_votes.retain(|(_, view), _| *view >= floor);
+    }
+}
+
+/// Commitment to the validator set and stakes the leader schedule is drawn from.
+pub fn validator_set_hash(validators: &[Validator]) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    for v in validators {
+        hasher.update(v.id.as_bytes());
+        hasher.update(&v.owner);
+        hasher.update(&v.stake.to_le_bytes());
+    }
+    *hasher.finalize().as_bytes()
+}
+
+fn same_members(a: &[Uuid], b: &[Uuid]) -> bool {
+    let mut a = a.to_vec();
+    let mut b = b.to_vec();
+    a.sort();
+    b.sort();
+    a == b
+}
+
+fn proposal_view(block: &Block) -> Option<u64> {
+    block
+        .header
+        .consensus_metadata
+        .get("view")
+        .and_then(|v| v.as_u64())
 }
 
 #[async_trait]
@@ -160,24 +499,63 @@ impl ConsensusEngine for HotStuffEngine {
     async fn propose(&self, proposal: SignedProposal) -> anyhow::Result<()> {
         let block = proposal.block.clone();
         let block_id = hash_block(&block);
-        verify_proposal(&proposal, block_id)?;
-
         let mut guard = self.inner.lock().unwrap();
-        if let Some(leader) = guard.leader_for_view(guard.state.view) {
-            if leader.owner != block.header.proposer_id {
-                tracing::warn!("proposal from non-leader for view {}", guard.state.view);
+        verify_proposal(&guard.chain_id, &proposal, block_id, guard.accept_legacy())?;
+
+        let view =
+            proposal_view(&block).ok_or_else(|| anyhow::anyhow!("proposal carries no view"))?;
+        let leader = match guard.leader_for_view(view) {
+            Some(leader) if leader.owner == block.header.proposer_id => leader,
+            _ => anyhow::bail!("proposal from non-leader for view {view}"),
+        };
+        guard.observe_proposal(&proposal, view, leader.id)?;
+        if view < guard.state.view {
+            anyhow::bail!("stale proposal for view {view}, current view {}", guard.state.view);
+        }
+        if guard.state.last_voted_view.is_some_and(|v| view <= v) {
+            anyhow::bail!("already accepted a proposal for view {view}");
+        }
+        if let Some(justify) = proposal.justify.as_ref() {
+            guard.verify_qc(justify)?;
+        }
+        if let Some(report) = LivenessReport::from_block(&block) {
+            let backed = proposal.justify.as_ref().is_some_and(|qc| {
+                qc.view == report.qc_view && same_members(&qc.voters, &report.signers)
+            });
+            if !backed {
+                anyhow::bail!("liveness report does not match the justify QC");
+            }
+        }
+
+        guard.pending_blocks.insert(block_id, block.clone());
+        guard.block_tree.insert(block_id, block.clone());
+        if !guard.safe_node(&block, proposal.justify.as_ref()) {
+            guard.pending_blocks.remove(&block_id);
+            guard.block_tree.remove(&block_id);
+            anyhow::bail!("proposal does not extend locked block");
+        }
+        if let Some(justify) = proposal.justify {
+            if !guard.qcs.contains_key(&justify.block_id) {
+                guard.update(justify)?;
             }
         }
-        let block_clone = proposal.block.clone();
-        guard.pending_blocks.insert(block_id, block_clone.clone());
-        guard.block_tree.insert(block_id, block_clone);
-        guard.state.view += 1;
+        guard.state.last_voted_view = Some(view);
+        guard.state.view = view + 1;
+        guard.prune_seen();
         Ok(())
     }
 
     async fn vote(&self, vote: SignedVote) -> anyhow::Result<()> {
         let mut guard = self.inner.lock().unwrap();
-        verify_vote(&vote, &guard.validators)?;
+        // Weighed by the stake on record, not the one the vote reports.
+        let stake = verify_vote(
+            &guard.chain_id,
+            &vote,
+            &guard.validators,
+            guard.accept_legacy(),
+        )?
+        .stake;
+        guard.observe_vote(&vote)?;
         let block_id = vote.block_id;
         let view = vote.view;
 
@@ -193,8 +571,8 @@ impl ConsensusEngine for HotStuffEngine {
             }
 
             tally.voters.push(vote.voter.id);
-            tally.stake = tally.stake.saturating_add(vote.voter.stake);
-            tally.signatures.push(vote.signature.clone());
+            tally.stake = tally.stake.saturating_add(stake);
+            tally.signatures.push(vote.bls_signature.clone());
 
             if tally.stake >= threshold {
                 (true, tally.signatures.clone(), tally.voters.clone())
@@ -203,39 +581,35 @@ impl ConsensusEngine for HotStuffEngine {
             }
         };
 
-        if enough {
+        if enough && !guard.qcs.contains_key(&block_id) {
             let qc = QuorumCertificate {
                 block_id,
                 view,
-                signatures,
+                aggregate_signature: bls_aggregate(&signatures)?,
                 voters,
             };
-            guard.state.pending_qc = Some(qc.clone());
-            guard.state.locked_qc = Some(qc.clone());
-            guard.commit_queue.push_back(block_id);
+            guard.update(qc)?;
         }
         Ok(())
     }
 
     async fn on_qc(&self, qc: QuorumCertificate) -> anyhow::Result<()> {
         let mut guard = self.inner.lock().unwrap();
-        guard.state.locked_qc = Some(qc.clone());
-        guard.state.height += 1;
-
-        // 3-chain commit simulation: commit parent of qc.block_id if exists.
-        if let Some(current) = guard.block_tree.get(&qc.block_id) {
-            let parent_hash = current.header.parent_hash;
-            if parent_hash != [0u8; 32] {
-                guard.commit_queue.push_back(parent_hash);
-            }
+        guard.verify_qc(&qc)?;
+        if guard.qcs.contains_key(&qc.block_id) {
+            return Ok(());
         }
-
-        Ok(())
+        guard.update(qc)
     }
 
     async fn on_timeout(&self, view: u64) -> anyhow::Result<()> {
         let mut guard = self.inner.lock().unwrap();
         if view == guard.state.view {
+            if guard.state.last_voted_view != Some(view) {
+                if let Some(leader) = guard.leader_for_view(view) {
+                    guard.missed_proposals.push((view, leader.id));
+                }
+            }
             guard.state.view += 1;
         }
         Ok(())
@@ -248,9 +622,10 @@ impl ConsensusEngine for HotStuffEngine {
 
     async fn record_slash(&self, evidence: SlashEvidence) -> anyhow::Result<()> {
         let mut guard = self.inner.lock().unwrap();
-        // Placeholder: slashing is enforced by runtime; consensus records evidence for observability.
-        let digest = blake3::hash(evidence.validator_id.as_bytes());
-        guard.commit_queue.push_back(*digest.as_bytes());
+        if let Some(proof) = evidence.proof.as_ref() {
+            proof.verify(&guard.chain_id, guard.accept_legacy())?;
+        }
+        guard.evidence.push(evidence);
         Ok(())
     }
 
@@ -261,6 +636,7 @@ impl ConsensusEngine for HotStuffEngine {
             locked_qc: guard.state.locked_qc.is_some(),
             pending_qc: guard.state.pending_qc.is_some(),
             commit_queue_depth: guard.commit_queue.len(),
+            committed_height: guard.state.height,
         }
     }
 
@@ -278,22 +654,55 @@ impl ConsensusEngine for HotStuffEngine {
         let guard = self.inner.lock().unwrap();
         guard.state.view
     }
+
+    fn high_qc(&self) -> Option<QuorumCertificate> {
+        let guard = self.inner.lock().unwrap();
+        guard.state.pending_qc.clone()
+    }
+
+    fn take_evidence(&self) -> Vec<SlashEvidence> {
+        let mut guard = self.inner.lock().unwrap();
+        std::mem::take(&mut guard.evidence)
+    }
+
+    fn liveness_report(&self) -> Option<LivenessReport> {
+        let mut guard = self.inner.lock().unwrap();
+        let qc = guard.state.pending_qc.clone()?;
+        Some(LivenessReport {
+            qc_view: qc.view,
+            signers: qc.voters,
+            missed_proposals: std::mem::take(&mut guard.missed_proposals),
+        })
+    }
 }
 
-fn verify_proposal(proposal: &SignedProposal, block_id: Hash) -> anyhow::Result<()> {
+pub fn verify_proposal(
+    chain_id: &str,
+    proposal: &SignedProposal,
+    block_id: Hash,
+    accept_legacy: bool,
+) -> anyhow::Result<()> {
     let proposer_addr = address_from_pubkey(&proposal.public_key);
     if proposer_addr != proposal.block.header.proposer_id {
         anyhow::bail!("proposal proposer_id does not match pubkey");
     }
-    verify_signature_bytes(&proposal.public_key, &proposal.signature, &block_id)?;
-    Ok(())
-}
-
-fn vote_signing_bytes(block_id: &Hash, view: u64) -> anyhow::Result<Vec<u8>> {
-    Ok(bincode::serialize(&(block_id, view))?)
+    verify_in_domain(
+        &proposal.public_key,
+        &proposal.signature,
+        SigningDomain::Proposal,
+        chain_id,
+        &block_id,
+        accept_legacy,
+    )
 }
 
-fn verify_vote(vote: &SignedVote, validators: &[Validator]) -> anyhow::Result<()> {
+/// Checks `vote` against the voter's entry in `validators` and returns it.
+fn verify_vote<'a>(
+    chain_id: &str,
+    vote: &SignedVote,
+    validators: &'a [Validator],
+    accept_legacy: bool,
+) -> anyhow::Result<&'a Validator> {
     let expected = validators
         .iter()
         .find(|v| v.id == vote.voter.id)
@@ -301,18 +710,46 @@ fn verify_vote(vote: &SignedVote, validators: &[Validator]) -> anyhow::Result<()
     if expected.pubkey != vote.voter.pubkey {
         anyhow::bail!("voter pubkey mismatch");
     }
-    let msg = vote_signing_bytes(&vote.block_id, vote.view)?;
-    verify_signature_bytes(&vote.voter.pubkey, &vote.signature, &msg)?;
-    Ok(())
+    if expected.bls_pubkey.is_empty() {
+        anyhow::bail!("voter has no registered bls key");
+    }
+    // Both signatures must cover the same message form so aggregates built
+    // from accepted votes stay verifiable.
+    let messages = vote_messages(chain_id, &vote.block_id, vote.view, accept_legacy)?;
+    let msg = messages
+        .iter()
+        .find(|msg| verify_signature_bytes(&vote.voter.pubkey, &vote.signature, msg).is_ok())
+        .ok_or_else(|| anyhow::anyhow!("invalid vote signature"))?;
+    bls_verify(&expected.bls_pubkey, &vote.bls_signature, msg)?;
+    Ok(expected)
+}
+
+pub fn sign_vote(
+    chain_id: &str,
+    block_id: &Hash,
+    view: u64,
+    signing_key: &ed25519_dalek::SigningKey,
+) -> anyhow::Result<Vec<u8>> {
+    let bytes = vote_signing_bytes(chain_id, block_id, view)?;
+    Ok(sign_bytes(signing_key, &bytes))
 }
 
-pub fn sign_vote(block_id: &Hash, view: u64, signing_key: &ed25519_dalek::SigningKey) -> Vec<u8> {
-    let bytes = bincode::serialize(&(block_id, view)).unwrap_or_default();
-    sign_bytes(signing_key, &bytes)
+pub fn sign_vote_bls(
+    chain_id: &str,
+    block_id: &Hash,
+    view: u64,
+    key: &BlsSecretKey,
+) -> anyhow::Result<Vec<u8>> {
+    let bytes = vote_signing_bytes(chain_id, block_id, view)?;
+    Ok(key.sign(&bytes))
 }
 
-pub fn sign_proposal(block: &Block, signing_key: &ed25519_dalek::SigningKey) -> Vec<u8> {
+pub fn sign_proposal(
+    chain_id: &str,
+    block: &Block,
+    signing_key: &ed25519_dalek::SigningKey,
+) -> Vec<u8> {
     let block_id = hash_block(block);
-    sign_bytes(signing_key, block_id.as_slice())
+    sign_in_domain(signing_key, SigningDomain::Proposal, chain_id, &block_id)
 }
 
@@ -1,13 +1,20 @@
 use consensus::{
-    build_block, sign_proposal, sign_vote, ConsensusEngine, HotStuffEngine, SignedProposal,
-    SignedVote,
+    build_block, sign_proposal, sign_vote, sign_vote_bls, ConsensusEngine, HotStuffEngine,
+    LeaderProof, QuorumCertificate, SignedProposal, SignedVote,
 };
 use ed25519_dalek::SigningKey;
 use proptest::prelude::*;
-use runtime::{address_from_pubkey, hash_block, Block, BlockHeader};
+use runtime::bls::BlsSecretKey;
+use runtime::{address_from_pubkey, hash_block, Block, BlockHeader, Hash};
 use state::{Validator, ValidatorStatus};
 use uuid::Uuid;
 
+const CHAIN: &str = "kova-devnet";
+
+fn bls_key(sk: &SigningKey) -> BlsSecretKey {
+    BlsSecretKey::from_seed(&sk.to_bytes()).unwrap()
+}
+
 fn make_validator(seed: u8, stake: u128) -> (Validator, SigningKey) {
     let sk = SigningKey::from_bytes(&[seed; 32]);
     let pk = sk.verifying_key().to_bytes().to_vec();
@@ -19,13 +26,20 @@ fn make_validator(seed: u8, stake: u128) -> (Validator, SigningKey) {
         stake: stake.max(1),
         status: ValidatorStatus::Active,
         commission_rate: 0,
+        bls_pubkey: bls_key(&sk).public_key(),
+        description: Default::default(),
+        commission_changed_epoch: None,
     };
     (v, sk)
 }
 
 fn empty_block_for(proposer: &Validator, height: u64) -> Block {
+    child_block(proposer, [0u8; 32], height, 0)
+}
+
+fn child_block(proposer: &Validator, parent_hash: Hash, height: u64, view: u64) -> Block {
     let header = BlockHeader {
-        parent_hash: [0u8; 32],
+        parent_hash,
         height,
         timestamp: 0,
         proposer_id: proposer.owner,
@@ -36,7 +50,9 @@ fn empty_block_for(proposer: &Validator, height: u64) -> Block {
         gas_used: 0,
         gas_limit: 30_000_000,
         base_fee: 1,
-        consensus_metadata: serde_json::json!({}),
+        snapshot_root: None,
+        protocol_version: 0,
+        consensus_metadata: serde_json::json!({ "view": view }),
     };
     build_block(header, vec![], vec![])
 }
@@ -49,7 +65,7 @@ proptest! {
             v
         }).collect();
 
-        let engine = HotStuffEngine::new(validators.clone());
+        let engine = HotStuffEngine::new(CHAIN, validators.clone());
         for view in 0..32 {
             let leader = engine.leader_for_view(view).expect("leader exists");
             prop_assert!(validators.iter().any(|v| v.id == leader.id));
@@ -57,51 +73,302 @@ proptest! {
     }
 }
 
+fn signed(block: &Block, sk: &SigningKey, engine: &HotStuffEngine) -> SignedProposal {
+    SignedProposal {
+        public_key: sk.verifying_key().to_bytes().to_vec(),
+        signature: sign_proposal(CHAIN, block, sk),
+        block: block.clone(),
+        justify: engine.high_qc(),
+        leader_proof: None,
+    }
+}
+
+async fn vote_all(
+    engine: &HotStuffEngine,
+    block_id: Hash,
+    view: u64,
+    voters: &[(Validator, SigningKey)],
+) {
+    for (v, sk) in voters {
+        let vote = SignedVote {
+            block_id,
+            view,
+            voter: v.clone(),
+            signature: sign_vote(CHAIN, &block_id, view, sk).unwrap(),
+            bls_signature: sign_vote_bls(CHAIN, &block_id, view, &bls_key(sk)).unwrap(),
+        };
+        engine.vote(vote).await.unwrap();
+    }
+}
+
 #[tokio::test]
 async fn quorum_commit_survives_timeout_and_late_votes() {
     let (v1, sk1) = make_validator(1, 10);
     let (v2, sk2) = make_validator(2, 15);
     let (v3, sk3) = make_validator(3, 25);
-    let engine = HotStuffEngine::new(vec![v1.clone(), v2.clone(), v3.clone()]);
+    let voters = vec![
+        (v1.clone(), sk1.clone()),
+        (v2.clone(), sk2),
+        (v3.clone(), sk3),
+    ];
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone(), v2.clone(), v3.clone()]);
 
-    let block = empty_block_for(&v1, 1);
-    let proposal = SignedProposal {
-        public_key: v1.pubkey.clone(),
-        signature: sign_proposal(&block, &sk1),
-        block: block.clone(),
-    };
-    engine.propose(proposal).await.unwrap();
-    let block_id = hash_block(&block);
+    let b1 = empty_block_for(&v1, 1);
+    engine.propose(signed(&b1, &sk1, &engine)).await.unwrap();
+    let b1_id = hash_block(&b1);
 
     // Simulate a timeout bumping the view before votes land.
     let view_before = engine.current_view();
     engine.on_timeout(view_before).await.unwrap();
     assert!(engine.current_view() >= view_before + 1);
 
-    // Late votes for the original view should still accumulate and reach quorum.
-    let vote0 = SignedVote {
-        block_id,
-        view: 0,
-        voter: v1.clone(),
-        signature: sign_vote(&block_id, 0, &sk1),
+    // Late votes for the original view should still accumulate and reach quorum,
+    // but a single QC is not enough to commit.
+    vote_all(&engine, b1_id, 0, &voters).await;
+    assert!(engine.pop_commit().is_none());
+
+    let view = engine.current_view();
+    let b2 = child_block(&v1, b1_id, 2, view);
+    engine.propose(signed(&b2, &sk1, &engine)).await.unwrap();
+    let b2_id = hash_block(&b2);
+    vote_all(&engine, b2_id, view, &voters).await;
+    assert!(engine.pop_commit().is_none());
+
+    let view = engine.current_view();
+    let b3 = child_block(&v1, b2_id, 3, view);
+    engine.propose(signed(&b3, &sk1, &engine)).await.unwrap();
+    vote_all(&engine, hash_block(&b3), view, &voters).await;
+
+    // Three-chain b1 <- b2 <- b3 commits b1 only.
+    assert_eq!(engine.pop_commit(), Some(b1_id));
+    assert!(engine.pop_commit().is_none());
+    assert_eq!(engine.metrics().committed_height, 1);
+}
+
+#[tokio::test]
+async fn proposal_conflicting_with_lock_is_rejected() {
+    let (v1, sk1) = make_validator(1, 10);
+    let (v2, sk2) = make_validator(2, 15);
+    let (v3, sk3) = make_validator(3, 25);
+    let voters = vec![
+        (v1.clone(), sk1.clone()),
+        (v2.clone(), sk2),
+        (v3.clone(), sk3),
+    ];
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone(), v2.clone(), v3.clone()]);
+
+    let b1 = empty_block_for(&v1, 1);
+    engine.propose(signed(&b1, &sk1, &engine)).await.unwrap();
+    let b1_id = hash_block(&b1);
+    vote_all(&engine, b1_id, 0, &voters).await;
+
+    let view = engine.current_view();
+    let b2 = child_block(&v1, b1_id, 2, view);
+    engine.propose(signed(&b2, &sk1, &engine)).await.unwrap();
+    vote_all(&engine, hash_block(&b2), view, &voters).await;
+
+    // b1 is now locked; a fork off genesis without a newer QC must be refused.
+    let fork = child_block(&v1, [7u8; 32], 2, engine.current_view());
+    let mut proposal = SignedProposal {
+        public_key: v1.pubkey.clone(),
+        signature: sign_proposal(CHAIN, &fork, &sk1),
+        block: fork,
+        justify: None,
+        leader_proof: None,
+    };
+    assert!(engine.propose(proposal.clone()).await.is_err());
+
+    // A QC newer than the lock only unlocks a child of the block it certifies.
+    proposal.justify = engine.high_qc();
+    assert!(proposal.justify.as_ref().unwrap().view > 0);
+    let err = engine.propose(proposal).await.unwrap_err();
+    assert!(err.to_string().contains("locked block"), "{err}");
+}
+
+#[tokio::test]
+async fn stale_and_duplicate_view_proposals_are_rejected() {
+    let (v1, sk1) = make_validator(1, 10);
+    let (v2, _) = make_validator(2, 15);
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone(), v2.clone()]);
+
+    let block = empty_block_for(&v1, 1);
+    engine.propose(signed(&block, &sk1, &engine)).await.unwrap();
+
+    let other = empty_block_for(&v1, 2);
+    assert!(engine.propose(signed(&other, &sk1, &engine)).await.is_err());
+}
+
+#[tokio::test]
+async fn proposals_without_a_view_are_rejected() {
+    let (v1, sk1) = make_validator(1, 10);
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone()]);
+
+    let mut block = empty_block_for(&v1, 1);
+    block.header.consensus_metadata = serde_json::json!({});
+    let err = engine
+        .propose(signed(&block, &sk1, &engine))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("no view"), "{err}");
+}
+
+#[tokio::test]
+async fn votes_count_the_stake_on_record() {
+    let (v1, sk1) = make_validator(1, 10);
+    let (v2, _) = make_validator(2, 15);
+    let (v3, _) = make_validator(3, 25);
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone(), v2, v3]);
+    let block = empty_block_for(&v1, 1);
+    engine.propose(signed(&block, &sk1, &engine)).await.unwrap();
+
+    // v1 claims the whole quorum for itself.
+    let inflated = Validator {
+        stake: 1_000,
+        ..v1.clone()
     };
-    engine.vote(vote0).await.unwrap();
+    vote_all(&engine, hash_block(&block), 0, &[(inflated, sk1)]).await;
+    assert!(engine.high_qc().is_none());
+}
 
-    let vote1 = SignedVote {
+#[tokio::test]
+async fn aggregated_qc_verifies_and_rejects_forgery() {
+    let (v1, sk1) = make_validator(1, 10);
+    let (v2, sk2) = make_validator(2, 15);
+    let (v3, sk3) = make_validator(3, 25);
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone(), v2.clone(), v3.clone()]);
+    let voters = vec![
+        (v1.clone(), sk1.clone()),
+        (v2.clone(), sk2),
+        (v3.clone(), sk3),
+    ];
+
+    let block = empty_block_for(&v1, 1);
+    engine.propose(signed(&block, &sk1, &engine)).await.unwrap();
+    let block_id = hash_block(&block);
+    vote_all(&engine, block_id, 0, &voters).await;
+
+    let qc = engine.high_qc().expect("qc formed");
+    assert_eq!(
+        qc.aggregate_signature.len(),
+        runtime::bls::BLS_SIGNATURE_LEN
+    );
+    assert_eq!(qc.voters.len(), 3);
+    engine.on_qc(qc.clone()).await.unwrap();
+
+    // Replaying the aggregate for another view must fail verification.
+    let forged = QuorumCertificate { view: 1, ..qc };
+    assert!(engine.on_qc(forged).await.is_err());
+}
+
+#[tokio::test]
+async fn vote_without_bls_key_is_rejected() {
+    let (v1, sk1) = make_validator(1, 10);
+    let (mut v2, sk2) = make_validator(2, 15);
+    v2.bls_pubkey.clear();
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone(), v2.clone()]);
+    let block_id = [1u8; 32];
+    let vote = SignedVote {
         block_id,
         view: 0,
         voter: v2.clone(),
-        signature: sign_vote(&block_id, 0, &sk2),
+        signature: sign_vote(CHAIN, &block_id, 0, &sk2).unwrap(),
+        bls_signature: sign_vote_bls(CHAIN, &block_id, 0, &bls_key(&sk1)).unwrap(),
     };
-    engine.vote(vote1).await.unwrap();
+    assert!(engine.vote(vote).await.is_err());
+}
 
-    let vote2 = SignedVote {
+#[tokio::test]
+async fn equivocating_vote_yields_verifiable_evidence() {
+    let (v1, _) = make_validator(1, 10);
+    let (v2, sk2) = make_validator(2, 15);
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone(), v2.clone()]);
+    let bls = bls_key(&sk2);
+    let vote_for = |block_id: Hash| SignedVote {
         block_id,
-        view: 0,
-        voter: v3.clone(),
-        signature: sign_vote(&block_id, 0, &sk3),
+        view: 3,
+        voter: v2.clone(),
+        signature: sign_vote(CHAIN, &block_id, 3, &sk2).unwrap(),
+        bls_signature: sign_vote_bls(CHAIN, &block_id, 3, &bls).unwrap(),
     };
-    engine.vote(vote2).await.unwrap();
+    engine.vote(vote_for([1u8; 32])).await.unwrap();
+    engine.vote(vote_for([1u8; 32])).await.unwrap();
+    assert!(engine.take_evidence().is_empty());
+
+    assert!(engine.vote(vote_for([2u8; 32])).await.is_err());
+    let evidence = engine.take_evidence();
+    assert_eq!(evidence.len(), 1);
+    assert_eq!(evidence[0].validator_id, v2.id);
+    let proof = evidence[0].proof.as_ref().unwrap();
+    proof.verify(CHAIN, false).unwrap();
+    assert_eq!(proof.offender(), v2.owner);
+}
+
+#[tokio::test]
+async fn double_proposal_yields_verifiable_evidence() {
+    let (v1, sk1) = make_validator(1, 10);
+    let (v2, _) = make_validator(2, 15);
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone(), v2.clone()]);
+
+    let first = empty_block_for(&v1, 1);
+    let mut second = empty_block_for(&v1, 1);
+    second.header.timestamp = 1;
+
+    engine.propose(signed(&first, &sk1, &engine)).await.unwrap();
+    assert!(engine
+        .propose(signed(&second, &sk1, &engine))
+        .await
+        .is_err());
+    let evidence = engine.take_evidence();
+    assert_eq!(evidence.len(), 1);
+    assert_eq!(evidence[0].validator_id, v1.id);
+    evidence[0].proof.as_ref().unwrap().verify(CHAIN, false).unwrap();
+}
+
+fn proposal_at(
+    proposer: &Validator,
+    sk: &SigningKey,
+    view: u64,
+    proof: Option<LeaderProof>,
+) -> SignedProposal {
+    let mut block = empty_block_for(proposer, 1);
+    block.header.consensus_metadata = serde_json::json!({ "view": view });
+    SignedProposal {
+        public_key: sk.verifying_key().to_bytes().to_vec(),
+        signature: sign_proposal(CHAIN, &block, sk),
+        block,
+        justify: None,
+        leader_proof: proof,
+    }
+}
+
+#[test]
+fn gossip_admits_only_proposals_from_the_scheduled_leader() {
+    let (v1, sk1) = make_validator(1, 10);
+    let (v2, sk2) = make_validator(2, 15);
+    let engine = HotStuffEngine::new(CHAIN, vec![v1.clone(), v2.clone()]);
+    // Views 0..10 fall in v1's stake slot, 10..25 in v2's.
+    assert_eq!(engine.leader_for_view(3).unwrap().id, v1.id);
+    assert_eq!(engine.leader_for_view(12).unwrap().id, v2.id);
+
+    let leader = proposal_at(&v1, &sk1, 3, Some(engine.leader_proof(3)));
+    assert!(engine.check_leader_proof(&leader).is_ok());
+
+    let missing = proposal_at(&v1, &sk1, 3, None);
+    assert!(engine.check_leader_proof(&missing).is_err());
+
+    let non_leader = proposal_at(&v2, &sk2, 3, Some(engine.leader_proof(3)));
+    assert!(engine.check_leader_proof(&non_leader).is_err());
+
+    // A proof for a view the proposer does lead cannot be attached to another view.
+    let mismatched = proposal_at(&v2, &sk2, 3, Some(engine.leader_proof(12)));
+    assert!(engine.check_leader_proof(&mismatched).is_err());
+
+    let mut foreign_set = engine.leader_proof(3);
+    foreign_set.validator_set_hash = [9u8; 32];
+    let foreign = proposal_at(&v1, &sk1, 3, Some(foreign_set));
+    assert!(engine.check_leader_proof(&foreign).is_err());
 
-    assert!(engine.pop_commit().is_some());
+    let mut other_chain = proposal_at(&v1, &sk1, 3, Some(engine.leader_proof(3)));
+    other_chain.signature = sign_proposal("kova-testnet", &other_chain.block, &sk1);
+    assert!(engine.check_leader_proof(&other_chain).is_err());
 }
@@ -11,6 +11,9 @@ async fn quorum_reached_with_stake_weight() {
         stake: 10,
         status: ValidatorStatus::Active,
         commission_rate: 0,
+        bls_pubkey: vec![],
+        description: Default::default(),
+        commission_changed_epoch: None,
     };
     let v2 = Validator {
         owner: [2u8; 32],
@@ -19,8 +22,11 @@ async fn quorum_reached_with_stake_weight() {
         stake: 10,
         status: ValidatorStatus::Active,
         commission_rate: 0,
+        bls_pubkey: vec![],
+        description: Default::default(),
+        commission_changed_epoch: None,
     };
-    let engine = HotStuffEngine::new(vec![v1.clone(), v2.clone()]);
+    let engine = HotStuffEngine::new("kova-devnet", vec![v1.clone(), v2.clone()]);
     let block_id = [0u8; 32];
 
     engine.vote(block_id, 0, &v1).await.unwrap();
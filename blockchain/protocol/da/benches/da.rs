@@ -0,0 +1,61 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use da::{verify_da_proof, DAConfig, DAProvider, InMemoryDA};
+use tokio::runtime::Runtime;
+
+const BLOB_SIZES: [usize; 3] = [4 * 1024, 64 * 1024, 1024 * 1024];
+
+fn provider() -> InMemoryDA {
+    InMemoryDA::with_config(DAConfig {
+        shard_size: 4 * 1024,
+        data_shards: 16,
+        parity_shards: 8,
+        ..Default::default()
+    })
+}
+
+fn bench_commit(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("da_commit");
+    for size in BLOB_SIZES {
+        let blob = vec![0xABu8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &blob, |b, blob| {
+            // A fresh provider per iteration keeps stored blobs from piling up.
+            b.iter_batched(
+                provider,
+                |da| rt.block_on(da.submit_blob("bench", blob)).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_proofs(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut prove = c.benchmark_group("da_prove");
+    let mut cases = Vec::new();
+    for size in BLOB_SIZES {
+        let da = provider();
+        let blob = rt
+            .block_on(da.submit_blob("bench", &vec![0xABu8; size]))
+            .unwrap();
+        prove.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| rt.block_on(da.prove_blob_availability(&blob.id)).unwrap())
+        });
+        let proof = rt.block_on(da.prove_blob_availability(&blob.id)).unwrap();
+        cases.push((size, proof));
+    }
+    prove.finish();
+
+    let mut verify = c.benchmark_group("da_verify");
+    for (size, proof) in &cases {
+        verify.bench_with_input(BenchmarkId::from_parameter(size), proof, |b, proof| {
+            b.iter(|| assert!(verify_da_proof(proof)))
+        });
+    }
+    verify.finish();
+}
+
+criterion_group!(benches, bench_commit, bench_proofs);
+criterion_main!(benches);
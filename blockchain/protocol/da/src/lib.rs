@@ -1,9 +1,10 @@
 use async_trait::async_trait;
 use blake3;
-use rand::{rngs::StdRng, Rng, SeedableRng};
+use reed_solomon_erasure::galois_8::ReedSolomon;
 use runtime::Hash;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +22,9 @@ pub struct DACommitment {
     pub data_shards: usize,
     pub parity_shards: usize,
     pub shard_size: usize,
+    /// Length of the original blob, used to strip shard padding on reconstruction.
+    #[serde(default)]
+    pub blob_len: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,12 +46,94 @@ pub trait DAProvider: Send + Sync {
     async fn submit_blob(&self, domain_id: &str, blob_bytes: &[u8]) -> anyhow::Result<BlobRef>;
     async fn get_blob(&self, blob_id: &str) -> anyhow::Result<Vec<u8>>;
     async fn prove_blob_availability(&self, blob_id: &str) -> anyhow::Result<DAProof>;
+    /// Prove availability of exactly the requested shard indices, in order.
+    async fn prove_samples(&self, blob_id: &str, indices: &[usize]) -> anyhow::Result<DAProof>;
     async fn get_commitment(&self, blob_id: &str) -> anyhow::Result<DACommitment>;
+    /// Rebuild a blob from any `data_shards` of its erasure-coded shards,
+    /// given as `(shard_index, shard_bytes)` pairs.
+    async fn reconstruct_blob(
+        &self,
+        blob_id: &str,
+        shards: Vec<(usize, Vec<u8>)>,
+    ) -> anyhow::Result<Vec<u8>>;
+    /// Record the height of the block header committing to this blob.
+    async fn anchor_blob(&self, blob_id: &str, height: u64) -> anyhow::Result<()>;
+    /// Drop data and shards of blobs anchored below `height`. Commitments are
+    /// kept so headers referencing pruned blobs stay verifiable.
+    async fn prune_before(&self, height: u64) -> anyhow::Result<PruneStats>;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PruneStats {
+    pub blobs: usize,
+    pub bytes_freed: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DAStorageStats {
+    pub stored_blobs: usize,
+    pub blob_bytes: u64,
+    pub shard_bytes: u64,
+    pub commitments: usize,
+    /// Blobs pruned since the provider started.
+    pub pruned_blobs: u64,
 }
 
 #[async_trait]
 pub trait DASampler: Send + Sync {
-    async fn sample(&self, blob_id: &str, samples: usize) -> anyhow::Result<bool>;
+    /// Sample shards chosen by the verifier from `seed`, e.g. a block hash.
+    async fn sample_with_seed(
+        &self,
+        blob_id: &str,
+        seed: Hash,
+        samples: usize,
+    ) -> anyhow::Result<bool>;
+
+    /// Sample with fresh local randomness.
+    async fn sample(&self, blob_id: &str, samples: usize) -> anyhow::Result<bool> {
+        self.sample_with_seed(blob_id, rand::random(), samples).await
+    }
+}
+
+/// Challenge seed binding a blob's sampling to the block that references it.
+pub fn challenge_seed(block_hash: &Hash, blob_id: &str) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(block_hash);
+    hasher.update(blob_id.as_bytes());
+    *hasher.finalize().as_bytes()
+}
+
+/// Distinct shard indices selected by `seed`. Anyone holding the seed and the
+/// commitment can recompute them.
+pub fn sample_indices(seed: &Hash, total_shards: usize, count: usize) -> Vec<usize> {
+    let count = count.min(total_shards);
+    let mut indices = Vec::with_capacity(count);
+    let mut counter = 0u64;
+    while indices.len() < count {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(seed);
+        hasher.update(&counter.to_le_bytes());
+        let digest = hasher.finalize();
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&digest.as_bytes()[..8]);
+        let idx = (u64::from_le_bytes(word) % total_shards as u64) as usize;
+        if !indices.contains(&idx) {
+            indices.push(idx);
+        }
+        counter += 1;
+    }
+    indices
+}
+
+/// How long blob data is kept after submission.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    #[default]
+    KeepAll,
+    /// Keep blobs anchored within this many blocks of the current height.
+    Blocks(u64),
+    /// Keep blobs submitted within this many milliseconds.
+    AgeMs(u64),
 }
 
 #[derive(Debug, Clone)]
@@ -55,6 +141,7 @@ pub struct DAConfig {
     pub shard_size: usize,
     pub data_shards: usize,
     pub parity_shards: usize,
+    pub retention: RetentionPolicy,
 }
 
 impl Default for DAConfig {
@@ -63,6 +150,7 @@ impl Default for DAConfig {
             shard_size: 1024,
             data_shards: 4,
             parity_shards: 2,
+            retention: RetentionPolicy::KeepAll,
         }
     }
 }
@@ -73,9 +161,17 @@ pub struct InMemoryDA {
     meta: Arc<Mutex<HashMap<String, BlobRef>>>,
     shards: Arc<Mutex<HashMap<String, Vec<Vec<u8>>>>>,
     commitments: Arc<Mutex<HashMap<String, DACommitment>>>,
+    anchors: Arc<Mutex<HashMap<String, BlobAnchor>>>,
+    pruned_total: Arc<AtomicU64>,
     config: DAConfig,
 }
 
+#[derive(Debug, Clone, Copy)]
+struct BlobAnchor {
+    height: Option<u64>,
+    submitted_at_ms: u64,
+}
+
 impl InMemoryDA {
     pub fn new() -> Self {
         Self::with_config(DAConfig::default())
@@ -87,62 +183,101 @@ impl InMemoryDA {
             meta: Arc::new(Mutex::new(HashMap::new())),
             shards: Arc::new(Mutex::new(HashMap::new())),
             commitments: Arc::new(Mutex::new(HashMap::new())),
+            anchors: Arc::new(Mutex::new(HashMap::new())),
+            pruned_total: Arc::new(AtomicU64::new(0)),
             config,
         }
     }
 
-    fn shard_blob(&self, blob_bytes: &[u8]) -> (Vec<Vec<u8>>, DACommitment) {
-        let cfg = &self.config;
-        let mut data_shards = Vec::new();
-        for chunk in blob_bytes.chunks(cfg.shard_size) {
-            let mut shard = vec![0u8; cfg.shard_size];
-            shard[..chunk.len()].copy_from_slice(chunk);
-            data_shards.push(shard);
+    pub fn storage_stats(&self) -> DAStorageStats {
+        let inner = self.inner.lock().unwrap();
+        let shards = self.shards.lock().unwrap();
+        DAStorageStats {
+            stored_blobs: shards.len(),
+            blob_bytes: inner.values().map(|b| b.len() as u64).sum(),
+            shard_bytes: shards
+                .values()
+                .flat_map(|s| s.iter())
+                .map(|s| s.len() as u64)
+                .sum(),
+            commitments: self.commitments.lock().unwrap().len(),
+            pruned_blobs: self.pruned_total.load(Ordering::Relaxed),
         }
+    }
 
-        // pad data shards up to configured count
-        while data_shards.len() < cfg.data_shards {
-            data_shards.push(vec![0u8; cfg.shard_size]);
+    /// Prune according to the configured retention policy.
+    pub fn apply_retention(&self, current_height: u64) -> PruneStats {
+        match self.config.retention {
+            RetentionPolicy::KeepAll => PruneStats::default(),
+            RetentionPolicy::Blocks(window) => {
+                let cutoff = current_height.saturating_sub(window);
+                self.prune_where(|anchor| anchor.height.is_some_and(|h| h < cutoff))
+            }
+            RetentionPolicy::AgeMs(max_age) => {
+                let cutoff = now_millis().saturating_sub(max_age);
+                self.prune_where(|anchor| anchor.submitted_at_ms < cutoff)
+            }
         }
+    }
 
-        // parity shards = xor of all data shards
-        let mut parity_shards = Vec::new();
-        for _ in 0..cfg.parity_shards {
-            let mut parity = vec![0u8; cfg.shard_size];
-            for shard in &data_shards {
-                for (i, byte) in shard.iter().enumerate() {
-                    parity[i] ^= byte;
-                }
+    fn prune_where(&self, expired: impl Fn(&BlobAnchor) -> bool) -> PruneStats {
+        let anchors = self.anchors.lock().unwrap();
+        let mut inner = self.inner.lock().unwrap();
+        let mut shards = self.shards.lock().unwrap();
+        let mut stats = PruneStats::default();
+        for (id, anchor) in anchors.iter() {
+            if !expired(anchor) {
+                continue;
             }
-            parity_shards.push(parity);
+            let Some(blob_shards) = shards.remove(id) else {
+                continue;
+            };
+            let blob_bytes = inner.remove(id).map(|b| b.len()).unwrap_or_default();
+            let shard_bytes: usize = blob_shards.iter().map(Vec::len).sum();
+            stats.blobs += 1;
+            stats.bytes_freed += (blob_bytes + shard_bytes) as u64;
         }
+        self.pruned_total
+            .fetch_add(stats.blobs as u64, Ordering::Relaxed);
+        stats
+    }
 
-        let mut shards = data_shards;
-        shards.extend(parity_shards);
+    fn missing_blob(&self, blob_id: &str) -> anyhow::Error {
+        if self.commitments.lock().unwrap().contains_key(blob_id) {
+            anyhow::anyhow!("blob {blob_id} was pruned")
+        } else {
+            anyhow::anyhow!("blob not found")
+        }
+    }
 
-        let leaf_hashes: Vec<Hash> = shards
-            .iter()
-            .map(|shard| *blake3::hash(shard).as_bytes())
-            .collect();
-        let root = merkle_root(&leaf_hashes);
-        let commitment = DACommitment {
-            root,
-            total_shards: leaf_hashes.len(),
-            data_shards: cfg.data_shards,
-            parity_shards: cfg.parity_shards,
-            shard_size: cfg.shard_size,
-        };
-        (shards, commitment)
+    /// Stored shards of a blob, data shards first followed by parity shards.
+    pub fn shards(&self, blob_id: &str) -> Option<Vec<Vec<u8>>> {
+        self.shards.lock().unwrap().get(blob_id).cloned()
     }
-}
 
-#[async_trait]
-impl DAProvider for InMemoryDA {
-    async fn submit_blob(&self, domain_id: &str, blob_bytes: &[u8]) -> anyhow::Result<BlobRef> {
+    /// Start a streamed submission of a blob of exactly `blob_len` bytes.
+    /// Bytes are sharded and hashed as they arrive instead of being buffered
+    /// as a whole.
+    pub fn begin_blob(&self, domain_id: &str, blob_len: usize) -> anyhow::Result<BlobWriter> {
+        Ok(BlobWriter {
+            da: self.clone(),
+            domain_id: domain_id.to_string(),
+            encoder: BlobEncoder::new(&self.config, blob_len)?,
+            shards: Vec::with_capacity(self.config.data_shards + self.config.parity_shards),
+        })
+    }
+
+    fn store_blob(
+        &self,
+        domain_id: &str,
+        blob_bytes: Option<Vec<u8>>,
+        shards: Vec<Vec<u8>>,
+        commitment: DACommitment,
+    ) -> BlobRef {
         let id = format!("{}-{}", domain_id, uuid::Uuid::new_v4());
-        let (shards, commitment) = self.shard_blob(blob_bytes);
-        let mut guard = self.inner.lock().unwrap();
-        guard.insert(id.clone(), blob_bytes.to_vec());
+        if let Some(bytes) = blob_bytes {
+            self.inner.lock().unwrap().insert(id.clone(), bytes);
+        }
         self.shards.lock().unwrap().insert(id.clone(), shards);
         self.commitments
             .lock()
@@ -151,25 +286,206 @@ impl DAProvider for InMemoryDA {
         let blob_ref = BlobRef {
             id: id.clone(),
             domain_id: domain_id.to_string(),
-            size_bytes: blob_bytes.len(),
-            commitment: commitment.clone(),
+            size_bytes: commitment.blob_len,
+            commitment,
         };
         self.meta.lock().unwrap().insert(id.clone(), blob_ref.clone());
-        Ok(blob_ref)
+        self.anchors.lock().unwrap().insert(
+            id,
+            BlobAnchor {
+                height: None,
+                submitted_at_ms: now_millis(),
+            },
+        );
+        blob_ref
+    }
+
+    fn shard_blob(&self, blob_bytes: &[u8]) -> anyhow::Result<(Vec<Vec<u8>>, DACommitment)> {
+        let cfg = &self.config;
+        let rs = ReedSolomon::new(cfg.data_shards, cfg.parity_shards)
+            .map_err(|e| anyhow::anyhow!("invalid erasure config: {e:?}"))?;
+        let shard_size = shard_size_for(cfg, blob_bytes.len());
+
+        let mut shards = Vec::with_capacity(cfg.data_shards + cfg.parity_shards);
+        for i in 0..cfg.data_shards {
+            let mut shard = vec![0u8; shard_size];
+            let start = (i * shard_size).min(blob_bytes.len());
+            let end = ((i + 1) * shard_size).min(blob_bytes.len());
+            shard[..end - start].copy_from_slice(&blob_bytes[start..end]);
+            shards.push(shard);
+        }
+        shards.resize(cfg.data_shards + cfg.parity_shards, vec![0u8; shard_size]);
+        rs.encode(&mut shards)
+            .map_err(|e| anyhow::anyhow!("erasure encoding failed: {e:?}"))?;
+
+        let commitment = DACommitment {
+            root: shard_root(&shards),
+            total_shards: shards.len(),
+            data_shards: cfg.data_shards,
+            parity_shards: cfg.parity_shards,
+            shard_size,
+            blob_len: blob_bytes.len(),
+        };
+        Ok((shards, commitment))
+    }
+}
+
+/// Blobs larger than data_shards * shard_size widen the shards instead of
+/// adding more of them, so the erasure geometry stays fixed.
+fn shard_size_for(cfg: &DAConfig, blob_len: usize) -> usize {
+    cfg.shard_size
+        .max(blob_len.div_ceil(cfg.data_shards.max(1)))
+        .max(1)
+}
+
+/// Incremental erasure coding of a blob whose length is known up front.
+/// Holds one data shard and the parity shards at a time; completed data
+/// shards are handed back to the caller as soon as they fill up.
+pub struct BlobEncoder {
+    rs: ReedSolomon,
+    data_shards: usize,
+    parity_shards: usize,
+    shard_size: usize,
+    blob_len: usize,
+    written: usize,
+    current: Vec<u8>,
+    parity: Vec<Vec<u8>>,
+    leaf_hashes: Vec<Hash>,
+}
+
+impl BlobEncoder {
+    pub fn new(config: &DAConfig, blob_len: usize) -> anyhow::Result<Self> {
+        let rs = ReedSolomon::new(config.data_shards, config.parity_shards)
+            .map_err(|e| anyhow::anyhow!("invalid erasure config: {e:?}"))?;
+        let shard_size = shard_size_for(config, blob_len);
+        Ok(Self {
+            rs,
+            data_shards: config.data_shards,
+            parity_shards: config.parity_shards,
+            shard_size,
+            blob_len,
+            written: 0,
+            current: Vec::with_capacity(shard_size),
+            parity: vec![vec![0u8; shard_size]; config.parity_shards],
+            leaf_hashes: Vec::with_capacity(config.data_shards + config.parity_shards),
+        })
+    }
+
+    pub fn shard_size(&self) -> usize {
+        self.shard_size
+    }
+
+    /// Feed the next bytes of the blob. Returns the data shards completed by
+    /// this chunk, in index order.
+    pub fn write(&mut self, mut chunk: &[u8]) -> anyhow::Result<Vec<Vec<u8>>> {
+        if self.written + chunk.len() > self.blob_len {
+            anyhow::bail!("blob exceeds declared length of {} bytes", self.blob_len);
+        }
+        self.written += chunk.len();
+        let mut completed = Vec::new();
+        while !chunk.is_empty() {
+            let take = (self.shard_size - self.current.len()).min(chunk.len());
+            self.current.extend_from_slice(&chunk[..take]);
+            chunk = &chunk[take..];
+            if self.current.len() == self.shard_size {
+                completed.push(self.seal_data_shard()?);
+            }
+        }
+        Ok(completed)
+    }
+
+    /// Zero-pad the remaining data shards and emit them with the parity
+    /// shards, together with the commitment over all of them.
+    pub fn finalize(mut self) -> anyhow::Result<(Vec<Vec<u8>>, DACommitment)> {
+        if self.written != self.blob_len {
+            anyhow::bail!(
+                "blob ended after {} of {} declared bytes",
+                self.written,
+                self.blob_len
+            );
+        }
+        let mut remaining = Vec::with_capacity(self.data_shards + self.parity_shards);
+        while self.leaf_hashes.len() < self.data_shards {
+            self.current.resize(self.shard_size, 0);
+            remaining.push(self.seal_data_shard()?);
+        }
+        for shard in std::mem::take(&mut self.parity) {
+            self.leaf_hashes.push(*blake3::hash(&shard).as_bytes());
+            remaining.push(shard);
+        }
+        let commitment = DACommitment {
+            root: merkle_root(&self.leaf_hashes),
+            total_shards: self.leaf_hashes.len(),
+            data_shards: self.data_shards,
+            parity_shards: self.parity_shards,
+            shard_size: self.shard_size,
+            blob_len: self.blob_len,
+        };
+        Ok((remaining, commitment))
+    }
+
+    fn seal_data_shard(&mut self) -> anyhow::Result<Vec<u8>> {
+        let shard = std::mem::replace(&mut self.current, Vec::with_capacity(self.shard_size));
+        // Parity accumulates one data shard at a time, in index order.
+        self.rs
+            .encode_single_sep(self.leaf_hashes.len(), &shard, &mut self.parity)
+            .map_err(|e| anyhow::anyhow!("erasure encoding failed: {e:?}"))?;
+        self.leaf_hashes.push(*blake3::hash(&shard).as_bytes());
+        Ok(shard)
+    }
+}
+
+/// Streamed blob submission to an `InMemoryDA`, see [`InMemoryDA::begin_blob`].
+pub struct BlobWriter {
+    da: InMemoryDA,
+    domain_id: String,
+    encoder: BlobEncoder,
+    shards: Vec<Vec<u8>>,
+}
+
+impl BlobWriter {
+    pub fn write(&mut self, chunk: &[u8]) -> anyhow::Result<()> {
+        let completed = self.encoder.write(chunk)?;
+        self.shards.extend(completed);
+        Ok(())
+    }
+
+    pub fn finalize(mut self) -> anyhow::Result<BlobRef> {
+        let (remaining, commitment) = self.encoder.finalize()?;
+        self.shards.extend(remaining);
+        // The blob is served back from its data shards, so the bytes are not
+        // kept a second time.
+        Ok(self.da.store_blob(&self.domain_id, None, self.shards, commitment))
+    }
+}
+
+#[async_trait]
+impl DAProvider for InMemoryDA {
+    async fn submit_blob(&self, domain_id: &str, blob_bytes: &[u8]) -> anyhow::Result<BlobRef> {
+        let (shards, commitment) = self.shard_blob(blob_bytes)?;
+        Ok(self.store_blob(domain_id, Some(blob_bytes.to_vec()), shards, commitment))
     }
 
     async fn get_blob(&self, blob_id: &str) -> anyhow::Result<Vec<u8>> {
-        let guard = self.inner.lock().unwrap();
-        guard
-            .get(blob_id)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("blob not found"))
+        if let Some(blob) = self.inner.lock().unwrap().get(blob_id).cloned() {
+            return Ok(blob);
+        }
+        let commitment = self.commitments.lock().unwrap().get(blob_id).cloned();
+        let shards = self.shards.lock().unwrap();
+        match (commitment, shards.get(blob_id)) {
+            (Some(commitment), Some(shards)) => {
+                let mut blob: Vec<u8> = shards[..commitment.data_shards].concat();
+                blob.truncate(commitment.blob_len);
+                Ok(blob)
+            }
+            _ => Err(self.missing_blob(blob_id)),
+        }
     }
 
     async fn prove_blob_availability(&self, blob_id: &str) -> anyhow::Result<DAProof> {
         let shards_guard = self.shards.lock().unwrap();
         let Some(shards) = shards_guard.get(blob_id) else {
-            anyhow::bail!("blob not found");
+            return Err(self.missing_blob(blob_id));
         };
         let commitment = self
             .commitments
@@ -178,8 +494,9 @@ impl DAProvider for InMemoryDA {
             .get(blob_id)
             .cloned()
             .ok_or_else(|| anyhow::anyhow!("commitment missing"))?;
-        let sample_count = (commitment.data_shards.max(1)).min(shards.len());
-        let proofs = derive_sample_proofs(shards, &commitment, sample_count);
+        let indices =
+            sample_indices(&commitment.root, shards.len(), commitment.data_shards.max(1));
+        let proofs = derive_sample_proofs(shards, &indices);
         Ok(DAProof {
             blob_id: blob_id.to_string(),
             commitment,
@@ -187,6 +504,22 @@ impl DAProvider for InMemoryDA {
         })
     }
 
+    async fn prove_samples(&self, blob_id: &str, indices: &[usize]) -> anyhow::Result<DAProof> {
+        let commitment = self.get_commitment(blob_id).await?;
+        let shards_guard = self.shards.lock().unwrap();
+        let Some(shards) = shards_guard.get(blob_id) else {
+            return Err(self.missing_blob(blob_id));
+        };
+        if let Some(idx) = indices.iter().find(|&&idx| idx >= shards.len()) {
+            anyhow::bail!("shard index {idx} out of range");
+        }
+        Ok(DAProof {
+            blob_id: blob_id.to_string(),
+            commitment,
+            samples: derive_sample_proofs(shards, indices),
+        })
+    }
+
     async fn get_commitment(&self, blob_id: &str) -> anyhow::Result<DACommitment> {
         self.commitments
             .lock()
@@ -195,53 +528,109 @@ impl DAProvider for InMemoryDA {
             .cloned()
             .ok_or_else(|| anyhow::anyhow!("commitment missing"))
     }
+
+    async fn reconstruct_blob(
+        &self,
+        blob_id: &str,
+        shards: Vec<(usize, Vec<u8>)>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let commitment = self.get_commitment(blob_id).await?;
+        reconstruct_from_shards(&commitment, shards)
+    }
+
+    async fn anchor_blob(&self, blob_id: &str, height: u64) -> anyhow::Result<()> {
+        let mut anchors = self.anchors.lock().unwrap();
+        let Some(anchor) = anchors.get_mut(blob_id) else {
+            anyhow::bail!("blob not found");
+        };
+        anchor.height = Some(height);
+        Ok(())
+    }
+
+    async fn prune_before(&self, height: u64) -> anyhow::Result<PruneStats> {
+        Ok(self.prune_where(|anchor| anchor.height.is_some_and(|h| h < height)))
+    }
+}
+
+/// Recover the original blob bytes from a subset of shards and check the
+/// rebuilt shard set against the commitment root.
+pub fn reconstruct_from_shards(
+    commitment: &DACommitment,
+    shards: Vec<(usize, Vec<u8>)>,
+) -> anyhow::Result<Vec<u8>> {
+    let rs = ReedSolomon::new(commitment.data_shards, commitment.parity_shards)
+        .map_err(|e| anyhow::anyhow!("invalid erasure config: {e:?}"))?;
+    let mut slots: Vec<Option<Vec<u8>>> = vec![None; commitment.total_shards];
+    for (index, bytes) in shards {
+        if index >= slots.len() {
+            anyhow::bail!("shard index {index} out of range");
+        }
+        if bytes.len() != commitment.shard_size {
+            anyhow::bail!("shard {index} has wrong size");
+        }
+        slots[index] = Some(bytes);
+    }
+    let present = slots.iter().filter(|s| s.is_some()).count();
+    if present < commitment.data_shards {
+        anyhow::bail!(
+            "need {} shards to reconstruct, got {}",
+            commitment.data_shards,
+            present
+        );
+    }
+    rs.reconstruct(&mut slots)
+        .map_err(|e| anyhow::anyhow!("reconstruction failed: {e:?}"))?;
+    let rebuilt: Vec<Vec<u8>> = slots.into_iter().flatten().collect();
+    if shard_root(&rebuilt) != commitment.root {
+        anyhow::bail!("reconstructed shards do not match commitment");
+    }
+    let mut blob: Vec<u8> = rebuilt
+        .into_iter()
+        .take(commitment.data_shards)
+        .flatten()
+        .collect();
+    blob.truncate(commitment.blob_len);
+    Ok(blob)
 }
 
 #[async_trait]
 impl DASampler for InMemoryDA {
-    async fn sample(&self, blob_id: &str, samples: usize) -> anyhow::Result<bool> {
-        let shards_guard = self.shards.lock().unwrap();
-        let Some(shards) = shards_guard.get(blob_id) else {
-            anyhow::bail!("blob not found");
-        };
-        let commitment = self
-            .commitments
-            .lock()
-            .unwrap()
-            .get(blob_id)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("commitment missing"))?;
-        let proofs = derive_sample_proofs(shards, &commitment, samples.max(1));
-        for sample in proofs {
-            if !verify_merkle_path(sample.shard_hash, &sample.merkle_path, &commitment.root, sample.shard_index) {
-                anyhow::bail!("invalid sampling proof");
-            }
+    async fn sample_with_seed(
+        &self,
+        blob_id: &str,
+        seed: Hash,
+        samples: usize,
+    ) -> anyhow::Result<bool> {
+        let commitment = self.get_commitment(blob_id).await?;
+        let indices = sample_indices(&seed, commitment.total_shards, samples.max(1));
+        let proof = self.prove_samples(blob_id, &indices).await?;
+        if proof.commitment.root != commitment.root {
+            anyhow::bail!("commitment mismatch");
+        }
+        if !verify_sampled_proof(&proof, &indices) {
+            anyhow::bail!("invalid sampling proof");
         }
         Ok(true)
     }
 }
 
-fn derive_sample_proofs(
-    shards: &[Vec<u8>],
-    commitment: &DACommitment,
-    samples: usize,
-) -> Vec<SampleProof> {
-    let mut rng = StdRng::seed_from_u64(42);
-    let mut proofs = Vec::new();
-    if shards.is_empty() {
-        return proofs;
-    }
-    for _ in 0..samples.min(shards.len()) {
-        let idx = rng.gen_range(0..shards.len());
-        let shard_hash = *blake3::hash(&shards[idx]).as_bytes();
-        let merkle_path = merkle_proof(shards, idx);
-        proofs.push(SampleProof {
+fn derive_sample_proofs(shards: &[Vec<u8>], indices: &[usize]) -> Vec<SampleProof> {
+    indices
+        .iter()
+        .map(|&idx| SampleProof {
             shard_index: idx,
-            shard_hash,
-            merkle_path,
-        });
-    }
-    proofs
+            shard_hash: *blake3::hash(&shards[idx]).as_bytes(),
+            merkle_path: merkle_proof(shards, idx),
+        })
+        .collect()
+}
+
+fn shard_root(shards: &[Vec<u8>]) -> Hash {
+    let leaf_hashes: Vec<Hash> = shards
+        .iter()
+        .map(|shard| *blake3::hash(shard).as_bytes())
+        .collect();
+    merkle_root(&leaf_hashes)
 }
 
 fn merkle_root(leaves: &[Hash]) -> Hash {
@@ -308,6 +697,17 @@ fn verify_merkle_path(leaf: Hash, path: &[Hash], root: &Hash, mut index: usize)
     &hash == root
 }
 
+/// Verify a proof answers exactly the challenged indices, in order.
+pub fn verify_sampled_proof(proof: &DAProof, indices: &[usize]) -> bool {
+    proof.samples.len() == indices.len()
+        && proof
+            .samples
+            .iter()
+            .zip(indices)
+            .all(|(sample, idx)| sample.shard_index == *idx)
+        && verify_da_proof(proof)
+}
+
 pub fn verify_da_proof(proof: &DAProof) -> bool {
     for sample in &proof.samples {
         if !verify_merkle_path(
@@ -322,3 +722,9 @@ pub fn verify_da_proof(proof: &DAProof) -> bool {
     true
 }
 
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,70 @@
+use da::{DAConfig, DAProvider, InMemoryDA};
+
+fn blob(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+#[tokio::test]
+async fn blob_recovers_with_up_to_parity_shards_missing() {
+    let da = InMemoryDA::new();
+    let bytes = blob(3000);
+    let blob_ref = da.submit_blob("l1", &bytes).await.unwrap();
+    let shards = da.shards(&blob_ref.id).unwrap();
+    let parity = blob_ref.commitment.parity_shards;
+    assert_eq!(shards.len(), blob_ref.commitment.total_shards);
+
+    for missing in 0..=parity {
+        // drop the first `missing` shards so data shards have to be rebuilt
+        let subset: Vec<(usize, Vec<u8>)> = shards
+            .iter()
+            .cloned()
+            .enumerate()
+            .skip(missing)
+            .collect();
+        let rebuilt = da.reconstruct_blob(&blob_ref.id, subset).await.unwrap();
+        assert_eq!(rebuilt, bytes);
+    }
+
+    let too_few: Vec<(usize, Vec<u8>)> = shards
+        .iter()
+        .cloned()
+        .enumerate()
+        .skip(parity + 1)
+        .collect();
+    assert!(da.reconstruct_blob(&blob_ref.id, too_few).await.is_err());
+}
+
+#[tokio::test]
+async fn oversized_blob_widens_shards_and_recovers() {
+    let da = InMemoryDA::with_config(DAConfig {
+        shard_size: 64,
+        data_shards: 4,
+        parity_shards: 3,
+        ..Default::default()
+    });
+    let bytes = blob(10_001);
+    let blob_ref = da.submit_blob("l1", &bytes).await.unwrap();
+    assert_eq!(blob_ref.commitment.total_shards, 7);
+    assert!(blob_ref.commitment.shard_size * 4 >= bytes.len());
+
+    let shards = da.shards(&blob_ref.id).unwrap();
+    let subset: Vec<(usize, Vec<u8>)> = shards
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| ![0, 2, 5].contains(i))
+        .collect();
+    let rebuilt = da.reconstruct_blob(&blob_ref.id, subset).await.unwrap();
+    assert_eq!(rebuilt, bytes);
+}
+
+#[tokio::test]
+async fn corrupted_shard_is_rejected() {
+    let da = InMemoryDA::new();
+    let bytes = blob(2048);
+    let blob_ref = da.submit_blob("l1", &bytes).await.unwrap();
+    let mut shards: Vec<(usize, Vec<u8>)> =
+        da.shards(&blob_ref.id).unwrap().into_iter().enumerate().collect();
+    shards.remove(0);
+    shards[0].1[0] ^= 0xFF;
+    assert!(da.reconstruct_blob(&blob_ref.id, shards).await.is_err());
+}
@@ -15,7 +15,8 @@ proptest! {
             prop_assert!(proof.samples.len() <= proof.commitment.total_shards);
             let sampled = da.sample(&blob.id, proof.samples.len()).await.unwrap();
             prop_assert!(sampled);
-        });
+            Ok(())
+        })?;
     }
 }
 
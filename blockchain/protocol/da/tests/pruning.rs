@@ -0,0 +1,58 @@
+use da::{DAConfig, DAProvider, InMemoryDA, RetentionPolicy};
+
+#[tokio::test]
+async fn prune_drops_data_but_keeps_commitments() {
+    let da = InMemoryDA::new();
+    let old = da.submit_blob("l1", &[1u8; 3000]).await.unwrap();
+    let recent = da.submit_blob("l1", &[2u8; 3000]).await.unwrap();
+    let unanchored = da.submit_blob("l1", &[3u8; 100]).await.unwrap();
+    da.anchor_blob(&old.id, 5).await.unwrap();
+    da.anchor_blob(&recent.id, 20).await.unwrap();
+    let before = da.storage_stats();
+    assert_eq!(before.stored_blobs, 3);
+
+    let stats = da.prune_before(10).await.unwrap();
+    assert_eq!(stats.blobs, 1);
+    assert!(stats.bytes_freed >= 3000);
+
+    let err = da.get_blob(&old.id).await.unwrap_err();
+    assert!(err.to_string().contains("pruned"));
+    assert!(da.prove_samples(&old.id, &[0]).await.is_err());
+    assert_eq!(da.get_commitment(&old.id).await.unwrap().root, old.commitment.root);
+    assert!(da.get_blob(&recent.id).await.is_ok());
+    assert!(da.get_blob(&unanchored.id).await.is_ok());
+
+    let after = da.storage_stats();
+    assert_eq!(after.stored_blobs, 2);
+    assert_eq!(after.commitments, 3);
+    assert_eq!(after.pruned_blobs, 1);
+    assert_eq!(
+        before.blob_bytes + before.shard_bytes - stats.bytes_freed,
+        after.blob_bytes + after.shard_bytes
+    );
+}
+
+#[tokio::test]
+async fn block_retention_window_follows_height() {
+    let da = InMemoryDA::with_config(DAConfig {
+        retention: RetentionPolicy::Blocks(10),
+        ..Default::default()
+    });
+    let blob = da.submit_blob("l1", &[9u8; 512]).await.unwrap();
+    da.anchor_blob(&blob.id, 100).await.unwrap();
+
+    assert_eq!(da.apply_retention(110).blobs, 0);
+    assert!(da.get_blob(&blob.id).await.is_ok());
+    assert_eq!(da.apply_retention(111).blobs, 1);
+    assert!(da.get_blob(&blob.id).await.is_err());
+    assert_eq!(da.apply_retention(200).blobs, 0);
+}
+
+#[tokio::test]
+async fn keep_all_never_prunes() {
+    let da = InMemoryDA::new();
+    let blob = da.submit_blob("l1", &[4u8; 64]).await.unwrap();
+    da.anchor_blob(&blob.id, 1).await.unwrap();
+    assert_eq!(da.apply_retention(u64::MAX).blobs, 0);
+    assert!(da.anchor_blob("missing", 1).await.is_err());
+}
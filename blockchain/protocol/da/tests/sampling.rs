@@ -0,0 +1,44 @@
+use da::{
+    challenge_seed, sample_indices, verify_sampled_proof, DAProvider, DASampler, InMemoryDA,
+};
+
+#[test]
+fn indices_are_distinct_and_seed_dependent() {
+    let a = sample_indices(&[1u8; 32], 6, 4);
+    assert_eq!(a, sample_indices(&[1u8; 32], 6, 4));
+    assert_eq!(a.len(), 4);
+    let mut dedup = a.clone();
+    dedup.sort();
+    dedup.dedup();
+    assert_eq!(dedup.len(), 4);
+    assert!(a.iter().all(|&i| i < 6));
+    assert_eq!(sample_indices(&[1u8; 32], 6, 10).len(), 6);
+
+    let seeds: Vec<Vec<usize>> = (0..8u8).map(|s| sample_indices(&[s; 32], 64, 4)).collect();
+    assert!(seeds.iter().any(|s| s != &seeds[0]));
+}
+
+#[tokio::test]
+async fn provider_answers_the_verifier_challenge() {
+    let da = InMemoryDA::new();
+    let blob = da.submit_blob("l1", &[7u8; 5000]).await.unwrap();
+    let seed = challenge_seed(&[3u8; 32], &blob.id);
+    let indices = sample_indices(&seed, blob.commitment.total_shards, 3);
+
+    let proof = da.prove_samples(&blob.id, &indices).await.unwrap();
+    assert!(verify_sampled_proof(&proof, &indices));
+    assert!(da.sample_with_seed(&blob.id, seed, 3).await.unwrap());
+
+    // A valid proof for other shards does not answer this challenge.
+    let other: Vec<usize> = (0..blob.commitment.total_shards)
+        .filter(|i| !indices.contains(i))
+        .take(3)
+        .collect();
+    let wrong = da.prove_samples(&blob.id, &other).await.unwrap();
+    assert!(!verify_sampled_proof(&wrong, &indices));
+
+    assert!(da
+        .prove_samples(&blob.id, &[blob.commitment.total_shards])
+        .await
+        .is_err());
+}
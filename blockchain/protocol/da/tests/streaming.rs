@@ -0,0 +1,80 @@
+use da::{BlobEncoder, DAConfig, DAProvider, DASampler, InMemoryDA};
+use proptest::prelude::*;
+use tokio::runtime::Runtime;
+
+fn small_config() -> DAConfig {
+    DAConfig {
+        shard_size: 32,
+        data_shards: 4,
+        parity_shards: 2,
+        ..Default::default()
+    }
+}
+
+proptest! {
+    #[test]
+    fn streamed_commitment_matches_one_shot(
+        blob in prop::collection::vec(any::<u8>(), 0..2048),
+        chunk in 1usize..300,
+    ) {
+        let rt = Runtime::new().expect("tokio runtime");
+        rt.block_on(async {
+            let da = InMemoryDA::with_config(small_config());
+            let one_shot = da.submit_blob("l1", &blob).await.unwrap();
+
+            let mut writer = da.begin_blob("l1", blob.len()).unwrap();
+            for piece in blob.chunks(chunk) {
+                writer.write(piece).unwrap();
+            }
+            let streamed = writer.finalize().unwrap();
+
+            prop_assert_eq!(streamed.commitment.root, one_shot.commitment.root);
+            prop_assert_eq!(streamed.commitment.shard_size, one_shot.commitment.shard_size);
+            prop_assert_eq!(streamed.size_bytes, blob.len());
+            prop_assert_eq!(da.shards(&streamed.id), da.shards(&one_shot.id));
+            prop_assert_eq!(da.get_blob(&streamed.id).await.unwrap(), blob);
+            Ok(())
+        })?;
+    }
+}
+
+#[test]
+fn encoder_emits_data_shards_as_they_fill() {
+    let cfg = small_config();
+    let mut encoder = BlobEncoder::new(&cfg, 100).unwrap();
+    assert!(encoder.write(&[1u8; 31]).unwrap().is_empty());
+    let done = encoder.write(&[2u8; 40]).unwrap();
+    assert_eq!(done.len(), 2);
+    assert!(done.iter().all(|s| s.len() == encoder.shard_size()));
+    assert_eq!(encoder.write(&[3u8; 29]).unwrap().len(), 1);
+
+    let (rest, commitment) = encoder.finalize().unwrap();
+    // The last four bytes land in a padded fourth data shard.
+    assert_eq!(rest.len(), 1 + cfg.parity_shards);
+    assert_eq!(commitment.total_shards, cfg.data_shards + cfg.parity_shards);
+    assert_eq!(commitment.blob_len, 100);
+}
+
+#[tokio::test]
+async fn declared_length_is_enforced() {
+    let da = InMemoryDA::with_config(small_config());
+    let mut writer = da.begin_blob("l1", 10).unwrap();
+    assert!(writer.write(&[0u8; 11]).is_err());
+    writer.write(&[0u8; 4]).unwrap();
+    assert!(writer.finalize().is_err());
+}
+
+#[tokio::test]
+async fn streamed_blobs_can_be_sampled() {
+    let da = InMemoryDA::new();
+    let bytes: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8).collect();
+    let mut writer = da.begin_blob("l1", bytes.len()).unwrap();
+    for piece in bytes.chunks(4096) {
+        writer.write(piece).unwrap();
+    }
+    let blob = writer.finalize().unwrap();
+    assert!(da.sample(&blob.id, 3).await.unwrap());
+    let stats = da.storage_stats();
+    assert_eq!(stats.stored_blobs, 1);
+    assert_eq!(stats.blob_bytes, 0);
+}
@@ -1,16 +1,27 @@
 use anyhow::Context;
-use consensus::{SignedProposal, SignedVote};
+use consensus::{HotStuffEngine, SignedProposal, SignedVote};
+use ed25519_dalek::SigningKey;
 use futures::StreamExt;
 use libp2p::{
     gossipsub,
-    gossipsub::{IdentTopic, MessageAuthenticity},
-    identity, multiaddr::Protocol, Multiaddr, PeerId, SwarmBuilder, SwarmEvent,
+    gossipsub::{
+        IdentTopic, MessageAcceptance, MessageAuthenticity, PeerScoreParams, PeerScoreThresholds,
+        TopicHash, TopicScoreParams,
+    },
+    identify, identity, kad, multiaddr::Protocol,
+    request_response::{self, OutboundRequestId, ProtocolSupport},
+    swarm::NetworkBehaviour,
+    Multiaddr, PeerId, StreamProtocol, SwarmBuilder, SwarmEvent,
 };
-use runtime::{Block, Tx};
+use runtime::{Block, Hash, Tx};
 use serde::{Deserialize, Serialize};
-use state::Validator;
-use std::sync::Arc;
-use tokio::sync::mpsc;
+use state::{SnapshotManifest, SnapshotStore, Validator};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
 use tracing::{debug, info, warn};
 
 #[derive(Debug, Clone)]
@@ -38,17 +49,78 @@ pub enum ConsensusMessage {
     },
 }
 
+/// A gossiped consensus message, tagged with the validator key its publisher
+/// proved it holds over the auth handshake. Until a proposal gate is
+/// installed messages from unauthenticated publishers are delivered untagged.
+#[derive(Debug, Clone)]
+pub struct InboundConsensus {
+    pub message: ConsensusMessage,
+    pub validator: Option<Vec<u8>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NetworkEnvelope {
     Consensus(ConsensusMessage),
     Tx(Tx),
+    /// Hashes of txs the publisher holds; bodies are pulled on demand.
+    TxAnnounce(Vec<Hash>),
+    /// Traffic for an additional chain hosted alongside the primary one.
+    Chain {
+        chain_id: String,
+        inner: Box<NetworkEnvelope>,
+    },
 }
 
 pub trait ConsensusNetwork: Send + Sync {
     fn broadcast(&self, msg: ConsensusMessage);
     fn broadcast_tx(&self, tx: &Tx);
+
+    /// Number of connected peers, if the transport tracks them.
+    fn peer_count(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Admission checks applied to gossiped proposals and votes before they are
+/// forwarded to peers or handed to the local engine.
+pub trait ProposalGate: Send + Sync {
+    fn check_proposal(&self, proposal: &SignedProposal) -> anyhow::Result<()>;
+
+    fn check_vote(&self, _vote: &SignedVote) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Whether `public_key` may publish on the consensus topics.
+    fn is_validator(&self, _public_key: &[u8]) -> bool {
+        true
+    }
 }
 
+impl ProposalGate for HotStuffEngine {
+    fn check_proposal(&self, proposal: &SignedProposal) -> anyhow::Result<()> {
+        self.check_leader_proof(proposal)
+    }
+
+    fn check_vote(&self, vote: &SignedVote) -> anyhow::Result<()> {
+        self.check_vote_signature(vote)
+    }
+
+    fn is_validator(&self, public_key: &[u8]) -> bool {
+        self.is_validator_key(public_key)
+    }
+}
+
+type SharedGate = Arc<RwLock<Option<Arc<dyn ProposalGate>>>>;
+
+#[derive(Clone)]
+struct ChainRoute {
+    consensus: mpsc::Sender<InboundConsensus>,
+    txs: mpsc::Sender<Tx>,
+    gate: SharedGate,
+}
+
+type ChainRoutes = Arc<RwLock<HashMap<String, ChainRoute>>>;
+
 #[derive(Default)]
 pub struct NoopConsensusNetwork;
 
@@ -62,11 +134,386 @@ impl ConsensusNetwork for NoopConsensusNetwork {
     }
 }
 
-const CONSENSUS_TOPIC: &str = "kova/consensus/1.0";
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SnapshotRequest {
+    /// `None` asks for the most recent snapshot the peer holds.
+    Manifest { height: Option<u64> },
+    Chunk { height: u64, index: u32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SnapshotResponse {
+    Manifest(Option<SnapshotManifest>),
+    Chunk(Option<Vec<u8>>),
+}
+
+/// A connected peer, with what it reported over identify once it has.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerInfo {
+    pub peer_id: String,
+    pub address: String,
+    pub agent_version: Option<String>,
+    pub protocol_version: Option<String>,
+    /// Validator key the peer proved it holds, hex encoded.
+    pub validator: Option<String>,
+    /// Tx announcements and bodies received from the peer, in bytes.
+    pub tx_bytes_in: u64,
+    /// Tx bodies served to the peer, in bytes.
+    pub tx_bytes_out: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxRequest {
+    pub hashes: Vec<Hash>,
+}
+
+/// Bodies for the requested hashes the peer still holds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxResponse {
+    pub txs: Vec<Tx>,
+}
+
+/// Fresh nonce a peer is asked to sign with its validator key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthChallenge {
+    pub nonce: [u8; 32],
+}
+
+/// Answer to an `AuthChallenge`; peers without a validator key send none.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidatorProof {
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+const BLOCKS_TOPIC: &str = "kova/blocks";
+/// Votes and timeouts.
+const VOTES_TOPIC: &str = "kova/votes";
+const TXS_TOPIC: &str = "kova/txs";
+const SNAPSHOT_PROTOCOL: &str = "/kova/snapshot/1.0";
+const KAD_PROTOCOL: &str = "/kova/kad/1.0";
+const TX_PULL_PROTOCOL: &str = "/kova/tx/1.0";
+/// How long a seen tx is remembered and its body served to peers.
+const SEEN_TX_TTL: Duration = Duration::from_secs(120);
+const MAX_SEEN_TXS: usize = 50_000;
+/// Most hashes carried by one announcement or pull request.
+const MAX_TX_PULL: usize = 256;
+/// Gossip does not track the chain's protocol version, so it lets untagged
+/// signatures through and leaves the cut-off to mempool admission.
+const GOSSIP_ACCEPTS_LEGACY: bool = true;
+const VALIDATOR_AUTH_PROTOCOL: &str = "/kova/validator-auth/1.0";
+const VALIDATOR_AUTH_DOMAIN: &[u8] = b"kova/validator-auth/v1\0";
+/// Protocol version peers announce over identify.
+const PROTOCOL_VERSION: &str = "/kova/1.0";
+/// How often the DHT is walked for new peers.
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(60);
+/// Discovered peers are only dialed while fewer than this are connected.
+const MAX_DIALED_PEERS: usize = 50;
+/// Peers whose addresses are kept across restarts.
+const MAX_KNOWN_PEERS: usize = 256;
+
+#[derive(NetworkBehaviour)]
+struct KovaBehaviour {
+    gossipsub: gossipsub::Behaviour,
+    snapshots: request_response::json::Behaviour<SnapshotRequest, SnapshotResponse>,
+    kademlia: kad::Behaviour<kad::store::MemoryStore>,
+    identify: identify::Behaviour,
+    validator_auth: request_response::json::Behaviour<AuthChallenge, Option<ValidatorProof>>,
+    tx_pull: request_response::json::Behaviour<TxRequest, TxResponse>,
+}
+
+/// Txs this node has published or received, kept for `SEEN_TX_TTL` so
+/// repeat announcements are not pulled again and peers can pull the bodies.
+#[derive(Default)]
+struct SeenTxs {
+    bodies: HashMap<Hash, (Instant, Tx)>,
+}
+
+impl SeenTxs {
+    fn contains(&self, hash: &Hash) -> bool {
+        self.bodies.contains_key(hash)
+    }
+
+    fn get(&self, hash: &Hash) -> Option<&Tx> {
+        self.bodies.get(hash).map(|(_, tx)| tx)
+    }
+
+    /// Returns whether the tx was new. Once full, new txs are only
+    /// remembered after expired ones make room.
+    fn insert(&mut self, hash: Hash, tx: Tx) -> bool {
+        if self.bodies.contains_key(&hash) {
+            return false;
+        }
+        if self.bodies.len() >= MAX_SEEN_TXS {
+            self.prune();
+        }
+        if self.bodies.len() < MAX_SEEN_TXS {
+            self.bodies.insert(hash, (Instant::now(), tx));
+        }
+        true
+    }
+
+    fn prune(&mut self) {
+        self.bodies.retain(|_, (seen, _)| seen.elapsed() < SEEN_TX_TTL);
+    }
+}
+
+/// Replaces tx bodies with announcements of their hashes, keeping the
+/// bodies for peers to pull. Txs already seen are not announced again.
+fn announce_txs(envelope: NetworkEnvelope, seen: &mut SeenTxs) -> Option<NetworkEnvelope> {
+    match envelope {
+        NetworkEnvelope::Tx(tx) => {
+            let hash = runtime::tx_hash(&tx);
+            seen.insert(hash, tx).then(|| NetworkEnvelope::TxAnnounce(vec![hash]))
+        }
+        NetworkEnvelope::Chain { chain_id, inner } => {
+            announce_txs(*inner, seen).map(|inner| NetworkEnvelope::Chain {
+                chain_id,
+                inner: Box::new(inner),
+            })
+        }
+        other => Some(other),
+    }
+}
+
+/// An announcement whose validation waits on pulling the bodies it lists,
+/// so it is only forwarded by peers able to serve them.
+struct PendingPull {
+    message_id: gossipsub::MessageId,
+    propagation_source: PeerId,
+    route: ChainRoute,
+    hashes: Vec<Hash>,
+}
+
+fn meter_tx_bytes(
+    connected: &RwLock<HashMap<PeerId, PeerInfo>>,
+    peer: &PeerId,
+    bytes_in: usize,
+    bytes_out: usize,
+) {
+    if let Some(info) = connected.write().unwrap().get_mut(peer) {
+        info.tx_bytes_in += bytes_in as u64;
+        info.tx_bytes_out += bytes_out as u64;
+    }
+}
+
+/// Bytes a validator signs to answer `nonce`. Binding the responder's peer
+/// id stops a proof from being replayed by another peer.
+fn validator_auth_bytes(peer_id: &PeerId, nonce: &[u8; 32]) -> Vec<u8> {
+    let mut bytes = VALIDATOR_AUTH_DOMAIN.to_vec();
+    bytes.extend_from_slice(&peer_id.to_bytes());
+    bytes.extend_from_slice(nonce);
+    bytes
+}
+
+/// Checks a peer's answer to `nonce` and returns the validator key it
+/// proved it holds. The key must be in the validator set of some hosted
+/// chain; chains with no gate installed yet accept any key.
+fn authenticate_validator(
+    gates: &[SharedGate],
+    peer: &PeerId,
+    nonce: &[u8; 32],
+    proof: ValidatorProof,
+) -> anyhow::Result<Vec<u8>> {
+    let msg = validator_auth_bytes(peer, nonce);
+    runtime::verify_signature_bytes(&proof.public_key, &proof.signature, &msg)?;
+    let member = gates.iter().any(|gate| {
+        gate.read()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|gate| gate.is_validator(&proof.public_key))
+    });
+    anyhow::ensure!(
+        member,
+        "{} is not a validator",
+        hex::encode(&proof.public_key)
+    );
+    Ok(proof.public_key)
+}
+
+/// Validator key proven by each peer over the auth handshake; `None` for
+/// peers that answered without one or with a proof that failed.
+type Authenticated = HashMap<PeerId, Option<Vec<u8>>>;
+
+/// Validator key that signed a consensus message.
+fn signer_key(msg: &ConsensusMessage) -> &[u8] {
+    match msg {
+        ConsensusMessage::Propose(proposal) => &proposal.public_key,
+        ConsensusMessage::Vote(vote) => &vote.voter.pubkey,
+        ConsensusMessage::Timeout { from, .. } => &from.pubkey,
+    }
+}
+
+type KnownPeers = BTreeMap<PeerId, Vec<Multiaddr>>;
+
+/// Peer addresses saved by an earlier run; a missing or unreadable file
+/// starts discovery from the bootstrap peers alone.
+fn load_known_peers(path: &Path) -> KnownPeers {
+    match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|err| {
+            warn!("ignoring known peers file {}: {err}", path.display());
+            KnownPeers::new()
+        }),
+        Err(_) => KnownPeers::new(),
+    }
+}
+
+fn save_known_peers(path: &Path, known: &KnownPeers) {
+    let result = serde_json::to_vec_pretty(known)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            Ok(std::fs::write(path, bytes)?)
+        });
+    if let Err(err) = result {
+        warn!("failed to save known peers to {}: {err}", path.display());
+    }
+}
+
+struct SnapshotCommand {
+    request: SnapshotRequest,
+    reply: oneshot::Sender<anyhow::Result<SnapshotResponse>>,
+}
 
 #[derive(Clone)]
 pub struct Libp2pConsensusNetwork {
     tx: mpsc::Sender<NetworkEnvelope>,
+    snapshot_tx: mpsc::Sender<SnapshotCommand>,
+    peer_count: Arc<AtomicUsize>,
+    connected: Arc<RwLock<HashMap<PeerId, PeerInfo>>>,
+    gate: SharedGate,
+    chains: ChainRoutes,
+}
+
+impl Libp2pConsensusNetwork {
+    /// Connected peers, ordered by peer id.
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        let mut peers: Vec<PeerInfo> = self.connected.read().unwrap().values().cloned().collect();
+        peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        peers
+    }
+
+    /// Proposals failing the gate are rejected at gossip validation, so they
+    /// are neither relayed nor delivered. Until a gate is installed every
+    /// well-formed proposal passes through.
+    pub fn set_proposal_gate(&self, gate: Arc<dyn ProposalGate>) {
+        *self.gate.write().unwrap() = Some(gate);
+    }
+
+    /// Multiplexes another chain over the same swarm and topics. Its messages
+    /// are wrapped in `NetworkEnvelope::Chain` and delivered to the returned
+    /// receivers only; peers not hosting the chain just relay them.
+    pub fn join_chain(
+        &self,
+        chain_id: &str,
+    ) -> (Arc<ChainNetwork>, mpsc::Receiver<InboundConsensus>, mpsc::Receiver<Tx>) {
+        let (consensus_tx, consensus_rx) = mpsc::channel(256);
+        let (tx_tx, tx_rx) = mpsc::channel(256);
+        let gate: SharedGate = Arc::new(RwLock::new(None));
+        self.chains.write().unwrap().insert(
+            chain_id.to_string(),
+            ChainRoute {
+                consensus: consensus_tx,
+                txs: tx_tx,
+                gate: gate.clone(),
+            },
+        );
+        let network = Arc::new(ChainNetwork {
+            chain_id: chain_id.to_string(),
+            tx: self.tx.clone(),
+            peer_count: self.peer_count.clone(),
+            gate,
+        });
+        (network, consensus_rx, tx_rx)
+    }
+
+    pub async fn request_snapshot(&self, request: SnapshotRequest) -> anyhow::Result<SnapshotResponse> {
+        let (reply, rx) = oneshot::channel();
+        self.snapshot_tx
+            .send(SnapshotCommand { request, reply })
+            .await
+            .context("network task stopped")?;
+        rx.await.context("snapshot request dropped")?
+    }
+
+    /// Downloads the snapshot at `height` and checks it against the manifest
+    /// hash committed in a trusted header before returning the chunks.
+    pub async fn fetch_snapshot(
+        &self,
+        height: u64,
+        expected_manifest: Hash,
+    ) -> anyhow::Result<(SnapshotManifest, Vec<Vec<u8>>)> {
+        let manifest = match self
+            .request_snapshot(SnapshotRequest::Manifest { height: Some(height) })
+            .await?
+        {
+            SnapshotResponse::Manifest(Some(m)) => m,
+            _ => anyhow::bail!("no peer served a manifest for height {height}"),
+        };
+        if manifest.hash() != expected_manifest {
+            anyhow::bail!("snapshot manifest does not match trusted header");
+        }
+        let mut chunks = Vec::with_capacity(manifest.chunk_count());
+        for index in 0..manifest.chunk_count() {
+            let chunk = match self
+                .request_snapshot(SnapshotRequest::Chunk { height, index: index as u32 })
+                .await?
+            {
+                SnapshotResponse::Chunk(Some(c)) => c,
+                _ => anyhow::bail!("missing snapshot chunk {index}"),
+            };
+            manifest.verify_chunk(index, &chunk)?;
+            chunks.push(chunk);
+        }
+        Ok((manifest, chunks))
+    }
+}
+
+/// Handle for a chain joined through `Libp2pConsensusNetwork::join_chain`.
+pub struct ChainNetwork {
+    chain_id: String,
+    tx: mpsc::Sender<NetworkEnvelope>,
+    peer_count: Arc<AtomicUsize>,
+    gate: SharedGate,
+}
+
+impl ChainNetwork {
+    pub fn set_proposal_gate(&self, gate: Arc<dyn ProposalGate>) {
+        *self.gate.write().unwrap() = Some(gate);
+    }
+
+    fn publish(&self, inner: NetworkEnvelope) {
+        let _ = self.tx.try_send(NetworkEnvelope::Chain {
+            chain_id: self.chain_id.clone(),
+            inner: Box::new(inner),
+        });
+    }
+}
+
+impl ConsensusNetwork for ChainNetwork {
+    fn broadcast(&self, msg: ConsensusMessage) {
+        self.publish(NetworkEnvelope::Consensus(msg));
+    }
+
+    fn broadcast_tx(&self, tx: &Tx) {
+        self.publish(NetworkEnvelope::Tx(tx.clone()));
+    }
+
+    fn peer_count(&self) -> Option<usize> {
+        Some(self.peer_count.load(Ordering::Relaxed))
+    }
+}
+
+fn serve_snapshot(store: &SnapshotStore, request: SnapshotRequest) -> SnapshotResponse {
+    match request {
+        SnapshotRequest::Manifest { height: Some(h) } => SnapshotResponse::Manifest(store.manifest(h)),
+        SnapshotRequest::Manifest { height: None } => SnapshotResponse::Manifest(store.latest_manifest()),
+        SnapshotRequest::Chunk { height, index } => {
+            SnapshotResponse::Chunk(store.chunk(height, index as usize))
+        }
+    }
 }
 
 impl ConsensusNetwork for Libp2pConsensusNetwork {
@@ -77,15 +524,35 @@ impl ConsensusNetwork for Libp2pConsensusNetwork {
     fn broadcast_tx(&self, tx: &Tx) {
         let _ = self.tx.try_send(NetworkEnvelope::Tx(tx.clone()));
     }
+
+    fn peer_count(&self) -> Option<usize> {
+        Some(self.peer_count.load(Ordering::Relaxed))
+    }
 }
 
+/// Starts the swarm and the task driving it. Peers are found through the
+/// bootstrap addresses, the addresses in `peers_file` saved by an earlier
+/// run and then the Kademlia DHT; `peers_file` is kept up to date with the
+/// addresses peers report over identify.
+///
+/// Txs are gossiped as announcements of their hashes; receivers pull the
+/// bodies they have not seen from the peer that relayed the announcement.
+///
+/// Every new peer is challenged to prove a validator key. Once a proposal
+/// gate is installed, consensus messages are only accepted from publishers
+/// that proved the key that signed them and that key is in the validator
+/// set. Only nodes given a `validator_key` join the votes topic, and peers
+/// that join it without proving one are blacklisted.
 pub async fn start_libp2p_consensus(
     keypair: identity::Keypair,
     listen_addr: Multiaddr,
     bootstrap: Vec<Multiaddr>,
+    snapshots: SnapshotStore,
+    peers_file: Option<PathBuf>,
+    validator_key: Option<SigningKey>,
 ) -> anyhow::Result<(
     Arc<Libp2pConsensusNetwork>,
-    mpsc::Receiver<ConsensusMessage>,
+    mpsc::Receiver<InboundConsensus>,
     mpsc::Receiver<Tx>,
 )> {
     let peer_id = PeerId::from(keypair.public());
@@ -96,59 +563,380 @@ pub async fn start_libp2p_consensus(
         MessageAuthenticity::Signed(keypair.clone()),
         gossipsub::ConfigBuilder::default()
             .validation_mode(gossipsub::ValidationMode::Strict)
+            .validate_messages()
             .mesh_n_low(4)
             .build()
             .context("building gossipsub config")?,
     )?;
-    let topic = IdentTopic::new(CONSENSUS_TOPIC);
-    gossipsub.subscribe(&topic)?;
+    let (score_params, score_thresholds) = peer_scoring();
+    gossipsub
+        .with_peer_score(score_params, score_thresholds)
+        .map_err(anyhow::Error::msg)?;
+    for topic in [BLOCKS_TOPIC, TXS_TOPIC] {
+        gossipsub.subscribe(&IdentTopic::new(topic))?;
+    }
+    let votes_topic = IdentTopic::new(VOTES_TOPIC).hash();
+    if validator_key.is_some() {
+        gossipsub.subscribe(&IdentTopic::new(VOTES_TOPIC))?;
+    }
+    let mut kademlia = kad::Behaviour::with_config(
+        peer_id,
+        kad::store::MemoryStore::new(peer_id),
+        kad::Config::new(StreamProtocol::new(KAD_PROTOCOL)),
+    );
+    kademlia.set_mode(Some(kad::Mode::Server));
+    let identify = identify::Behaviour::new(
+        identify::Config::new(PROTOCOL_VERSION.into(), keypair.public())
+            .with_agent_version(format!("kova/{}", env!("CARGO_PKG_VERSION"))),
+    );
+    let behaviour = KovaBehaviour {
+        gossipsub,
+        snapshots: request_response::json::Behaviour::new(
+            [(StreamProtocol::new(SNAPSHOT_PROTOCOL), ProtocolSupport::Full)],
+            request_response::Config::default(),
+        ),
+        kademlia,
+        identify,
+        validator_auth: request_response::json::Behaviour::new(
+            [(StreamProtocol::new(VALIDATOR_AUTH_PROTOCOL), ProtocolSupport::Full)],
+            request_response::Config::default(),
+        ),
+        tx_pull: request_response::json::Behaviour::new(
+            [(StreamProtocol::new(TX_PULL_PROTOCOL), ProtocolSupport::Full)],
+            request_response::Config::default(),
+        ),
+    };
 
-    let mut swarm = SwarmBuilder::with_tokio_executor(transport, gossipsub, peer_id).build();
+    let mut swarm = SwarmBuilder::with_tokio_executor(transport, behaviour, peer_id).build();
     swarm.listen_on(listen_addr)?;
     for addr in bootstrap {
         if swarm.dial(addr.clone()).is_ok() {
             info!("dialing bootstrap peer {}", addr);
         }
     }
+    let mut known = peers_file.as_deref().map(load_known_peers).unwrap_or_default();
+    known.remove(&peer_id);
+    for (peer, addrs) in &known {
+        for addr in addrs {
+            swarm.behaviour_mut().kademlia.add_address(peer, addr.clone());
+        }
+        if swarm.dial(*peer).is_ok() {
+            debug!("dialing known peer {peer}");
+        }
+    }
 
     let (publish_tx, mut publish_rx) = mpsc::channel::<NetworkEnvelope>(256);
-    let (consensus_tx, consensus_rx) = mpsc::channel::<ConsensusMessage>(256);
+    let (consensus_tx, consensus_rx) = mpsc::channel::<InboundConsensus>(256);
     let (tx_tx, tx_rx) = mpsc::channel::<Tx>(256);
-    let network = Arc::new(Libp2pConsensusNetwork { tx: publish_tx.clone() });
-    let topic_clone = topic.clone();
+    let (snapshot_tx, mut snapshot_rx) = mpsc::channel::<SnapshotCommand>(64);
+    let peer_count = Arc::new(AtomicUsize::new(0));
+    let connected: Arc<RwLock<HashMap<PeerId, PeerInfo>>> = Arc::new(RwLock::new(HashMap::new()));
+    let gate: SharedGate = Arc::new(RwLock::new(None));
+    let chains: ChainRoutes = Arc::new(RwLock::new(HashMap::new()));
+    let network = Arc::new(Libp2pConsensusNetwork {
+        tx: publish_tx.clone(),
+        snapshot_tx,
+        peer_count: peer_count.clone(),
+        connected: connected.clone(),
+        gate: gate.clone(),
+        chains: chains.clone(),
+    });
+    let primary = ChainRoute {
+        consensus: consensus_tx,
+        txs: tx_tx,
+        gate,
+    };
+    let mut peers: Vec<PeerId> = Vec::new();
+    let mut next_peer = 0usize;
+    let mut pending: HashMap<OutboundRequestId, oneshot::Sender<anyhow::Result<SnapshotResponse>>> =
+        HashMap::new();
+    let mut discovery = tokio::time::interval(DISCOVERY_INTERVAL);
+    let mut authenticated = Authenticated::new();
+    let mut challenges: HashMap<OutboundRequestId, (PeerId, [u8; 32])> = HashMap::new();
+    let mut vote_subscribers: HashSet<PeerId> = HashSet::new();
+    let local_peer_id = peer_id;
+    let txs_topic = IdentTopic::new(TXS_TOPIC).hash();
+    let mut seen = SeenTxs::default();
+    let mut pulls: HashMap<OutboundRequestId, PendingPull> = HashMap::new();
+    let mut prune_seen = tokio::time::interval(SEEN_TX_TTL / 4);
 
     tokio::spawn(async move {
         loop {
             tokio::select! {
+                _ = discovery.tick() => {
+                    // Fails only while the routing table is still empty.
+                    let _ = swarm.behaviour_mut().kademlia.bootstrap();
+                }
+                _ = prune_seen.tick() => seen.prune(),
                 maybe_msg = publish_rx.recv() => {
                     if let Some(msg) = maybe_msg {
-                        match serde_json::to_vec(&msg) {
+                        let Some(msg) = announce_txs(msg, &mut seen) else { continue };
+                        if validator_key.is_none() && topic_for(&msg) != TXS_TOPIC {
+                            debug!("not publishing {} msg without a validator key", topic_for(&msg));
+                            continue;
+                        }
+                        match bincode::serialize(&msg) {
                             Ok(bytes) => {
-                                if let Err(err) = swarm.behaviour_mut().publish(topic_clone.clone(), bytes) {
-                                    warn!("failed to publish consensus msg: {err}");
+                                let topic = IdentTopic::new(topic_for(&msg));
+                                if let Err(err) = swarm.behaviour_mut().gossipsub.publish(topic, bytes) {
+                                    warn!("failed to publish {} msg: {err}", topic_for(&msg));
                                 }
                             }
-                            Err(err) => warn!("serialize consensus msg failed: {err}"),
+                            Err(err) => warn!("serialize gossip msg failed: {err}"),
                         }
                     } else {
                         break;
                     }
                 }
+                maybe_cmd = snapshot_rx.recv() => {
+                    let Some(cmd) = maybe_cmd else { continue };
+                    if peers.is_empty() {
+                        let _ = cmd.reply.send(Err(anyhow::anyhow!("no connected peers")));
+                        continue;
+                    }
+                    let peer = peers[next_peer % peers.len()];
+                    next_peer = next_peer.wrapping_add(1);
+                    let id = swarm.behaviour_mut().snapshots.send_request(&peer, cmd.request);
+                    pending.insert(id, cmd.reply);
+                }
                 event = swarm.select_next_some() => {
                     match event {
-                        SwarmEvent::Behaviour(gossipsub::Event::Message { message, .. }) => {
-                            match serde_json::from_slice::<NetworkEnvelope>(&message.data) {
-                                Ok(NetworkEnvelope::Consensus(msg)) => {
-                                    if consensus_tx.send(msg).await.is_err() {
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::Snapshots(request_response::Event::Message { message, .. })) => {
+                            match message {
+                                request_response::Message::Request { request, channel, .. } => {
+                                    let response = serve_snapshot(&snapshots, request);
+                                    if swarm.behaviour_mut().snapshots.send_response(channel, response).is_err() {
+                                        warn!("failed to send snapshot response");
+                                    }
+                                }
+                                request_response::Message::Response { request_id, response } => {
+                                    if let Some(reply) = pending.remove(&request_id) {
+                                        let _ = reply.send(Ok(response));
+                                    }
+                                }
+                            }
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::Snapshots(request_response::Event::OutboundFailure { request_id, error, .. })) => {
+                            if let Some(reply) = pending.remove(&request_id) {
+                                let _ = reply.send(Err(anyhow::anyhow!("snapshot request failed: {error}")));
+                            }
+                        }
+                        SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. } => {
+                            if !peers.contains(&peer_id) {
+                                peers.push(peer_id);
+                            }
+                            peer_count.store(peers.len(), Ordering::Relaxed);
+                            connected.write().unwrap().entry(peer_id).or_insert_with(|| PeerInfo {
+                                peer_id: peer_id.to_string(),
+                                address: endpoint.get_remote_address().to_string(),
+                                agent_version: None,
+                                protocol_version: None,
+                                validator: None,
+                                tx_bytes_in: 0,
+                                tx_bytes_out: 0,
+                            });
+                            if !authenticated.contains_key(&peer_id) && !challenges.values().any(|(p, _)| p == &peer_id) {
+                                let nonce: [u8; 32] = rand::random();
+                                let id = swarm.behaviour_mut().validator_auth.send_request(&peer_id, AuthChallenge { nonce });
+                                challenges.insert(id, (peer_id, nonce));
+                            }
+                        }
+                        SwarmEvent::ConnectionClosed { peer_id, num_established, .. } => {
+                            if num_established == 0 {
+                                peers.retain(|p| p != &peer_id);
+                                connected.write().unwrap().remove(&peer_id);
+                                authenticated.remove(&peer_id);
+                                vote_subscribers.remove(&peer_id);
+                            }
+                            peer_count.store(peers.len(), Ordering::Relaxed);
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::Identify(identify::Event::Received { peer_id, info, .. })) => {
+                            if let Some(peer) = connected.write().unwrap().get_mut(&peer_id) {
+                                peer.agent_version = Some(info.agent_version.clone());
+                                peer.protocol_version = Some(info.protocol_version.clone());
+                            }
+                            for addr in &info.listen_addrs {
+                                swarm.behaviour_mut().kademlia.add_address(&peer_id, addr.clone());
+                            }
+                            let Some(path) = peers_file.as_deref() else { continue };
+                            let fits = known.contains_key(&peer_id) || known.len() < MAX_KNOWN_PEERS;
+                            if fits && !info.listen_addrs.is_empty() && known.get(&peer_id) != Some(&info.listen_addrs) {
+                                known.insert(peer_id, info.listen_addrs);
+                                save_known_peers(path, &known);
+                            }
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::ValidatorAuth(request_response::Event::Message { peer, message, .. })) => {
+                            match message {
+                                request_response::Message::Request { request, channel, .. } => {
+                                    let proof = validator_key.as_ref().map(|key| ValidatorProof {
+                                        public_key: key.verifying_key().to_bytes().to_vec(),
+                                        signature: runtime::sign_bytes(key, &validator_auth_bytes(&local_peer_id, &request.nonce)),
+                                    });
+                                    if swarm.behaviour_mut().validator_auth.send_response(channel, proof).is_err() {
+                                        warn!("failed to answer auth challenge from {peer}");
+                                    }
+                                }
+                                request_response::Message::Response { request_id, response } => {
+                                    let Some((peer, nonce)) = challenges.remove(&request_id) else { continue };
+                                    let gates: Vec<SharedGate> = std::iter::once(primary.gate.clone())
+                                        .chain(chains.read().unwrap().values().map(|route| route.gate.clone()))
+                                        .collect();
+                                    let key = response.and_then(|proof| {
+                                        match authenticate_validator(&gates, &peer, &nonce, proof) {
+                                            Ok(key) => Some(key),
+                                            Err(err) => {
+                                                warn!("invalid validator proof from {peer}: {err}");
+                                                None
+                                            }
+                                        }
+                                    });
+                                    if let Some(info) = connected.write().unwrap().get_mut(&peer) {
+                                        info.validator = key.as_ref().map(hex::encode);
+                                    }
+                                    authenticated.insert(peer, key);
+                                    if vote_subscribers.contains(&peer) {
+                                        restrict_votes_subscriber(&mut swarm, &authenticated, &peer);
+                                    }
+                                }
+                            }
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::ValidatorAuth(request_response::Event::OutboundFailure { request_id, error, .. })) => {
+                            if let Some((peer, _)) = challenges.remove(&request_id) {
+                                debug!("auth challenge to {peer} failed: {error}");
+                            }
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::TxPull(request_response::Event::Message { peer, message, .. })) => {
+                            match message {
+                                request_response::Message::Request { request, channel, .. } => {
+                                    let txs: Vec<Tx> = request
+                                        .hashes
+                                        .iter()
+                                        .take(MAX_TX_PULL)
+                                        .filter_map(|hash| seen.get(hash).cloned())
+                                        .collect();
+                                    let size = serde_json::to_vec(&txs).map(|b| b.len()).unwrap_or_default();
+                                    meter_tx_bytes(&connected, &peer, 0, size);
+                                    if swarm.behaviour_mut().tx_pull.send_response(channel, TxResponse { txs }).is_err() {
+                                        warn!("failed to serve txs to {peer}");
+                                    }
+                                }
+                                request_response::Message::Response { request_id, response } => {
+                                    let Some(pull) = pulls.remove(&request_id) else { continue };
+                                    let size = serde_json::to_vec(&response.txs).map(|b| b.len()).unwrap_or_default();
+                                    meter_tx_bytes(&connected, &peer, size, 0);
+                                    let mut acceptance = MessageAcceptance::Accept;
+                                    let mut received = HashSet::new();
+                                    for tx in response.txs {
+                                        let hash = runtime::tx_hash(&tx);
+                                        if !pull.hashes.contains(&hash) || runtime::verify_tx_signature(&tx, GOSSIP_ACCEPTS_LEGACY).is_err() {
+                                            debug!("{peer} served a tx that was not announced or is unsigned");
+                                            acceptance = MessageAcceptance::Reject;
+                                            break;
+                                        }
+                                        received.insert(hash);
+                                        if seen.insert(hash, tx.clone()) && pull.route.txs.send(tx).await.is_err() {
+                                            warn!("inbound tx channel closed");
+                                        }
+                                    }
+                                    // A peer that no longer holds some bodies is not at fault,
+                                    // but the announcement is not forwarded without them.
+                                    if matches!(acceptance, MessageAcceptance::Accept) && received.len() < pull.hashes.len() {
+                                        acceptance = MessageAcceptance::Ignore;
+                                    }
+                                    swarm.behaviour_mut().gossipsub.report_message_validation_result(
+                                        &pull.message_id,
+                                        &pull.propagation_source,
+                                        acceptance,
+                                    );
+                                }
+                            }
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::TxPull(request_response::Event::OutboundFailure { request_id, error, .. })) => {
+                            if let Some(pull) = pulls.remove(&request_id) {
+                                debug!("tx pull from {} failed: {error}", pull.propagation_source);
+                                swarm.behaviour_mut().gossipsub.report_message_validation_result(
+                                    &pull.message_id,
+                                    &pull.propagation_source,
+                                    MessageAcceptance::Ignore,
+                                );
+                            }
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::Gossipsub(gossipsub::Event::Subscribed { peer_id, topic })) => {
+                            if topic == votes_topic {
+                                vote_subscribers.insert(peer_id);
+                                restrict_votes_subscriber(&mut swarm, &authenticated, &peer_id);
+                            }
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::Gossipsub(gossipsub::Event::Unsubscribed { peer_id, topic })) => {
+                            if topic == votes_topic {
+                                vote_subscribers.remove(&peer_id);
+                            }
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::Kademlia(kad::Event::RoutingUpdated { peer, .. })) => {
+                            if peers.len() < MAX_DIALED_PEERS && !swarm.is_connected(&peer) {
+                                let _ = swarm.dial(peer);
+                            }
+                        }
+                        SwarmEvent::Behaviour(KovaBehaviourEvent::Gossipsub(gossipsub::Event::Message { propagation_source, message_id, message })) => {
+                            if message.topic == txs_topic {
+                                meter_tx_bytes(&connected, &propagation_source, message.data.len(), 0);
+                            }
+                            let (acceptance, delivery) = match bincode::deserialize::<NetworkEnvelope>(&message.data) {
+                                Ok(envelope) if !on_topic(&envelope, &message.topic) => {
+                                    (MessageAcceptance::Reject, None)
+                                }
+                                Ok(envelope) => {
+                                    let publisher = message.source.as_ref().and_then(|source| authenticated.get(source));
+                                    if publisher.is_none() && topic_for(&envelope) != TXS_TOPIC {
+                                        // Dialing the publisher runs the handshake, so later
+                                        // messages from it can be admitted.
+                                        if let Some(source) = message.source.filter(|s| s != &local_peer_id && !swarm.is_connected(s)) {
+                                            let _ = swarm.dial(source);
+                                        }
+                                    }
+                                    let publisher = publisher.cloned().flatten();
+                                    route_envelope(&primary, &chains, envelope, publisher)
+                                }
+                                Err(err) => {
+                                    warn!("failed to decode gossipsub msg: {err}");
+                                    (MessageAcceptance::Reject, None)
+                                }
+                            };
+                            if let Some((route, NetworkEnvelope::TxAnnounce(hashes), _)) = &delivery {
+                                let mut missing: Vec<Hash> = hashes.iter().filter(|h| !seen.contains(h)).copied().collect();
+                                missing.sort();
+                                missing.dedup();
+                                if !missing.is_empty() {
+                                    let id = swarm.behaviour_mut().tx_pull.send_request(
+                                        &propagation_source,
+                                        TxRequest { hashes: missing.clone() },
+                                    );
+                                    pulls.insert(id, PendingPull {
+                                        message_id,
+                                        propagation_source,
+                                        route: route.clone(),
+                                        hashes: missing,
+                                    });
+                                    continue;
+                                }
+                            }
+                            swarm.behaviour_mut().gossipsub.report_message_validation_result(
+                                &message_id,
+                                &propagation_source,
+                                acceptance,
+                            );
+                            match delivery {
+                                Some((route, NetworkEnvelope::Consensus(message), validator)) => {
+                                    if route.consensus.send(InboundConsensus { message, validator }).await.is_err() {
                                         warn!("inbound consensus channel closed");
                                     }
                                 }
-                                Ok(NetworkEnvelope::Tx(tx)) => {
-                                    if tx_tx.send(tx).await.is_err() {
+                                Some((route, NetworkEnvelope::Tx(tx), _)) => {
+                                    if seen.insert(runtime::tx_hash(&tx), tx.clone()) && route.txs.send(tx).await.is_err() {
                                         warn!("inbound tx channel closed");
                                     }
                                 }
-                                Err(err) => warn!("failed to decode gossipsub msg: {err}"),
+                                Some((_, NetworkEnvelope::TxAnnounce(_) | NetworkEnvelope::Chain { .. }, _)) | None => {}
                             }
                         }
                         SwarmEvent::NewListenAddr { address, .. } => {
@@ -170,6 +958,126 @@ pub async fn start_libp2p_consensus(
     Ok((network, consensus_rx, tx_rx))
 }
 
+/// Peers that joined the votes topic but answered the auth challenge
+/// without a validator key are blacklisted. Peers that proved a key stay,
+/// since the validator set may still come to include them.
+fn restrict_votes_subscriber(
+    swarm: &mut libp2p::Swarm<KovaBehaviour>,
+    authenticated: &Authenticated,
+    peer: &PeerId,
+) {
+    if let Some(None) = authenticated.get(peer) {
+        warn!("blacklisting {peer}: joined the votes topic without a validator key");
+        swarm.behaviour_mut().gossipsub.blacklist_peer(peer);
+    }
+}
+
+/// Picks the chain a gossiped envelope belongs to and runs its admission
+/// checks. Envelopes for chains this process does not host are relayed
+/// without delivery.
+fn route_envelope(
+    primary: &ChainRoute,
+    chains: &ChainRoutes,
+    envelope: NetworkEnvelope,
+    publisher: Option<Vec<u8>>,
+) -> (MessageAcceptance, Option<(ChainRoute, NetworkEnvelope, Option<Vec<u8>>)>) {
+    let (route, envelope) = match envelope {
+        NetworkEnvelope::Chain { inner, .. } if matches!(*inner, NetworkEnvelope::Chain { .. }) => {
+            return (MessageAcceptance::Reject, None);
+        }
+        NetworkEnvelope::Chain { chain_id, inner } => match chains.read().unwrap().get(&chain_id) {
+            Some(route) => (route.clone(), *inner),
+            None => return (MessageAcceptance::Accept, None),
+        },
+        other => (primary.clone(), other),
+    };
+    match validate_envelope(&route.gate, &envelope, publisher.as_deref()) {
+        MessageAcceptance::Accept => (MessageAcceptance::Accept, Some((route, envelope, publisher))),
+        rejected => (rejected, None),
+    }
+}
+
+/// Topic an envelope is published on, by what it carries.
+fn topic_for(envelope: &NetworkEnvelope) -> &'static str {
+    match envelope {
+        NetworkEnvelope::Consensus(ConsensusMessage::Propose(_)) => BLOCKS_TOPIC,
+        NetworkEnvelope::Consensus(_) => VOTES_TOPIC,
+        NetworkEnvelope::Tx(_) | NetworkEnvelope::TxAnnounce(_) => TXS_TOPIC,
+        NetworkEnvelope::Chain { inner, .. } => topic_for(inner),
+    }
+}
+
+fn on_topic(envelope: &NetworkEnvelope, topic: &TopicHash) -> bool {
+    IdentTopic::new(topic_for(envelope)).hash() == *topic
+}
+
+/// Rejected messages weigh heavily on the sending peer's score, so peers
+/// relaying invalid blocks, votes or txs are pruned from the mesh and then
+/// ignored. Quiet topics are not penalised for missing deliveries.
+fn peer_scoring() -> (PeerScoreParams, PeerScoreThresholds) {
+    let mut params = PeerScoreParams::default();
+    for (topic, weight) in [(BLOCKS_TOPIC, 1.0), (VOTES_TOPIC, 0.5), (TXS_TOPIC, 0.25)] {
+        let topic_params = TopicScoreParams {
+            topic_weight: weight,
+            mesh_message_deliveries_weight: 0.0,
+            mesh_failure_penalty_weight: 0.0,
+            invalid_message_deliveries_weight: -100.0,
+            invalid_message_deliveries_decay: 0.9,
+            ..Default::default()
+        };
+        params.topics.insert(IdentTopic::new(topic).hash(), topic_params);
+    }
+    let thresholds = PeerScoreThresholds {
+        gossip_threshold: -100.0,
+        publish_threshold: -500.0,
+        graylist_threshold: -1_000.0,
+        ..Default::default()
+    };
+    (params, thresholds)
+}
+
+/// Signature checks run before a message is propagated. Txs only need a
+/// valid signature; proposals and votes are checked by the chain's gate
+/// once one is installed, and must come from the validator that signed
+/// them. Messages from publishers that have not authenticated yet are
+/// ignored rather than rejected, so relaying peers are not penalised.
+fn validate_envelope(
+    gate: &SharedGate,
+    envelope: &NetworkEnvelope,
+    publisher: Option<&[u8]>,
+) -> MessageAcceptance {
+    let result = match envelope {
+        NetworkEnvelope::Tx(tx) => runtime::verify_tx_signature(tx, GOSSIP_ACCEPTS_LEGACY).map(|_| ()),
+        NetworkEnvelope::TxAnnounce(hashes) if hashes.is_empty() || hashes.len() > MAX_TX_PULL => {
+            Err(anyhow::anyhow!("announcement carries {} hashes", hashes.len()))
+        }
+        NetworkEnvelope::TxAnnounce(_) => Ok(()),
+        NetworkEnvelope::Consensus(msg) => {
+            let Some(gate) = gate.read().unwrap().clone() else {
+                return MessageAcceptance::Accept;
+            };
+            let signer = signer_key(msg);
+            if publisher != Some(signer) || !gate.is_validator(signer) {
+                debug!("ignoring {} msg not published by its validator", topic_for(envelope));
+                return MessageAcceptance::Ignore;
+            }
+            match msg {
+                ConsensusMessage::Propose(proposal) => gate.check_proposal(proposal),
+                ConsensusMessage::Vote(vote) => gate.check_vote(vote),
+                ConsensusMessage::Timeout { .. } => Ok(()),
+            }
+        }
+        NetworkEnvelope::Chain { .. } => Ok(()),
+    };
+    match result {
+        Ok(()) => MessageAcceptance::Accept,
+        Err(err) => {
+            debug!("dropping gossiped {}: {err}", topic_for(envelope));
+            MessageAcceptance::Reject
+        }
+    }
+}
+
 pub fn parse_multiaddr_list(addrs: &str) -> Vec<Multiaddr> {
     addrs
         .split(',')
@@ -183,3 +1091,171 @@ pub fn parse_multiaddr_list(addrs: &str) -> Vec<Multiaddr> {
         .collect()
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer_id(seed: u8) -> PeerId {
+        identity::Keypair::ed25519_from_bytes([seed; 32].to_vec())
+            .unwrap()
+            .public()
+            .to_peer_id()
+    }
+
+    struct ValidatorSet(Vec<Vec<u8>>);
+
+    impl ProposalGate for ValidatorSet {
+        fn check_proposal(&self, _proposal: &SignedProposal) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn is_validator(&self, public_key: &[u8]) -> bool {
+            self.0.iter().any(|key| key == public_key)
+        }
+    }
+
+    fn prove(key: &SigningKey, peer: &PeerId, nonce: &[u8; 32]) -> ValidatorProof {
+        ValidatorProof {
+            public_key: key.verifying_key().to_bytes().to_vec(),
+            signature: runtime::sign_bytes(key, &validator_auth_bytes(peer, nonce)),
+        }
+    }
+
+    fn gate_for(keys: &[&SigningKey]) -> SharedGate {
+        let set = ValidatorSet(
+            keys.iter()
+                .map(|k| k.verifying_key().to_bytes().to_vec())
+                .collect(),
+        );
+        Arc::new(RwLock::new(Some(Arc::new(set) as Arc<dyn ProposalGate>)))
+    }
+
+    #[test]
+    fn validators_authenticate_with_a_signed_challenge() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let (peer, nonce) = (peer_id(1), [3; 32]);
+        let gates = [gate_for(&[&key])];
+        let proven =
+            authenticate_validator(&gates, &peer, &nonce, prove(&key, &peer, &nonce)).unwrap();
+        assert_eq!(proven, key.verifying_key().to_bytes().to_vec());
+
+        // Before the chain installs its gate any key is taken at its word.
+        let ungated: [SharedGate; 1] = [Arc::new(RwLock::new(None))];
+        assert!(
+            authenticate_validator(&ungated, &peer, &nonce, prove(&key, &peer, &nonce)).is_ok()
+        );
+    }
+
+    #[test]
+    fn proofs_with_a_bad_signature_are_rejected() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let (peer, nonce) = (peer_id(1), [3; 32]);
+        let gates = [gate_for(&[&key])];
+
+        let mut forged = prove(&key, &peer, &nonce);
+        forged.signature[0] ^= 1;
+        assert!(authenticate_validator(&gates, &peer, &nonce, forged).is_err());
+
+        // A proof answers one challenge for one peer.
+        let stale = prove(&key, &peer, &[4; 32]);
+        assert!(authenticate_validator(&gates, &peer, &nonce, stale).is_err());
+        let replayed = prove(&key, &peer_id(2), &nonce);
+        assert!(authenticate_validator(&gates, &peer, &nonce, replayed).is_err());
+    }
+
+    #[test]
+    fn keys_outside_the_validator_set_are_rejected() {
+        let validator = SigningKey::from_bytes(&[7; 32]);
+        let outsider = SigningKey::from_bytes(&[8; 32]);
+        let (peer, nonce) = (peer_id(1), [3; 32]);
+        let proof = prove(&outsider, &peer, &nonce);
+        assert!(
+            authenticate_validator(&[gate_for(&[&validator])], &peer, &nonce, proof.clone())
+                .is_err()
+        );
+
+        // Validators of any hosted chain are let in.
+        let gates = [gate_for(&[&validator]), gate_for(&[&outsider])];
+        assert!(authenticate_validator(&gates, &peer, &nonce, proof).is_ok());
+    }
+
+    fn stake_tx(nonce: u64) -> Tx {
+        Tx {
+            chain_id: "kova-devnet".into(),
+            nonce,
+            gas_limit: 21_000,
+            max_fee: None,
+            max_priority_fee: None,
+            gas_price: Some(1),
+            payload: runtime::TxPayload::Stake { amount: 10 },
+            public_key: vec![],
+            signature: vec![],
+        }
+    }
+
+    #[test]
+    fn seen_txs_are_announced_once() {
+        let mut seen = SeenTxs::default();
+        let tx = stake_tx(0);
+        let hash = runtime::tx_hash(&tx);
+        let first = announce_txs(NetworkEnvelope::Tx(tx.clone()), &mut seen);
+        assert!(matches!(first, Some(NetworkEnvelope::TxAnnounce(hashes)) if hashes == [hash]));
+        assert!(seen.get(&hash).is_some());
+
+        assert!(announce_txs(NetworkEnvelope::Tx(tx.clone()), &mut seen).is_none());
+        let hosted = NetworkEnvelope::Chain {
+            chain_id: "hosted".into(),
+            inner: Box::new(NetworkEnvelope::Tx(tx)),
+        };
+        assert!(announce_txs(hosted, &mut seen).is_none());
+        assert!(announce_txs(NetworkEnvelope::Tx(stake_tx(1)), &mut seen).is_some());
+    }
+
+    #[test]
+    fn expired_txs_are_evicted_to_make_room() {
+        let mut seen = SeenTxs::default();
+        let expired = Instant::now().checked_sub(SEEN_TX_TTL).unwrap();
+        let mut stale_hash = [0; 32];
+        stale_hash[..8].copy_from_slice(&u64::MAX.to_be_bytes());
+        seen.bodies.insert(stale_hash, (expired, stake_tx(0)));
+        for i in 1..MAX_SEEN_TXS as u64 {
+            let mut hash = [0; 32];
+            hash[..8].copy_from_slice(&i.to_be_bytes());
+            seen.bodies.insert(hash, (Instant::now(), stake_tx(i)));
+        }
+
+        let tx = stake_tx(0);
+        let hash = runtime::tx_hash(&tx);
+        assert!(seen.insert(hash, tx));
+        assert!(seen.contains(&hash));
+        assert!(!seen.contains(&stale_hash));
+        assert_eq!(seen.bodies.len(), MAX_SEEN_TXS);
+
+        // With nothing expired the tx is still new, but is not remembered.
+        let tx = stake_tx(1);
+        let hash = runtime::tx_hash(&tx);
+        assert!(seen.insert(hash, tx));
+        assert!(!seen.contains(&hash));
+    }
+
+    #[test]
+    fn known_peers_survive_a_restart() {
+        let dir = std::env::temp_dir().join(format!("kova-peers-{}", rand::random::<u64>()));
+        let path = dir.join("known_peers.json");
+        assert!(load_known_peers(&path).is_empty());
+
+        let mut known = KnownPeers::new();
+        known.insert(peer_id(1), parse_multiaddr_list("/ip4/10.0.0.1/udp/9000"));
+        known.insert(
+            peer_id(2),
+            parse_multiaddr_list("/ip4/10.0.0.2/udp/9000,/dns4/node.kova.test/udp/9001"),
+        );
+        save_known_peers(&path, &known);
+        assert_eq!(load_known_peers(&path), known);
+
+        std::fs::write(&path, b"not json").unwrap();
+        assert!(load_known_peers(&path).is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+//! Additional chains hosted by the same process. Each one gets its own state
+//! store, DA layer and consensus engine; the libp2p swarm is shared through
+//! `Libp2pConsensusNetwork::join_chain` and RPC is namespaced under
+//! `/chains/:id`.
+
+use std::collections::HashSet;
+use std::env;
+use std::sync::Arc;
+
+use da::InMemoryDA;
+use networking::{ConsensusNetwork, Libp2pConsensusNetwork, NoopConsensusNetwork};
+use runtime::{bootstrap_state, load_genesis_from_file, ExecutionContext};
+use state::InMemoryStateStore;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::info;
+use zk_core::ZkBackend;
+
+use crate::{
+    create_node_with, da_config_from_env, persistence, proving, spawn_block_production,
+    spawn_p2p_consensus_listener, spawn_tx_gossip_listener, Node,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainSpec {
+    pub id: String,
+    pub genesis: Option<String>,
+}
+
+impl ChainSpec {
+    /// Chains without a genesis file start from the devnet genesis under
+    /// their own chain id.
+    fn load_context(&self) -> anyhow::Result<ExecutionContext<InMemoryStateStore>> {
+        let Some(path) = &self.genesis else {
+            let mut ctx = bootstrap_state();
+            ctx.chain_id = self.id.clone();
+            return Ok(ctx);
+        };
+        let ctx = load_genesis_from_file(path)?;
+        if ctx.chain_id != self.id {
+            anyhow::bail!(
+                "genesis {path} is for chain {}, expected {}",
+                ctx.chain_id,
+                self.id
+            );
+        }
+        Ok(ctx)
+    }
+}
+
+/// Reads `CHAINS`, e.g. `payments=/etc/kova/payments.json,games`.
+pub fn chain_specs_from_env() -> anyhow::Result<Vec<ChainSpec>> {
+    parse_chain_specs(&env::var("CHAINS").unwrap_or_default())
+}
+
+fn parse_chain_specs(raw: &str) -> anyhow::Result<Vec<ChainSpec>> {
+    let mut seen = HashSet::new();
+    let mut specs = Vec::new();
+    for entry in raw.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (id, genesis) = match entry.split_once('=') {
+            Some((id, path)) => (id.trim(), Some(path.trim().to_string())),
+            None => (entry, None),
+        };
+        let valid = id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if id.is_empty() || !valid {
+            anyhow::bail!("invalid chain id {id:?}");
+        }
+        if !seen.insert(id.to_string()) {
+            anyhow::bail!("chain {id} listed twice");
+        }
+        specs.push(ChainSpec {
+            id: id.to_string(),
+            genesis,
+        });
+    }
+    Ok(specs)
+}
+
+/// Builds the node for `spec`, recovers its block log and starts its
+/// production, timeout and gossip tasks. Without libp2p the chain runs
+/// standalone. Returns the node with its block production task.
+pub async fn start_hosted_chain(
+    node_id: &str,
+    spec: &ChainSpec,
+    p2p: Option<&Arc<Libp2pConsensusNetwork>>,
+    zk: Option<Arc<dyn ZkBackend>>,
+    shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<(Node, JoinHandle<()>)> {
+    let ctx = spec.load_context()?.with_zk(zk.clone());
+    let joined = p2p.map(|net| net.join_chain(&spec.id));
+    let network: Arc<dyn ConsensusNetwork + Send + Sync> = match &joined {
+        Some((chain, _, _)) => chain.clone(),
+        None => Arc::new(NoopConsensusNetwork),
+    };
+    let da = InMemoryDA::with_config(da_config_from_env());
+    let mut node = create_node_with(node_id, ctx, da, network, zk).await?;
+    node.shutdown = shutdown;
+    persistence::recover(&mut node).await?;
+    proving::start(&mut node)?;
+
+    let producer = spawn_block_production(node.clone());
+    tokio::spawn(node.consensus.clone().run_timeouts());
+    if let Some((chain, consensus_rx, tx_rx)) = joined {
+        chain.set_proposal_gate(Arc::new(node.consensus.clone()));
+        spawn_p2p_consensus_listener(node.clone(), consensus_rx);
+        spawn_tx_gossip_listener(node.clone(), tx_rx);
+    }
+    info!("hosting chain {}", spec.id);
+    Ok((node, producer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain_specs_parse_ids_and_optional_genesis() {
+        let specs = parse_chain_specs(" payments=/etc/payments.json , games ,").unwrap();
+        assert_eq!(
+            specs,
+            vec![
+                ChainSpec {
+                    id: "payments".into(),
+                    genesis: Some("/etc/payments.json".into()),
+                },
+                ChainSpec {
+                    id: "games".into(),
+                    genesis: None,
+                },
+            ]
+        );
+        assert!(parse_chain_specs("").unwrap().is_empty());
+        assert!(parse_chain_specs("a,a").is_err());
+        assert!(parse_chain_specs("bad/id").is_err());
+        assert!(parse_chain_specs("=genesis.json").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+//! Circuit breaker for state root divergence. A node whose local execution
+//! disagrees with a block header stops producing and voting until an operator
+//! resumes it or state is resynced from peers.
+
+use std::collections::BTreeMap;
+use std::env;
+
+use runtime::{Address, Block, Hash, UnsupportedProtocolVersion};
+use serde::Serialize;
+use state::{ChainState, StateStore};
+use tracing::{error, info};
+
+use crate::{fork_choice, now_millis, publish_view, Node};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergencePolicy {
+    /// Stay halted until an operator calls `/divergence/resume` or `/divergence/resync`.
+    Halt,
+    /// Restore state from a peer snapshot when the diverged header commits to one.
+    Resync,
+}
+
+impl DivergencePolicy {
+    pub fn from_env() -> Self {
+        match env::var("DIVERGENCE_POLICY").as_deref() {
+            Ok("resync") => DivergencePolicy::Resync,
+            _ => DivergencePolicy::Halt,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DivergenceReport {
+    pub height: u64,
+    pub block_hash: Hash,
+    pub proposer: Address,
+    /// Root committed in the block header by the proposer.
+    pub expected_state_root: Hash,
+    /// Root produced by local execution of the same block.
+    pub local_state_root: Hash,
+    /// Section roots of the diverged local state; compare with `/state/components`
+    /// on a healthy peer to find the offending section.
+    pub local_components: BTreeMap<String, Hash>,
+    /// Sections whose roots differ from the last agreed state.
+    pub changed_components: Vec<String>,
+    pub snapshot_root: Option<Hash>,
+    pub detected_at_ms: u64,
+    #[serde(skip)]
+    block: Option<Block>,
+}
+
+impl DivergenceReport {
+    pub fn new(
+        block: &Block,
+        block_hash: Hash,
+        before: &ChainState,
+        diverged: &ChainState,
+        local_state_root: Hash,
+    ) -> Self {
+        let previous = before.component_roots();
+        let local_components = diverged.component_roots();
+        let changed_components = local_components
+            .iter()
+            .filter(|(name, root)| previous.get(*name) != Some(*root))
+            .map(|(name, _)| name.clone())
+            .collect();
+        Self {
+            height: block.header.height,
+            block_hash,
+            proposer: block.header.proposer_id,
+            expected_state_root: block.header.state_root,
+            local_state_root,
+            local_components,
+            changed_components,
+            snapshot_root: block.header.snapshot_root,
+            detected_at_ms: now_millis(),
+            block: Some(block.clone()),
+        }
+    }
+}
+
+/// Also true once the chain has moved to a protocol version this build
+/// can't execute, which only upgrading the binary clears.
+pub fn is_halted(node: &Node) -> bool {
+    node.divergence.lock().unwrap().is_some() || node.unsupported_version.lock().unwrap().is_some()
+}
+
+pub fn halt_unsupported(node: &Node, unsupported: UnsupportedProtocolVersion) {
+    error!("{unsupported}; halting until the node is upgraded");
+    *node.unsupported_version.lock().unwrap() = Some(unsupported);
+}
+
+pub fn trip(node: &Node, report: DivergenceReport) {
+    error!(
+        "state root divergence at height {}: header {} vs local {} (changed: {:?}); halting",
+        report.height,
+        hex::encode(report.expected_state_root),
+        hex::encode(report.local_state_root),
+        report.changed_components
+    );
+    *node.divergence.lock().unwrap() = Some(report);
+}
+
+/// Operator acknowledgement: clear the breaker without touching state.
+pub fn resume(node: &Node) -> Option<DivergenceReport> {
+    node.divergence.lock().unwrap().take()
+}
+
+/// Replace local state with the peers' snapshot of the diverged block and
+/// clear the breaker.
+pub async fn resync(node: &Node) -> anyhow::Result<()> {
+    let Some(report) = node.divergence.lock().unwrap().clone() else {
+        anyhow::bail!("node is not halted");
+    };
+    let Some(manifest_hash) = report.snapshot_root else {
+        anyhow::bail!(
+            "diverged block at height {} commits to no snapshot; restart with SNAPSHOT_CHECKPOINT",
+            report.height
+        );
+    };
+    let Some(net) = node.p2p.as_ref() else {
+        anyhow::bail!("resync requires the libp2p network");
+    };
+    let (manifest, chunks) = net.fetch_snapshot(report.height, manifest_hash).await?;
+    if manifest.state_root != report.expected_state_root {
+        anyhow::bail!("peer snapshot does not match the diverged header");
+    }
+    let chain = ChainState::restore_snapshot(&manifest, &chunks)?;
+    node.state.state.put_chain_state(chain).await?;
+    node.state.state.archive(report.height).await?;
+    publish_view(node).await?;
+    // The peers' version of the block becomes our tip, as if executed locally.
+    if let Some(block) = report.block {
+        if let Some(disk) = &node.disk {
+            disk.append(&block)?;
+        }
+        node.block_store.lock().unwrap().insert(report.block_hash, block.clone());
+        node.blocks.lock().unwrap().push(block);
+    }
+    node.applied.lock().unwrap().insert(report.block_hash);
+    fork_choice::reset(node);
+    *node.divergence.lock().unwrap() = None;
+    info!("resynced state from peers at height {}", report.height);
+    Ok(())
+}
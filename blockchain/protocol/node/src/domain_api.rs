@@ -0,0 +1,400 @@
+//! Read access to domain execution for fraud-proof tooling and explorers:
+//! committed and live roots, the last execution trace, the outbox and inbox
+//! receipts, and the bonded sequencers with the current rotation round. List endpoints page with `?from=&limit=` and attach Merkle
+//! proofs against the live domain root with `?proof=true`. Domain state at
+//! a past height can be fetched and calls replayed on it, to reproduce
+//! disputed executions.
+
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use runtime::{
+    CrossDomainMessage, DomainCall, DomainProof, DomainState, Hash, InboxReceipt, ReplayStep,
+    SequencerParams,
+};
+use serde::{Deserialize, Serialize};
+use state::{DomainRoot, SequencerBond, SequencerRound};
+use uuid::Uuid;
+
+use crate::Node;
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Default, Deserialize)]
+struct PageQuery {
+    from: Option<usize>,
+    limit: Option<usize>,
+    #[serde(default)]
+    proof: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct DomainPage<T> {
+    total: usize,
+    from: usize,
+    limit: usize,
+    next: Option<usize>,
+    /// Root the proofs verify against, and whether chain state has
+    /// committed to it yet.
+    root: Hash,
+    root_committed: bool,
+    items: Vec<T>,
+}
+
+#[derive(Debug, Serialize)]
+struct OutboxItem {
+    index: usize,
+    message: CrossDomainMessage,
+    proof: Option<DomainProof>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReceiptItem {
+    index: usize,
+    receipt: InboxReceipt,
+    /// Proves the source's consumed-nonce watermark, which covers this
+    /// receipt's nonce.
+    proof: Option<DomainProof>,
+}
+
+#[derive(Debug, Serialize)]
+struct TraceView {
+    state_root: Hash,
+    gas_used: u64,
+    events: Vec<String>,
+    proof: Option<serde_json::Value>,
+    trace: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct DomainRootView {
+    domain_id: Uuid,
+    committed: Option<DomainRoot>,
+    /// Root of the live domain state, including unconsumed inbox messages.
+    state_root: Hash,
+    latest_root: Option<Hash>,
+    last_trace: Option<TraceView>,
+}
+
+#[derive(Debug, Serialize)]
+struct SequencersView {
+    domain_id: Uuid,
+    /// Height the next block will have; sequencer coordinators derive the
+    /// round and slot from it.
+    height: u64,
+    rotation_blocks: u64,
+    failover_blocks: u64,
+    min_bond: u128,
+    round: Option<SequencerRound>,
+    bonds: Vec<SequencerBond>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapshotQuery {
+    height: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct SnapshotView {
+    domain_id: Uuid,
+    height: u64,
+    state_root: Hash,
+    state: DomainState,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplayRequest {
+    /// Height whose end state the calls run on.
+    height: u64,
+    calls: Vec<DomainCall>,
+    /// Height the calls see; the next block's by default.
+    block_height: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReplayView {
+    domain_id: Uuid,
+    pre_root: Hash,
+    steps: Vec<ReplayStep>,
+}
+
+pub fn routes(node: Node) -> Router {
+    Router::new()
+        .route(
+            "/domain/:id/root",
+            get({
+                let node = node.clone();
+                move |Path(id): Path<Uuid>| domain_root(node.clone(), id)
+            }),
+        )
+        .route(
+            "/domain/:id/outbox",
+            get({
+                let node = node.clone();
+                move |Path(id): Path<Uuid>, Query(q): Query<PageQuery>| {
+                    domain_outbox(node.clone(), id, q)
+                }
+            }),
+        )
+        .route(
+            "/domain/:id/receipts",
+            get({
+                let node = node.clone();
+                move |Path(id): Path<Uuid>, Query(q): Query<PageQuery>| {
+                    domain_receipts(node.clone(), id, q)
+                }
+            }),
+        )
+        .route(
+            "/domain/:id/sequencers",
+            get({
+                let node = node.clone();
+                move |Path(id): Path<Uuid>| domain_sequencers(node.clone(), id)
+            }),
+        )
+        .route(
+            "/domain/:id/snapshot",
+            get({
+                let node = node.clone();
+                move |Path(id): Path<Uuid>, Query(q): Query<SnapshotQuery>| {
+                    domain_snapshot(node.clone(), id, q)
+                }
+            }),
+        )
+        .route(
+            "/domain/:id/replay",
+            post({
+                let node = node.clone();
+                move |Path(id): Path<Uuid>, Json(req): Json<ReplayRequest>| {
+                    domain_replay(node.clone(), id, req)
+                }
+            }),
+        )
+}
+
+/// The committed root, or `NOT_FOUND` when the domain is unknown.
+fn committed_root(node: &Node, id: &Uuid) -> Result<Option<DomainRoot>, StatusCode> {
+    let chain = node.view.load();
+    if !chain.domains.contains_key(id) && !node.state.domains.has_domain(id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(chain.domain_roots.get(id).cloned())
+}
+
+async fn domain_root(node: Node, id: Uuid) -> Result<Json<DomainRootView>, StatusCode> {
+    let committed = committed_root(&node, &id)?;
+    let domains = &node.state.domains;
+    let last_trace = domains.last_trace(&id).map(|r| TraceView {
+        state_root: r.state_root,
+        gas_used: r.gas_used,
+        events: r.events,
+        proof: r.proof,
+        trace: r.trace,
+    });
+    Ok(Json(DomainRootView {
+        domain_id: id,
+        committed,
+        state_root: domains.state_root(&id),
+        latest_root: domains.latest_root(&id),
+        last_trace,
+    }))
+}
+
+async fn domain_outbox(
+    node: Node,
+    id: Uuid,
+    q: PageQuery,
+) -> Result<Json<DomainPage<OutboxItem>>, StatusCode> {
+    let committed = committed_root(&node, &id)?;
+    let state = node.state.domains.domain_state(&id);
+    let outbox = state.outbox.clone();
+    let page = paginate(&state, committed, outbox.len(), &q, |index| {
+        let message = outbox[index].clone();
+        let proof = q
+            .proof
+            .then(|| DomainState::message_leaf(&message).and_then(|leaf| state.prove(leaf)))
+            .flatten();
+        OutboxItem {
+            index,
+            message,
+            proof,
+        }
+    });
+    Ok(Json(page))
+}
+
+async fn domain_receipts(
+    node: Node,
+    id: Uuid,
+    q: PageQuery,
+) -> Result<Json<DomainPage<ReceiptItem>>, StatusCode> {
+    let committed = committed_root(&node, &id)?;
+    let state = node.state.domains.domain_state(&id);
+    let receipts = node.state.domains.inbox_receipts(&id);
+    let page = paginate(&state, committed, receipts.len(), &q, |index| {
+        let receipt = receipts[index].clone();
+        let proof = q
+            .proof
+            .then(|| receipt_proof(&state, &receipt))
+            .flatten();
+        ReceiptItem {
+            index,
+            receipt,
+            proof,
+        }
+    });
+    Ok(Json(page))
+}
+
+async fn domain_sequencers(node: Node, id: Uuid) -> Result<Json<SequencersView>, StatusCode> {
+    let chain = node.view.load();
+    let entry = chain.domains.get(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let params = SequencerParams::from_risk_params(&entry.risk_params)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(SequencersView {
+        domain_id: id,
+        height: crate::next_height(&node),
+        rotation_blocks: params.rotation_blocks,
+        failover_blocks: params.failover_blocks,
+        min_bond: params.min_bond,
+        round: chain.sequencer_rounds.get(&id).cloned(),
+        bonds: chain.sequencer_bonds.get(&id).cloned().unwrap_or_default(),
+    }))
+}
+
+async fn domain_snapshot(
+    node: Node,
+    id: Uuid,
+    q: SnapshotQuery,
+) -> Result<Json<SnapshotView>, (StatusCode, String)> {
+    committed_root(&node, &id).map_err(|code| (code, "unknown domain".into()))?;
+    let state = node
+        .state
+        .domains
+        .snapshot(&id, q.height)
+        .map_err(|err| (StatusCode::NOT_FOUND, err.to_string()))?;
+    Ok(Json(SnapshotView {
+        domain_id: id,
+        height: q.height,
+        state_root: state.root(),
+        state,
+    }))
+}
+
+async fn domain_replay(
+    node: Node,
+    id: Uuid,
+    req: ReplayRequest,
+) -> Result<Json<ReplayView>, (StatusCode, String)> {
+    committed_root(&node, &id).map_err(|code| (code, "unknown domain".into()))?;
+    let domains = &node.state.domains;
+    let pre_state = domains
+        .snapshot(&id, req.height)
+        .map_err(|err| (StatusCode::NOT_FOUND, err.to_string()))?;
+    let pre_root = pre_state.root();
+    let block_height = req.block_height.unwrap_or(req.height + 1);
+    let steps = domains
+        .replay(&id, pre_state, &req.calls, &node.state, block_height)
+        .await
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    Ok(Json(ReplayView {
+        domain_id: id,
+        pre_root,
+        steps,
+    }))
+}
+
+fn receipt_proof(state: &DomainState, receipt: &InboxReceipt) -> Option<DomainProof> {
+    let next = *state.processed_nonces.get(&receipt.from)?;
+    if next <= receipt.nonce {
+        return None;
+    }
+    state.prove(DomainState::nonce_leaf(&receipt.from, next))
+}
+
+fn paginate<T>(
+    state: &DomainState,
+    committed: Option<DomainRoot>,
+    total: usize,
+    q: &PageQuery,
+    item: impl Fn(usize) -> T,
+) -> DomainPage<T> {
+    let from = q.from.unwrap_or(0);
+    let limit = q.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let end = from.saturating_add(limit).min(total);
+    let root = state.root();
+    DomainPage {
+        total,
+        from,
+        limit,
+        next: (end < total).then_some(end),
+        root,
+        root_committed: committed.is_some_and(|c| c.state_root == root),
+        items: (from.min(end)..end).map(item).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(nonce: u64) -> CrossDomainMessage {
+        CrossDomainMessage {
+            from: Uuid::from_u128(1),
+            to: Uuid::from_u128(2),
+            nonce,
+            fee: 0,
+            payload: serde_json::json!({ "n": nonce }),
+            timeout_height: 0,
+            ack: None,
+        }
+    }
+
+    #[test]
+    fn outbox_pages_carry_verifiable_proofs() {
+        let state = DomainState {
+            outbox: (0..5).map(message).collect(),
+            ..Default::default()
+        };
+        let q = PageQuery {
+            from: Some(3),
+            limit: Some(10),
+            proof: true,
+        };
+        let page = paginate(&state, None, state.outbox.len(), &q, |index| {
+            let leaf = DomainState::message_leaf(&state.outbox[index]).unwrap();
+            (index, state.prove(leaf).unwrap())
+        });
+        assert_eq!(page.next, None);
+        assert!(!page.root_committed);
+        assert_eq!(page.items.len(), 2);
+        assert!(page.items.iter().all(|(_, proof)| proof.verify(&page.root)));
+
+        let first = paginate(&state, None, 5, &PageQuery { limit: Some(2), ..q }, |i| i);
+        assert_eq!(first.items, vec![0, 1]);
+        assert_eq!(first.next, Some(2));
+    }
+
+    #[test]
+    fn receipt_proofs_need_a_consumed_nonce() {
+        let from = Uuid::from_u128(1);
+        let mut state = DomainState::default();
+        state.processed_nonces.insert(from, 2);
+        let receipt = |nonce| InboxReceipt {
+            from,
+            nonce,
+            success: true,
+            gas_used: 0,
+            events: Vec::new(),
+            error: None,
+            block_height: 1,
+        };
+        let proof = receipt_proof(&state, &receipt(1)).unwrap();
+        assert!(proof.verify(&state.root()));
+        assert!(receipt_proof(&state, &receipt(2)).is_none());
+    }
+}
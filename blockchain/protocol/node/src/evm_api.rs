@@ -0,0 +1,283 @@
+//! EVM views of a domain for the `evm-rpc` facade: the chain id signed txs
+//! must carry, accounts, read-only calls, and receipts and logs of the
+//! signed txs the domain executed. Receipts and logs only cover calls
+//! executed since the node started.
+
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use runtime::{
+    evm_chain_id, DomainExecutionReceipt, EvmAccountView, EvmAdapter, EvmCallResult, Hash,
+};
+use serde::{Deserialize, Serialize};
+use state::DomainType;
+use uuid::Uuid;
+
+use crate::{next_height, Node};
+
+/// Most logs one `/evm/logs` query returns.
+const MAX_LOGS: usize = 10_000;
+
+#[derive(Debug, Serialize)]
+struct EvmDomainView {
+    domain_id: Uuid,
+    chain_id: u64,
+    /// Height the next block will have.
+    height: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct EvmTxView {
+    block_height: u64,
+    state_root: Hash,
+    gas_used: u64,
+    trace: serde_json::Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LogQuery {
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    address: Option<String>,
+    /// Only logs whose first topic is this one.
+    topic0: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct EvmLogView {
+    address: String,
+    topics: Vec<String>,
+    data: String,
+    block_height: u64,
+    tx_hash: String,
+    /// Position among the logs of its block.
+    log_index: usize,
+}
+
+pub fn routes(node: Node) -> Router {
+    Router::new()
+        .route(
+            "/domain/:id/evm",
+            get({
+                let node = node.clone();
+                move |Path(id): Path<Uuid>| evm_domain(node.clone(), id)
+            }),
+        )
+        .route(
+            "/domain/:id/evm/call",
+            post({
+                let node = node.clone();
+                move |Path(id): Path<Uuid>, Json(body): Json<serde_json::Value>| {
+                    evm_call(node.clone(), id, body)
+                }
+            }),
+        )
+        .route(
+            "/domain/:id/evm/account/:address",
+            get({
+                let node = node.clone();
+                move |Path((id, address)): Path<(Uuid, String)>| {
+                    evm_account(node.clone(), id, address)
+                }
+            }),
+        )
+        .route(
+            "/domain/:id/evm/tx/:hash",
+            get({
+                let node = node.clone();
+                move |Path((id, hash)): Path<(Uuid, String)>| evm_tx(node.clone(), id, hash)
+            }),
+        )
+        .route(
+            "/domain/:id/evm/logs",
+            get({
+                let node = node.clone();
+                move |Path(id): Path<Uuid>, Query(q): Query<LogQuery>| evm_logs(node.clone(), id, q)
+            }),
+        )
+}
+
+/// `NOT_FOUND` unless `id` is a registered EVM domain.
+fn ensure_evm(node: &Node, id: &Uuid) -> Result<(), StatusCode> {
+    let chain = node.view.load();
+    match chain.domains.get(id) {
+        Some(entry) if entry.kind == DomainType::EvmSharedSecurity => Ok(()),
+        _ => Err(StatusCode::NOT_FOUND),
+    }
+}
+
+async fn evm_domain(node: Node, id: Uuid) -> Result<Json<EvmDomainView>, StatusCode> {
+    ensure_evm(&node, &id)?;
+    Ok(Json(EvmDomainView {
+        domain_id: id,
+        chain_id: evm_chain_id(&id),
+        height: next_height(&node),
+    }))
+}
+
+async fn evm_call(
+    node: Node,
+    id: Uuid,
+    body: serde_json::Value,
+) -> Result<Json<EvmCallResult>, (StatusCode, String)> {
+    ensure_evm(&node, &id).map_err(|code| (code, "unknown evm domain".into()))?;
+    let state = node.state.domains.domain_state(&id);
+    EvmAdapter::new(id)
+        .simulate(&state, &body, next_height(&node))
+        .map(Json)
+        .map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, format!("{err:#}")))
+}
+
+async fn evm_account(
+    node: Node,
+    id: Uuid,
+    address: String,
+) -> Result<Json<EvmAccountView>, StatusCode> {
+    ensure_evm(&node, &id)?;
+    let state = node.state.domains.domain_state(&id);
+    EvmAdapter::account_view(&state, &address)
+        .map(Json)
+        .map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+async fn evm_tx(node: Node, id: Uuid, hash: String) -> Result<Json<EvmTxView>, StatusCode> {
+    ensure_evm(&node, &id)?;
+    node.state
+        .domains
+        .with_traces(&id, |traces| {
+            traces
+                .iter()
+                .find(|r| tx_hash(r).is_some_and(|h| h.eq_ignore_ascii_case(&hash)))
+                .map(|receipt| EvmTxView {
+                    block_height: receipt.trace["block_height"].as_u64().unwrap_or_default(),
+                    state_root: receipt.state_root,
+                    gas_used: receipt.gas_used,
+                    trace: receipt.trace.clone(),
+                })
+        })
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn evm_logs(node: Node, id: Uuid, q: LogQuery) -> Result<Json<Vec<EvmLogView>>, StatusCode> {
+    ensure_evm(&node, &id)?;
+    let logs = node
+        .state
+        .domains
+        .with_traces(&id, |traces| filter_logs(traces, &q));
+    if logs.len() > MAX_LOGS {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    Ok(Json(logs))
+}
+
+fn tx_hash(receipt: &DomainExecutionReceipt) -> Option<&str> {
+    receipt.trace["tx_hash"].as_str()
+}
+
+/// Logs of signed txs in `q`'s block range, narrowed to its address and
+/// first topic. Calls submitted as JSON have no tx hash and are skipped.
+fn filter_logs(traces: &[DomainExecutionReceipt], q: &LogQuery) -> Vec<EvmLogView> {
+    let matches = |want: &Option<String>, got: &str| {
+        want.as_ref()
+            .is_none_or(|want| want.eq_ignore_ascii_case(got))
+    };
+    let mut out = Vec::new();
+    let mut block = None;
+    let mut log_index = 0;
+    for receipt in traces {
+        let trace = &receipt.trace;
+        let block_height = trace["block_height"].as_u64().unwrap_or_default();
+        if block != Some(block_height) {
+            block = Some(block_height);
+            log_index = 0;
+        }
+        let logs = trace["logs"].as_array().map(Vec::as_slice).unwrap_or_default();
+        let first = log_index;
+        log_index += logs.len();
+        let in_range = q.from_block.is_none_or(|from| block_height >= from)
+            && q.to_block.is_none_or(|to| block_height <= to);
+        let Some(hash) = tx_hash(receipt).filter(|_| in_range) else {
+            continue;
+        };
+        for (i, log) in logs.iter().enumerate() {
+            let address = log["address"].as_str().unwrap_or_default();
+            let topics: Vec<String> = log["topics"]
+                .as_array()
+                .map(|topics| {
+                    topics
+                        .iter()
+                        .filter_map(|t| t.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let topic0 = topics.first().map(String::as_str).unwrap_or_default();
+            if !matches(&q.address, address) || !matches(&q.topic0, topic0) {
+                continue;
+            }
+            out.push(EvmLogView {
+                address: address.to_string(),
+                topics,
+                data: log["data"].as_str().unwrap_or("0x").to_string(),
+                block_height,
+                tx_hash: hash.to_string(),
+                log_index: first + i,
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runtime::DomainState;
+
+    fn receipt(block: u64, hash: Option<&str>, addresses: &[&str]) -> DomainExecutionReceipt {
+        let logs: Vec<serde_json::Value> = addresses
+            .iter()
+            .map(|a| serde_json::json!({ "address": a, "topics": ["0x01"], "data": "0x" }))
+            .collect();
+        DomainExecutionReceipt {
+            domain_id: Uuid::nil(),
+            state_root: [0u8; 32],
+            gas_used: 0,
+            events: Vec::new(),
+            proof: None,
+            trace: serde_json::json!({ "block_height": block, "tx_hash": hash, "logs": logs }),
+            state: DomainState::default(),
+        }
+    }
+
+    #[test]
+    fn logs_are_filtered_by_block_address_and_topic() {
+        let traces = vec![
+            receipt(1, Some("0xaa"), &["0xA1", "0xB2"]),
+            receipt(1, None, &["0xA1"]),
+            receipt(1, Some("0xbb"), &["0xa1"]),
+            receipt(2, Some("0xcc"), &["0xA1"]),
+        ];
+        let q = LogQuery {
+            to_block: Some(1),
+            address: Some("0xa1".into()),
+            ..Default::default()
+        };
+        let logs = filter_logs(&traces, &q);
+        let found: Vec<(&str, usize)> = logs
+            .iter()
+            .map(|l| (l.tx_hash.as_str(), l.log_index))
+            .collect();
+        // Indexes count the JSON call's log too, as it sits in the same block.
+        assert_eq!(found, vec![("0xaa", 0), ("0xbb", 3)]);
+
+        let q = LogQuery {
+            from_block: Some(2),
+            topic0: Some("0x02".into()),
+            ..Default::default()
+        };
+        assert!(filter_logs(&traces, &q).is_empty());
+    }
+}
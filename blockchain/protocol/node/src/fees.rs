@@ -0,0 +1,72 @@
+//! `/estimate_gas` and `/fee_suggestion`, so wallets can price transactions
+//! without hard-coding gas limits and fees.
+
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use runtime::{estimate_gas, suggest_fees, FeeSuggestion, GasEstimate, TxPayload};
+use serde::Deserialize;
+
+use crate::{next_height, parse_address, Node};
+
+const DEFAULT_FEE_HISTORY: usize = 20;
+const MAX_FEE_HISTORY: usize = 1_024;
+
+#[derive(Debug, Deserialize)]
+struct EstimateRequest {
+    /// Hex address the payload is simulated from.
+    from: String,
+    payload: TxPayload,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FeeHistoryQuery {
+    blocks: Option<usize>,
+}
+
+pub fn routes(node: Node) -> Router {
+    Router::new()
+        .route(
+            "/estimate_gas",
+            post({
+                let node = node.clone();
+                move |Json(body): Json<EstimateRequest>| estimate(node.clone(), body)
+            }),
+        )
+        .route(
+            "/fee_suggestion",
+            get({
+                let node = node.clone();
+                move |Query(q): Query<FeeHistoryQuery>| {
+                    let node = node.clone();
+                    async move { Json(fee_suggestion(&node, q.blocks)) }
+                }
+            }),
+        )
+}
+
+async fn estimate(
+    node: Node,
+    body: EstimateRequest,
+) -> Result<Json<GasEstimate>, (StatusCode, String)> {
+    let Some(sender) = parse_address(&body.from) else {
+        return Err((StatusCode::BAD_REQUEST, "invalid from address".into()));
+    };
+    let base = (*node.view.load()).clone();
+    estimate_gas(&node.state, base, sender, body.payload, next_height(&node))
+        .await
+        .map(Json)
+        .map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, format!("{err:#}")))
+}
+
+fn fee_suggestion(node: &Node, blocks: Option<usize>) -> FeeSuggestion {
+    let depth = blocks
+        .unwrap_or(DEFAULT_FEE_HISTORY)
+        .clamp(1, MAX_FEE_HISTORY);
+    let recent = node.blocks.lock().unwrap();
+    let start = recent.len().saturating_sub(depth);
+    suggest_fees(&recent[start..], node.state.base_fee)
+}
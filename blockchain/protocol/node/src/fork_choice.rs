@@ -0,0 +1,596 @@
+//! Block tree and fork choice. A proposal that doesn't build on the canonical
+//! head is kept on a side branch. The node switches to a branch when it grows
+//! longer than the canonical chain or when consensus commits a block on it:
+//! state is rolled back to the common ancestor and the branch is re-applied.
+//! Committed blocks are final, and nothing deeper than `MAX_REORG_DEPTH` is
+//! ever rolled back.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use runtime::{hash_block, Block, DomainCheckpoint, Hash};
+use state::{ChainState, StateStore};
+use tracing::{info, warn};
+
+use crate::subscriptions::NodeEvent;
+use crate::{apply_and_record, enqueue_tx, publish_view, tx_hash, Node};
+
+pub const MAX_REORG_DEPTH: usize = 64;
+
+/// State right after a block was applied, kept so the node can roll back to it.
+#[derive(Clone)]
+struct Snapshot {
+    chain: Arc<ChainState>,
+    domains: DomainCheckpoint,
+}
+
+struct TreeBlock {
+    block: Block,
+    /// Only set once the block has been applied on the canonical chain.
+    post: Option<Snapshot>,
+}
+
+/// Blocks above the last point the node can roll back to. The root is that
+/// point: the last finalized block, the block `MAX_REORG_DEPTH` below the
+/// head, or where the local chain starts.
+pub struct BlockTree {
+    root: Hash,
+    root_state: Snapshot,
+    /// Height of the first block on top of the root.
+    next_height: u64,
+    blocks: HashMap<Hash, TreeBlock>,
+    /// Canonical chain above the root, oldest first.
+    canonical: Vec<Hash>,
+    /// Ids consensus knows a block by when it differs from the hash of the
+    /// sealed block, as for blocks this node proposed.
+    aliases: HashMap<Hash, Hash>,
+}
+
+/// A move of the canonical chain from one branch to another.
+struct Switch {
+    ancestor: Hash,
+    restore: Snapshot,
+    /// Index into `canonical` of the first block that is rolled back.
+    keep: usize,
+    from: u64,
+    apply: Vec<Block>,
+}
+
+impl BlockTree {
+    fn new(root: Hash, next_height: u64, root_state: Snapshot) -> Self {
+        Self {
+            root,
+            root_state,
+            next_height,
+            blocks: HashMap::new(),
+            canonical: Vec::new(),
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Tree for a node that has applied no blocks yet.
+    pub fn genesis(chain: Arc<ChainState>, domains: DomainCheckpoint) -> Self {
+        Self::new([0u8; 32], 0, Snapshot { chain, domains })
+    }
+
+    pub fn head(&self) -> Hash {
+        self.canonical.last().copied().unwrap_or(self.root)
+    }
+
+    fn resolve(&self, id: &Hash) -> Option<Hash> {
+        let hash = self.aliases.get(id).copied().unwrap_or(*id);
+        (hash == self.root || self.blocks.contains_key(&hash)).then_some(hash)
+    }
+
+    /// Height a child of `hash` must have.
+    fn child_height(&self, hash: &Hash) -> Option<u64> {
+        if *hash == self.root {
+            return Some(self.next_height);
+        }
+        self.blocks.get(hash).map(|b| b.block.header.height + 1)
+    }
+
+    fn canonical_index(&self, hash: &Hash) -> Option<usize> {
+        self.canonical.iter().rposition(|h| h == hash)
+    }
+
+    fn post_state(&self, hash: &Hash) -> Option<Snapshot> {
+        if *hash == self.root {
+            return Some(self.root_state.clone());
+        }
+        self.blocks.get(hash)?.post.clone()
+    }
+
+    fn extend(&mut self, block: Block, hash: Hash, post: Snapshot) {
+        self.canonical.push(hash);
+        self.blocks.insert(
+            hash,
+            TreeBlock {
+                block,
+                post: Some(post),
+            },
+        );
+        if self.canonical.len() > MAX_REORG_DEPTH {
+            let new_root = self.canonical[self.canonical.len() - MAX_REORG_DEPTH - 1];
+            self.advance_root(new_root);
+        }
+    }
+
+    /// Stores a block off the canonical head and returns whether its branch
+    /// is now longer than the canonical chain.
+    fn insert_side(&mut self, block: Block, hash: Hash) -> anyhow::Result<bool> {
+        let parent = block.header.parent_hash;
+        let Some(height) = self.child_height(&parent) else {
+            anyhow::bail!("unknown parent {}", hex::encode(parent));
+        };
+        if block.header.height != height {
+            anyhow::bail!(
+                "block height {} does not follow its parent",
+                block.header.height
+            );
+        }
+        let longer = height >= self.next_height + self.canonical.len() as u64;
+        self.blocks
+            .entry(hash)
+            .or_insert(TreeBlock { block, post: None });
+        Ok(longer)
+    }
+
+    /// Highest block descending from `hash`, the lowest hash on a tie.
+    fn best_descendant(&self, hash: Hash) -> Hash {
+        let mut best = (self.child_height(&hash).unwrap_or(0), hash);
+        for (candidate, entry) in &self.blocks {
+            let height = entry.block.header.height + 1;
+            let better = height > best.0 || (height == best.0 && *candidate < best.1);
+            if better && self.descends_from(*candidate, hash) {
+                best = (height, *candidate);
+            }
+        }
+        best.1
+    }
+
+    fn descends_from(&self, mut hash: Hash, ancestor: Hash) -> bool {
+        loop {
+            if hash == ancestor {
+                return true;
+            }
+            match self.blocks.get(&hash) {
+                Some(entry) => hash = entry.block.header.parent_hash,
+                None => return false,
+            }
+        }
+    }
+
+    /// What it takes to make `tip` the canonical head.
+    fn switch_to(&self, tip: Hash) -> anyhow::Result<Switch> {
+        let mut apply = Vec::new();
+        let mut cursor = tip;
+        let keep = loop {
+            if cursor == self.root {
+                break 0;
+            }
+            if let Some(index) = self.canonical_index(&cursor) {
+                break index + 1;
+            }
+            let Some(entry) = self.blocks.get(&cursor) else {
+                anyhow::bail!("branch does not connect to the canonical chain");
+            };
+            apply.push(entry.block.clone());
+            cursor = entry.block.header.parent_hash;
+        };
+        apply.reverse();
+        let restore = self
+            .post_state(&cursor)
+            .ok_or_else(|| anyhow::anyhow!("no state kept for the common ancestor"))?;
+        Ok(Switch {
+            ancestor: cursor,
+            restore,
+            keep,
+            from: self.next_height + keep as u64,
+            apply,
+        })
+    }
+
+    /// Drops the canonical chain after `keep` and returns the dropped blocks.
+    fn rewind(&mut self, keep: usize) -> Vec<(Hash, Block)> {
+        self.canonical
+            .split_off(keep)
+            .into_iter()
+            .filter_map(|hash| {
+                let entry = self.blocks.get_mut(&hash)?;
+                entry.post = None;
+                Some((hash, entry.block.clone()))
+            })
+            .collect()
+    }
+
+    /// Makes canonical block `hash` the new root, forgetting everything that
+    /// doesn't descend from it.
+    fn advance_root(&mut self, hash: Hash) {
+        let Some(index) = self.canonical_index(&hash) else {
+            return;
+        };
+        let (Some(root_state), Some(next_height)) =
+            (self.post_state(&hash), self.child_height(&hash))
+        else {
+            return;
+        };
+        let mut ordered: Vec<(u64, Hash)> = self
+            .blocks
+            .iter()
+            .map(|(h, entry)| (entry.block.header.height, *h))
+            .collect();
+        ordered.sort_unstable();
+        let mut keep = HashSet::from([hash]);
+        for (_, h) in ordered {
+            if keep.contains(&self.blocks[&h].block.header.parent_hash) {
+                keep.insert(h);
+            }
+        }
+        keep.remove(&hash);
+        self.blocks.retain(|h, _| keep.contains(h));
+        self.aliases.retain(|_, h| keep.contains(h));
+        self.canonical.drain(..=index);
+        self.next_height = next_height;
+        self.root = hash;
+        self.root_state = root_state;
+    }
+}
+
+/// Roots the tree at the node's current head, as after startup or a resync.
+pub fn reset(node: &Node) {
+    let root = node
+        .blocks
+        .lock()
+        .unwrap()
+        .last()
+        .map(hash_block)
+        .or(node.anchor.map(|a| a.hash))
+        .unwrap_or([0u8; 32]);
+    let root_state = Snapshot {
+        chain: node.view.load(),
+        domains: node.state.domains.checkpoint(),
+    };
+    *node.tree.lock().unwrap() = BlockTree::new(root, crate::next_height(node), root_state);
+}
+
+/// Timestamp of the block `id` refers to, if the node still holds it.
+pub fn block_timestamp(node: &Node, id: &Hash) -> Option<u64> {
+    let from_tree = {
+        let tree = node.tree.lock().unwrap();
+        tree.resolve(id)
+            .and_then(|hash| tree.blocks.get(&hash))
+            .map(|b| b.block.header.timestamp)
+    };
+    from_tree.or_else(|| {
+        let store = node.block_store.lock().unwrap();
+        store.get(id).map(|b| b.header.timestamp)
+    })
+}
+
+pub fn extends_head(node: &Node, block: &Block) -> bool {
+    node.tree.lock().unwrap().head() == block.header.parent_hash
+}
+
+/// Adds a block that was just applied on top of the canonical head. `id` is
+/// the hash consensus refers to it by.
+pub fn record_head(node: &Node, block: &Block, id: Hash) {
+    let hash = hash_block(block);
+    let post = Snapshot {
+        chain: node.view.load(),
+        domains: node.state.domains.checkpoint(),
+    };
+    let mut tree = node.tree.lock().unwrap();
+    if id != hash {
+        tree.aliases.insert(id, hash);
+    }
+    tree.extend(block.clone(), hash, post);
+}
+
+/// Keeps a block that builds on something other than the head, switching to
+/// its branch when that branch is now the longest. Errors when the block
+/// stays on a side branch, so callers don't vote for it.
+pub async fn add_side_block(node: &Node, block: &Block) -> anyhow::Result<()> {
+    let hash = hash_block(block);
+    let longer = node.tree.lock().unwrap().insert_side(block.clone(), hash)?;
+    if !longer {
+        anyhow::bail!("block {} kept on a side branch", hex::encode(hash));
+    }
+    reorg(node, hash).await
+}
+
+/// Consensus committed `id`: switch to its branch if it isn't canonical and
+/// make it the root, since it can no longer be rolled back.
+pub async fn on_commit(node: &Node, id: Hash) {
+    let (hash, canonical) = {
+        let tree = node.tree.lock().unwrap();
+        let Some(hash) = tree.resolve(&id) else {
+            return;
+        };
+        (
+            hash,
+            hash == tree.root || tree.canonical_index(&hash).is_some(),
+        )
+    };
+    if !canonical {
+        let tip = node.tree.lock().unwrap().best_descendant(hash);
+        if let Err(err) = reorg(node, tip).await {
+            warn!(
+                "failed to switch to committed block {}: {err:#}",
+                hex::encode(hash)
+            );
+            return;
+        }
+    }
+    node.tree.lock().unwrap().advance_root(hash);
+}
+
+async fn reorg(node: &Node, tip: Hash) -> anyhow::Result<()> {
+    let (switch, old_head) = {
+        let tree = node.tree.lock().unwrap();
+        (tree.switch_to(tip)?, tree.head())
+    };
+    let orphaned = rollback(node, &switch, tip).await?;
+    if let Err(err) = apply_blocks(node, &switch.apply).await {
+        // Put the old branch back and drop the one that failed to apply.
+        rollback(node, &switch, old_head).await?;
+        {
+            let mut tree = node.tree.lock().unwrap();
+            for block in &switch.apply {
+                tree.blocks.remove(&hash_block(block));
+            }
+        }
+        let old: Vec<Block> = orphaned.into_iter().map(|(_, block)| block).collect();
+        apply_blocks(node, &old).await?;
+        return Err(err.context("reorg aborted"));
+    }
+
+    let included: HashSet<Hash> = switch
+        .apply
+        .iter()
+        .flat_map(|b| b.transactions.iter().map(tx_hash))
+        .collect();
+    for (_, block) in &orphaned {
+        for tx in &block.transactions {
+            if !included.contains(&tx_hash(tx)) {
+                enqueue_tx(node, tx.clone());
+            }
+        }
+    }
+    node.metrics.reorgs.inc();
+    info!(
+        "reorg at height {}: {} blocks orphaned, new head {}",
+        switch.from,
+        orphaned.len(),
+        hex::encode(tip)
+    );
+    Ok(())
+}
+
+/// Restores state to the common ancestor and forgets the canonical blocks
+/// after it, announcing the switch to `new_head`.
+async fn rollback(
+    node: &Node,
+    switch: &Switch,
+    new_head: Hash,
+) -> anyhow::Result<Vec<(Hash, Block)>> {
+    let (old_head, orphaned, mut ids) = {
+        let mut tree = node.tree.lock().unwrap();
+        let old_head = tree.head();
+        let orphaned = tree.rewind(switch.keep);
+        let ids: Vec<Hash> = tree
+            .aliases
+            .iter()
+            .filter(|(_, hash)| orphaned.iter().any(|(o, _)| o == *hash))
+            .map(|(id, _)| *id)
+            .collect();
+        (old_head, orphaned, ids)
+    };
+    ids.extend(orphaned.iter().map(|(hash, _)| *hash));
+    node.state
+        .state
+        .put_chain_state((*switch.restore.chain).clone())
+        .await?;
+    node.state.domains.restore(switch.restore.domains.clone());
+    node.state
+        .state
+        .archive(switch.from.saturating_sub(1))
+        .await?;
+    publish_view(node).await?;
+
+    let base = node.anchor.map(|a| a.height + 1).unwrap_or(0);
+    node.blocks
+        .lock()
+        .unwrap()
+        .truncate(switch.from.saturating_sub(base) as usize);
+    {
+        let mut applied = node.applied.lock().unwrap();
+        let mut tx_index = node.tx_index.lock().unwrap();
+        let mut receipts = node.receipts.lock().unwrap();
+        for id in &ids {
+            applied.remove(id);
+        }
+        for (_, block) in &orphaned {
+            for tx in &block.transactions {
+                tx_index.remove(&tx_hash(tx));
+                receipts.remove(&tx_hash(tx));
+            }
+        }
+    }
+    let _ = node.events.send(NodeEvent::Reorg {
+        height: switch.from,
+        common_ancestor: hex::encode(switch.ancestor),
+        old_head: hex::encode(old_head),
+        new_head: hex::encode(new_head),
+        orphaned: orphaned.iter().map(|(h, _)| hex::encode(h)).collect(),
+    });
+    Ok(orphaned)
+}
+
+async fn apply_blocks(node: &Node, blocks: &[Block]) -> anyhow::Result<()> {
+    for block in blocks {
+        apply_and_record(node, block, hash_block(block), false).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_block, create_node_with, execute_and_record};
+    use da::InMemoryDA;
+    use ed25519_dalek::SigningKey;
+    use networking::NoopConsensusNetwork;
+    use runtime::{
+        address_from_pubkey, devnet_genesis, from_genesis, sign_bytes, tx_signing_bytes,
+        GenesisConfig, Tx, TxPayload,
+    };
+
+    fn user() -> SigningKey {
+        SigningKey::from_bytes(&[4u8; 32])
+    }
+
+    async fn node(da: &InMemoryDA) -> anyhow::Result<Node> {
+        let user_pk = user().verifying_key().to_bytes();
+        let ctx = from_genesis(GenesisConfig {
+            initial_accounts: vec![(address_from_pubkey(&user_pk), 1_000_000)],
+            ..devnet_genesis()
+        })
+        .await?;
+        create_node_with(
+            "node-0",
+            ctx,
+            da.clone(),
+            Arc::new(NoopConsensusNetwork),
+            None,
+        )
+        .await
+    }
+
+    fn transfer(nonce: u64, amount: u128) -> Tx {
+        let mut tx = Tx {
+            chain_id: "kova-devnet".into(),
+            nonce,
+            gas_limit: 50_000,
+            max_fee: Some(1),
+            max_priority_fee: Some(0),
+            gas_price: None,
+            payload: TxPayload::Transfer {
+                to: [5u8; 32],
+                amount,
+            },
+            public_key: user().verifying_key().to_bytes().to_vec(),
+            signature: vec![],
+        };
+        tx.signature = sign_bytes(&user(), &tx_signing_bytes(&tx).unwrap());
+        tx
+    }
+
+    /// Builds and applies one block on `node` carrying `tx`.
+    async fn produce(node: &Node, tx: Tx) -> anyhow::Result<Block> {
+        crate::enqueue_tx(node, tx);
+        let block = build_block(node).await.expect("mempool has a tx");
+        Ok(execute_and_record(node, &block).await?.0)
+    }
+
+    async fn balance(node: &Node) -> anyhow::Result<u128> {
+        let account = node.state.state.get_account(&[5u8; 32]).await?;
+        Ok(account.map_or(0, |a| a.balance_x))
+    }
+
+    #[tokio::test]
+    async fn blocks_are_stamped_after_their_parent_and_not_far_ahead() -> anyhow::Result<()> {
+        let da = InMemoryDA::new();
+        let (a, replica) = (node(&da).await?, node(&da).await?);
+        let a1 = produce(&a, transfer(0, 10)).await?;
+        let a2 = produce(&a, transfer(1, 10)).await?;
+        execute_and_record(&replica, &a1).await?;
+
+        let mut stale = a2.clone();
+        stale.header.timestamp = a1.header.timestamp;
+        let err = execute_and_record(&replica, &stale).await.unwrap_err();
+        assert!(err.to_string().contains("not after its parent"), "{err}");
+
+        let mut early = a2.clone();
+        early.header.timestamp = crate::now_millis() + replica.max_clock_drift_ms + 60_000;
+        let err = execute_and_record(&replica, &early).await.unwrap_err();
+        assert!(err.to_string().contains("ahead of local time"), "{err}");
+
+        execute_and_record(&replica, &a2).await?;
+        assert_eq!(balance(&replica).await?, 20);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn longer_branch_replaces_the_canonical_chain() -> anyhow::Result<()> {
+        let da = InMemoryDA::new();
+        let (a, b, replica) = (node(&da).await?, node(&da).await?, node(&da).await?);
+        let a1 = produce(&a, transfer(0, 10)).await?;
+        let b1 = produce(&b, transfer(0, 20)).await?;
+        let b2 = produce(&b, transfer(1, 30)).await?;
+        let mut events = replica.events.subscribe();
+
+        execute_and_record(&replica, &a1).await?;
+        let kept = execute_and_record(&replica, &b1).await.unwrap_err();
+        assert!(kept.to_string().contains("side branch"));
+        assert_eq!(balance(&replica).await?, 10);
+
+        execute_and_record(&replica, &b2).await?;
+        assert_eq!(balance(&replica).await?, 50);
+        assert_eq!(
+            replica.state.state.commit().await?,
+            b.state.state.commit().await?
+        );
+        let heads: Vec<Hash> = replica
+            .blocks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(hash_block)
+            .collect();
+        assert_eq!(heads, vec![hash_block(&b1), hash_block(&b2)]);
+        assert_eq!(replica.view.load().accounts[&[5u8; 32]].balance_x, 50);
+        assert!(!replica
+            .tx_index
+            .lock()
+            .unwrap()
+            .contains_key(&tx_hash(&a1.transactions[0])));
+        assert_eq!(replica.metrics.reorgs.get(), 1);
+
+        let mut orphaned = None;
+        while let Ok(event) = events.try_recv() {
+            if let NodeEvent::Reorg {
+                height,
+                orphaned: o,
+                ..
+            } = event
+            {
+                assert_eq!(height, 0);
+                orphaned = Some(o);
+            }
+        }
+        assert_eq!(orphaned, Some(vec![hex::encode(hash_block(&a1))]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn commits_pick_the_branch_and_finalize_it() -> anyhow::Result<()> {
+        let da = InMemoryDA::new();
+        let (a, b, replica) = (node(&da).await?, node(&da).await?, node(&da).await?);
+        let a1 = produce(&a, transfer(0, 10)).await?;
+        let a2 = produce(&a, transfer(1, 10)).await?;
+        let b1 = produce(&b, transfer(0, 20)).await?;
+
+        execute_and_record(&replica, &a1).await?;
+        assert!(execute_and_record(&replica, &b1).await.is_err());
+        on_commit(&replica, hash_block(&b1)).await;
+        assert_eq!(balance(&replica).await?, 20);
+        assert_eq!(replica.tree.lock().unwrap().head(), hash_block(&b1));
+
+        // The committed block is final, so nothing can build beside it.
+        assert!(execute_and_record(&replica, &a2).await.is_err());
+        on_commit(&replica, hash_block(&a1)).await;
+        assert_eq!(balance(&replica).await?, 20);
+        Ok(())
+    }
+}
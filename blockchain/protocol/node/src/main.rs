@@ -1,39 +1,94 @@
 use axum::{
-    extract::{Path, Query},
+    extract::{ws::WebSocketUpgrade, Path, Query},
+    http::StatusCode,
     routing::{get, post},
     Json, Router,
 };
-use consensus::{sign_proposal, sign_vote, ConsensusEngine, HotStuffEngine, SignedProposal, SignedVote};
-use da::{DAProvider, InMemoryDA, verify_da_proof};
-use networking::{parse_multiaddr_list, start_libp2p_consensus, ConsensusMessage, ConsensusNetwork, NoopConsensusNetwork};
+use consensus::{
+    sign_proposal, sign_vote, sign_vote_bls, verify_proposal, ConsensusEngine, HotStuffEngine,
+    SignedProposal, SignedVote,
+};
+use da::{
+    challenge_seed, sample_indices, verify_sampled_proof, DAConfig, DAProvider, DASampler,
+    InMemoryDA, RetentionPolicy,
+};
+use networking::{
+    parse_multiaddr_list, start_libp2p_consensus, ConsensusMessage, ConsensusNetwork, InboundConsensus,
+    Libp2pConsensusNetwork, NoopConsensusNetwork,
+};
+use runtime::bls::BlsSecretKey;
 use runtime::{
     address_from_pubkey, apply_block, bootstrap_state, hash_block, load_genesis_from_file, verify_signature_bytes,
-    verify_tx_signature,
-    Block, BlockHeader, ExecutionContext, Hash, Tx,
+    verify_tx_signature, select_block_txs, sign_bytes, tx_hash, tx_root,
+    tx_signing_bytes, vote_messages, protocol_version_at, UnsupportedProtocolVersion, Block, BlockHeader, DoubleSignEvidence, ExecutionContext, ExecutionOutcome, Hash, Tx,
+    TxPayload,
 };
 use serde::{Deserialize, Serialize};
-use state::{ChainState, InMemoryStateStore, StateStore, Validator, ValidatorStatus};
+use state::{
+    Account, Address, ChainState, InMemoryStateStore, MerklePath, PrivacyPoolStats, ProposalQuery,
+    SnapshotStore, StateArchive, StateStore, StateView, Validator, ValidatorStatus,
+    VestingSchedule, DEFAULT_ARCHIVE_CHECKPOINT_INTERVAL, DEFAULT_PRIVACY_POOL,
+    DEFAULT_SNAPSHOT_CHUNK_SIZE,
+};
 use std::env;
 use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use tokio::net::TcpListener;
 use tokio::task::JoinHandle;
 use tokio::time::{self, Duration};
-use tokio::sync::{broadcast, mpsc};
-use tracing::{info, warn};
+use tokio::sync::{broadcast, mpsc, watch};
+use tracing::{debug, info, warn};
 use ed25519_dalek::SigningKey;
 use libp2p::{identity, Multiaddr};
+use metrics::Metrics;
 use blake3;
 use uuid::Uuid;
-use zk_core::{BlockProof, ProgramId, ProofRequest, ZkBackend};
+use zk_core::{ProgramId, ZkBackend};
 use zk_program_block;
 use zk_program_privacy;
 use zk_program_rollup;
+use zk_risczero::{Risc0Backend, Risc0Config, Risc0Program};
 use zk_sp1::{Sp1Backend, Sp1Config, Sp1Program};
 use std::fs;
 
+mod chains;
+mod divergence;
+mod domain_api;
+mod evm_api;
+mod fees;
+mod fork_choice;
+mod persistence;
+mod proving;
+mod rate_limit;
+mod subscriptions;
+
+use divergence::{DivergencePolicy, DivergenceReport};
+use fork_choice::BlockTree;
+use persistence::DiskStore;
+use subscriptions::{NodeEvent, EVENT_BUS_CAPACITY};
+
 const MEMPOOL_LIMIT: usize = 10_000;
+const DEFAULT_SNAPSHOT_INTERVAL: u64 = 1_000;
+/// How far ahead of the local clock a block's timestamp may run.
+const DEFAULT_MAX_CLOCK_DRIFT_MS: u64 = 15_000;
+const SNAPSHOT_FETCH_ATTEMPTS: u32 = 10;
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+/// Block the local chain starts from when bootstrapped from a snapshot
+/// instead of genesis.
+#[derive(Debug, Clone, Copy)]
+struct ChainAnchor {
+    height: u64,
+    hash: Hash,
+}
+
+#[derive(Deserialize)]
+struct TrustedCheckpoint {
+    header: BlockHeader,
+    block_hash: Hash,
+}
 
 #[derive(Clone)]
 struct Node {
@@ -41,17 +96,91 @@ struct Node {
     consensus: HotStuffEngine,
     da: InMemoryDA,
     state: ExecutionContext<InMemoryStateStore>,
+    /// Chain state as of the last applied block; RPC reads are served from here
+    /// so they never wait on block execution.
+    view: StateView,
     blocks: Arc<Mutex<Vec<Block>>>,
     mempool: Arc<Mutex<Vec<Tx>>>,
     local_validator: Option<Validator>,
     network: Arc<dyn ConsensusNetwork + Send + Sync>,
     tx_index: Arc<Mutex<HashMap<Hash, (Tx, u64)>>>,
+    receipts: Arc<Mutex<HashMap<Hash, TxReceipt>>>,
     block_store: Arc<Mutex<HashMap<Hash, Block>>>,
-    block_proofs: Arc<Mutex<HashMap<Hash, BlockProof>>>,
+    proving: proving::ProofQueue,
     applied: Arc<Mutex<HashSet<Hash>>>,
     signing_key: Arc<SigningKey>,
     verifying_key: Vec<u8>,
+    bls_key: Arc<BlsSecretKey>,
     zk: Option<Arc<dyn ZkBackend>>,
+    snapshots: SnapshotStore,
+    snapshot_interval: u64,
+    /// Blocks stamped further than this ahead of `now_millis()` are refused.
+    max_clock_drift_ms: u64,
+    anchor: Option<ChainAnchor>,
+    probes: ProbeConfig,
+    started_at: u64,
+    heartbeat: Arc<AtomicU64>,
+    last_zk_error: Arc<Mutex<Option<String>>>,
+    p2p: Option<Arc<Libp2pConsensusNetwork>>,
+    divergence: Arc<Mutex<Option<DivergenceReport>>>,
+    divergence_policy: DivergencePolicy,
+    /// Set once the chain activates a protocol version this build can't run.
+    unsupported_version: Arc<Mutex<Option<UnsupportedProtocolVersion>>>,
+    events: broadcast::Sender<NodeEvent>,
+    /// Recent blocks, including side branches, for fork choice.
+    tree: Arc<Mutex<BlockTree>>,
+    metrics: Metrics,
+    disk: Option<Arc<DiskStore>>,
+    /// Flips to `true` once the process is shutting down.
+    shutdown: watch::Receiver<bool>,
+}
+
+/// Thresholds for `/readyz` and `/livez`, tunable per deployment.
+#[derive(Debug, Clone)]
+struct ProbeConfig {
+    min_peers: usize,
+    max_block_age_ms: u64,
+    max_mempool: usize,
+    max_stall_ms: u64,
+}
+
+impl ProbeConfig {
+    fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(key: &str, default: T) -> T {
+            env::var(key).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+        Self {
+            min_peers: var("READY_MIN_PEERS", 0),
+            max_block_age_ms: var("READY_MAX_BLOCK_AGE_MS", 30_000),
+            max_mempool: var("READY_MAX_MEMPOOL", MEMPOOL_LIMIT * 8 / 10),
+            max_stall_ms: var("LIVE_MAX_STALL_MS", 60_000),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProbeCheck {
+    name: &'static str,
+    ok: bool,
+    detail: String,
+}
+
+#[derive(Serialize)]
+struct ProbeReport {
+    ok: bool,
+    checks: Vec<ProbeCheck>,
+}
+
+impl ProbeReport {
+    fn new(checks: Vec<ProbeCheck>) -> (StatusCode, Json<Self>) {
+        let ok = checks.iter().all(|c| c.ok);
+        let code = if ok {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        (code, Json(Self { ok, checks }))
+    }
 }
 
 #[derive(Clone)]
@@ -88,28 +217,87 @@ fn default_listen_addr() -> Multiaddr {
 
 async fn init_consensus_network(
     node_id: &str,
+    snapshots: SnapshotStore,
 ) -> (
     Arc<dyn ConsensusNetwork + Send + Sync>,
-    Option<mpsc::Receiver<ConsensusMessage>>,
+    Option<Arc<Libp2pConsensusNetwork>>,
+    Option<mpsc::Receiver<InboundConsensus>>,
     Option<mpsc::Receiver<Tx>>,
 ) {
     let listen = env::var("P2P_LISTEN").unwrap_or_else(|_| "/ip4/0.0.0.0/udp/9000/quic-v1".into());
     let listen_addr: Multiaddr = listen.parse().unwrap_or_else(|_| default_listen_addr());
     let bootstrap = env::var("P2P_BOOTSTRAP").unwrap_or_default();
-    let seed = derive_signing_key(node_id).to_bytes();
+    let validator_key = derive_signing_key(node_id);
+    let seed = validator_key.to_bytes();
     let keypair = identity::Keypair::ed25519_from_bytes(seed.to_vec())
         .unwrap_or_else(|_| identity::Keypair::generate_ed25519());
-    match start_libp2p_consensus(keypair, listen_addr, parse_multiaddr_list(&bootstrap)).await {
+    // Discovered peers outlive restarts when the node has a data directory.
+    let peers_file = env::var("DATA_DIR")
+        .ok()
+        .map(|dir| std::path::PathBuf::from(dir).join("peers.json"));
+    let bootstrap = parse_multiaddr_list(&bootstrap);
+    let started = start_libp2p_consensus(
+        keypair,
+        listen_addr,
+        bootstrap,
+        snapshots,
+        peers_file,
+        Some(validator_key),
+    )
+    .await;
+    match started {
         Ok((net, consensus_rx, tx_rx)) => (
-            net as Arc<dyn ConsensusNetwork + Send + Sync>,
+            net.clone() as Arc<dyn ConsensusNetwork + Send + Sync>,
+            Some(net),
             Some(consensus_rx),
             Some(tx_rx),
         ),
         Err(err) => {
             warn!("libp2p consensus fallback to noop: {err}");
-            (Arc::new(NoopConsensusNetwork::default()), None, None)
+            (Arc::new(NoopConsensusNetwork::default()), None, None, None)
+        }
+    }
+}
+
+/// Restores state from a peer snapshot matching the trusted checkpoint header
+/// so the node can start at that height instead of replaying from genesis.
+async fn bootstrap_from_snapshot(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    net: &Libp2pConsensusNetwork,
+    path: &str,
+) -> anyhow::Result<ChainAnchor> {
+    let checkpoint: TrustedCheckpoint = serde_json::from_slice(&fs::read(path)?)?;
+    let header = checkpoint.header;
+    let Some(manifest_hash) = header.snapshot_root else {
+        anyhow::bail!("checkpoint header at height {} carries no snapshot root", header.height);
+    };
+
+    let mut attempt = 0;
+    let (manifest, chunks) = loop {
+        match net.fetch_snapshot(header.height, manifest_hash).await {
+            Ok(found) => break found,
+            Err(err) if attempt + 1 < SNAPSHOT_FETCH_ATTEMPTS => {
+                warn!("snapshot fetch attempt {} failed: {err}", attempt + 1);
+                attempt += 1;
+                time::sleep(Duration::from_secs(2)).await;
+            }
+            Err(err) => return Err(err),
         }
+    };
+    if manifest.state_root != header.state_root {
+        anyhow::bail!("snapshot state root does not match checkpoint header");
     }
+    let chain = ChainState::restore_snapshot(&manifest, &chunks)?;
+    ctx.state.put_chain_state(chain).await?;
+    info!(
+        "restored state snapshot at height {} ({} chunks)",
+        header.height,
+        manifest.chunk_count()
+    );
+    Ok(ChainAnchor {
+        height: header.height,
+        hash: checkpoint.block_hash,
+    })
 }
 
 #[derive(Serialize)]
@@ -119,11 +307,25 @@ struct Status {
     view: u64,
 }
 
+/// `ZK_BACKEND` picks the prover when `ENABLE_ZK` is set: `sp1` (default)
+/// or `risc0`.
 fn init_zk_backend() -> Option<Arc<dyn ZkBackend>> {
     let enabled = env::var("ENABLE_ZK").unwrap_or_else(|_| "0".into());
     if enabled != "1" && enabled.to_lowercase() != "true" {
         return None;
     }
+    let backend = env::var("ZK_BACKEND").unwrap_or_else(|_| "sp1".into());
+    match backend.to_lowercase().as_str() {
+        "sp1" => Some(init_sp1_backend()),
+        "risc0" => init_risc0_backend(),
+        other => {
+            warn!("unknown ZK_BACKEND {other}; expected sp1 or risc0");
+            None
+        }
+    }
+}
+
+fn init_sp1_backend() -> Arc<dyn ZkBackend> {
     let block_elf = load_elf("ZK_SP1_BLOCK_ELF", "zk/artifacts/block.elf");
     let rollup_elf = load_elf("ZK_SP1_ROLLUP_ELF", "zk/artifacts/rollup.elf");
     let privacy_elf = load_elf("ZK_SP1_PRIVACY_ELF", "zk/artifacts/privacy.elf");
@@ -152,7 +354,54 @@ fn init_zk_backend() -> Option<Arc<dyn ZkBackend>> {
         programs,
         verify_only: false,
     });
-    Some(Arc::new(backend))
+    Arc::new(backend)
+}
+
+/// RISC Zero programs are registered by the image ID in
+/// `ZK_RISC0_<PROGRAM>_IMAGE_ID`; programs without one are left out.
+fn init_risc0_backend() -> Option<Arc<dyn ZkBackend>> {
+    let programs = [
+        (zk_program_block::program_id(), "block_transition", "BLOCK"),
+        (zk_program_rollup::program_id(), "rollup_batch", "ROLLUP"),
+        (zk_program_privacy::program_id(), "privacy_withdraw", "PRIVACY"),
+    ]
+    .into_iter()
+    .filter_map(|(id, name, key)| risc0_program(id, name, key))
+    .collect();
+    match Risc0Backend::new(Risc0Config {
+        programs,
+        verify_only: false,
+    }) {
+        Ok(backend) => Some(Arc::new(backend)),
+        Err(err) => {
+            warn!("RISC Zero backend disabled: {err}");
+            None
+        }
+    }
+}
+
+fn risc0_program(id: ProgramId, name: &str, key: &str) -> Option<Risc0Program> {
+    let image_key = format!("ZK_RISC0_{key}_IMAGE_ID");
+    let Ok(image_hex) = env::var(&image_key) else {
+        warn!("{image_key} not set; {name} not registered with RISC Zero");
+        return None;
+    };
+    let Some(image_id) = hex::decode(image_hex.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+    else {
+        warn!("{image_key} must be 32 hex-encoded bytes");
+        return None;
+    };
+    let elf_key = format!("ZK_RISC0_{key}_ELF");
+    let default_path = format!("zk/artifacts/risc0/{}.elf", key.to_lowercase());
+    Some(Risc0Program {
+        id,
+        image_id,
+        elf: load_elf(&elf_key, &default_path).unwrap_or_default(),
+        name: name.into(),
+        version: "0.1.0".into(),
+    })
 }
 
 fn load_elf(env_key: &str, default_path: &str) -> Option<Vec<u8>> {
@@ -171,10 +420,61 @@ struct TxRequest {
     tx: Tx,
 }
 
+/// Outcome of an included tx; failed txs are included and charged too.
+#[derive(Debug, Clone, Serialize)]
+struct TxReceipt {
+    height: u64,
+    success: bool,
+    #[serde(flatten)]
+    outcome: ExecutionOutcome,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HeightQuery {
+    /// Reads state as of this block instead of the tip; archive nodes only.
+    height: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct ArchivedState {
+    height: u64,
+    state_root: Hash,
+    state: ChainState,
+}
+
+/// An account's vesting as of the next block, the height its txs run at.
+#[derive(Serialize)]
+struct VestingStatus {
+    height: u64,
+    vested: u128,
+    locked: u128,
+    schedules: Vec<VestingProgress>,
+}
+
+#[derive(Serialize)]
+struct VestingProgress {
+    schedule: VestingSchedule,
+    vested: u128,
+    locked: u128,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoolQuery {
+    pool: Option<String>,
+}
+
+impl PoolQuery {
+    fn pool(&self) -> &str {
+        self.pool.as_deref().unwrap_or(DEFAULT_PRIVACY_POOL)
+    }
+}
+
 #[derive(Deserialize)]
 struct SampleQuery {
     blob_id: String,
     samples: Option<usize>,
+    /// Hex-encoded 32-byte challenge seed; local randomness when absent.
+    seed: Option<String>,
 }
 
 #[tokio::main]
@@ -185,26 +485,59 @@ async fn main() -> anyhow::Result<()> {
 
     let zk_backend = init_zk_backend();
 
-    let genesis_ctx = if let Ok(path) = env::var("GENESIS_PATH") {
+    let mut genesis_ctx = if let Ok(path) = env::var("GENESIS_PATH") {
         info!("loading genesis from {}", path);
         load_genesis_from_file(path)?
     } else {
         bootstrap_state()
     }
     .with_zk(zk_backend.clone());
+    if env::var("ARCHIVE_MODE").is_ok_and(|v| v == "1" || v.to_lowercase() == "true") {
+        let interval = env::var("ARCHIVE_CHECKPOINT_INTERVAL")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_ARCHIVE_CHECKPOINT_INTERVAL);
+        info!("archive mode on, full checkpoint every {interval} heights");
+        genesis_ctx.state = genesis_ctx.state.clone().with_archive(StateArchive::new(interval));
+    }
+
+    let snapshots = SnapshotStore::default();
+    let (network, p2p, consensus_rx, tx_rx) = init_consensus_network(&node_id, snapshots.clone()).await;
 
-    let (network, consensus_rx, tx_rx) = init_consensus_network(&node_id).await;
+    let mut anchor = None;
+    if let Ok(path) = env::var("SNAPSHOT_CHECKPOINT") {
+        let Some(net) = p2p.as_ref() else {
+            anyhow::bail!("snapshot bootstrap requires the libp2p network");
+        };
+        anchor = Some(bootstrap_from_snapshot(&genesis_ctx, net, &path).await?);
+    }
+    genesis_ctx.state.archive(anchor.as_ref().map_or(0, |a| a.height)).await?;
 
-    let node = create_node_with(
+    let mut node = create_node_with(
         &node_id,
         genesis_ctx,
-        InMemoryDA::new(),
+        InMemoryDA::with_config(da_config_from_env()),
         network.clone(),
         zk_backend.clone(),
     )
     .await?;
-
-    let proposer = spawn_block_production(node.clone());
+    node.snapshots = snapshots;
+    node.anchor = anchor;
+    fork_choice::reset(&node);
+    node.p2p = p2p.clone();
+    if let Some(net) = p2p.as_ref() {
+        net.set_proposal_gate(Arc::new(node.consensus.clone()));
+    }
+    node.snapshot_interval = env::var("SNAPSHOT_INTERVAL")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SNAPSHOT_INTERVAL);
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    node.shutdown = shutdown_rx.clone();
+    persistence::recover(&mut node).await?;
+    proving::start(&mut node)?;
+
+    let mut proposer = spawn_block_production(node.clone());
     tokio::spawn(node.consensus.clone().run_timeouts());
     if let Some(rx) = consensus_rx {
         spawn_p2p_consensus_listener(node.clone(), rx);
@@ -213,8 +546,130 @@ async fn main() -> anyhow::Result<()> {
         spawn_tx_gossip_listener(node.clone(), rx);
     }
 
-    let app = Router::new()
+    let mut hosted = Vec::new();
+    for spec in chains::chain_specs_from_env()? {
+        if spec.id == node.state.chain_id {
+            anyhow::bail!("chain {} is already the primary chain", spec.id);
+        }
+        let zk = zk_backend.clone();
+        let shutdown = shutdown_rx.clone();
+        hosted.push(chains::start_hosted_chain(&node_id, &spec, p2p.as_ref(), zk, shutdown).await?);
+    }
+
+    let mut chain_ids = vec![node.state.chain_id.clone()];
+    let mut app = chain_router(node.clone())
+        .nest(&format!("/chains/{}", node.state.chain_id), chain_router(node.clone()));
+    for (chain, _) in &hosted {
+        chain_ids.push(chain.state.chain_id.clone());
+        app = app.nest(&format!("/chains/{}", chain.state.chain_id), chain_router(chain.clone()));
+    }
+    let app = app.route(
+        "/chains",
+        get(move || {
+            let chain_ids = chain_ids.clone();
+            async move { Json(chain_ids) }
+        }),
+    );
+    let app = rate_limit::layer(app, rate_limit::RateLimitConfig::from_env());
+
+    let addr: SocketAddr = "0.0.0.0:8545".parse()?;
+    info!("RPC listening on {}", addr);
+    let listener = TcpListener::bind(addr).await?;
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal());
+
+    tokio::select! {
+        _ = &mut proposer => {}
+        res = server => {
+            if let Err(err) = res {
+                warn!("server error: {err}");
+            }
+        }
+    }
+
+    // Let in-flight blocks finish, then make sure everything committed is on disk.
+    let _ = shutdown_tx.send(true);
+    hosted.push((node, proposer));
+    for (node, producer) in hosted {
+        if !producer.is_finished() && time::timeout(SHUTDOWN_GRACE, producer).await.is_err() {
+            warn!("block production for {} did not stop in time", node.state.chain_id);
+        }
+        if let Err(err) = persistence::flush(&node).await {
+            warn!("failed to flush {}: {err}", node.state.chain_id);
+        }
+    }
+    info!("node stopped");
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!("failed to listen for ctrl-c: {err}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(err) => {
+                warn!("failed to listen for SIGTERM: {err}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    info!("shutdown signal received, stopping");
+}
+
+/// RPC surface for one chain. The primary chain is served at the root and
+/// every chain, primary included, under `/chains/:id`.
+fn chain_router(node: Node) -> Router {
+    Router::new()
+        .merge(metrics::router(node.metrics.clone()))
+        .merge(domain_api::routes(node.clone()))
+        .merge(evm_api::routes(node.clone()))
+        .merge(fees::routes(node.clone()))
         .route("/health", get(|| async { "ok" }))
+        .route(
+            "/peers",
+            get({
+                let node = node.clone();
+                move || {
+                    let node = node.clone();
+                    async move { Json(node.p2p.as_ref().map(|p2p| p2p.peers()).unwrap_or_default()) }
+                }
+            }),
+        )
+        .route(
+            "/livez",
+            get({
+                let node = node.clone();
+                move || {
+                    let node = node.clone();
+                    async move { ProbeReport::new(liveness_checks(&node).await) }
+                }
+            }),
+        )
+        .route(
+            "/readyz",
+            get({
+                let node = node.clone();
+                move || {
+                    let node = node.clone();
+                    async move { ProbeReport::new(readiness_checks(&node).await) }
+                }
+            }),
+        )
         .route(
             "/status",
             get({
@@ -222,7 +677,7 @@ async fn main() -> anyhow::Result<()> {
                 move || {
                     let node = node.clone();
                     async move {
-                        let height = node.blocks.lock().unwrap().len() as u64;
+                        let height = next_height(&node);
                         let mempool_len = node.mempool.lock().unwrap().len();
                         let view = node.consensus.current_view();
                         Json(Status {
@@ -236,15 +691,21 @@ async fn main() -> anyhow::Result<()> {
         )
         .route(
             "/governance/proposals",
+            get({
+                let node = node.clone();
+                move |Query(q): Query<ProposalQuery>| {
+                    let node = node.clone();
+                    async move { Json(node.state.state.list_proposals(&q).await.ok()) }
+                }
+            }),
+        )
+        .route(
+            "/governance/proposals/summary",
             get({
                 let node = node.clone();
                 move || {
                     let node = node.clone();
-                    async move {
-                        let chain = node.state.state.get_chain_state().await.ok();
-                        let proposals = chain.map(|c| c.proposals.values().cloned().collect::<Vec<_>>());
-                        Json(proposals)
-                    }
+                    async move { Json(node.state.state.proposal_summary().await.ok()) }
                 }
             }),
         )
@@ -258,13 +719,7 @@ async fn main() -> anyhow::Result<()> {
                         let Ok(uuid) = Uuid::parse_str(&id) else {
                             return Json(None::<state::Proposal>);
                         };
-                        let proposal = node
-                            .state
-                            .state
-                            .get_chain_state()
-                            .await
-                            .ok()
-                            .and_then(|c| c.proposals.get(&uuid).cloned());
+                        let proposal = node.view.load().proposals.get(&uuid).cloned();
                         Json(proposal)
                     }
                 }
@@ -274,21 +729,56 @@ async fn main() -> anyhow::Result<()> {
             "/privacy/pool",
             get({
                 let node = node.clone();
-                move || {
+                move |Query(q): Query<PoolQuery>| {
                     let node = node.clone();
                     async move {
-                        let pool = node
-                            .state
-                            .state
-                            .get_chain_state()
-                            .await
-                            .ok()
-                            .and_then(|c| c.privacy_pools.get("shielded").cloned());
+                        let pool = node.view.load().privacy_pools.get(q.pool()).cloned();
                         Json(pool)
                     }
                 }
             }),
         )
+        .route(
+            "/privacy/pools",
+            get({
+                let node = node.clone();
+                move || {
+                    let node = node.clone();
+                    async move {
+                        let mut stats: Vec<PrivacyPoolStats> = node
+                            .view
+                            .load()
+                            .privacy_pools
+                            .iter()
+                            .map(|(id, pool)| pool.stats(id))
+                            .collect();
+                        stats.sort_by(|a, b| a.pool.cmp(&b.pool));
+                        Json(stats)
+                    }
+                }
+            }),
+        )
+        .route(
+            "/privacy/witness/:commitment",
+            get({
+                let node = node.clone();
+                move |Path(commitment): Path<String>, Query(q): Query<PoolQuery>| {
+                    let node = node.clone();
+                    async move {
+                        let Some(commitment) = parse_address(&commitment) else {
+                            return Json(None::<MerklePath>);
+                        };
+                        let witness = node
+                            .view
+                            .load()
+                            .privacy_pools
+                            .get(q.pool())
+                            .and_then(|pool| pool.witness(&commitment));
+                        Json(witness)
+                    }
+                }
+            }),
+        )
         .route(
             "/send_raw_tx",
             post({
@@ -296,7 +786,8 @@ async fn main() -> anyhow::Result<()> {
                 move |Json(body): Json<TxRequest>| {
                     let node = node.clone();
                     async move {
-                        if verify_tx_signature(&body.tx).is_err() {
+                        let accept_legacy = node.consensus.accepts_legacy_signatures();
+                        if verify_tx_signature(&body.tx, accept_legacy).is_err() {
                             return Json("invalid signature");
                         }
                         enqueue_tx(&node, body.tx.clone());
@@ -310,16 +801,69 @@ async fn main() -> anyhow::Result<()> {
             "/get_block/:height",
             get({
                 let node = node.clone();
-                move |Path(height): Path<usize>| {
+                move |Path(height): Path<u64>| {
+                    let node = node.clone();
+                    async move { Json(block_at(&node, height)) }
+                }
+            }),
+        )
+        .route(
+            "/divergence",
+            get({
+                let node = node.clone();
+                move || {
+                    let node = node.clone();
+                    async move { Json(node.divergence.lock().unwrap().clone()) }
+                }
+            }),
+        )
+        .route(
+            "/divergence/resume",
+            post({
+                let node = node.clone();
+                move || {
+                    let node = node.clone();
+                    async move { Json(divergence::resume(&node)) }
+                }
+            }),
+        )
+        .route(
+            "/divergence/resync",
+            post({
+                let node = node.clone();
+                move || {
                     let node = node.clone();
                     async move {
-                        let blocks = node.blocks.lock().unwrap();
-                        let block = blocks.get(height).cloned();
-                        Json(block)
+                        match divergence::resync(&node).await {
+                            Ok(()) => (StatusCode::OK, "resynced".to_string()),
+                            Err(err) => (StatusCode::CONFLICT, err.to_string()),
+                        }
                     }
                 }
             }),
         )
+        .route(
+            "/state/components",
+            get({
+                let node = node.clone();
+                move || {
+                    let node = node.clone();
+                    async move {
+                        Json(Some(node.view.load().component_roots()))
+                    }
+                }
+            }),
+        )
+        .route(
+            "/snapshot/manifest",
+            get({
+                let node = node.clone();
+                move || {
+                    let node = node.clone();
+                    async move { Json(node.snapshots.latest_manifest()) }
+                }
+            }),
+        )
         .route(
             "/da/commitment/:id",
             get({
@@ -340,30 +884,53 @@ async fn main() -> anyhow::Result<()> {
                 move |Query(q): Query<SampleQuery>| {
                     let node = node.clone();
                     async move {
-                        let ok = node
-                            .da
-                            .sample(&q.blob_id, q.samples.unwrap_or(2))
-                            .await
-                            .is_ok();
+                        let samples = q.samples.unwrap_or(2);
+                        let ok = match q.seed.as_deref() {
+                            Some(seed) => match parse_address(seed) {
+                                Some(seed) => node
+                                    .da
+                                    .sample_with_seed(&q.blob_id, seed, samples)
+                                    .await
+                                    .is_ok(),
+                                None => false,
+                            },
+                            None => node.da.sample(&q.blob_id, samples).await.is_ok(),
+                        };
                         Json(ok)
                     }
                 }
             }),
         )
+        .route(
+            "/ws",
+            get({
+                let node = node.clone();
+                move |ws: WebSocketUpgrade| {
+                    let events = node.events.subscribe();
+                    async move { ws.on_upgrade(move |socket| subscriptions::serve(socket, events)) }
+                }
+            }),
+        )
+        .route(
+            "/da/stats",
+            get({
+                let node = node.clone();
+                move || {
+                    let node = node.clone();
+                    async move { Json(node.da.storage_stats()) }
+                }
+            }),
+        )
         .route(
             "/block_proof/:height",
             get({
                 let node = node.clone();
-                move |Path(height): Path<usize>| {
+                move |Path(height): Path<u64>| {
                     let node = node.clone();
                     async move {
-                        let blocks = node.blocks.lock().unwrap();
-                        let block = blocks.get(height).cloned();
-                        let proof = block.and_then(|b| {
-                            let h = hash_block(&b);
-                            node.block_proofs.lock().unwrap().get(&h).cloned()
-                        });
-                        Json(proof)
+                        let status = block_at(&node, height)
+                            .and_then(|b| node.proving.status(&hash_block(&b)));
+                        Json(status)
                     }
                 }
             }),
@@ -389,46 +956,106 @@ async fn main() -> anyhow::Result<()> {
                 }
             }),
         )
+        .route(
+            "/get_receipt/:hash",
+            get({
+                let node = node.clone();
+                move |Path(hash_hex): Path<String>| {
+                    let node = node.clone();
+                    async move {
+                        let receipt = parse_address(&hash_hex)
+                            .and_then(|h| node.receipts.lock().unwrap().get(&h).cloned());
+                        Json(receipt)
+                    }
+                }
+            }),
+        )
         .route(
             "/get_balance/:address",
             get({
                 let node = node.clone();
-                move |Path(addr_hex): Path<String>| {
+                move |Path(addr_hex): Path<String>, Query(q): Query<HeightQuery>| {
                     let node = node.clone();
                     async move {
                         let Some(address) = parse_address(&addr_hex) else {
-                            return Json(None::<u128>);
+                            return Ok(Json(None::<u128>));
                         };
-                        let account = node
-                            .state
-                            .state
-                            .get_account(&address)
-                            .await
-                            .ok()
-                            .flatten();
-                        Json(account.map(|a| a.balance_x))
+                        let account = account_at(&node, &address, q.height).await?;
+                        Ok(Json(account.map(|a| a.balance_x)))
                     }
                 }
             }),
         )
         .route(
             "/get_nonce/:address",
+            get({
+                let node = node.clone();
+                move |Path(addr_hex): Path<String>, Query(q): Query<HeightQuery>| {
+                    let node = node.clone();
+                    async move {
+                        let Some(address) = parse_address(&addr_hex) else {
+                            return Ok(Json(None::<u64>));
+                        };
+                        let account = account_at(&node, &address, q.height).await?;
+                        Ok(Json(account.map(|a| a.nonce)))
+                    }
+                }
+            }),
+        )
+        .route(
+            "/get_vesting/:address",
             get({
                 let node = node.clone();
                 move |Path(addr_hex): Path<String>| {
                     let node = node.clone();
                     async move {
                         let Some(address) = parse_address(&addr_hex) else {
-                            return Json(None::<u64>);
+                            return Json(None::<VestingStatus>);
                         };
-                        let account = node
-                            .state
-                            .state
-                            .get_account(&address)
-                            .await
-                            .ok()
-                            .flatten();
-                        Json(account.map(|a| a.nonce))
+                        let height = next_height(&node);
+                        let schedules: Vec<VestingProgress> = node
+                            .view
+                            .load()
+                            .vesting
+                            .get(&address)
+                            .into_iter()
+                            .flatten()
+                            .map(|schedule| VestingProgress {
+                                vested: schedule.vested(height),
+                                locked: schedule.locked(height),
+                                schedule: schedule.clone(),
+                            })
+                            .collect();
+                        Json(Some(VestingStatus {
+                            height,
+                            vested: schedules.iter().map(|s| s.vested).sum(),
+                            locked: schedules.iter().map(|s| s.locked).sum(),
+                            schedules,
+                        }))
+                    }
+                }
+            }),
+        )
+        .route(
+            "/get_chain_state/:height",
+            get({
+                let node = node.clone();
+                move |Path(height): Path<u64>| {
+                    let node = node.clone();
+                    async move {
+                        let state_root = archived_root(&node, height).await?;
+                        let state = match node.state.state.get_chain_state_at(height).await {
+                            Ok(Some(state)) => state,
+                            Ok(None) => return Err(not_archived(height)),
+                            Err(err) => {
+                                return Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+                            }
+                        };
+                        Ok(Json(ArchivedState {
+                            height,
+                            state_root,
+                            state,
+                        }))
                     }
                 }
             }),
@@ -445,22 +1072,164 @@ async fn main() -> anyhow::Result<()> {
                     }
                 }
             }),
-        );
+        )
+}
 
-    let addr: SocketAddr = "0.0.0.0:8545".parse()?;
-    info!("RPC listening on {}", addr);
-    let listener = TcpListener::bind(addr).await?;
-    let server = axum::serve(listener, app.into_make_service());
+/// The account at the tip, or as of `height` on an archive node.
+async fn account_at(
+    node: &Node,
+    address: &Address,
+    height: Option<u64>,
+) -> Result<Option<Account>, (StatusCode, String)> {
+    let Some(height) = height else {
+        return Ok(node.view.account(address));
+    };
+    archived_root(node, height).await?;
+    node.state
+        .state
+        .get_account_at(address, height)
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
 
-    tokio::select! {
-        _ = proposer => {}
-        res = server => {
-            if let Err(err) = res {
-                warn!("server error: {err}");
+async fn archived_root(node: &Node, height: u64) -> Result<Hash, (StatusCode, String)> {
+    match node.state.state.state_root_at(height).await {
+        Ok(Some(root)) => Ok(root),
+        Ok(None) => Err(not_archived(height)),
+        Err(err) => Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string())),
+    }
+}
+
+fn not_archived(height: u64) -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, format!("height {height} is not archived"))
+}
+
+async fn state_db_check(node: &Node) -> ProbeCheck {
+    let res = node.state.state.get_account(&[0u8; 32]).await;
+    ProbeCheck {
+        name: "state_db",
+        ok: res.is_ok(),
+        detail: res.err().map(|e| e.to_string()).unwrap_or_default(),
+    }
+}
+
+fn heartbeat_check(cfg: &ProbeConfig, since_tick_ms: u64) -> ProbeCheck {
+    ProbeCheck {
+        name: "block_production",
+        ok: since_tick_ms <= cfg.max_stall_ms,
+        detail: format!("last tick {since_tick_ms}ms ago"),
+    }
+}
+
+async fn liveness_checks(node: &Node) -> Vec<ProbeCheck> {
+    let since_tick = now_millis().saturating_sub(node.heartbeat.load(Ordering::Relaxed));
+    vec![
+        heartbeat_check(&node.probes, since_tick),
+        state_db_check(node).await,
+    ]
+}
+
+/// How caught up the node is, sampled for `/readyz`.
+#[derive(Clone, Copy)]
+struct SyncStatus {
+    /// `None` when the transport doesn't track peers.
+    peers: Option<usize>,
+    mempool_len: usize,
+    block_age_ms: u64,
+}
+
+fn sync_checks(cfg: &ProbeConfig, status: SyncStatus) -> Vec<ProbeCheck> {
+    let SyncStatus {
+        peers,
+        mempool_len,
+        block_age_ms,
+    } = status;
+    vec![
+        ProbeCheck {
+            name: "peers",
+            ok: peers.is_none_or(|n| n >= cfg.min_peers),
+            detail: match peers {
+                Some(n) => format!("{n} connected, need {}", cfg.min_peers),
+                None => "not tracked by transport".into(),
+            },
+        },
+        // An idle chain produces no blocks, so block age only matters while
+        // transactions are waiting to be included.
+        ProbeCheck {
+            name: "last_block_age",
+            ok: mempool_len == 0 || block_age_ms <= cfg.max_block_age_ms,
+            detail: format!("{block_age_ms}ms since last block"),
+        },
+        ProbeCheck {
+            name: "mempool",
+            ok: mempool_len <= cfg.max_mempool,
+            detail: format!("{mempool_len} pending, max {}", cfg.max_mempool),
+        },
+    ]
+}
+
+async fn readiness_checks(node: &Node) -> Vec<ProbeCheck> {
+    let last_block = node.blocks.lock().unwrap().last().cloned();
+    let last_ts = last_block
+        .as_ref()
+        .map(|b| b.header.timestamp)
+        .unwrap_or(node.started_at);
+    let status = SyncStatus {
+        peers: node.network.peer_count(),
+        mempool_len: node.mempool.lock().unwrap().len(),
+        block_age_ms: now_millis().saturating_sub(last_ts),
+    };
+    let mut checks = sync_checks(&node.probes, status);
+
+    checks.push(state_db_check(node).await);
+
+    let divergence = node.divergence.lock().unwrap().clone();
+    checks.push(ProbeCheck {
+        name: "state_root",
+        ok: divergence.is_none(),
+        detail: divergence
+            .map(|r| format!("halted on divergence at height {}", r.height))
+            .unwrap_or_default(),
+    });
+    let unsupported = *node.unsupported_version.lock().unwrap();
+    checks.push(ProbeCheck {
+        name: "protocol_version",
+        ok: unsupported.is_none(),
+        detail: unsupported.map(|u| u.to_string()).unwrap_or_default(),
+    });
+
+    let da_err = match last_block.as_ref().and_then(|b| b.da_blobs.first()) {
+        Some(blob_id) => node.da.get_commitment(blob_id).await.err(),
+        None => None,
+    };
+    checks.push(ProbeCheck {
+        name: "da",
+        ok: da_err.is_none(),
+        detail: da_err.map(|e| e.to_string()).unwrap_or_default(),
+    });
+
+    let zk_check = match node.zk.as_ref() {
+        None => ProbeCheck {
+            name: "zk",
+            ok: true,
+            detail: "disabled".into(),
+        },
+        Some(zk) if zk.registry().get(&ProgramId::Block).is_none() => ProbeCheck {
+            name: "zk",
+            ok: false,
+            detail: format!("{} has no block program", zk.backend_id()),
+        },
+        Some(zk) => {
+            let last_err = node.last_zk_error.lock().unwrap().clone();
+            ProbeCheck {
+                name: "zk",
+                ok: last_err.is_none(),
+                detail: last_err.unwrap_or_else(|| zk.backend_id().to_string()),
             }
         }
-    }
-    Ok(())
+    };
+    checks.push(zk_check);
+    checks
 }
 
 fn spawn_block_production(node: Node) -> JoinHandle<()> {
@@ -468,6 +1237,13 @@ fn spawn_block_production(node: Node) -> JoinHandle<()> {
         let mut interval = time::interval(Duration::from_millis(node.state.block_time_ms));
         loop {
             interval.tick().await;
+            if *node.shutdown.borrow() {
+                break;
+            }
+            node.heartbeat.store(now_millis(), Ordering::Relaxed);
+            if divergence::is_halted(&node) {
+                continue;
+            }
             let is_leader = node
                 .consensus
                 .leader_for_view(node.consensus.current_view())
@@ -491,7 +1267,13 @@ fn spawn_block_production(node: Node) -> JoinHandle<()> {
                         let proposal = SignedProposal {
                             block: sealed.clone(),
                             public_key: node.verifying_key.clone(),
-                            signature: sign_proposal(&sealed, &node.signing_key),
+                            signature: sign_proposal(
+                                &node.state.chain_id,
+                                &sealed,
+                                &node.signing_key,
+                            ),
+                            justify: node.consensus.high_qc(),
+                            leader_proof: Some(node.consensus.leader_proof(view)),
                         };
                         if let Err(err) = node.consensus.propose(proposal.clone()).await {
                             warn!("proposal rejected: {err}");
@@ -501,18 +1283,15 @@ fn spawn_block_production(node: Node) -> JoinHandle<()> {
                             .broadcast(ConsensusMessage::Propose(proposal.clone()));
 
                         if let Some(validator) = node.local_validator.clone() {
-                            let vote = SignedVote {
-                                block_id,
-                                view,
-                                voter: validator,
-                                signature: sign_vote(&block_id, view, &node.signing_key),
-                            };
-                            let _ = node.consensus.vote(vote.clone()).await;
-                            node.network.broadcast(ConsensusMessage::Vote(vote));
-                        }
-                        while let Some(committed) = node.consensus.pop_commit() {
-                            info!("commit block {:?}", committed);
+                            match sign_local_vote(&node, validator, block_id, view) {
+                                Ok(vote) => {
+                                    let _ = node.consensus.vote(vote.clone()).await;
+                                    node.network.broadcast(ConsensusMessage::Vote(vote));
+                                }
+                                Err(err) => warn!("failed to sign vote: {err}"),
+                            }
                         }
+                        process_commits(&node).await;
                     }
                     Err(err) => warn!("failed to build block: {err}"),
                 }
@@ -522,14 +1301,22 @@ fn spawn_block_production(node: Node) -> JoinHandle<()> {
 }
 
 async fn handle_message(node: &Node, msg: ConsensusMessage) {
+    if divergence::is_halted(node) {
+        return;
+    }
     if !verify_consensus_message(node, &msg).await {
         warn!("discarded invalid consensus message");
         return;
     }
     match msg {
         ConsensusMessage::Propose(proposal) => {
+            if let Err(err) = check_timestamp(node, &proposal.block) {
+                warn!("rejected proposal: {err}");
+                return;
+            }
             if let Err(err) = node.consensus.propose(proposal.clone()).await {
                 warn!("consensus rejected proposal: {err}");
+                submit_evidence(node).await;
                 return;
             }
             if let Err(err) = execute_and_record(node, &proposal.block).await {
@@ -545,14 +1332,13 @@ async fn handle_message(node: &Node, msg: ConsensusMessage) {
                     .and_then(|v| v.as_u64())
                     .unwrap_or(node.consensus.current_view());
                 let block_id = hash_block(&proposal.block);
-                let vote = SignedVote {
-                    block_id,
-                    view,
-                    voter: validator,
-                    signature: sign_vote(&block_id, view, &node.signing_key),
-                };
-                let _ = node.consensus.vote(vote.clone()).await;
-                node.network.broadcast(ConsensusMessage::Vote(vote));
+                match sign_local_vote(node, validator, block_id, view) {
+                    Ok(vote) => {
+                        let _ = node.consensus.vote(vote.clone()).await;
+                        node.network.broadcast(ConsensusMessage::Vote(vote));
+                    }
+                    Err(err) => warn!("failed to sign vote: {err}"),
+                }
             }
         }
         ConsensusMessage::Vote(vote) => {
@@ -567,14 +1353,27 @@ async fn handle_message(node: &Node, msg: ConsensusMessage) {
     process_commits(node).await;
 }
 
+fn sign_local_vote(
+    node: &Node,
+    voter: Validator,
+    block_id: Hash,
+    view: u64,
+) -> anyhow::Result<SignedVote> {
+    let chain_id = &node.state.chain_id;
+    Ok(SignedVote {
+        block_id,
+        view,
+        voter,
+        signature: sign_vote(chain_id, &block_id, view, &node.signing_key)?,
+        bls_signature: sign_vote_bls(chain_id, &block_id, view, &node.bls_key)?,
+    })
+}
+
 async fn verify_consensus_message(node: &Node, msg: &ConsensusMessage) -> bool {
     match msg {
         ConsensusMessage::Propose(p) => {
-            if address_from_pubkey(&p.public_key) != p.block.header.proposer_id {
-                return false;
-            }
-            let block_id = hash_block(&p.block);
-            verify_signature_bytes(&p.public_key, &p.signature, block_id.as_slice()).is_ok()
+            let accept_legacy = node.consensus.accepts_legacy_signatures();
+            verify_proposal(&node.state.chain_id, p, hash_block(&p.block), accept_legacy).is_ok()
         }
         ConsensusMessage::Vote(v) => {
             let validators = node.consensus.validator_set().await.unwrap_or_default();
@@ -583,8 +1382,11 @@ async fn verify_consensus_message(node: &Node, msg: &ConsensusMessage) -> bool {
                     return false;
                 }
             }
-            let msg_bytes = bincode::serialize(&(v.block_id, v.view)).unwrap_or_default();
-            verify_signature_bytes(&v.voter.pubkey, &v.signature, &msg_bytes).is_ok()
+            let accept_legacy = node.consensus.accepts_legacy_signatures();
+            vote_messages(&node.state.chain_id, &v.block_id, v.view, accept_legacy)
+                .unwrap_or_default()
+                .iter()
+                .any(|msg| verify_signature_bytes(&v.voter.pubkey, &v.signature, msg).is_ok())
         }
         ConsensusMessage::Timeout { from, .. } => {
             let validators = node.consensus.validator_set().await.unwrap_or_default();
@@ -596,16 +1398,76 @@ async fn verify_consensus_message(node: &Node, msg: &ConsensusMessage) -> bool {
 async fn process_commits(node: &Node) {
     while let Some(committed) = node.consensus.pop_commit() {
         info!("commit block {:?}", hex::encode(committed));
+        fork_choice::on_commit(node, committed).await;
+    }
+    submit_evidence(node).await;
+}
+
+/// Turns equivocation detected by consensus into `SubmitEvidence` txs so the
+/// runtime slashes the offender.
+async fn submit_evidence(node: &Node) {
+    for evidence in node.consensus.take_evidence() {
+        warn!(
+            "{} by validator {} at height {}",
+            evidence.reason, evidence.validator_id, evidence.height
+        );
+        let Some(proof) = evidence.proof else {
+            continue;
+        };
+        match build_evidence_tx(node, proof).await {
+            Ok(tx) => {
+                node.network.broadcast_tx(&tx);
+                enqueue_tx(node, tx);
+            }
+            Err(err) => warn!("failed to build evidence tx: {err}"),
+        }
     }
 }
 
+async fn build_evidence_tx(node: &Node, evidence: DoubleSignEvidence) -> anyhow::Result<Tx> {
+    let sender = address_from_pubkey(&node.verifying_key);
+    let confirmed = node
+        .state
+        .state
+        .get_account(&sender)
+        .await?
+        .map(|a| a.nonce)
+        .unwrap_or(0);
+    let pending = node
+        .mempool
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|t| t.public_key == node.verifying_key)
+        .count() as u64;
+    let mut tx = Tx {
+        chain_id: node.state.chain_id.clone(),
+        nonce: confirmed + pending,
+        gas_limit: 90_000,
+        max_fee: Some(node.state.base_fee),
+        max_priority_fee: Some(0),
+        gas_price: None,
+        payload: TxPayload::SubmitEvidence { evidence },
+        public_key: node.verifying_key.clone(),
+        signature: vec![],
+    };
+    tx.signature = sign_bytes(&node.signing_key, &tx_signing_bytes(&tx)?);
+    Ok(tx)
+}
+
 fn spawn_p2p_consensus_listener(
     node: Node,
-    mut rx: mpsc::Receiver<ConsensusMessage>,
+    mut rx: mpsc::Receiver<InboundConsensus>,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            handle_message(&node, msg).await;
+        while let Some(inbound) = rx.recv().await {
+            if *node.shutdown.borrow() {
+                break;
+            }
+            if let Some(validator) = &inbound.validator {
+                debug!("consensus msg published by validator {}", hex::encode(validator));
+            }
+            handle_message(&node, inbound.message).await;
         }
     })
 }
@@ -627,7 +1489,7 @@ fn spawn_network_listener(node: Node, mut rx: broadcast::Receiver<ConsensusMessa
 }
 
 async fn build_block(node: &Node) -> Option<Block> {
-    let txs = {
+    let candidates = {
         let mut mempool = node.mempool.lock().unwrap();
         if mempool.is_empty() {
             return None;
@@ -635,6 +1497,33 @@ async fn build_block(node: &Node) -> Option<Block> {
         mempool.sort_by(|a, b| tx_priority(b, node.state.base_fee).cmp(&tx_priority(a, node.state.base_fee)));
         mempool.drain(..).collect::<Vec<_>>()
     };
+    let height = next_height(node);
+    // Selection simulates the txs at the time the block will carry, which
+    // has to move past the parent's even if its proposer's clock ran ahead.
+    let after_parent = node
+        .blocks
+        .lock()
+        .unwrap()
+        .last()
+        .map_or(0, |b| b.header.timestamp + 1);
+    let timestamp = now_millis().max(after_parent);
+    node.state.clock.set_block_time(timestamp);
+    let selection = match select_block_txs(&node.state, candidates.clone(), height).await {
+        Ok(selection) => selection,
+        Err(err) => {
+            warn!("tx selection failed: {err}");
+            requeue_txs(node, candidates);
+            return None;
+        }
+    };
+    for (tx, reason) in &selection.rejected {
+        warn!("dropped tx {}: {reason}", hex::encode(tx_hash(tx)));
+    }
+    requeue_txs(node, selection.deferred);
+    let txs = selection.included;
+    if txs.is_empty() {
+        return None;
+    }
 
     let parent_hash = node
         .blocks
@@ -642,6 +1531,7 @@ async fn build_block(node: &Node) -> Option<Block> {
         .unwrap()
         .last()
         .map(hash_block)
+        .or(node.anchor.map(|a| a.hash))
         .unwrap_or([0u8; 32]);
 
     let blob = match serde_json::to_vec(&txs) {
@@ -655,12 +1545,19 @@ async fn build_block(node: &Node) -> Option<Block> {
         .map(|v| v.owner)
         .unwrap_or([0u8; 32]);
 
+    let protocol_version = match protocol_version_at(&node.state.state, height).await {
+        Ok(version) => version,
+        Err(err) => {
+            warn!("reading protocol version failed: {err}");
+            requeue_txs(node, txs);
+            return None;
+        }
+    };
     let l1_tx_root = tx_root(&txs);
-    let height = node.blocks.lock().unwrap().len() as u64;
     let header = BlockHeader {
         parent_hash,
         height,
-        timestamp: now_millis(),
+        timestamp,
         proposer_id,
         state_root: [0u8; 32],
         l1_tx_root,
@@ -675,8 +1572,11 @@ async fn build_block(node: &Node) -> Option<Block> {
         gas_used: 0,
         gas_limit: node.state.max_gas_per_block,
         base_fee: node.state.base_fee,
+        snapshot_root: None,
+        protocol_version,
         consensus_metadata: serde_json::json!({
-            "view": node.consensus.current_view()
+            "view": node.consensus.current_view(),
+            "liveness": node.consensus.liveness_report(),
         }),
     };
 
@@ -687,14 +1587,15 @@ async fn build_block(node: &Node) -> Option<Block> {
     })
 }
 
-fn tx_root(txs: &[Tx]) -> [u8; 32] {
-    let bytes = bincode::serialize(txs).unwrap_or_default();
-    *blake3::hash(&bytes).as_bytes()
+fn next_height(node: &Node) -> u64 {
+    let base = node.anchor.map(|a| a.height + 1).unwrap_or(0);
+    base + node.blocks.lock().unwrap().len() as u64
 }
 
-fn tx_hash(tx: &Tx) -> [u8; 32] {
-    let bytes = bincode::serialize(tx).unwrap_or_default();
-    *blake3::hash(&bytes).as_bytes()
+fn block_at(node: &Node, height: u64) -> Option<Block> {
+    let base = node.anchor.map(|a| a.height + 1).unwrap_or(0);
+    let offset = height.checked_sub(base)?;
+    node.blocks.lock().unwrap().get(offset as usize).cloned()
 }
 
 fn tx_priority(tx: &Tx, base_fee: u128) -> u128 {
@@ -706,7 +1607,7 @@ fn tx_priority(tx: &Tx, base_fee: u128) -> u128 {
 }
 
 fn enqueue_tx(node: &Node, tx: Tx) {
-    if verify_tx_signature(&tx).is_err() {
+    if verify_tx_signature(&tx, node.consensus.accepts_legacy_signatures()).is_err() {
         warn!("dropped tx with invalid signature");
         return;
     }
@@ -723,106 +1624,232 @@ fn enqueue_tx(node: &Node, tx: Tx) {
         return;
     }
     mempool.push(tx);
+    node.metrics.mempool_depth.set(mempool.len() as i64);
+}
+
+/// Returns txs that didn't make it into a block to the mempool.
+fn requeue_txs(node: &Node, txs: Vec<Tx>) {
+    let mut mempool = node.mempool.lock().unwrap();
+    mempool.extend(txs);
+    node.metrics.mempool_depth.set(mempool.len() as i64);
 }
 
 fn drop_included_txs(node: &Node, txs: &[Tx]) {
     let drop_hashes: HashSet<_> = txs.iter().map(tx_hash).collect();
     let mut mempool = node.mempool.lock().unwrap();
     mempool.retain(|t| !drop_hashes.contains(&tx_hash(t)));
+    node.metrics.mempool_depth.set(mempool.len() as i64);
 }
 
 
-async fn execute_and_record(node: &Node, block: &Block) -> anyhow::Result<(Block, Hash)> {
-    let mut sealed = block.clone();
-    let block_id = hash_block(&sealed);
-    {
-        let applied = node.applied.lock().unwrap();
-        if applied.contains(&block_id) {
-            return Ok((sealed, block_id));
-        }
+/// DA_RETENTION_BLOCKS takes precedence over DA_RETENTION_MS; neither keeps
+/// every blob.
+fn da_config_from_env() -> DAConfig {
+    let env_u64 = |key: &str| env::var(key).ok().and_then(|v| v.parse::<u64>().ok());
+    let retention = if let Some(blocks) = env_u64("DA_RETENTION_BLOCKS") {
+        RetentionPolicy::Blocks(blocks)
+    } else if let Some(ms) = env_u64("DA_RETENTION_MS") {
+        RetentionPolicy::AgeMs(ms)
+    } else {
+        RetentionPolicy::KeepAll
+    };
+    DAConfig {
+        retention,
+        ..DAConfig::default()
     }
+}
 
-    for blob_id in &sealed.da_blobs {
-        let proof = node.da.prove_blob_availability(blob_id).await?;
-        if proof.samples.is_empty() {
-            anyhow::bail!("empty DA proof");
-        }
-        let Some(commitment) = sealed.header.da_commitment.as_ref() else {
+async fn check_da_samples(node: &Node, block: &Block, block_id: Hash) -> anyhow::Result<()> {
+    for blob_id in &block.da_blobs {
+        let Some(commitment) = block.header.da_commitment.as_ref() else {
             anyhow::bail!("missing da commitment in header");
         };
+        // Shards to check are chosen by the block hash, not by the provider.
+        let indices = sample_indices(
+            &challenge_seed(&block_id, blob_id),
+            commitment.total_shards as usize,
+            node.state.da_sample_count.max(1) as usize,
+        );
+        if indices.is_empty() {
+            anyhow::bail!("empty DA proof");
+        }
+        let proof = node.da.prove_samples(blob_id, &indices).await?;
         if proof.commitment.root != commitment.root {
             anyhow::bail!("da commitment root mismatch");
         }
-        if !verify_da_proof(&proof) {
+        if !verify_sampled_proof(&proof, &indices) {
             anyhow::bail!("invalid DA sampling proof");
         }
     }
+    Ok(())
+}
 
-    let result = apply_block(&node.state, &sealed).await?;
-    if sealed.header.state_root != [0u8; 32] && sealed.header.state_root != result.state_root {
-        anyhow::bail!("state root mismatch for block");
-    }
-    sealed.header.state_root = result.state_root;
-    sealed.header.gas_used = result.gas_used;
-
-    if let Some(zk) = node.zk.clone() {
-        if let Err(err) = prove_block(node, zk, &sealed, &result, block_id).await {
-            warn!("zk proof generation failed: {err}");
+async fn execute_and_record(node: &Node, block: &Block) -> anyhow::Result<(Block, Hash)> {
+    let block_id = hash_block(block);
+    {
+        let applied = node.applied.lock().unwrap();
+        if applied.contains(&block_id) {
+            return Ok((block.clone(), block_id));
         }
     }
 
-    {
-        let mut store = node.block_store.lock().unwrap();
-        store.insert(block_id, sealed.clone());
+    check_timestamp(node, block)?;
+    if let Err(err) = check_da_samples(node, block, block_id).await {
+        node.metrics.da_sampling_failures.inc();
+        return Err(err);
     }
-    {
-        let mut applied = node.applied.lock().unwrap();
-        applied.insert(block_id);
+
+    if divergence::is_halted(node) {
+        anyhow::bail!("node halted; see /health/ready");
     }
-    {
-        let mut chain = node.blocks.lock().unwrap();
-        chain.push(sealed.clone());
+    if !fork_choice::extends_head(node, block) {
+        fork_choice::add_side_block(node, block).await?;
+        return Ok((block.clone(), block_id));
     }
-    drop_included_txs(node, &sealed.transactions);
-    index_txs(node, &sealed);
+    let sealed = apply_and_record(node, block, block_id, true).await?;
     Ok((sealed, block_id))
 }
 
-async fn prove_block(
+/// Applies a block that builds on the current head and records it. A block
+/// whose state root disagrees with ours trips the divergence breaker when
+/// `halt_on_divergence` is set; otherwise it is just rejected.
+async fn apply_and_record(
     node: &Node,
-    zk: Arc<dyn ZkBackend>,
     block: &Block,
-    result: &runtime::BlockApplyResult,
     block_id: Hash,
-) -> anyhow::Result<()> {
-    let events_root = zk_program_block::hash_events(&result.events);
-    let witness =
-        zk_program_block::encode_witness(block, result.state_root, &result.events, result.gas_used)?;
-    let da_root = block
-        .header
-        .da_commitment
-        .as_ref()
-        .map(|c| c.root)
-        .unwrap_or([0u8; 32]);
-    let commitments = zk_program_block::commitments(result.state_root, events_root, da_root);
-    let artifact = zk
-        .prove(ProofRequest {
-            program_id: ProgramId::Block,
-            witness,
-            commitments: Some(commitments),
-        })
-        .await
-        .map_err(|e| anyhow::anyhow!("prove error: {e}"))?;
-    zk.verify(&artifact)
-        .await
-        .map_err(|e| anyhow::anyhow!("verify error: {e}"))?;
-
-    let record = BlockProof {
-        block_hash: block_id,
-        state_root: result.state_root,
-        proof: artifact,
+    halt_on_divergence: bool,
+) -> anyhow::Result<Block> {
+    let mut sealed = block.clone();
+    // Blocks from other proposers commit to a root; keep the pre-state so a
+    // mismatch does not leave the node on a diverged state.
+    // Subscribers are sent proposal status changes, which also need it.
+    let expects_root = sealed.header.state_root != [0u8; 32];
+    let pre_state = if expects_root || node.events.receiver_count() > 0 {
+        Some(node.state.state.get_chain_state().await?)
+    } else {
+        None
+    };
+    // Domain state lives beside the store and needs its own rollback.
+    let pre_domains = expects_root.then(|| node.state.domains.checkpoint());
+    let result = match apply_block(&node.state, &sealed).await {
+        Ok(result) => result,
+        Err(err) => {
+            if let Some(unsupported) = err.downcast_ref::<UnsupportedProtocolVersion>() {
+                divergence::halt_unsupported(node, *unsupported);
+            }
+            return Err(err);
+        }
     };
-    node.block_proofs.lock().unwrap().insert(block_id, record);
+    if let (Some(pre_state), Some(pre_domains)) =
+        (pre_state.as_ref().filter(|_| expects_root), pre_domains)
+    {
+        if sealed.header.state_root != result.state_root {
+            let diverged = node.state.state.get_chain_state().await?;
+            node.state.state.put_chain_state(pre_state.clone()).await?;
+            // The archive recorded the diverged state; roll it back with the store.
+            node.state.state.archive(sealed.header.height.saturating_sub(1)).await?;
+            node.state.domains.restore(pre_domains);
+            if !halt_on_divergence {
+                anyhow::bail!("state root mismatch for block");
+            }
+            let report =
+                DivergenceReport::new(&sealed, block_id, pre_state, &diverged, result.state_root);
+            divergence::trip(node, report);
+            if node.divergence_policy == DivergencePolicy::Resync {
+                let node = node.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = divergence::resync(&node).await {
+                        warn!("automatic resync failed: {err}");
+                    }
+                });
+            }
+            anyhow::bail!("state root mismatch for block");
+        }
+        // Roots follow from state, so a proposer that got the state right
+        // but lists other domain roots is lying about them.
+        if sealed.header.domain_roots != result.domain_roots {
+            node.state.state.put_chain_state(pre_state.clone()).await?;
+            node.state.state.archive(sealed.header.height.saturating_sub(1)).await?;
+            node.state.domains.restore(pre_domains);
+            anyhow::bail!("domain roots mismatch for block");
+        }
+    }
+    // The block may have activated an upgrade that retires legacy signatures.
+    node.consensus.set_protocol_version(node.state.state.get_protocol_version().await?);
+    sealed.header.state_root = result.state_root;
+    sealed.header.domain_roots = result.domain_roots.clone();
+    sealed.header.gas_used = result.gas_used;
+
+    let height = sealed.header.height;
+    let snapshot_due = node.snapshot_interval > 0 && height > 0 && height % node.snapshot_interval == 0;
+    if snapshot_due || sealed.header.snapshot_root.is_some() {
+        let chain = node.state.state.get_chain_state().await?;
+        let snapshot = chain.snapshot(height, DEFAULT_SNAPSHOT_CHUNK_SIZE)?;
+        let manifest_hash = snapshot.manifest.hash();
+        if let Some(expected) = sealed.header.snapshot_root {
+            if expected != manifest_hash {
+                anyhow::bail!("snapshot root mismatch for block");
+            }
+        }
+        sealed.header.snapshot_root = Some(manifest_hash);
+        node.snapshots.insert(snapshot);
+    }
+
+    // Commitments stay in chain state; only the blob bytes age out.
+    for blob_id in &sealed.da_blobs {
+        if let Err(err) = node.da.anchor_blob(blob_id, height).await {
+            warn!("failed to anchor blob {blob_id}: {err}");
+        }
+    }
+    let pruned = node.da.apply_retention(height);
+    if pruned.blobs > 0 {
+        info!("pruned {} DA blobs ({} bytes)", pruned.blobs, pruned.bytes_freed);
+    }
+
+    // Logged before it becomes visible so a restart never forgets a block
+    // peers may already have built on.
+    if let Some(disk) = &node.disk {
+        disk.append(&sealed)?;
+    }
+    // Proved in the background; `/block_proof` reports when it lands.
+    if node.zk.is_some() {
+        match proving::block_job(&sealed, &result, block_id) {
+            Ok(job) => proving::submit(node, job).await,
+            Err(err) => {
+                warn!("zk proof generation failed: {err}");
+                *node.last_zk_error.lock().unwrap() = Some(err.to_string());
+            }
+        }
+    }
+    publish_view(node).await?;
+    record_block(node, &sealed, block_id);
+    fork_choice::record_head(node, &sealed, block_id);
+    index_receipts(node, &sealed, result.receipts);
+    if node.events.receiver_count() > 0 {
+        let post_state = pre_state.as_ref().map(|_| node.view.load());
+        let states = pre_state.as_ref().zip(post_state.as_deref());
+        for event in subscriptions::block_events(&sealed, block_id, states) {
+            let _ = node.events.send(event);
+        }
+    }
+    Ok(sealed)
+}
+
+/// Refuses a block stamped no later than its parent, when we hold the
+/// parent, or further than `max_clock_drift_ms` ahead of our clock.
+fn check_timestamp(node: &Node, block: &Block) -> anyhow::Result<()> {
+    let timestamp = block.header.timestamp;
+    if let Some(parent) = fork_choice::block_timestamp(node, &block.header.parent_hash) {
+        if timestamp <= parent {
+            anyhow::bail!("block timestamp {timestamp} is not after its parent's {parent}");
+        }
+    }
+    if timestamp > now_millis().saturating_add(node.max_clock_drift_ms) {
+        anyhow::bail!(
+            "block timestamp {timestamp} is more than {}ms ahead of local time",
+            node.max_clock_drift_ms
+        );
+    }
     Ok(())
 }
 
@@ -838,13 +1865,26 @@ fn derive_signing_key(node_id: &str) -> SigningKey {
     SigningKey::from_bytes(digest.as_bytes())
 }
 
+fn derive_bls_key(node_id: &str) -> BlsSecretKey {
+    let seed = blake3::derive_key("kova validator bls key v1", node_id.as_bytes());
+    BlsSecretKey::from_seed(&seed).expect("32-byte seed is valid bls ikm")
+}
+
 async fn ensure_local_validator(
     ctx: &ExecutionContext<InMemoryStateStore>,
     verifying_key: &[u8],
+    bls_pubkey: &[u8],
 ) -> anyhow::Result<Validator> {
     let mut chain = ctx.state.get_chain_state().await?;
     let owner = address_from_pubkey(verifying_key);
-    if let Some(v) = chain.validators.values().find(|v| v.owner == owner).cloned() {
+    if let Some(v) = chain.validators.values_mut().find(|v| v.owner == owner) {
+        // Devnet convenience: validators created before BLS support get the
+        // node's key; production validators register it via RegisterBlsKey.
+        if v.bls_pubkey.is_empty() {
+            v.bls_pubkey = bls_pubkey.to_vec();
+        }
+        let v = v.clone();
+        ctx.state.put_chain_state(chain).await?;
         return Ok(v);
     }
     let id = Uuid::new_v5(&Uuid::NAMESPACE_OID, verifying_key);
@@ -855,6 +1895,9 @@ async fn ensure_local_validator(
         stake: 1_000,
         status: ValidatorStatus::Active,
         commission_rate: 0,
+        bls_pubkey: bls_pubkey.to_vec(),
+        description: Default::default(),
+        commission_changed_epoch: None,
     };
     chain.validators.insert(id, validator.clone());
     ctx.state.put_chain_state(chain).await?;
@@ -898,30 +1941,80 @@ async fn create_node_with(
 ) -> anyhow::Result<Node> {
     let signing_key = Arc::new(derive_signing_key(node_id));
     let verifying_key = signing_key.verifying_key().to_bytes().to_vec();
-    let local_validator = ensure_local_validator(&ctx, &verifying_key).await?;
+    let bls_key = Arc::new(derive_bls_key(node_id));
+    let local_validator = ensure_local_validator(&ctx, &verifying_key, &bls_key.public_key()).await?;
     let chain_state = ctx.state.get_chain_state().await?;
-    let mut validators: Vec<Validator> = chain_state.validators.values().cloned().collect();
+    let mut validators: Vec<Validator> = chain_state
+        .validators
+        .values()
+        .filter(|v| matches!(v.status, ValidatorStatus::Active))
+        .cloned()
+        .collect();
     validators.sort_by_key(|v| v.owner);
-    let consensus = HotStuffEngine::new(validators.clone());
+    let consensus = HotStuffEngine::new(ctx.chain_id.clone(), validators.clone());
+    consensus.set_protocol_version(chain_state.protocol_version);
+    let chain_id = ctx.chain_id.clone();
+    let view = StateView::new(chain_state);
+    let tree = BlockTree::genesis(view.load(), ctx.domains.checkpoint());
     Ok(Node {
         id: node_id.to_string(),
         consensus,
         da,
         state: ctx,
+        view,
         blocks: Arc::new(Mutex::new(Vec::new())),
         mempool: Arc::new(Mutex::new(Vec::new())),
         local_validator: Some(local_validator),
         network,
         tx_index: Arc::new(Mutex::new(HashMap::new())),
+        receipts: Arc::new(Mutex::new(HashMap::new())),
         block_store: Arc::new(Mutex::new(HashMap::new())),
-        block_proofs: Arc::new(Mutex::new(HashMap::new())),
+        proving: proving::ProofQueue::default(),
         applied: Arc::new(Mutex::new(HashSet::new())),
         signing_key,
         verifying_key,
+        bls_key,
         zk,
+        snapshots: SnapshotStore::default(),
+        snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+        max_clock_drift_ms: env::var("MAX_CLOCK_DRIFT_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_CLOCK_DRIFT_MS),
+        anchor: None,
+        probes: ProbeConfig::from_env(),
+        started_at: now_millis(),
+        heartbeat: Arc::new(AtomicU64::new(now_millis())),
+        last_zk_error: Arc::new(Mutex::new(None)),
+        p2p: None,
+        divergence: Arc::new(Mutex::new(None)),
+        divergence_policy: DivergencePolicy::from_env(),
+        unsupported_version: Arc::new(Mutex::new(None)),
+        events: broadcast::channel(EVENT_BUS_CAPACITY).0,
+        tree: Arc::new(Mutex::new(tree)),
+        metrics: Metrics::new(&[("service", "node"), ("chain", &chain_id)]),
+        disk: None,
+        shutdown: watch::channel(false).1,
     })
 }
 
+/// Publishes the store's current chain state to `node.view`.
+async fn publish_view(node: &Node) -> anyhow::Result<()> {
+    node.view.publish(node.state.state.get_chain_state().await?);
+    Ok(())
+}
+
+/// In-memory bookkeeping for a block that has been applied to state.
+fn record_block(node: &Node, block: &Block, block_id: Hash) {
+    node.block_store.lock().unwrap().insert(block_id, block.clone());
+    node.applied.lock().unwrap().insert(block_id);
+    node.blocks.lock().unwrap().push(block.clone());
+    node.metrics.block_height.set(block.header.height as i64);
+    node.metrics.consensus_view.set(node.consensus.current_view() as i64);
+    drop_included_txs(node, &block.transactions);
+    index_txs(node, block);
+}
+
 fn index_txs(node: &Node, block: &Block) {
     let mut index = node.tx_index.lock().unwrap();
     for tx in &block.transactions {
@@ -929,6 +2022,18 @@ fn index_txs(node: &Node, block: &Block) {
     }
 }
 
+fn index_receipts(node: &Node, block: &Block, outcomes: Vec<ExecutionOutcome>) {
+    let mut receipts = node.receipts.lock().unwrap();
+    for (tx, outcome) in block.transactions.iter().zip(outcomes) {
+        let receipt = TxReceipt {
+            height: block.header.height,
+            success: outcome.succeeded(),
+            outcome,
+        };
+        receipts.insert(tx_hash(tx), receipt);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -945,22 +2050,192 @@ mod tests {
             .unwrap_or(0)
     }
 
+    const PROBES: ProbeConfig = ProbeConfig {
+        min_peers: 2,
+        max_block_age_ms: 10_000,
+        max_mempool: 100,
+        max_stall_ms: 5_000,
+    };
+
+    fn failing(checks: &[ProbeCheck]) -> Vec<&'static str> {
+        checks.iter().filter(|c| !c.ok).map(|c| c.name).collect()
+    }
+
+    #[test]
+    fn caught_up_node_is_ready() {
+        let checks = sync_checks(
+            &PROBES,
+            SyncStatus {
+                peers: Some(3),
+                mempool_len: 5,
+                block_age_ms: 2_000,
+            },
+        );
+        assert!(failing(&checks).is_empty());
+        assert!(heartbeat_check(&PROBES, 1_000).ok);
+    }
+
+    #[test]
+    fn stale_head_with_pending_txs_is_not_ready() {
+        let lagging = SyncStatus {
+            peers: Some(3),
+            mempool_len: 5,
+            block_age_ms: 60_000,
+        };
+        assert_eq!(failing(&sync_checks(&PROBES, lagging)), ["last_block_age"]);
+
+        // Nothing to include, so an old head is just an idle chain.
+        let idle = SyncStatus {
+            mempool_len: 0,
+            ..lagging
+        };
+        assert!(failing(&sync_checks(&PROBES, idle)).is_empty());
+        assert!(!heartbeat_check(&PROBES, 60_000).ok);
+    }
+
+    #[test]
+    fn node_without_enough_peers_is_not_ready() {
+        let isolated = SyncStatus {
+            peers: Some(1),
+            mempool_len: 0,
+            block_age_ms: 0,
+        };
+        assert_eq!(failing(&sync_checks(&PROBES, isolated)), ["peers"]);
+
+        let untracked = SyncStatus {
+            peers: None,
+            ..isolated
+        };
+        assert!(failing(&sync_checks(&PROBES, untracked)).is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_txs_are_included_and_replay_to_the_same_root() -> anyhow::Result<()> {
+        let user_sk = SigningKey::from_bytes(&[7u8; 32]);
+        let user_pk = user_sk.verifying_key().to_bytes().to_vec();
+        let genesis = GenesisConfig {
+            initial_accounts: vec![(address_from_pubkey(&user_pk), 1_000_000)],
+            ..runtime::devnet_genesis()
+        };
+        let da = InMemoryDA::new();
+        let network = Arc::new(NoopConsensusNetwork);
+        let ctx = runtime::from_genesis(genesis.clone()).await?;
+        let proposer = create_node_with("node-0", ctx, da.clone(), network.clone(), None).await?;
+        let ctx = runtime::from_genesis(genesis).await?;
+        let replica = create_node_with("node-0", ctx, da, network, None).await?;
+
+        let transfer = |nonce: u64, amount: u128| {
+            let mut tx = runtime::Tx {
+                chain_id: "kova-devnet".into(),
+                nonce,
+                gas_limit: 50_000,
+                max_fee: Some(1),
+                max_priority_fee: Some(0),
+                gas_price: None,
+                payload: TxPayload::Transfer { to: [5u8; 32], amount },
+                public_key: user_pk.clone(),
+                signature: vec![],
+            };
+            tx.signature = sign_bytes(&user_sk, &tx_signing_bytes(&tx).unwrap());
+            tx
+        };
+        let overdraft = transfer(0, 5_000_000);
+        for tx in [overdraft.clone(), transfer(1, 10), transfer(7, 10)] {
+            enqueue_tx(&proposer, tx);
+        }
+        let block = build_block(&proposer).await.expect("mempool has txs");
+        assert_eq!(block.transactions.len(), 2);
+        // The nonce-gapped tx waits for a later block.
+        assert_eq!(proposer.mempool.lock().unwrap().len(), 1);
+
+        let (sealed, _) = execute_and_record(&proposer, &block).await?;
+        execute_and_record(&replica, &sealed).await?;
+        assert_eq!(proposer.state.state.commit().await?, replica.state.state.commit().await?);
+        for node in [&proposer, &replica] {
+            let receipts = node.receipts.lock().unwrap();
+            assert!(!receipts[&tx_hash(&overdraft)].success);
+            assert!(receipts[&tx_hash(&sealed.transactions[1])].success);
+        }
+        assert_eq!(recipient_balance(&replica, &[5u8; 32]), 10);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn diverged_blocks_roll_back_domain_state() -> anyhow::Result<()> {
+        let user_sk = SigningKey::from_bytes(&[8u8; 32]);
+        let user_pk = user_sk.verifying_key().to_bytes().to_vec();
+        let genesis = GenesisConfig {
+            initial_accounts: vec![(address_from_pubkey(&user_pk), 10_000_000)],
+            ..runtime::devnet_genesis()
+        };
+        let da = InMemoryDA::new();
+        let network = Arc::new(NoopConsensusNetwork);
+        let ctx = runtime::from_genesis(genesis.clone()).await?;
+        let proposer = create_node_with("node-0", ctx, da.clone(), network.clone(), None).await?;
+        let ctx = runtime::from_genesis(genesis).await?;
+        let replica = create_node_with("node-0", ctx, da, network, None).await?;
+
+        let domain_id = Uuid::new_v4();
+        let payloads = [
+            TxPayload::DomainCreate {
+                domain_id,
+                params: serde_json::json!({"kind": "wasm"}),
+            },
+            TxPayload::RollupBridgeDeposit { domain_id, amount: 500 },
+        ];
+        for (nonce, payload) in payloads.into_iter().enumerate() {
+            let mut tx = runtime::Tx {
+                chain_id: "kova-devnet".into(),
+                nonce: nonce as u64,
+                gas_limit: 300_000,
+                max_fee: Some(1),
+                max_priority_fee: Some(0),
+                gas_price: None,
+                payload,
+                public_key: user_pk.clone(),
+                signature: vec![],
+            };
+            tx.signature = sign_bytes(&user_sk, &tx_signing_bytes(&tx).unwrap());
+            enqueue_tx(&proposer, tx);
+        }
+        let block = build_block(&proposer).await.expect("mempool has txs");
+        let (sealed, _) = execute_and_record(&proposer, &block).await?;
+
+        let mut forged = sealed.clone();
+        forged.header.state_root = [1u8; 32];
+        let err = apply_and_record(&replica, &forged, hash_block(&forged), false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("state root mismatch"));
+        assert!(replica.state.domains.domain_state(&domain_id).inbox.is_empty());
+
+        execute_and_record(&replica, &sealed).await?;
+        assert_eq!(replica.state.domains.domain_state(&domain_id).inbox.len(), 1);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn consensus_da_state_end_to_end() -> anyhow::Result<()> {
         let node1_id = "node-1";
         let node2_id = "node-2";
         let node1_sk = derive_signing_key(node1_id);
         let node2_sk = derive_signing_key(node2_id);
+        let node1_bls = derive_bls_key(node1_id);
+        let node2_bls = derive_bls_key(node2_id);
         let validators = vec![
             GenesisValidator {
                 pubkey: node1_sk.verifying_key().to_bytes().to_vec(),
                 stake: 1_000,
                 commission_rate: 0,
+                bls_pubkey: node1_bls.public_key(),
+                bls_proof_of_possession: node1_bls.proof_of_possession(),
             },
             GenesisValidator {
                 pubkey: node2_sk.verifying_key().to_bytes().to_vec(),
                 stake: 1_000,
                 commission_rate: 0,
+                bls_pubkey: node2_bls.public_key(),
+                bls_proof_of_possession: node2_bls.proof_of_possession(),
             },
         ];
 
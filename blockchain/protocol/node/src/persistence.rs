@@ -0,0 +1,328 @@
+//! On-disk block log and shutdown checkpoint. Every committed block is
+//! appended and fsynced before it becomes visible, so after a crash the log
+//! is replayed on top of the last checkpoint and the node resumes at the
+//! last committed height. Proof job states are logged alongside the blocks.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use runtime::{apply_block, hash_block, Block, Hash};
+use serde::{Deserialize, Serialize};
+use state::{ChainState, StateStore};
+use tracing::{info, warn};
+
+use crate::proving::ProofRecord;
+use crate::{fork_choice, index_receipts, publish_view, record_block, ChainAnchor, Node};
+
+const WAL_FILE: &str = "blocks.wal";
+const PROOF_LOG_FILE: &str = "proofs.log";
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    height: u64,
+    block_hash: Hash,
+    chain: ChainState,
+}
+
+pub struct DiskStore {
+    dir: PathBuf,
+    wal: Mutex<File>,
+    proof_log: Mutex<File>,
+}
+
+impl DiskStore {
+    /// Opens the store and returns the blocks already logged. A torn final
+    /// record from an interrupted write is truncated away.
+    pub fn open(dir: &Path) -> anyhow::Result<(Self, Vec<Block>)> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(WAL_FILE);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let mut blocks = Vec::new();
+        let mut valid_len = 0;
+        for record in bytes.split_inclusive(|b| *b == b'\n') {
+            if !record.ends_with(b"\n") {
+                break;
+            }
+            let block: Block = serde_json::from_slice(record)
+                .with_context(|| format!("corrupt block log record {}", blocks.len()))?;
+            blocks.push(block);
+            valid_len += record.len();
+        }
+        let wal = OpenOptions::new().create(true).append(true).open(&path)?;
+        if valid_len < bytes.len() {
+            warn!("dropping torn record at the end of {}", path.display());
+            wal.set_len(valid_len as u64)?;
+        }
+        let proof_log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(PROOF_LOG_FILE))?;
+        Ok((
+            Self {
+                dir: dir.to_path_buf(),
+                wal: Mutex::new(wal),
+                proof_log: Mutex::new(proof_log),
+            },
+            blocks,
+        ))
+    }
+
+    pub fn append(&self, block: &Block) -> anyhow::Result<()> {
+        let mut record = serde_json::to_vec(block)?;
+        record.push(b'\n');
+        let mut wal = self.wal.lock().unwrap();
+        wal.write_all(&record)?;
+        wal.sync_data()?;
+        Ok(())
+    }
+
+    pub fn append_proof(&self, record: &ProofRecord) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut log = self.proof_log.lock().unwrap();
+        log.write_all(&line)?;
+        log.sync_data()?;
+        Ok(())
+    }
+
+    /// Proof records in the order they were logged. Unlike blocks a proof can
+    /// always be regenerated, so unreadable records, such as one torn by a
+    /// crash and then appended to, are skipped.
+    pub fn load_proofs(&self) -> anyhow::Result<Vec<ProofRecord>> {
+        let bytes = match fs::read(self.dir.join(PROOF_LOG_FILE)) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut records = Vec::new();
+        for line in bytes.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+            match serde_json::from_slice(line) {
+                Ok(record) => records.push(record),
+                Err(err) => warn!("skipping unreadable proof log record: {err}"),
+            }
+        }
+        Ok(records)
+    }
+
+    fn load_checkpoint(&self) -> anyhow::Result<Option<Checkpoint>> {
+        match fs::read(self.dir.join(CHECKPOINT_FILE)) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write_checkpoint(&self, checkpoint: &Checkpoint) -> anyhow::Result<()> {
+        let tmp = self.dir.join(format!("{CHECKPOINT_FILE}.tmp"));
+        fs::write(&tmp, serde_json::to_vec(checkpoint)?)?;
+        File::open(&tmp)?.sync_all()?;
+        fs::rename(tmp, self.dir.join(CHECKPOINT_FILE))?;
+        Ok(())
+    }
+}
+
+/// `DATA_DIR/<chain id>`; persistence is off when `DATA_DIR` is unset.
+fn data_dir(chain_id: &str) -> Option<PathBuf> {
+    env::var("DATA_DIR")
+        .ok()
+        .map(|dir| PathBuf::from(dir).join(chain_id))
+}
+
+/// Restores the checkpoint when it matches a logged block, re-executes the
+/// blocks after it and attaches the store so new blocks are logged.
+pub async fn recover(node: &mut Node) -> anyhow::Result<()> {
+    let Some(dir) = data_dir(&node.state.chain_id) else {
+        return Ok(());
+    };
+    let (store, blocks) = DiskStore::open(&dir)?;
+    attach(node, store, blocks).await
+}
+
+/// The chain ending at the last logged block. Blocks orphaned by a reorg stay
+/// in the log, and the branch that replaced them is logged after them.
+fn canonical_chain(blocks: Vec<Block>) -> Vec<Block> {
+    let Some(tip) = blocks.last().cloned() else {
+        return blocks;
+    };
+    let by_hash: HashMap<Hash, Block> = blocks.into_iter().map(|b| (hash_block(&b), b)).collect();
+    let mut chain = vec![tip];
+    while let Some(parent) = by_hash.get(&chain[chain.len() - 1].header.parent_hash) {
+        chain.push(parent.clone());
+    }
+    chain.reverse();
+    chain
+}
+
+async fn attach(node: &mut Node, store: DiskStore, blocks: Vec<Block>) -> anyhow::Result<()> {
+    let blocks = canonical_chain(blocks);
+    if let Some(first) = blocks.first() {
+        if first.header.height > 0 && node.anchor.is_none() {
+            node.anchor = Some(ChainAnchor {
+                height: first.header.height - 1,
+                hash: first.header.parent_hash,
+            });
+        }
+    }
+    let checkpoint = store.load_checkpoint()?.filter(|cp| {
+        blocks
+            .iter()
+            .any(|b| b.header.height == cp.height && hash_block(b) == cp.block_hash)
+    });
+    let resume_after = match checkpoint {
+        Some(cp) => {
+            node.state.state.put_chain_state(cp.chain).await?;
+            node.state.state.archive(cp.height).await?;
+            Some(cp.height)
+        }
+        None => None,
+    };
+
+    for block in &blocks {
+        let height = block.header.height;
+        let replay = match resume_after {
+            Some(checkpointed) => height > checkpointed,
+            None => true,
+        };
+        if replay {
+            let result = apply_block(&node.state, block).await?;
+            if result.state_root != block.header.state_root
+                || result.domain_roots != block.header.domain_roots
+            {
+                anyhow::bail!(
+                    "block log replay diverged at height {height}; \
+                     restore from SNAPSHOT_CHECKPOINT or clear {}",
+                    store.dir.display()
+                );
+            }
+            index_receipts(node, block, result.receipts);
+        }
+        record_block(node, block, hash_block(block));
+    }
+    if let Some(tip) = blocks.last() {
+        info!(
+            "recovered {} blocks up to height {} (checkpoint {:?})",
+            blocks.len(),
+            tip.header.height,
+            resume_after
+        );
+    }
+    node.disk = Some(Arc::new(store));
+    publish_view(node).await?;
+    fork_choice::reset(node);
+    Ok(())
+}
+
+/// Syncs the log and checkpoints state at the current tip. Called once block
+/// production has stopped.
+pub async fn flush(node: &Node) -> anyhow::Result<()> {
+    let Some(store) = node.disk.as_ref() else {
+        return Ok(());
+    };
+    store.wal.lock().unwrap().sync_all()?;
+    let Some(tip) = node.blocks.lock().unwrap().last().cloned() else {
+        return Ok(());
+    };
+    let checkpoint = Checkpoint {
+        height: tip.header.height,
+        block_hash: hash_block(&tip),
+        chain: node.state.state.get_chain_state().await?,
+    };
+    store.write_checkpoint(&checkpoint)?;
+    info!("checkpointed state at height {}", checkpoint.height);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{build_block, create_node_with, enqueue_tx, execute_and_record};
+    use da::InMemoryDA;
+    use ed25519_dalek::SigningKey;
+    use networking::NoopConsensusNetwork;
+    use runtime::{
+        address_from_pubkey, devnet_genesis, from_genesis, sign_bytes, tx_signing_bytes,
+        GenesisConfig, Tx, TxPayload,
+    };
+
+    fn user() -> SigningKey {
+        SigningKey::from_bytes(&[9u8; 32])
+    }
+
+    async fn fresh_node() -> anyhow::Result<Node> {
+        let user_pk = user().verifying_key().to_bytes();
+        let ctx = from_genesis(GenesisConfig {
+            initial_accounts: vec![(address_from_pubkey(&user_pk), 1_000_000)],
+            ..devnet_genesis()
+        })
+        .await?;
+        let network = Arc::new(NoopConsensusNetwork);
+        create_node_with("node-0", ctx, InMemoryDA::new(), network, None).await
+    }
+
+    fn signed_transfer(nonce: u64) -> anyhow::Result<Tx> {
+        let mut tx = Tx {
+            chain_id: "kova-devnet".into(),
+            nonce,
+            gas_limit: 50_000,
+            max_fee: Some(1),
+            max_priority_fee: Some(0),
+            gas_price: None,
+            payload: TxPayload::Transfer {
+                to: [5u8; 32],
+                amount: 10,
+            },
+            public_key: user().verifying_key().to_bytes().to_vec(),
+            signature: vec![],
+        };
+        tx.signature = sign_bytes(&user(), &tx_signing_bytes(&tx)?);
+        Ok(tx)
+    }
+
+    #[tokio::test]
+    async fn restart_replays_logged_blocks_after_checkpoint() -> anyhow::Result<()> {
+        let dir = env::temp_dir().join(format!("kova-wal-{}", uuid::Uuid::new_v4()));
+        let mut node = fresh_node().await?;
+        let (store, logged) = DiskStore::open(&dir)?;
+        assert!(logged.is_empty());
+        attach(&mut node, store, logged).await?;
+
+        for nonce in 0..3 {
+            enqueue_tx(&node, signed_transfer(nonce)?);
+            let block = build_block(&node).await.expect("mempool has a tx");
+            execute_and_record(&node, &block).await?;
+            if nonce == 0 {
+                flush(&node).await?;
+            }
+        }
+        let expected = node.state.state.get_chain_state().await?;
+        drop(node);
+
+        // Simulate a crash mid-append.
+        let mut wal = OpenOptions::new().append(true).open(dir.join(WAL_FILE))?;
+        wal.write_all(b"{\"header\":")?;
+
+        let mut restarted = fresh_node().await?;
+        let (store, logged) = DiskStore::open(&dir)?;
+        assert_eq!(logged.len(), 3);
+        attach(&mut restarted, store, logged).await?;
+        assert_eq!(crate::next_height(&restarted), 3);
+        let recovered = restarted.state.state.get_chain_state().await?;
+        assert_eq!(
+            serde_json::to_value(&recovered)?,
+            serde_json::to_value(&expected)?
+        );
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,419 @@
+//! Block proving off the commit path. A committed block becomes a proof job
+//! on a bounded queue and worker tasks prove it in the background, so a slow
+//! prover never holds up the proposer. `/block_proof/:height` reports each
+//! job as pending, complete or failed, and a finished proof is attached to
+//! its block whenever it lands. Job states are logged next to the block log
+//! and pending jobs are queued again after a restart.
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use runtime::{Block, BlockApplyResult, Hash};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing::{info, warn};
+use zk_core::{BlockProof, ProgramId, ProofRequest};
+
+use crate::Node;
+
+const DEFAULT_PROOF_WORKERS: usize = 2;
+const DEFAULT_PROOF_QUEUE: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ProofStatus {
+    Pending,
+    Complete { proof: BlockProof },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofJob {
+    pub block_hash: Hash,
+    pub height: u64,
+    pub state_root: Hash,
+    pub request: ProofRequest,
+}
+
+/// One line of the proof log. Pending records carry the job so it can be
+/// queued again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProofRecord {
+    pub block_hash: Hash,
+    pub height: u64,
+    pub status: ProofStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<ProofJob>,
+}
+
+/// What `/block_proof/:height` serves.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockProofStatus {
+    pub height: u64,
+    pub block_hash: Hash,
+    #[serde(flatten)]
+    pub status: ProofStatus,
+}
+
+#[derive(Clone, Default)]
+pub struct ProofQueue {
+    statuses: Arc<Mutex<HashMap<Hash, (u64, ProofStatus)>>>,
+    /// Unset until `start` runs, or when `PROOF_WORKERS=0`; jobs are then
+    /// proved inline.
+    jobs: Option<mpsc::Sender<ProofJob>>,
+}
+
+impl ProofQueue {
+    pub fn status(&self, block_hash: &Hash) -> Option<BlockProofStatus> {
+        let statuses = self.statuses.lock().unwrap();
+        statuses
+            .get(block_hash)
+            .map(|(height, status)| BlockProofStatus {
+                height: *height,
+                block_hash: *block_hash,
+                status: status.clone(),
+            })
+    }
+
+    fn depth(&self) -> i64 {
+        self.jobs
+            .as_ref()
+            .map_or(0, |tx| (tx.max_capacity() - tx.capacity()) as i64)
+    }
+}
+
+/// The block program's input for an applied block.
+pub fn block_job(
+    block: &Block,
+    result: &BlockApplyResult,
+    block_id: Hash,
+) -> anyhow::Result<ProofJob> {
+    let events_root = zk_program_block::hash_events(&result.events);
+    let witness = zk_program_block::encode_witness(
+        block,
+        result.state_root,
+        &result.events,
+        result.gas_used,
+    )?;
+    let da_root = block
+        .header
+        .da_commitment
+        .as_ref()
+        .map(|c| c.root)
+        .unwrap_or([0u8; 32]);
+    let commitments = zk_program_block::commitments(result.state_root, events_root, da_root);
+    Ok(ProofJob {
+        block_hash: block_id,
+        height: block.header.height,
+        state_root: result.state_root,
+        request: ProofRequest {
+            program_id: ProgramId::Block,
+            witness,
+            commitments: Some(commitments),
+        },
+    })
+}
+
+/// Queues `job`. A full queue fails the job rather than stalling the
+/// caller; without workers the job is proved before this returns.
+pub async fn submit(node: &Node, job: ProofJob) {
+    set_status(
+        node,
+        job.block_hash,
+        job.height,
+        ProofStatus::Pending,
+        Some(&job),
+    );
+    let Some(jobs) = node.proving.jobs.as_ref() else {
+        prove(node, job).await;
+        return;
+    };
+    match jobs.try_send(job) {
+        Ok(()) => node.metrics.zk_proof_queue_depth.set(node.proving.depth()),
+        Err(TrySendError::Full(job)) | Err(TrySendError::Closed(job)) => {
+            warn!("proof queue full, dropping job for height {}", job.height);
+            let failed = ProofStatus::Failed {
+                error: "proof queue full".into(),
+            };
+            set_status(node, job.block_hash, job.height, failed, None);
+            *node.last_zk_error.lock().unwrap() = Some("proof queue full".into());
+        }
+    }
+}
+
+/// Starts the workers and restores logged job states, queueing pending jobs
+/// again. Worker count and queue size come from `PROOF_WORKERS` and
+/// `PROOF_QUEUE_SIZE`.
+pub fn start(node: &mut Node) -> anyhow::Result<()> {
+    if node.zk.is_none() {
+        return Ok(());
+    }
+    let env_usize = |key: &str, default: usize| {
+        env::var(key)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+    let workers = env_usize("PROOF_WORKERS", DEFAULT_PROOF_WORKERS);
+    let capacity = env_usize("PROOF_QUEUE_SIZE", DEFAULT_PROOF_QUEUE).max(1);
+
+    let mut pending = Vec::new();
+    if let Some(disk) = &node.disk {
+        let mut statuses = node.proving.statuses.lock().unwrap();
+        let mut jobs = HashMap::new();
+        for record in disk.load_proofs()? {
+            if let Some(job) = record.job {
+                jobs.insert(record.block_hash, job);
+            }
+            statuses.insert(record.block_hash, (record.height, record.status));
+        }
+        // Only the last record for a block counts.
+        pending.extend(jobs.into_values().filter(|job| {
+            matches!(
+                statuses.get(&job.block_hash),
+                Some((_, ProofStatus::Pending))
+            )
+        }));
+        pending.sort_by_key(|job| job.height);
+    }
+    if workers == 0 {
+        info!("proving inline");
+    } else {
+        let (tx, rx) = mpsc::channel(capacity);
+        node.proving.jobs = Some(tx);
+        let rx = Arc::new(tokio::sync::Mutex::new(rx));
+        for _ in 0..workers {
+            spawn_worker(node.clone(), rx.clone());
+        }
+        info!("proving with {workers} workers, queue of {capacity}");
+    }
+    if !pending.is_empty() {
+        info!("resuming {} pending proof jobs", pending.len());
+        let node = node.clone();
+        tokio::spawn(async move {
+            for job in pending {
+                match node.proving.jobs.as_ref() {
+                    // Recovered jobs wait for room instead of failing.
+                    Some(jobs) => {
+                        if jobs.send(job).await.is_err() {
+                            break;
+                        }
+                        node.metrics.zk_proof_queue_depth.set(node.proving.depth());
+                    }
+                    None => prove(&node, job).await,
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+fn spawn_worker(node: Node, rx: Arc<tokio::sync::Mutex<mpsc::Receiver<ProofJob>>>) {
+    tokio::spawn(async move {
+        loop {
+            let Some(job) = rx.lock().await.recv().await else {
+                break;
+            };
+            node.metrics.zk_proof_queue_depth.set(node.proving.depth());
+            prove(&node, job).await;
+        }
+    });
+}
+
+async fn prove(node: &Node, job: ProofJob) {
+    let Some(zk) = node.zk.clone() else {
+        return;
+    };
+    let started = std::time::Instant::now();
+    let outcome = async {
+        let artifact = zk
+            .prove(job.request.clone())
+            .await
+            .map_err(|e| anyhow::anyhow!("prove error: {e}"))?;
+        node.metrics
+            .zk_proof_seconds
+            .observe(started.elapsed().as_secs_f64());
+        zk.verify(&artifact)
+            .await
+            .map_err(|e| anyhow::anyhow!("verify error: {e}"))?;
+        anyhow::Ok(artifact)
+    }
+    .await;
+    let status = match outcome {
+        Ok(proof) => {
+            *node.last_zk_error.lock().unwrap() = None;
+            ProofStatus::Complete {
+                proof: BlockProof {
+                    block_hash: job.block_hash,
+                    state_root: job.state_root,
+                    proof,
+                },
+            }
+        }
+        Err(err) => {
+            warn!(
+                "zk proof generation failed for height {}: {err}",
+                job.height
+            );
+            *node.last_zk_error.lock().unwrap() = Some(err.to_string());
+            ProofStatus::Failed {
+                error: err.to_string(),
+            }
+        }
+    };
+    set_status(node, job.block_hash, job.height, status, None);
+}
+
+fn set_status(
+    node: &Node,
+    block_hash: Hash,
+    height: u64,
+    status: ProofStatus,
+    job: Option<&ProofJob>,
+) {
+    if let Some(disk) = &node.disk {
+        let record = ProofRecord {
+            block_hash,
+            height,
+            status: status.clone(),
+            job: job.cloned(),
+        };
+        if let Err(err) = disk.append_proof(&record) {
+            warn!("failed to log proof status for height {height}: {err}");
+        }
+    }
+    node.proving
+        .statuses
+        .lock()
+        .unwrap()
+        .insert(block_hash, (height, status));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        build_block, create_node_with, enqueue_tx, execute_and_record, persistence::DiskStore,
+    };
+    use da::InMemoryDA;
+    use ed25519_dalek::SigningKey;
+    use networking::NoopConsensusNetwork;
+    use runtime::{
+        address_from_pubkey, devnet_genesis, from_genesis, sign_bytes, tx_signing_bytes,
+        GenesisConfig, Tx, TxPayload,
+    };
+    use tokio::time::{sleep, Duration};
+    use zk_core::ZkBackend;
+    use zk_risczero::{Risc0Backend, Risc0Config, Risc0Program};
+
+    async fn proving_node() -> anyhow::Result<Node> {
+        let user = SigningKey::from_bytes(&[9u8; 32]);
+        let user_pk = user.verifying_key().to_bytes();
+        let ctx = from_genesis(GenesisConfig {
+            initial_accounts: vec![(address_from_pubkey(&user_pk), 1_000_000)],
+            ..devnet_genesis()
+        })
+        .await?;
+        // Without the `risc0` feature this backend proves with stubs.
+        let zk: Arc<dyn ZkBackend> = Arc::new(Risc0Backend::new(Risc0Config {
+            programs: vec![Risc0Program {
+                id: ProgramId::Block,
+                image_id: [1u8; 32],
+                elf: vec![0x7f, b'E', b'L', b'F'],
+                name: "block_transition".into(),
+                version: "0.1.0".into(),
+            }],
+            verify_only: false,
+        })?);
+        let network = Arc::new(NoopConsensusNetwork);
+        create_node_with("node-0", ctx, InMemoryDA::new(), network, Some(zk)).await
+    }
+
+    fn signed_transfer(nonce: u64) -> anyhow::Result<Tx> {
+        let user = SigningKey::from_bytes(&[9u8; 32]);
+        let mut tx = Tx {
+            chain_id: "kova-devnet".into(),
+            nonce,
+            gas_limit: 50_000,
+            max_fee: Some(1),
+            max_priority_fee: Some(0),
+            gas_price: None,
+            payload: TxPayload::Transfer {
+                to: [5u8; 32],
+                amount: 10,
+            },
+            public_key: user.verifying_key().to_bytes().to_vec(),
+            signature: vec![],
+        };
+        tx.signature = sign_bytes(&user, &tx_signing_bytes(&tx)?);
+        Ok(tx)
+    }
+
+    #[tokio::test]
+    async fn proofs_attach_after_commit_and_pending_jobs_survive_restart() -> anyhow::Result<()> {
+        let dir = env::temp_dir().join(format!("kova-proofs-{}", uuid::Uuid::new_v4()));
+        let mut node = proving_node().await?;
+        let (store, _) = DiskStore::open(&dir)?;
+        node.disk = Some(Arc::new(store));
+        start(&mut node)?;
+
+        enqueue_tx(&node, signed_transfer(0)?);
+        let block = build_block(&node).await.expect("mempool has a tx");
+        let (_, block_id) = execute_and_record(&node, &block).await?;
+        let mut status = None;
+        for _ in 0..50 {
+            status = node.proving.status(&block_id).map(|s| s.status);
+            if matches!(status, Some(ProofStatus::Complete { .. })) {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        let Some(ProofStatus::Complete { proof }) = status else {
+            panic!("proof was not attached: {status:?}");
+        };
+        assert_eq!(proof.block_hash, block_id);
+        node.zk.as_ref().unwrap().verify(&proof.proof).await?;
+
+        // A job logged as pending when the node stopped is proved on restart.
+        let job = ProofJob {
+            block_hash: [7u8; 32],
+            height: 2,
+            state_root: proof.state_root,
+            request: ProofRequest {
+                program_id: ProgramId::Block,
+                witness: vec![1, 2, 3],
+                commitments: None,
+            },
+        };
+        set_status(
+            &node,
+            job.block_hash,
+            job.height,
+            ProofStatus::Pending,
+            Some(&job),
+        );
+        drop(node);
+
+        let mut restarted = proving_node().await?;
+        let (store, _) = DiskStore::open(&dir)?;
+        restarted.disk = Some(Arc::new(store));
+        start(&mut restarted)?;
+        assert!(matches!(
+            restarted.proving.status(&block_id).map(|s| s.status),
+            Some(ProofStatus::Complete { .. })
+        ));
+        let mut resumed = None;
+        for _ in 0..50 {
+            resumed = restarted.proving.status(&[7u8; 32]).map(|s| s.status);
+            if matches!(resumed, Some(ProofStatus::Complete { .. })) {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        assert!(matches!(resumed, Some(ProofStatus::Complete { .. })));
+
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
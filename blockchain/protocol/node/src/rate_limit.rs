@@ -0,0 +1,276 @@
+//! Throttling for the RPC server: per-IP token buckets, a request body limit,
+//! a cap on requests in flight and an IP allowlist for admin endpoints.
+//! Throttled requests get a 429 with a `Retry-After` hint.
+
+use axum::{
+    extract::{ConnectInfo, DefaultBodyLimit, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+const DEFAULT_RATE_PER_SEC: f64 = 50.0;
+const DEFAULT_BURST: f64 = 100.0;
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+const DEFAULT_MAX_CONCURRENT: usize = 256;
+/// A bucket idle this long has refilled, so dropping it loses nothing. Once
+/// the table is full only such buckets make room for new IPs.
+const BUCKET_IDLE: Duration = Duration::from_secs(300);
+const MAX_BUCKETS: usize = 10_000;
+/// Endpoints, relative to a chain's router, that only allowlisted IPs may call.
+const ADMIN_PATHS: &[&str] = &["/divergence/resume", "/divergence/resync"];
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Requests per second each IP's bucket refills at.
+    pub rate_per_sec: f64,
+    /// Requests an idle IP may send at once.
+    pub burst: f64,
+    pub max_body_bytes: usize,
+    /// Requests handled at once across all clients.
+    pub max_concurrent: usize,
+    pub admin_allowlist: Vec<IpAddr>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            rate_per_sec: DEFAULT_RATE_PER_SEC,
+            burst: DEFAULT_BURST,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_concurrent: DEFAULT_MAX_CONCURRENT,
+            admin_allowlist: vec![IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)],
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Reads `RPC_RATE_LIMIT` (requests per second per IP), `RPC_RATE_BURST`,
+    /// `RPC_MAX_BODY_BYTES`, `RPC_MAX_CONCURRENT` and `RPC_ADMIN_ALLOWLIST`
+    /// (comma-separated IPs). Unset or invalid values keep the defaults.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let positive = |key: &str| {
+            env::var(key)
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|v| v.is_finite() && *v > 0.0)
+        };
+        let count = |key: &str| env::var(key).ok().and_then(|v| v.parse::<usize>().ok()).filter(|v| *v > 0);
+        Self {
+            rate_per_sec: positive("RPC_RATE_LIMIT").unwrap_or(defaults.rate_per_sec),
+            burst: positive("RPC_RATE_BURST").unwrap_or(defaults.burst).max(1.0),
+            max_body_bytes: count("RPC_MAX_BODY_BYTES").unwrap_or(defaults.max_body_bytes),
+            max_concurrent: count("RPC_MAX_CONCURRENT").unwrap_or(defaults.max_concurrent),
+            admin_allowlist: env::var("RPC_ADMIN_ALLOWLIST")
+                .map(|list| list.split(',').filter_map(|ip| ip.trim().parse().ok()).collect())
+                .unwrap_or(defaults.admin_allowlist),
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(config: &RateLimitConfig, now: Instant) -> Self {
+        Self {
+            tokens: config.burst,
+            updated: now,
+        }
+    }
+
+    fn take(&mut self, config: &RateLimitConfig, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.rate_per_sec).min(config.burst);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / config.rate_per_sec,
+            ))
+        }
+    }
+}
+
+struct Buckets {
+    by_ip: HashMap<IpAddr, Bucket>,
+    /// The keys of `by_ip` ordered by last use, oldest first.
+    by_age: BTreeSet<(Instant, IpAddr)>,
+    /// Shared by new IPs while every one of the `MAX_BUCKETS` buckets is in
+    /// use, so a flood of fresh addresses cannot grow the table or reset the
+    /// buckets of active clients.
+    overflow: Bucket,
+}
+
+struct Limiter {
+    config: RateLimitConfig,
+    buckets: Mutex<Buckets>,
+    in_flight: Arc<Semaphore>,
+}
+
+impl Limiter {
+    fn new(config: RateLimitConfig) -> Self {
+        Self {
+            in_flight: Arc::new(Semaphore::new(config.max_concurrent)),
+            buckets: Mutex::new(Buckets {
+                by_ip: HashMap::new(),
+                by_age: BTreeSet::new(),
+                overflow: Bucket::full(&config, Instant::now()),
+            }),
+            config,
+        }
+    }
+
+    /// Takes a token from `ip`'s bucket, or returns how long until one refills.
+    fn take(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut guard = self.buckets.lock().unwrap();
+        let buckets = &mut *guard;
+        if buckets.by_ip.len() >= MAX_BUCKETS && !buckets.by_ip.contains_key(&ip) {
+            match buckets.by_age.first().copied() {
+                Some((updated, oldest))
+                    if now.saturating_duration_since(updated) >= BUCKET_IDLE =>
+                {
+                    buckets.by_age.pop_first();
+                    buckets.by_ip.remove(&oldest);
+                }
+                _ => return buckets.overflow.take(&self.config, now),
+            }
+        }
+        let bucket = buckets
+            .by_ip
+            .entry(ip)
+            .or_insert_with(|| Bucket::full(&self.config, now));
+        buckets.by_age.remove(&(bucket.updated, ip));
+        buckets.by_age.insert((now, ip));
+        bucket.take(&self.config, now)
+    }
+}
+
+fn is_admin(path: &str) -> bool {
+    ADMIN_PATHS.iter().any(|admin| path.ends_with(admin))
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    let secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, secs.to_string())],
+        "rate limit exceeded",
+    )
+        .into_response()
+}
+
+async fn throttle(
+    State(limiter): State<Arc<Limiter>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = addr.ip();
+    if is_admin(request.uri().path()) && !limiter.config.admin_allowlist.contains(&ip) {
+        return (StatusCode::FORBIDDEN, "admin endpoint").into_response();
+    }
+    if let Err(wait) = limiter.take(ip, Instant::now()) {
+        return too_many_requests(wait);
+    }
+    let Ok(_permit) = limiter.in_flight.clone().try_acquire_owned() else {
+        return too_many_requests(Duration::from_secs(1));
+    };
+    next.run(request).await
+}
+
+/// Applies `config` to every route of `router`. The server must be started
+/// with `into_make_service_with_connect_info::<SocketAddr>` so client
+/// addresses are known.
+pub fn layer(router: Router, config: RateLimitConfig) -> Router {
+    let max_body_bytes = config.max_body_bytes;
+    router
+        .layer(middleware::from_fn_with_state(Arc::new(Limiter::new(config)), throttle))
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_allow_a_burst_then_hint_when_to_retry() {
+        let limiter = Limiter::new(RateLimitConfig {
+            rate_per_sec: 2.0,
+            burst: 3.0,
+            ..RateLimitConfig::default()
+        });
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.take(ip, start).is_ok());
+        }
+        let wait = limiter.take(ip, start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+        assert!(limiter.take(other, start).is_ok());
+
+        assert!(limiter.take(ip, start + Duration::from_millis(500)).is_ok());
+        assert!(limiter.take(ip, start + Duration::from_millis(500)).is_err());
+        // Idle time refills up to the burst, never past it.
+        let later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.take(ip, later).is_ok());
+        }
+        assert!(limiter.take(ip, later).is_err());
+    }
+
+    #[test]
+    fn a_full_table_evicts_idle_buckets_and_shares_one_otherwise() {
+        let config = RateLimitConfig {
+            rate_per_sec: 1.0,
+            burst: 2.0,
+            ..RateLimitConfig::default()
+        };
+        let limiter = Limiter::new(config);
+        let ip = |n: u32| IpAddr::V4(Ipv4Addr::from(0x0a00_0000 + n));
+        let start = Instant::now();
+        for n in 0..MAX_BUCKETS as u32 {
+            assert!(limiter.take(ip(n), start).is_ok());
+        }
+
+        // Every bucket is in use, so newcomers draw on one shared bucket.
+        let later = start + Duration::from_secs(1);
+        let newcomer = ip(MAX_BUCKETS as u32);
+        assert!(limiter.take(newcomer, later).is_ok());
+        assert!(limiter.take(ip(MAX_BUCKETS as u32 + 1), later).is_ok());
+        assert!(limiter.take(newcomer, later).is_err());
+        assert!(limiter.take(ip(0), later).is_ok());
+        assert_eq!(limiter.buckets.lock().unwrap().by_ip.len(), MAX_BUCKETS);
+
+        // Once the least recently used bucket goes idle it makes room.
+        let idle = start + BUCKET_IDLE;
+        assert!(limiter.take(newcomer, idle).is_ok());
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.by_ip.len(), MAX_BUCKETS);
+        assert_eq!(buckets.by_age.len(), MAX_BUCKETS);
+        assert!(buckets.by_ip.contains_key(&newcomer));
+        assert!(!buckets.by_ip.contains_key(&ip(1)));
+        assert!(buckets.by_ip.contains_key(&ip(0)));
+    }
+
+    #[test]
+    fn admin_paths_match_under_chain_prefixes() {
+        assert!(is_admin("/divergence/resume"));
+        assert!(is_admin("/chains/kova-devnet/divergence/resync"));
+        assert!(!is_admin("/divergence"));
+        assert!(!is_admin("/send_raw_tx"));
+    }
+}
@@ -0,0 +1,358 @@
+//! Push feed behind `/ws`. `execute_and_record` publishes every applied block
+//! to a broadcast bus; each socket filters the bus by its own subscriptions.
+
+use std::collections::BTreeMap;
+
+use axum::extract::ws::{Message, WebSocket};
+use runtime::{address_from_pubkey, Address, Block, Hash, Tx, TxPayload};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use state::{ChainState, ProposalStatus};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::{parse_address, tx_hash};
+
+pub const EVENT_BUS_CAPACITY: usize = 1_024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "channel", rename_all = "snake_case")]
+pub enum NodeEvent {
+    NewHead {
+        height: u64,
+        hash: String,
+        parent_hash: String,
+        proposer: String,
+        state_root: String,
+        gas_used: u64,
+        tx_count: usize,
+        timestamp: u64,
+    },
+    TxIncluded {
+        tx_hash: String,
+        height: u64,
+        block_hash: String,
+        /// Sender and any account the payload targets.
+        addresses: Vec<String>,
+    },
+    ProposalStatus {
+        proposal_id: Uuid,
+        status: ProposalStatus,
+        height: u64,
+    },
+    DomainEvent {
+        domain_id: Uuid,
+        kind: String,
+        tx_hash: String,
+        height: u64,
+    },
+    /// The canonical chain switched branches. Blocks from `height` on were
+    /// rolled back; `new_head` events follow for the blocks that replace them.
+    Reorg {
+        height: u64,
+        common_ancestor: String,
+        old_head: String,
+        new_head: String,
+        orphaned: Vec<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "channel", rename_all = "snake_case")]
+enum Subscription {
+    NewHeads,
+    Txs { address: String },
+    Proposals,
+    DomainEvents { domain_id: Option<Uuid> },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+enum ClientRequest {
+    Subscribe(Subscription),
+    Unsubscribe { id: u64 },
+}
+
+enum Filter {
+    NewHeads,
+    Txs(String),
+    Proposals,
+    DomainEvents(Option<Uuid>),
+}
+
+impl Filter {
+    fn from_subscription(sub: Subscription) -> anyhow::Result<Self> {
+        Ok(match sub {
+            Subscription::NewHeads => Filter::NewHeads,
+            Subscription::Txs { address } => {
+                let address =
+                    parse_address(&address).ok_or_else(|| anyhow::anyhow!("invalid address"))?;
+                Filter::Txs(hex::encode(address))
+            }
+            Subscription::Proposals => Filter::Proposals,
+            Subscription::DomainEvents { domain_id } => Filter::DomainEvents(domain_id),
+        })
+    }
+
+    fn matches(&self, event: &NodeEvent) -> bool {
+        match (self, event) {
+            (Filter::NewHeads, NodeEvent::NewHead { .. } | NodeEvent::Reorg { .. }) => true,
+            (Filter::Txs(address), NodeEvent::TxIncluded { addresses, .. }) => {
+                addresses.contains(address)
+            }
+            (Filter::Proposals, NodeEvent::ProposalStatus { .. }) => true,
+            (Filter::DomainEvents(wanted), NodeEvent::DomainEvent { domain_id, .. }) => {
+                wanted.is_none() || *wanted == Some(*domain_id)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Events for a freshly applied block. Proposal changes are only reported
+/// when both the pre- and post-state are available.
+pub fn block_events(
+    block: &Block,
+    block_hash: Hash,
+    states: Option<(&ChainState, &ChainState)>,
+) -> Vec<NodeEvent> {
+    let height = block.header.height;
+    let block_hex = hex::encode(block_hash);
+    let mut events = vec![NodeEvent::NewHead {
+        height,
+        hash: block_hex.clone(),
+        parent_hash: hex::encode(block.header.parent_hash),
+        proposer: hex::encode(block.header.proposer_id),
+        state_root: hex::encode(block.header.state_root),
+        gas_used: block.header.gas_used,
+        tx_count: block.transactions.len(),
+        timestamp: block.header.timestamp,
+    }];
+    for tx in &block.transactions {
+        let hash = hex::encode(tx_hash(tx));
+        events.push(NodeEvent::TxIncluded {
+            tx_hash: hash.clone(),
+            height,
+            block_hash: block_hex.clone(),
+            addresses: tx_addresses(tx).iter().map(hex::encode).collect(),
+        });
+        for (domain_id, kind) in domain_refs(&tx.payload) {
+            events.push(NodeEvent::DomainEvent {
+                domain_id,
+                kind: kind.into(),
+                tx_hash: hash.clone(),
+                height,
+            });
+        }
+    }
+    if let Some((before, after)) = states {
+        let mut changed: Vec<_> = after
+            .proposals
+            .values()
+            .filter(|p| before.proposals.get(&p.id).map(|b| &b.status) != Some(&p.status))
+            .collect();
+        changed.sort_by_key(|p| p.id);
+        events.extend(changed.into_iter().map(|p| NodeEvent::ProposalStatus {
+            proposal_id: p.id,
+            status: p.status.clone(),
+            height,
+        }));
+    }
+    events
+}
+
+fn tx_addresses(tx: &Tx) -> Vec<Address> {
+    let mut addresses = vec![address_from_pubkey(&tx.public_key)];
+    let target = match &tx.payload {
+        TxPayload::Transfer { to, .. } => Some(*to),
+        TxPayload::Delegate { validator, .. }
+        | TxPayload::Undelegate { validator, .. }
+        | TxPayload::ClaimRewards { validator } => Some(*validator),
+        TxPayload::Slash { validator, .. } => Some(*validator),
+        TxPayload::PrivacyWithdraw { recipient, .. } => Some(*recipient),
+        _ => None,
+    };
+    if let Some(target) = target.filter(|t| *t != addresses[0]) {
+        addresses.push(target);
+    }
+    addresses
+}
+
+fn domain_refs(payload: &TxPayload) -> Vec<(Uuid, &'static str)> {
+    match payload {
+        TxPayload::DomainExecute(call) => vec![(call.domain_id, "domain_execute")],
+        TxPayload::CrossDomainSend {
+            from_domain,
+            to_domain,
+            ..
+        } => vec![
+            (*from_domain, "cross_domain_send"),
+            (*to_domain, "cross_domain_send"),
+        ],
+        TxPayload::CrossDomainRelay { message, .. } => vec![(message.to, "cross_domain_relay")],
+        TxPayload::CrossDomainRefund {
+            from_domain,
+            to_domain,
+            ..
+        } => vec![
+            (*from_domain, "cross_domain_refund"),
+            (*to_domain, "cross_domain_refund"),
+        ],
+        TxPayload::DomainInboxProcess { domain_id, .. } => vec![(*domain_id, "inbox_process")],
+        TxPayload::FraudChallenge { domain_id, .. } => vec![(*domain_id, "fraud_challenge")],
+        TxPayload::DomainCreate { domain_id, .. } => vec![(*domain_id, "domain_create")],
+        TxPayload::DomainConfigUpdate { domain_id, .. } => {
+            vec![(*domain_id, "domain_config_update")]
+        }
+        TxPayload::DomainPause { domain_id } => vec![(*domain_id, "domain_pause")],
+        TxPayload::DomainResume { domain_id } => vec![(*domain_id, "domain_resume")],
+        TxPayload::DomainRetire { domain_id } => vec![(*domain_id, "domain_retire")],
+        TxPayload::RollupBatchCommit { domain_id, .. } => vec![(*domain_id, "batch_commit")],
+        TxPayload::RollupBridgeDeposit { domain_id, .. } => vec![(*domain_id, "bridge_deposit")],
+        TxPayload::RollupBridgeBurn { domain_id, .. } => vec![(*domain_id, "bridge_burn")],
+        TxPayload::RollupBridgeWithdraw { domain_id, .. } => {
+            vec![(*domain_id, "bridge_withdraw")]
+        }
+        TxPayload::ForcedWithdraw { domain_id, .. } => vec![(*domain_id, "forced_withdraw")],
+        TxPayload::RollupBatchClaim { domain_id, .. } => vec![(*domain_id, "batch_claim")],
+        TxPayload::FraudBisect { domain_id, .. } => vec![(*domain_id, "fraud_bisect")],
+        TxPayload::FraudRespond { domain_id, .. } => vec![(*domain_id, "fraud_respond")],
+        TxPayload::FraudDefend { domain_id, .. } => vec![(*domain_id, "fraud_defend")],
+        TxPayload::SequencerRegister { domain_id, .. } => {
+            vec![(*domain_id, "sequencer_register")]
+        }
+        TxPayload::SequencerExit { domain_id } => vec![(*domain_id, "sequencer_exit")],
+        TxPayload::PreconfirmationChallenge { preconfirmation } => {
+            vec![(preconfirmation.domain_id, "preconfirmation_challenge")]
+        }
+        TxPayload::ForceInclude { domain_id, .. } => vec![(*domain_id, "force_include")],
+        TxPayload::ForceIncludeProve { domain_id, .. } => {
+            vec![(*domain_id, "force_include_prove")]
+        }
+        TxPayload::LightClientPacket { packet, .. } => Uuid::parse_str(&packet.dst_domain)
+            .map(|to| vec![(to, "light_client_packet")])
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Drive one socket. Clients send
+/// `{"method": "subscribe", "channel": "txs", "address": "<hex>"}` (channels:
+/// `new_heads`, which also carries reorgs, `txs`, `proposals`, `domain_events`
+/// with optional `domain_id`) and `{"method": "unsubscribe", "id": n}`;
+/// matching events arrive as `{"subscription": n, "event": {...}}`.
+pub async fn serve(mut socket: WebSocket, mut events: broadcast::Receiver<NodeEvent>) {
+    let mut filters: BTreeMap<u64, Filter> = BTreeMap::new();
+    let mut next_id = 1u64;
+    loop {
+        tokio::select! {
+            msg = socket.recv() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let reply = handle_request(&text, &mut filters, &mut next_id);
+                if socket.send(Message::Text(reply.to_string())).await.is_err() {
+                    break;
+                }
+            }
+            event = events.recv() => {
+                let replies = match event {
+                    Ok(event) => filters
+                        .iter()
+                        .filter(|(_, filter)| filter.matches(&event))
+                        .map(|(id, _)| json!({ "subscription": id, "event": &event }))
+                        .collect(),
+                    Err(RecvError::Lagged(missed)) => {
+                        vec![json!({ "error": "lagged", "missed": missed })]
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                for reply in replies {
+                    if socket.send(Message::Text(reply.to_string())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn handle_request(
+    text: &str,
+    filters: &mut BTreeMap<u64, Filter>,
+    next_id: &mut u64,
+) -> serde_json::Value {
+    let request = match serde_json::from_str::<ClientRequest>(text) {
+        Ok(request) => request,
+        Err(err) => return json!({ "error": format!("invalid request: {err}") }),
+    };
+    match request {
+        ClientRequest::Subscribe(sub) => match Filter::from_subscription(sub) {
+            Ok(filter) => {
+                let id = *next_id;
+                *next_id += 1;
+                filters.insert(id, filter);
+                json!({ "subscribed": id })
+            }
+            Err(err) => json!({ "error": err.to_string() }),
+        },
+        ClientRequest::Unsubscribe { id } => {
+            json!({ "unsubscribed": id, "ok": filters.remove(&id).is_some() })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_route_events_by_channel() {
+        let mut filters = BTreeMap::new();
+        let mut next_id = 1;
+        let address = hex::encode([5u8; 32]);
+        let reply = handle_request(
+            &format!(r#"{{"method":"subscribe","channel":"txs","address":"{address}"}}"#),
+            &mut filters,
+            &mut next_id,
+        );
+        assert_eq!(reply["subscribed"], 1);
+        let reply = handle_request(
+            r#"{"method":"subscribe","channel":"bogus"}"#,
+            &mut filters,
+            &mut next_id,
+        );
+        assert!(reply.get("error").is_some());
+
+        let tx_event = |addresses: Vec<String>| NodeEvent::TxIncluded {
+            tx_hash: String::new(),
+            height: 1,
+            block_hash: String::new(),
+            addresses,
+        };
+        assert!(filters[&1].matches(&tx_event(vec![address.clone()])));
+        assert!(!filters[&1].matches(&tx_event(vec![hex::encode([6u8; 32])])));
+
+        let domain = Uuid::new_v4();
+        let any_domain = Filter::DomainEvents(None);
+        let one_domain = Filter::DomainEvents(Some(Uuid::new_v4()));
+        let event = NodeEvent::DomainEvent {
+            domain_id: domain,
+            kind: "domain_execute".into(),
+            tx_hash: String::new(),
+            height: 1,
+        };
+        assert!(any_domain.matches(&event));
+        assert!(!one_domain.matches(&event));
+
+        let reply = handle_request(
+            r#"{"method":"unsubscribe","id":1}"#,
+            &mut filters,
+            &mut next_id,
+        );
+        assert_eq!(reply["ok"], true);
+        assert!(filters.is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, apply_block, apply_tx, bootstrap_state, sign_bytes, tx_signing_bytes,
+    verify_tx_signature, Block, BlockHeader, ExecutionContext, Tx, TxPayload,
+};
+use state::{Account, InMemoryStateStore, StateStore};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+type Ctx = ExecutionContext<InMemoryStateStore>;
+
+fn key(i: u32) -> SigningKey {
+    let mut seed = [7u8; 32];
+    seed[..4].copy_from_slice(&i.to_le_bytes());
+    SigningKey::from_bytes(&seed)
+}
+
+fn signed(sk: &SigningKey, nonce: u64, payload: TxPayload) -> Tx {
+    let mut tx = Tx {
+        chain_id: "kova-devnet".into(),
+        nonce,
+        gas_limit: 300_000,
+        max_fee: None,
+        max_priority_fee: None,
+        gas_price: Some(1),
+        payload,
+        public_key: sk.verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    };
+    let msg = tx_signing_bytes(&tx).unwrap();
+    tx.signature = sign_bytes(sk, &msg);
+    tx
+}
+
+async fn fund(ctx: &Ctx, sk: &SigningKey) {
+    let address = address_from_pubkey(&sk.verifying_key().to_bytes());
+    ctx.state
+        .put_account(Account {
+            address,
+            nonce: 0,
+            balance_x: 1_000_000_000,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await
+        .unwrap();
+}
+
+/// Fresh funded context plus a validator owned by `key(1)`, so every payload
+/// kind has what it needs.
+fn setup(rt: &Runtime) -> Ctx {
+    let ctx = bootstrap_state();
+    rt.block_on(async {
+        fund(&ctx, &key(0)).await;
+        fund(&ctx, &key(1)).await;
+        let stake = signed(&key(1), 0, TxPayload::Stake { amount: 1_000_000 });
+        apply_tx(&ctx, &stake, 0).await.unwrap();
+    });
+    ctx
+}
+
+fn payloads() -> Vec<(&'static str, TxPayload)> {
+    let validator = address_from_pubkey(&key(1).verifying_key().to_bytes());
+    vec![
+        (
+            "transfer",
+            TxPayload::Transfer {
+                to: [9u8; 32],
+                amount: 1_000,
+            },
+        ),
+        ("stake", TxPayload::Stake { amount: 10_000 }),
+        (
+            "delegate",
+            TxPayload::Delegate {
+                validator,
+                amount: 10_000,
+            },
+        ),
+        (
+            "privacy_deposit",
+            TxPayload::PrivacyDeposit {
+                commitment: [3u8; 32],
+                amount: 1_000,
+                pool: None,
+                memo: None,
+            },
+        ),
+        (
+            "governance_proposal",
+            TxPayload::GovernanceProposal {
+                payload: serde_json::json!({ "param": "base_fee", "value": 2 }),
+                kind: None,
+            },
+        ),
+        (
+            "domain_create",
+            TxPayload::DomainCreate {
+                domain_id: Uuid::from_u128(1),
+                params: serde_json::json!({ "kind": "wasm" }),
+            },
+        ),
+    ]
+}
+
+fn bench_apply_tx(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("apply_tx");
+    for (name, payload) in payloads() {
+        let tx = signed(&key(0), 0, payload);
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || setup(&rt),
+                |ctx| rt.block_on(apply_tx(&ctx, &tx, 1)).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_apply_block(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("apply_block");
+    group.sample_size(20);
+    for count in [1u32, 10, 100, 500] {
+        let senders: Vec<SigningKey> = (100..100 + count).map(key).collect();
+        let txs: Vec<Tx> = senders
+            .iter()
+            .map(|sk| {
+                signed(
+                    sk,
+                    0,
+                    TxPayload::Transfer {
+                        to: [9u8; 32],
+                        amount: 1,
+                    },
+                )
+            })
+            .collect();
+        let block = Block {
+            header: BlockHeader {
+                parent_hash: [0u8; 32],
+                height: 1,
+                timestamp: 0,
+                proposer_id: [0u8; 32],
+                state_root: [0u8; 32],
+                l1_tx_root: [0u8; 32],
+                da_commitment: None,
+                domain_roots: vec![],
+                gas_used: 0,
+                gas_limit: 30_000_000,
+                base_fee: 1,
+                snapshot_root: None,
+                protocol_version: 0,
+                consensus_metadata: serde_json::json!({}),
+            },
+            transactions: txs,
+            da_blobs: vec![],
+        };
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &block, |b, block| {
+            b.iter_batched(
+                || {
+                    let ctx = bootstrap_state();
+                    rt.block_on(async {
+                        for sk in &senders {
+                            fund(&ctx, sk).await;
+                        }
+                    });
+                    ctx
+                },
+                |ctx| rt.block_on(apply_block(&ctx, block)).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_signatures(c: &mut Criterion) {
+    let sk = key(0);
+    let tx = signed(
+        &sk,
+        0,
+        TxPayload::Transfer {
+            to: [9u8; 32],
+            amount: 1,
+        },
+    );
+    let mut group = c.benchmark_group("signatures");
+    group.bench_function("sign_tx", |b| {
+        b.iter(|| {
+            let msg = tx_signing_bytes(&tx).unwrap();
+            sign_bytes(&sk, &msg)
+        })
+    });
+    group.bench_function("verify_tx", |b| {
+        b.iter(|| verify_tx_signature(&tx, false).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_apply_tx, bench_apply_block, bench_signatures);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+//! BLS12-381 (min-pk) helpers for aggregatable consensus signatures.
+
+use blst::min_pk::{AggregateSignature, PublicKey, SecretKey, Signature};
+use blst::BLST_ERROR;
+
+const VOTE_DST: &[u8] = b"KOVA_BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_";
+const POP_DST: &[u8] = b"KOVA_BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+pub const BLS_PUBKEY_LEN: usize = 48;
+pub const BLS_SIGNATURE_LEN: usize = 96;
+
+pub struct BlsSecretKey(SecretKey);
+
+impl BlsSecretKey {
+    /// Deterministically derives a key from at least 32 bytes of seed material.
+    pub fn from_seed(seed: &[u8]) -> anyhow::Result<Self> {
+        SecretKey::key_gen(seed, &[])
+            .map(Self)
+            .map_err(|e| anyhow::anyhow!("bls keygen failed: {e:?}"))
+    }
+
+    pub fn public_key(&self) -> Vec<u8> {
+        self.0.sk_to_pk().to_bytes().to_vec()
+    }
+
+    pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
+        self.0.sign(msg, VOTE_DST, &[]).to_bytes().to_vec()
+    }
+
+    /// Proof of possession over the public key, required at registration to
+    /// rule out rogue-key attacks on aggregated signatures.
+    pub fn proof_of_possession(&self) -> Vec<u8> {
+        self.0.sign(&self.public_key(), POP_DST, &[]).to_bytes().to_vec()
+    }
+}
+
+fn parse_pubkey(bytes: &[u8]) -> anyhow::Result<PublicKey> {
+    PublicKey::key_validate(bytes).map_err(|e| anyhow::anyhow!("invalid bls pubkey: {e:?}"))
+}
+
+fn parse_signature(bytes: &[u8]) -> anyhow::Result<Signature> {
+    Signature::sig_validate(bytes, true)
+        .map_err(|e| anyhow::anyhow!("invalid bls signature: {e:?}"))
+}
+
+fn check(result: BLST_ERROR) -> anyhow::Result<()> {
+    match result {
+        BLST_ERROR::BLST_SUCCESS => Ok(()),
+        err => anyhow::bail!("bls verification failed: {err:?}"),
+    }
+}
+
+pub fn bls_verify(pubkey: &[u8], signature: &[u8], msg: &[u8]) -> anyhow::Result<()> {
+    let pk = parse_pubkey(pubkey)?;
+    let sig = parse_signature(signature)?;
+    check(sig.verify(false, msg, VOTE_DST, &[], &pk, false))
+}
+
+pub fn bls_verify_pop(pubkey: &[u8], pop: &[u8]) -> anyhow::Result<()> {
+    let pk = parse_pubkey(pubkey)?;
+    let sig = parse_signature(pop)?;
+    check(sig.verify(false, pubkey, POP_DST, &[], &pk, false))
+}
+
+pub fn bls_aggregate(signatures: &[Vec<u8>]) -> anyhow::Result<Vec<u8>> {
+    let sigs = signatures
+        .iter()
+        .map(|s| parse_signature(s))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let refs: Vec<&Signature> = sigs.iter().collect();
+    let agg = AggregateSignature::aggregate(&refs, false)
+        .map_err(|e| anyhow::anyhow!("bls aggregation failed: {e:?}"))?;
+    Ok(agg.to_signature().to_bytes().to_vec())
+}
+
+/// Verifies an aggregate of signatures by `pubkeys` over the same message.
+/// Keys are assumed to have passed `bls_verify_pop` at registration.
+pub fn bls_verify_aggregate(
+    pubkeys: &[Vec<u8>],
+    aggregate: &[u8],
+    msg: &[u8],
+) -> anyhow::Result<()> {
+    if pubkeys.is_empty() {
+        anyhow::bail!("empty signer set");
+    }
+    let pks = pubkeys
+        .iter()
+        .map(|p| parse_pubkey(p))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let refs: Vec<&PublicKey> = pks.iter().collect();
+    let sig = parse_signature(aggregate)?;
+    check(sig.fast_aggregate_verify(false, msg, VOTE_DST, &refs))
+}
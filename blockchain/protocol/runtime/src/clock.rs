@@ -0,0 +1,60 @@
+//! Time as state transitions see it. Execution never reads the wall clock:
+//! `apply_block` moves the context's clock to the block's timestamp, so every
+//! node replaying a block sees the same time.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub trait ChainClock: Send + Sync {
+    /// Milliseconds since the Unix epoch.
+    fn now_ms(&self) -> u64;
+
+    /// Called by `apply_block` with the timestamp of the block it executes.
+    fn set_block_time(&self, timestamp_ms: u64);
+}
+
+/// The timestamp of the block being executed, or of the last one for txs
+/// executed between blocks. The default clock.
+#[derive(Debug, Default)]
+pub struct BlockClock(AtomicU64);
+
+impl BlockClock {
+    pub fn new(timestamp_ms: u64) -> Self {
+        Self(AtomicU64::new(timestamp_ms))
+    }
+}
+
+impl ChainClock for BlockClock {
+    fn now_ms(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn set_block_time(&self, timestamp_ms: u64) {
+        self.0.store(timestamp_ms, Ordering::SeqCst);
+    }
+}
+
+/// A clock moved only by hand; block timestamps are ignored. For tests.
+#[derive(Debug, Default)]
+pub struct ManualClock(AtomicU64);
+
+impl ManualClock {
+    pub fn new(now_ms: u64) -> Self {
+        Self(AtomicU64::new(now_ms))
+    }
+
+    pub fn set(&self, now_ms: u64) {
+        self.0.store(now_ms, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.0.fetch_add(ms, Ordering::SeqCst);
+    }
+}
+
+impl ChainClock for ManualClock {
+    fn now_ms(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    fn set_block_time(&self, _timestamp_ms: u64) {}
+}
@@ -0,0 +1,424 @@
+//! Optimistic domain roots and the bisection game that challenges them. A
+//! sequencer commits a root for a run of domain calls and bonds it; until the
+//! challenge window closes anyone can dispute it with an equal bond. The two
+//! sides then bisect the calls, each move due within the response window,
+//! down to a single call the runtime re-executes. The side that fails to move
+//! in time loses. A proven fraud rolls the root back, pays the claim bond to
+//! the challenger and slashes the sequencer's registered bond; otherwise the
+//! challenger's bond goes to the sequencer.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use state::{sections, Address, FraudDispute, Hash, OptimisticClaim, StateStore};
+use uuid::Uuid;
+
+use crate::domains::{call_leaf, DomainCall, DomainProof, DomainState};
+use crate::{
+    add_payout, credit_payouts, domain_entry, preconf, sequencers, Event, ExecutionContext, Tx,
+    TxPayload,
+};
+
+/// Challenge and response windows and the claim bond of an optimistic
+/// domain, read from its `risk_params`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisputeParams {
+    pub challenge_window_blocks: u64,
+    pub response_blocks: u64,
+    pub bond: u128,
+}
+
+impl Default for DisputeParams {
+    fn default() -> Self {
+        Self {
+            challenge_window_blocks: 100,
+            response_blocks: 20,
+            bond: 10_000,
+        }
+    }
+}
+
+impl DisputeParams {
+    /// Defaults overridden by `challenge_window_blocks`, `response_blocks`
+    /// and `claim_bond` in `params`.
+    pub fn from_risk_params(params: &serde_json::Value) -> anyhow::Result<Self> {
+        let param = |key: &str| -> anyhow::Result<Option<u64>> {
+            match params.get(key) {
+                None => Ok(None),
+                Some(value) => match value.as_u64() {
+                    Some(n) if n > 0 => Ok(Some(n)),
+                    _ => anyhow::bail!("{key} must be a positive integer"),
+                },
+            }
+        };
+        let mut out = Self::default();
+        if let Some(n) = param("challenge_window_blocks")? {
+            out.challenge_window_blocks = n;
+        }
+        if let Some(n) = param("response_blocks")? {
+            out.response_blocks = n;
+        }
+        if let Some(n) = param("claim_bond")? {
+            out.bond = n as u128;
+        }
+        Ok(out)
+    }
+}
+
+/// What the sequencer posts to settle a dispute narrowed to one call: the
+/// domain state at the agreed root and the call, proven to be that step of
+/// the claim.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepWitness {
+    pub pre_state: DomainState,
+    pub call: DomainCall,
+    pub call_proof: DomainProof,
+}
+
+/// `domain_id`'s calls in a batch blob, in the order a claim over the batch
+/// commits to them.
+pub fn batch_calls(domain_id: &Uuid, blob: &[u8]) -> anyhow::Result<Vec<DomainCall>> {
+    let txs: Vec<Tx> = zk_program_rollup::decode_batch(blob)?;
+    Ok(txs
+        .into_iter()
+        .filter_map(|tx| match tx.payload {
+            TxPayload::DomainExecute(call) if call.domain_id == *domain_id => Some(call),
+            _ => None,
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    Defended,
+    FraudProven,
+}
+
+pub(crate) async fn params<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+) -> anyhow::Result<DisputeParams> {
+    DisputeParams::from_risk_params(&domain_entry(ctx, domain_id).await?.risk_params)
+}
+
+async fn open_claim<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+) -> anyhow::Result<OptimisticClaim> {
+    ctx.state
+        .get_entry::<sections::OptimisticClaims>(domain_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("no open claim for domain"))
+}
+
+/// Bond a challenger must match to dispute the domain's open claim.
+pub(crate) async fn challenge_bond<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+) -> anyhow::Result<u128> {
+    ctx.state
+        .get_entry::<sections::OptimisticClaims>(domain_id)
+        .await?
+        .map(|claim| claim.bond)
+        .ok_or_else(|| anyhow::anyhow!("no open claim to challenge"))
+}
+
+pub(crate) async fn open<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    challenger: Address,
+    challenger_root: Hash,
+    height: u64,
+) -> anyhow::Result<Event> {
+    let response_blocks = params(ctx, domain_id).await?.response_blocks;
+    let mut claim = ctx
+        .state
+        .get_entry::<sections::OptimisticClaims>(domain_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("no open claim to challenge"))?;
+    if height > claim.challenge_until {
+        anyhow::bail!("claim is past its challenge window");
+    }
+    if claim.dispute.is_some() {
+        anyhow::bail!("claim is already disputed");
+    }
+    if challenger == claim.sequencer {
+        anyhow::bail!("sequencers cannot challenge their own claim");
+    }
+    if challenger_root == claim.state_root {
+        anyhow::bail!("challenge agrees with the claimed root");
+    }
+    claim.dispute = Some(FraudDispute {
+        challenger,
+        bond: claim.bond,
+        challenger_root,
+        agreed_step: 0,
+        agreed_root: claim.prev_root,
+        disputed_step: claim.steps,
+        disputed_root: claim.state_root,
+        midpoint_root: None,
+        deadline: height + response_blocks,
+    });
+    let steps = claim.steps;
+    ctx.state
+        .put_entry::<sections::OptimisticClaims>(*domain_id, claim)
+        .await?;
+    Ok(Event::new("fraud_challenge")
+        .with_hex("challenger", challenger)
+        .with("domain_id", domain_id)
+        .with_hex("claimed_root", challenger_root)
+        .with("steps", steps))
+}
+
+/// A copy of `claim`'s dispute, if it is `sender`'s move.
+fn dispute_for_move(
+    claim: &OptimisticClaim,
+    sender: Address,
+    height: u64,
+) -> anyhow::Result<FraudDispute> {
+    let dispute = claim
+        .dispute
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("claim is not disputed"))?;
+    let mover = if dispute.sequencer_to_move() {
+        claim.sequencer
+    } else {
+        dispute.challenger
+    };
+    if sender != mover {
+        anyhow::bail!("not the sender's move");
+    }
+    if height > dispute.deadline {
+        anyhow::bail!("dispute deadline has passed");
+    }
+    Ok(dispute)
+}
+
+/// The sequencer's root after the midpoint call of the disputed range.
+pub(crate) async fn bisect<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    sender: Address,
+    midpoint_root: Hash,
+    height: u64,
+) -> anyhow::Result<Event> {
+    let response_blocks = params(ctx, domain_id).await?.response_blocks;
+    let mut claim = open_claim(ctx, domain_id).await?;
+    let mut dispute = dispute_for_move(&claim, sender, height)?;
+    if !dispute.sequencer_to_move() {
+        anyhow::bail!("waiting on the challenger");
+    }
+    if dispute.disputed_step - dispute.agreed_step <= 1 {
+        anyhow::bail!("dispute is down to one call; defend it with a witness");
+    }
+    let step = dispute.midpoint();
+    dispute.midpoint_root = Some(midpoint_root);
+    dispute.deadline = height + response_blocks;
+    claim.dispute = Some(dispute);
+    ctx.state
+        .put_entry::<sections::OptimisticClaims>(*domain_id, claim)
+        .await?;
+    Ok(Event::new("fraud_bisect")
+        .with("domain_id", domain_id)
+        .with("step", step)
+        .with_hex("root", midpoint_root))
+}
+
+/// The challenger's answer to the sequencer's midpoint root: agreeing moves
+/// the range past the midpoint, disagreeing ends it there.
+pub(crate) async fn respond<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    sender: Address,
+    agree: bool,
+    height: u64,
+) -> anyhow::Result<Event> {
+    let response_blocks = params(ctx, domain_id).await?.response_blocks;
+    let mut claim = open_claim(ctx, domain_id).await?;
+    let mut dispute = dispute_for_move(&claim, sender, height)?;
+    let Some(root) = dispute.midpoint_root.take() else {
+        anyhow::bail!("waiting on the sequencer");
+    };
+    let step = dispute.midpoint();
+    if agree {
+        dispute.agreed_step = step;
+        dispute.agreed_root = root;
+    } else {
+        dispute.disputed_step = step;
+        dispute.disputed_root = root;
+    }
+    dispute.deadline = height + response_blocks;
+    claim.dispute = Some(dispute);
+    ctx.state
+        .put_entry::<sections::OptimisticClaims>(*domain_id, claim)
+        .await?;
+    Ok(Event::new("fraud_respond")
+        .with("domain_id", domain_id)
+        .with("step", step)
+        .with("agree", agree))
+}
+
+/// Re-executes the one disputed call from the agreed root.
+pub(crate) async fn defend<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    sender: Address,
+    witness: &StepWitness,
+    height: u64,
+) -> anyhow::Result<Verdict> {
+    let StepWitness {
+        pre_state,
+        call,
+        call_proof,
+    } = witness;
+    let claim = open_claim(ctx, domain_id).await?;
+    let dispute = dispute_for_move(&claim, sender, height)?;
+    if !dispute.sequencer_to_move() {
+        anyhow::bail!("waiting on the challenger");
+    }
+    if dispute.disputed_step - dispute.agreed_step != 1 {
+        anyhow::bail!("bisect the dispute down to one call first");
+    }
+    if pre_state.root() != dispute.agreed_root {
+        anyhow::bail!("pre-state does not match the agreed root");
+    }
+    if call.domain_id != *domain_id
+        || call_proof.index as u64 != dispute.agreed_step
+        || call_proof.leaf != call_leaf(call)
+        || !call_proof.verify(&claim.calls_root)
+    {
+        anyhow::bail!("call is not the disputed step of the claim");
+    }
+    if !ctx.domains.has_domain(domain_id) {
+        ctx.domains.register(&domain_entry(ctx, domain_id).await?)?;
+    }
+    let steps = ctx
+        .domains
+        .replay(
+            domain_id,
+            pre_state.clone(),
+            std::slice::from_ref(call),
+            ctx,
+            claim.claimed_at,
+        )
+        .await?;
+    Ok(if steps[0].state_root == dispute.disputed_root {
+        Verdict::Defended
+    } else {
+        Verdict::FraudProven
+    })
+}
+
+/// Ends the dispute on `domain_id`'s claim and queues the bonds for the
+/// winner. A proven fraud drops the claim, restores the root it built on and
+/// slashes the sequencer's registered bond.
+pub(crate) async fn resolve<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    verdict: Verdict,
+    height: u64,
+    payouts: &mut HashMap<Address, u128>,
+) -> anyhow::Result<Vec<Event>> {
+    let mut claim = open_claim(ctx, domain_id).await?;
+    let dispute = claim
+        .dispute
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("claim is not disputed"))?;
+    let event = Event::new("fraud_dispute_resolved").with("domain_id", domain_id);
+    match verdict {
+        Verdict::Defended => {
+            add_payout(payouts, claim.sequencer, dispute.bond);
+            let winner = claim.sequencer;
+            ctx.state
+                .put_entry::<sections::OptimisticClaims>(*domain_id, claim)
+                .await?;
+            Ok(vec![event
+                .with("outcome", "defended")
+                .with_hex("winner", winner)])
+        }
+        Verdict::FraudProven => {
+            ctx.state
+                .remove_entry::<sections::OptimisticClaims>(domain_id)
+                .await?;
+            add_payout(payouts, dispute.challenger, dispute.bond + claim.bond);
+            let mut batch_height = 0;
+            if let Some(mut root) = ctx
+                .state
+                .get_entry::<sections::DomainRoots>(domain_id)
+                .await?
+            {
+                root.state_root = claim.prev_root;
+                root.batch_height = root.batch_height.saturating_sub(1);
+                root.last_verified_epoch = height;
+                root.proof_meta = serde_json::json!({
+                    "rolled_back": hex::encode(claim.state_root),
+                });
+                batch_height = root.batch_height;
+                ctx.state
+                    .put_entry::<sections::DomainRoots>(*domain_id, root)
+                    .await?;
+            }
+            preconf::forget_batches_above(ctx, domain_id, batch_height).await?;
+            let fraud_slash_bps = sequencers::params(ctx, domain_id).await?.fraud_slash_bps;
+            let slashed =
+                sequencers::slash(ctx, domain_id, &claim.sequencer, fraud_slash_bps, "fraud")
+                    .await?;
+            let mut events = vec![event
+                .with("outcome", "fraud_proven")
+                .with_hex("winner", dispute.challenger)
+                .with_hex("state_root", claim.prev_root)];
+            events.extend(slashed);
+            Ok(events)
+        }
+    }
+}
+
+/// Settles claims at the end of a block: a dispute whose deadline has come
+/// goes against the side to move, and an undisputed claim past its window is
+/// final: its root opens to bridge withdrawals and its bond is returned.
+pub(crate) async fn settle<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    height: u64,
+) -> anyhow::Result<Vec<Event>> {
+    let claims = ctx
+        .state
+        .get_section::<sections::OptimisticClaims>()
+        .await?;
+    if claims.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut domain_ids: Vec<Uuid> = claims.keys().copied().collect();
+    domain_ids.sort();
+    let mut payouts = HashMap::new();
+    let mut events = Vec::new();
+    for domain_id in domain_ids {
+        let claim = &claims[&domain_id];
+        match &claim.dispute {
+            Some(dispute) if height >= dispute.deadline => {
+                let verdict = if dispute.sequencer_to_move() {
+                    Verdict::FraudProven
+                } else {
+                    Verdict::Defended
+                };
+                events.extend(resolve(ctx, &domain_id, verdict, height, &mut payouts).await?);
+            }
+            None if height >= claim.challenge_until => {
+                add_payout(&mut payouts, claim.sequencer, claim.bond);
+                events.push(
+                    Event::new("optimistic_claim_final")
+                        .with("domain_id", domain_id)
+                        .with_hex("state_root", claim.state_root),
+                );
+                ctx.state
+                    .remove_entry::<sections::OptimisticClaims>(&domain_id)
+                    .await?;
+                ctx.state
+                    .put_entry::<sections::FinalizedDomainRoots>(domain_id, claim.state_root)
+                    .await?;
+            }
+            _ => {}
+        }
+    }
+    credit_payouts(ctx, payouts).await?;
+    Ok(events)
+}
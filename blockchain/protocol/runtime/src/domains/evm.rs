@@ -1,11 +1,63 @@
-use anyhow::Context;
-use revm::primitives::{keccak256, B160};
+//! EVM domains executed with revm. Accounts, contract code and storage
+//! live in the domain's key-value state under `evm:` keys, so they are
+//! committed to by the domain state root like any other entry:
+//!
+//! - `evm:account:<address>`: nonce, balance and code hash
+//! - `evm:code:<code hash>`: deployed bytecode
+//! - `evm:storage:<address>:<slot>`: a non-zero storage word
+//!
+//! A call either names its sender in a JSON payload or carries a signed
+//! Ethereum tx (EIP-2718 encoded) as its raw bytes, whose signer becomes the
+//! sender. Signed txs must carry the domain's `evm_chain_id`. Gas is paid by
+//! the outer Kova tx, so EVM gas is priced at zero.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use alloy_consensus::transaction::SignerRecoverable;
+use alloy_consensus::{Transaction, TxEnvelope};
+use alloy_eips::eip2718::Decodable2718;
+use anyhow::Context as _;
+use revm::context::TxEnv;
+use revm::context_interface::result::{ExecutionResult, Output};
+use revm::database::{AccountState, CacheDB};
+use revm::primitives::{keccak256, Address, Bytes, Log, TxKind, B256, KECCAK_EMPTY, U256};
+use revm::state::{AccountInfo, Bytecode};
+use revm::{Context, DatabaseRef, ExecuteCommitEvm, MainBuilder, MainContext};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::{DomainCall, DomainExecutionReceipt, DomainState, DomainVm, DomainVmCtx};
 use state::DomainType;
 
+/// Gas a read-only call gets.
+const DEFAULT_GAS_LIMIT: u64 = 5_000_000;
+
+/// Chain id signed txs for `domain_id` must carry, so a tx signed for one
+/// domain can't be replayed on another.
+pub fn evm_chain_id(domain_id: &Uuid) -> u64 {
+    let hash = blake3::hash(domain_id.as_bytes());
+    u32::from_le_bytes(hash.as_bytes()[..4].try_into().unwrap()) as u64
+}
+
+/// Outcome of a call run against a domain's state without committing it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvmCallResult {
+    pub status: String,
+    pub output: String,
+    pub gas_used: u64,
+    pub halt_reason: Option<String>,
+}
+
+/// An account as Ethereum tooling reads it; balances are decimal strings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvmAccountView {
+    pub address: String,
+    pub nonce: u64,
+    pub balance: String,
+    pub code: String,
+}
+
 #[derive(Clone)]
 pub struct EvmAdapter {
     domain_id: Uuid,
@@ -14,9 +66,207 @@ pub struct EvmAdapter {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct EvmCall {
     from: Option<String>,
+    /// Omitted to deploy `input` as a contract's init code.
     to: Option<String>,
     input: Option<String>,
     value: Option<String>,
+    /// The sender's account nonce when omitted.
+    #[serde(default)]
+    nonce: Option<u64>,
+}
+
+fn account_key(address: &Address) -> String {
+    format!("evm:account:{}", hex::encode(address))
+}
+
+fn code_key(code_hash: &B256) -> String {
+    format!("evm:code:{}", hex::encode(code_hash))
+}
+
+fn storage_prefix(address: &Address) -> String {
+    format!("evm:storage:{}:", hex::encode(address))
+}
+
+fn storage_key(address: &Address, slot: &U256) -> String {
+    format!(
+        "{}{}",
+        storage_prefix(address),
+        hex::encode(slot.to_be_bytes::<32>())
+    )
+}
+
+fn encode_account(info: &AccountInfo) -> Vec<u8> {
+    let mut bytes = info.nonce.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&info.balance.to_be_bytes::<32>());
+    bytes.extend_from_slice(info.code_hash.as_slice());
+    bytes
+}
+
+/// Nonce, balance and code hash of a stored account.
+fn decode_account(bytes: &[u8]) -> Option<(u64, U256, B256)> {
+    if bytes.len() != 72 {
+        return None;
+    }
+    let nonce = u64::from_le_bytes(bytes[..8].try_into().ok()?);
+    let balance = U256::from_be_slice(&bytes[8..40]);
+    let code_hash = B256::from_slice(&bytes[40..]);
+    Some((nonce, balance, code_hash))
+}
+
+/// Read-only view of the `evm:` entries of a domain's state.
+struct DomainDb<'a> {
+    kv: &'a HashMap<String, Vec<u8>>,
+}
+
+impl DomainDb<'_> {
+    fn code(&self, code_hash: &B256) -> Bytecode {
+        if *code_hash == KECCAK_EMPTY {
+            return Bytecode::default();
+        }
+        self.kv
+            .get(&code_key(code_hash))
+            .map(|code| Bytecode::new_raw(Bytes::from(code.clone())))
+            .unwrap_or_default()
+    }
+}
+
+impl DatabaseRef for DomainDb<'_> {
+    type Error = Infallible;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let Some((nonce, balance, code_hash)) = self
+            .kv
+            .get(&account_key(&address))
+            .and_then(|bytes| decode_account(bytes))
+        else {
+            return Ok(None);
+        };
+        let code = self.code(&code_hash);
+        Ok(Some(AccountInfo::new(balance, nonce, code_hash, code)))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        Ok(self.code(&code_hash))
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        Ok(self
+            .kv
+            .get(&storage_key(&address, &index))
+            .map(|word| U256::from_be_slice(word))
+            .unwrap_or_default())
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        // Domains have no block hashes of their own; derive a stable one so
+        // BLOCKHASH is deterministic across nodes.
+        Ok(B256::from(*blake3::hash(&number.to_le_bytes()).as_bytes()))
+    }
+}
+
+/// Writes the accounts a call changed back into `state`. Accounts left
+/// empty are removed, as EIP-161 does.
+fn write_back(state: &mut DomainState, db: &CacheDB<DomainDb<'_>>) {
+    for (address, account) in &db.cache.accounts {
+        let cleared = matches!(
+            account.account_state,
+            AccountState::NotExisting | AccountState::StorageCleared
+        );
+        if !cleared && account.account_state != AccountState::Touched {
+            continue;
+        }
+        if cleared {
+            let existed = state.kv.contains_key(&account_key(address));
+            if existed {
+                let prefix = storage_prefix(address);
+                state.kv.retain(|key, _| !key.starts_with(&prefix));
+            }
+        }
+        let info = &account.info;
+        let empty = info.nonce == 0 && info.balance.is_zero() && info.code_hash == KECCAK_EMPTY;
+        if account.account_state == AccountState::NotExisting || empty {
+            state.kv.remove(&account_key(address));
+            continue;
+        }
+        state.kv.insert(account_key(address), encode_account(info));
+        if let Some(code) = info.code.as_ref().filter(|code| !code.is_empty()) {
+            state
+                .kv
+                .entry(code_key(&info.code_hash))
+                .or_insert_with(|| code.original_bytes().to_vec());
+        }
+        for (slot, value) in &account.storage {
+            let key = storage_key(address, slot);
+            if value.is_zero() {
+                state.kv.remove(&key);
+            } else {
+                state.kv.insert(key, value.to_be_bytes::<32>().to_vec());
+            }
+        }
+    }
+}
+
+fn log_event(log: &Log) -> String {
+    let topics: Vec<String> = log.topics().iter().map(hex::encode).collect();
+    format!(
+        "evm_log:{}:{}:{}",
+        hex::encode(log.address),
+        topics.join(","),
+        hex::encode(&log.data.data)
+    )
+}
+
+fn log_json(log: &Log) -> serde_json::Value {
+    serde_json::json!({
+        "address": log.address.to_string(),
+        "topics": log.topics().iter().map(|topic| topic.to_string()).collect::<Vec<_>>(),
+        "data": format!("0x{}", hex::encode(&log.data.data)),
+    })
+}
+
+/// Status, return data, created contract and halt reason of a call.
+fn outcome(result: &ExecutionResult) -> (&'static str, Bytes, Option<Address>, Option<String>) {
+    match result {
+        ExecutionResult::Success { output, .. } => match output {
+            Output::Call(data) => ("success", data.clone(), None, None),
+            Output::Create(data, address) => ("success", data.clone(), *address, None),
+        },
+        ExecutionResult::Revert { output, .. } => ("revert", output.clone(), None, None),
+        ExecutionResult::Halt { reason, .. } => {
+            ("halt", Bytes::new(), None, Some(format!("{reason:?}")))
+        }
+    }
+}
+
+/// Decodes a signed Ethereum tx and recovers its sender, returning the tx
+/// hash alongside the tx to run.
+fn decode_signed(raw: &[u8], chain_id: u64) -> anyhow::Result<(B256, TxEnv)> {
+    let envelope = TxEnvelope::decode_2718(&mut &raw[..]).context("invalid signed evm tx")?;
+    if envelope.is_eip4844() || envelope.is_eip7702() {
+        anyhow::bail!("unsupported evm tx type {}", envelope.tx_type());
+    }
+    // Pre-EIP-155 txs carry no chain id and could be replayed anywhere.
+    anyhow::ensure!(
+        envelope.chain_id() == Some(chain_id),
+        "evm tx is not signed for chain {chain_id}"
+    );
+    let caller = envelope
+        .recover_signer()
+        .map_err(|err| anyhow::anyhow!("invalid evm tx signature: {err}"))?;
+    let access_list = envelope.access_list().cloned().unwrap_or_default();
+    let tx = TxEnv {
+        tx_type: if access_list.is_empty() { 0 } else { 1 },
+        caller,
+        kind: envelope.kind(),
+        data: envelope.input().clone(),
+        value: envelope.value(),
+        gas_limit: envelope.gas_limit(),
+        nonce: envelope.nonce(),
+        chain_id: Some(chain_id),
+        access_list,
+        ..Default::default()
+    };
+    Ok((keccak256(raw), tx))
 }
 
 impl EvmAdapter {
@@ -24,86 +274,179 @@ impl EvmAdapter {
         Self { domain_id }
     }
 
-    fn parse_addr(s: &str) -> anyhow::Result<B160> {
-        let clean = s.trim_start_matches("0x");
-        let bytes = hex::decode(clean)?;
-        let mut arr = [0u8; 20];
-        for (i, b) in bytes.iter().take(20).enumerate() {
-            arr[i] = *b;
-        }
-        Ok(B160::from(arr))
+    fn parse_addr(s: &str) -> anyhow::Result<Address> {
+        s.parse::<Address>()
+            .with_context(|| format!("invalid evm address {s}"))
     }
-}
 
-#[async_trait::async_trait]
-impl DomainVm for EvmAdapter {
-    fn kind(&self) -> DomainType {
-        DomainType::EvmSharedSecurity
+    /// Nonce, balance and code of `address` in `state`.
+    pub fn account(state: &DomainState, address: &Address) -> Option<AccountInfo> {
+        DomainDb { kv: &state.kv }
+            .basic_ref(*address)
+            .unwrap_or_default()
     }
 
-    async fn execute(
+    pub fn storage(state: &DomainState, address: &Address, slot: &U256) -> U256 {
+        DomainDb { kv: &state.kv }
+            .storage_ref(*address, *slot)
+            .unwrap_or_default()
+    }
+
+    /// `address` as Ethereum tooling reads it; accounts the domain hasn't
+    /// seen are empty.
+    pub fn account_view(state: &DomainState, address: &str) -> anyhow::Result<EvmAccountView> {
+        let address = Self::parse_addr(address)?;
+        let info = Self::account(state, &address).unwrap_or_default();
+        let code = info
+            .code
+            .map(|code| code.original_bytes())
+            .unwrap_or_default();
+        Ok(EvmAccountView {
+            address: address.to_string(),
+            nonce: info.nonce,
+            balance: info.balance.to_string(),
+            code: format!("0x{}", hex::encode(code)),
+        })
+    }
+
+    /// Runs a JSON call payload against `state` without committing it, as
+    /// `eth_call` does. Nonces aren't checked.
+    pub fn simulate(
         &self,
-        call: &DomainCall,
-        ctx: DomainVmCtx<'_>,
-    ) -> anyhow::Result<DomainExecutionReceipt> {
+        state: &DomainState,
+        payload: &serde_json::Value,
+        block_height: u64,
+    ) -> anyhow::Result<EvmCallResult> {
+        let tx = Self::json_tx(payload, state, DEFAULT_GAS_LIMIT)?;
+        let (result, _) = self.transact(state, tx, block_height, true)?;
+        let (status, output, _, halt_reason) = outcome(&result);
+        Ok(EvmCallResult {
+            status: status.to_string(),
+            output: format!("0x{}", hex::encode(&output)),
+            gas_used: result.gas_used(),
+            halt_reason,
+        })
+    }
+
+    fn json_tx(
+        payload: &serde_json::Value,
+        state: &DomainState,
+        gas_limit: u64,
+    ) -> anyhow::Result<TxEnv> {
         let parsed: EvmCall =
-            serde_json::from_value(call.payload.clone()).context("invalid evm call payload")?;
+            serde_json::from_value(payload.clone()).context("invalid evm call payload")?;
         let from = parsed
             .from
             .as_deref()
-            .and_then(|s| Self::parse_addr(s).ok())
-            .unwrap_or_else(|| B160::from_low_u64_be(0));
-        let to = parsed
-            .to
-            .as_deref()
-            .and_then(|s| Self::parse_addr(s).ok());
-        let input_bytes = parsed
+            .map(Self::parse_addr)
+            .transpose()?
+            .unwrap_or(Address::ZERO);
+        let to = parsed.to.as_deref().map(Self::parse_addr).transpose()?;
+        let input = parsed
             .input
             .as_deref()
             .map(|s| hex::decode(s.trim_start_matches("0x")))
-            .transpose()?
+            .transpose()
+            .context("invalid evm call input")?
             .unwrap_or_default();
         let value = parsed
             .value
             .as_deref()
             .unwrap_or("0")
             .parse::<u128>()
-            .unwrap_or(0);
-        let gas_used = call.max_gas.unwrap_or(5_000_000);
+            .context("invalid evm call value")?;
+        let nonce = match parsed.nonce {
+            Some(nonce) => nonce,
+            None => Self::account(state, &from).map_or(0, |info| info.nonce),
+        };
+        Ok(TxEnv {
+            caller: from,
+            kind: to.map_or(TxKind::Create, TxKind::Call),
+            data: input.into(),
+            value: U256::from(value),
+            gas_limit,
+            nonce,
+            ..Default::default()
+        })
+    }
+
+    fn transact<'a>(
+        &self,
+        state: &'a DomainState,
+        tx: TxEnv,
+        block_height: u64,
+        simulate: bool,
+    ) -> anyhow::Result<(ExecutionResult, CacheDB<DomainDb<'a>>)> {
+        let chain_id = evm_chain_id(&self.domain_id);
+        let mut db = CacheDB::new(DomainDb { kv: &state.kv });
+        let result = {
+            let mut evm = Context::mainnet()
+                .with_db(&mut db)
+                .modify_cfg_chained(|cfg| {
+                    cfg.chain_id = chain_id;
+                    cfg.disable_nonce_check = simulate;
+                })
+                .modify_block_chained(|block| block.number = block_height.into())
+                .build_mainnet();
+            evm.transact_commit(tx)
+                .map_err(|err| anyhow::anyhow!("evm execution failed: {err:?}"))?
+        };
+        Ok((result, db))
+    }
+}
+
+#[async_trait::async_trait]
+impl DomainVm for EvmAdapter {
+    fn kind(&self) -> DomainType {
+        DomainType::EvmSharedSecurity
+    }
 
+    async fn execute(
+        &self,
+        call: &DomainCall,
+        ctx: DomainVmCtx<'_>,
+    ) -> anyhow::Result<DomainExecutionReceipt> {
+        let gas_limit = call.max_gas.unwrap_or(super::DEFAULT_DOMAIN_MAX_GAS);
+        let (tx_hash, tx) = if call.raw.is_empty() {
+            (None, Self::json_tx(&call.payload, &ctx.state, gas_limit)?)
+        } else {
+            let (hash, mut tx) = decode_signed(&call.raw, evm_chain_id(&self.domain_id))?;
+            tx.gas_limit = tx.gas_limit.min(gas_limit);
+            (Some(hash), tx)
+        };
+        let (from, to, value, nonce) = (tx.caller, tx.kind.to().copied(), tx.value, tx.nonce);
+
+        let (result, db) = self.transact(&ctx.state, tx, ctx.block_height, false)?;
         let mut state = ctx.state.clone();
-        let mut trace = serde_json::json!({
-            "from": format!("{from:?}"),
-            "to": to.map(|addr| format!("{addr:?}")),
-            "value": value,
-            "input_len": input_bytes.len(),
+        write_back(&mut state, &db);
+
+        let (status, output, created, halt_reason) = outcome(&result);
+        let mut events = vec![match (to, created) {
+            (None, Some(address)) => format!("evm_create:{}", hex::encode(address)),
+            _ => format!("evm_call:{status}"),
+        }];
+        events.extend(result.logs().iter().map(log_event));
+        let trace = serde_json::json!({
+            "domain_id": self.domain_id,
             "block_height": ctx.block_height,
+            "tx_hash": tx_hash.map(|hash| hash.to_string()),
+            "from": from.to_string(),
+            "to": to.map(|address| address.to_string()),
+            "value": value.to_string(),
+            "nonce": nonce,
+            "status": status,
+            "output": hex::encode(&output),
+            "halt_reason": halt_reason,
+            "contract_address": created.map(|address| address.to_string()),
+            "gas_used": result.gas_used(),
+            "logs": result.logs().iter().map(log_json).collect::<Vec<_>>(),
         });
-        let mut seed = Vec::new();
-        seed.extend(input_bytes);
-        seed.extend_from_slice(&value.to_le_bytes());
-        seed.extend_from_slice(&ctx.block_height.to_le_bytes());
-        let root = keccak256(seed);
-        state
-            .kv
-            .insert("evm:last_root".into(), root.0.to_vec());
-        state
-            .kv
-            .insert("evm:last_from".into(), from.as_bytes().to_vec());
-        if let Some(to_addr) = to {
-            state
-                .kv
-                .insert("evm:last_to".into(), to_addr.as_bytes().to_vec());
-        }
-        if let Some(obj) = trace.as_object_mut() {
-            obj.insert("domain_id".into(), serde_json::json!(self.domain_id));
-        }
 
         Ok(DomainExecutionReceipt {
             domain_id: self.domain_id,
-            state_root: [0u8; 32],
-            gas_used,
-            events: vec!["evm_call".into()],
+            state_root: state.root(),
+            gas_used: result.gas_used(),
+            events,
             proof: None,
             trace,
             state,
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::{Arc, Mutex, RwLock};
 
 use anyhow::Context;
@@ -8,18 +8,52 @@ use uuid::Uuid;
 use blake3;
 use bincode;
 
-use crate::{Hash, FeeSplit};
+use crate::{Address, Hash, FeeSplit};
 use state::{DomainEntry, DomainType};
 
 pub mod evm;
 pub mod wasm;
 
-pub use evm::EvmAdapter;
-pub use wasm::WasmAdapter;
+pub use evm::{evm_chain_id, EvmAccountView, EvmAdapter, EvmCallResult};
+pub use wasm::{WasmAdapter, WasmLimits};
+
+/// Upper bound on inbox messages consumed by a single `DomainInboxProcess`.
+pub const DEFAULT_INBOX_BATCH: u32 = 64;
+
+/// Blocks a cross-domain message has to be delivered in, unless its
+/// destination sets `message_timeout_blocks` in its risk params.
+pub const DEFAULT_MESSAGE_TIMEOUT_BLOCKS: u64 = 1_000;
+
+/// Snapshots a node keeps per domain; older heights can no longer be
+/// replayed from.
+pub const MAX_DOMAIN_SNAPSHOTS: usize = 256;
+
+/// Domain gas a call submitted on L1 may use when it sets no `max_gas`.
+pub const DEFAULT_DOMAIN_MAX_GAS: u64 = 3_000_000;
+
+/// Source id of messages the L1 bridge writes into domain inboxes. Relayers
+/// cannot submit messages from it; only the runtime's bridge handlers can.
+pub const L1_BRIDGE_ID: Uuid = Uuid::nil();
+
+/// Bridge instructions carried from L1 into a domain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "bridge", rename_all = "snake_case")]
+pub enum BridgeMessage {
+    Mint { recipient: Address, amount: u128 },
+}
+
+/// Domain tokens burned to be withdrawn on L1. Burns stay in the domain
+/// state so they can be proven against any later root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeBurn {
+    pub owner: Address,
+    pub amount: u128,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DomainCall {
     pub domain_id: Uuid,
+    #[serde(with = "crate::json_value")]
     pub payload: serde_json::Value,
     #[serde(default)]
     pub raw: Vec<u8>,
@@ -31,9 +65,83 @@ pub struct DomainCall {
 pub struct CrossDomainMessage {
     pub from: Uuid,
     pub to: Uuid,
+    /// Position among the messages `from` sent to `to`, counting from zero.
     pub nonce: u64,
     pub fee: u128,
+    #[serde(with = "crate::json_value")]
     pub payload: serde_json::Value,
+    /// L1 height after which the destination drops the message instead of
+    /// executing it; zero if it never expires.
+    #[serde(default)]
+    pub timeout_height: u64,
+    /// Set on acknowledgements, whose payload is empty.
+    #[serde(default)]
+    pub ack: Option<MessageAck>,
+}
+
+/// How a destination domain handled a message, as reported back to the
+/// domain that sent it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Delivered,
+    Failed,
+    TimedOut,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+            DeliveryStatus::TimedOut => "timed_out",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageAck {
+    /// Nonce of the acknowledged message.
+    pub nonce: u64,
+    pub status: DeliveryStatus,
+}
+
+/// What a domain keeps of a message it sent until the destination
+/// acknowledges it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentMessage {
+    pub to: Uuid,
+    /// L1 account that paid the fee, and gets it back if the message
+    /// times out.
+    pub sender: Address,
+    pub fee: u128,
+    pub timeout_height: u64,
+    /// Set once the destination acknowledged the message as timed out.
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+/// `message_timeout_blocks` from a destination's risk params.
+pub fn message_timeout_blocks(risk_params: &serde_json::Value) -> anyhow::Result<u64> {
+    match risk_params.get("message_timeout_blocks") {
+        None => Ok(DEFAULT_MESSAGE_TIMEOUT_BLOCKS),
+        Some(value) => match value.as_u64() {
+            Some(n) if n > 0 => Ok(n),
+            _ => anyhow::bail!("message_timeout_blocks must be a positive integer"),
+        },
+    }
+}
+
+/// `gas_price` from a domain's risk params: what a unit of domain gas costs
+/// on L1. Domains that set none only charge the flat L1 fee.
+pub fn domain_gas_price(risk_params: &serde_json::Value) -> anyhow::Result<u128> {
+    match risk_params.get("gas_price") {
+        None => Ok(0),
+        Some(value) => value
+            .as_u64()
+            .map(u128::from)
+            .ok_or_else(|| anyhow::anyhow!("gas_price must be a non-negative integer")),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,10 +156,60 @@ pub struct DomainExecutionReceipt {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FraudProof {
-    pub domain_id: Uuid,
-    pub claimed_root: Hash,
-    pub witness: serde_json::Value,
+pub struct InboxReceipt {
+    pub from: Uuid,
+    pub nonce: u64,
+    pub success: bool,
+    pub gas_used: u64,
+    pub events: Vec<String>,
+    pub error: Option<String>,
+    pub block_height: u64,
+}
+
+/// One call of a replay. A failed call leaves the state as it was.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayStep {
+    pub state_root: Hash,
+    pub gas_used: u64,
+    pub events: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Bridged native token balances held inside a domain.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DomainToken {
+    pub balances: HashMap<Address, u128>,
+    pub supply: u128,
+}
+
+impl DomainToken {
+    fn mint(&mut self, to: Address, amount: u128) -> anyhow::Result<()> {
+        let balance = self.balances.entry(to).or_default();
+        *balance = balance
+            .checked_add(amount)
+            .ok_or_else(|| anyhow::anyhow!("domain balance overflow"))?;
+        self.supply = self.supply.saturating_add(amount);
+        Ok(())
+    }
+
+    fn transfer(&mut self, from: &Address, to: Address, amount: u128) -> anyhow::Result<()> {
+        self.burn(from, amount)?;
+        self.mint(to, amount)
+    }
+
+    fn burn(&mut self, from: &Address, amount: u128) -> anyhow::Result<()> {
+        let balance = self.balances.get(from).copied().unwrap_or(0);
+        if balance < amount {
+            anyhow::bail!("insufficient domain balance: have {balance}, need {amount}");
+        }
+        if balance == amount {
+            self.balances.remove(from);
+        } else {
+            self.balances.insert(*from, balance - amount);
+        }
+        self.supply = self.supply.saturating_sub(amount);
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -61,43 +219,245 @@ pub struct DomainState {
     pub outbox: Vec<CrossDomainMessage>,
     pub next_out_nonce: u64,
     pub next_in_nonce: u64,
+    /// Next nonce expected from each source domain when consuming the inbox.
+    #[serde(default)]
+    pub processed_nonces: HashMap<Uuid, u64>,
+    #[serde(default)]
+    pub token: DomainToken,
+    #[serde(default)]
+    pub next_bridge_nonce: u64,
+    /// Messages this domain sent that await an ack, by destination and
+    /// nonce.
+    #[serde(default)]
+    pub unacked: HashMap<Uuid, HashMap<u64, SentMessage>>,
+    /// Nonce of the next message to each destination domain.
+    #[serde(default)]
+    pub channel_nonces: HashMap<Uuid, u64>,
+    /// Nonce the next relayed message from each source domain must carry.
+    #[serde(default)]
+    pub relayed_nonces: HashMap<Uuid, u64>,
+    /// Bridge burns by nonce, for L1 withdrawals to prove.
+    #[serde(default)]
+    pub bridge_burns: HashMap<u64, BridgeBurn>,
+    #[serde(default)]
+    pub next_burn_nonce: u64,
 }
 
 impl DomainState {
+    /// Merkle root over the sorted state leaves.
     pub fn root(&self) -> Hash {
+        let levels = merkle_levels(self.leaves());
+        levels.last().and_then(|top| top.first()).copied().unwrap_or([0u8; 32])
+    }
+
+    /// Inclusion proof for `leaf` against `root()`.
+    pub fn prove(&self, leaf: Hash) -> Option<DomainProof> {
+        let leaves = self.leaves();
+        let index = leaves.binary_search(&leaf).ok()?;
+        Some(merkle_proof(leaves, index))
+    }
+
+    pub fn message_leaf(msg: &CrossDomainMessage) -> Option<Hash> {
+        bincode::serialize(msg)
+            .ok()
+            .map(|bytes| leaf_hash("kova.domain.message", &[&bytes]))
+    }
+
+    /// Leaf recording that messages from `from` below `next_nonce` were consumed.
+    pub fn nonce_leaf(from: &Uuid, next_nonce: u64) -> Hash {
+        leaf_hash(
+            "kova.domain.nonce",
+            &[from.as_bytes(), &next_nonce.to_le_bytes()],
+        )
+    }
+
+    /// Leaf recording the next nonce of the channel to or from `peer`;
+    /// `context` tells outgoing and relayed channels apart.
+    fn channel_leaf(context: &str, peer: &Uuid, next_nonce: u64) -> Hash {
+        leaf_hash(context, &[peer.as_bytes(), &next_nonce.to_le_bytes()])
+    }
+
+    /// Leaf recording that message `nonce` this domain sent awaits an ack.
+    pub fn unacked_leaf(nonce: u64, sent: &SentMessage) -> Hash {
+        let bytes = bincode::serialize(&(nonce, sent)).unwrap_or_default();
+        leaf_hash("kova.domain.unacked", &[&bytes])
+    }
+
+    /// Leaf recording `owner`'s domain token balance.
+    pub fn balance_leaf(owner: &Address, balance: u128) -> Hash {
+        leaf_hash("kova.domain.balance", &[owner, &balance.to_le_bytes()])
+    }
+
+    /// Leaf recording bridge burn `nonce`.
+    pub fn burn_leaf(nonce: u64, burn: &BridgeBurn) -> Hash {
+        let bytes = bincode::serialize(&(nonce, burn)).unwrap_or_default();
+        leaf_hash("kova.domain.burn", &[&bytes])
+    }
+
+    fn leaves(&self) -> Vec<Hash> {
         let mut leaves = Vec::new();
         for (k, v) in &self.kv {
-            let mut data = k.as_bytes().to_vec();
-            data.extend(v);
-            leaves.push(*blake3::hash(&data).as_bytes());
+            let len = (k.len() as u64).to_le_bytes();
+            leaves.push(leaf_hash("kova.domain.kv", &[&len, k.as_bytes(), v]));
         }
-        for msg in &self.inbox {
-            if let Ok(bytes) = bincode::serialize(msg) {
-                leaves.push(*blake3::hash(&bytes).as_bytes());
-            }
+        leaves.extend(self.inbox.iter().filter_map(Self::message_leaf));
+        leaves.extend(self.outbox.iter().filter_map(Self::message_leaf));
+        for (from, nonce) in &self.processed_nonces {
+            leaves.push(Self::nonce_leaf(from, *nonce));
         }
-        for msg in &self.outbox {
-            if let Ok(bytes) = bincode::serialize(msg) {
-                leaves.push(*blake3::hash(&bytes).as_bytes());
-            }
+        for (owner, balance) in &self.token.balances {
+            leaves.push(Self::balance_leaf(owner, *balance));
+        }
+        for (nonce, sent) in self.unacked.values().flatten() {
+            leaves.push(Self::unacked_leaf(*nonce, sent));
+        }
+        for (to, nonce) in &self.channel_nonces {
+            leaves.push(Self::channel_leaf("kova.domain.channel.out", to, *nonce));
         }
-        leaves.push(*blake3::hash(&self.next_out_nonce.to_le_bytes()).as_bytes());
-        leaves.push(*blake3::hash(&self.next_in_nonce.to_le_bytes()).as_bytes());
-        if leaves.is_empty() {
-            return [0u8; 32];
+        for (from, nonce) in &self.relayed_nonces {
+            leaves.push(Self::channel_leaf(
+                "kova.domain.channel.relayed",
+                from,
+                *nonce,
+            ));
+        }
+        for (nonce, burn) in &self.bridge_burns {
+            leaves.push(Self::burn_leaf(*nonce, burn));
+        }
+        let supply = self.token.supply.to_le_bytes();
+        leaves.push(leaf_hash("kova.domain.supply", &[&supply]));
+        for (context, nonce) in [
+            ("kova.domain.next_bridge_nonce", self.next_bridge_nonce),
+            ("kova.domain.next_burn_nonce", self.next_burn_nonce),
+            ("kova.domain.next_out_nonce", self.next_out_nonce),
+            ("kova.domain.next_in_nonce", self.next_in_nonce),
+        ] {
+            leaves.push(leaf_hash(context, &[&nonce.to_le_bytes()]));
         }
-        let mut hasher = blake3::Hasher::new();
         leaves.sort();
-        for leaf in leaves {
-            hasher.update(&leaf);
+        leaves
+    }
+}
+
+/// Hashes a state leaf under a key derived from its kind, so leaves of
+/// different kinds can't be passed off as one another.
+fn leaf_hash(context: &str, parts: &[&[u8]]) -> Hash {
+    let mut hasher = blake3::Hasher::new_derive_key(context);
+    for part in parts {
+        hasher.update(part);
+    }
+    *hasher.finalize().as_bytes()
+}
+
+/// Merkle path from one leaf of a domain state to its root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainProof {
+    pub leaf: Hash,
+    /// Position of the leaf among the sorted state leaves.
+    pub index: usize,
+    pub path: Vec<Hash>,
+}
+
+impl DomainProof {
+    pub fn verify(&self, root: &Hash) -> bool {
+        let mut index = self.index;
+        let mut hash = self.leaf;
+        for sibling in &self.path {
+            hash = if index % 2 == 0 {
+                hash_pair(&hash, sibling)
+            } else {
+                hash_pair(sibling, &hash)
+            };
+            index /= 2;
         }
-        *hasher.finalize().as_bytes()
+        &hash == root
+    }
+}
+
+fn hash_pair(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
+
+/// Leaf committing to one call of an optimistic claim.
+pub fn call_leaf(call: &DomainCall) -> Hash {
+    let bytes = bincode::serialize(call).unwrap_or_default();
+    *blake3::hash(&bytes).as_bytes()
+}
+
+/// Root over a claim's calls, kept in execution order so a leaf's index is
+/// its step.
+pub fn calls_root(calls: &[DomainCall]) -> Hash {
+    let levels = merkle_levels(calls.iter().map(call_leaf).collect());
+    levels
+        .last()
+        .and_then(|top| top.first())
+        .copied()
+        .unwrap_or([0u8; 32])
+}
+
+/// Proof that `calls[step]` is step `step` under `calls_root(calls)`.
+pub fn call_proof(calls: &[DomainCall], step: usize) -> Option<DomainProof> {
+    if step >= calls.len() {
+        return None;
+    }
+    Some(merkle_proof(calls.iter().map(call_leaf).collect(), step))
+}
+
+fn merkle_proof(leaves: Vec<Hash>, position: usize) -> DomainProof {
+    let leaf = leaves[position];
+    let levels = merkle_levels(leaves);
+    let mut index = position;
+    let mut path = Vec::new();
+    for level in &levels[..levels.len() - 1] {
+        path.push(*level.get(index ^ 1).unwrap_or(&level[index]));
+        index /= 2;
+    }
+    DomainProof {
+        leaf,
+        index: position,
+        path,
+    }
+}
+
+/// Tree levels from the leaves up; an odd node is paired with itself.
+fn merkle_levels(leaves: Vec<Hash>) -> Vec<Vec<Hash>> {
+    let mut levels = vec![leaves];
+    while levels[levels.len() - 1].len() > 1 {
+        let next = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+#[derive(Clone)]
+pub struct DomainCheckpoint {
+    state: HashMap<Uuid, Arc<DomainState>>,
+    traces: HashMap<Uuid, usize>,
+    inbox_receipts: HashMap<Uuid, usize>,
+}
+
+fn lengths<T>(map: &HashMap<Uuid, Vec<T>>) -> HashMap<Uuid, usize> {
+    map.iter().map(|(id, items)| (*id, items.len())).collect()
+}
+
+fn truncate_to<T>(map: &mut HashMap<Uuid, Vec<T>>, lengths: &HashMap<Uuid, usize>) {
+    map.retain(|id, _| lengths.contains_key(id));
+    for (id, items) in map.iter_mut() {
+        items.truncate(lengths[id]);
     }
 }
 
+/// Each domain's state sits behind its own `Arc`, so checkpoints and forks
+/// share it and a write replaces only the domain it touches.
 #[derive(Clone)]
 pub struct DomainStateStore {
-    inner: Arc<Mutex<HashMap<Uuid, DomainState>>>,
+    inner: Arc<Mutex<HashMap<Uuid, Arc<DomainState>>>>,
 }
 
 impl DomainStateStore {
@@ -112,12 +472,15 @@ impl DomainStateStore {
             .lock()
             .unwrap()
             .get(domain_id)
-            .cloned()
+            .map(|state| DomainState::clone(state))
             .unwrap_or_default()
     }
 
     pub fn persist(&self, domain_id: &Uuid, state: DomainState) {
-        self.inner.lock().unwrap().insert(*domain_id, state);
+        self.inner
+            .lock()
+            .unwrap()
+            .insert(*domain_id, Arc::new(state));
     }
 }
 
@@ -125,6 +488,9 @@ pub struct DomainVmCtx<'a> {
     pub chain_id: &'a str,
     pub fee_split: &'a FeeSplit,
     pub block_height: u64,
+    /// Kova account that submitted the call; zero for relayed messages and
+    /// dispute replays, which don't carry one.
+    pub caller: Address,
     pub state: DomainState,
 }
 
@@ -132,8 +498,25 @@ pub struct DomainVmCtx<'a> {
 pub trait DomainVm: Send + Sync {
     fn kind(&self) -> DomainType;
     async fn execute(&self, call: &DomainCall, ctx: DomainVmCtx<'_>) -> anyhow::Result<DomainExecutionReceipt>;
+
+    /// Applies a relayed message. By default the payload is treated as a
+    /// regular call against the destination domain.
+    async fn process_message(
+        &self,
+        msg: &CrossDomainMessage,
+        ctx: DomainVmCtx<'_>,
+    ) -> anyhow::Result<DomainExecutionReceipt> {
+        let call = DomainCall {
+            domain_id: msg.to,
+            payload: msg.payload.clone(),
+            raw: Vec::new(),
+            max_gas: None,
+        };
+        self.execute(&call, ctx).await
+    }
 }
 
+#[derive(Clone)]
 enum DomainAdapter {
     Evm(Arc<EvmAdapter>),
     Wasm(Arc<WasmAdapter>),
@@ -157,6 +540,17 @@ impl DomainAdapter {
             DomainAdapter::Wasm(vm) => vm.execute(call, ctx).await,
         }
     }
+
+    async fn process_message(
+        &self,
+        msg: &CrossDomainMessage,
+        ctx: DomainVmCtx<'_>,
+    ) -> anyhow::Result<DomainExecutionReceipt> {
+        match self {
+            DomainAdapter::Evm(vm) => vm.process_message(msg, ctx).await,
+            DomainAdapter::Wasm(vm) => vm.process_message(msg, ctx).await,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -164,6 +558,10 @@ pub struct DomainRuntime {
     adapters: Arc<RwLock<HashMap<Uuid, DomainAdapter>>>,
     state: DomainStateStore,
     traces: Arc<RwLock<HashMap<Uuid, Vec<DomainExecutionReceipt>>>>,
+    inbox_receipts: Arc<RwLock<HashMap<Uuid, Vec<InboxReceipt>>>>,
+    /// Domain states at the end of the blocks that changed them, since this
+    /// node started.
+    snapshots: Arc<RwLock<HashMap<Uuid, BTreeMap<u64, DomainState>>>>,
 }
 
 impl Default for DomainRuntime {
@@ -178,6 +576,8 @@ impl DomainRuntime {
             adapters: Arc::new(RwLock::new(HashMap::new())),
             state: DomainStateStore::new(),
             traces: Arc::new(RwLock::new(HashMap::new())),
+            inbox_receipts: Arc::new(RwLock::new(HashMap::new())),
+            snapshots: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -186,7 +586,10 @@ impl DomainRuntime {
             DomainType::EvmSharedSecurity => {
                 DomainAdapter::Evm(Arc::new(EvmAdapter::new(entry.domain_id)))
             }
-            DomainType::Wasm => DomainAdapter::Wasm(Arc::new(WasmAdapter::new(entry.domain_id))),
+            DomainType::Wasm => {
+                let limits = WasmLimits::from_risk_params(&entry.risk_params)?;
+                DomainAdapter::Wasm(Arc::new(WasmAdapter::new(entry.domain_id, limits)))
+            }
             _ => anyhow::bail!("unsupported domain kind {:?}", entry.kind),
         };
         self.adapters
@@ -196,32 +599,77 @@ impl DomainRuntime {
         Ok(())
     }
 
+    /// Independent copy of registered domains and their state, without
+    /// traces, receipts or snapshots, for executing against without side
+    /// effects.
+    pub fn fork(&self) -> Self {
+        let state = self.state.inner.lock().unwrap().clone();
+        Self {
+            adapters: Arc::new(RwLock::new(self.adapters.read().unwrap().clone())),
+            state: DomainStateStore {
+                inner: Arc::new(Mutex::new(state)),
+            },
+            traces: Arc::new(RwLock::new(HashMap::new())),
+            inbox_receipts: Arc::new(RwLock::new(HashMap::new())),
+            snapshots: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Domain state and how many traces and receipts each domain has, so a
+    /// failed tx's domain effects can be undone with `restore`. The state is
+    /// shared with the store, not copied.
+    pub fn checkpoint(&self) -> DomainCheckpoint {
+        DomainCheckpoint {
+            state: self.state.inner.lock().unwrap().clone(),
+            traces: lengths(&self.traces.read().unwrap()),
+            inbox_receipts: lengths(&self.inbox_receipts.read().unwrap()),
+        }
+    }
+
+    pub fn restore(&self, checkpoint: DomainCheckpoint) {
+        *self.state.inner.lock().unwrap() = checkpoint.state;
+        truncate_to(&mut self.traces.write().unwrap(), &checkpoint.traces);
+        truncate_to(&mut self.inbox_receipts.write().unwrap(), &checkpoint.inbox_receipts);
+    }
+
     pub fn has_domain(&self, id: &Uuid) -> bool {
         self.adapters.read().unwrap().contains_key(id)
     }
 
-    pub fn next_out_nonce(&self, id: &Uuid) -> u64 {
-        self.state.load(id).next_out_nonce
+    /// Nonce of the next message `from` sends to `to`.
+    pub fn next_out_nonce(&self, from: &Uuid, to: &Uuid) -> u64 {
+        self.state
+            .load(from)
+            .channel_nonces
+            .get(to)
+            .copied()
+            .unwrap_or(0)
     }
 
     pub async fn execute(
         &self,
         call: &DomainCall,
+        caller: Address,
         ctx: &crate::ExecutionContext<impl state::StateStore>,
         block_height: u64,
     ) -> anyhow::Result<DomainExecutionReceipt> {
-        let adapters = self.adapters.read().unwrap();
-        let adapter = adapters
+        // The guard must not live across the await below, or the future
+        // isn't `Send`.
+        let adapter = self
+            .adapters
+            .read()
+            .unwrap()
             .get(&call.domain_id)
+            .cloned()
             .with_context(|| format!("domain {} not registered", call.domain_id))?;
         let domain_state = self.state.load(&call.domain_id);
         let vm_ctx = DomainVmCtx {
             chain_id: &ctx.chain_id,
             fee_split: &ctx.fee_split,
             block_height,
+            caller,
             state: domain_state.clone(),
         };
-        drop(adapters);
         let mut receipt = adapter.execute(call, vm_ctx).await?;
         self.state.persist(&call.domain_id, receipt.state.clone());
         receipt.state_root = receipt.state.root();
@@ -242,6 +690,17 @@ impl DomainRuntime {
             .and_then(|v| v.last().cloned())
     }
 
+    /// Runs `f` over the receipts of the domain's calls executed since this
+    /// node started, without cloning them.
+    pub fn with_traces<R>(
+        &self,
+        domain_id: &Uuid,
+        f: impl FnOnce(&[DomainExecutionReceipt]) -> R,
+    ) -> R {
+        let traces = self.traces.read().unwrap();
+        f(traces.get(domain_id).map(Vec::as_slice).unwrap_or_default())
+    }
+
     pub fn latest_root(&self, domain_id: &Uuid) -> Option<Hash> {
         self.traces
             .read()
@@ -255,36 +714,432 @@ impl DomainRuntime {
             })
     }
 
+    /// Root of the persisted domain state, including inbox consumption that
+    /// is not reflected in execution traces.
+    pub fn state_root(&self, domain_id: &Uuid) -> Hash {
+        self.state.load(domain_id).root()
+    }
+
+    pub fn domain_state(&self, domain_id: &Uuid) -> DomainState {
+        self.state.load(domain_id)
+    }
+
     pub fn outbox(&self, domain_id: &Uuid) -> Vec<CrossDomainMessage> {
         self.state.load(domain_id).outbox
     }
 
-    pub fn submit_fraud_proof(&self, proof: &FraudProof) -> anyhow::Result<()> {
-        let traces = self.traces.read().unwrap();
-        let Some(last) = traces.get(&proof.domain_id).and_then(|v| v.last()) else {
-            anyhow::bail!("no execution trace for domain");
-        };
-        if last.state_root == proof.claimed_root {
-            anyhow::bail!("claimed root already canonical");
+    /// Records the state of every registered domain that changed since its
+    /// last snapshot as its state at the end of block `height`.
+    pub fn record_snapshots(&self, height: u64) {
+        let ids: Vec<Uuid> = self.adapters.read().unwrap().keys().copied().collect();
+        let mut snapshots = self.snapshots.write().unwrap();
+        for id in ids {
+            let state = self.state.load(&id);
+            let history = snapshots.entry(id).or_default();
+            if history
+                .last_key_value()
+                .is_some_and(|(_, last)| last.root() == state.root())
+            {
+                continue;
+            }
+            history.insert(height, state);
+            while history.len() > MAX_DOMAIN_SNAPSHOTS {
+                history.pop_first();
+            }
         }
-        if !serde_json::to_string(&proof.witness).is_ok() {
-            anyhow::bail!("invalid witness");
+    }
+
+    /// State of `domain_id` at the end of block `height`.
+    pub fn snapshot(&self, domain_id: &Uuid, height: u64) -> anyhow::Result<DomainState> {
+        self.snapshots
+            .read()
+            .unwrap()
+            .get(domain_id)
+            .and_then(|history| history.range(..=height).next_back())
+            .map(|(_, state)| state.clone())
+            .with_context(|| format!("no snapshot of domain {domain_id} at height {height}"))
+    }
+
+    /// Re-executes `calls` in order from `from_snapshot` without touching
+    /// stored state or traces, as of block `block_height`. Claims commit to
+    /// calls but not their senders, so calls run with a zero caller.
+    pub async fn replay(
+        &self,
+        domain_id: &Uuid,
+        from_snapshot: DomainState,
+        calls: &[DomainCall],
+        ctx: &crate::ExecutionContext<impl state::StateStore>,
+        block_height: u64,
+    ) -> anyhow::Result<Vec<ReplayStep>> {
+        let adapter = self
+            .adapters
+            .read()
+            .unwrap()
+            .get(domain_id)
+            .cloned()
+            .with_context(|| format!("domain {domain_id} not registered"))?;
+        let mut state = from_snapshot;
+        let mut steps = Vec::with_capacity(calls.len());
+        for call in calls {
+            if call.domain_id != *domain_id {
+                anyhow::bail!("call is for domain {}, not {domain_id}", call.domain_id);
+            }
+            let vm_ctx = DomainVmCtx {
+                chain_id: &ctx.chain_id,
+                fee_split: &ctx.fee_split,
+                block_height,
+                caller: [0u8; 32],
+                state: state.clone(),
+            };
+            steps.push(match adapter.execute(call, vm_ctx).await {
+                Ok(receipt) => {
+                    state = receipt.state;
+                    ReplayStep {
+                        state_root: state.root(),
+                        gas_used: receipt.gas_used,
+                        events: receipt.events,
+                        error: None,
+                    }
+                }
+                Err(err) => ReplayStep {
+                    state_root: state.root(),
+                    gas_used: 0,
+                    events: Vec::new(),
+                    error: Some(err.to_string()),
+                },
+            });
         }
-        Ok(())
+        Ok(steps)
     }
 
-    pub fn push_outbox(&self, msg: CrossDomainMessage) {
-        let mut state = self.state.load(&msg.from);
+    /// Queues `msg` in its source's outbox, to be acknowledged by its
+    /// destination. `sender` paid its fee.
+    pub fn push_outbox(&self, msg: CrossDomainMessage, sender: Address) {
+        let from = msg.from;
+        let mut state = self.state.load(&from);
+        state.unacked.entry(msg.to).or_default().insert(
+            msg.nonce,
+            SentMessage {
+                to: msg.to,
+                sender,
+                fee: msg.fee,
+                timeout_height: msg.timeout_height,
+                timed_out: false,
+            },
+        );
+        state.channel_nonces.insert(msg.to, msg.nonce.saturating_add(1));
         state.outbox.push(msg);
         state.next_out_nonce = state.next_out_nonce.saturating_add(1);
-        self.state.persist(&msg.from, state);
+        self.state.persist(&from, state);
     }
 
+    /// Drops message `nonce` that `from` sent to `to` and returns its record
+    /// so the fee can be refunded. Only allowed once it timed out
+    /// undelivered: `to` acknowledged it as timed out, or hasn't consumed it
+    /// and `height` is past its timeout, so any later delivery is dropped
+    /// too.
+    pub fn take_timed_out(
+        &self,
+        from: &Uuid,
+        to: &Uuid,
+        nonce: u64,
+        height: u64,
+    ) -> anyhow::Result<SentMessage> {
+        let mut state = self.state.load(from);
+        let channel = state.unacked.entry(*to).or_default();
+        let sent = channel.remove(&nonce);
+        if channel.is_empty() {
+            state.unacked.remove(to);
+        }
+        let sent = sent.context("no unacknowledged message with that nonce")?;
+        if !sent.timed_out {
+            if sent.timeout_height == 0 || height <= sent.timeout_height {
+                anyhow::bail!("message has not timed out");
+            }
+            let consumed = self
+                .state
+                .load(&sent.to)
+                .processed_nonces
+                .get(from)
+                .is_some_and(|next| *next > nonce);
+            if consumed {
+                anyhow::bail!("message was consumed; its ack is still to be relayed");
+            }
+        }
+        self.state.persist(from, state);
+        Ok(sent)
+    }
+
+    /// Queues `msg` in its destination's inbox. Messages from each source
+    /// must be relayed in nonce order, each exactly once; callers check the
+    /// message against the source's committed outbox first.
     pub fn relay_message(&self, msg: CrossDomainMessage) -> anyhow::Result<()> {
+        if msg.from == L1_BRIDGE_ID {
+            anyhow::bail!("bridge messages are only issued by the runtime");
+        }
         let mut dest = self.state.load(&msg.to);
+        let expected = dest.relayed_nonces.get(&msg.from).copied().unwrap_or(0);
+        if msg.nonce < expected {
+            anyhow::bail!("message {} from {} was already relayed", msg.nonce, msg.from);
+        }
+        if msg.nonce > expected {
+            anyhow::bail!("out of order relay: expected nonce {expected}, got {}", msg.nonce);
+        }
+        dest.relayed_nonces.insert(msg.from, expected + 1);
         dest.inbox.push(msg.clone());
         dest.next_in_nonce = dest.next_in_nonce.saturating_add(1);
         self.state.persist(&msg.to, dest);
         Ok(())
     }
+
+    /// Consumes up to `max_messages` inbox messages, per source domain in
+    /// nonce order. Messages behind a nonce gap stay queued; replays of
+    /// already processed nonces are dropped. A failing or expired message is
+    /// still consumed so it cannot block the queue, and its receipt records
+    /// why. Each consumed message other than bridge mints and acks is
+    /// acknowledged through the domain's outbox.
+    pub async fn process_inbox(
+        &self,
+        domain_id: &Uuid,
+        ctx: &crate::ExecutionContext<impl state::StateStore>,
+        block_height: u64,
+        max_messages: usize,
+    ) -> anyhow::Result<Vec<InboxReceipt>> {
+        let adapter = self
+            .adapters
+            .read()
+            .unwrap()
+            .get(domain_id)
+            .cloned()
+            .with_context(|| format!("domain {} not registered", domain_id))?;
+        let mut state = self.state.load(domain_id);
+        let mut pending = std::mem::take(&mut state.inbox);
+        pending.sort_by(|a, b| (a.from, a.nonce).cmp(&(b.from, b.nonce)));
+
+        let mut receipts = Vec::new();
+        let mut remaining = Vec::new();
+        for msg in pending {
+            let expected = state.processed_nonces.get(&msg.from).copied().unwrap_or(0);
+            if msg.nonce < expected {
+                continue;
+            }
+            if msg.nonce > expected || receipts.len() >= max_messages {
+                remaining.push(msg);
+                continue;
+            }
+            let expired = msg.timeout_height != 0 && block_height > msg.timeout_height;
+            let result = if msg.from == L1_BRIDGE_ID {
+                apply_bridge_message(&state, &msg)
+            } else if let Some(ack) = &msg.ack {
+                apply_ack(&state, &msg, ack)
+            } else if expired {
+                Err(anyhow::anyhow!("message timed out"))
+            } else {
+                let vm_ctx = DomainVmCtx {
+                    chain_id: &ctx.chain_id,
+                    fee_split: &ctx.fee_split,
+                    block_height,
+                    caller: [0u8; 32],
+                    state: state.clone(),
+                };
+                adapter.process_message(&msg, vm_ctx).await
+            };
+            let status = match &result {
+                Ok(_) => DeliveryStatus::Delivered,
+                Err(_) if expired => DeliveryStatus::TimedOut,
+                Err(_) => DeliveryStatus::Failed,
+            };
+            let receipt = match result {
+                Ok(exec) => {
+                    state = exec.state;
+                    InboxReceipt {
+                        from: msg.from,
+                        nonce: msg.nonce,
+                        success: true,
+                        gas_used: exec.gas_used,
+                        events: exec.events,
+                        error: None,
+                        block_height,
+                    }
+                }
+                Err(err) => InboxReceipt {
+                    from: msg.from,
+                    nonce: msg.nonce,
+                    success: false,
+                    gas_used: 0,
+                    events: Vec::new(),
+                    error: Some(err.to_string()),
+                    block_height,
+                },
+            };
+            if msg.from != L1_BRIDGE_ID && msg.ack.is_none() {
+                push_ack(&mut state, &msg, status);
+            }
+            state.processed_nonces.insert(msg.from, expected + 1);
+            receipts.push(receipt);
+        }
+        state.inbox = remaining;
+        self.state.persist(domain_id, state);
+        self.inbox_receipts
+            .write()
+            .unwrap()
+            .entry(*domain_id)
+            .or_default()
+            .extend(receipts.iter().cloned());
+        Ok(receipts)
+    }
+
+    /// Queues an L1 deposit as a mint message in the domain's inbox; the
+    /// balance is credited when the inbox is processed.
+    pub fn bridge_deposit(
+        &self,
+        domain_id: &Uuid,
+        recipient: Address,
+        amount: u128,
+    ) -> anyhow::Result<CrossDomainMessage> {
+        let mut state = self.state.load(domain_id);
+        let msg = CrossDomainMessage {
+            from: L1_BRIDGE_ID,
+            to: *domain_id,
+            nonce: state.next_bridge_nonce,
+            fee: 0,
+            payload: serde_json::to_value(BridgeMessage::Mint { recipient, amount })?,
+            timeout_height: 0,
+            ack: None,
+        };
+        state.inbox.push(msg.clone());
+        state.next_bridge_nonce += 1;
+        state.next_in_nonce = state.next_in_nonce.saturating_add(1);
+        self.state.persist(domain_id, state);
+        Ok(msg)
+    }
+
+    /// Burns `amount` of `owner`'s domain balance and records the burn for
+    /// an L1 withdrawal to prove. Returns the burn nonce.
+    pub fn bridge_withdrawal_burn(
+        &self,
+        domain_id: &Uuid,
+        owner: Address,
+        amount: u128,
+    ) -> anyhow::Result<u64> {
+        let mut state = self.state.load(domain_id);
+        state.token.burn(&owner, amount)?;
+        let nonce = state.next_burn_nonce;
+        state.bridge_burns.insert(nonce, BridgeBurn { owner, amount });
+        state.next_burn_nonce += 1;
+        self.state.persist(domain_id, state);
+        Ok(nonce)
+    }
+
+    /// Burns `amount` of `owner`'s domain balance ahead of a forced exit.
+    pub fn bridge_burn(&self, domain_id: &Uuid, owner: &Address, amount: u128) -> anyhow::Result<()> {
+        let mut state = self.state.load(domain_id);
+        state.token.burn(owner, amount)?;
+        self.state.persist(domain_id, state);
+        Ok(())
+    }
+
+    pub fn token_balance(&self, domain_id: &Uuid, owner: &Address) -> u128 {
+        self.state
+            .load(domain_id)
+            .token
+            .balances
+            .get(owner)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn token_supply(&self, domain_id: &Uuid) -> u128 {
+        self.state.load(domain_id).token.supply
+    }
+
+    pub fn inbox_receipts(&self, domain_id: &Uuid) -> Vec<InboxReceipt> {
+        self.inbox_receipts
+            .read()
+            .unwrap()
+            .get(domain_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Queues the ack for `msg` in the outbox of `state`, its destination.
+fn push_ack(state: &mut DomainState, msg: &CrossDomainMessage, status: DeliveryStatus) {
+    let nonce = state.channel_nonces.get(&msg.from).copied().unwrap_or(0);
+    state.channel_nonces.insert(msg.from, nonce.saturating_add(1));
+    state.outbox.push(CrossDomainMessage {
+        from: msg.to,
+        to: msg.from,
+        nonce,
+        fee: 0,
+        payload: serde_json::Value::Null,
+        timeout_height: 0,
+        ack: Some(MessageAck {
+            nonce: msg.nonce,
+            status,
+        }),
+    });
+    state.next_out_nonce = state.next_out_nonce.saturating_add(1);
+}
+
+/// Settles the sent message an ack is for. Messages that timed out stay on
+/// record until their fee is refunded.
+fn apply_ack(
+    state: &DomainState,
+    msg: &CrossDomainMessage,
+    ack: &MessageAck,
+) -> anyhow::Result<DomainExecutionReceipt> {
+    let mut state = state.clone();
+    let Some(channel) = state.unacked.get_mut(&msg.from) else {
+        anyhow::bail!("ack for unknown message {}", ack.nonce);
+    };
+    let Some(sent) = channel.get_mut(&ack.nonce) else {
+        anyhow::bail!("ack for unknown message {}", ack.nonce);
+    };
+    if ack.status == DeliveryStatus::TimedOut {
+        sent.timed_out = true;
+    } else {
+        channel.remove(&ack.nonce);
+        if channel.is_empty() {
+            state.unacked.remove(&msg.from);
+        }
+    }
+    Ok(DomainExecutionReceipt {
+        domain_id: msg.to,
+        state_root: state.root(),
+        gas_used: 0,
+        events: vec![format!(
+            "message_ack:{}:{}:{}",
+            msg.from,
+            ack.nonce,
+            ack.status.as_str()
+        )],
+        proof: None,
+        trace: serde_json::json!({ "ack": ack }),
+        state,
+    })
+}
+
+fn apply_bridge_message(
+    state: &DomainState,
+    msg: &CrossDomainMessage,
+) -> anyhow::Result<DomainExecutionReceipt> {
+    let bridge: BridgeMessage = serde_json::from_value(msg.payload.clone())
+        .map_err(|e| anyhow::anyhow!("invalid bridge message: {e}"))?;
+    let mut state = state.clone();
+    let events = match bridge {
+        BridgeMessage::Mint { recipient, amount } => {
+            state.token.mint(recipient, amount)?;
+            vec![format!("bridge_mint:{}:{amount}", hex::encode(recipient))]
+        }
+    };
+    Ok(DomainExecutionReceipt {
+        domain_id: msg.to,
+        state_root: state.root(),
+        gas_used: 0,
+        events,
+        proof: None,
+        trace: serde_json::json!({ "bridge": msg.payload }),
+        state,
+    })
 }
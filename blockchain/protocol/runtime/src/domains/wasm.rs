@@ -1,34 +1,567 @@
+//! WASM domains executed with wasmtime. Contracts are deployed as modules
+//! and reach domain state only through the host functions they import from
+//! the `kova` module, each of which charges gas on top of the fuel spent on
+//! instructions. Pointers and lengths are offsets into the contract's
+//! exported `memory`:
+//!
+//! - `storage_get(key_ptr, key_len, out_ptr, out_cap) -> i32`: copies up to
+//!   `out_cap` bytes of the value and returns its full length, or -1 if unset
+//! - `storage_set(key_ptr, key_len, value_ptr, value_len)`: an empty value
+//!   removes the key
+//! - `emit_event(ptr, len)`
+//! - `caller(out_ptr)`: the 32-byte Kova address that submitted the call
+//! - `block_height() -> i64`
+//! - `transfer(to_ptr, amount: i64) -> i32`: moves domain tokens from the
+//!   contract's address, returning 0 on success and 1 if it can't cover it
+//! - `input(out_ptr, out_cap) -> i32`: copies the call's input like
+//!   `storage_get` does
+//!
+//! Contract storage lives in the domain's key-value state under
+//! `wasm:storage:<module id>:<hex key>`.
+//!
+//! Modules are validated before they compile: they may import nothing but
+//! these host functions, must fit the domain's [`WasmLimits`], and may only
+//! use the MVP instruction set plus sign extension and multi-value. Floats
+//! are out because their NaN bits aren't deterministic across hosts;
+//! bulk memory because a `memory.fill` or `memory.copy` of any length costs
+//! one unit of fuel, which would leave it unmetered.
+
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
 use anyhow::Context;
-use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use wasmtime::{Config, Engine as WasmEngine, Module, Store};
+use wasmparser::{Parser, Payload, TypeRef, Validator, WasmFeatures};
+use wasmtime::{
+    Caller, Config, Engine as WasmEngine, Extern, Linker, Memory, Module, ResourceLimiter, Store,
+    Trap,
+};
 
 use super::{DomainCall, DomainExecutionReceipt, DomainState, DomainVm, DomainVmCtx};
-use state::DomainType;
+use state::{Address, DomainType};
+
+/// Import module contracts take host functions from.
+const HOST_MODULE: &str = "kova";
+/// Gas every host call costs on top of its own charge.
+const HOST_CALL_GAS: u64 = 100;
+const STORAGE_READ_GAS: u64 = 800;
+const STORAGE_WRITE_GAS: u64 = 5_000;
+const EVENT_GAS: u64 = 400;
+const TRANSFER_GAS: u64 = 2_500;
+/// Charged per byte a host call copies in or out of contract memory.
+const BYTE_GAS: u64 = 10;
+/// Deploys cost this plus `BYTE_GAS` per byte of module code stored.
+const DEPLOY_GAS: u64 = 10_000;
+const HOST_FUNCTIONS: &[&str] = &[
+    "storage_get",
+    "storage_set",
+    "emit_event",
+    "caller",
+    "block_height",
+    "transfer",
+    "input",
+];
+const MODULE_FEATURES: WasmFeatures = WasmFeatures::MUTABLE_GLOBAL
+    .union(WasmFeatures::SIGN_EXTENSION)
+    .union(WasmFeatures::MULTI_VALUE);
+const WASM_PAGE_BYTES: u64 = 64 << 10;
+/// Compiled modules an adapter keeps around.
+const MODULE_CACHE_ENTRIES: usize = 256;
+
+/// Resource limits for a wasm domain, read from its `risk_params`. Fuel
+/// bounds instructions; these bound everything else a module can consume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    pub max_memory_bytes: usize,
+    pub max_table_elements: u32,
+    /// Native stack available to wasm frames, which caps call depth.
+    pub max_stack_bytes: usize,
+    pub max_module_bytes: usize,
+    /// Functions a module may define or import.
+    pub max_functions: u32,
+    /// Wall-clock watchdog. Unlike the other limits this is not deterministic
+    /// across nodes, so it should sit well above what fuel allows and only
+    /// catch pathological modules.
+    pub max_execution_ms: u64,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            max_memory_bytes: 16 << 20,
+            max_table_elements: 10_000,
+            max_stack_bytes: 512 << 10,
+            max_module_bytes: 1 << 20,
+            max_functions: 10_000,
+            max_execution_ms: 1_000,
+        }
+    }
+}
+
+impl WasmLimits {
+    /// Defaults overridden by any `wasm_max_*` keys in `params`.
+    pub fn from_risk_params(params: &serde_json::Value) -> anyhow::Result<Self> {
+        let param = |key: &str| -> anyhow::Result<Option<u64>> {
+            match params.get(key) {
+                None => Ok(None),
+                Some(value) => match value.as_u64() {
+                    Some(n) if n > 0 => Ok(Some(n)),
+                    _ => anyhow::bail!("{key} must be a positive integer"),
+                },
+            }
+        };
+        let mut limits = Self::default();
+        if let Some(n) = param("wasm_max_memory_bytes")? {
+            limits.max_memory_bytes = usize::try_from(n)?;
+        }
+        if let Some(n) = param("wasm_max_table_elements")? {
+            limits.max_table_elements = u32::try_from(n)?;
+        }
+        if let Some(n) = param("wasm_max_stack_bytes")? {
+            limits.max_stack_bytes = usize::try_from(n)?;
+        }
+        if let Some(n) = param("wasm_max_module_bytes")? {
+            limits.max_module_bytes = usize::try_from(n)?;
+        }
+        if let Some(n) = param("wasm_max_functions")? {
+            limits.max_functions = u32::try_from(n)?;
+        }
+        if let Some(n) = param("wasm_max_execution_ms")? {
+            limits.max_execution_ms = n;
+        }
+        Ok(limits)
+    }
+
+    /// Checks `bytes` is a module these limits allow a domain to run. Memory
+    /// and tables are checked at their initial size here and again as they
+    /// grow.
+    pub fn validate(&self, bytes: &[u8]) -> anyhow::Result<()> {
+        if bytes.len() > self.max_module_bytes {
+            anyhow::bail!("wasm module exceeds size limit");
+        }
+        Validator::new_with_features(MODULE_FEATURES)
+            .validate_all(bytes)
+            .context("invalid or unsupported wasm module")?;
+        let mut functions = 0u64;
+        for payload in Parser::new(0).parse_all(bytes) {
+            match payload? {
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        let import = import?;
+                        let known = import.module == HOST_MODULE
+                            && HOST_FUNCTIONS.contains(&import.name)
+                            && matches!(import.ty, TypeRef::Func(_));
+                        if !known {
+                            anyhow::bail!(
+                                "wasm module imports unknown {}::{}",
+                                import.module,
+                                import.name
+                            );
+                        }
+                        functions += 1;
+                    }
+                }
+                Payload::FunctionSection(funcs) => functions += u64::from(funcs.count()),
+                Payload::MemorySection(memories) => {
+                    for memory in memories {
+                        let bytes = memory?.initial.saturating_mul(WASM_PAGE_BYTES);
+                        if bytes > self.max_memory_bytes as u64 {
+                            anyhow::bail!("wasm memory limit exceeded");
+                        }
+                    }
+                }
+                Payload::TableSection(tables) => {
+                    for table in tables {
+                        if u64::from(table?.ty.initial) > u64::from(self.max_table_elements) {
+                            anyhow::bail!("wasm table limit exceeded");
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if functions > u64::from(self.max_functions) {
+            anyhow::bail!("wasm module has too many functions");
+        }
+        Ok(())
+    }
+}
+
+/// Per-call store data: enforces memory and table limits and remembers
+/// which one was hit so the error doesn't depend on wasmtime internals.
+struct CallLimiter {
+    limits: WasmLimits,
+    violation: Option<&'static str>,
+}
+
+impl CallLimiter {
+    fn exceeded(&mut self, what: &'static str) -> anyhow::Result<bool> {
+        self.violation = Some(what);
+        anyhow::bail!(what)
+    }
+}
+
+impl ResourceLimiter for CallLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if desired > self.limits.max_memory_bytes {
+            return self.exceeded("wasm memory limit exceeded");
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        desired: u32,
+        _maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        if desired > self.limits.max_table_elements {
+            return self.exceeded("wasm table limit exceeded");
+        }
+        Ok(true)
+    }
+
+    fn instances(&self) -> usize {
+        1
+    }
+}
+
+/// What a contract's host calls read and change during one invocation.
+struct HostEnv {
+    module_id: String,
+    caller: Address,
+    block_height: u64,
+    input: Vec<u8>,
+    state: DomainState,
+    events: Vec<String>,
+}
+
+struct CallData {
+    limiter: CallLimiter,
+    host: HostEnv,
+}
+
+type HostCaller<'a> = Caller<'a, CallData>;
+
+fn storage_key(module_id: &str, key: &[u8]) -> String {
+    format!("wasm:storage:{module_id}:{}", hex::encode(key))
+}
+
+/// Fails the call with an error that reads the same on every node.
+fn host_fail(caller: &mut HostCaller<'_>, what: &'static str) -> anyhow::Error {
+    caller.data_mut().limiter.violation = Some(what);
+    anyhow::anyhow!(what)
+}
+
+fn charge(caller: &mut HostCaller<'_>, gas: u64) -> anyhow::Result<()> {
+    let fuel = caller.get_fuel()?;
+    match fuel.checked_sub(gas) {
+        Some(left) => caller.set_fuel(left),
+        None => {
+            caller.set_fuel(0)?;
+            Err(host_fail(caller, "wasm out of gas"))
+        }
+    }
+}
+
+/// The contract's exported memory, if `[ptr, ptr + len)` lies inside it.
+fn memory(caller: &mut HostCaller<'_>, ptr: u32, len: usize) -> anyhow::Result<Memory> {
+    let Some(memory) = caller.get_export("memory").and_then(Extern::into_memory) else {
+        return Err(host_fail(caller, "wasm contract exports no memory"));
+    };
+    let end = (ptr as usize).checked_add(len);
+    if end.map_or(true, |end| end > memory.data_size(&*caller)) {
+        return Err(host_fail(caller, "wasm memory access out of bounds"));
+    }
+    Ok(memory)
+}
+
+fn read_bytes(caller: &mut HostCaller<'_>, ptr: u32, len: u32) -> anyhow::Result<Vec<u8>> {
+    charge(caller, u64::from(len) * BYTE_GAS)?;
+    let memory = memory(caller, ptr, len as usize)?;
+    let mut buf = vec![0u8; len as usize];
+    memory.read(&*caller, ptr as usize, &mut buf)?;
+    Ok(buf)
+}
+
+fn write_bytes(caller: &mut HostCaller<'_>, ptr: u32, bytes: &[u8]) -> anyhow::Result<()> {
+    charge(caller, bytes.len() as u64 * BYTE_GAS)?;
+    let memory = memory(caller, ptr, bytes.len())?;
+    memory.write(&mut *caller, ptr as usize, bytes)?;
+    Ok(())
+}
+
+/// Copies as much of `value` as fits in `out_cap` bytes at `out_ptr` and
+/// returns its full length.
+fn copy_out(
+    caller: &mut HostCaller<'_>,
+    value: &[u8],
+    out_ptr: u32,
+    out_cap: u32,
+) -> anyhow::Result<i32> {
+    let n = value.len().min(out_cap as usize);
+    write_bytes(caller, out_ptr, &value[..n])?;
+    i32::try_from(value.len()).map_err(|_| host_fail(caller, "wasm value too large"))
+}
+
+fn link(engine: &WasmEngine) -> anyhow::Result<Linker<CallData>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap(
+        HOST_MODULE,
+        "storage_get",
+        |mut caller: HostCaller<'_>,
+         key_ptr: u32,
+         key_len: u32,
+         out_ptr: u32,
+         out_cap: u32|
+         -> anyhow::Result<i32> {
+            charge(&mut caller, HOST_CALL_GAS + STORAGE_READ_GAS)?;
+            let key = read_bytes(&mut caller, key_ptr, key_len)?;
+            let host = &caller.data().host;
+            let Some(value) = host
+                .state
+                .kv
+                .get(&storage_key(&host.module_id, &key))
+                .cloned()
+            else {
+                return Ok(-1);
+            };
+            copy_out(&mut caller, &value, out_ptr, out_cap)
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "storage_set",
+        |mut caller: HostCaller<'_>,
+         key_ptr: u32,
+         key_len: u32,
+         value_ptr: u32,
+         value_len: u32|
+         -> anyhow::Result<()> {
+            charge(&mut caller, HOST_CALL_GAS + STORAGE_WRITE_GAS)?;
+            let key = read_bytes(&mut caller, key_ptr, key_len)?;
+            let value = read_bytes(&mut caller, value_ptr, value_len)?;
+            let host = &mut caller.data_mut().host;
+            let key = storage_key(&host.module_id, &key);
+            if value.is_empty() {
+                host.state.kv.remove(&key);
+            } else {
+                host.state.kv.insert(key, value);
+            }
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "emit_event",
+        |mut caller: HostCaller<'_>, ptr: u32, len: u32| -> anyhow::Result<()> {
+            charge(&mut caller, HOST_CALL_GAS + EVENT_GAS)?;
+            let data = read_bytes(&mut caller, ptr, len)?;
+            let host = &mut caller.data_mut().host;
+            let event = format!("wasm_event:{}:{}", host.module_id, hex::encode(data));
+            host.events.push(event);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "caller",
+        |mut caller: HostCaller<'_>, out_ptr: u32| -> anyhow::Result<()> {
+            charge(&mut caller, HOST_CALL_GAS)?;
+            let address = caller.data().host.caller;
+            write_bytes(&mut caller, out_ptr, &address)
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "block_height",
+        |mut caller: HostCaller<'_>| -> anyhow::Result<u64> {
+            charge(&mut caller, HOST_CALL_GAS)?;
+            Ok(caller.data().host.block_height)
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "transfer",
+        |mut caller: HostCaller<'_>, to_ptr: u32, amount: u64| -> anyhow::Result<i32> {
+            charge(&mut caller, HOST_CALL_GAS + TRANSFER_GAS)?;
+            let to: Address = read_bytes(&mut caller, to_ptr, 32)?
+                .try_into()
+                .expect("read 32 bytes");
+            let host = &mut caller.data_mut().host;
+            let from = WasmAdapter::contract_address(&host.module_id);
+            if host.state.token.transfer(&from, to, amount.into()).is_err() {
+                return Ok(1);
+            }
+            let event = format!(
+                "wasm_transfer:{}:{}:{amount}",
+                host.module_id,
+                hex::encode(to)
+            );
+            host.events.push(event);
+            Ok(0)
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "input",
+        |mut caller: HostCaller<'_>, out_ptr: u32, out_cap: u32| -> anyhow::Result<i32> {
+            charge(&mut caller, HOST_CALL_GAS)?;
+            let input = std::mem::take(&mut caller.data_mut().host.input);
+            let len = copy_out(&mut caller, &input, out_ptr, out_cap);
+            caller.data_mut().host.input = input;
+            len
+        },
+    )?;
+    Ok(linker)
+}
 
 #[derive(Clone)]
 pub struct WasmAdapter {
     domain_id: Uuid,
     engine: WasmEngine,
+    limits: WasmLimits,
+    /// Compiled modules keyed by the blake3 hash of their code.
+    modules: Arc<Mutex<HashMap<[u8; 32], Module>>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "action", rename_all = "snake_case")]
 enum WasmAction {
-    Deploy { module_id: String, code_b64: String },
-    Invoke { module_id: String, entry: Option<String> },
+    Deploy {
+        module_id: String,
+        code_b64: String,
+    },
+    Invoke {
+        module_id: String,
+        entry: Option<String>,
+        /// What the contract's `input` host call returns.
+        #[serde(default)]
+        input_b64: Option<String>,
+    },
 }
 
 impl WasmAdapter {
-    pub fn new(domain_id: Uuid) -> Self {
+    pub fn new(domain_id: Uuid, limits: WasmLimits) -> Self {
         let mut cfg = Config::new();
         cfg.consume_fuel(true);
+        cfg.epoch_interruption(true);
+        cfg.max_wasm_stack(limits.max_stack_bytes);
         Self {
             domain_id,
             engine: WasmEngine::new(&cfg).unwrap_or_else(|_| WasmEngine::default()),
+            limits,
+            modules: Arc::default(),
+        }
+    }
+
+    pub fn limits(&self) -> WasmLimits {
+        self.limits
+    }
+
+    /// Domain token account a contract's `transfer` host call pays from.
+    pub fn contract_address(module_id: &str) -> Address {
+        *blake3::hash(format!("wasm:contract:{module_id}").as_bytes()).as_bytes()
+    }
+
+    /// Validates and compiles `bytes`, or returns the module compiled for
+    /// the same code earlier.
+    fn compile(&self, bytes: &[u8]) -> anyhow::Result<Module> {
+        let hash = *blake3::hash(bytes).as_bytes();
+        if let Some(module) = self.modules.lock().unwrap().get(&hash) {
+            return Ok(module.clone());
+        }
+        self.limits.validate(bytes)?;
+        let module =
+            Module::new(&self.engine, bytes).context("failed to compile wasm module for domain")?;
+        let mut modules = self.modules.lock().unwrap();
+        if modules.len() >= MODULE_CACHE_ENTRIES {
+            if let Some(evict) = modules.keys().next().copied() {
+                modules.remove(&evict);
+            }
+        }
+        modules.insert(hash, module.clone());
+        Ok(module)
+    }
+
+    /// Instantiates `code` and runs `entry` with `fuel`, returning the fuel
+    /// consumed and `host` as the contract's host calls left it.
+    fn invoke(
+        &self,
+        code: &[u8],
+        entry: Option<&str>,
+        fuel: u64,
+        host: HostEnv,
+    ) -> anyhow::Result<(u64, HostEnv)> {
+        let module = self.compile(code)?;
+        let linker = link(&self.engine)?;
+        let mut store = Store::new(
+            &self.engine,
+            CallData {
+                limiter: CallLimiter {
+                    limits: self.limits,
+                    violation: None,
+                },
+                host,
+            },
+        );
+        store.limiter(|data| &mut data.limiter);
+        store.set_fuel(fuel)?;
+        store.set_epoch_deadline(1);
+        let _watchdog = self.watchdog();
+
+        let run = |store: &mut Store<CallData>| -> anyhow::Result<()> {
+            let instance = linker.instantiate(&mut *store, &module)?;
+            if let Some(func_name) = entry {
+                if let Ok(func) = instance.get_typed_func::<(), ()>(&mut *store, func_name) {
+                    func.call(&mut *store, ())?;
+                }
+            }
+            Ok(())
+        };
+        if let Err(err) = run(&mut store) {
+            return Err(violation(&store, err));
         }
+        let consumed = fuel - store.get_fuel().unwrap_or(0);
+        Ok((consumed, store.into_data().host))
+    }
+
+    /// Interrupts calls on this engine once the execution time limit passes,
+    /// unless the returned sender is dropped first.
+    fn watchdog(&self) -> mpsc::Sender<()> {
+        let (done, finished) = mpsc::channel::<()>();
+        let engine = self.engine.clone();
+        let limit = Duration::from_millis(self.limits.max_execution_ms);
+        thread::spawn(move || {
+            if let Err(mpsc::RecvTimeoutError::Timeout) = finished.recv_timeout(limit) {
+                engine.increment_epoch();
+            }
+        });
+        done
+    }
+}
+
+/// Maps a failed call to an error that reads the same on every node.
+fn violation(store: &Store<CallData>, err: anyhow::Error) -> anyhow::Error {
+    if let Some(what) = store.data().limiter.violation {
+        return anyhow::anyhow!(what);
+    }
+    match err.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => anyhow::anyhow!("wasm out of gas"),
+        Some(Trap::StackOverflow) => anyhow::anyhow!("wasm call depth limit exceeded"),
+        Some(Trap::Interrupt) => anyhow::anyhow!("wasm execution time limit exceeded"),
+        Some(trap) => anyhow::anyhow!("wasm trap: {trap}"),
+        None => anyhow::anyhow!("wasm instantiation failed"),
     }
 }
 
@@ -47,43 +580,55 @@ impl DomainVm for WasmAdapter {
             serde_json::from_value(call.payload.clone()).context("invalid wasm call payload")?;
         let mut state = ctx.state.clone();
         let mut events = vec![];
-        let mut gas_used = call.max_gas.unwrap_or(3_000_000);
+        let max_gas = call.max_gas.unwrap_or(super::DEFAULT_DOMAIN_MAX_GAS);
+        let gas_used;
 
         match action {
-            WasmAction::Deploy { module_id, code_b64 } => {
+            WasmAction::Deploy {
+                module_id,
+                code_b64,
+            } => {
                 let bytes = BASE64
                     .decode(code_b64.as_bytes())
                     .context("invalid base64 wasm module")?;
-                // Ensure module is valid.
-                let _ = Module::new(&self.engine, &bytes)
-                    .context("failed to compile wasm module for domain")?;
+                gas_used = DEPLOY_GAS.saturating_add(BYTE_GAS.saturating_mul(bytes.len() as u64));
+                if gas_used > max_gas {
+                    anyhow::bail!("deploy needs {gas_used} gas, over the limit of {max_gas}");
+                }
+                self.compile(&bytes)?;
                 state.kv.insert(format!("wasm:{module_id}"), bytes);
                 events.push(format!("wasm_deploy:{module_id}"));
             }
-            WasmAction::Invoke { module_id, entry } => {
-                if let Some(code) = state.kv.get(&format!("wasm:{module_id}")) {
-                    let module =
-                        Module::new(&self.engine, code).context("wasm module failed to load")?;
-                    let mut store = Store::new(&self.engine, ());
-                    let fuel = call.max_gas.unwrap_or(3_000_000) as u64;
-                    let _ = store.add_fuel(fuel);
-                    let instance =
-                        wasmtime::Instance::new(&mut store, &module, &[]).context("instantiation failed")?;
-                    if let Some(func_name) = entry {
-                        if let Some(func) = instance.get_typed_func::<(), ()>(&mut store, &func_name).ok() {
-                            let _ = func.call(&mut store, ());
-                        }
-                    }
-                    let consumed = store.fuel_consumed().unwrap_or(fuel);
-                    gas_used = consumed as u64;
-                    state.kv.insert(
-                        format!("wasm:consumed:{module_id}"),
-                        consumed.to_le_bytes().to_vec(),
-                    );
-                    events.push(format!("wasm_invoke:{module_id}"));
-                } else {
+            WasmAction::Invoke {
+                module_id,
+                entry,
+                input_b64,
+            } => {
+                let Some(code) = state.kv.get(&format!("wasm:{module_id}")).cloned() else {
                     anyhow::bail!("missing wasm module {module_id}");
-                }
+                };
+                let input = input_b64
+                    .map(|input| BASE64.decode(input.as_bytes()))
+                    .transpose()
+                    .context("invalid base64 wasm input")?
+                    .unwrap_or_default();
+                let host = HostEnv {
+                    module_id: module_id.clone(),
+                    caller: ctx.caller,
+                    block_height: ctx.block_height,
+                    input,
+                    state,
+                    events: Vec::new(),
+                };
+                let (consumed, host) = self.invoke(&code, entry.as_deref(), max_gas, host)?;
+                gas_used = consumed;
+                state = host.state;
+                state.kv.insert(
+                    format!("wasm:consumed:{module_id}"),
+                    consumed.to_le_bytes().to_vec(),
+                );
+                events.push(format!("wasm_invoke:{module_id}"));
+                events.extend(host.events);
             }
         }
 
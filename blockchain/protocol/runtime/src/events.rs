@@ -0,0 +1,54 @@
+//! Structured events emitted while applying txs and blocks. Attribute values
+//! are strings: addresses, hashes and roots are hex, ids are UUIDs and
+//! amounts are decimal, so indexers can match on them without knowing the
+//! event kind.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use state::VoteChoice;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Event {
+    pub kind: String,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Event {
+    pub fn new(kind: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    pub fn with(mut self, key: &str, value: impl ToString) -> Self {
+        self.attributes.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Sets `key` to the hex encoding of `bytes`.
+    pub fn with_hex(self, key: &str, bytes: impl AsRef<[u8]>) -> Self {
+        self.with(key, hex::encode(bytes))
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(String::as_str)
+    }
+}
+
+/// Domain VMs report free-form strings; they are passed through as `data`.
+pub(crate) fn domain_event(domain_id: Uuid, data: &str) -> Event {
+    Event::new("domain_event")
+        .with("domain_id", domain_id)
+        .with("data", data)
+}
+
+pub(crate) fn vote_choice_str(choice: &VoteChoice) -> &'static str {
+    match choice {
+        VoteChoice::For => "for",
+        VoteChoice::Against => "against",
+        VoteChoice::Abstain => "abstain",
+    }
+}
@@ -0,0 +1,180 @@
+//! Self-contained proofs that a validator signed two conflicting consensus
+//! messages for the same view.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accepted_messages, address_from_pubkey, encode, hash_block, signing_message, verify_in_domain,
+    verify_in_domain_or_legacy, Address, Block, Hash, SigningDomain,
+};
+
+fn vote_payload(block_id: &Hash, view: u64) -> anyhow::Result<Vec<u8>> {
+    encode(&(block_id, view))
+}
+
+/// Bytes untagged vote signatures cover, from before the canonical encoding.
+fn legacy_vote_payload(block_id: &Hash, view: u64) -> anyhow::Result<Vec<u8>> {
+    Ok(bincode::serialize(&(block_id, view))?)
+}
+
+/// Message covered by a validator's ed25519 and BLS vote signatures.
+pub fn vote_signing_bytes(chain_id: &str, block_id: &Hash, view: u64) -> anyhow::Result<Vec<u8>> {
+    Ok(signing_message(
+        SigningDomain::Vote,
+        chain_id,
+        &vote_payload(block_id, view)?,
+    ))
+}
+
+/// Every message a vote signature may cover; the untagged form only with
+/// `accept_legacy`.
+pub fn vote_messages(
+    chain_id: &str,
+    block_id: &Hash,
+    view: u64,
+    accept_legacy: bool,
+) -> anyhow::Result<Vec<Vec<u8>>> {
+    Ok(accepted_messages(
+        SigningDomain::Vote,
+        chain_id,
+        &vote_payload(block_id, view)?,
+        &legacy_vote_payload(block_id, view)?,
+        accept_legacy,
+    ))
+}
+
+fn block_view(block: &Block) -> Option<u64> {
+    block
+        .header
+        .consensus_metadata
+        .get("view")
+        .and_then(|v| v.as_u64())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DoubleSignEvidence {
+    /// Two votes for different blocks in the same view.
+    Vote {
+        public_key: Vec<u8>,
+        view: u64,
+        first: (Hash, Vec<u8>),
+        second: (Hash, Vec<u8>),
+    },
+    /// Two different blocks proposed for the same view. Full blocks are
+    /// carried because proposal signatures cover the block hash only.
+    Proposal {
+        public_key: Vec<u8>,
+        first: Box<Block>,
+        first_signature: Vec<u8>,
+        second: Box<Block>,
+        second_signature: Vec<u8>,
+    },
+}
+
+impl DoubleSignEvidence {
+    pub fn public_key(&self) -> &[u8] {
+        match self {
+            DoubleSignEvidence::Vote { public_key, .. }
+            | DoubleSignEvidence::Proposal { public_key, .. } => public_key,
+        }
+    }
+
+    pub fn offender(&self) -> Address {
+        address_from_pubkey(self.public_key())
+    }
+
+    pub fn view(&self) -> u64 {
+        match self {
+            DoubleSignEvidence::Vote { view, .. } => *view,
+            DoubleSignEvidence::Proposal { first, .. } => block_view(first).unwrap_or_default(),
+        }
+    }
+
+    pub fn height(&self) -> Option<u64> {
+        match self {
+            DoubleSignEvidence::Vote { .. } => None,
+            DoubleSignEvidence::Proposal { first, .. } => Some(first.header.height),
+        }
+    }
+
+    fn block_ids(&self) -> (Hash, Hash) {
+        match self {
+            DoubleSignEvidence::Vote { first, second, .. } => (first.0, second.0),
+            DoubleSignEvidence::Proposal { first, second, .. } => {
+                (hash_block(first), hash_block(second))
+            }
+        }
+    }
+
+    /// Identifier that does not depend on the order of the two messages, so
+    /// the same equivocation cannot be punished twice.
+    pub fn id(&self) -> Hash {
+        let (a, b) = self.block_ids();
+        let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+        let kind: u8 = match self {
+            DoubleSignEvidence::Vote { .. } => 0,
+            DoubleSignEvidence::Proposal { .. } => 1,
+        };
+        let bytes =
+            bincode::serialize(&(kind, self.public_key(), self.view(), lo, hi)).unwrap_or_default();
+        *blake3::hash(&bytes).as_bytes()
+    }
+
+    /// Checks both signatures as made on `chain_id`, untagged ones only with
+    /// `accept_legacy`.
+    pub fn verify(&self, chain_id: &str, accept_legacy: bool) -> anyhow::Result<()> {
+        let (first_id, second_id) = self.block_ids();
+        if first_id == second_id {
+            anyhow::bail!("evidence references the same block twice");
+        }
+        match self {
+            DoubleSignEvidence::Vote {
+                public_key,
+                view,
+                first,
+                second,
+            } => {
+                for (block_id, signature) in [first, second] {
+                    verify_in_domain_or_legacy(
+                        public_key,
+                        signature,
+                        SigningDomain::Vote,
+                        chain_id,
+                        &vote_payload(block_id, *view)?,
+                        &legacy_vote_payload(block_id, *view)?,
+                        accept_legacy,
+                    )?;
+                }
+            }
+            DoubleSignEvidence::Proposal {
+                public_key,
+                first,
+                first_signature,
+                second,
+                second_signature,
+            } => {
+                let proposer = address_from_pubkey(public_key);
+                if first.header.proposer_id != proposer || second.header.proposer_id != proposer {
+                    anyhow::bail!("blocks were not proposed by the accused key");
+                }
+                let view = block_view(first).ok_or_else(|| anyhow::anyhow!("block has no view"))?;
+                if block_view(second) != Some(view) {
+                    anyhow::bail!("proposals are for different views");
+                }
+                for (signature, block_id) in
+                    [(first_signature, first_id), (second_signature, second_id)]
+                {
+                    verify_in_domain(
+                        public_key,
+                        signature,
+                        SigningDomain::Proposal,
+                        chain_id,
+                        &block_id,
+                        accept_legacy,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
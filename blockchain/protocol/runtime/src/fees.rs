@@ -0,0 +1,127 @@
+//! Gas estimation by simulation and fee suggestions from recent blocks.
+
+use serde::{Deserialize, Serialize};
+use state::{ChainState, StateStore};
+
+use crate::{execute_tx, Address, Block, Event, ExecutionContext, Tx, TxPayload};
+
+/// Headroom added on top of simulated gas, since state may change between
+/// estimation and inclusion.
+const GAS_HEADROOM_PCT: u64 = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasEstimate {
+    pub gas_used: u64,
+    /// `gas_used` plus headroom, capped at the block gas limit.
+    pub gas_limit: u64,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeTier {
+    pub max_priority_fee: u128,
+    pub max_fee: u128,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeSuggestion {
+    pub base_fee: u128,
+    /// Number of blocks the tips were sampled from.
+    pub sampled_blocks: usize,
+    pub slow: FeeTier,
+    pub standard: FeeTier,
+    pub fast: FeeTier,
+}
+
+/// Executes `payload` as `sender` at its current nonce on top of `base` and a
+/// copy of domain state, skipping the signature check. Nothing is written
+/// back to `ctx`.
+pub async fn estimate_gas<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    base: ChainState,
+    sender: Address,
+    payload: TxPayload,
+    height: u64,
+) -> anyhow::Result<GasEstimate> {
+    let sim = ctx.sandbox(base).await?;
+    let nonce = sim
+        .state
+        .get_account(&sender)
+        .await?
+        .map(|a| a.nonce)
+        .unwrap_or(0);
+    let tx = Tx {
+        chain_id: sim.chain_id.clone(),
+        nonce,
+        gas_limit: sim.max_gas_per_block,
+        max_fee: None,
+        max_priority_fee: None,
+        gas_price: None,
+        payload,
+        public_key: vec![],
+        signature: vec![],
+    };
+    let outcome = execute_tx(&sim, &tx, sender, height).await?;
+    let headroom = outcome.gas_used.saturating_mul(GAS_HEADROOM_PCT) / 100;
+    Ok(GasEstimate {
+        gas_used: outcome.gas_used,
+        gas_limit: outcome
+            .gas_used
+            .saturating_add(headroom)
+            .min(ctx.max_gas_per_block)
+            .max(outcome.gas_used),
+        events: outcome.events,
+    })
+}
+
+/// Suggests fees from the tips paid in `blocks` (oldest first) at the 25th,
+/// 50th and 90th percentiles. `max_fee` leaves room for the base fee to
+/// double before a transaction is priced out.
+pub fn suggest_fees(blocks: &[Block], default_base_fee: u128) -> FeeSuggestion {
+    let base_fee = blocks
+        .last()
+        .map(|b| b.header.base_fee)
+        .unwrap_or(default_base_fee);
+    let mut tips: Vec<u128> = blocks
+        .iter()
+        .flat_map(|b| {
+            b.transactions
+                .iter()
+                .map(move |tx| tip_paid(tx, b.header.base_fee))
+        })
+        .collect();
+    tips.sort_unstable();
+    let tier = |pct: usize| {
+        let tip = percentile(&tips, pct);
+        FeeTier {
+            max_priority_fee: tip,
+            max_fee: base_fee.saturating_mul(2).saturating_add(tip),
+        }
+    };
+    FeeSuggestion {
+        base_fee,
+        sampled_blocks: blocks.len(),
+        slow: tier(25),
+        standard: tier(50),
+        fast: tier(90),
+    }
+}
+
+/// Price paid above the base fee, mirroring the effective gas price rules.
+fn tip_paid(tx: &Tx, base_fee: u128) -> u128 {
+    match (tx.max_fee, tx.gas_price) {
+        (Some(max_fee), _) => tx
+            .max_priority_fee
+            .unwrap_or(0)
+            .min(max_fee.saturating_sub(base_fee)),
+        (None, Some(gas_price)) => gas_price.saturating_sub(base_fee),
+        (None, None) => 0,
+    }
+}
+
+fn percentile(sorted: &[u128], pct: usize) -> u128 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    sorted[(sorted.len() - 1) * pct / 100]
+}
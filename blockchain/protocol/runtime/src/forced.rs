@@ -0,0 +1,225 @@
+//! Force inclusion. A user whose domain tx the sequencer will not take can
+//! queue it on L1. A batch posted after the tx was queued must carry it,
+//! which anyone can show with the batch's blob before the deadline. Past the
+//! deadline L1 applies the call to the domain itself and slashes the round's
+//! leader for leaving it out.
+
+use state::{sections, Address, DomainStatus, ForcedInclusion, Hash, StateStore};
+use uuid::Uuid;
+
+use crate::{
+    accepts_legacy_signatures, address_from_pubkey, batch_height, domain_entry, domain_event,
+    preconf, sequencers, track_domain_root, verify_tx_signature, Event, ExecutionContext, Tx,
+    TxPayload,
+};
+
+/// Most txs a domain's force-inclusion queue holds at once.
+pub const MAX_FORCED_INCLUSIONS: usize = 64;
+
+/// Decodes `tx_bytes` as a signed call on `domain_id`.
+fn forced_tx(
+    chain_id: &str,
+    domain_id: &Uuid,
+    tx_bytes: &[u8],
+    accept_legacy: bool,
+) -> anyhow::Result<Tx> {
+    let tx: Tx = serde_json::from_slice(tx_bytes)
+        .map_err(|e| anyhow::anyhow!("tx_bytes is not an encoded tx: {e}"))?;
+    if tx.chain_id != chain_id {
+        anyhow::bail!("forced tx is for another chain");
+    }
+    verify_tx_signature(&tx, accept_legacy)?;
+    match &tx.payload {
+        TxPayload::DomainExecute(call) if call.domain_id == *domain_id => Ok(tx),
+        _ => anyhow::bail!("forced tx must be a call on the domain"),
+    }
+}
+
+/// Queues `tx_bytes`, a signed call on `domain_id`, for a batch to carry.
+pub(crate) async fn queue<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    sender: Address,
+    tx_bytes: &[u8],
+    height: u64,
+) -> anyhow::Result<Event> {
+    let params = sequencers::params(ctx, domain_id).await?;
+    let accept_legacy = accepts_legacy_signatures(ctx).await?;
+    let tx = forced_tx(&ctx.chain_id, domain_id, tx_bytes, accept_legacy)?;
+    let tx_hash = crate::tx_hash(&tx);
+    let queued_head = batch_height(ctx, domain_id).await?;
+    let mut queue = ctx
+        .state
+        .get_entry::<sections::ForcedInclusions>(domain_id)
+        .await?
+        .unwrap_or_default();
+    if queue.len() >= MAX_FORCED_INCLUSIONS {
+        anyhow::bail!("force-inclusion queue is full");
+    }
+    if queue.iter().any(|f| f.tx_hash == tx_hash) {
+        anyhow::bail!("tx is already queued");
+    }
+    let deadline = height + params.force_include_blocks;
+    queue.push(ForcedInclusion {
+        domain_id: *domain_id,
+        tx_hash,
+        tx_bytes: tx_bytes.to_vec(),
+        sender,
+        queued_head,
+        deadline,
+    });
+    ctx.state
+        .put_entry::<sections::ForcedInclusions>(*domain_id, queue)
+        .await?;
+    Ok(Event::new("force_include_queued")
+        .with_hex("sender", sender)
+        .with("domain_id", domain_id)
+        .with_hex("tx_hash", tx_hash)
+        .with("deadline", deadline))
+}
+
+/// Drops `tx_hash` from the queue once `blob`, the batch at `batch_height`,
+/// is shown to carry it.
+pub(crate) async fn prove<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    tx_hash: &Hash,
+    batch_height: u64,
+    blob: &[u8],
+    height: u64,
+) -> anyhow::Result<Event> {
+    let mut queue = ctx
+        .state
+        .get_entry::<sections::ForcedInclusions>(domain_id)
+        .await?
+        .unwrap_or_default();
+    let forced = queue
+        .iter()
+        .find(|f| f.tx_hash == *tx_hash)
+        .ok_or_else(|| anyhow::anyhow!("tx is not queued"))?;
+    if height > forced.deadline {
+        anyhow::bail!("force inclusion is past its deadline");
+    }
+    if batch_height <= forced.queued_head {
+        anyhow::bail!("batch was posted before the tx was queued");
+    }
+    preconf::check_batch_carries(ctx, domain_id, batch_height, blob, tx_hash).await?;
+    queue.retain(|f| f.tx_hash != *tx_hash);
+    if queue.is_empty() {
+        ctx.state
+            .remove_entry::<sections::ForcedInclusions>(domain_id)
+            .await?;
+    } else {
+        ctx.state
+            .put_entry::<sections::ForcedInclusions>(*domain_id, queue)
+            .await?;
+    }
+    Ok(Event::new("force_include_proven")
+        .with("domain_id", domain_id)
+        .with_hex("tx_hash", tx_hash)
+        .with("batch_height", batch_height))
+}
+
+/// Applies every queued tx past its deadline to its domain and slashes the
+/// domain's current leader for each.
+pub(crate) async fn settle<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    height: u64,
+) -> anyhow::Result<Vec<Event>> {
+    let queues = ctx
+        .state
+        .get_section::<sections::ForcedInclusions>()
+        .await?;
+    let mut expired: Vec<(Uuid, Vec<ForcedInclusion>)> = Vec::new();
+    for (domain_id, queue) in queues {
+        if !queue.iter().any(|f| height > f.deadline) {
+            continue;
+        }
+        let (due, waiting): (Vec<_>, Vec<_>) = queue.into_iter().partition(|f| height > f.deadline);
+        if waiting.is_empty() {
+            ctx.state
+                .remove_entry::<sections::ForcedInclusions>(&domain_id)
+                .await?;
+        } else {
+            ctx.state
+                .put_entry::<sections::ForcedInclusions>(domain_id, waiting)
+                .await?;
+        }
+        expired.push((domain_id, due));
+    }
+    if expired.is_empty() {
+        return Ok(Vec::new());
+    }
+    expired.sort_by_key(|(domain_id, _)| *domain_id);
+    let accept_legacy = accepts_legacy_signatures(ctx).await?;
+    let mut events = Vec::new();
+    for (domain_id, due) in expired {
+        let (Ok(entry), Ok(params)) = (
+            domain_entry(ctx, &domain_id).await,
+            sequencers::params(ctx, &domain_id).await,
+        ) else {
+            continue;
+        };
+        // Forcing a tx in does not get it past a paused or retired domain.
+        if entry.status != DomainStatus::Active {
+            continue;
+        }
+        if !ctx.domains.has_domain(&domain_id) {
+            ctx.domains.register(&entry)?;
+        }
+        for forced in due {
+            let Ok(Tx {
+                payload: TxPayload::DomainExecute(call),
+                public_key,
+                ..
+            }) = forced_tx(&ctx.chain_id, &domain_id, &forced.tx_bytes, accept_legacy)
+            else {
+                continue;
+            };
+            let caller = address_from_pubkey(&public_key);
+            match ctx.domains.execute(&call, caller, ctx, height).await {
+                Ok(receipt) => {
+                    track_domain_root(ctx, entry.proof_mode, &receipt, height).await?;
+                    events.extend(
+                        receipt
+                            .events
+                            .iter()
+                            .map(|data| domain_event(receipt.domain_id, data)),
+                    );
+                    events.push(
+                        Event::new("force_include_applied")
+                            .with("domain_id", domain_id)
+                            .with_hex("tx_hash", forced.tx_hash)
+                            .with_hex("state_root", receipt.state_root),
+                    );
+                }
+                // The call is dropped, but the sequencer still failed to
+                // carry it.
+                Err(err) => events.push(
+                    Event::new("force_include_failed")
+                        .with("domain_id", domain_id)
+                        .with_hex("tx_hash", forced.tx_hash)
+                        .with("error", err.to_string()),
+                ),
+            }
+            let leader = ctx
+                .state
+                .get_entry::<sections::SequencerRounds>(&domain_id)
+                .await?
+                .map(|r| r.leader);
+            if let Some(leader) = leader {
+                events.extend(
+                    sequencers::slash(
+                        ctx,
+                        &domain_id,
+                        &leader,
+                        params.force_include_slash_bps,
+                        "force_include_missed",
+                    )
+                    .await?,
+                );
+            }
+        }
+    }
+    Ok(events)
+}
@@ -0,0 +1,92 @@
+//! Derive a genesis for a new network from another network's state, e.g. a
+//! testnet mirroring mainnet balances.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use state::ChainState;
+
+use crate::{Address, GenesisConfig, GenesisValidator};
+
+/// Edits applied to the forked balances after stake has been folded back.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ForkPatch {
+    /// Balances overwritten (or created) in the new genesis.
+    #[serde(default)]
+    pub set_balances: Vec<(Address, u128)>,
+    /// Accounts dropped from the new genesis.
+    #[serde(default)]
+    pub remove: Vec<Address>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForkOptions {
+    pub chain_id: String,
+    /// Validator set of the new network; the source validators are dropped.
+    pub validators: Vec<GenesisValidator>,
+    /// When set, only these addresses are carried over.
+    pub allowlist: Option<HashSet<Address>>,
+    pub patch: ForkPatch,
+}
+
+/// Build a genesis from `source`, taking every non-state parameter from
+/// `template`. Bonded, delegated and unbonding stake is returned to its owners
+/// as liquid balance, since the source validator set does not carry over.
+pub fn fork_genesis(
+    source: &ChainState,
+    template: GenesisConfig,
+    options: ForkOptions,
+) -> anyhow::Result<GenesisConfig> {
+    if options.validators.is_empty() {
+        anyhow::bail!("forked genesis needs at least one validator");
+    }
+    // A distinct chain id keeps transactions signed for the source network
+    // from replaying on the fork.
+    if options.chain_id.is_empty() || options.chain_id == template.chain_id {
+        anyhow::bail!("fork needs a new, non-empty chain id");
+    }
+
+    let mut balances: BTreeMap<Address, u128> = BTreeMap::new();
+    let mut credit = |address: Address, amount: u128| -> anyhow::Result<()> {
+        let entry = balances.entry(address).or_default();
+        *entry = entry
+            .checked_add(amount)
+            .ok_or_else(|| anyhow::anyhow!("balance overflow"))?;
+        Ok(())
+    };
+
+    for account in source.accounts.values() {
+        credit(account.address, account.balance_x)?;
+    }
+    for validator in source.validators.values() {
+        let delegated = source.delegated_stake(&validator.id);
+        credit(validator.owner, validator.stake.saturating_sub(delegated))?;
+    }
+    for position in source.delegations.values() {
+        let rewards = source.delegation_rewards(&position.delegator, &position.validator_id);
+        credit(position.delegator, position.stake.saturating_add(rewards))?;
+    }
+    for unbond in &source.pending_unbonds {
+        credit(unbond.owner, unbond.amount)?;
+    }
+
+    if let Some(allowlist) = &options.allowlist {
+        balances.retain(|address, _| allowlist.contains(address));
+    }
+    for address in &options.patch.remove {
+        balances.remove(address);
+    }
+    for (address, balance) in &options.patch.set_balances {
+        balances.insert(*address, *balance);
+    }
+    balances.retain(|_, balance| *balance > 0);
+
+    Ok(GenesisConfig {
+        chain_id: options.chain_id,
+        initial_validators: options.validators,
+        initial_accounts: balances.into_iter().collect(),
+        // Recomputed from the forked balances and stake by `from_genesis`.
+        initial_total_supply: 0,
+        ..template
+    })
+}
@@ -0,0 +1,694 @@
+//! Typed governance proposals. A proposal whose kind names an action has its
+//! execution payload validated when submitted and applied when executed;
+//! other kinds, such as `general`, only signal.
+//!
+//! Proposers put down a deposit, settled by the end-of-block sweep once the
+//! proposal is no longer open: refunded, or burned when it was defeated with
+//! little support.
+
+use serde::{Deserialize, Serialize};
+use state::{
+    sections, Address, DomainStatus, FeeSplit, GovernanceParams, ParamOverrides, PrivacyPool,
+    Proposal, ProposalQuery, ProposalStatus, ProtocolUpgrade, RewardParams, StakingParams,
+    StateStore, TreasuryPeriod, DEFAULT_PRIVACY_POOL, MAX_PROPOSAL_PAGE_SIZE,
+};
+use uuid::Uuid;
+use zk_core::ProgramId;
+
+use crate::{
+    default_account, domain_entry, finalize_proposal, validate_domain_risk, Event, ExecutionContext,
+};
+
+/// Sets a privacy pool's withdraw fees.
+pub const PRIVACY_FEE_PROPOSAL: &str = "privacy_fee";
+/// Registers a fixed-denomination privacy pool.
+pub const PRIVACY_POOL_PROPOSAL: &str = "privacy_pool";
+/// Sets one chain parameter: `{"param": "<name>", "value": <value>}`.
+pub const PARAM_CHANGE_PROPOSAL: &str = "param_change";
+/// Replaces the fee split.
+pub const FEE_SPLIT_PROPOSAL: &str = "fee_split_update";
+/// Replaces the inflation and reward parameters.
+pub const REWARD_PARAMS_PROPOSAL: &str = "reward_params_update";
+/// Changes a registered domain's entry.
+pub const DOMAIN_ADMIN_PROPOSAL: &str = "domain_admin";
+/// Pays from the treasury pool.
+pub const TREASURY_SPEND_PROPOSAL: &str = "treasury_spend";
+/// Registers a zk program's verification key version.
+pub const VERIFICATION_KEY_PROPOSAL: &str = "verification_key";
+/// Sets a domain's bridge limits or pauses its bridge.
+pub const BRIDGE_LIMITS_PROPOSAL: &str = "bridge_limits";
+/// Activates a pending domain.
+pub const DOMAIN_APPROVAL_PROPOSAL: &str = "domain_approval";
+/// Schedules a protocol version to take effect at a height.
+pub const PROTOCOL_UPGRADE_PROPOSAL: &str = "protocol_upgrade";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyFeeUpdate {
+    #[serde(default = "default_privacy_pool_id")]
+    pub pool: String,
+    pub withdraw_fee_bps: u16,
+    #[serde(default)]
+    pub relayer_fee_share_bps: u16,
+}
+
+fn default_privacy_pool_id() -> String {
+    DEFAULT_PRIVACY_POOL.into()
+}
+
+const MAX_PRIVACY_POOL_ID_LEN: usize = 32;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyPoolRegistration {
+    pub pool: String,
+    pub denomination: u128,
+    #[serde(default)]
+    pub withdraw_fee_bps: u16,
+    #[serde(default)]
+    pub relayer_fee_share_bps: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "param", content = "value", rename_all = "snake_case")]
+pub enum ParamChange {
+    SlashPenaltyBps(u16),
+    UnbondingDelayBlocks(u64),
+    ExitChurnBps(u16),
+    MaxCommissionChangePerEpoch(u8),
+    MinValidatorStake(u128),
+    MaxActiveValidators(u32),
+    VotingPeriodMs(u64),
+    TimelockMs(u64),
+    QuorumBps(u16),
+    ApprovalThresholdBps(u16),
+    TreasurySpendCap(u128),
+    TreasurySpendPeriodBlocks(u64),
+    DomainRegistrationBond(u128),
+    SharedSecurityApproval(bool),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainAdmin {
+    pub domain_id: Uuid,
+    #[serde(default)]
+    pub risk_params: Option<serde_json::Value>,
+    #[serde(default)]
+    pub sequencer_binding: Option<Uuid>,
+    #[serde(default)]
+    pub bridge_contracts: Option<Vec<String>>,
+    /// Moves the domain to this status, e.g. to pause it on its owner's
+    /// behalf.
+    #[serde(default)]
+    pub status: Option<DomainStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainApproval {
+    pub domain_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreasurySpend {
+    /// Hex-encoded address.
+    pub recipient: String,
+    pub amount: u128,
+}
+
+const MAX_VK_VERSION_LEN: usize = 32;
+const MAX_UPGRADE_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationKeyUpdate {
+    pub program: ProgramId,
+    pub version: String,
+    /// Hex-encoded key, as backends put it in `ProofArtifact::verification_key`.
+    pub verification_key: String,
+    /// Switches verification to this version once executed. Registering
+    /// without activating stages a key ahead of an upgrade.
+    pub activate: bool,
+}
+
+/// Bridge limits to change for a domain; fields left out keep their value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeLimitsUpdate {
+    pub domain_id: Uuid,
+    #[serde(default)]
+    pub max_withdrawal: Option<u128>,
+    #[serde(default)]
+    pub epoch_outflow_cap: Option<u128>,
+    #[serde(default)]
+    pub paused: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+pub enum GovernanceAction {
+    PrivacyFee(PrivacyFeeUpdate),
+    PrivacyPool(PrivacyPoolRegistration),
+    ParamChange(ParamChange),
+    FeeSplit(FeeSplit),
+    RewardParams(RewardParams),
+    DomainAdmin(DomainAdmin),
+    TreasurySpend(TreasurySpend),
+    VerificationKey(VerificationKeyUpdate),
+    BridgeLimits(BridgeLimitsUpdate),
+    DomainApproval(DomainApproval),
+    ProtocolUpgrade(ProtocolUpgrade),
+}
+
+fn decode<T: serde::de::DeserializeOwned>(
+    kind: &str,
+    payload: &serde_json::Value,
+) -> anyhow::Result<T> {
+    serde_json::from_value(payload.clone())
+        .map_err(|e| anyhow::anyhow!("invalid {kind} payload: {e}"))
+}
+
+fn parse_verification_key(hex_str: &str) -> anyhow::Result<Vec<u8>> {
+    let key = hex::decode(hex_str.trim_start_matches("0x"))?;
+    if key.is_empty() {
+        anyhow::bail!("verification key must not be empty");
+    }
+    Ok(key)
+}
+
+fn parse_recipient(hex_str: &str) -> anyhow::Result<Address> {
+    hex::decode(hex_str.trim_start_matches("0x"))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("recipient must be 32 bytes"))
+}
+
+impl ParamChange {
+    fn validate(&self) -> anyhow::Result<()> {
+        match *self {
+            Self::SlashPenaltyBps(bps)
+            | Self::ExitChurnBps(bps)
+            | Self::QuorumBps(bps)
+            | Self::ApprovalThresholdBps(bps)
+                if bps > 10_000 =>
+            {
+                anyhow::bail!("bps must be <= 10000")
+            }
+            Self::ExitChurnBps(0) => anyhow::bail!("exit churn must be > 0"),
+            Self::MaxCommissionChangePerEpoch(points) if points > 100 => {
+                anyhow::bail!("commission change must be <= 100 points")
+            }
+            Self::MaxActiveValidators(0) => anyhow::bail!("active set must not be empty"),
+            Self::VotingPeriodMs(0) => anyhow::bail!("voting period must be > 0"),
+            Self::TreasurySpendPeriodBlocks(0) => anyhow::bail!("spend period must be > 0"),
+            _ => Ok(()),
+        }
+    }
+
+    fn apply(
+        &self,
+        overrides: &mut ParamOverrides,
+        staking: &mut StakingParams,
+        governance: &mut GovernanceParams,
+    ) {
+        match *self {
+            Self::SlashPenaltyBps(bps) => overrides.slash_penalty_bps = Some(bps),
+            Self::UnbondingDelayBlocks(blocks) => overrides.unbonding_delay_blocks = Some(blocks),
+            Self::ExitChurnBps(bps) => overrides.exit_churn_bps = Some(bps),
+            Self::MaxCommissionChangePerEpoch(points) => {
+                overrides.max_commission_change_per_epoch = Some(points)
+            }
+            Self::MinValidatorStake(stake) => staking.min_validator_stake = stake,
+            Self::MaxActiveValidators(max) => staking.max_active_validators = max,
+            Self::VotingPeriodMs(ms) => governance.voting_period_ms = ms,
+            Self::TimelockMs(ms) => governance.timelock_ms = ms,
+            Self::QuorumBps(bps) => governance.quorum_bps = bps,
+            Self::ApprovalThresholdBps(bps) => governance.approval_threshold_bps = bps,
+            Self::TreasurySpendCap(cap) => governance.treasury_spend_cap = cap,
+            Self::TreasurySpendPeriodBlocks(blocks) => {
+                governance.treasury_spend_period_blocks = blocks
+            }
+            Self::DomainRegistrationBond(bond) => governance.domain_registration_bond = bond,
+            Self::SharedSecurityApproval(required) => {
+                governance.shared_security_approval = required
+            }
+        }
+    }
+}
+
+fn validate_fee_split(split: &FeeSplit) -> anyhow::Result<()> {
+    let sums = [
+        (
+            "l1 gas",
+            split.l1_gas_burn_pct as u16 + split.l1_gas_validators_pct as u16,
+        ),
+        (
+            "da",
+            split.da_validators_pct as u16
+                + split.da_nodes_pct as u16
+                + split.da_treasury_pct as u16,
+        ),
+        (
+            "l2",
+            split.l2_sequencer_pct as u16
+                + split.l2_da_costs_pct as u16
+                + split.l2_l1_rent_pct as u16,
+        ),
+    ];
+    for (name, sum) in sums {
+        if sum != 100 {
+            anyhow::bail!("{name} fee shares must sum to 100, got {sum}");
+        }
+    }
+    Ok(())
+}
+
+fn validate_reward_params(params: &RewardParams) -> anyhow::Result<()> {
+    if params.max_inflation_bps > 10_000 || params.target_stake_bps > 10_000 {
+        anyhow::bail!("reward bps must be <= 10000");
+    }
+    if params.base_inflation_bps > params.max_inflation_bps {
+        anyhow::bail!("base inflation above max inflation");
+    }
+    if params.treasury_pct as u16 + params.proposer_bonus_pct as u16 > 100 {
+        anyhow::bail!("treasury and proposer shares exceed 100%");
+    }
+    Ok(())
+}
+
+impl GovernanceAction {
+    /// The validated action a proposal of `kind` carries, or `None` for kinds
+    /// without an on-chain effect.
+    pub fn parse(kind: &str, payload: &serde_json::Value) -> anyhow::Result<Option<Self>> {
+        let action = match kind {
+            PRIVACY_FEE_PROPOSAL => {
+                let update: PrivacyFeeUpdate = decode(kind, payload)?;
+                if update.withdraw_fee_bps > 10_000 || update.relayer_fee_share_bps > 10_000 {
+                    anyhow::bail!("privacy fee bps must be <= 10000");
+                }
+                Self::PrivacyFee(update)
+            }
+            PRIVACY_POOL_PROPOSAL => {
+                let registration: PrivacyPoolRegistration = decode(kind, payload)?;
+                let id = &registration.pool;
+                if id.is_empty()
+                    || id.len() > MAX_PRIVACY_POOL_ID_LEN
+                    || !id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+                {
+                    anyhow::bail!(
+                        "pool id must be 1 to {MAX_PRIVACY_POOL_ID_LEN} letters, digits, '_' or '-'"
+                    );
+                }
+                if registration.denomination == 0 {
+                    anyhow::bail!("denomination must be > 0");
+                }
+                if registration.withdraw_fee_bps > 10_000
+                    || registration.relayer_fee_share_bps > 10_000
+                {
+                    anyhow::bail!("privacy fee bps must be <= 10000");
+                }
+                Self::PrivacyPool(registration)
+            }
+            PARAM_CHANGE_PROPOSAL => {
+                let change: ParamChange = decode(kind, payload)?;
+                change.validate()?;
+                Self::ParamChange(change)
+            }
+            FEE_SPLIT_PROPOSAL => {
+                let split: FeeSplit = decode(kind, payload)?;
+                validate_fee_split(&split)?;
+                Self::FeeSplit(split)
+            }
+            REWARD_PARAMS_PROPOSAL => {
+                let params: RewardParams = decode(kind, payload)?;
+                validate_reward_params(&params)?;
+                Self::RewardParams(params)
+            }
+            DOMAIN_ADMIN_PROPOSAL => {
+                let admin: DomainAdmin = decode(kind, payload)?;
+                if let Some(risk) = &admin.risk_params {
+                    validate_domain_risk(risk)?;
+                }
+                Self::DomainAdmin(admin)
+            }
+            TREASURY_SPEND_PROPOSAL => {
+                let spend: TreasurySpend = decode(kind, payload)?;
+                parse_recipient(&spend.recipient)?;
+                if spend.amount == 0 {
+                    anyhow::bail!("amount must be > 0");
+                }
+                Self::TreasurySpend(spend)
+            }
+            VERIFICATION_KEY_PROPOSAL => {
+                let update: VerificationKeyUpdate = decode(kind, payload)?;
+                if update.version.is_empty() || update.version.len() > MAX_VK_VERSION_LEN {
+                    anyhow::bail!("version must be 1 to {MAX_VK_VERSION_LEN} characters");
+                }
+                parse_verification_key(&update.verification_key)?;
+                Self::VerificationKey(update)
+            }
+            BRIDGE_LIMITS_PROPOSAL => Self::BridgeLimits(decode(kind, payload)?),
+            DOMAIN_APPROVAL_PROPOSAL => Self::DomainApproval(decode(kind, payload)?),
+            PROTOCOL_UPGRADE_PROPOSAL => {
+                let upgrade: ProtocolUpgrade = decode(kind, payload)?;
+                if upgrade.name.is_empty() || upgrade.name.len() > MAX_UPGRADE_NAME_LEN {
+                    anyhow::bail!("upgrade name must be 1 to {MAX_UPGRADE_NAME_LEN} characters");
+                }
+                Self::ProtocolUpgrade(upgrade)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(action))
+    }
+
+    /// Applies the action to the state store.
+    pub(crate) async fn apply<S: StateStore>(
+        &self,
+        ctx: &ExecutionContext<S>,
+        height: u64,
+    ) -> anyhow::Result<Event> {
+        match self {
+            Self::PrivacyFee(update) => {
+                let pool = ctx
+                    .state
+                    .get_entry::<sections::PrivacyPools>(&update.pool)
+                    .await?;
+                let mut pool = match pool {
+                    Some(pool) => pool,
+                    None if update.pool == DEFAULT_PRIVACY_POOL => PrivacyPool::default(),
+                    None => anyhow::bail!("privacy pool not registered"),
+                };
+                pool.withdraw_fee_bps = update.withdraw_fee_bps;
+                pool.relayer_fee_share_bps = update.relayer_fee_share_bps;
+                ctx.state
+                    .put_entry::<sections::PrivacyPools>(update.pool.clone(), pool)
+                    .await?;
+                Ok(Event::new(PRIVACY_FEE_PROPOSAL)
+                    .with("pool", &update.pool)
+                    .with("withdraw_fee_bps", update.withdraw_fee_bps)
+                    .with("relayer_fee_share_bps", update.relayer_fee_share_bps))
+            }
+            Self::PrivacyPool(registration) => {
+                let existing = ctx
+                    .state
+                    .get_entry::<sections::PrivacyPools>(&registration.pool)
+                    .await?;
+                if existing.is_some() {
+                    anyhow::bail!("privacy pool {} already exists", registration.pool);
+                }
+                let pool = PrivacyPool {
+                    denomination: Some(registration.denomination),
+                    withdraw_fee_bps: registration.withdraw_fee_bps,
+                    relayer_fee_share_bps: registration.relayer_fee_share_bps,
+                    ..PrivacyPool::default()
+                };
+                ctx.state
+                    .put_entry::<sections::PrivacyPools>(registration.pool.clone(), pool)
+                    .await?;
+                Ok(Event::new(PRIVACY_POOL_PROPOSAL)
+                    .with("pool", &registration.pool)
+                    .with("denomination", registration.denomination))
+            }
+            Self::ParamChange(change) => {
+                let mut overrides = ctx.state.get_param_overrides().await?;
+                let mut staking = ctx.state.get_section::<sections::StakingParams>().await?;
+                let mut governance = ctx.state.get_governance_params().await?;
+                change.apply(&mut overrides, &mut staking, &mut governance);
+                ctx.state
+                    .put_section::<sections::ParamOverrides>(overrides)
+                    .await?;
+                ctx.state
+                    .put_section::<sections::StakingParams>(staking)
+                    .await?;
+                ctx.state
+                    .put_section::<sections::GovernanceParams>(governance)
+                    .await?;
+                let encoded = serde_json::to_value(change)?;
+                Ok(Event::new(PARAM_CHANGE_PROPOSAL)
+                    .with("param", encoded["param"].as_str().unwrap_or_default())
+                    .with("value", &encoded["value"]))
+            }
+            Self::FeeSplit(split) => {
+                let mut overrides = ctx.state.get_param_overrides().await?;
+                overrides.fee_split = Some(split.clone());
+                ctx.state
+                    .put_section::<sections::ParamOverrides>(overrides)
+                    .await?;
+                Ok(Event::new(FEE_SPLIT_PROPOSAL)
+                    .with("l1_gas_burn_pct", split.l1_gas_burn_pct)
+                    .with("l1_gas_validators_pct", split.l1_gas_validators_pct))
+            }
+            Self::RewardParams(params) => {
+                let mut overrides = ctx.state.get_param_overrides().await?;
+                overrides.reward_params = Some(params.clone());
+                ctx.state
+                    .put_section::<sections::ParamOverrides>(overrides)
+                    .await?;
+                Ok(Event::new(REWARD_PARAMS_PROPOSAL)
+                    .with("base_inflation_bps", params.base_inflation_bps)
+                    .with("max_inflation_bps", params.max_inflation_bps))
+            }
+            Self::DomainAdmin(admin) => {
+                let mut entry = domain_entry(ctx, &admin.domain_id).await?;
+                if let Some(risk) = &admin.risk_params {
+                    entry.risk_params = risk.clone();
+                }
+                if let Some(binding) = admin.sequencer_binding {
+                    entry.sequencer_binding = Some(binding);
+                }
+                if let Some(contracts) = &admin.bridge_contracts {
+                    entry.bridge_contracts = contracts.clone();
+                }
+                if let Some(status) = admin.status {
+                    if entry.status == DomainStatus::Retired {
+                        anyhow::bail!("domain is retired");
+                    }
+                    if status == DomainStatus::Retired {
+                        anyhow::bail!("only the domain owner may retire it");
+                    }
+                    entry.status = status;
+                }
+                // Adapters are built from the entry, so rebuild it for new limits.
+                if ctx.domains.has_domain(&admin.domain_id) {
+                    ctx.domains.register(&entry)?;
+                }
+                ctx.state
+                    .put_entry::<sections::Domains>(admin.domain_id, entry)
+                    .await?;
+                Ok(Event::new(DOMAIN_ADMIN_PROPOSAL).with("domain_id", admin.domain_id))
+            }
+            Self::TreasurySpend(spend) => {
+                let recipient = parse_recipient(&spend.recipient)?;
+                let params = ctx.state.get_governance_params().await?;
+                let mut tracked = ctx.state.get_section::<sections::TreasuryPeriod>().await?;
+                let spent = record_treasury_spend(&params, &mut tracked, height, spend.amount)?;
+                let mut pools = ctx.state.get_fee_pools().await?;
+                pools.treasury = pools
+                    .treasury
+                    .checked_sub(spend.amount)
+                    .ok_or_else(|| anyhow::anyhow!("treasury balance too low"))?;
+                ctx.state
+                    .put_section::<sections::TreasuryPeriod>(tracked)
+                    .await?;
+                ctx.state.put_fee_pools(pools).await?;
+                credit(ctx, recipient, spend.amount).await?;
+                Ok(Event::new(TREASURY_SPEND_PROPOSAL)
+                    .with_hex("recipient", recipient)
+                    .with("amount", spend.amount)
+                    .with("period_spent", spent))
+            }
+            Self::VerificationKey(update) => {
+                let program = update.program.to_string();
+                let mut keys = ctx
+                    .state
+                    .get_section::<sections::VerificationKeys>()
+                    .await?;
+                keys.register(
+                    &program,
+                    &update.version,
+                    parse_verification_key(&update.verification_key)?,
+                    update.activate,
+                )?;
+                ctx.state
+                    .put_section::<sections::VerificationKeys>(keys)
+                    .await?;
+                Ok(Event::new(VERIFICATION_KEY_PROPOSAL)
+                    .with("program", program)
+                    .with("version", &update.version)
+                    .with("active", update.activate))
+            }
+            Self::DomainApproval(approval) => {
+                let mut entry = domain_entry(ctx, &approval.domain_id).await?;
+                if entry.status != DomainStatus::Pending {
+                    anyhow::bail!("domain is not pending approval");
+                }
+                entry.status = DomainStatus::Active;
+                ctx.state
+                    .put_entry::<sections::Domains>(approval.domain_id, entry)
+                    .await?;
+                Ok(Event::new(DOMAIN_APPROVAL_PROPOSAL).with("domain_id", approval.domain_id))
+            }
+            Self::BridgeLimits(update) => {
+                domain_entry(ctx, &update.domain_id).await?;
+                let mut escrow = ctx
+                    .state
+                    .get_entry::<sections::BridgeEscrows>(&update.domain_id)
+                    .await?
+                    .unwrap_or_default();
+                let limits = &mut escrow.limits;
+                if let Some(max) = update.max_withdrawal {
+                    limits.max_withdrawal = max;
+                }
+                if let Some(cap) = update.epoch_outflow_cap {
+                    limits.epoch_outflow_cap = cap;
+                }
+                if let Some(paused) = update.paused {
+                    limits.paused = paused;
+                }
+                let event = Event::new(BRIDGE_LIMITS_PROPOSAL)
+                    .with("domain_id", update.domain_id)
+                    .with("max_withdrawal", limits.max_withdrawal)
+                    .with("epoch_outflow_cap", limits.epoch_outflow_cap)
+                    .with("paused", limits.paused);
+                ctx.state
+                    .put_entry::<sections::BridgeEscrows>(update.domain_id, escrow)
+                    .await?;
+                Ok(event)
+            }
+            Self::ProtocolUpgrade(upgrade) => {
+                let active = ctx.state.get_protocol_version().await?;
+                if upgrade.version <= active {
+                    anyhow::bail!(
+                        "protocol version {} is not newer than the active {}",
+                        upgrade.version,
+                        active
+                    );
+                }
+                if upgrade.activation_height <= height {
+                    anyhow::bail!("activation height {} has passed", upgrade.activation_height);
+                }
+                // A later upgrade replaces one still pending.
+                ctx.state
+                    .put_section::<sections::PendingUpgrade>(Some(upgrade.clone()))
+                    .await?;
+                Ok(Event::new(PROTOCOL_UPGRADE_PROPOSAL)
+                    .with("name", &upgrade.name)
+                    .with("version", upgrade.version)
+                    .with("activation_height", upgrade.activation_height))
+            }
+        }
+    }
+}
+
+/// Counts `amount` against the cap of the period `height` falls in, starting
+/// a new period when needed. Returns the period's total so far.
+fn record_treasury_spend(
+    params: &GovernanceParams,
+    tracked: &mut TreasuryPeriod,
+    height: u64,
+    amount: u128,
+) -> anyhow::Result<u128> {
+    let period = height / params.treasury_spend_period_blocks.max(1);
+    if tracked.period != period {
+        tracked.period = period;
+        tracked.spent = 0;
+    }
+    let spent = tracked.spent.saturating_add(amount);
+    if spent > params.treasury_spend_cap {
+        anyhow::bail!(
+            "treasury spend cap of {} per period exceeded ({} already spent)",
+            params.treasury_spend_cap,
+            tracked.spent
+        );
+    }
+    tracked.spent = spent;
+    Ok(spent)
+}
+
+async fn credit<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    address: Address,
+    amount: u128,
+) -> anyhow::Result<()> {
+    let mut account = ctx
+        .state
+        .get_account(&address)
+        .await?
+        .unwrap_or_else(|| default_account(address));
+    account.balance_x = account
+        .balance_x
+        .checked_add(amount)
+        .ok_or_else(|| anyhow::anyhow!("balance overflow"))?;
+    ctx.state.put_account(account).await
+}
+
+/// Defeated proposals that drew too little support forfeit their deposit.
+fn deposit_burned(p: &Proposal, params: &GovernanceParams) -> bool {
+    p.status == ProposalStatus::Defeated
+        && p.for_votes.saturating_mul(10_000)
+            < params.deposit_burn_threshold_bps as u128 * p.snapshot_total_stake
+}
+
+/// Closes proposals whose voting period is over, expires queued ones left
+/// unexecuted past the grace period, and settles the deposits of proposals
+/// no longer open.
+pub(crate) async fn sweep_proposals<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    now: u64,
+) -> anyhow::Result<Vec<Event>> {
+    let params = ctx.state.get_governance_params().await?;
+    let mut proposals = all_proposals(ctx).await?;
+    proposals.sort_by_key(|p| p.id);
+    let mut total_supply = ctx.state.get_section::<sections::TotalSupply>().await?;
+    let mut events = Vec::new();
+    for original in proposals {
+        let id = original.id;
+        let mut p = original.clone();
+        finalize_proposal(&mut p, &params, now);
+        let stale = p
+            .eta
+            .is_some_and(|eta| now > eta.saturating_add(params.queued_grace_ms));
+        if p.status == ProposalStatus::Queued && stale {
+            p.status = ProposalStatus::Expired;
+            events.push(Event::new("gov_expired").with("proposal_id", id));
+        }
+        let open = matches!(p.status, ProposalStatus::Pending | ProposalStatus::Active);
+        if p.deposit != 0 && !open {
+            let deposit = std::mem::take(&mut p.deposit);
+            if deposit_burned(&p, &params) {
+                total_supply = total_supply.saturating_sub(deposit);
+                events.push(
+                    Event::new("gov_deposit_burned")
+                        .with("proposal_id", id)
+                        .with("amount", deposit),
+                );
+            } else {
+                credit(ctx, p.proposer, deposit).await?;
+                events.push(
+                    Event::new("gov_deposit_refunded")
+                        .with("proposal_id", id)
+                        .with_hex("proposer", p.proposer)
+                        .with("amount", deposit),
+                );
+            }
+        }
+        if p.status != original.status || p.deposit != original.deposit {
+            ctx.state.put_proposal(p).await?;
+        }
+    }
+    ctx.state
+        .put_section::<sections::TotalSupply>(total_supply)
+        .await?;
+    Ok(events)
+}
+
+/// Every proposal, read a page at a time.
+async fn all_proposals<S: StateStore>(ctx: &ExecutionContext<S>) -> anyhow::Result<Vec<Proposal>> {
+    let mut query = ProposalQuery {
+        limit: Some(MAX_PROPOSAL_PAGE_SIZE),
+        ..ProposalQuery::default()
+    };
+    let mut proposals = Vec::new();
+    loop {
+        let page = ctx.state.list_proposals(&query).await?;
+        let done = page.items.is_empty() || proposals.len() + page.items.len() >= page.total;
+        query.offset = Some(page.offset + page.items.len());
+        proposals.extend(page.items);
+        if done {
+            return Ok(proposals);
+        }
+    }
+}
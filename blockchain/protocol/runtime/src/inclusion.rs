@@ -0,0 +1,170 @@
+//! What it takes for a tx to be part of a block. A tx must pass admission
+//! (signature, chain id, nonce, fee, and the sender being able to pay for
+//! gas out of funds that have vested) or the whole block is invalid, so proposers filter on it. Once
+//! admitted, a tx that fails execution is still included: its effects are
+//! rolled back, its gas is charged and the sender's nonce advances.
+
+use state::{locked_balance, Checkpoint, StateStore};
+
+use crate::{
+    accepts_legacy_signatures, default_account, effective_gas_price, execute_tx, gas_cost,
+    route_gas_fee_in_store, verify_tx_signature, Address, ExecutionContext, ExecutionOutcome, Tx,
+};
+
+struct Admission {
+    sender: Address,
+    gas_used: u64,
+    gas_fee: u128,
+    /// Part of the sender's balance still vesting, which gas can't touch.
+    locked: u128,
+}
+
+async fn admit<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    tx: &Tx,
+    sender: Address,
+    height: u64,
+) -> anyhow::Result<Admission> {
+    if tx.chain_id != ctx.chain_id {
+        anyhow::bail!("invalid chain id");
+    }
+    let account = ctx
+        .state
+        .get_account(&sender)
+        .await?
+        .unwrap_or(default_account(sender));
+    if account.nonce != tx.nonce {
+        anyhow::bail!("invalid nonce");
+    }
+    // The gas schedule is static, so a failed tx pays what it would have on
+    // success.
+    let gas_used = gas_cost(&tx.payload);
+    let gas_fee = (gas_used as u128)
+        .checked_mul(effective_gas_price(tx, ctx.base_fee)?)
+        .ok_or_else(|| anyhow::anyhow!("gas fee overflow"))?;
+    let locked = locked_balance(&ctx.state.get_vesting(&sender).await?, height);
+    if account.balance_x.saturating_sub(locked) < gas_fee {
+        anyhow::bail!("insufficient funds for gas");
+    }
+    Ok(Admission {
+        sender,
+        gas_used,
+        gas_fee,
+        locked,
+    })
+}
+
+/// Applies `tx` as part of a block at `height`. Errors only when `tx` fails
+/// admission; an execution failure comes back as a failed outcome.
+pub async fn include_tx<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    tx: &Tx,
+    height: u64,
+) -> anyhow::Result<ExecutionOutcome> {
+    let sender = verify_tx_signature(tx, accepts_legacy_signatures(ctx).await?)?;
+    include_from(ctx, tx, sender, height).await
+}
+
+/// `include_tx` for a tx whose sender is authorized by other means than its
+/// signature, such as a call the sender scheduled earlier.
+pub(crate) async fn include_from<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    tx: &Tx,
+    sender: Address,
+    height: u64,
+) -> anyhow::Result<ExecutionOutcome> {
+    let admission = admit(ctx, tx, sender, height).await?;
+    let checkpoint = ctx.state.checkpoint().await?;
+    let pre_domains = ctx.domains.checkpoint();
+    match execute_tx(ctx, tx, admission.sender, height).await {
+        Ok(outcome) => Ok(outcome),
+        Err(err) => {
+            ctx.domains.restore(pre_domains);
+            charge_failed(ctx, checkpoint, &admission).await?;
+            Ok(ExecutionOutcome::failed(
+                admission.gas_used,
+                format!("{err:#}"),
+            ))
+        }
+    }
+}
+
+/// Puts back the state from before a failed tx, then charges its gas and
+/// bumps the sender's nonce.
+async fn charge_failed<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    checkpoint: Checkpoint,
+    admission: &Admission,
+) -> anyhow::Result<()> {
+    ctx.state.rollback(checkpoint).await?;
+    let mut sender = ctx
+        .state
+        .get_account(&admission.sender)
+        .await?
+        .unwrap_or(default_account(admission.sender));
+    if sender.balance_x.saturating_sub(admission.locked) < admission.gas_fee {
+        anyhow::bail!("insufficient funds for gas");
+    }
+    sender.balance_x -= admission.gas_fee;
+    sender.nonce += 1;
+    ctx.state.put_account(sender).await?;
+    route_gas_fee_in_store(ctx, admission.gas_fee).await
+}
+
+#[derive(Debug, Default)]
+pub struct BlockSelection {
+    pub included: Vec<Tx>,
+    /// Over the block's gas limit or ahead of the sender's nonce; may fit a
+    /// later block.
+    pub deferred: Vec<Tx>,
+    /// Would make the block invalid, with the reason.
+    pub rejected: Vec<(Tx, String)>,
+}
+
+/// Picks, in order, the `candidates` that `apply_block` would accept at
+/// `height`. They are run on a copy of state so each is checked against the
+/// effects of those before it; passes repeat while txs held back by a nonce
+/// gap become includable.
+pub async fn select_block_txs<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    candidates: Vec<Tx>,
+    height: u64,
+) -> anyhow::Result<BlockSelection> {
+    let sim = ctx.sandbox(ctx.state.get_chain_state().await?).await?;
+    let mut selection = BlockSelection::default();
+    let mut gas_used = 0_u64;
+    let mut pending = candidates;
+    loop {
+        let included_before = selection.included.len();
+        let mut deferred = Vec::new();
+        for tx in pending {
+            if gas_used.saturating_add(gas_cost(&tx.payload)) > ctx.max_gas_per_block
+                || nonce_ahead(&sim, &tx).await?
+            {
+                deferred.push(tx);
+                continue;
+            }
+            match include_tx(&sim, &tx, height).await {
+                Ok(outcome) => {
+                    gas_used = gas_used.saturating_add(outcome.gas_used);
+                    selection.included.push(tx);
+                }
+                Err(err) => selection.rejected.push((tx, format!("{err:#}"))),
+            }
+        }
+        pending = deferred;
+        if selection.included.len() == included_before || pending.is_empty() {
+            break;
+        }
+    }
+    selection.deferred = pending;
+    Ok(selection)
+}
+
+async fn nonce_ahead<S: StateStore>(ctx: &ExecutionContext<S>, tx: &Tx) -> anyhow::Result<bool> {
+    let Ok(sender) = verify_tx_signature(tx, accepts_legacy_signatures(ctx).await?) else {
+        return Ok(false);
+    };
+    let nonce = ctx.state.get_account(&sender).await?.map_or(0, |a| a.nonce);
+    Ok(tx.nonce > nonce)
+}
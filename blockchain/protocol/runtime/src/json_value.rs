@@ -0,0 +1,28 @@
+//! Serde adapter for JSON values in types that are also bincode encoded.
+//! Bincode can't decode a `serde_json::Value`, which needs
+//! `deserialize_any`, so binary formats carry it as JSON text while
+//! human-readable ones keep it inline.
+
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+pub(crate) fn serialize<S: Serializer>(
+    value: &serde_json::Value,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        return value.serialize(serializer);
+    }
+    serde_json::to_string(value)
+        .map_err(S::Error::custom)?
+        .serialize(serializer)
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<serde_json::Value, D::Error> {
+    if deserializer.is_human_readable() {
+        return serde_json::Value::deserialize(deserializer);
+    }
+    let text = String::deserialize(deserializer)?;
+    serde_json::from_str(&text).map_err(D::Error::custom)
+}
@@ -1,14 +1,79 @@
 use blake3;
 use ed25519_dalek::{Signature, SigningKey, Signer, Verifier, VerifyingKey};
 use serde::{Deserialize, Serialize};
+pub mod bls;
+mod clock;
+mod disputes;
 mod domains;
+mod encoding;
+mod events;
+mod evidence;
+mod fees;
+mod forced;
+mod fork;
+mod governance;
+mod inclusion;
+mod json_value;
+mod light_clients;
+mod liveness;
+mod multisig;
+mod preconf;
+mod schedule;
+mod sequencers;
+mod signing;
+mod upgrades;
 pub use domains::{
-    CrossDomainMessage, DomainCall, DomainExecutionReceipt, DomainRuntime, FraudProof,
+    call_leaf, call_proof, calls_root, domain_gas_price, message_timeout_blocks,
+    CrossDomainMessage, DomainCall,
+    DomainCheckpoint, DomainExecutionReceipt, DomainProof, DomainRuntime, DomainState,
+    BridgeBurn, BridgeMessage, DeliveryStatus, DomainToken, evm_chain_id, EvmAccountView, EvmAdapter,
+    EvmCallResult, InboxReceipt, MessageAck, ReplayStep, SentMessage, WasmLimits,
+    DEFAULT_INBOX_BATCH, DEFAULT_DOMAIN_MAX_GAS, DEFAULT_MESSAGE_TIMEOUT_BLOCKS, L1_BRIDGE_ID,
+    MAX_DOMAIN_SNAPSHOTS,
+};
+pub use clock::{BlockClock, ChainClock, ManualClock};
+pub use disputes::{batch_calls, DisputeParams, StepWitness};
+pub use encoding::{canonical_hash, decode, encode, ENCODING_VERSION};
+pub use events::Event;
+use events::{domain_event, vote_choice_str};
+pub use evidence::{vote_messages, vote_signing_bytes, DoubleSignEvidence};
+pub use forced::MAX_FORCED_INCLUSIONS;
+pub use fees::{estimate_gas, suggest_fees, FeeSuggestion, FeeTier, GasEstimate};
+pub use fork::{fork_genesis, ForkOptions, ForkPatch};
+pub use governance::{
+    BridgeLimitsUpdate, DomainAdmin, DomainApproval, GovernanceAction, ParamChange,
+    PrivacyFeeUpdate, PrivacyPoolRegistration, TreasurySpend, VerificationKeyUpdate,
+    BRIDGE_LIMITS_PROPOSAL, DOMAIN_ADMIN_PROPOSAL, DOMAIN_APPROVAL_PROPOSAL, FEE_SPLIT_PROPOSAL,
+    PARAM_CHANGE_PROPOSAL, PRIVACY_FEE_PROPOSAL, PRIVACY_POOL_PROPOSAL, REWARD_PARAMS_PROPOSAL,
+    PROTOCOL_UPGRADE_PROPOSAL, TREASURY_SPEND_PROPOSAL, VERIFICATION_KEY_PROPOSAL,
+};
+pub use inclusion::{include_tx, select_block_txs, BlockSelection};
+pub use light_clients::{
+    client_domain_id, header_signing_bytes, packet_leaf, verify_packet_proof, CrossDomainPacket,
+    HeaderSignature, ValidatorSet, MAX_LIGHT_CLIENT_HEADERS,
+};
+pub use liveness::{LivenessParams, LivenessReport};
+pub use preconf::{Preconfirmation, BATCH_RECORD_LIMIT};
+pub use sequencers::{failover_pick, rotation_seed, stake_weighted_pick, SequencerParams};
+pub use upgrades::{
+    legacy_signatures_accepted, protocol_version_at, UnsupportedProtocolVersion, PROTOCOL_VERSION,
+    TAGGED_SIGNATURES_VERSION,
+};
+pub use signing::{
+    accepted_messages, sign_in_domain, signing_message, verify_in_domain,
+    verify_in_domain_or_legacy, SigningDomain,
+};
+pub use state::{
+    DomainStatus, FeeSplit, LightClient, LightClientHeader, MultisigCall, ParamOverrides,
+    ProofMode, ProtocolUpgrade, RewardParams, SequencerBond, VerificationKeyRegistry, VestingSchedule,
 };
 use state::{
-    Account, ChainState, Delegation, FeePools, GovernanceParams, InMemoryStateStore, PrivacyPool,
-    Proposal, ProposalStatus, StateStore, Unbonding, Validator, ValidatorStatus, VoteChoice,
-    VoteRecord,
+    delegate_to, locked_balance, sections, slash_positions, undelegate_from, Account, Asset, ChainState, CommitmentTree, FeePools, GovernanceParams,
+    InMemoryStateStore, Multisig, MultisigProposal, PendingExit, PrivacyPool, Proposal,
+    ProposalStatus, RetentionParams, ScheduledCall, StakingParams, StateOverlay, StateStore, Unbonding, Validator,
+    ValidatorDescription, ValidatorStatus, VoteChoice, VoteRecord, DEFAULT_PRIVACY_POOL,
+    MAX_ASSET_DECIMALS, MAX_ASSET_SYMBOL_LEN, MAX_SCHEDULED_PER_ACCOUNT, MAX_SCHEDULE_DELAY_BLOCKS,
+    MAX_VESTING_SCHEDULES_PER_ACCOUNT, MIN_VESTING_AMOUNT,
 };
 use std::fs;
 use std::path::Path;
@@ -32,21 +97,76 @@ pub enum TxPayload {
     CrossDomainSend {
         from_domain: Uuid,
         to_domain: Uuid,
+        #[serde(with = "crate::json_value")]
         payload: serde_json::Value,
         fee: u128,
     },
-    CrossDomainRelay { message: CrossDomainMessage },
+    /// Delivers `message` to its destination's inbox. `proof` shows it is in
+    /// the outbox under the source domain's committed state root.
+    CrossDomainRelay {
+        message: CrossDomainMessage,
+        proof: DomainProof,
+    },
+    /// Refunds the fee of message `nonce` from `from_domain` to `to_domain`
+    /// to whoever paid it, once the message timed out undelivered.
+    CrossDomainRefund {
+        from_domain: Uuid,
+        to_domain: Uuid,
+        nonce: u64,
+    },
+    DomainInboxProcess {
+        domain_id: Uuid,
+        max_messages: Option<u32>,
+    },
+    /// Disputes the domain's open optimistic claim, bonding as much as its
+    /// sequencer did. `witness` is evidence for watchers; it isn't read.
     FraudChallenge {
         domain_id: Uuid,
         claimed_root: Hash,
+        #[serde(with = "crate::json_value")]
         witness: serde_json::Value,
     },
-    DomainCreate { domain_id: Uuid, params: serde_json::Value },
-    DomainConfigUpdate { domain_id: Uuid, params: serde_json::Value },
-    RollupBatchCommit { domain_id: Uuid, blob_id: String },
+    /// Registers a domain, locking the registration bond from the sender.
+    DomainCreate {
+        domain_id: Uuid,
+        #[serde(with = "crate::json_value")]
+        params: serde_json::Value,
+    },
+    DomainConfigUpdate {
+        domain_id: Uuid,
+        #[serde(with = "crate::json_value")]
+        params: serde_json::Value,
+    },
+    /// Stops an active domain from executing calls; owner only.
+    DomainPause { domain_id: Uuid },
+    DomainResume { domain_id: Uuid },
+    /// Closes a domain for good and returns its registration bond.
+    DomainRetire { domain_id: Uuid },
+    /// A sequencer batch and the roots its rollup proof attests to.
+    RollupBatchCommit {
+        domain_id: Uuid,
+        blob_id: String,
+        state_root: Hash,
+        da_root: Hash,
+        proof: ProofArtifact,
+    },
+    /// Escrows `amount` for the domain and mints it to the sender there.
     RollupBridgeDeposit { domain_id: Uuid, amount: u128 },
-    RollupBridgeWithdraw { domain_id: Uuid, amount: u128 },
-    GovernanceProposal { payload: serde_json::Value, kind: Option<String> },
+    /// Burns the sender's domain tokens so they can be withdrawn on L1.
+    RollupBridgeBurn { domain_id: Uuid, amount: u128 },
+    /// Pays burn `burn_nonce` out of the domain's escrow, with `proof` of
+    /// the burn under the domain's committed root.
+    RollupBridgeWithdraw {
+        domain_id: Uuid,
+        burn_nonce: u64,
+        amount: u128,
+        proof: DomainProof,
+    },
+    GovernanceProposal {
+        #[serde(with = "crate::json_value")]
+        payload: serde_json::Value,
+        kind: Option<String>,
+    },
     GovernanceVote { proposal_id: Uuid, support: VoteChoice },
     GovernanceBridgeApprove { proposal_id: Uuid },
     GovernanceExecute { proposal_id: Uuid },
@@ -55,7 +175,17 @@ pub enum TxPayload {
         penalty_bps: u16,
         reason: Option<String>,
     },
-    PrivacyDeposit { commitment: Hash, amount: u128 },
+    SubmitEvidence { evidence: DoubleSignEvidence },
+    PrivacyDeposit {
+        commitment: Hash,
+        amount: u128,
+        /// `state::DEFAULT_PRIVACY_POOL` when unset.
+        #[serde(default)]
+        pool: Option<String>,
+        /// The note sealed to its owner's viewing key, for wallet scanning.
+        #[serde(default)]
+        memo: Option<zk_program_privacy::EncryptedNote>,
+    },
     PrivacyWithdraw {
         nullifier: Hash,
         recipient: Address,
@@ -63,8 +193,158 @@ pub enum TxPayload {
         merkle_root: Hash,
         commitment: Hash,
         proof: ProofArtifact,
+        #[serde(default)]
+        pool: Option<String>,
+        /// Paid `relayer_fee` out of `amount` for submitting the withdrawal
+        /// on the recipient's behalf; both are bound by the proof.
+        #[serde(default)]
+        relayer: Option<Address>,
+        #[serde(default)]
+        relayer_fee: u128,
+    },
+    /// Proposes moving the chain to protocol `version` at `activation_height`;
+    /// voted on like any `protocol_upgrade` proposal.
+    SystemUpgrade {
+        name: String,
+        version: u32,
+        activation_height: u64,
+    },
+    RegisterBlsKey {
+        bls_pubkey: Vec<u8>,
+        proof_of_possession: Vec<u8>,
+    },
+    /// Withdraws the sender's accrued rewards from its delegation to `validator`.
+    ClaimRewards {
+        validator: Address,
+    },
+    /// Updates the sender's validator; fields left `None` are unchanged.
+    ValidatorEdit {
+        commission_rate: Option<u8>,
+        moniker: Option<String>,
+        website: Option<String>,
+        details: Option<String>,
+    },
+    /// Returns the sender's validator, jailed for downtime, to the active set
+    /// once its cooldown has passed.
+    Unjail,
+    /// Withdraws the sender's proposal while voting is open.
+    GovernanceCancel { proposal_id: Uuid },
+    /// Registers a new asset issued by the sender. Its id is derived from the
+    /// sender and the tx nonce (`state::asset_id`).
+    AssetCreate {
+        symbol: String,
+        decimals: u8,
+        max_supply: Option<u128>,
+    },
+    /// Issuer-only.
+    AssetMint {
+        asset_id: Uuid,
+        to: Address,
+        amount: u128,
+    },
+    AssetTransfer {
+        asset_id: Uuid,
+        to: Address,
+        amount: u128,
+    },
+    /// Registers a multisig at `state::multisig_address(sender, nonce)`.
+    MultisigCreate { signers: Vec<Address>, threshold: u16 },
+    /// Proposes a call from `multisig`, approved by the submitting signer.
+    MultisigSubmit { multisig: Address, call: MultisigCall },
+    MultisigApprove { multisig: Address, proposal_id: u64 },
+    /// Runs a call once `threshold` signers have approved it. Any signer may
+    /// execute; the executor pays the gas.
+    MultisigExecute { multisig: Address, proposal_id: u64 },
+    /// Runs `inner` as a tx from the sender at `execute_at_height`, paying
+    /// its gas then. Payloads carrying free-form JSON can't be scheduled.
+    Schedule {
+        execute_at_height: u64,
+        inner: Box<TxPayload>,
+    },
+    ScheduleCancel { schedule_id: u64 },
+    /// Moves `amount` to `beneficiary` under a vesting schedule starting at
+    /// the current height; it can't be spent until it vests.
+    VestingCreate {
+        beneficiary: Address,
+        amount: u128,
+        cliff_height: u64,
+        end_height: u64,
+    },
+    /// Exits a validity domain without its sequencer: pays out the sender's
+    /// `balance` as proven against the domain's last proven root.
+    ForcedWithdraw {
+        domain_id: Uuid,
+        balance: u128,
+        proof: DomainProof,
+    },
+    /// Commits an optimistic domain root after `steps` calls, committed to
+    /// in order by `calls_root`, and bonds it until its challenge window
+    /// closes.
+    RollupBatchClaim {
+        domain_id: Uuid,
+        blob_id: String,
+        state_root: Hash,
+        calls_root: Hash,
+        steps: u64,
+    },
+    /// The sequencer's root after the midpoint call of its disputed claim.
+    FraudBisect { domain_id: Uuid, midpoint_root: Hash },
+    /// The challenger's answer to the sequencer's midpoint root.
+    FraudRespond { domain_id: Uuid, agree: bool },
+    /// Settles a dispute narrowed to one call by re-executing it.
+    FraudDefend { domain_id: Uuid, witness: StepWitness },
+    /// Bonds `bond` to sequence `domain_id`, or adds to the sender's bond.
+    SequencerRegister { domain_id: Uuid, bond: u128 },
+    /// Leaves the domain's rotation; the bond is returned once the challenge
+    /// window has passed.
+    SequencerExit { domain_id: Uuid },
+    /// Challenges a pre-confirmation whose promised batches have all been
+    /// posted, bonded like a fraud challenge.
+    PreconfirmationChallenge {
+        preconfirmation: Preconfirmation,
+    },
+    /// Answers a pre-confirmation challenge with the blob of the batch at
+    /// `batch_height` that carries the tx.
+    PreconfirmationFulfill {
+        tx_hash: Hash,
+        batch_height: u64,
+        blob: Vec<u8>,
+    },
+    /// Queues a JSON-encoded, signed call on `domain_id` for the domain's
+    /// batches to carry; L1 applies it itself if none does in time.
+    ForceInclude { domain_id: Uuid, tx_bytes: Vec<u8> },
+    /// Shows that the batch at `batch_height` carries a force-included tx.
+    ForceIncludeProve {
+        domain_id: Uuid,
+        tx_hash: Hash,
+        batch_height: u64,
+        blob: Vec<u8>,
+    },
+    /// Starts following external chain `chain_id` from a trusted `header`,
+    /// which commits to `validators`.
+    LightClientCreate {
+        client_id: String,
+        chain_id: String,
+        header: LightClientHeader,
+        validators: ValidatorSet,
+    },
+    /// Advances a light client to `header`, signed by its current
+    /// validators; `next_validators` is the set the header rotates to, if
+    /// any.
+    LightClientUpdate {
+        client_id: String,
+        header: LightClientHeader,
+        signatures: Vec<HeaderSignature>,
+        next_validators: Option<ValidatorSet>,
+    },
+    /// Delivers a packet the external chain committed to under the client's
+    /// header at `height` to its destination's inbox.
+    LightClientPacket {
+        client_id: String,
+        height: u64,
+        packet: CrossDomainPacket,
+        proof: DomainProof,
     },
-    SystemUpgrade { module: String, version: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,15 +364,23 @@ pub struct Tx {
 pub struct BlockHeader {
     pub parent_hash: Hash,
     pub height: u64,
+    /// Milliseconds since the Unix epoch; the only time execution sees.
     pub timestamp: u64,
     pub proposer_id: Address,
     pub state_root: Hash,
     pub l1_tx_root: Hash,
     pub da_commitment: Option<BlockDACommitment>,
-    pub domain_roots: Vec<Hash>,
+    /// Domain roots the block changed, sorted by domain id.
+    pub domain_roots: Vec<DomainRootCommitment>,
     pub gas_used: u64,
     pub gas_limit: u64,
     pub base_fee: u128,
+    #[serde(default)]
+    pub snapshot_root: Option<Hash>,
+    /// Protocol version the block executes under; see `protocol_version_at`.
+    #[serde(default)]
+    pub protocol_version: u32,
+    #[serde(with = "crate::json_value")]
     pub consensus_metadata: serde_json::Value,
 }
 
@@ -105,6 +393,27 @@ pub struct BlockDACommitment {
     pub shard_size: u32,
 }
 
+/// A domain's root as a block left it, with the DA commitment of the batch
+/// that set it; zero for roots optimistic calls advanced directly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainRootCommitment {
+    pub domain_id: Uuid,
+    pub state_root: Hash,
+    pub da_root: Hash,
+    pub batch_height: u64,
+}
+
+impl DomainRootCommitment {
+    fn new(root: &state::DomainRoot) -> Self {
+        Self {
+            domain_id: root.domain_id,
+            state_root: root.state_root,
+            da_root: root.da_root,
+            batch_height: root.batch_height,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub header: BlockHeader,
@@ -112,45 +421,24 @@ pub struct Block {
     pub da_blobs: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct FeeSplit {
-    pub l1_gas_burn_pct: u8,
-    pub l1_gas_validators_pct: u8,
-    pub da_validators_pct: u8,
-    pub da_nodes_pct: u8,
-    pub da_treasury_pct: u8,
-    pub l2_sequencer_pct: u8,
-    pub l2_da_costs_pct: u8,
-    pub l2_l1_rent_pct: u8,
+fn default_unbonding_delay_blocks() -> u64 {
+    10
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RewardParams {
-    pub base_inflation_bps: u16,
-    pub max_inflation_bps: u16,
-    pub target_stake_bps: u16,
-    pub treasury_pct: u8,
-    pub proposer_bonus_pct: u8,
+fn default_slash_penalty_bps() -> u16 {
+    500
 }
 
-impl Default for RewardParams {
-    fn default() -> Self {
-        Self {
-            base_inflation_bps: 500,   // 5% when at target or above
-            max_inflation_bps: 1500,   // 15% when below target stake
-            target_stake_bps: 6_700,   // 67% staked target
-            treasury_pct: 10,
-            proposer_bonus_pct: 5,
-        }
-    }
+fn default_epoch_length_blocks() -> u64 {
+    100
 }
 
-fn default_unbonding_delay_blocks() -> u64 {
-    10
+fn default_exit_churn_bps() -> u16 {
+    1_000
 }
 
-fn default_slash_penalty_bps() -> u16 {
-    500
+fn default_max_commission_change_per_epoch() -> u8 {
+    1
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -158,6 +446,10 @@ pub struct GenesisValidator {
     pub pubkey: Vec<u8>,
     pub stake: u128,
     pub commission_rate: u8,
+    #[serde(default)]
+    pub bls_pubkey: Vec<u8>,
+    #[serde(default)]
+    pub bls_proof_of_possession: Vec<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -179,6 +471,26 @@ pub struct GenesisConfig {
     pub unbonding_delay_blocks: u64,
     #[serde(default = "default_slash_penalty_bps")]
     pub slash_penalty_bps: u16,
+    #[serde(default = "default_epoch_length_blocks")]
+    pub epoch_length_blocks: u64,
+    /// Share of bonded stake, in basis points, that may leave the validator
+    /// set per epoch.
+    #[serde(default = "default_exit_churn_bps")]
+    pub exit_churn_bps: u16,
+    /// Percentage points a validator's commission may move per epoch.
+    #[serde(default = "default_max_commission_change_per_epoch")]
+    pub max_commission_change_per_epoch: u8,
+    #[serde(default = "LivenessParams::default")]
+    pub liveness_params: LivenessParams,
+    /// Initial active-set rules; governance may change them later.
+    #[serde(default = "StakingParams::default")]
+    pub staking_params: StakingParams,
+    /// How long settled history is kept before it is pruned.
+    #[serde(default)]
+    pub retention: RetentionParams,
+    /// Lock part of an initial account's balance under a vesting schedule.
+    #[serde(default)]
+    pub vesting: Vec<VestingSchedule>,
 }
 
 #[derive(Clone)]
@@ -194,8 +506,14 @@ pub struct ExecutionContext<S: StateStore> {
     pub reward_params: RewardParams,
     pub unbonding_delay_blocks: u64,
     pub slash_penalty_bps: u16,
+    pub epoch_length_blocks: u64,
+    pub exit_churn_bps: u16,
+    pub max_commission_change_per_epoch: u8,
+    pub liveness_params: LivenessParams,
     pub zk: Option<Arc<dyn ZkBackend>>,
     pub domains: Arc<DomainRuntime>,
+    /// The only time source execution reads.
+    pub clock: Arc<dyn ChainClock>,
 }
 
 impl<S: StateStore> ExecutionContext<S> {
@@ -211,6 +529,10 @@ impl<S: StateStore> ExecutionContext<S> {
         reward_params: RewardParams,
         unbonding_delay_blocks: u64,
         slash_penalty_bps: u16,
+        epoch_length_blocks: u64,
+        exit_churn_bps: u16,
+        max_commission_change_per_epoch: u8,
+        liveness_params: LivenessParams,
     ) -> Self {
         Self {
             state,
@@ -224,8 +546,13 @@ impl<S: StateStore> ExecutionContext<S> {
             reward_params,
             unbonding_delay_blocks,
             slash_penalty_bps,
+            epoch_length_blocks,
+            exit_churn_bps,
+            max_commission_change_per_epoch,
+            liveness_params,
             zk: None,
             domains: Arc::new(DomainRuntime::new()),
+            clock: Arc::new(BlockClock::default()),
         }
     }
 
@@ -238,6 +565,48 @@ impl<S: StateStore> ExecutionContext<S> {
         self.domains = domains;
         self
     }
+
+    pub fn with_clock(mut self, clock: Arc<dyn ChainClock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// A context over `base` and a fork of domain state, for executing
+    /// without side effects on `self`.
+    pub(crate) async fn sandbox(
+        &self,
+        base: ChainState,
+    ) -> anyhow::Result<ExecutionContext<InMemoryStateStore>> {
+        let state = InMemoryStateStore::new();
+        state.put_chain_state(base).await?;
+        let mut sandbox = self.with_state(state);
+        sandbox.domains = Arc::new(self.domains.fork());
+        Ok(sandbox)
+    }
+
+    /// The same context over another store, sharing domain state.
+    pub fn with_state<T: StateStore>(&self, state: T) -> ExecutionContext<T> {
+        ExecutionContext {
+            state,
+            fee_split: self.fee_split.clone(),
+            chain_id: self.chain_id.clone(),
+            base_fee: self.base_fee,
+            max_gas_per_block: self.max_gas_per_block,
+            block_time_ms: self.block_time_ms,
+            da_sample_count: self.da_sample_count,
+            slashing_double_sign: self.slashing_double_sign,
+            reward_params: self.reward_params.clone(),
+            unbonding_delay_blocks: self.unbonding_delay_blocks,
+            slash_penalty_bps: self.slash_penalty_bps,
+            epoch_length_blocks: self.epoch_length_blocks,
+            exit_churn_bps: self.exit_churn_bps,
+            max_commission_change_per_epoch: self.max_commission_change_per_epoch,
+            liveness_params: self.liveness_params.clone(),
+            zk: self.zk.clone(),
+            domains: self.domains.clone(),
+            clock: self.clock.clone(),
+        }
+    }
 }
 
 pub async fn apply_tx<S: StateStore>(
@@ -245,10 +614,21 @@ pub async fn apply_tx<S: StateStore>(
     tx: &Tx,
     current_height: u64,
 ) -> anyhow::Result<ExecutionOutcome> {
-    let sender = verify_tx_signature(tx)?;
+    let sender = verify_tx_signature(tx, accepts_legacy_signatures(ctx).await?)?;
+    execute_tx(ctx, tx, sender, current_height).await
+}
+
+/// `apply_tx` for an already authenticated `sender`.
+async fn execute_tx<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    tx: &Tx,
+    sender: Address,
+    current_height: u64,
+) -> anyhow::Result<ExecutionOutcome> {
     if tx.chain_id != ctx.chain_id {
         anyhow::bail!("invalid chain id");
     }
+    upgrades::ensure_payload_enabled(&tx.payload, ctx.state.get_protocol_version().await?)?;
 
     let mut sender_account = ctx
         .state
@@ -266,11 +646,15 @@ pub async fn apply_tx<S: StateStore>(
         .checked_mul(gas_price)
         .ok_or_else(|| anyhow::anyhow!("gas fee overflow"))?;
 
-    let mut chain = ctx.state.get_chain_state().await?;
+    let locked = locked_balance(&ctx.state.get_vesting(&sender).await?, current_height);
+    // Unvested funds can't pay for gas either, whatever the payload.
+    if locked > 0 {
+        ensure_funds(&sender_account, locked, 0, gas_fee)?;
+    }
 
     match &tx.payload {
         TxPayload::Transfer { to, amount } => {
-            ensure_funds(&sender_account, *amount, gas_fee)?;
+            ensure_funds(&sender_account, locked, *amount, gas_fee)?;
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(*amount + gas_fee)
@@ -284,188 +668,403 @@ pub async fn apply_tx<S: StateStore>(
                 .checked_add(*amount)
                 .ok_or_else(|| anyhow::anyhow!("overflow"))?;
             ctx.state.put_account(to_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
 
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["transfer".into()],
+                vec![Event::new("transfer")
+                    .with_hex("sender", sender)
+                    .with_hex("recipient", to)
+                    .with("amount", amount)],
             ))
         }
         TxPayload::Stake { amount } => {
-            ensure_funds(&sender_account, *amount, gas_fee)?;
+            ensure_funds(&sender_account, locked, *amount, gas_fee)?;
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(*amount + gas_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            // ensure chain state fetched early stays accurate
-            if let Some(v) = chain.validators.values_mut().find(|v| v.owner == sender) {
-                v.stake = v
-                    .stake
-                    .checked_add(*amount)
-                    .ok_or_else(|| anyhow::anyhow!("stake overflow"))?;
-                v.status = ValidatorStatus::Active;
-            } else {
-                let id = Uuid::new_v4();
-                let validator = Validator {
+            let validator = match validator_of(ctx, &sender).await? {
+                Some(mut v) => {
+                    v.stake = v
+                        .stake
+                        .checked_add(*amount)
+                        .ok_or_else(|| anyhow::anyhow!("stake overflow"))?;
+                    // Returning validators wait for the next epoch's active
+                    // set; a jailed one rejoins only through `Unjail`.
+                    if matches!(v.status, ValidatorStatus::Exited) {
+                        v.status = ValidatorStatus::Candidate;
+                    }
+                    v
+                }
+                None => Validator {
                     owner: sender,
-                    id,
+                    id: validator_id_from_pubkey(&tx.public_key),
                     pubkey: tx.signature.clone(),
                     stake: *amount,
-                    status: ValidatorStatus::Active,
+                    status: ValidatorStatus::Candidate,
                     commission_rate: 0,
-                };
-                chain.validators.insert(id, validator);
-            }
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
-            Ok(ExecutionOutcome::success(gas_used, vec!["stake".into()]))
+                    bls_pubkey: Vec::new(),
+                    description: ValidatorDescription::default(),
+                    commission_changed_epoch: None,
+                },
+            };
+            let validator_id = validator.id;
+            ctx.state.put_validator(validator).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("stake")
+                    .with_hex("sender", sender)
+                    .with("validator_id", validator_id)
+                    .with("amount", amount)],
+            ))
         }
         TxPayload::Unstake { amount } => {
             if sender_account.balance_x < gas_fee {
                 anyhow::bail!("insufficient funds for gas");
             }
-            let Some(v) = chain.validators.values_mut().find(|v| v.owner == sender) else {
+            let Some(v) = validator_of(ctx, &sender).await? else {
                 anyhow::bail!("no validator for sender");
             };
-            if v.stake < *amount {
+            let validator_id = v.id;
+            let mut exit_queue = ctx.state.get_section::<sections::ExitQueue>().await?;
+            if v.stake
+                .saturating_sub(queued_exits(&exit_queue, &validator_id))
+                < *amount
+            {
                 anyhow::bail!("insufficient staked amount");
             }
-            v.stake = v.stake.saturating_sub(*amount);
-            if v.stake == 0 {
-                v.status = ValidatorStatus::Exited;
-            }
-            let release_height = current_height.saturating_add(ctx.unbonding_delay_blocks);
-            chain.pending_unbonds.push(Unbonding {
+            exit_queue.push(PendingExit {
                 owner: sender,
-                validator_id: Some(v.id),
+                validator_id,
                 amount: *amount,
-                release_height,
+                requested_height: current_height,
             });
+            ctx.state
+                .put_section::<sections::ExitQueue>(exit_queue)
+                .await?;
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(gas_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
-            Ok(ExecutionOutcome::success(gas_used, vec!["unstake_init".into()]))
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("unstake_init")
+                    .with_hex("sender", sender)
+                    .with("validator_id", validator_id)
+                    .with("amount", amount)],
+            ))
         }
         TxPayload::Delegate { validator, amount } => {
-            ensure_funds(&sender_account, *amount, gas_fee)?;
-            let Some(v) = chain.validators.values_mut().find(|v| v.owner == *validator) else {
+            ensure_funds(&sender_account, locked, *amount, gas_fee)?;
+            let Some(mut v) = validator_of(ctx, validator).await? else {
                 anyhow::bail!("validator not found");
             };
             v.stake = v
                 .stake
                 .checked_add(*amount)
                 .ok_or_else(|| anyhow::anyhow!("stake overflow"))?;
-            chain.delegations.push(Delegation {
-                delegator: sender,
-                validator_id: v.id,
-                stake: *amount,
-            });
+            let validator_id = v.id;
+            let key = (sender, validator_id);
+            let mut rewards = ctx
+                .state
+                .get_entry::<sections::ValidatorRewards>(&validator_id)
+                .await?
+                .unwrap_or_default();
+            let position = ctx.state.get_entry::<sections::Delegations>(&key).await?;
+            let position = delegate_to(&mut rewards, position, sender, validator_id, *amount)?;
+            ctx.state.put_validator(v).await?;
+            ctx.state
+                .put_entry::<sections::ValidatorRewards>(validator_id, rewards)
+                .await?;
+            ctx.state
+                .put_entry::<sections::Delegations>(key, position)
+                .await?;
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(*amount + gas_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["delegate".into()],
+                vec![Event::new("delegate")
+                    .with_hex("sender", sender)
+                    .with_hex("validator", validator)
+                    .with("validator_id", validator_id)
+                    .with("amount", amount)],
             ))
         }
         TxPayload::Undelegate { validator, amount } => {
-            let Some(v) = chain.validators.values_mut().find(|v| v.owner == *validator) else {
+            let Some(validator_id) = validator_of(ctx, validator).await?.map(|v| v.id) else {
                 anyhow::bail!("validator not found");
             };
-            let mut found = false;
-            for delegation in chain.delegations.iter_mut() {
-                if delegation.delegator == sender && delegation.validator_id == v.id {
-                    if delegation.stake < *amount {
-                        anyhow::bail!("undelegate amount exceeds delegation");
-                    }
-                    delegation.stake -= *amount;
-                    v.stake = v.stake.saturating_sub(*amount);
-                    found = true;
-                    break;
-                }
-            }
-            if !found {
+            let key = (sender, validator_id);
+            let Some(mut position) = ctx.state.get_entry::<sections::Delegations>(&key).await?
+            else {
                 anyhow::bail!("delegation not found");
+            };
+            let mut rewards = ctx
+                .state
+                .get_entry::<sections::ValidatorRewards>(&validator_id)
+                .await?;
+            undelegate_from(rewards.as_mut(), &mut position, *amount)?;
+            // Rewards are paid out with the undelegation so emptied
+            // positions do not linger.
+            let rewards_paid =
+                position.withdraw_rewards(rewards.as_ref().map_or(0, |r| r.reward_index));
+            if let Some(rewards) = rewards {
+                ctx.state
+                    .put_entry::<sections::ValidatorRewards>(validator_id, rewards)
+                    .await?;
             }
-            chain.delegations.retain(|d| d.stake > 0);
-            let release_height = current_height.saturating_add(ctx.unbonding_delay_blocks);
-            chain.pending_unbonds.push(Unbonding {
+            if position.stake == 0 {
+                ctx.state
+                    .remove_entry::<sections::Delegations>(&key)
+                    .await?;
+            } else {
+                ctx.state
+                    .put_entry::<sections::Delegations>(key, position)
+                    .await?;
+            }
+            let mut exit_queue = ctx.state.get_section::<sections::ExitQueue>().await?;
+            exit_queue.push(PendingExit {
                 owner: sender,
-                validator_id: Some(v.id),
+                validator_id,
                 amount: *amount,
-                release_height,
+                requested_height: current_height,
             });
+            ctx.state
+                .put_section::<sections::ExitQueue>(exit_queue)
+                .await?;
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(gas_fee)
-                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?
+                .checked_add(rewards_paid)
+                .ok_or_else(|| anyhow::anyhow!("balance overflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("undelegate_init")
+                    .with_hex("sender", sender)
+                    .with_hex("validator", validator)
+                    .with("validator_id", validator_id)
+                    .with("amount", amount)
+                    .with("rewards", rewards_paid)],
+            ))
+        }
+        TxPayload::ClaimRewards { validator } => {
+            let Some(validator_id) = validator_of(ctx, validator).await?.map(|v| v.id) else {
+                anyhow::bail!("validator not found");
+            };
+            let key = (sender, validator_id);
+            let Some(mut position) = ctx.state.get_entry::<sections::Delegations>(&key).await?
+            else {
+                anyhow::bail!("delegation not found");
+            };
+            let index = ctx
+                .state
+                .get_entry::<sections::ValidatorRewards>(&validator_id)
+                .await?
+                .map_or(0, |r| r.reward_index);
+            let rewards = position.withdraw_rewards(index);
+            if position.stake == 0 {
+                ctx.state
+                    .remove_entry::<sections::Delegations>(&key)
+                    .await?;
+            } else {
+                ctx.state
+                    .put_entry::<sections::Delegations>(key, position)
+                    .await?;
+            }
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("insufficient funds for gas"))?
+                .checked_add(rewards)
+                .ok_or_else(|| anyhow::anyhow!("balance overflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("claim_rewards")
+                    .with_hex("sender", sender)
+                    .with_hex("validator", validator)
+                    .with("validator_id", validator_id)
+                    .with("amount", rewards)],
+            ))
+        }
+        TxPayload::ValidatorEdit {
+            commission_rate,
+            moniker,
+            website,
+            details,
+        } => {
+            let epoch = current_height / ctx.epoch_length_blocks.max(1);
+            let max_change = ctx
+                .state
+                .get_param_overrides()
+                .await?
+                .max_commission_change_per_epoch
+                .unwrap_or(ctx.max_commission_change_per_epoch);
+            let Some(mut v) = validator_of(ctx, &sender).await? else {
+                anyhow::bail!("no validator for sender");
+            };
+            if let Some(rate) = *commission_rate {
+                if rate > 100 {
+                    anyhow::bail!("commission rate above 100%");
+                }
+                if rate != v.commission_rate {
+                    if v.commission_changed_epoch == Some(epoch) {
+                        anyhow::bail!("commission already changed this epoch");
+                    }
+                    if rate.abs_diff(v.commission_rate) > max_change {
+                        anyhow::bail!(
+                            "commission may change by at most {max_change} points per epoch"
+                        );
+                    }
+                    v.commission_rate = rate;
+                    v.commission_changed_epoch = Some(epoch);
+                }
+            }
+            let fields = [
+                (&mut v.description.moniker, moniker, MAX_MONIKER_LEN, "moniker"),
+                (&mut v.description.website, website, MAX_WEBSITE_LEN, "website"),
+                (&mut v.description.details, details, MAX_DETAILS_LEN, "details"),
+            ];
+            for (field, value, max_len, name) in fields {
+                if let Some(value) = value {
+                    if value.len() > max_len {
+                        anyhow::bail!("{name} longer than {max_len} bytes");
+                    }
+                    *field = value.clone();
+                }
+            }
+            let validator_id = v.id;
+            let commission = v.commission_rate;
+            ctx.state.put_validator(v).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("insufficient funds for gas"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("validator_edit")
+                    .with_hex("sender", sender)
+                    .with("validator_id", validator_id)
+                    .with("commission_rate", commission)],
+            ))
+        }
+        TxPayload::Unjail => {
+            let Some(mut v) = validator_of(ctx, &sender).await? else {
+                anyhow::bail!("no validator for sender");
+            };
+            if !matches!(v.status, ValidatorStatus::Jailed) {
+                anyhow::bail!("validator is not jailed");
+            }
+            if v.stake == 0 {
+                anyhow::bail!("validator has no stake");
+            }
+            let validator_id = v.id;
+            if let Some(mut record) = ctx
+                .state
+                .get_entry::<sections::Liveness>(&validator_id)
+                .await?
+            {
+                if let Some(until) = record.jailed_until.filter(|h| current_height < *h) {
+                    anyhow::bail!("validator is jailed until height {until}");
+                }
+                record.jailed_until = None;
+                ctx.state
+                    .put_entry::<sections::Liveness>(validator_id, record)
+                    .await?;
+            }
+            v.status = ValidatorStatus::Candidate;
+            ctx.state.put_validator(v).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("insufficient funds for gas"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["undelegate_init".into()],
+                vec![Event::new("unjail")
+                    .with_hex("sender", sender)
+                    .with("validator_id", validator_id)],
             ))
         }
         TxPayload::DomainExecute(call) => {
-            let entry = chain
-                .domains
-                .get(&call.domain_id)
-                .cloned()
-                .ok_or_else(|| anyhow::anyhow!("domain not registered"))?;
+            let entry = domain_entry(ctx, &call.domain_id).await?;
+            ensure_domain_active(&entry)?;
             if !ctx.domains.has_domain(&call.domain_id) {
                 ctx.domains.register(&entry)?;
             }
-            if sender_account.balance_x < gas_fee {
-                anyhow::bail!("insufficient funds for gas");
-            }
+            // Domain gas is paid up front for the whole `max_gas` and what
+            // the call does not use is refunded.
+            let max_gas = call.max_gas.unwrap_or(DEFAULT_DOMAIN_MAX_GAS);
+            let domain_gas_price = domain_gas_price(&entry.risk_params)?;
+            let prepaid = (max_gas as u128)
+                .checked_mul(domain_gas_price)
+                .ok_or_else(|| anyhow::anyhow!("domain gas fee overflow"))?;
+            ensure_funds(&sender_account, locked, prepaid, gas_fee)?;
+            // Delivered messages run ahead of the call, so it sees them.
+            let inbox = ctx
+                .domains
+                .process_inbox(&call.domain_id, ctx, current_height, DEFAULT_INBOX_BATCH as usize)
+                .await?;
             let receipt = ctx
                 .domains
-                .execute(call, ctx, current_height)
+                .execute(call, sender, ctx, current_height)
                 .await
                 .map_err(|e| anyhow::anyhow!("domain execution failed: {e}"))?;
+            let domain_gas_used = receipt.gas_used.min(max_gas);
+            let domain_fee = domain_gas_used as u128 * domain_gas_price;
 
             sender_account.balance_x = sender_account
                 .balance_x
-                .checked_sub(gas_fee)
+                .checked_sub(gas_fee + domain_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-
-            chain.domain_roots.insert(
-                receipt.domain_id,
-                state::DomainRoot {
-                    domain_id: receipt.domain_id,
-                    state_root: receipt.state_root,
-                    da_root: [0u8; 32],
-                    last_verified_epoch: current_height,
-                    proof_meta: serde_json::json!({ "trace": receipt.trace }),
-                },
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            route_domain_fee(ctx, call.domain_id, domain_fee).await?;
+
+            track_domain_root(ctx, entry.proof_mode, &receipt, current_height).await?;
+            let mut events: Vec<Event> = inbox
+                .iter()
+                .map(|r| inbox_event(&call.domain_id, r))
+                .collect();
+            events.extend(
+                receipt
+                    .events
+                    .iter()
+                    .map(|data| domain_event(receipt.domain_id, data)),
+            );
+            events.push(
+                Event::new("domain_execute")
+                    .with_hex("sender", sender)
+                    .with("domain_id", receipt.domain_id)
+                    .with_hex("state_root", receipt.state_root)
+                    .with("domain_gas_used", domain_gas_used)
+                    .with("domain_fee", domain_fee)
+                    .with("refund", prepaid - domain_fee),
             );
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
-            let mut events = receipt.events.clone();
-            events.push("domain_execute".into());
             Ok(ExecutionOutcome::success(receipt.gas_used, events))
         }
         TxPayload::CrossDomainSend {
@@ -477,38 +1076,84 @@ pub async fn apply_tx<S: StateStore>(
             if sender_account.balance_x < gas_fee.saturating_add(*fee) {
                 anyhow::bail!("insufficient funds for gas + fee");
             }
-            let _ = chain
-                .domains
-                .get(from_domain)
+            ensure_funds(&sender_account, locked, *fee, gas_fee)?;
+            let from_entry = ctx
+                .state
+                .get_entry::<sections::Domains>(from_domain)
+                .await?
                 .ok_or_else(|| anyhow::anyhow!("from_domain not registered"))?;
-            let _ = chain
-                .domains
-                .get(to_domain)
+            let to_entry = ctx
+                .state
+                .get_entry::<sections::Domains>(to_domain)
+                .await?
                 .ok_or_else(|| anyhow::anyhow!("to_domain not registered"))?;
-            let nonce = ctx.domains.next_out_nonce(from_domain);
+            let timeout_blocks = message_timeout_blocks(&to_entry.risk_params)?;
+            let nonce = ctx.domains.next_out_nonce(from_domain, to_domain);
+            let timeout_height = current_height.saturating_add(timeout_blocks);
             let msg = CrossDomainMessage {
                 from: *from_domain,
                 to: *to_domain,
                 nonce,
                 fee: *fee,
                 payload: payload.clone(),
+                timeout_height,
+                ack: None,
             };
-            ctx.domains.push_outbox(msg);
+            ctx.domains.push_outbox(msg, sender);
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(gas_fee.saturating_add(*fee))
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            // Relays prove messages against the committed root, so an
+            // optimistic source commits its outbox right away.
+            if from_entry.proof_mode == ProofMode::Optimistic {
+                commit_executed_root(
+                    ctx,
+                    from_domain,
+                    ctx.domains.state_root(from_domain),
+                    serde_json::json!({ "outbox_nonce": nonce }),
+                    current_height,
+                )
+                .await?;
+            }
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["cross_domain_send".into()],
+                vec![Event::new("cross_domain_send")
+                    .with_hex("sender", sender)
+                    .with("from_domain", from_domain)
+                    .with("to_domain", to_domain)
+                    .with("nonce", nonce)
+                    .with("fee", fee)
+                    .with("timeout_height", timeout_height)],
             ))
         }
-        TxPayload::CrossDomainRelay { message } => {
+        TxPayload::CrossDomainRelay { message, proof } => {
+            if ctx
+                .state
+                .get_entry::<sections::Domains>(&message.to)
+                .await?
+                .is_none()
+            {
+                anyhow::bail!("to_domain not registered");
+            }
+            let committed = ctx
+                .state
+                .get_entry::<sections::DomainRoots>(&message.from)
+                .await?
+                .map(|root| root.state_root)
+                .ok_or_else(|| anyhow::anyhow!("source domain has no committed root"))?;
+            if DomainState::message_leaf(message) != Some(proof.leaf) || !proof.verify(&committed)
+            {
+                anyhow::bail!("message is not in the source domain's committed outbox");
+            }
+            let relayed = Event::new("cross_domain_relay")
+                .with_hex("sender", sender)
+                .with("from_domain", message.from)
+                .with("to_domain", message.to)
+                .with("nonce", message.nonce);
             ctx.domains.relay_message(message.clone())?;
             sender_account.balance_x = sender_account
                 .balance_x
@@ -516,52 +1161,124 @@ pub async fn apply_tx<S: StateStore>(
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["cross_domain_relay".into()],
+                vec![relayed],
             ))
         }
-        TxPayload::FraudChallenge {
-            domain_id,
-            claimed_root,
-            witness,
+        TxPayload::CrossDomainRefund {
+            from_domain,
+            to_domain,
+            nonce,
         } => {
-            let proof = FraudProof {
-                domain_id: *domain_id,
-                claimed_root: *claimed_root,
-                witness: witness.clone(),
-            };
-            ctx.domains
-                .submit_fraud_proof(&proof)
-                .map_err(|e| anyhow::anyhow!("fraud proof rejected: {e}"))?;
-            chain.domain_roots.insert(
-                *domain_id,
-                state::DomainRoot {
-                    domain_id: *domain_id,
-                    state_root: *claimed_root,
-                    da_root: [0u8; 32],
-                    last_verified_epoch: current_height,
-                    proof_meta: serde_json::json!({ "fraud_proof": witness.clone() }),
-                },
-            );
+            let sent = ctx
+                .domains
+                .take_timed_out(from_domain, to_domain, *nonce, current_height)?;
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(gas_fee)
-                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+                .ok_or_else(|| anyhow::anyhow!("insufficient funds for gas"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            // Anyone may submit the refund; it goes to whoever paid the fee.
+            let mut payer = ctx
+                .state
+                .get_account(&sent.sender)
+                .await?
+                .unwrap_or(default_account(sent.sender));
+            payer.balance_x = payer
+                .balance_x
+                .checked_add(sent.fee)
+                .ok_or_else(|| anyhow::anyhow!("overflow"))?;
+            ctx.state.put_account(payer).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["fraud_challenge".into()],
+                vec![Event::new("cross_domain_refund")
+                    .with_hex("sender", sender)
+                    .with("from_domain", from_domain)
+                    .with("to_domain", to_domain)
+                    .with("nonce", nonce)
+                    .with_hex("refunded_to", sent.sender)
+                    .with("fee", sent.fee)],
             ))
         }
+        TxPayload::DomainInboxProcess {
+            domain_id,
+            max_messages,
+        } => {
+            let entry = domain_entry(ctx, domain_id).await?;
+            if !ctx.domains.has_domain(domain_id) {
+                ctx.domains.register(&entry)?;
+            }
+            if sender_account.balance_x < gas_fee {
+                anyhow::bail!("insufficient funds for gas");
+            }
+            let max = max_messages.unwrap_or(DEFAULT_INBOX_BATCH).min(DEFAULT_INBOX_BATCH);
+            let receipts = ctx
+                .domains
+                .process_inbox(domain_id, ctx, current_height, max as usize)
+                .await?;
+
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+
+            if entry.proof_mode == ProofMode::Optimistic {
+                commit_executed_root(
+                    ctx,
+                    domain_id,
+                    ctx.domains.state_root(domain_id),
+                    serde_json::json!({ "inbox_receipts": receipts }),
+                    current_height,
+                )
+                .await?;
+            }
+            let mut events: Vec<Event> =
+                receipts.iter().map(|r| inbox_event(domain_id, r)).collect();
+            events.push(
+                Event::new("domain_inbox_process")
+                    .with_hex("sender", sender)
+                    .with("domain_id", domain_id)
+                    .with("processed", receipts.len()),
+            );
+            Ok(ExecutionOutcome::success(gas_used, events))
+        }
+        TxPayload::FraudChallenge {
+            domain_id,
+            claimed_root,
+            ..
+        } => {
+            if proof_mode(ctx, domain_id).await? == ProofMode::Validity {
+                anyhow::bail!("validity domains only advance with a validity proof");
+            }
+            let bond = disputes::challenge_bond(ctx, domain_id).await?;
+            ensure_funds(&sender_account, locked, bond, gas_fee)?;
+            let event =
+                disputes::open(ctx, domain_id, sender, *claimed_root, current_height).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(bond + gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
         TxPayload::DomainCreate { domain_id, params } => {
+            if ctx
+                .state
+                .get_entry::<sections::Domains>(domain_id)
+                .await?
+                .is_some()
+            {
+                anyhow::bail!("domain already registered");
+            }
             validate_domain_risk(params)?;
             let kind = params
                 .get("kind")
@@ -574,34 +1291,74 @@ pub async fn apply_tx<S: StateStore>(
                     _ => state::DomainType::Custom,
                 })
                 .unwrap_or(state::DomainType::Custom);
+            let proof_mode = match params.get("proof_mode").and_then(|v| v.as_str()) {
+                None | Some("optimistic") => ProofMode::Optimistic,
+                Some("validity") => ProofMode::Validity,
+                Some(other) => anyhow::bail!("unknown proof_mode {other}"),
+            };
+            let security_model = match params.get("security_model").and_then(|v| v.as_str()) {
+                None | Some("shared") => state::SecurityModel::SharedSecurity,
+                Some("own") => state::SecurityModel::OwnSecurity,
+                Some(other) => anyhow::bail!("unknown security_model {other}"),
+            };
+            let governance = ctx.state.get_governance_params().await?;
+            // Shared security puts the validator set behind the domain, so
+            // governance may want a say first.
+            let status = if security_model == state::SecurityModel::SharedSecurity
+                && governance.shared_security_approval
+            {
+                DomainStatus::Pending
+            } else {
+                DomainStatus::Active
+            };
+            let bond = governance.domain_registration_bond;
+            ensure_funds(&sender_account, locked, bond, gas_fee)?;
             let entry = state::DomainEntry {
                 domain_id: *domain_id,
                 kind,
-                security_model: state::SecurityModel::SharedSecurity,
+                security_model,
                 sequencer_binding: None,
                 bridge_contracts: vec![],
                 risk_params: params.clone(),
+                proof_mode,
+                status,
+                owner: sender,
+                bond,
             };
-            chain.domains.insert(*domain_id, entry.clone());
             let _ = ctx.domains.register(&entry);
+            ctx.state
+                .put_entry::<sections::Domains>(*domain_id, entry)
+                .await?;
             sender_account.balance_x = sender_account
                 .balance_x
-                .checked_sub(gas_fee)
+                .checked_sub(bond + gas_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["domain_create".into()],
+                vec![Event::new("domain_create")
+                    .with_hex("sender", sender)
+                    .with("domain_id", domain_id)
+                    .with("bond", bond)
+                    .with("status", format!("{status:?}").to_lowercase())],
             ))
         }
         TxPayload::DomainConfigUpdate { domain_id, params } => {
             validate_domain_risk(params)?;
-            if let Some(entry) = chain.domains.get_mut(domain_id) {
+            if let Some(mut entry) = ctx.state.get_entry::<sections::Domains>(domain_id).await? {
+                if entry.owner != sender {
+                    anyhow::bail!("only the domain owner may update it");
+                }
                 entry.risk_params = params.clone();
+                // Adapters are built from the entry, so rebuild it for new limits.
+                if ctx.domains.has_domain(domain_id) {
+                    ctx.domains.register(&entry)?;
+                }
+                ctx.state
+                    .put_entry::<sections::Domains>(*domain_id, entry)
+                    .await?;
             }
             sender_account.balance_x = sender_account
                 .balance_x
@@ -609,65 +1366,244 @@ pub async fn apply_tx<S: StateStore>(
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["domain_config_update".into()],
+                vec![Event::new("domain_config_update")
+                    .with_hex("sender", sender)
+                    .with("domain_id", domain_id)],
             ))
         }
-        TxPayload::RollupBatchCommit { domain_id, blob_id } => {
-            chain.da_commitments.push(state::DACommitment {
-                block_height: 0,
-                da_root: [0u8; 32],
+        TxPayload::DomainPause { domain_id }
+        | TxPayload::DomainResume { domain_id }
+        | TxPayload::DomainRetire { domain_id } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let mut entry = domain_entry(ctx, domain_id).await?;
+            if entry.owner != sender {
+                anyhow::bail!("only the domain owner may change its status");
+            }
+            let (status, kind) = match (&tx.payload, entry.status) {
+                (TxPayload::DomainPause { .. }, DomainStatus::Active) => {
+                    (DomainStatus::Paused, "domain_pause")
+                }
+                (TxPayload::DomainResume { .. }, DomainStatus::Paused) => {
+                    (DomainStatus::Active, "domain_resume")
+                }
+                (
+                    TxPayload::DomainRetire { .. },
+                    DomainStatus::Pending | DomainStatus::Active | DomainStatus::Paused,
+                ) => (DomainStatus::Retired, "domain_retire"),
+                (_, current) => anyhow::bail!("domain is {current:?}"),
+            };
+            entry.status = status;
+            let refund = if status == DomainStatus::Retired {
+                std::mem::take(&mut entry.bond)
+            } else {
+                0
+            };
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_add(refund)
+                .and_then(|b| b.checked_sub(gas_fee))
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state
+                .put_entry::<sections::Domains>(*domain_id, entry)
+                .await?;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new(kind)
+                    .with_hex("sender", sender)
+                    .with("domain_id", domain_id)
+                    .with("refund", refund)],
+            ))
+        }
+        TxPayload::RollupBatchCommit {
+            domain_id,
+            blob_id,
+            state_root,
+            da_root,
+            proof,
+        } => {
+            domain_entry(ctx, domain_id).await?;
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let mut events =
+                sequencers::record_batch(ctx, domain_id, sender, current_height).await?;
+            let keys = ctx
+                .state
+                .get_section::<sections::VerificationKeys>()
+                .await?;
+            let batch_commitment =
+                verify_rollup_batch(ctx, &keys, *domain_id, *state_root, *da_root, proof).await?;
+            let batch_height = batch_height(ctx, domain_id).await? + 1;
+            preconf::record_batch(
+                ctx,
+                domain_id,
+                state::BatchRecord {
+                    batch_height,
+                    block_height: current_height,
+                    commitment: batch_commitment,
+                    optimistic: false,
+                },
+            )
+            .await?;
+            let mut da_commitments = ctx.state.get_section::<sections::DaCommitments>().await?;
+            da_commitments.push(state::DACommitment {
+                block_height: current_height,
+                da_root: *da_root,
                 blob_ids: vec![blob_id.clone()],
             });
-            chain.domain_roots.insert(
-                *domain_id,
-                state::DomainRoot {
-                    domain_id: *domain_id,
-                    state_root: [0u8; 32],
-                    da_root: [0u8; 32],
-                    last_verified_epoch: 0,
-                    proof_meta: serde_json::json!({"blob": blob_id}),
-                },
-            );
+            ctx.state
+                .put_section::<sections::DaCommitments>(da_commitments)
+                .await?;
+            ctx.state
+                .put_entry::<sections::DomainRoots>(
+                    *domain_id,
+                    state::DomainRoot {
+                        domain_id: *domain_id,
+                        state_root: *state_root,
+                        da_root: *da_root,
+                        last_verified_epoch: current_height,
+                        proof_meta: serde_json::json!({
+                            "blob": blob_id,
+                            "batch_commitment": hex::encode(batch_commitment),
+                        }),
+                        batch_height,
+                    },
+                )
+                .await?;
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(gas_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            events.push(
+                Event::new("rollup_batch_commit")
+                    .with_hex("sender", sender)
+                    .with("domain_id", domain_id)
+                    .with("blob_id", blob_id)
+                    .with_hex("state_root", state_root)
+                    .with("batch_height", batch_height),
+            );
+            Ok(ExecutionOutcome::success(gas_used, events))
+        }
+        TxPayload::RollupBridgeDeposit { domain_id, amount } => {
+            ensure_positive(*amount)?;
+            ensure_domain_active(&domain_entry(ctx, domain_id).await?)?;
+            ensure_bridge_open(ctx, domain_id).await?;
+            ensure_funds(&sender_account, locked, *amount, gas_fee)?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(*amount + gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            let mint = ctx.domains.bridge_deposit(domain_id, sender, *amount)?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            let mut escrow = ctx
+                .state
+                .get_entry::<sections::BridgeEscrows>(domain_id)
+                .await?
+                .unwrap_or_default();
+            escrow.balance = escrow.balance.saturating_add(*amount);
+            ctx.state
+                .put_entry::<sections::BridgeEscrows>(*domain_id, escrow)
+                .await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["rollup_batch_commit".into()],
+                vec![
+                    Event::new("bridge_deposit")
+                        .with_hex("sender", sender)
+                        .with("domain_id", domain_id)
+                        .with("amount", amount),
+                    Event::new("bridge_mint_queued")
+                        .with("domain_id", domain_id)
+                        .with("nonce", mint.nonce),
+                ],
             ))
         }
-        TxPayload::RollupBridgeDeposit { domain_id: _, amount } => {
-            ensure_funds(&sender_account, *amount, gas_fee)?;
+        TxPayload::RollupBridgeBurn { domain_id, amount } => {
+            ensure_positive(*amount)?;
+            let entry = domain_entry(ctx, domain_id).await?;
+            ensure_bridge_open(ctx, domain_id).await?;
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let burn_nonce = ctx.domains.bridge_withdrawal_burn(domain_id, sender, *amount)?;
             sender_account.balance_x = sender_account
                 .balance_x
-                .checked_sub(*amount + gas_fee)
+                .checked_sub(gas_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            chain.fee_pools.treasury = chain
-                .fee_pools
-                .treasury
-                .saturating_add(*amount);
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            // Withdrawals prove the burn against the committed root, so an
+            // optimistic domain commits it right away.
+            if entry.proof_mode == ProofMode::Optimistic {
+                commit_executed_root(
+                    ctx,
+                    domain_id,
+                    ctx.domains.state_root(domain_id),
+                    serde_json::json!({ "burn_nonce": burn_nonce }),
+                    current_height,
+                )
+                .await?;
+            }
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["bridge_deposit".into()],
+                vec![Event::new("bridge_burn")
+                    .with_hex("sender", sender)
+                    .with("domain_id", domain_id)
+                    .with("amount", amount)
+                    .with("burn_nonce", burn_nonce)],
             ))
         }
-        TxPayload::RollupBridgeWithdraw { amount, .. } => {
+        TxPayload::RollupBridgeWithdraw {
+            domain_id,
+            burn_nonce,
+            amount,
+            proof,
+        } => {
+            ensure_positive(*amount)?;
+            // Validity domains only settle what a proof covers, optimistic
+            // ones what can no longer be challenged.
+            let root = match proof_mode(ctx, domain_id).await? {
+                ProofMode::Optimistic => {
+                    ctx.state
+                        .get_entry::<sections::FinalizedDomainRoots>(domain_id)
+                        .await?
+                }
+                ProofMode::Validity => ctx
+                    .state
+                    .get_entry::<sections::DomainRoots>(domain_id)
+                    .await?
+                    .filter(|r| r.batch_height > 0)
+                    .map(|r| r.state_root),
+            }
+            .ok_or_else(|| anyhow::anyhow!("domain has no verified root"))?;
+            let burn = BridgeBurn {
+                owner: sender,
+                amount: *amount,
+            };
+            if proof.leaf != DomainState::burn_leaf(*burn_nonce, &burn) {
+                anyhow::bail!("proof is not for the sender's burn");
+            }
+            if !proof.verify(&root) {
+                anyhow::bail!("burn proof does not match the domain's final root");
+            }
+            let mut escrow = ctx
+                .state
+                .get_entry::<sections::BridgeEscrows>(domain_id)
+                .await?
+                .unwrap_or_default();
+            if escrow.withdrawn.contains(burn_nonce) {
+                anyhow::bail!("burn {burn_nonce} was already withdrawn");
+            }
+            let epoch = current_height / ctx.epoch_length_blocks.max(1);
+            debit_bridge_escrow(&mut escrow, *amount, epoch)?;
+            escrow.withdrawn.insert(*burn_nonce);
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_add(*amount)
@@ -675,62 +1611,477 @@ pub async fn apply_tx<S: StateStore>(
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            ctx.state
+                .put_entry::<sections::BridgeEscrows>(*domain_id, escrow)
+                .await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("bridge_withdraw")
+                    .with_hex("sender", sender)
+                    .with("domain_id", domain_id)
+                    .with("amount", amount)
+                    .with("burn_nonce", burn_nonce)],
+            ))
+        }
+        TxPayload::ForcedWithdraw {
+            domain_id,
+            balance,
+            proof,
+        } => {
+            ensure_positive(*balance)?;
+            if proof_mode(ctx, domain_id).await? != ProofMode::Validity {
+                anyhow::bail!("forced withdrawals are only open on validity domains");
+            }
+            let root = ctx
+                .state
+                .get_entry::<sections::DomainRoots>(domain_id)
+                .await?
+                .filter(|r| r.batch_height > 0)
+                .ok_or_else(|| anyhow::anyhow!("domain has no proven root"))?;
+            if proof.leaf != DomainState::balance_leaf(&sender, *balance) {
+                anyhow::bail!("proof is not for the sender's balance");
+            }
+            if !proof.verify(&root.state_root) {
+                anyhow::bail!("balance proof does not match the last proven root");
+            }
+            let batch_height = root.batch_height;
+            let epoch = current_height / ctx.epoch_length_blocks.max(1);
+            let mut escrow = ctx
+                .state
+                .get_entry::<sections::BridgeEscrows>(domain_id)
+                .await?
+                .unwrap_or_default();
+            debit_bridge_escrow(&mut escrow, *balance, epoch)?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_add(*balance)
+                .and_then(|b| b.checked_sub(gas_fee))
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            // Burning the domain balance keeps the exit from being paid
+            // twice, or again through the bridge.
+            ctx.domains.bridge_burn(domain_id, &sender, *balance)?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            ctx.state
+                .put_entry::<sections::BridgeEscrows>(*domain_id, escrow)
+                .await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("forced_withdraw")
+                    .with_hex("sender", sender)
+                    .with("domain_id", domain_id)
+                    .with("amount", balance)
+                    .with("batch_height", batch_height)],
+            ))
+        }
+        TxPayload::RollupBatchClaim {
+            domain_id,
+            blob_id,
+            state_root,
+            calls_root,
+            steps,
+        } => {
+            if proof_mode(ctx, domain_id).await? == ProofMode::Validity {
+                anyhow::bail!("validity domains only advance with a validity proof");
+            }
+            if *steps == 0 {
+                anyhow::bail!("claim must cover at least one call");
+            }
+            if ctx
+                .state
+                .get_entry::<sections::OptimisticClaims>(domain_id)
+                .await?
+                .is_some()
+            {
+                anyhow::bail!("previous claim is not final yet");
+            }
+            let params = disputes::params(ctx, domain_id).await?;
+            ensure_funds(&sender_account, locked, params.bond, gas_fee)?;
+            let mut events =
+                sequencers::record_batch(ctx, domain_id, sender, current_height).await?;
+            let prev_root = ctx
+                .state
+                .get_entry::<sections::DomainRoots>(domain_id)
+                .await?
+                .map_or_else(|| DomainState::default().root(), |r| r.state_root);
+            let batch_height = batch_height(ctx, domain_id).await? + 1;
+            let challenge_until = current_height + params.challenge_window_blocks;
+            preconf::record_batch(
+                ctx,
+                domain_id,
+                state::BatchRecord {
+                    batch_height,
+                    block_height: current_height,
+                    commitment: *calls_root,
+                    optimistic: true,
+                },
+            )
+            .await?;
+            let mut da_commitments = ctx.state.get_section::<sections::DaCommitments>().await?;
+            da_commitments.push(state::DACommitment {
+                block_height: current_height,
+                da_root: *calls_root,
+                blob_ids: vec![blob_id.clone()],
+            });
+            ctx.state
+                .put_section::<sections::DaCommitments>(da_commitments)
+                .await?;
+            ctx.state
+                .put_entry::<sections::DomainRoots>(
+                    *domain_id,
+                    state::DomainRoot {
+                        domain_id: *domain_id,
+                        state_root: *state_root,
+                        da_root: *calls_root,
+                        last_verified_epoch: current_height,
+                        proof_meta: serde_json::json!({ "blob": blob_id, "optimistic": true }),
+                        batch_height,
+                    },
+                )
+                .await?;
+            ctx.state
+                .put_entry::<sections::OptimisticClaims>(
+                    *domain_id,
+                    state::OptimisticClaim {
+                        domain_id: *domain_id,
+                        sequencer: sender,
+                        bond: params.bond,
+                        prev_root,
+                        state_root: *state_root,
+                        calls_root: *calls_root,
+                        steps: *steps,
+                        claimed_at: current_height,
+                        challenge_until,
+                        dispute: None,
+                    },
+                )
+                .await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(params.bond + gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            events.push(
+                Event::new("rollup_batch_claim")
+                    .with_hex("sender", sender)
+                    .with("domain_id", domain_id)
+                    .with("blob_id", blob_id)
+                    .with_hex("state_root", state_root)
+                    .with("steps", steps)
+                    .with("challenge_until", challenge_until),
+            );
+            Ok(ExecutionOutcome::success(gas_used, events))
+        }
+        TxPayload::FraudBisect {
+            domain_id,
+            midpoint_root,
+        } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let event =
+                disputes::bisect(ctx, domain_id, sender, *midpoint_root, current_height).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
+        TxPayload::FraudRespond { domain_id, agree } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let event = disputes::respond(ctx, domain_id, sender, *agree, current_height).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
+        TxPayload::FraudDefend { domain_id, witness } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let verdict = disputes::defend(ctx, domain_id, sender, witness, current_height).await?;
+            let mut payouts = HashMap::new();
+            let events =
+                disputes::resolve(ctx, domain_id, verdict, current_height, &mut payouts).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            credit_payouts(ctx, payouts).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, events))
+        }
+        TxPayload::SequencerRegister { domain_id, bond } => {
+            ensure_positive(*bond)?;
+            ensure_funds(&sender_account, locked, *bond, gas_fee)?;
+            let event = sequencers::register(ctx, domain_id, sender, *bond, current_height).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(*bond + gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
+        TxPayload::SequencerExit { domain_id } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let event = sequencers::exit(ctx, domain_id, sender, current_height).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
+        TxPayload::PreconfirmationChallenge { preconfirmation } => {
+            let bond = disputes::params(ctx, &preconfirmation.domain_id)
+                .await?
+                .bond;
+            ensure_funds(&sender_account, locked, bond, gas_fee)?;
+            let event =
+                preconf::challenge(ctx, preconfirmation, sender, bond, current_height).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(bond + gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
+        TxPayload::PreconfirmationFulfill {
+            tx_hash,
+            batch_height,
+            blob,
+        } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let mut payouts = HashMap::new();
+            let event = preconf::fulfill(
+                ctx,
+                tx_hash,
+                *batch_height,
+                blob,
+                current_height,
+                &mut payouts,
+            )
+            .await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            credit_payouts(ctx, payouts).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
+        TxPayload::ForceInclude {
+            domain_id,
+            tx_bytes,
+        } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let event = forced::queue(ctx, domain_id, sender, tx_bytes, current_height).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
+        TxPayload::ForceIncludeProve {
+            domain_id,
+            tx_hash,
+            batch_height,
+            blob,
+        } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let event = forced::prove(
+                ctx,
+                domain_id,
+                tx_hash,
+                *batch_height,
+                blob,
+                current_height,
+            )
+            .await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
+        TxPayload::LightClientCreate {
+            client_id,
+            chain_id,
+            header,
+            validators,
+        } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let event = light_clients::create(ctx, client_id, chain_id, header, validators).await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["bridge_withdraw".into()],
+                vec![event.with_hex("sender", sender)],
             ))
         }
-        TxPayload::GovernanceProposal { payload, kind } => {
+        TxPayload::LightClientUpdate {
+            client_id,
+            header,
+            signatures,
+            next_validators,
+        } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let event = light_clients::update(
+                ctx,
+                client_id,
+                header,
+                signatures,
+                next_validators.as_ref(),
+            )
+            .await?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![event.with_hex("sender", sender)],
+            ))
+        }
+        TxPayload::LightClientPacket {
+            client_id,
+            height,
+            packet,
+            proof,
+        } => {
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let message = light_clients::receive_packet(
+                ctx,
+                client_id,
+                *height,
+                packet,
+                proof,
+                current_height,
+            )
+            .await?;
+            let event = Event::new("light_client_packet")
+                .with_hex("sender", sender)
+                .with("client_id", client_id)
+                .with("to_domain", message.to)
+                .with("sequence", message.nonce);
+            ctx.domains.relay_message(message)?;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, vec![event]))
+        }
+        TxPayload::GovernanceProposal { .. } | TxPayload::SystemUpgrade { .. } => {
+            let (kind, payload) = match &tx.payload {
+                TxPayload::GovernanceProposal { payload, kind } => (
+                    kind.clone().unwrap_or_else(|| "general".into()),
+                    payload.clone(),
+                ),
+                TxPayload::SystemUpgrade {
+                    name,
+                    version,
+                    activation_height,
+                } => (
+                    PROTOCOL_UPGRADE_PROPOSAL.to_string(),
+                    serde_json::to_value(ProtocolUpgrade {
+                        name: name.clone(),
+                        version: *version,
+                        activation_height: *activation_height,
+                    })?,
+                ),
+                _ => unreachable!(),
+            };
+            GovernanceAction::parse(&kind, &payload)?;
             let id = Uuid::new_v4();
-            let now = now_millis();
-            let voter_weights = snapshot_validator_weights(&chain);
+            let now = ctx.clock.now_ms();
+            let voter_weights =
+                snapshot_validator_weights(&ctx.state.get_section::<sections::Validators>().await?);
             let snapshot_total_stake = voter_weights.values().copied().sum();
+            let params = ctx.state.get_governance_params().await?;
+            let deposit = params.min_deposit;
+            ensure_funds(&sender_account, locked, deposit, gas_fee)?;
             let proposal = state::Proposal {
                 id,
                 payload: payload.clone(),
-                kind: kind.clone().unwrap_or_else(|| "general".into()),
+                kind,
                 status: ProposalStatus::Active,
                 proposer: sender,
                 start: now,
-                end: now + chain.governance_params.voting_period_ms,
+                end: now + params.voting_period_ms,
                 eta: None,
                 snapshot_total_stake,
                 for_votes: 0,
                 against_votes: 0,
                 abstain_votes: 0,
                 votes: Vec::new(),
-                execution: payload.clone(),
+                execution: payload,
                 voter_weights,
                 approvals: Vec::new(),
+                deposit,
             };
-            chain.proposals.insert(id, proposal);
+            let proposed = Event::new("gov_proposal")
+                .with_hex("sender", sender)
+                .with("proposal_id", id)
+                .with("proposal_kind", &proposal.kind)
+                .with("deposit", deposit);
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(gas_fee)
-                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+                .and_then(|b| b.checked_sub(deposit))
+                .ok_or_else(|| anyhow::anyhow!("insufficient funds for deposit"))?;
             sender_account.nonce += 1;
+            ctx.state.put_proposal(proposal).await?;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["gov_proposal".into()],
+                vec![proposed],
             ))
         }
         TxPayload::GovernanceVote { proposal_id, support } => {
-            let Some(p) = chain.proposals.get_mut(proposal_id) else {
+            let Some(mut p) = ctx.state.get_proposal(proposal_id).await? else {
                 anyhow::bail!("proposal not found");
             };
             if p.status != ProposalStatus::Active {
                 anyhow::bail!("proposal not active");
             }
-            let now = now_millis();
+            let params = ctx.state.get_governance_params().await?;
+            let now = ctx.clock.now_ms();
             if now > p.end {
-                finalize_proposal(p, &chain.governance_params, now);
                 anyhow::bail!("voting window closed");
             }
             if p.votes.iter().any(|v| v.voter == sender) {
@@ -750,7 +2101,8 @@ pub async fn apply_tx<S: StateStore>(
                 choice: support.clone(),
                 weight,
             });
-            finalize_proposal(p, &chain.governance_params, now);
+            finalize_proposal(&mut p, &params, now);
+            ctx.state.put_proposal(p).await?;
 
             sender_account.balance_x = sender_account
                 .balance_x
@@ -758,19 +2110,24 @@ pub async fn apply_tx<S: StateStore>(
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
-            Ok(ExecutionOutcome::success(gas_used, vec!["gov_vote".into()]))
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("gov_vote")
+                    .with_hex("sender", sender)
+                    .with("proposal_id", proposal_id)
+                    .with("choice", vote_choice_str(support))
+                    .with("weight", weight)],
+            ))
         }
         TxPayload::GovernanceBridgeApprove { proposal_id } => {
-            let Some(p) = chain.proposals.get_mut(proposal_id) else {
+            let Some(mut p) = ctx.state.get_proposal(proposal_id).await? else {
                 anyhow::bail!("proposal not found");
             };
             if !matches!(p.status, ProposalStatus::Queued | ProposalStatus::Succeeded) {
                 anyhow::bail!("proposal not ready for bridge approval");
             }
-            ensure_multisig_eligibility(&chain.governance_params, &sender)?;
+            ensure_multisig_eligibility(&ctx.state.get_governance_params().await?, &sender)?;
             if p.approvals.contains(&sender) {
                 anyhow::bail!("already approved");
             }
@@ -780,21 +2137,23 @@ pub async fn apply_tx<S: StateStore>(
                 .checked_sub(gas_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
+            ctx.state.put_proposal(p).await?;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["gov_bridge_approve".into()],
+                vec![Event::new("gov_bridge_approve")
+                    .with_hex("sender", sender)
+                    .with("proposal_id", proposal_id)],
             ))
         }
         TxPayload::GovernanceExecute { proposal_id } => {
-            let Some(p) = chain.proposals.get_mut(proposal_id) else {
+            let Some(mut p) = ctx.state.get_proposal(proposal_id).await? else {
                 anyhow::bail!("proposal not found");
             };
-            let now = now_millis();
-            finalize_proposal(p, &chain.governance_params, now);
+            let params = ctx.state.get_governance_params().await?;
+            let now = ctx.clock.now_ms();
+            finalize_proposal(&mut p, &params, now);
             if p.status != ProposalStatus::Queued {
                 anyhow::bail!("proposal not queued for execution");
             }
@@ -805,21 +2164,462 @@ pub async fn apply_tx<S: StateStore>(
             } else {
                 anyhow::bail!("missing eta");
             }
-            ensure_multisig_threshold_met(&chain.governance_params, &p.approvals)?;
+            ensure_multisig_threshold_met(&params, &p.approvals)?;
             p.status = ProposalStatus::Executed;
+            let action = GovernanceAction::parse(&p.kind, &p.execution)?;
+            ctx.state.put_proposal(p).await?;
 
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(gas_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
+            // Stored before the action, which may credit the sender.
+            ctx.state.put_account(sender_account).await?;
+            let mut events = vec![Event::new("gov_execute")
+                .with_hex("sender", sender)
+                .with("proposal_id", proposal_id)];
+            if let Some(action) = action {
+                events.push(
+                    action
+                        .apply(ctx, current_height)
+                        .await?
+                        .with("proposal_id", proposal_id),
+                );
+            }
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(gas_used, events))
+        }
+        TxPayload::GovernanceCancel { proposal_id } => {
+            let Some(mut p) = ctx.state.get_proposal(proposal_id).await? else {
+                anyhow::bail!("proposal not found");
+            };
+            if p.proposer != sender {
+                anyhow::bail!("only the proposer may cancel");
+            }
+            if p.status != ProposalStatus::Active || ctx.clock.now_ms() >= p.end {
+                anyhow::bail!("voting has ended");
+            }
+            // The deposit is refunded by the end-of-block sweep.
+            p.status = ProposalStatus::Cancelled;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_proposal(p).await?;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("gov_cancel")
+                    .with_hex("sender", sender)
+                    .with("proposal_id", proposal_id)],
+            ))
+        }
+        TxPayload::AssetCreate {
+            symbol,
+            decimals,
+            max_supply,
+        } => {
+            if symbol.is_empty()
+                || symbol.len() > MAX_ASSET_SYMBOL_LEN
+                || !symbol.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                anyhow::bail!("invalid asset symbol");
+            }
+            if *decimals > MAX_ASSET_DECIMALS {
+                anyhow::bail!("asset decimals above {}", MAX_ASSET_DECIMALS);
+            }
+            if *max_supply == Some(0) {
+                anyhow::bail!("max supply must be non-zero");
+            }
+            let id = state::asset_id(&sender, sender_account.nonce);
+            if ctx
+                .state
+                .get_entry::<sections::Assets>(&id)
+                .await?
+                .is_some()
+            {
+                anyhow::bail!("asset already exists");
+            }
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            ctx.state
+                .put_entry::<sections::Assets>(
+                    id,
+                    Asset {
+                        id,
+                        symbol: symbol.clone(),
+                        decimals: *decimals,
+                        issuer: sender,
+                        supply: 0,
+                        max_supply: *max_supply,
+                        created_height: current_height,
+                    },
+                )
+                .await?;
+            sender_account.balance_x -= gas_fee;
+            sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["gov_execute".into()],
+                vec![Event::new("asset_create")
+                    .with("asset_id", id)
+                    .with_hex("issuer", sender)
+                    .with("symbol", symbol)
+                    .with("decimals", decimals)],
+            ))
+        }
+        TxPayload::AssetMint {
+            asset_id,
+            to,
+            amount,
+        } => {
+            let Some(mut asset) = ctx.state.get_entry::<sections::Assets>(asset_id).await? else {
+                anyhow::bail!("asset not found");
+            };
+            if asset.issuer != sender {
+                anyhow::bail!("only the issuer may mint");
+            }
+            if *amount == 0 {
+                anyhow::bail!("amount must be non-zero");
+            }
+            let supply = asset
+                .supply
+                .checked_add(*amount)
+                .ok_or_else(|| anyhow::anyhow!("asset supply overflow"))?;
+            if asset.max_supply.is_some_and(|max| supply > max) {
+                anyhow::bail!("mint exceeds max supply");
+            }
+            asset.supply = supply;
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            ctx.state
+                .put_entry::<sections::Assets>(*asset_id, asset)
+                .await?;
+            sender_account.balance_x -= gas_fee;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+
+            let mut to_account = ctx.state.get_account(to).await?.unwrap_or(default_account(*to));
+            to_account.credit_asset(*asset_id, *amount)?;
+            ctx.state.put_account(to_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("asset_mint")
+                    .with("asset_id", asset_id)
+                    .with_hex("recipient", to)
+                    .with("amount", amount)
+                    .with("supply", supply)],
+            ))
+        }
+        TxPayload::AssetTransfer {
+            asset_id,
+            to,
+            amount,
+        } => {
+            if ctx
+                .state
+                .get_entry::<sections::Assets>(asset_id)
+                .await?
+                .is_none()
+            {
+                anyhow::bail!("asset not found");
+            }
+            if *amount == 0 {
+                anyhow::bail!("amount must be non-zero");
+            }
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            sender_account.debit_asset(asset_id, *amount)?;
+            sender_account.balance_x -= gas_fee;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+
+            let mut to_account = ctx.state.get_account(to).await?.unwrap_or(default_account(*to));
+            to_account.credit_asset(*asset_id, *amount)?;
+            ctx.state.put_account(to_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("asset_transfer")
+                    .with("asset_id", asset_id)
+                    .with_hex("sender", sender)
+                    .with_hex("recipient", to)
+                    .with("amount", amount)],
+            ))
+        }
+        TxPayload::MultisigCreate { signers, threshold } => {
+            state::validate_signers(signers, *threshold)?;
+            let address = state::multisig_address(&sender, sender_account.nonce);
+            if ctx
+                .state
+                .get_entry::<sections::Multisigs>(&address)
+                .await?
+                .is_some()
+            {
+                anyhow::bail!("multisig already exists");
+            }
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            ctx.state
+                .put_entry::<sections::Multisigs>(
+                    address,
+                    Multisig {
+                        address,
+                        signers: signers.clone(),
+                        threshold: *threshold,
+                        created_height: current_height,
+                        pending: Default::default(),
+                        next_proposal_id: 0,
+                    },
+                )
+                .await?;
+            sender_account.balance_x -= gas_fee;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("multisig_create")
+                    .with_hex("multisig", address)
+                    .with_hex("creator", sender)
+                    .with("signers", signers.len())
+                    .with("threshold", threshold)],
+            ))
+        }
+        TxPayload::MultisigSubmit { multisig, call } => {
+            let Some(mut ms) = ctx.state.get_entry::<sections::Multisigs>(multisig).await? else {
+                anyhow::bail!("multisig not found");
+            };
+            if !ms.is_signer(&sender) {
+                anyhow::bail!("not a multisig signer");
+            }
+            if let MultisigCall::SetSigners { signers, threshold } = call {
+                state::validate_signers(signers, *threshold)?;
+            }
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let proposal_id = ms.next_proposal_id;
+            ms.next_proposal_id += 1;
+            ms.pending.insert(
+                proposal_id,
+                MultisigProposal {
+                    call: call.clone(),
+                    proposer: sender,
+                    approvals: [sender].into(),
+                    submitted_height: current_height,
+                },
+            );
+            ctx.state
+                .put_entry::<sections::Multisigs>(*multisig, ms)
+                .await?;
+            sender_account.balance_x -= gas_fee;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("multisig_submit")
+                    .with_hex("multisig", multisig)
+                    .with_hex("sender", sender)
+                    .with("proposal_id", proposal_id)],
+            ))
+        }
+        TxPayload::MultisigApprove {
+            multisig,
+            proposal_id,
+        } => {
+            let Some(mut ms) = ctx.state.get_entry::<sections::Multisigs>(multisig).await? else {
+                anyhow::bail!("multisig not found");
+            };
+            if !ms.is_signer(&sender) {
+                anyhow::bail!("not a multisig signer");
+            }
+            let Some(proposal) = ms.pending.get_mut(proposal_id) else {
+                anyhow::bail!("multisig proposal not found");
+            };
+            if !proposal.approvals.insert(sender) {
+                anyhow::bail!("already approved");
+            }
+            let approvals = proposal.approvals.len();
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            ctx.state
+                .put_entry::<sections::Multisigs>(*multisig, ms)
+                .await?;
+            sender_account.balance_x -= gas_fee;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("multisig_approve")
+                    .with_hex("multisig", multisig)
+                    .with_hex("sender", sender)
+                    .with("proposal_id", proposal_id)
+                    .with("approvals", approvals)],
+            ))
+        }
+        TxPayload::MultisigExecute {
+            multisig,
+            proposal_id,
+        } => {
+            let Some(mut ms) = ctx.state.get_entry::<sections::Multisigs>(multisig).await? else {
+                anyhow::bail!("multisig not found");
+            };
+            if !ms.is_signer(&sender) {
+                anyhow::bail!("not a multisig signer");
+            }
+            let Some(proposal) = ms.pending.get(proposal_id) else {
+                anyhow::bail!("multisig proposal not found");
+            };
+            if proposal.approvals.len() < ms.threshold as usize {
+                anyhow::bail!(
+                    "{} of {} approvals",
+                    proposal.approvals.len(),
+                    ms.threshold
+                );
+            }
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let proposal = ms.pending.remove(proposal_id).expect("pending proposal");
+            // Stored before the call, which may replace the signers.
+            ctx.state
+                .put_entry::<sections::Multisigs>(*multisig, ms)
+                .await?;
+            let event = multisig::apply_call(ctx, *multisig, *proposal_id, &proposal.call).await?;
+            // Reloaded: the call may have paid the executor.
+            let mut sender_account = ctx
+                .state
+                .get_account(&sender)
+                .await?
+                .unwrap_or(default_account(sender));
+            sender_account.balance_x -= gas_fee;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![event.with_hex("sender", sender)],
+            ))
+        }
+        TxPayload::Schedule {
+            execute_at_height,
+            inner,
+        } => {
+            if *execute_at_height <= current_height {
+                anyhow::bail!("execute_at_height must be in the future");
+            }
+            if *execute_at_height - current_height > MAX_SCHEDULE_DELAY_BLOCKS {
+                anyhow::bail!("cannot schedule more than {MAX_SCHEDULE_DELAY_BLOCKS} blocks ahead");
+            }
+            let payload = schedule::encode(inner)?;
+            let mut schedule = ctx.state.get_section::<sections::Schedule>().await?;
+            if schedule.pending_for(&sender) >= MAX_SCHEDULED_PER_ACCOUNT {
+                anyhow::bail!("too many scheduled calls");
+            }
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            let schedule_id = schedule.next_id;
+            schedule.next_id += 1;
+            schedule.calls.insert(
+                schedule_id,
+                ScheduledCall {
+                    owner: sender,
+                    public_key: tx.public_key.clone(),
+                    execute_at_height: *execute_at_height,
+                    payload,
+                    scheduled_height: current_height,
+                },
+            );
+            ctx.state
+                .put_section::<sections::Schedule>(schedule)
+                .await?;
+            sender_account.balance_x -= gas_fee;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("schedule")
+                    .with("schedule_id", schedule_id)
+                    .with_hex("owner", sender)
+                    .with("execute_at_height", execute_at_height)],
+            ))
+        }
+        TxPayload::ScheduleCancel { schedule_id } => {
+            let mut schedule = ctx.state.get_section::<sections::Schedule>().await?;
+            match schedule.calls.get(schedule_id) {
+                Some(call) if call.owner == sender => {}
+                Some(_) => anyhow::bail!("only the owner may cancel"),
+                None => anyhow::bail!("scheduled call not found"),
+            }
+            ensure_funds(&sender_account, locked, 0, gas_fee)?;
+            schedule.calls.remove(schedule_id);
+            ctx.state
+                .put_section::<sections::Schedule>(schedule)
+                .await?;
+            sender_account.balance_x -= gas_fee;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("schedule_cancel")
+                    .with("schedule_id", schedule_id)
+                    .with_hex("owner", sender)],
+            ))
+        }
+        TxPayload::VestingCreate {
+            beneficiary,
+            amount,
+            cliff_height,
+            end_height,
+        } => {
+            let vesting = VestingSchedule {
+                beneficiary: *beneficiary,
+                total: *amount,
+                start_height: current_height,
+                cliff_height: *cliff_height,
+                end_height: *end_height,
+            };
+            vesting.validate()?;
+            if *amount < MIN_VESTING_AMOUNT {
+                anyhow::bail!("vesting amount must be at least {MIN_VESTING_AMOUNT}");
+            }
+            let mut schedules = ctx
+                .state
+                .get_entry::<sections::Vesting>(beneficiary)
+                .await?
+                .unwrap_or_default();
+            schedules.retain(|s| s.locked(current_height) > 0);
+            if schedules.len() >= MAX_VESTING_SCHEDULES_PER_ACCOUNT {
+                anyhow::bail!("too many vesting schedules");
+            }
+            ensure_funds(&sender_account, locked, *amount, gas_fee)?;
+            schedules.push(vesting);
+            ctx.state
+                .put_entry::<sections::Vesting>(*beneficiary, schedules)
+                .await?;
+            sender_account.balance_x -= *amount + gas_fee;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+
+            let mut beneficiary_account = ctx
+                .state
+                .get_account(beneficiary)
+                .await?
+                .unwrap_or(default_account(*beneficiary));
+            beneficiary_account.balance_x = beneficiary_account
+                .balance_x
+                .checked_add(*amount)
+                .ok_or_else(|| anyhow::anyhow!("overflow"))?;
+            ctx.state.put_account(beneficiary_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("vesting_create")
+                    .with_hex("sender", sender)
+                    .with_hex("beneficiary", beneficiary)
+                    .with("amount", amount)
+                    .with("cliff_height", cliff_height)
+                    .with("end_height", end_height)],
             ))
         }
         TxPayload::Slash {
@@ -827,45 +2627,64 @@ pub async fn apply_tx<S: StateStore>(
             penalty_bps,
             reason: _,
         } => {
-            let Some(v) = chain.validators.values_mut().find(|v| v.owner == *validator) else {
-                anyhow::bail!("validator not found");
-            };
-            let stake_before = v.stake;
-            if stake_before == 0 {
-                anyhow::bail!("validator has no stake to slash");
-            }
             let effective_bps = if *penalty_bps == 0 {
-                ctx.slash_penalty_bps
+                ctx.state
+                    .get_param_overrides()
+                    .await?
+                    .slash_penalty_bps
+                    .unwrap_or(ctx.slash_penalty_bps)
             } else {
                 *penalty_bps
-            }
-            .min(10_000);
-            let penalty = stake_before
-                .saturating_mul(effective_bps as u128)
-                / 10_000;
-            if penalty == 0 {
-                anyhow::bail!("penalty too small");
-            }
+            };
+            slash_validator_in_store(ctx, validator, effective_bps).await?;
 
-            if stake_before > 0 && penalty > 0 && !chain.delegations.is_empty() {
-                let mut updated = Vec::with_capacity(chain.delegations.len());
-                for mut d in chain.delegations.drain(..) {
-                    if d.validator_id == v.id {
-                        let cut = penalty.saturating_mul(d.stake) / stake_before;
-                        d.stake = d.stake.saturating_sub(cut);
-                    }
-                    if d.stake > 0 {
-                        updated.push(d);
-                    }
-                }
-                chain.delegations = updated;
+            sender_account.balance_x = sender_account
+                .balance_x
+                .checked_sub(gas_fee)
+                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+            sender_account.nonce += 1;
+            ctx.state.put_account(sender_account).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![Event::new("slash")
+                    .with_hex("sender", sender)
+                    .with_hex("validator", validator)
+                    .with("penalty_bps", effective_bps)],
+            ))
+        }
+        TxPayload::SubmitEvidence { evidence } => {
+            evidence.verify(&ctx.chain_id, accepts_legacy_signatures(ctx).await?)?;
+            let evidence_id = evidence.id();
+            let mut processed = ctx
+                .state
+                .get_section::<sections::ProcessedEvidence>()
+                .await?;
+            if processed.contains(&evidence_id) {
+                anyhow::bail!("evidence already processed");
             }
-
-            v.stake = v.stake.saturating_sub(penalty);
-            if v.stake == 0 {
+            if sender_account.balance_x < gas_fee {
+                anyhow::bail!("insufficient funds for gas");
+            }
+            let offender = evidence.offender();
+            let bps = if ctx.slashing_double_sign == 0 {
+                ctx.state
+                    .get_param_overrides()
+                    .await?
+                    .slash_penalty_bps
+                    .unwrap_or(ctx.slash_penalty_bps)
+            } else {
+                ctx.slashing_double_sign as u16 * 100
+            };
+            slash_validator_in_store(ctx, &offender, bps).await?;
+            if let Some(mut v) = validator_of(ctx, &offender).await? {
                 v.status = ValidatorStatus::Jailed;
+                ctx.state.put_validator(v).await?;
             }
-            chain.fee_pools.treasury = chain.fee_pools.treasury.saturating_add(penalty);
+            processed.insert(evidence_id);
+            ctx.state
+                .put_section::<sections::ProcessedEvidence>(processed)
+                .await?;
 
             sender_account.balance_x = sender_account
                 .balance_x
@@ -873,37 +2692,65 @@ pub async fn apply_tx<S: StateStore>(
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
-            Ok(ExecutionOutcome::success(gas_used, vec!["slash".into()]))
+            route_gas_fee_in_store(ctx, gas_fee).await?;
+            Ok(ExecutionOutcome::success(
+                gas_used,
+                vec![
+                    Event::new("submit_evidence")
+                        .with_hex("sender", sender)
+                        .with_hex("offender", offender)
+                        .with_hex("evidence_id", evidence_id),
+                    Event::new("slash")
+                        .with_hex("validator", offender)
+                        .with("penalty_bps", bps),
+                ],
+            ))
         }
-        TxPayload::PrivacyDeposit { commitment, amount } => {
+        TxPayload::PrivacyDeposit {
+            commitment,
+            amount,
+            pool: pool_id,
+            memo,
+        } => {
             ensure_positive(*amount)?;
-            ensure_funds(&sender_account, *amount, gas_fee)?;
-            let pool = ensure_privacy_pool(&mut chain);
+            if let Some(memo) = memo {
+                if memo.ciphertext.len() > zk_program_privacy::MAX_NOTE_CIPHERTEXT_LEN {
+                    anyhow::bail!("note memo too large");
+                }
+            }
+            ensure_funds(&sender_account, locked, *amount, gas_fee)?;
+            let pool_id = pool_id.as_deref().unwrap_or(DEFAULT_PRIVACY_POOL);
+            let mut pool = privacy_pool(ctx, pool_id).await?;
+            ensure_denomination(&pool, *amount)?;
             if pool.commitments.contains(commitment) {
                 anyhow::bail!("commitment already exists in pool");
             }
-            pool.commitments.push(*commitment);
             pool.total_shielded = pool
                 .total_shielded
                 .checked_add(*amount)
                 .ok_or_else(|| anyhow::anyhow!("shielded total overflow"))?;
-            pool.merkle_root = compute_merkle_root(&pool.commitments);
+            let leaf_index = pool.tree.insert(*commitment)?;
+            pool.commitments.push(*commitment);
+            pool.merkle_root = pool.tree.root();
 
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(*amount + gas_fee)
                 .ok_or_else(|| anyhow::anyhow!("underflow"))?;
             sender_account.nonce += 1;
+            ctx.state
+                .put_entry::<sections::PrivacyPools>(pool_id.to_string(), pool)
+                .await?;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["privacy_deposit".into()],
+                vec![Event::new("privacy_deposit")
+                    .with_hex("sender", sender)
+                    .with("pool", pool_id)
+                    .with_hex("commitment", commitment)
+                    .with("leaf_index", leaf_index)
+                    .with("amount", amount)],
             ))
         }
         TxPayload::PrivacyWithdraw {
@@ -913,14 +2760,22 @@ pub async fn apply_tx<S: StateStore>(
             merkle_root,
             commitment,
             proof,
+            pool: pool_id,
+            relayer,
+            relayer_fee,
         } => {
             ensure_positive(*amount)?;
-            let pool = ensure_privacy_pool(&mut chain);
+            if *relayer_fee > 0 && relayer.is_none() {
+                anyhow::bail!("relayer fee set without a relayer");
+            }
+            let pool_id = pool_id.as_deref().unwrap_or(DEFAULT_PRIVACY_POOL);
+            let mut pool = privacy_pool(ctx, pool_id).await?;
+            ensure_denomination(&pool, *amount)?;
             if pool.nullifiers.contains(nullifier) {
                 anyhow::bail!("nullifier already spent");
             }
-            if &pool.merkle_root != merkle_root {
-                anyhow::bail!("merkle root mismatch");
+            if !pool.tree.is_known_root(merkle_root) {
+                anyhow::bail!("unknown or expired merkle root");
             }
             if !pool.commitments.contains(commitment) {
                 anyhow::bail!("commitment not found in pool");
@@ -928,6 +2783,14 @@ pub async fn apply_tx<S: StateStore>(
             if pool.total_shielded < *amount {
                 anyhow::bail!("insufficient shielded liquidity");
             }
+            // The fee in force at execution is part of the proven statement, so
+            // a proof made before a fee change no longer verifies.
+            let fee = zk_program_privacy::withdraw_fee(*amount, pool.withdraw_fee_bps);
+            let relayer_cut = fee.saturating_mul(pool.relayer_fee_share_bps as u128) / 10_000;
+            let payout = amount
+                .checked_sub(fee)
+                .and_then(|rest| rest.checked_sub(*relayer_fee))
+                .ok_or_else(|| anyhow::anyhow!("fees exceed the withdrawn amount"))?;
 
             let input = zk_program_privacy::PrivacyWithdrawInput {
                 nullifier: *nullifier,
@@ -935,109 +2798,228 @@ pub async fn apply_tx<S: StateStore>(
                 recipient: *recipient,
                 amount: *amount,
                 commitment: *commitment,
+                fee,
+                relayer: *relayer,
+                relayer_fee: *relayer_fee,
             };
-            verify_privacy_withdraw(ctx, &input, proof).await?;
+            let keys = ctx
+                .state
+                .get_section::<sections::VerificationKeys>()
+                .await?;
+            verify_privacy_withdraw(ctx, &keys, &input, proof).await?;
 
             pool.nullifiers.push(*nullifier);
             pool.total_shielded = pool.total_shielded.saturating_sub(*amount);
+            ctx.state
+                .put_entry::<sections::PrivacyPools>(pool_id.to_string(), pool)
+                .await?;
+            let mut pools = ctx.state.get_fee_pools().await?;
+            pools.treasury = pools.treasury.saturating_add(fee - relayer_cut);
+            ctx.state.put_fee_pools(pools).await?;
             let mut to_account =
                 ctx.state.get_account(recipient).await?.unwrap_or(default_account(*recipient));
             to_account.balance_x = to_account
                 .balance_x
-                .checked_add(*amount)
+                .checked_add(payout)
                 .ok_or_else(|| anyhow::anyhow!("overflow"))?;
             ctx.state.put_account(to_account).await?;
+            // The submitter's account is written last, so a relayer that
+            // submitted its own withdrawal is credited on it directly.
+            let mut sender_credit = relayer_cut;
+            match relayer {
+                Some(relayer) if *relayer == sender => sender_credit += relayer_fee,
+                Some(relayer) if *relayer_fee > 0 => {
+                    let mut relayer_account = ctx
+                        .state
+                        .get_account(relayer)
+                        .await?
+                        .unwrap_or(default_account(*relayer));
+                    relayer_account.balance_x = relayer_account
+                        .balance_x
+                        .checked_add(*relayer_fee)
+                        .ok_or_else(|| anyhow::anyhow!("overflow"))?;
+                    ctx.state.put_account(relayer_account).await?;
+                }
+                _ => {}
+            }
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(gas_fee)
-                .ok_or_else(|| anyhow::anyhow!("insufficient funds for gas"))?;
+                .ok_or_else(|| anyhow::anyhow!("insufficient funds for gas"))?
+                .saturating_add(sender_credit);
             sender_account.nonce += 1;
             ctx.state.put_account(sender_account).await?;
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["privacy_withdraw".into()],
+                vec![Event::new("privacy_withdraw")
+                    .with_hex("sender", sender)
+                    .with("pool", pool_id)
+                    .with_hex("recipient", recipient)
+                    .with_hex("nullifier", nullifier)
+                    .with("amount", amount)
+                    .with("fee", fee)
+                    .with("relayer_fee", relayer_fee)],
             ))
         }
-        TxPayload::SystemUpgrade { module, version } => {
+        TxPayload::RegisterBlsKey {
+            bls_pubkey,
+            proof_of_possession,
+        } => {
+            bls::bls_verify_pop(bls_pubkey, proof_of_possession)?;
+            let validators = ctx.state.get_section::<sections::Validators>().await?;
+            if validators
+                .values()
+                .any(|v| v.owner != sender && &v.bls_pubkey == bls_pubkey)
+            {
+                anyhow::bail!("bls key already registered");
+            }
+            let Some(mut v) = validators.into_values().find(|v| v.owner == sender) else {
+                anyhow::bail!("no validator for sender");
+            };
+            v.bls_pubkey = bls_pubkey.clone();
             sender_account.balance_x = sender_account
                 .balance_x
                 .checked_sub(gas_fee)
-                .ok_or_else(|| anyhow::anyhow!("underflow"))?;
+                .ok_or_else(|| anyhow::anyhow!("insufficient funds for gas"))?;
             sender_account.nonce += 1;
+            ctx.state.put_validator(v).await?;
             ctx.state.put_account(sender_account).await?;
-            let now = now_millis();
-            let id = Uuid::new_v4();
-            chain.proposals.insert(
-                id,
-                state::Proposal {
-                    id,
-                    payload: serde_json::json!({ "module": module, "version": version }),
-                    kind: "upgrade".into(),
-                    status: ProposalStatus::Queued,
-                    proposer: sender,
-                    start: now,
-                    end: now,
-                    eta: Some(now + chain.governance_params.timelock_ms),
-                    snapshot_total_stake: 0,
-                    for_votes: 0,
-                    against_votes: 0,
-                    abstain_votes: 0,
-                    votes: Vec::new(),
-                    execution: serde_json::json!({ "module": module, "version": version }),
-                    voter_weights: HashMap::new(),
-                    approvals: Vec::new(),
-                },
-            );
-            route_gas_fee(&mut chain, gas_fee, &ctx.fee_split);
-            sync_accounts_from_store(ctx, &mut chain).await?;
-            ctx.state.put_chain_state(chain).await?;
+            route_gas_fee_in_store(ctx, gas_fee).await?;
             Ok(ExecutionOutcome::success(
                 gas_used,
-                vec!["system_upgrade".into()],
+                vec![Event::new("register_bls_key")
+                    .with_hex("sender", sender)
+                    .with_hex("bls_pubkey", bls_pubkey)],
             ))
         }
     }
 }
 
-pub async fn apply_block<S: StateStore>(
-    ctx: &ExecutionContext<S>,
+/// Executes `block` against a `StateOverlay` of `ctx.state`, which is only
+/// written to once the whole block has applied.
+pub async fn apply_block<S: StateStore + Clone>(
+    base: &ExecutionContext<S>,
     block: &Block,
 ) -> anyhow::Result<BlockApplyResult> {
+    // Domain state lives outside the block's overlay, so a block that fails
+    // after a domain-mutating tx has to put it back by hand.
+    let pre_domains = base.domains.checkpoint();
+    let result = execute_block(base, block).await;
+    if result.is_err() {
+        base.domains.restore(pre_domains);
+    }
+    result
+}
+
+async fn execute_block<S: StateStore + Clone>(
+    base: &ExecutionContext<S>,
+    block: &Block,
+) -> anyhow::Result<BlockApplyResult> {
+    base.clock.set_block_time(block.header.timestamp);
+    let block_ctx = base.with_state(StateOverlay::new(base.state.clone()).await?);
+    let ctx = &block_ctx;
+    let mut events = upgrades::begin_block(ctx, &block.header).await?;
+    let roots_before = ctx.state.get_section::<sections::DomainRoots>().await?;
     let mut gas_used = 0_u64;
-    let mut events = Vec::new();
+    let mut receipts = Vec::with_capacity(block.transactions.len());
     for tx in &block.transactions {
-        let result = apply_tx(ctx, tx, block.header.height).await?;
+        let result = include_tx(ctx, tx, block.header.height).await?;
         gas_used = gas_used.saturating_add(result.gas_used);
-        events.extend(result.events);
+        events.extend(result.events.iter().cloned());
+        receipts.push(result);
         if gas_used > ctx.max_gas_per_block {
             anyhow::bail!("block exceeds gas limit");
         }
     }
+    // Scheduled calls don't count towards the block's gas limit.
+    events.extend(schedule::run_due(ctx, block.header.height).await?);
+    if is_epoch_boundary(block.header.height, ctx.epoch_length_blocks) {
+        let processed = process_exit_queue(ctx, block.header.height).await?;
+        if processed > 0 {
+            events.push(Event::new("exit_queue_processed").with("count", processed));
+        }
+        events.extend(rotate_active_set(ctx).await?);
+        events.extend(prune_state(ctx).await?);
+    }
     process_unbondings(ctx, block.header.height).await?;
+    events.extend(disputes::settle(ctx, block.header.height).await?);
+    events.extend(sequencers::settle(ctx, block.header.height).await?);
+    events.extend(preconf::settle(ctx, block.header.height).await?);
+    events.extend(forced::settle(ctx, block.header.height).await?);
+    events.extend(liveness::apply_liveness_report(ctx, block).await?);
+    events.extend(governance::sweep_proposals(ctx, ctx.clock.now_ms()).await?);
     let minted = apply_inflation_rewards(ctx, block).await?;
     if minted > 0 {
-        events.push("block_reward".into());
+        events.push(
+            Event::new("block_reward")
+                .with_hex("proposer", block.header.proposer_id)
+                .with("amount", minted),
+        );
     }
-    let state_root = ctx.state.commit().await?;
+    let domain_roots = changed_domain_roots(
+        &roots_before,
+        &ctx.state.get_section::<sections::DomainRoots>().await?,
+    );
+    block_ctx.state.flush().await?;
+    let state_root = base.state.commit().await?;
+    base.state.archive(block.header.height).await?;
+    base.domains.record_snapshots(block.header.height);
     Ok(BlockApplyResult {
         state_root,
         gas_used,
         events,
+        receipts,
+        domain_roots,
     })
 }
 
-#[derive(Debug, Clone)]
+/// Roots in `after` that are new or moved since `before`, sorted by domain
+/// id so every node builds the same list.
+fn changed_domain_roots(
+    before: &HashMap<Uuid, state::DomainRoot>,
+    after: &HashMap<Uuid, state::DomainRoot>,
+) -> Vec<DomainRootCommitment> {
+    let mut roots: Vec<DomainRootCommitment> = after
+        .values()
+        .map(DomainRootCommitment::new)
+        .filter(|root| {
+            before
+                .get(&root.domain_id)
+                .is_none_or(|prev| DomainRootCommitment::new(prev) != *root)
+        })
+        .collect();
+    roots.sort_by_key(|root| root.domain_id);
+    roots
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionOutcome {
     pub gas_used: u64,
-    pub events: Vec<String>,
+    pub events: Vec<Event>,
+    /// Why execution failed; the tx was still included and charged for gas.
+    pub error: Option<String>,
 }
 
 impl ExecutionOutcome {
-    pub fn success(gas_used: u64, events: Vec<String>) -> Self {
-        Self { gas_used, events }
+    pub fn success(gas_used: u64, events: Vec<Event>) -> Self {
+        Self {
+            gas_used,
+            events,
+            error: None,
+        }
+    }
+
+    pub fn failed(gas_used: u64, error: String) -> Self {
+        Self {
+            gas_used,
+            events: vec![Event::new("tx_failed").with("error", &error)],
+            error: Some(error),
+        }
+    }
+
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
     }
 }
 
@@ -1045,11 +3027,17 @@ impl ExecutionOutcome {
 pub struct BlockApplyResult {
     pub state_root: Hash,
     pub gas_used: u64,
-    pub events: Vec<String>,
+    /// Tx events in block order, followed by those of the block itself.
+    pub events: Vec<Event>,
+    /// One per transaction, in block order.
+    pub receipts: Vec<ExecutionOutcome>,
+    /// What the block's header must carry as `domain_roots`.
+    pub domain_roots: Vec<DomainRootCommitment>,
 }
 
-pub fn bootstrap_state() -> ExecutionContext<InMemoryStateStore> {
-    let default_genesis = GenesisConfig {
+/// Devnet parameters with no accounts or validators.
+pub fn devnet_genesis() -> GenesisConfig {
+    GenesisConfig {
         chain_id: "kova-devnet".into(),
         initial_validators: vec![],
         initial_accounts: vec![],
@@ -1072,8 +3060,18 @@ pub fn bootstrap_state() -> ExecutionContext<InMemoryStateStore> {
         reward_params: RewardParams::default(),
         unbonding_delay_blocks: default_unbonding_delay_blocks(),
         slash_penalty_bps: default_slash_penalty_bps(),
-    };
-    futures::executor::block_on(from_genesis(default_genesis)).unwrap()
+        epoch_length_blocks: default_epoch_length_blocks(),
+        exit_churn_bps: default_exit_churn_bps(),
+        max_commission_change_per_epoch: default_max_commission_change_per_epoch(),
+        liveness_params: LivenessParams::default(),
+        staking_params: StakingParams::default(),
+        retention: RetentionParams::default(),
+        vesting: vec![],
+    }
+}
+
+pub fn bootstrap_state() -> ExecutionContext<InMemoryStateStore> {
+    futures::executor::block_on(from_genesis(devnet_genesis())).unwrap()
 }
 
 pub async fn from_genesis(
@@ -1091,11 +3089,15 @@ pub async fn from_genesis(
                 balance_x: balance,
                 code_hash: None,
                 storage_root: None,
+                assets: Default::default(),
             },
         );
     }
 
     for v in genesis.initial_validators {
+        if !v.bls_pubkey.is_empty() {
+            bls::bls_verify_pop(&v.bls_pubkey, &v.bls_proof_of_possession)?;
+        }
         let id = validator_id_from_pubkey(&v.pubkey);
         chain.validators.insert(
             id,
@@ -1106,6 +3108,9 @@ pub async fn from_genesis(
                 stake: v.stake,
                 status: ValidatorStatus::Active,
                 commission_rate: v.commission_rate,
+                bls_pubkey: v.bls_pubkey.clone(),
+                description: ValidatorDescription::default(),
+                commission_changed_epoch: None,
             },
         );
     }
@@ -1125,6 +3130,24 @@ pub async fn from_genesis(
     }
     chain.total_supply = computed_supply;
     chain.last_reward_height = 0;
+    chain.staking_params = genesis.staking_params;
+    chain.retention = genesis.retention;
+
+    for schedule in genesis.vesting {
+        schedule.validate()?;
+        let balance = chain
+            .accounts
+            .get(&schedule.beneficiary)
+            .map_or(0, |a| a.balance_x);
+        let schedules = chain.vesting.entry(schedule.beneficiary).or_default();
+        schedules.push(schedule);
+        let vesting_total = schedules
+            .iter()
+            .fold(0u128, |acc, s| acc.saturating_add(s.total));
+        if balance < vesting_total {
+            anyhow::bail!("genesis vesting exceeds the beneficiary's initial balance");
+        }
+    }
 
     store.put_chain_state(chain).await?;
 
@@ -1140,6 +3163,10 @@ pub async fn from_genesis(
         genesis.reward_params,
         genesis.unbonding_delay_blocks,
         genesis.slash_penalty_bps,
+        genesis.epoch_length_blocks,
+        genesis.exit_churn_bps,
+        genesis.max_commission_change_per_epoch,
+        genesis.liveness_params,
     ))
 }
 
@@ -1158,26 +3185,131 @@ fn default_account(address: Address) -> Account {
         balance_x: 0,
         code_hash: None,
         storage_root: None,
+        assets: Default::default(),
     }
 }
 
-fn gas_cost(payload: &TxPayload) -> u64 {
+/// Gas a tx with `payload` is charged, whether it succeeds or fails.
+pub fn gas_cost(payload: &TxPayload) -> u64 {
     match payload {
         TxPayload::Transfer { .. } => 21_000,
         TxPayload::Stake { .. } | TxPayload::Unstake { .. } => 50_000,
         TxPayload::Delegate { .. } | TxPayload::Undelegate { .. } => 60_000,
         TxPayload::Slash { .. } => 70_000,
+        TxPayload::SubmitEvidence { .. } => 90_000,
         TxPayload::PrivacyDeposit { .. } => 80_000,
         TxPayload::PrivacyWithdraw { .. } => 120_000,
         TxPayload::GovernanceExecute { .. } => 80_000,
         TxPayload::DomainExecute(_) => 200_000,
         TxPayload::CrossDomainSend { .. } => 90_000,
         TxPayload::CrossDomainRelay { .. } => 50_000,
+        TxPayload::CrossDomainRefund { .. } => 50_000,
+        TxPayload::DomainInboxProcess { .. } => 120_000,
         TxPayload::FraudChallenge { .. } => 150_000,
+        TxPayload::RollupBatchCommit { .. } => 150_000,
+        TxPayload::RollupBridgeWithdraw { .. } => 80_000,
+        TxPayload::ForcedWithdraw { .. } => 120_000,
+        TxPayload::RollupBatchClaim { .. } => 100_000,
+        TxPayload::FraudBisect { .. } | TxPayload::FraudRespond { .. } => 40_000,
+        TxPayload::FraudDefend { .. } => 250_000,
+        TxPayload::SequencerRegister { .. } => 60_000,
+        TxPayload::SequencerExit { .. } => 40_000,
+        TxPayload::PreconfirmationChallenge { .. } => 80_000,
+        TxPayload::PreconfirmationFulfill { .. } => 250_000,
+        TxPayload::ForceInclude { .. } => 200_000,
+        TxPayload::ForceIncludeProve { .. } => 250_000,
+        TxPayload::LightClientCreate { .. } => 80_000,
+        TxPayload::LightClientUpdate { .. } => 100_000,
+        TxPayload::LightClientPacket { .. } => 80_000,
+        TxPayload::AssetCreate { .. } => 60_000,
+        TxPayload::AssetMint { .. } => 40_000,
+        TxPayload::AssetTransfer { .. } => 30_000,
+        TxPayload::MultisigCreate { .. } => 60_000,
+        TxPayload::MultisigApprove { .. } => 30_000,
+        TxPayload::MultisigExecute { .. } => 60_000,
+        TxPayload::VestingCreate { .. } => 40_000,
         _ => 50_000,
     }
 }
 
+/// Cuts `bps` of the validator's stake, pro rata across its delegations,
+/// and moves the penalty to the treasury. Returns the amount slashed.
+fn slash_validator(chain: &mut ChainState, validator: &Address, bps: u16) -> anyhow::Result<u128> {
+    let Some(v) = chain.validators.values_mut().find(|v| v.owner == *validator) else {
+        anyhow::bail!("validator not found");
+    };
+    let stake_before = v.stake;
+    let penalty = cut_stake(v, bps)?;
+    let validator_id = v.id;
+    chain.slash_delegations(&validator_id, penalty, stake_before);
+    chain.fee_pools.treasury = chain.fee_pools.treasury.saturating_add(penalty);
+    Ok(penalty)
+}
+
+/// `slash_validator` against the store's entries.
+async fn slash_validator_in_store<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    validator: &Address,
+    bps: u16,
+) -> anyhow::Result<u128> {
+    let Some(mut v) = validator_of(ctx, validator).await? else {
+        anyhow::bail!("validator not found");
+    };
+    let stake_before = v.stake;
+    let penalty = cut_stake(&mut v, bps)?;
+    let mut delegations = ctx.state.get_section::<sections::Delegations>().await?;
+    let mut rewards = ctx.state.get_entry::<sections::ValidatorRewards>(&v.id).await?;
+    slash_positions(&mut delegations, rewards.as_mut(), &v.id, penalty, stake_before);
+    ctx.state.put_section::<sections::Delegations>(delegations).await?;
+    if let Some(rewards) = rewards {
+        ctx.state
+            .put_entry::<sections::ValidatorRewards>(v.id, rewards)
+            .await?;
+    }
+    ctx.state.put_validator(v).await?;
+    let mut pools = ctx.state.get_fee_pools().await?;
+    pools.treasury = pools.treasury.saturating_add(penalty);
+    ctx.state.put_fee_pools(pools).await?;
+    Ok(penalty)
+}
+
+/// Takes `bps` of `v`'s stake, jailing it once none is left. Returns the
+/// penalty.
+fn cut_stake(v: &mut Validator, bps: u16) -> anyhow::Result<u128> {
+    if v.stake == 0 {
+        anyhow::bail!("validator has no stake to slash");
+    }
+    let penalty = v.stake.saturating_mul(bps.min(10_000) as u128) / 10_000;
+    if penalty == 0 {
+        anyhow::bail!("penalty too small");
+    }
+    v.stake = v.stake.saturating_sub(penalty);
+    if v.stake == 0 {
+        v.status = ValidatorStatus::Jailed;
+    }
+    Ok(penalty)
+}
+
+/// The validator `owner` runs.
+async fn validator_of<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    owner: &Address,
+) -> anyhow::Result<Option<Validator>> {
+    let validators = ctx.state.get_section::<sections::Validators>().await?;
+    Ok(validators.into_values().find(|v| v.owner == *owner))
+}
+
+/// `domain_id`'s registry entry.
+async fn domain_entry<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+) -> anyhow::Result<state::DomainEntry> {
+    ctx.state
+        .get_entry::<sections::Domains>(domain_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("domain not registered"))
+}
+
 fn effective_gas_price(tx: &Tx, base_fee: u128) -> anyhow::Result<u128> {
     if let Some(max_fee) = tx.max_fee {
         let priority = tx.max_priority_fee.unwrap_or(0);
@@ -1196,21 +3328,72 @@ fn effective_gas_price(tx: &Tx, base_fee: u128) -> anyhow::Result<u128> {
     }
 }
 
-fn ensure_funds(account: &Account, amount: u128, gas_fee: u128) -> anyhow::Result<()> {
+/// Checks `account` can pay `amount` plus `gas_fee` without touching its
+/// `locked` (unvested) balance.
+fn ensure_funds(
+    account: &Account,
+    locked: u128,
+    amount: u128,
+    gas_fee: u128,
+) -> anyhow::Result<()> {
     let total = amount
         .checked_add(gas_fee)
         .ok_or_else(|| anyhow::anyhow!("overflow"))?;
     if account.balance_x < total {
         anyhow::bail!("insufficient funds");
     }
+    if account.balance_x - total < locked {
+        anyhow::bail!("insufficient funds: {} is still vesting", locked);
+    }
     Ok(())
 }
 
-fn route_gas_fee(chain: &mut ChainState, gas_fee: u128, split: &FeeSplit) {
+/// Splits a tx's gas fee between the treasury and validators, by the fee
+/// split governance set if any.
+pub(crate) async fn route_gas_fee_in_store<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    gas_fee: u128,
+) -> anyhow::Result<()> {
+    let overrides = ctx.state.get_param_overrides().await?;
+    let mut pools = ctx.state.get_fee_pools().await?;
+    credit_gas_fee(&mut pools, gas_fee, overrides.fee_split.as_ref().unwrap_or(&ctx.fee_split));
+    ctx.state.put_fee_pools(pools).await
+}
+
+fn credit_gas_fee(pools: &mut FeePools, gas_fee: u128, split: &FeeSplit) {
     let burn = gas_fee.saturating_mul(split.l1_gas_burn_pct as u128) / 100;
     let validators = gas_fee.saturating_mul(split.l1_gas_validators_pct as u128) / 100;
-    chain.fee_pools.l1_gas = chain.fee_pools.l1_gas.saturating_add(validators);
-    chain.fee_pools.treasury = chain.fee_pools.treasury.saturating_add(burn);
+    pools.l1_gas = pools.l1_gas.saturating_add(validators);
+    pools.treasury = pools.treasury.saturating_add(burn);
+}
+
+/// Splits a domain's gas fee by the L2 shares of the fee split: the
+/// sequencer share is owed to the domain's sequencers, the DA share goes to
+/// the DA pool and the rest, the domain's L1 rent, to validators.
+async fn route_domain_fee<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: Uuid,
+    fee: u128,
+) -> anyhow::Result<()> {
+    let overrides = ctx.state.get_param_overrides().await?;
+    let split = overrides.fee_split.as_ref().unwrap_or(&ctx.fee_split);
+    let sequencer = fee.saturating_mul(split.l2_sequencer_pct as u128) / 100;
+    let da = fee.saturating_mul(split.l2_da_costs_pct as u128) / 100;
+    let rent = fee.saturating_sub(sequencer).saturating_sub(da);
+    if sequencer > 0 {
+        let owed = ctx
+            .state
+            .get_entry::<sections::SequencerFees>(&domain_id)
+            .await?
+            .unwrap_or_default();
+        ctx.state
+            .put_entry::<sections::SequencerFees>(domain_id, owed + sequencer)
+            .await?;
+    }
+    let mut pools = ctx.state.get_fee_pools().await?;
+    pools.da = pools.da.saturating_add(da);
+    pools.l1_gas = pools.l1_gas.saturating_add(rent);
+    ctx.state.put_fee_pools(pools).await
 }
 
 fn derive_owner_from_pubkey(pubkey: &[u8]) -> Address {
@@ -1221,10 +3404,20 @@ fn validator_id_from_pubkey(pubkey: &[u8]) -> Uuid {
     Uuid::new_v5(&Uuid::NAMESPACE_OID, pubkey)
 }
 
+/// Canonical hash of the whole block, header first. Blocks hold no floats
+/// and carry JSON as text, so their encoding can't fail.
 pub fn hash_block(block: &Block) -> Hash {
-    let bytes = bincode::serialize(block).unwrap_or_default();
-    let digest = blake3::hash(&bytes);
-    *digest.as_bytes()
+    canonical_hash(block).expect("blocks have a canonical encoding")
+}
+
+/// Hash a tx is indexed and pre-confirmed under.
+pub fn tx_hash(tx: &Tx) -> Hash {
+    canonical_hash(tx).expect("txs have a canonical encoding")
+}
+
+/// Root a header's `l1_tx_root` commits its block's txs under.
+pub fn tx_root(txs: &[Tx]) -> Hash {
+    canonical_hash(txs).expect("txs have a canonical encoding")
 }
 
 pub fn address_from_pubkey(pubkey: &[u8]) -> Address {
@@ -1253,8 +3446,17 @@ pub fn verify_signature_bytes(
     Ok(())
 }
 
-pub fn tx_signing_bytes(tx: &Tx) -> anyhow::Result<Vec<u8>> {
-    let signable = (
+/// Tagged message a transaction signature covers.
+pub fn tx_signing_bytes(tx: &Tx) -> anyhow::Result<Vec<u8>> {
+    Ok(signing_message(
+        SigningDomain::Tx,
+        &tx.chain_id,
+        &tx_payload_bytes(tx)?,
+    ))
+}
+
+fn tx_signable(tx: &Tx) -> impl Serialize + '_ {
+    (
         &tx.chain_id,
         tx.nonce,
         tx.gas_limit,
@@ -1263,16 +3465,42 @@ pub fn tx_signing_bytes(tx: &Tx) -> anyhow::Result<Vec<u8>> {
         tx.gas_price,
         &tx.payload,
         &tx.public_key,
-    );
-    Ok(bincode::serialize(&signable)?)
+    )
+}
+
+fn tx_payload_bytes(tx: &Tx) -> anyhow::Result<Vec<u8>> {
+    encode(&tx_signable(tx))
 }
 
-pub fn verify_tx_signature(tx: &Tx) -> anyhow::Result<Address> {
-    let msg = tx_signing_bytes(tx)?;
-    verify_signature_bytes(&tx.public_key, &tx.signature, &msg)?;
+/// Bytes untagged tx signatures cover, from before the canonical encoding.
+fn legacy_tx_payload_bytes(tx: &Tx) -> anyhow::Result<Vec<u8>> {
+    Ok(bincode::serialize(&tx_signable(tx))?)
+}
+
+/// Checks `tx`'s signature and returns its sender. Untagged signatures only
+/// verify with `accept_legacy`.
+pub fn verify_tx_signature(tx: &Tx, accept_legacy: bool) -> anyhow::Result<Address> {
+    verify_in_domain_or_legacy(
+        &tx.public_key,
+        &tx.signature,
+        SigningDomain::Tx,
+        &tx.chain_id,
+        &tx_payload_bytes(tx)?,
+        &legacy_tx_payload_bytes(tx)?,
+        accept_legacy,
+    )?;
     Ok(address_from_pubkey(&tx.public_key))
 }
 
+/// Whether the chain `ctx` executes still accepts untagged signatures.
+pub(crate) async fn accepts_legacy_signatures<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+) -> anyhow::Result<bool> {
+    Ok(legacy_signatures_accepted(
+        ctx.state.get_protocol_version().await?,
+    ))
+}
+
 fn ensure_positive(amount: u128) -> anyhow::Result<()> {
     if amount == 0 {
         anyhow::bail!("amount must be > 0");
@@ -1280,32 +3508,45 @@ fn ensure_positive(amount: u128) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn ensure_privacy_pool<'a>(chain: &'a mut ChainState) -> &'a mut PrivacyPool {
-    chain
-        .privacy_pools
-        .entry("shielded".into())
-        .or_insert_with(PrivacyPool::default)
-}
+const MAX_MONIKER_LEN: usize = 70;
+const MAX_WEBSITE_LEN: usize = 140;
+const MAX_DETAILS_LEN: usize = 280;
 
-fn compute_merkle_root(commitments: &[Hash]) -> Hash {
-    if commitments.is_empty() {
-        return [0u8; 32];
+/// The pool named `id`. The default pool is created on first use; others
+/// must be registered through governance.
+async fn privacy_pool<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    id: &str,
+) -> anyhow::Result<PrivacyPool> {
+    let pool = ctx
+        .state
+        .get_entry::<sections::PrivacyPools>(&id.to_string())
+        .await?;
+    let mut pool = match pool {
+        Some(pool) => pool,
+        None if id == DEFAULT_PRIVACY_POOL => PrivacyPool::default(),
+        None => anyhow::bail!("privacy pool {id} not registered"),
+    };
+    // Pools stored before the incremental tree have commitments but no tree.
+    if pool.tree.len() != pool.commitments.len() as u64 {
+        pool.tree = CommitmentTree::from_leaves(&pool.commitments)?;
+        pool.merkle_root = pool.tree.root();
     }
-    let mut leaves: Vec<Hash> = commitments
-        .iter()
-        .map(|c| *blake3::hash(c).as_bytes())
-        .collect();
-    leaves.sort();
-    let mut hasher = blake3::Hasher::new();
-    for leaf in leaves {
-        hasher.update(&leaf);
+    Ok(pool)
+}
+
+fn ensure_denomination(pool: &PrivacyPool, amount: u128) -> anyhow::Result<()> {
+    match pool.denomination {
+        Some(denomination) if denomination != amount => {
+            anyhow::bail!("pool only takes notes of {denomination}")
+        }
+        _ => Ok(()),
     }
-    *hasher.finalize().as_bytes()
 }
 
-fn snapshot_validator_weights(chain: &ChainState) -> HashMap<Address, u128> {
+fn snapshot_validator_weights(validators: &HashMap<Uuid, Validator>) -> HashMap<Address, u128> {
     let mut weights = HashMap::new();
-    for v in chain.validators.values() {
+    for v in validators.values() {
         weights.insert(v.owner, v.stake);
     }
     weights
@@ -1381,6 +3622,11 @@ fn validate_domain_risk(params: &serde_json::Value) -> anyhow::Result<()> {
             anyhow::bail!("risk_cap must be > 0");
         }
     }
+    WasmLimits::from_risk_params(params)?;
+    DisputeParams::from_risk_params(params)?;
+    SequencerParams::from_risk_params(params)?;
+    message_timeout_blocks(params)?;
+    domain_gas_price(params)?;
     Ok(())
 }
 
@@ -1394,17 +3640,195 @@ fn commitments_equal(a: &Option<Commitments>, b: &Option<Commitments>) -> bool {
     }
 }
 
-async fn sync_accounts_from_store<S: StateStore>(
+/// Rejects `artifact` unless it carries the key governance activated for its
+/// program. Programs without an active key are left to the backend.
+pub fn check_verification_key(
+    keys: &VerificationKeyRegistry,
+    artifact: &ProofArtifact,
+) -> anyhow::Result<()> {
+    let program = artifact.program_id.to_string();
+    let Some(expected) = keys.active_key(&program) else {
+        return Ok(());
+    };
+    match &artifact.verification_key {
+        Some(vk) if vk.as_slice() == expected => Ok(()),
+        Some(_) => anyhow::bail!("proof verification key is not the registered {program} key"),
+        None => anyhow::bail!("proof has no verification key; {program} requires one"),
+    }
+}
+
+async fn proof_mode<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+) -> anyhow::Result<ProofMode> {
+    Ok(ctx
+        .state
+        .get_entry::<sections::Domains>(domain_id)
+        .await?
+        .map_or(ProofMode::Optimistic, |d| d.proof_mode))
+}
+
+/// Commits a root the L1 computed itself for an optimistic domain. Nothing
+/// can challenge it, so withdrawals may prove against it straight away.
+async fn commit_executed_root<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    state_root: Hash,
+    proof_meta: serde_json::Value,
+    height: u64,
+) -> anyhow::Result<()> {
+    let batch_height = batch_height(ctx, domain_id).await?;
+    ctx.state
+        .put_entry::<sections::DomainRoots>(
+            *domain_id,
+            state::DomainRoot {
+                domain_id: *domain_id,
+                state_root,
+                da_root: [0u8; 32],
+                last_verified_epoch: height,
+                proof_meta,
+                batch_height,
+            },
+        )
+        .await?;
+    ctx.state
+        .put_entry::<sections::FinalizedDomainRoots>(*domain_id, state_root)
+        .await
+}
+
+/// Follows an executed call's root on optimistic domains, whose roots are
+/// not only advanced by proven batches.
+async fn track_domain_root<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    proof_mode: ProofMode,
+    receipt: &DomainExecutionReceipt,
+    height: u64,
+) -> anyhow::Result<()> {
+    if proof_mode == ProofMode::Optimistic {
+        commit_executed_root(
+            ctx,
+            &receipt.domain_id,
+            receipt.state_root,
+            serde_json::json!({ "trace": receipt.trace }),
+            height,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+fn inbox_event(domain_id: &Uuid, receipt: &InboxReceipt) -> Event {
+    let status = if receipt.success { "ok" } else { "failed" };
+    Event::new("inbox_message")
+        .with("domain_id", domain_id)
+        .with("from_domain", receipt.from)
+        .with("nonce", receipt.nonce)
+        .with("status", status)
+}
+
+fn ensure_domain_active(entry: &state::DomainEntry) -> anyhow::Result<()> {
+    if entry.status != DomainStatus::Active {
+        anyhow::bail!("domain is {:?}", entry.status);
+    }
+    Ok(())
+}
+
+async fn ensure_bridge_open<S: StateStore>(
     ctx: &ExecutionContext<S>,
-    chain: &mut ChainState,
+    domain_id: &Uuid,
+) -> anyhow::Result<()> {
+    if ctx
+        .state
+        .get_entry::<sections::BridgeEscrows>(domain_id)
+        .await?
+        .is_some_and(|e| e.limits.paused)
+    {
+        anyhow::bail!("bridge is paused for this domain");
+    }
+    Ok(())
+}
+
+/// Takes `amount` out of `escrow` within its bridge limits, counting it
+/// towards `epoch`'s outflow.
+fn debit_bridge_escrow(
+    escrow: &mut state::BridgeEscrow,
+    amount: u128,
+    epoch: u64,
 ) -> anyhow::Result<()> {
-    let current = ctx.state.get_chain_state().await?;
-    chain.accounts = current.accounts;
+    let limits = &escrow.limits;
+    if limits.paused {
+        anyhow::bail!("bridge is paused for this domain");
+    }
+    if limits.max_withdrawal > 0 && amount > limits.max_withdrawal {
+        anyhow::bail!(
+            "withdrawal exceeds the bridge limit of {} per tx",
+            limits.max_withdrawal
+        );
+    }
+    if escrow.outflow_epoch != epoch {
+        escrow.outflow_epoch = epoch;
+        escrow.epoch_outflow = 0;
+    }
+    let outflow = escrow.epoch_outflow.saturating_add(amount);
+    if limits.epoch_outflow_cap > 0 && outflow > limits.epoch_outflow_cap {
+        anyhow::bail!(
+            "bridge outflow cap of {} per epoch exceeded ({} already withdrawn)",
+            limits.epoch_outflow_cap,
+            escrow.epoch_outflow
+        );
+    }
+    if escrow.balance < amount {
+        anyhow::bail!("bridge escrow cannot cover withdrawal");
+    }
+    escrow.balance -= amount;
+    escrow.epoch_outflow = outflow;
     Ok(())
 }
 
+async fn batch_height<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+) -> anyhow::Result<u64> {
+    Ok(ctx
+        .state
+        .get_entry::<sections::DomainRoots>(domain_id)
+        .await?
+        .map_or(0, |r| r.batch_height))
+}
+
+/// Checks a rollup proof for the claimed roots and returns the batch
+/// commitment it proves.
+async fn verify_rollup_batch<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    keys: &VerificationKeyRegistry,
+    domain_id: Uuid,
+    state_root: Hash,
+    da_root: Hash,
+    artifact: &ProofArtifact,
+) -> anyhow::Result<Hash> {
+    let batch_commitment =
+        zk_program_rollup::check_claim(artifact, domain_id, state_root, da_root)?;
+    check_verification_key(keys, artifact)?;
+
+    if let Some(zk) = ctx.zk.clone() {
+        zk.verify(artifact)
+            .await
+            .map_err(|e| anyhow::anyhow!("zk verification failed: {e}"))?;
+        return Ok(batch_commitment);
+    }
+
+    // The batch bytes live in DA, so a stub proof can only be checked
+    // against the commitments above.
+    if artifact.backend == "stub" {
+        return Ok(batch_commitment);
+    }
+
+    anyhow::bail!("no zk backend configured for rollup verification")
+}
+
 async fn verify_privacy_withdraw<S: StateStore>(
     ctx: &ExecutionContext<S>,
+    keys: &VerificationKeyRegistry,
     input: &zk_program_privacy::PrivacyWithdrawInput,
     artifact: &ProofArtifact,
 ) -> anyhow::Result<()> {
@@ -1415,6 +3839,7 @@ async fn verify_privacy_withdraw<S: StateStore>(
     if !commitments_equal(&artifact.commitments, &Some(commitments.clone())) {
         anyhow::bail!("proof commitments mismatch");
     }
+    check_verification_key(keys, artifact)?;
 
     if let Some(zk) = ctx.zk.clone() {
         zk.verify(artifact)
@@ -1485,16 +3910,146 @@ async fn credit_payouts<S: StateStore>(
     Ok(())
 }
 
+fn is_epoch_boundary(height: u64, epoch_length_blocks: u64) -> bool {
+    epoch_length_blocks > 0 && height % epoch_length_blocks == 0
+}
+
+fn queued_exits(exit_queue: &[PendingExit], validator_id: &Uuid) -> u128 {
+    exit_queue
+        .iter()
+        .filter(|e| &e.validator_id == validator_id)
+        .map(|e| e.amount)
+        .sum()
+}
+
+/// Maximum stake that may leave the validator set in one epoch.
+pub fn exit_churn_limit(chain: &ChainState, exit_churn_bps: u16) -> u128 {
+    (total_bonded_stake(chain).saturating_mul(exit_churn_bps as u128) / 10_000).max(1)
+}
+
+/// Apply queued stake reductions in request order until the epoch's churn
+/// budget is spent. A partially served entry keeps its place at the head of
+/// the queue. Returns the number of entries that made progress.
+async fn process_exit_queue<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    current_height: u64,
+) -> anyhow::Result<usize> {
+    let mut chain = ctx.state.get_chain_state().await?;
+    if chain.exit_queue.is_empty() {
+        return Ok(0);
+    }
+    let overrides = &chain.param_overrides;
+    let churn_bps = overrides.exit_churn_bps.unwrap_or(ctx.exit_churn_bps);
+    let delay = overrides
+        .unbonding_delay_blocks
+        .unwrap_or(ctx.unbonding_delay_blocks);
+    let mut budget = exit_churn_limit(&chain, churn_bps);
+    let release_height = current_height.saturating_add(delay);
+    let mut processed = 0;
+    let mut remaining = Vec::new();
+    for mut exit in std::mem::take(&mut chain.exit_queue) {
+        if budget == 0 {
+            remaining.push(exit);
+            continue;
+        }
+        // Validators removed since the request have nothing left to release.
+        let Some(v) = chain.validators.get_mut(&exit.validator_id) else {
+            continue;
+        };
+        // Slashing may have left less stake than was requested.
+        let applied = exit.amount.min(budget).min(v.stake);
+        v.stake -= applied;
+        budget -= applied;
+        exit.amount -= applied;
+        if v.stake == 0 {
+            v.status = ValidatorStatus::Exited;
+            exit.amount = 0;
+        }
+        if applied > 0 {
+            processed += 1;
+            chain.pending_unbonds.push(Unbonding {
+                owner: exit.owner,
+                validator_id: Some(exit.validator_id),
+                amount: applied,
+                release_height,
+            });
+        }
+        if exit.amount > 0 {
+            remaining.push(exit);
+        }
+    }
+    chain.exit_queue = remaining;
+    ctx.state.put_chain_state(chain).await?;
+    Ok(processed)
+}
+
+/// Ranks active validators and candidates by stake, ties broken by id, and
+/// makes the top `max_active_validators` with at least `min_validator_stake`
+/// the active set. The rest become candidates.
+async fn rotate_active_set<S: StateStore>(ctx: &ExecutionContext<S>) -> anyhow::Result<Vec<Event>> {
+    let mut chain = ctx.state.get_chain_state().await?;
+    let params = chain.staking_params.clone();
+    let mut ranked: Vec<(u128, Uuid)> = chain
+        .validators
+        .values()
+        .filter(|v| matches!(v.status, ValidatorStatus::Active | ValidatorStatus::Candidate))
+        .map(|v| (v.stake, v.id))
+        .collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    let mut events = Vec::new();
+    for (rank, (stake, id)) in ranked.into_iter().enumerate() {
+        let active = rank < params.max_active_validators as usize
+            && stake >= params.min_validator_stake.max(1);
+        let Some(v) = chain.validators.get_mut(&id) else {
+            continue;
+        };
+        let (kind, status) = match (&v.status, active) {
+            (ValidatorStatus::Candidate, true) => ("validator_promoted", ValidatorStatus::Active),
+            (ValidatorStatus::Active, false) => ("validator_demoted", ValidatorStatus::Candidate),
+            _ => continue,
+        };
+        v.status = status;
+        events.push(
+            Event::new(kind)
+                .with_hex("validator", v.owner)
+                .with("validator_id", id)
+                .with("stake", stake),
+        );
+    }
+    ctx.state.put_chain_state(chain).await?;
+    Ok(events)
+}
+
+/// Drops history past the chain's retention limits, keeping the leaf
+/// hashes of what was dropped in the pruned-state accumulator.
+async fn prune_state<S: StateStore>(ctx: &ExecutionContext<S>) -> anyhow::Result<Option<Event>> {
+    let mut chain = ctx.state.get_chain_state().await?;
+    let epoch_ms = ctx.epoch_length_blocks.saturating_mul(ctx.block_time_ms);
+    let report = chain.prune(ctx.clock.now_ms(), epoch_ms)?;
+    if report.total() == 0 {
+        return Ok(None);
+    }
+    let event = Event::new("state_pruned")
+        .with("proposals", report.proposals)
+        .with("da_commitments", report.da_commitments)
+        .with("delegations", report.delegations)
+        .with("unbonds", report.unbonds)
+        .with_hex("accumulator_root", chain.pruned.root());
+    ctx.state.put_chain_state(chain).await?;
+    Ok(Some(event))
+}
+
 async fn process_unbondings<S: StateStore>(
     ctx: &ExecutionContext<S>,
     current_height: u64,
 ) -> anyhow::Result<()> {
-    let mut chain = ctx.state.get_chain_state().await?;
-    if chain.pending_unbonds.is_empty() {
+    let pending = ctx.state.get_section::<sections::PendingUnbonds>().await?;
+    if pending.is_empty() {
         return Ok(());
     }
-    let mut remaining = Vec::with_capacity(chain.pending_unbonds.len());
-    for entry in chain.pending_unbonds.drain(..) {
+    let mut remaining = Vec::with_capacity(pending.len());
+    for entry in pending {
         if entry.release_height > current_height {
             remaining.push(entry);
             continue;
@@ -1510,9 +4065,9 @@ async fn process_unbondings<S: StateStore>(
             .ok_or_else(|| anyhow::anyhow!("balance overflow"))?;
         ctx.state.put_account(account).await?;
     }
-    chain.pending_unbonds = remaining;
-    sync_accounts_from_store(ctx, &mut chain).await?;
-    ctx.state.put_chain_state(chain).await?;
+    ctx.state
+        .put_section::<sections::PendingUnbonds>(remaining)
+        .await?;
     Ok(())
 }
 
@@ -1525,7 +4080,12 @@ async fn apply_inflation_rewards<S: StateStore>(
     if total_stake == 0 {
         return Ok(0);
     }
-    let inflation_bps = current_inflation_bps(&chain, &ctx.reward_params);
+    let params = chain
+        .param_overrides
+        .reward_params
+        .clone()
+        .unwrap_or_else(|| ctx.reward_params.clone());
+    let inflation_bps = current_inflation_bps(&chain, &params);
     let blocks_per_year = blocks_per_year(ctx.block_time_ms);
     let mint = chain
         .total_supply
@@ -1537,18 +4097,19 @@ async fn apply_inflation_rewards<S: StateStore>(
     }
 
     let mut payouts: HashMap<Address, u128> = HashMap::new();
-    let treasury = mint.saturating_mul(ctx.reward_params.treasury_pct as u128) / 100;
+    let treasury = mint.saturating_mul(params.treasury_pct as u128) / 100;
     chain.fee_pools.treasury = chain.fee_pools.treasury.saturating_add(treasury);
     let mut distributable = mint.saturating_sub(treasury);
 
     let proposer_bonus =
-        distributable.saturating_mul(ctx.reward_params.proposer_bonus_pct as u128) / 100;
+        distributable.saturating_mul(params.proposer_bonus_pct as u128) / 100;
     if proposer_bonus > 0 {
         add_payout(&mut payouts, block.header.proposer_id, proposer_bonus);
         distributable = distributable.saturating_sub(proposer_bonus);
     }
 
     if distributable > 0 {
+        let mut delegator_pools = Vec::new();
         for v in chain.validators.values() {
             if v.stake == 0 {
                 continue;
@@ -1558,12 +4119,7 @@ async fn apply_inflation_rewards<S: StateStore>(
                 continue;
             }
 
-            let delegated_total: u128 = chain
-                .delegations
-                .iter()
-                .filter(|d| d.validator_id == v.id)
-                .map(|d| d.stake)
-                .sum();
+            let delegated_total = chain.delegated_stake(&v.id).min(v.stake);
             let self_stake = v.stake.saturating_sub(delegated_total);
 
             let delegated_reward = if v.stake > 0 {
@@ -1585,28 +4141,30 @@ async fn apply_inflation_rewards<S: StateStore>(
             add_payout(&mut payouts, v.owner, validator_reward);
 
             if delegated_total > 0 && delegator_pool > 0 {
-                for delegation in chain.delegations.iter().filter(|d| d.validator_id == v.id) {
-                    let reward = delegator_pool.saturating_mul(delegation.stake) / delegated_total;
-                    add_payout(&mut payouts, delegation.delegator, reward);
-                }
+                delegator_pools.push((v.id, delegator_pool));
             }
         }
+        // Delegators' shares accrue to their positions and are paid out
+        // when claimed.
+        for (validator_id, pool) in delegator_pools {
+            chain.distribute_delegator_rewards(&validator_id, pool);
+        }
     }
 
     credit_payouts(ctx, payouts).await?;
-    sync_accounts_from_store(ctx, &mut chain).await?;
-    chain.total_supply = chain.total_supply.saturating_add(mint);
-    chain.last_reward_height = block.header.height;
-    ctx.state.put_chain_state(chain).await?;
+    ctx.state.put_fee_pools(chain.fee_pools).await?;
+    ctx.state
+        .put_section::<sections::ValidatorRewards>(chain.validator_rewards)
+        .await?;
+    ctx.state
+        .put_section::<sections::TotalSupply>(chain.total_supply.saturating_add(mint))
+        .await?;
+    ctx.state
+        .put_section::<sections::LastRewardHeight>(block.header.height)
+        .await?;
     Ok(mint)
 }
 
-fn now_millis() -> u64 {
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap();
-    now.as_millis() as u64
-}
 
 #[cfg(test)]
 mod tests {
@@ -1650,6 +4208,13 @@ mod tests {
             reward_params: RewardParams::default(),
             unbonding_delay_blocks: default_unbonding_delay_blocks(),
             slash_penalty_bps: default_slash_penalty_bps(),
+            epoch_length_blocks: default_epoch_length_blocks(),
+            exit_churn_bps: default_exit_churn_bps(),
+            max_commission_change_per_epoch: default_max_commission_change_per_epoch(),
+            liveness_params: LivenessParams::default(),
+            staking_params: StakingParams::default(),
+            retention: RetentionParams::default(),
+            vesting: vec![],
         }
     }
 
@@ -1690,6 +4255,8 @@ mod tests {
                 TxPayload::PrivacyDeposit {
                     commitment,
                     amount: 10,
+                    pool: None,
+                    memo: None,
                 },
                 &sk,
                 0,
@@ -1715,6 +4282,9 @@ mod tests {
                 recipient: recipient_addr,
                 amount: 10,
                 commitment,
+                fee: 0,
+                relayer: None,
+                relayer_fee: 0,
             };
             let proof = zk_program_privacy::stub_withdraw_proof(&input).unwrap();
 
@@ -1726,6 +4296,9 @@ mod tests {
                     merkle_root: pool.merkle_root,
                     commitment,
                     proof,
+                    pool: None,
+                    relayer: None,
+                    relayer_fee: 0,
                 },
                 &sk,
                 sender_after.nonce,
@@ -1754,6 +4327,9 @@ mod tests {
                     merkle_root: pool.merkle_root,
                     commitment,
                     proof: proof2,
+                    pool: None,
+                    relayer: None,
+                    relayer_fee: 0,
                 },
                 &sk,
                 2,
@@ -1762,6 +4338,274 @@ mod tests {
         });
     }
 
+    #[test]
+    fn privacy_withdraw_accepts_recent_roots() {
+        let rt = TokioRuntime::new().unwrap();
+        rt.block_on(async {
+            let sk = signer();
+            let recipient = address_from_pubkey(&recipient_signer().verifying_key().to_bytes());
+            let ctx = from_genesis(default_genesis()).await.unwrap();
+
+            let nullifier = [6u8; 32];
+            let commitment =
+                zk_program_privacy::note_commitment(&nullifier, &recipient, 10, &[7u8; 32]);
+            let deposit = |commitment: Hash, nonce: u64| {
+                build_tx(
+                    TxPayload::PrivacyDeposit {
+                        commitment,
+                        amount: 10,
+                        pool: None,
+                        memo: None,
+                    },
+                    &sk,
+                    nonce,
+                )
+            };
+            apply_tx(&ctx, &deposit(commitment, 0), 0).await.unwrap();
+            let chain = ctx.state.get_chain_state().await.unwrap();
+            let old_root = chain.privacy_pools["shielded"].merkle_root;
+
+            // A later deposit moves the root on; the earlier one stays usable.
+            apply_tx(&ctx, &deposit([8u8; 32], 1), 1).await.unwrap();
+            let chain = ctx.state.get_chain_state().await.unwrap();
+            let pool = &chain.privacy_pools["shielded"];
+            assert_ne!(pool.merkle_root, old_root);
+            let witness = pool.witness(&commitment).unwrap();
+            assert_eq!(witness.root, pool.merkle_root);
+            assert!(witness.verify(&commitment));
+
+            let input = zk_program_privacy::PrivacyWithdrawInput {
+                nullifier,
+                merkle_root: old_root,
+                recipient,
+                amount: 10,
+                commitment,
+                fee: 0,
+                relayer: None,
+                relayer_fee: 0,
+            };
+            let withdraw = build_tx(
+                TxPayload::PrivacyWithdraw {
+                    nullifier,
+                    recipient,
+                    amount: 10,
+                    merkle_root: old_root,
+                    commitment,
+                    proof: zk_program_privacy::stub_withdraw_proof(&input).unwrap(),
+                    pool: None,
+                    relayer: None,
+                    relayer_fee: 0,
+                },
+                &sk,
+                2,
+            );
+            apply_tx(&ctx, &withdraw, 2).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn privacy_withdraw_charges_governance_fee() {
+        let rt = TokioRuntime::new().unwrap();
+        rt.block_on(async {
+            let sk = signer();
+            let sender = address_from_pubkey(&sk.verifying_key().to_bytes());
+            let recipient = address_from_pubkey(&recipient_signer().verifying_key().to_bytes());
+            let ctx = from_genesis(default_genesis()).await.unwrap();
+
+            let nullifier = [4u8; 32];
+            let commitment =
+                zk_program_privacy::note_commitment(&nullifier, &recipient, 10_000, &[5u8; 32]);
+            let deposit = build_tx(
+                TxPayload::PrivacyDeposit {
+                    commitment,
+                    amount: 10_000,
+                    pool: None,
+                    memo: None,
+                },
+                &sk,
+                0,
+            );
+            apply_tx(&ctx, &deposit, 0).await.unwrap();
+
+            // A queued fee proposal past its timelock.
+            let mut chain = ctx.state.get_chain_state().await.unwrap();
+            let proposal_id = Uuid::new_v4();
+            let execution = serde_json::json!({
+                "withdraw_fee_bps": 100,
+                "relayer_fee_share_bps": 5_000,
+            });
+            chain.proposals.insert(
+                proposal_id,
+                Proposal {
+                    id: proposal_id,
+                    payload: execution.clone(),
+                    kind: PRIVACY_FEE_PROPOSAL.into(),
+                    status: ProposalStatus::Queued,
+                    proposer: sender,
+                    start: 0,
+                    end: 0,
+                    eta: Some(0),
+                    snapshot_total_stake: 0,
+                    for_votes: 0,
+                    against_votes: 0,
+                    abstain_votes: 0,
+                    votes: Vec::new(),
+                    execution,
+                    voter_weights: HashMap::new(),
+                    approvals: Vec::new(),
+                    deposit: 0,
+                },
+            );
+            ctx.state.put_chain_state(chain).await.unwrap();
+            let execute = build_tx(TxPayload::GovernanceExecute { proposal_id }, &sk, 1);
+            apply_tx(&ctx, &execute, 1).await.unwrap();
+
+            let chain = ctx.state.get_chain_state().await.unwrap();
+            let pool = chain.privacy_pools.get("shielded").cloned().unwrap();
+            assert_eq!(pool.withdraw_fee_bps, 100);
+            assert_eq!(pool.relayer_fee_share_bps, 5_000);
+
+            let withdraw = |fee: u128, nonce: u64| {
+                let input = zk_program_privacy::PrivacyWithdrawInput {
+                    nullifier,
+                    merkle_root: pool.merkle_root,
+                    recipient,
+                    amount: 10_000,
+                    commitment,
+                    fee,
+                    relayer: None,
+                    relayer_fee: 0,
+                };
+                build_tx(
+                    TxPayload::PrivacyWithdraw {
+                        nullifier,
+                        recipient,
+                        amount: 10_000,
+                        merkle_root: pool.merkle_root,
+                        commitment,
+                        proof: zk_program_privacy::stub_withdraw_proof(&input).unwrap(),
+                        pool: None,
+                        relayer: None,
+                        relayer_fee: 0,
+                    },
+                    &sk,
+                    nonce,
+                )
+            };
+            // A proof that ignores the fee does not verify.
+            assert!(apply_tx(&ctx, &withdraw(0, 2), 2).await.is_err());
+
+            let sender_before = ctx.state.get_account(&sender).await.unwrap().unwrap();
+            apply_tx(&ctx, &withdraw(100, 2), 2).await.unwrap();
+            let sender_after = ctx.state.get_account(&sender).await.unwrap().unwrap();
+            let recipient_account = ctx.state.get_account(&recipient).await.unwrap().unwrap();
+            assert_eq!(recipient_account.balance_x, 9_900);
+
+            let gas_fee = sender_before.balance_x + 50 - sender_after.balance_x;
+            let chain_after = ctx.state.get_chain_state().await.unwrap();
+            assert_eq!(
+                chain_after.fee_pools.treasury - chain.fee_pools.treasury,
+                gas_fee * 30 / 100 + 50
+            );
+        });
+    }
+
+    #[test]
+    fn privacy_withdraw_pays_the_bound_relayer() {
+        let rt = TokioRuntime::new().unwrap();
+        rt.block_on(async {
+            let sk = signer();
+            let recipient = address_from_pubkey(&recipient_signer().verifying_key().to_bytes());
+            let relayer_sk = SigningKey::from_bytes(&[3u8; 32]);
+            let relayer = address_from_pubkey(&relayer_sk.verifying_key().to_bytes());
+            let ctx = from_genesis(default_genesis()).await.unwrap();
+            ctx.state
+                .put_account(Account {
+                    address: relayer,
+                    nonce: 0,
+                    balance_x: 1_000_000,
+                    code_hash: None,
+                    storage_root: None,
+                    assets: Default::default(),
+                })
+                .await
+                .unwrap();
+
+            let nullifier = [4u8; 32];
+            let commitment =
+                zk_program_privacy::note_commitment(&nullifier, &recipient, 10_000, &[5u8; 32]);
+            let deposit = build_tx(
+                TxPayload::PrivacyDeposit {
+                    commitment,
+                    amount: 10_000,
+                    pool: None,
+                    memo: None,
+                },
+                &sk,
+                0,
+            );
+            apply_tx(&ctx, &deposit, 0).await.unwrap();
+            let merkle_root =
+                ctx.state.get_chain_state().await.unwrap().privacy_pools["shielded"].merkle_root;
+
+            let withdraw = |proven_relayer: Address, relayer_fee: u128| {
+                let input = zk_program_privacy::PrivacyWithdrawInput {
+                    nullifier,
+                    merkle_root,
+                    recipient,
+                    amount: 10_000,
+                    commitment,
+                    fee: 0,
+                    relayer: Some(proven_relayer),
+                    relayer_fee,
+                };
+                build_tx(
+                    TxPayload::PrivacyWithdraw {
+                        nullifier,
+                        recipient,
+                        amount: 10_000,
+                        merkle_root,
+                        commitment,
+                        proof: zk_program_privacy::stub_withdraw_proof(&input).unwrap(),
+                        pool: None,
+                        relayer: Some(relayer),
+                        relayer_fee,
+                    },
+                    &relayer_sk,
+                    0,
+                )
+            };
+            // The proof names another relayer, so this one cannot claim it.
+            assert!(apply_tx(&ctx, &withdraw([8u8; 32], 300), 1).await.is_err());
+            assert!(apply_tx(&ctx, &withdraw(relayer, 10_001), 1).await.is_err());
+
+            let outcome = apply_tx(&ctx, &withdraw(relayer, 300), 1).await.unwrap();
+            let recipient_account = ctx.state.get_account(&recipient).await.unwrap().unwrap();
+            assert_eq!(recipient_account.balance_x, 9_700);
+            let relayer_account = ctx.state.get_account(&relayer).await.unwrap().unwrap();
+            let gas_fee = outcome.gas_used as u128;
+            assert_eq!(relayer_account.balance_x, 1_000_000 - gas_fee + 300);
+        });
+    }
+
+    #[test]
+    fn privacy_fee_proposals_are_validated() {
+        let rt = TokioRuntime::new().unwrap();
+        rt.block_on(async {
+            let sk = signer();
+            let ctx = from_genesis(default_genesis()).await.unwrap();
+            let proposal = build_tx(
+                TxPayload::GovernanceProposal {
+                    payload: serde_json::json!({ "withdraw_fee_bps": 20_000 }),
+                    kind: Some(PRIVACY_FEE_PROPOSAL.into()),
+                },
+                &sk,
+                0,
+            );
+            assert!(apply_tx(&ctx, &proposal, 0).await.is_err());
+        });
+    }
+
     #[test]
     fn unstake_uses_unbonding_delay() {
         let rt = TokioRuntime::new().unwrap();
@@ -1772,6 +4616,8 @@ mod tests {
 
             let mut ctx = bootstrap_state();
             ctx.unbonding_delay_blocks = 1;
+            ctx.epoch_length_blocks = 1;
+            ctx.exit_churn_bps = 10_000;
             ctx.state
                 .put_account(Account {
                     address: owner,
@@ -1779,6 +4625,7 @@ mod tests {
                     balance_x: 1_000_000,
                     code_hash: None,
                     storage_root: None,
+                    assets: Default::default(),
                 })
                 .await
                 .unwrap();
@@ -1797,6 +4644,8 @@ mod tests {
                     gas_used: 0,
                     gas_limit: 30_000_000,
                     base_fee: 1,
+                    snapshot_root: None,
+                    protocol_version: 0,
                     consensus_metadata: serde_json::json!({}),
                 },
                 transactions: vec![stake_tx],
@@ -1818,6 +4667,8 @@ mod tests {
                     gas_used: 0,
                     gas_limit: 30_000_000,
                     base_fee: 1,
+                    snapshot_root: None,
+                    protocol_version: 0,
                     consensus_metadata: serde_json::json!({}),
                 },
                 transactions: vec![unstake_tx],
@@ -1844,6 +4695,8 @@ mod tests {
                     gas_used: 0,
                     gas_limit: 30_000_000,
                     base_fee: 1,
+                    snapshot_root: None,
+                    protocol_version: 0,
                     consensus_metadata: serde_json::json!({}),
                 },
                 transactions: vec![],
@@ -1854,5 +4707,38 @@ mod tests {
             assert!(after_release.balance_x >= 850_000 - 2);
         });
     }
-}
 
+    #[test]
+    fn blocks_with_json_fields_roundtrip_through_bincode() {
+        let create = TxPayload::DomainCreate {
+            domain_id: Uuid::nil(),
+            params: serde_json::json!({"kind": "wasm", "gas_price": 2}),
+        };
+        let block = Block {
+            header: BlockHeader {
+                parent_hash: [0u8; 32],
+                height: 1,
+                timestamp: 0,
+                proposer_id: [0u8; 32],
+                state_root: [0u8; 32],
+                l1_tx_root: [0u8; 32],
+                da_commitment: None,
+                domain_roots: vec![],
+                gas_used: 0,
+                gas_limit: 30_000_000,
+                base_fee: 1,
+                snapshot_root: None,
+                protocol_version: 0,
+                consensus_metadata: serde_json::json!({"view": 3}),
+            },
+            transactions: vec![build_tx(create, &signer(), 0)],
+            da_blobs: vec![],
+        };
+        let decoded: Block = bincode::deserialize(&bincode::serialize(&block).unwrap()).unwrap();
+        assert_eq!(hash_block(&decoded), hash_block(&block));
+        assert_eq!(decoded.header.consensus_metadata["view"], 3);
+        // JSON keeps the values inline.
+        let json = serde_json::to_value(&decoded.transactions[0].payload).unwrap();
+        assert_eq!(json["DomainCreate"]["params"]["gas_price"], 2);
+    }
+}
@@ -0,0 +1,264 @@
+//! Light clients of external chains. A client starts from a trusted header
+//! and the validator set that signs the next one; every update must carry
+//! `threshold` signatures of the current set and rotates to the set the new
+//! header commits to. Packets the external chain committed to under an
+//! accepted header's state root are delivered into domain inboxes, from a
+//! source id derived from the client id.
+
+use serde::{Deserialize, Serialize};
+use state::{sections, LightClient, LightClientHeader, StateStore};
+use uuid::Uuid;
+
+use crate::{
+    verify_signature_bytes, CrossDomainMessage, DomainProof, Event, ExecutionContext, Hash,
+};
+
+/// Headers a light client keeps; older ones can no longer prove packets.
+pub const MAX_LIGHT_CLIENT_HEADERS: usize = 256;
+
+const HEADER_TAG: &str = "kova/light-client-header/v1";
+
+/// Packet an external chain sends to a Kova domain. Sequences count from
+/// zero per destination, and the packet is dropped once the Kova chain is
+/// past `timeout_height`, unless that is zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossDomainPacket {
+    /// Chain id of the sending chain.
+    pub src_domain: String,
+    /// Id of the receiving domain.
+    pub dst_domain: String,
+    pub sequence: u64,
+    #[serde(with = "crate::json_value")]
+    pub payload: serde_json::Value,
+    pub timeout_height: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorSet {
+    /// Ed25519 public keys.
+    pub validators: Vec<Vec<u8>>,
+    pub threshold: u32,
+}
+
+impl ValidatorSet {
+    /// Hash headers commit to; independent of key order.
+    pub fn hash(&self) -> Hash {
+        let mut keys = self.validators.clone();
+        keys.sort();
+        let bytes = bincode::serialize(&(self.threshold, keys)).unwrap_or_default();
+        *blake3::hash(&bytes).as_bytes()
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.validators.iter().any(|key| key.len() != 32) {
+            anyhow::bail!("validator keys must be 32-byte ed25519 keys");
+        }
+        let mut keys = self.validators.clone();
+        keys.sort();
+        keys.dedup();
+        if keys.len() != self.validators.len() {
+            anyhow::bail!("duplicate validator key");
+        }
+        if self.threshold == 0 || self.threshold as usize > keys.len() {
+            anyhow::bail!("threshold must be between 1 and the number of validators");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderSignature {
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+/// Bytes an external validator signs to attest `header` of `chain_id`.
+pub fn header_signing_bytes(chain_id: &str, header: &LightClientHeader) -> Vec<u8> {
+    let mut msg = HEADER_TAG.as_bytes().to_vec();
+    msg.push(0);
+    msg.extend_from_slice(&(chain_id.len() as u32).to_le_bytes());
+    msg.extend_from_slice(chain_id.as_bytes());
+    msg.extend_from_slice(&bincode::serialize(header).unwrap_or_default());
+    msg
+}
+
+/// Leaf a packet commitment occupies under the external chain's state root.
+pub fn packet_leaf(packet: &CrossDomainPacket) -> Hash {
+    let bytes = bincode::serialize(packet).unwrap_or_default();
+    *blake3::hash(&bytes).as_bytes()
+}
+
+/// Whether `proof` shows `packet` was committed under `header`.
+pub fn verify_packet_proof(
+    packet: &CrossDomainPacket,
+    header: &LightClientHeader,
+    proof: &DomainProof,
+) -> bool {
+    proof.leaf == packet_leaf(packet) && proof.verify(&header.state_root)
+}
+
+/// Source id the client's packets carry in domain inboxes; acks to it queue
+/// in domain outboxes for relayers to carry back.
+pub fn client_domain_id(client_id: &str) -> Uuid {
+    let name = format!("kova/light-client/{client_id}");
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+}
+
+pub(crate) async fn create<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    client_id: &str,
+    chain_id: &str,
+    header: &LightClientHeader,
+    validators: &ValidatorSet,
+) -> anyhow::Result<Event> {
+    if client_id.is_empty() || chain_id.is_empty() {
+        anyhow::bail!("client and chain ids must not be empty");
+    }
+    let client_key = client_id.to_string();
+    if ctx
+        .state
+        .get_entry::<sections::LightClients>(&client_key)
+        .await?
+        .is_some()
+    {
+        anyhow::bail!("light client {client_id} already exists");
+    }
+    if ctx
+        .state
+        .get_entry::<sections::Domains>(&client_domain_id(client_id))
+        .await?
+        .is_some()
+    {
+        anyhow::bail!("client id collides with a domain");
+    }
+    validators.check()?;
+    if header.validator_set_hash != validators.hash() {
+        anyhow::bail!("header does not commit to the validator set");
+    }
+    ctx.state
+        .put_entry::<sections::LightClients>(
+            client_key,
+            LightClient {
+                client_id: client_id.to_string(),
+                chain_id: chain_id.to_string(),
+                validators: validators.validators.clone(),
+                threshold: validators.threshold,
+                headers: [(header.height, header.clone())].into(),
+            },
+        )
+        .await?;
+    Ok(Event::new("light_client_create")
+        .with("client_id", client_id)
+        .with("chain_id", chain_id)
+        .with("height", header.height))
+}
+
+/// Accepts `header` once `threshold` of the client's validators signed it.
+/// A header committing to another validator set must come with it.
+pub(crate) async fn update<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    client_id: &str,
+    header: &LightClientHeader,
+    signatures: &[HeaderSignature],
+    next_validators: Option<&ValidatorSet>,
+) -> anyhow::Result<Event> {
+    let mut client = ctx
+        .state
+        .get_entry::<sections::LightClients>(&client_id.to_string())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("unknown light client"))?;
+    if header.height <= client.latest_height() {
+        anyhow::bail!("header is not newer than height {}", client.latest_height());
+    }
+    let msg = header_signing_bytes(&client.chain_id, header);
+    let mut signers: Vec<&[u8]> = signatures
+        .iter()
+        .filter(|sig| client.validators.contains(&sig.public_key))
+        .filter(|sig| verify_signature_bytes(&sig.public_key, &sig.signature, &msg).is_ok())
+        .map(|sig| sig.public_key.as_slice())
+        .collect();
+    signers.sort();
+    signers.dedup();
+    if signers.len() < client.threshold as usize {
+        anyhow::bail!(
+            "header has {} of {} required signatures",
+            signers.len(),
+            client.threshold
+        );
+    }
+    let current = ValidatorSet {
+        validators: client.validators.clone(),
+        threshold: client.threshold,
+    };
+    if header.validator_set_hash != current.hash() {
+        let next = next_validators
+            .ok_or_else(|| anyhow::anyhow!("header rotates the validator set; provide it"))?;
+        next.check()?;
+        if header.validator_set_hash != next.hash() {
+            anyhow::bail!("header does not commit to the validator set");
+        }
+        client.validators = next.validators.clone();
+        client.threshold = next.threshold;
+    }
+    client.headers.insert(header.height, header.clone());
+    while client.headers.len() > MAX_LIGHT_CLIENT_HEADERS {
+        client.headers.pop_first();
+    }
+    let signers = signers.len();
+    ctx.state
+        .put_entry::<sections::LightClients>(client_id.to_string(), client)
+        .await?;
+    Ok(Event::new("light_client_update")
+        .with("client_id", client_id)
+        .with("height", header.height)
+        .with("signers", signers))
+}
+
+/// Checks `packet` against the client's header at `height` and returns it
+/// as a message for its destination's inbox.
+pub(crate) async fn receive_packet<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    client_id: &str,
+    height: u64,
+    packet: &CrossDomainPacket,
+    proof: &DomainProof,
+    current_height: u64,
+) -> anyhow::Result<CrossDomainMessage> {
+    let client = ctx
+        .state
+        .get_entry::<sections::LightClients>(&client_id.to_string())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("unknown light client"))?;
+    let header = client
+        .headers
+        .get(&height)
+        .ok_or_else(|| anyhow::anyhow!("light client has no header at height {height}"))?;
+    if packet.src_domain != client.chain_id {
+        anyhow::bail!("packet is not from the client's chain");
+    }
+    let to = Uuid::parse_str(&packet.dst_domain)
+        .map_err(|_| anyhow::anyhow!("packet destination is not a domain id"))?;
+    if ctx
+        .state
+        .get_entry::<sections::Domains>(&to)
+        .await?
+        .is_none()
+    {
+        anyhow::bail!("packet destination not registered");
+    }
+    if packet.timeout_height != 0 && current_height > packet.timeout_height {
+        anyhow::bail!("packet timed out");
+    }
+    if !verify_packet_proof(packet, header, proof) {
+        anyhow::bail!("packet is not committed under the header's state root");
+    }
+    Ok(CrossDomainMessage {
+        from: client_domain_id(client_id),
+        to,
+        nonce: packet.sequence,
+        fee: 0,
+        payload: packet.payload.clone(),
+        timeout_height: packet.timeout_height,
+        ack: None,
+    })
+}
@@ -0,0 +1,140 @@
+//! Downtime jailing. Proposers attach a `LivenessReport` to each block's
+//! consensus metadata; every active validator is expected to vote in the
+//! reported QC and to propose in the views it leads. A validator whose uptime
+//! over a window falls below the threshold is jailed with a small penalty and
+//! may `Unjail` once the cooldown has passed.
+
+use serde::{Deserialize, Serialize};
+use state::{ChainState, StateStore, ValidatorStatus};
+use uuid::Uuid;
+
+use crate::{slash_validator, Block, Event, ExecutionContext};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LivenessReport {
+    /// View of the QC the block extends.
+    pub qc_view: u64,
+    /// Validators whose votes that QC aggregates.
+    pub signers: Vec<Uuid>,
+    /// `(view, leader)` for views that timed out without a proposal.
+    pub missed_proposals: Vec<(u64, Uuid)>,
+}
+
+impl LivenessReport {
+    /// The report carried in `consensus_metadata["liveness"]`, if any.
+    pub fn from_block(block: &Block) -> Option<Self> {
+        let value = block.header.consensus_metadata.get("liveness")?;
+        serde_json::from_value(value.clone()).ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LivenessParams {
+    /// Votes and proposals expected of a validator per window.
+    pub window: u64,
+    /// Uptime, in basis points, below which a validator is jailed.
+    pub min_uptime_bps: u16,
+    pub downtime_penalty_bps: u16,
+    /// Blocks a jailed validator waits before it may unjail.
+    pub jail_cooldown_blocks: u64,
+}
+
+impl Default for LivenessParams {
+    fn default() -> Self {
+        Self {
+            window: 100,
+            min_uptime_bps: 5_000,
+            downtime_penalty_bps: 10,
+            jail_cooldown_blocks: 600,
+        }
+    }
+}
+
+/// Counts the block's report against each active validator and jails those
+/// that finished a window below the uptime threshold.
+pub(crate) async fn apply_liveness_report<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    block: &Block,
+) -> anyhow::Result<Vec<Event>> {
+    let Some(report) = LivenessReport::from_block(block) else {
+        return Ok(Vec::new());
+    };
+    let mut chain = ctx.state.get_chain_state().await?;
+    let counted = |view: u64| chain.liveness_view.is_none_or(|last| view > last);
+    let signed = counted(report.qc_view).then_some(&report.signers);
+    let missed_proposals: Vec<Uuid> = report
+        .missed_proposals
+        .iter()
+        .filter(|(view, _)| counted(*view))
+        .map(|(_, leader)| *leader)
+        .collect();
+    let last_view = report
+        .missed_proposals
+        .iter()
+        .map(|(view, _)| *view)
+        .chain([report.qc_view])
+        .max();
+    chain.liveness_view = chain.liveness_view.max(last_view);
+
+    let mut active: Vec<Uuid> = chain
+        .validators
+        .values()
+        .filter(|v| matches!(v.status, ValidatorStatus::Active) && v.stake > 0)
+        .map(|v| v.id)
+        .collect();
+    active.sort();
+
+    let params = &ctx.liveness_params;
+    let mut events = Vec::new();
+    for id in active {
+        let record = chain.liveness.entry(id).or_default();
+        if let Some(signers) = signed {
+            record.expected += 1;
+            if !signers.contains(&id) {
+                record.missed += 1;
+            }
+        }
+        let skipped = missed_proposals.iter().filter(|l| **l == id).count() as u64;
+        record.expected += skipped;
+        record.missed += skipped;
+        if record.expected < params.window.max(1) {
+            continue;
+        }
+        let uptime_bps = (record.expected - record.missed.min(record.expected))
+            .saturating_mul(10_000)
+            / record.expected;
+        record.expected = 0;
+        record.missed = 0;
+        if uptime_bps >= params.min_uptime_bps as u64 {
+            continue;
+        }
+        if let Some(event) = jail_for_downtime(&mut chain, &id, block.header.height, params) {
+            events.push(event.with("uptime_bps", uptime_bps));
+        }
+    }
+    ctx.state.put_chain_state(chain).await?;
+    Ok(events)
+}
+
+fn jail_for_downtime(
+    chain: &mut ChainState,
+    id: &Uuid,
+    height: u64,
+    params: &LivenessParams,
+) -> Option<Event> {
+    let owner = chain.validators.get(id)?.owner;
+    // A stake too small to cut still gets jailed.
+    let penalty = slash_validator(chain, &owner, params.downtime_penalty_bps).unwrap_or(0);
+    let jailed_until = height.saturating_add(params.jail_cooldown_blocks);
+    if let Some(v) = chain.validators.get_mut(id) {
+        v.status = ValidatorStatus::Jailed;
+    }
+    chain.liveness.entry(*id).or_default().jailed_until = Some(jailed_until);
+    Some(
+        Event::new("validator_jailed")
+            .with_hex("validator", owner)
+            .with("validator_id", id)
+            .with("penalty", penalty)
+            .with("jailed_until", jailed_until),
+    )
+}
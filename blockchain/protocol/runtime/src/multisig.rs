@@ -0,0 +1,97 @@
+//! Execution of approved multisig calls. Authorization and gas are handled
+//! by the `Multisig*` arms of `execute_tx`.
+
+use state::{sections, Address, MultisigCall, StateStore};
+
+use crate::{default_account, Event, ExecutionContext};
+
+/// Runs `call` from the multisig at `address`. Balances are checked before
+/// anything is written.
+pub(crate) async fn apply_call<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    address: Address,
+    proposal_id: u64,
+    call: &MultisigCall,
+) -> anyhow::Result<Event> {
+    let event = Event::new("multisig_execute")
+        .with_hex("multisig", address)
+        .with("proposal_id", proposal_id);
+    match call {
+        MultisigCall::Transfer { to, amount } => {
+            let mut account = ctx
+                .state
+                .get_account(&address)
+                .await?
+                .unwrap_or_else(|| default_account(address));
+            account.balance_x = account
+                .balance_x
+                .checked_sub(*amount)
+                .ok_or_else(|| anyhow::anyhow!("insufficient multisig funds"))?;
+            ctx.state.put_account(account).await?;
+            let mut to_account = ctx
+                .state
+                .get_account(to)
+                .await?
+                .unwrap_or_else(|| default_account(*to));
+            to_account.balance_x = to_account
+                .balance_x
+                .checked_add(*amount)
+                .ok_or_else(|| anyhow::anyhow!("overflow"))?;
+            ctx.state.put_account(to_account).await?;
+            Ok(event
+                .with("call", "transfer")
+                .with_hex("recipient", to)
+                .with("amount", amount))
+        }
+        MultisigCall::AssetTransfer {
+            asset_id,
+            to,
+            amount,
+        } => {
+            if ctx
+                .state
+                .get_entry::<sections::Assets>(asset_id)
+                .await?
+                .is_none()
+            {
+                anyhow::bail!("asset not found");
+            }
+            let mut account = ctx
+                .state
+                .get_account(&address)
+                .await?
+                .unwrap_or_else(|| default_account(address));
+            account.debit_asset(asset_id, *amount)?;
+            ctx.state.put_account(account).await?;
+            let mut to_account = ctx
+                .state
+                .get_account(to)
+                .await?
+                .unwrap_or_else(|| default_account(*to));
+            to_account.credit_asset(*asset_id, *amount)?;
+            ctx.state.put_account(to_account).await?;
+            Ok(event
+                .with("call", "asset_transfer")
+                .with("asset_id", asset_id)
+                .with_hex("recipient", to)
+                .with("amount", amount))
+        }
+        MultisigCall::SetSigners { signers, threshold } => {
+            state::validate_signers(signers, *threshold)?;
+            let Some(mut multisig) = ctx.state.get_entry::<sections::Multisigs>(&address).await?
+            else {
+                anyhow::bail!("multisig not found");
+            };
+            multisig.signers = signers.clone();
+            multisig.threshold = *threshold;
+            multisig.pending.clear();
+            ctx.state
+                .put_entry::<sections::Multisigs>(address, multisig)
+                .await?;
+            Ok(event
+                .with("call", "set_signers")
+                .with("signers", signers.len())
+                .with("threshold", threshold))
+        }
+    }
+}
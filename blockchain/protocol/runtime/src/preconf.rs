@@ -0,0 +1,339 @@
+//! Sequencer pre-confirmations. A sequencer accepting a tx signs a promise to
+//! include it in one of the domain's next batches. Once the domain has moved
+//! past the promised batches, anyone holding the promise can challenge it
+//! with a bond, and the sequencer must answer within the response window
+//! with the blob of a recorded batch that carries the tx. An answered
+//! challenge pays the bond to the sequencer; an unanswered one refunds the
+//! challenger and slashes the sequencer's bond.
+
+use std::collections::HashMap;
+
+use ed25519_dalek::SigningKey;
+use serde::{Deserialize, Serialize};
+use state::{sections, Address, BatchRecord, Hash, PreconfDispute, StateStore};
+use uuid::Uuid;
+
+use crate::domains::calls_root;
+use crate::{
+    add_payout, address_from_pubkey, batch_height, credit_payouts, disputes, sequencers,
+    sign_in_domain, signing_message, verify_signature_bytes, Event, ExecutionContext,
+    SigningDomain, Tx, TxPayload,
+};
+
+/// Batches kept per domain for challenges to be answered against.
+pub const BATCH_RECORD_LIMIT: usize = 256;
+
+/// A sequencer's signed promise to include `tx_hash` in a batch of
+/// `domain_id` after `domain_head` and no later than `expiry`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Preconfirmation {
+    pub chain_id: String,
+    pub domain_id: Uuid,
+    pub tx_hash: Hash,
+    /// Domain batch height when the tx was accepted.
+    pub domain_head: u64,
+    /// Last batch height the tx may land in.
+    pub expiry: u64,
+    pub sequencer_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl Preconfirmation {
+    /// Promises inclusion within `batches` batches after `domain_head`.
+    pub fn sign(
+        signing_key: &SigningKey,
+        chain_id: &str,
+        domain_id: Uuid,
+        tx_hash: Hash,
+        domain_head: u64,
+        batches: u64,
+    ) -> Self {
+        let mut preconf = Self {
+            chain_id: chain_id.to_string(),
+            domain_id,
+            tx_hash,
+            domain_head,
+            expiry: domain_head.saturating_add(batches),
+            sequencer_key: signing_key.verifying_key().to_bytes().to_vec(),
+            signature: Vec::new(),
+        };
+        preconf.signature = sign_in_domain(
+            signing_key,
+            SigningDomain::SoftReceipt,
+            chain_id,
+            &preconf.signing_bytes(),
+        );
+        preconf
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        bincode::serialize(&(self.domain_id, self.tx_hash, self.domain_head, self.expiry))
+            .unwrap_or_default()
+    }
+
+    /// Checks the signature and returns the signing sequencer's address.
+    /// Pre-confirmations postdate tagged signing, so no untagged form is
+    /// accepted.
+    pub fn verify(&self) -> anyhow::Result<Address> {
+        let msg = signing_message(
+            SigningDomain::SoftReceipt,
+            &self.chain_id,
+            &self.signing_bytes(),
+        );
+        verify_signature_bytes(&self.sequencer_key, &self.signature, &msg)?;
+        Ok(address_from_pubkey(&self.sequencer_key))
+    }
+}
+
+/// Records a batch `domain_id` advanced by, dropping the oldest beyond
+/// `BATCH_RECORD_LIMIT` along with resolved challenges that could no longer
+/// be raised.
+pub(crate) async fn record_batch<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    record: BatchRecord,
+) -> anyhow::Result<()> {
+    let mut records = ctx
+        .state
+        .get_entry::<sections::BatchRecords>(domain_id)
+        .await?
+        .unwrap_or_default();
+    records.retain(|r| r.batch_height < record.batch_height);
+    records.push(record);
+    if records.len() > BATCH_RECORD_LIMIT {
+        records.drain(..records.len() - BATCH_RECORD_LIMIT);
+    }
+    let oldest = records[0].batch_height;
+    ctx.state
+        .put_entry::<sections::BatchRecords>(*domain_id, records)
+        .await?;
+    let stale: Vec<Hash> = ctx
+        .state
+        .get_section::<sections::PreconfDisputes>()
+        .await?
+        .into_values()
+        .filter(|d| d.resolved && d.domain_id == *domain_id && d.domain_head + 1 < oldest)
+        .map(|d| d.tx_hash)
+        .collect();
+    for tx_hash in stale {
+        ctx.state
+            .remove_entry::<sections::PreconfDisputes>(&tx_hash)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Forgets `domain_id`'s batches above `batch_height` once they are rolled
+/// back, so they cannot answer a challenge.
+pub(crate) async fn forget_batches_above<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    batch_height: u64,
+) -> anyhow::Result<()> {
+    if let Some(mut records) = ctx
+        .state
+        .get_entry::<sections::BatchRecords>(domain_id)
+        .await?
+    {
+        records.retain(|r| r.batch_height <= batch_height);
+        ctx.state
+            .put_entry::<sections::BatchRecords>(*domain_id, records)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Checks that `blob` is `domain_id`'s recorded batch at `batch_height` and
+/// carries the tx hashed `tx_hash`.
+pub(crate) async fn check_batch_carries<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    batch_height: u64,
+    blob: &[u8],
+    tx_hash: &Hash,
+) -> anyhow::Result<()> {
+    let record = ctx
+        .state
+        .get_entry::<sections::BatchRecords>(domain_id)
+        .await?
+        .and_then(|records| records.into_iter().find(|r| r.batch_height == batch_height))
+        .ok_or_else(|| anyhow::anyhow!("batch is not on record"))?;
+    let txs: Vec<Tx> = zk_program_rollup::decode_batch(blob)?;
+    let tx = txs
+        .iter()
+        .find(|tx| crate::tx_hash(tx) == *tx_hash)
+        .ok_or_else(|| anyhow::anyhow!("batch does not carry the tx"))?;
+    if record.optimistic {
+        // A claim commits only to the domain's calls, so only one of those
+        // proves inclusion.
+        let committed = match &tx.payload {
+            TxPayload::DomainExecute(call) => call.domain_id == *domain_id,
+            _ => false,
+        };
+        if !committed {
+            anyhow::bail!("tx is not a call the claim commits to");
+        }
+        if calls_root(&disputes::batch_calls(domain_id, blob)?) != record.commitment {
+            anyhow::bail!("blob does not match the batch");
+        }
+    } else if zk_program_rollup::hash_blob(blob) != record.commitment {
+        anyhow::bail!("blob does not match the batch");
+    }
+    Ok(())
+}
+
+/// Opens a challenge of `preconf`, bonded with `bond`, that the sequencer
+/// must answer within the domain's response window.
+pub(crate) async fn challenge<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    preconf: &Preconfirmation,
+    challenger: Address,
+    bond: u128,
+    height: u64,
+) -> anyhow::Result<Event> {
+    if preconf.chain_id != ctx.chain_id {
+        anyhow::bail!("pre-confirmation is for another chain");
+    }
+    let sequencer = preconf.verify()?;
+    let domain_id = &preconf.domain_id;
+    let bonded = ctx
+        .state
+        .get_entry::<sections::SequencerBonds>(domain_id)
+        .await?
+        .is_some_and(|bonds| bonds.iter().any(|b| b.sequencer == sequencer));
+    if !bonded {
+        anyhow::bail!("pre-confirmation signer is not a bonded sequencer");
+    }
+    if preconf.expiry <= preconf.domain_head {
+        anyhow::bail!("pre-confirmation covers no batch");
+    }
+    if batch_height(ctx, domain_id).await? <= preconf.expiry {
+        anyhow::bail!("pre-confirmation has not expired");
+    }
+    let answerable = ctx
+        .state
+        .get_entry::<sections::BatchRecords>(domain_id)
+        .await?
+        .and_then(|records| records.first().map(|first| first.batch_height))
+        .is_some_and(|first| first <= preconf.domain_head + 1);
+    if !answerable {
+        anyhow::bail!("pre-confirmation is too old to challenge");
+    }
+    if ctx
+        .state
+        .get_entry::<sections::PreconfDisputes>(&preconf.tx_hash)
+        .await?
+        .is_some()
+    {
+        anyhow::bail!("pre-confirmation was already challenged");
+    }
+    let deadline = height + disputes::params(ctx, domain_id).await?.response_blocks;
+    ctx.state
+        .put_entry::<sections::PreconfDisputes>(
+            preconf.tx_hash,
+            PreconfDispute {
+                domain_id: *domain_id,
+                tx_hash: preconf.tx_hash,
+                sequencer,
+                challenger,
+                bond,
+                domain_head: preconf.domain_head,
+                expiry: preconf.expiry,
+                deadline,
+                resolved: false,
+            },
+        )
+        .await?;
+    Ok(Event::new("preconf_challenge")
+        .with_hex("challenger", challenger)
+        .with_hex("sequencer", sequencer)
+        .with("domain_id", domain_id)
+        .with_hex("tx_hash", preconf.tx_hash)
+        .with("deadline", deadline))
+}
+
+/// Answers the challenge of `tx_hash` with `blob`, the batch at
+/// `batch_height`, and pays the challenger's bond to the sequencer.
+pub(crate) async fn fulfill<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    tx_hash: &Hash,
+    batch_height: u64,
+    blob: &[u8],
+    height: u64,
+    payouts: &mut HashMap<Address, u128>,
+) -> anyhow::Result<Event> {
+    let mut dispute = ctx
+        .state
+        .get_entry::<sections::PreconfDisputes>(tx_hash)
+        .await?
+        .filter(|d| !d.resolved)
+        .ok_or_else(|| anyhow::anyhow!("no open pre-confirmation challenge"))?;
+    if height > dispute.deadline {
+        anyhow::bail!("challenge is past its deadline");
+    }
+    if batch_height <= dispute.domain_head || batch_height > dispute.expiry {
+        anyhow::bail!("batch is outside the pre-confirmed range");
+    }
+    check_batch_carries(ctx, &dispute.domain_id, batch_height, blob, tx_hash).await?;
+    dispute.resolved = true;
+    add_payout(payouts, dispute.sequencer, dispute.bond);
+    let event = Event::new("preconf_fulfilled")
+        .with_hex("sequencer", dispute.sequencer)
+        .with("domain_id", dispute.domain_id)
+        .with_hex("tx_hash", tx_hash)
+        .with("batch_height", batch_height);
+    ctx.state
+        .put_entry::<sections::PreconfDisputes>(*tx_hash, dispute)
+        .await?;
+    Ok(event)
+}
+
+/// Resolves challenges past their deadline against the sequencer: the
+/// challenger is refunded and the sequencer's bond slashed.
+pub(crate) async fn settle<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    height: u64,
+) -> anyhow::Result<Vec<Event>> {
+    let mut expired: Vec<PreconfDispute> = ctx
+        .state
+        .get_section::<sections::PreconfDisputes>()
+        .await?
+        .into_values()
+        .filter(|d| !d.resolved && height > d.deadline)
+        .collect();
+    if expired.is_empty() {
+        return Ok(Vec::new());
+    }
+    expired.sort_by_key(|d| d.tx_hash);
+    let mut payouts = HashMap::new();
+    let mut events = Vec::new();
+    for mut dispute in expired {
+        dispute.resolved = true;
+        ctx.state
+            .put_entry::<sections::PreconfDisputes>(dispute.tx_hash, dispute.clone())
+            .await?;
+        add_payout(&mut payouts, dispute.challenger, dispute.bond);
+        events.push(
+            Event::new("preconf_violation")
+                .with_hex("sequencer", dispute.sequencer)
+                .with("domain_id", dispute.domain_id)
+                .with_hex("tx_hash", dispute.tx_hash)
+                .with("expiry", dispute.expiry),
+        );
+        let bps = sequencers::params(ctx, &dispute.domain_id)
+            .await?
+            .preconf_slash_bps;
+        events.extend(
+            sequencers::slash(
+                ctx,
+                &dispute.domain_id,
+                &dispute.sequencer,
+                bps,
+                "preconf_violation",
+            )
+            .await?,
+        );
+    }
+    credit_payouts(ctx, payouts).await?;
+    Ok(events)
+}
@@ -0,0 +1,84 @@
+//! Scheduled calls. `TxPayload::Schedule` stores a payload to run as a tx
+//! from its sender at a later height; `run_due` executes the due ones after
+//! the block's txs. Each pays its own gas at the base fee of the block it
+//! runs in and takes the owner's next nonce. A call whose owner can't pay for
+//! its gas by then is dropped.
+
+use state::{sections, StateStore, MAX_SCHEDULED_PER_BLOCK};
+
+use crate::inclusion::include_from;
+use crate::{gas_cost, Event, ExecutionContext, Tx, TxPayload};
+
+/// Encodes `inner` for storage, rejecting payloads that can't be scheduled.
+pub(crate) fn encode(inner: &TxPayload) -> anyhow::Result<Vec<u8>> {
+    if matches!(
+        inner,
+        TxPayload::Schedule { .. } | TxPayload::ScheduleCancel { .. }
+    ) {
+        anyhow::bail!("scheduled calls cannot be nested");
+    }
+    let bytes = bincode::serialize(inner)?;
+    // Payloads carrying free-form JSON don't survive bincode.
+    bincode::deserialize::<TxPayload>(&bytes)
+        .map_err(|_| anyhow::anyhow!("payload cannot be scheduled"))?;
+    Ok(bytes)
+}
+
+pub(crate) async fn run_due<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    height: u64,
+) -> anyhow::Result<Vec<Event>> {
+    let due = ctx
+        .state
+        .get_section::<sections::Schedule>()
+        .await?
+        .due(height, MAX_SCHEDULED_PER_BLOCK);
+    let mut events = Vec::new();
+    for id in due {
+        let mut schedule = ctx.state.get_section::<sections::Schedule>().await?;
+        let Some(call) = schedule.calls.remove(&id) else {
+            continue;
+        };
+        ctx.state
+            .put_section::<sections::Schedule>(schedule)
+            .await?;
+
+        let event = Event::new("scheduled_call")
+            .with("schedule_id", id)
+            .with_hex("owner", call.owner);
+        let payload: TxPayload = match bincode::deserialize(&call.payload) {
+            Ok(payload) => payload,
+            Err(err) => {
+                events.push(event.with("dropped", err));
+                continue;
+            }
+        };
+        let nonce = ctx
+            .state
+            .get_account(&call.owner)
+            .await?
+            .map_or(0, |a| a.nonce);
+        let tx = Tx {
+            chain_id: ctx.chain_id.clone(),
+            nonce,
+            gas_limit: gas_cost(&payload),
+            max_fee: None,
+            max_priority_fee: None,
+            gas_price: None,
+            payload,
+            public_key: call.public_key,
+            signature: vec![],
+        };
+        match include_from(ctx, &tx, call.owner, height).await {
+            Ok(outcome) => {
+                let event = event
+                    .with("gas_used", outcome.gas_used)
+                    .with("success", outcome.succeeded());
+                events.extend(outcome.events);
+                events.push(event);
+            }
+            Err(err) => events.push(event.with("dropped", format!("{err:#}"))),
+        }
+    }
+    Ok(events)
+}
@@ -0,0 +1,506 @@
+//! Bonded sequencers. A domain with bonded sequencers rotates posting rights
+//! between them every `rotation_blocks`, each round's leader drawn with odds
+//! proportional to its bond. Only the leader may post a batch in its round;
+//! a leader that has posted nothing `failover_blocks` into its slot is
+//! slashed and the round handed to the next sequencer drawn for it, a leader
+//! that posts nothing is slashed when the round ends, and a proven fraud
+//! slashes the claiming sequencer. Domains without bonded sequencers
+//! stay open to anyone. Slashed stake goes to the treasury.
+
+use std::collections::HashMap;
+
+use state::{sections, Address, SequencerBond, SequencerRound, StateStore};
+use uuid::Uuid;
+
+use crate::{add_payout, credit_payouts, disputes, domain_entry, Event, ExecutionContext};
+
+/// Bond and rotation settings of a domain, read from its `risk_params`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequencerParams {
+    pub min_bond: u128,
+    pub rotation_blocks: u64,
+    /// Blocks a round's leader has to post before the round fails over; 0
+    /// leaves the round with its first leader.
+    pub failover_blocks: u64,
+    pub missed_round_slash_bps: u16,
+    pub fraud_slash_bps: u16,
+    pub preconf_slash_bps: u16,
+    /// Blocks a force-included tx waits for a batch before L1 applies it.
+    pub force_include_blocks: u64,
+    pub force_include_slash_bps: u16,
+}
+
+impl Default for SequencerParams {
+    fn default() -> Self {
+        Self {
+            min_bond: 100_000,
+            rotation_blocks: 100,
+            failover_blocks: 25,
+            missed_round_slash_bps: 500,
+            fraud_slash_bps: 10_000,
+            preconf_slash_bps: 1_000,
+            force_include_blocks: 50,
+            force_include_slash_bps: 1_000,
+        }
+    }
+}
+
+impl SequencerParams {
+    /// Defaults overridden by `min_sequencer_bond`, `rotation_blocks`,
+    /// `failover_blocks`, `missed_round_slash_bps`, `fraud_slash_bps`, `preconf_slash_bps`,
+    /// `force_include_blocks` and `force_include_slash_bps` in `params`.
+    pub fn from_risk_params(params: &serde_json::Value) -> anyhow::Result<Self> {
+        let param = |key: &str, max: u64| -> anyhow::Result<Option<u64>> {
+            match params.get(key) {
+                None => Ok(None),
+                Some(value) => match value.as_u64() {
+                    Some(n) if n <= max => Ok(Some(n)),
+                    _ => anyhow::bail!("{key} must be an integer <= {max}"),
+                },
+            }
+        };
+        let mut out = Self::default();
+        if let Some(n) = param("min_sequencer_bond", u64::MAX)? {
+            out.min_bond = n as u128;
+        }
+        if let Some(n) = param("rotation_blocks", u64::MAX)? {
+            if n == 0 {
+                anyhow::bail!("rotation_blocks must be > 0");
+            }
+            out.rotation_blocks = n;
+        }
+        if let Some(n) = param("failover_blocks", u64::MAX)? {
+            out.failover_blocks = n;
+        }
+        if let Some(n) = param("missed_round_slash_bps", 10_000)? {
+            out.missed_round_slash_bps = n as u16;
+        }
+        if let Some(n) = param("fraud_slash_bps", 10_000)? {
+            out.fraud_slash_bps = n as u16;
+        }
+        if let Some(n) = param("preconf_slash_bps", 10_000)? {
+            out.preconf_slash_bps = n as u16;
+        }
+        if let Some(n) = param("force_include_blocks", u64::MAX)? {
+            if n == 0 {
+                anyhow::bail!("force_include_blocks must be > 0");
+            }
+            out.force_include_blocks = n;
+        }
+        if let Some(n) = param("force_include_slash_bps", 10_000)? {
+            out.force_include_slash_bps = n as u16;
+        }
+        Ok(out)
+    }
+}
+
+/// Index into `stakes` drawn with odds proportional to each stake. The same
+/// seed always draws the same index.
+pub fn stake_weighted_pick(stakes: &[u128], seed: &[u8]) -> Option<usize> {
+    let total = stakes.iter().fold(0u128, |acc, s| acc.saturating_add(*s));
+    if total == 0 {
+        return None;
+    }
+    let digest = blake3::hash(seed);
+    let mut draw = [0u8; 16];
+    draw.copy_from_slice(&digest.as_bytes()[..16]);
+    let mut point = u128::from_le_bytes(draw) % total;
+    for (index, stake) in stakes.iter().enumerate() {
+        if point < *stake {
+            return Some(index);
+        }
+        point -= stake;
+    }
+    None
+}
+
+/// Seed of `domain_id`'s leader draw for `round`.
+pub fn rotation_seed(domain_id: &Uuid, round: u64) -> Vec<u8> {
+    [domain_id.as_bytes().as_slice(), &round.to_le_bytes()].concat()
+}
+
+/// Index into `stakes` of `round`'s leader after `attempt` failovers. Each
+/// failover draws again among the stakes not yet drawn for the round, so
+/// attempt 0 is the round's `stake_weighted_pick` and attempts wrap once
+/// every stake has been drawn.
+pub fn failover_pick(stakes: &[u128], domain_id: &Uuid, round: u64, attempt: u32) -> Option<usize> {
+    let mut remaining: Vec<usize> = (0..stakes.len()).filter(|i| stakes[*i] > 0).collect();
+    if remaining.is_empty() {
+        return None;
+    }
+    let target = attempt as usize % remaining.len();
+    let seed = rotation_seed(domain_id, round);
+    for draw in 0..=target {
+        let draw_seed = match draw {
+            0 => seed.clone(),
+            n => [seed.as_slice(), &(n as u32).to_le_bytes()].concat(),
+        };
+        let weights: Vec<u128> = remaining.iter().map(|i| stakes[*i]).collect();
+        let index = remaining.remove(stake_weighted_pick(&weights, &draw_seed)?);
+        if draw == target {
+            return Some(index);
+        }
+    }
+    None
+}
+
+pub(crate) async fn params<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+) -> anyhow::Result<SequencerParams> {
+    SequencerParams::from_risk_params(&domain_entry(ctx, domain_id).await?.risk_params)
+}
+
+/// Bonds `amount` for `sender`, or tops up its existing bond.
+pub(crate) async fn register<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    sender: Address,
+    amount: u128,
+    height: u64,
+) -> anyhow::Result<Event> {
+    let min_bond = params(ctx, domain_id).await?.min_bond;
+    let mut bonds = ctx
+        .state
+        .get_entry::<sections::SequencerBonds>(domain_id)
+        .await?
+        .unwrap_or_default();
+    let bond = match bonds.binary_search_by(|b| b.sequencer.cmp(&sender)) {
+        Ok(index) => {
+            let existing = &mut bonds[index];
+            if existing.exiting_since.is_some() {
+                anyhow::bail!("sequencer is exiting");
+            }
+            existing.bond = existing.bond.saturating_add(amount);
+            existing.bond
+        }
+        Err(index) => {
+            if amount < min_bond {
+                anyhow::bail!("bond is below the domain minimum of {min_bond}");
+            }
+            bonds.insert(
+                index,
+                SequencerBond {
+                    domain_id: *domain_id,
+                    sequencer: sender,
+                    bond: amount,
+                    registered_at: height,
+                    exiting_since: None,
+                },
+            );
+            amount
+        }
+    };
+    ctx.state
+        .put_entry::<sections::SequencerBonds>(*domain_id, bonds)
+        .await?;
+    Ok(Event::new("sequencer_register")
+        .with_hex("sequencer", sender)
+        .with("domain_id", domain_id)
+        .with("bond", bond))
+}
+
+/// Starts `sender`'s exit; its bond stays slashable until released.
+pub(crate) async fn exit<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    sender: Address,
+    height: u64,
+) -> anyhow::Result<Event> {
+    let mut bonds = ctx
+        .state
+        .get_entry::<sections::SequencerBonds>(domain_id)
+        .await?
+        .unwrap_or_default();
+    let bond = bonds
+        .iter_mut()
+        .find(|b| b.sequencer == sender)
+        .ok_or_else(|| anyhow::anyhow!("sender is not a bonded sequencer"))?;
+    if bond.exiting_since.is_some() {
+        anyhow::bail!("sequencer is already exiting");
+    }
+    bond.exiting_since = Some(height);
+    let event = Event::new("sequencer_exit")
+        .with_hex("sequencer", sender)
+        .with("domain_id", domain_id)
+        .with("bond", bond.bond);
+    ctx.state
+        .put_entry::<sections::SequencerBonds>(*domain_id, bonds)
+        .await?;
+    Ok(event)
+}
+
+/// Cuts `bps` of `sequencer`'s bond on `domain_id` into the treasury.
+pub(crate) async fn slash<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    sequencer: &Address,
+    bps: u16,
+    reason: &str,
+) -> anyhow::Result<Option<Event>> {
+    let Some(mut bonds) = ctx
+        .state
+        .get_entry::<sections::SequencerBonds>(domain_id)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let Some(index) = bonds.iter().position(|b| b.sequencer == *sequencer) else {
+        return Ok(None);
+    };
+    let bond = &mut bonds[index];
+    let penalty = bond.bond.saturating_mul(bps.min(10_000) as u128) / 10_000;
+    if penalty == 0 {
+        return Ok(None);
+    }
+    bond.bond -= penalty;
+    if bond.bond == 0 {
+        bonds.remove(index);
+        // A leader slashed out of the set no longer holds its round.
+        if ctx
+            .state
+            .get_entry::<sections::SequencerRounds>(domain_id)
+            .await?
+            .is_some_and(|r| r.leader == *sequencer)
+        {
+            ctx.state
+                .remove_entry::<sections::SequencerRounds>(domain_id)
+                .await?;
+        }
+    }
+    ctx.state
+        .put_entry::<sections::SequencerBonds>(*domain_id, bonds)
+        .await?;
+    let mut pools = ctx.state.get_fee_pools().await?;
+    pools.treasury = pools.treasury.saturating_add(penalty);
+    ctx.state.put_fee_pools(pools).await?;
+    Ok(Some(
+        Event::new("sequencer_slashed")
+            .with_hex("sequencer", sequencer)
+            .with("domain_id", domain_id)
+            .with("amount", penalty)
+            .with("reason", reason),
+    ))
+}
+
+/// Moves `domain_id` to the round and slot containing `height`. A new
+/// round slashes the previous leader if it never posted; a new slot of an
+/// unposted round slashes its leader and fails over to the next draw. Draws
+/// are among the bonds that are not exiting.
+async fn roll_round<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    params: &SequencerParams,
+    height: u64,
+) -> anyhow::Result<Vec<Event>> {
+    let round = height / params.rotation_blocks;
+    let attempt = match params.failover_blocks {
+        0 => 0,
+        blocks => ((height % params.rotation_blocks) / blocks) as u32,
+    };
+    let mut events = Vec::new();
+    match ctx
+        .state
+        .get_entry::<sections::SequencerRounds>(domain_id)
+        .await?
+    {
+        Some(current) if current.round == round => {
+            if current.posted || current.attempt >= attempt {
+                return Ok(events);
+            }
+            let Some(leader) = draw_leader(ctx, domain_id, params, round, attempt).await? else {
+                return Ok(events);
+            };
+            if leader != current.leader {
+                events.extend(
+                    slash(
+                        ctx,
+                        domain_id,
+                        &current.leader,
+                        params.missed_round_slash_bps,
+                        "missed_slot",
+                    )
+                    .await?,
+                );
+                events.push(
+                    Event::new("sequencer_failover")
+                        .with("domain_id", domain_id)
+                        .with("round", round)
+                        .with("attempt", attempt)
+                        .with_hex("leader", leader),
+                );
+            }
+            ctx.state
+                .put_entry::<sections::SequencerRounds>(
+                    *domain_id,
+                    SequencerRound {
+                        round,
+                        leader,
+                        posted: false,
+                        attempt,
+                    },
+                )
+                .await?;
+            return Ok(events);
+        }
+        Some(previous) => {
+            ctx.state
+                .remove_entry::<sections::SequencerRounds>(domain_id)
+                .await?;
+            if !previous.posted {
+                events.extend(
+                    slash(
+                        ctx,
+                        domain_id,
+                        &previous.leader,
+                        params.missed_round_slash_bps,
+                        "missed_round",
+                    )
+                    .await?,
+                );
+            }
+        }
+        None => {}
+    }
+    if let Some(leader) = draw_leader(ctx, domain_id, params, round, attempt).await? {
+        ctx.state
+            .put_entry::<sections::SequencerRounds>(
+                *domain_id,
+                SequencerRound {
+                    round,
+                    leader,
+                    posted: false,
+                    attempt,
+                },
+            )
+            .await?;
+        events.push(
+            Event::new("sequencer_round")
+                .with("domain_id", domain_id)
+                .with("round", round)
+                .with_hex("leader", leader),
+        );
+    }
+    Ok(events)
+}
+
+/// `round`'s leader after `attempt` failovers, drawn from the bonds that are
+/// not exiting.
+async fn draw_leader<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    params: &SequencerParams,
+    round: u64,
+    attempt: u32,
+) -> anyhow::Result<Option<Address>> {
+    let bonds = ctx
+        .state
+        .get_entry::<sections::SequencerBonds>(domain_id)
+        .await?
+        .unwrap_or_default();
+    let eligible: Vec<&SequencerBond> = bonds
+        .iter()
+        .filter(|b| b.exiting_since.is_none() && b.bond >= params.min_bond)
+        .collect();
+    let stakes: Vec<u128> = eligible.iter().map(|b| b.bond).collect();
+    Ok(failover_pick(&stakes, domain_id, round, attempt).map(|index| eligible[index].sequencer))
+}
+
+/// Checks `sender` may post a batch for `domain_id` at `height` and counts
+/// the round as posted. Anyone may post while no sequencer is eligible.
+pub(crate) async fn record_batch<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    domain_id: &Uuid,
+    sender: Address,
+    height: u64,
+) -> anyhow::Result<Vec<Event>> {
+    let params = params(ctx, domain_id).await?;
+    let events = roll_round(ctx, domain_id, &params, height).await?;
+    if let Some(mut round) = ctx
+        .state
+        .get_entry::<sections::SequencerRounds>(domain_id)
+        .await?
+    {
+        if round.leader != sender {
+            anyhow::bail!("sender is not the domain's sequencer for this round");
+        }
+        round.posted = true;
+        ctx.state
+            .put_entry::<sections::SequencerRounds>(*domain_id, round)
+            .await?;
+    }
+    Ok(events)
+}
+
+/// Rolls every bonded domain's round and releases exited bonds whose
+/// challenge window has passed, once they have no open claim and no round
+/// left to post for.
+pub(crate) async fn settle<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    height: u64,
+) -> anyhow::Result<Vec<Event>> {
+    let bonded = ctx.state.get_section::<sections::SequencerBonds>().await?;
+    let rounds = ctx.state.get_section::<sections::SequencerRounds>().await?;
+    if bonded.is_empty() && rounds.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut domain_ids: Vec<Uuid> = bonded.keys().chain(rounds.keys()).copied().collect();
+    domain_ids.sort();
+    domain_ids.dedup();
+    let mut payouts = HashMap::new();
+    let mut events = Vec::new();
+    for domain_id in domain_ids {
+        let (Ok(params), Ok(disputes)) = (
+            params(ctx, &domain_id).await,
+            disputes::params(ctx, &domain_id).await,
+        ) else {
+            continue;
+        };
+        events.extend(roll_round(ctx, &domain_id, &params, height).await?);
+        let claimant = ctx
+            .state
+            .get_entry::<sections::OptimisticClaims>(&domain_id)
+            .await?
+            .map(|claim| claim.sequencer);
+        let leader = ctx
+            .state
+            .get_entry::<sections::SequencerRounds>(&domain_id)
+            .await?
+            .map(|r| r.leader);
+        let Some(mut bonds) = ctx
+            .state
+            .get_entry::<sections::SequencerBonds>(&domain_id)
+            .await?
+        else {
+            continue;
+        };
+        bonds.retain(|bond| {
+            let released = bond.exiting_since.is_some_and(|since| {
+                height >= since + disputes.challenge_window_blocks
+                    && claimant != Some(bond.sequencer)
+                    && leader != Some(bond.sequencer)
+            });
+            if released {
+                add_payout(&mut payouts, bond.sequencer, bond.bond);
+                events.push(
+                    Event::new("sequencer_released")
+                        .with_hex("sequencer", bond.sequencer)
+                        .with("domain_id", domain_id)
+                        .with("amount", bond.bond),
+                );
+            }
+            !released
+        });
+        if bonds.is_empty() {
+            ctx.state
+                .remove_entry::<sections::SequencerBonds>(&domain_id)
+                .await?;
+        } else {
+            ctx.state
+                .put_entry::<sections::SequencerBonds>(domain_id, bonds)
+                .await?;
+        }
+    }
+    credit_payouts(ctx, payouts).await?;
+    Ok(events)
+}
@@ -0,0 +1,113 @@
+//! Domain-separated signing. Every signed artifact is prefixed with a tag
+//! naming its message type and the chain it belongs to, so a signature over
+//! one kind of message can't be replayed as another or on another chain.
+//!
+//! Untagged signatures, as produced before tagging, are still accepted when
+//! the caller passes `accept_legacy`, which follows the chain's protocol
+//! version (see `legacy_signatures_accepted`). Those cover the payload as
+//! legacy clients encoded it, which for txs and votes is bincode rather than
+//! the canonical encoding.
+
+use ed25519_dalek::SigningKey;
+
+use crate::{sign_bytes, verify_signature_bytes};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningDomain {
+    Tx,
+    Vote,
+    Proposal,
+    /// Sequencer confirmations handed out before batch inclusion.
+    SoftReceipt,
+    /// Heartbeats and batch announcements sequencers gossip to each other.
+    SequencerGossip,
+}
+
+impl SigningDomain {
+    pub fn tag(self) -> &'static str {
+        match self {
+            SigningDomain::Tx => "kova/tx/v1",
+            SigningDomain::Vote => "kova/vote/v1",
+            SigningDomain::Proposal => "kova/proposal/v1",
+            SigningDomain::SoftReceipt => "kova/soft-receipt/v1",
+            SigningDomain::SequencerGossip => "kova/sequencer-gossip/v1",
+        }
+    }
+}
+
+/// `tag || 0 || len(chain_id) as u32 LE || chain_id || payload`. The tag is
+/// NUL-terminated and the chain id length-prefixed, so no two
+/// (domain, chain, payload) triples encode to the same bytes.
+pub fn signing_message(domain: SigningDomain, chain_id: &str, payload: &[u8]) -> Vec<u8> {
+    let tag = domain.tag().as_bytes();
+    let mut msg = Vec::with_capacity(tag.len() + 5 + chain_id.len() + payload.len());
+    msg.extend_from_slice(tag);
+    msg.push(0);
+    msg.extend_from_slice(&(chain_id.len() as u32).to_le_bytes());
+    msg.extend_from_slice(chain_id.as_bytes());
+    msg.extend_from_slice(payload);
+    msg
+}
+
+/// Messages a signature over `payload` may cover, tagged form first, then
+/// the untagged `legacy` encoding with `accept_legacy`.
+pub fn accepted_messages(
+    domain: SigningDomain,
+    chain_id: &str,
+    payload: &[u8],
+    legacy: &[u8],
+    accept_legacy: bool,
+) -> Vec<Vec<u8>> {
+    let mut messages = vec![signing_message(domain, chain_id, payload)];
+    if accept_legacy {
+        messages.push(legacy.to_vec());
+    }
+    messages
+}
+
+pub fn sign_in_domain(
+    signing_key: &SigningKey,
+    domain: SigningDomain,
+    chain_id: &str,
+    payload: &[u8],
+) -> Vec<u8> {
+    sign_bytes(signing_key, &signing_message(domain, chain_id, payload))
+}
+
+pub fn verify_in_domain(
+    public_key: &[u8],
+    signature: &[u8],
+    domain: SigningDomain,
+    chain_id: &str,
+    payload: &[u8],
+    accept_legacy: bool,
+) -> anyhow::Result<()> {
+    verify_in_domain_or_legacy(
+        public_key,
+        signature,
+        domain,
+        chain_id,
+        payload,
+        payload,
+        accept_legacy,
+    )
+}
+
+/// Like `verify_in_domain`, for payloads whose legacy signatures cover a
+/// different encoding than the tagged ones.
+pub fn verify_in_domain_or_legacy(
+    public_key: &[u8],
+    signature: &[u8],
+    domain: SigningDomain,
+    chain_id: &str,
+    payload: &[u8],
+    legacy: &[u8],
+    accept_legacy: bool,
+) -> anyhow::Result<()> {
+    let tagged = signing_message(domain, chain_id, payload);
+    match verify_signature_bytes(public_key, signature, &tagged) {
+        Ok(()) => Ok(()),
+        Err(_) if accept_legacy => verify_signature_bytes(public_key, signature, legacy),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,145 @@
+//! Height-activated protocol upgrades. Governance schedules a protocol
+//! version and the height it takes effect at; from that block on, headers
+//! carry the new version and payloads gated on it become valid. A build that
+//! doesn't know the version refuses to execute past the activation height
+//! rather than diverge from upgraded nodes.
+
+use state::{ChainState, StateStore};
+
+use crate::{BlockHeader, Event, ExecutionContext, TxPayload};
+
+/// Highest protocol version this build can execute.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// First protocol version that only accepts signatures under a signing
+/// domain. Governance schedules it once clients have moved to tagged
+/// signing, so every validator stops accepting untagged ones at the same
+/// height.
+pub const TAGGED_SIGNATURES_VERSION: u32 = 1;
+
+/// A payload added by an upgrade and the protocol version that enables it.
+type PayloadGate = (fn(&TxPayload) -> bool, u32);
+
+/// Payloads introduced by upgrades. Everything not listed is valid from
+/// genesis.
+const PAYLOAD_GATES: &[PayloadGate] = &[];
+
+/// Returned by `apply_block` when the chain has moved to a protocol version
+/// this build can't execute. Nodes halt on it until the binary is upgraded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedProtocolVersion {
+    pub version: u32,
+    pub height: u64,
+}
+
+impl std::fmt::Display for UnsupportedProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "protocol version {} is active from height {}, but this build only supports up to {}",
+            self.version, self.height, PROTOCOL_VERSION
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedProtocolVersion {}
+
+fn required_version(gates: &[PayloadGate], payload: &TxPayload) -> u32 {
+    gates
+        .iter()
+        .filter(|(matches, _)| matches(payload))
+        .map(|(_, version)| *version)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Whether signatures over the bare payload still verify under
+/// `protocol_version`.
+pub fn legacy_signatures_accepted(protocol_version: u32) -> bool {
+    protocol_version < TAGGED_SIGNATURES_VERSION
+}
+
+pub(crate) fn ensure_payload_enabled(payload: &TxPayload, version: u32) -> anyhow::Result<()> {
+    let required = required_version(PAYLOAD_GATES, payload);
+    if required > version {
+        anyhow::bail!("payload requires protocol version {required}, chain is at {version}");
+    }
+    Ok(())
+}
+
+/// Version a block at `height` executes under: the active one, or the
+/// pending upgrade's once its activation height is reached. Proposers put
+/// it in the header.
+pub async fn protocol_version_at<S: StateStore>(state: &S, height: u64) -> anyhow::Result<u32> {
+    let pending = state
+        .get_pending_upgrade()
+        .await?
+        .filter(|upgrade| upgrade.activation_height <= height);
+    match pending {
+        Some(upgrade) => Ok(upgrade.version),
+        None => state.get_protocol_version().await,
+    }
+}
+
+/// Activates a pending upgrade that is due, then checks that this build
+/// supports the active version and that `header` was built for it.
+pub(crate) async fn begin_block<S: StateStore>(
+    ctx: &ExecutionContext<S>,
+    header: &BlockHeader,
+) -> anyhow::Result<Vec<Event>> {
+    let mut events = Vec::new();
+    let due = ctx
+        .state
+        .get_pending_upgrade()
+        .await?
+        .is_some_and(|upgrade| upgrade.activation_height <= header.height);
+    if due {
+        let mut chain = ctx.state.get_chain_state().await?;
+        events.extend(activate(&mut chain));
+        ctx.state.put_chain_state(chain).await?;
+    }
+    let version = ctx.state.get_protocol_version().await?;
+    if version > PROTOCOL_VERSION {
+        return Err(UnsupportedProtocolVersion {
+            version,
+            height: header.height,
+        }
+        .into());
+    }
+    if header.protocol_version != version {
+        anyhow::bail!(
+            "block is for protocol version {}, chain is at {version}",
+            header.protocol_version
+        );
+    }
+    Ok(events)
+}
+
+fn activate(chain: &mut ChainState) -> Option<Event> {
+    let upgrade = chain.pending_upgrade.take()?;
+    let previous = std::mem::replace(&mut chain.protocol_version, upgrade.version);
+    Some(
+        Event::new("protocol_upgraded")
+            .with("name", &upgrade.name)
+            .with("from_version", previous)
+            .with("version", upgrade.version)
+            .with("activation_height", upgrade.activation_height),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gated_payloads_need_their_version() {
+        let gates: &[PayloadGate] = &[(|p| matches!(p, TxPayload::Transfer { .. }), 2)];
+        let transfer = TxPayload::Transfer {
+            to: [0u8; 32],
+            amount: 1,
+        };
+        assert_eq!(required_version(gates, &transfer), 2);
+        assert_eq!(required_version(gates, &TxPayload::Unjail), 0);
+        assert!(ensure_payload_enabled(&transfer, 0).is_ok());
+    }
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use runtime::{apply_tx, bootstrap_state, gas_cost, TxPayload};
+use state::{asset_id, StateStore};
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::legacy(1_000_000, 100_000);
+
+#[tokio::test]
+async fn issuer_mints_and_holders_transfer_paying_gas_in_native_asset() {
+    let ctx = bootstrap_state();
+    let (issuer_sk, issuer) = FIXTURE.funded(&ctx, 1).await;
+    let (holder_sk, holder) = FIXTURE.funded(&ctx, 2).await;
+    let recipient = [9u8; 32];
+
+    let create = TxPayload::AssetCreate {
+        symbol: "USDK".into(),
+        decimals: 6,
+        max_supply: Some(1_000),
+    };
+    let outcome = apply_tx(&ctx, &FIXTURE.signed_tx(&issuer_sk, 0, create.clone()), 1)
+        .await
+        .unwrap();
+    let id = asset_id(&issuer, 0);
+    assert_eq!(outcome.events[0].kind, "asset_create");
+    assert_eq!(
+        outcome.events[0].attribute("asset_id"),
+        Some(id.to_string().as_str())
+    );
+
+    let mint = |to, amount| TxPayload::AssetMint {
+        asset_id: id,
+        to,
+        amount,
+    };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&holder_sk, 0, mint(holder, 10)), 1)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("only the issuer"));
+    let err = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&issuer_sk, 1, mint(holder, 1_001)),
+        1,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("max supply"));
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&issuer_sk, 1, mint(holder, 600)),
+        1,
+    )
+    .await
+    .unwrap();
+
+    let transfer = |amount| TxPayload::AssetTransfer {
+        asset_id: id,
+        to: recipient,
+        amount,
+    };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&holder_sk, 0, transfer(601)), 2)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("insufficient asset balance"));
+    apply_tx(&ctx, &FIXTURE.signed_tx(&holder_sk, 0, transfer(600)), 2)
+        .await
+        .unwrap();
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let asset = &chain.assets[&id];
+    assert_eq!(asset.supply, 600);
+    assert_eq!(asset.issuer, issuer);
+
+    // Emptied holdings are dropped; gas came out of the native balance.
+    let holder_account = ctx.state.get_account(&holder).await.unwrap().unwrap();
+    assert!(holder_account.assets.is_empty());
+    assert_eq!(
+        holder_account.balance_x,
+        1_000_000 - gas_cost(&transfer(600)) as u128
+    );
+    let recipient_account = ctx.state.get_account(&recipient).await.unwrap().unwrap();
+    assert_eq!(recipient_account.asset_balance(&id), 600);
+    assert_eq!(recipient_account.balance_x, 0);
+
+    let issuer_account = ctx.state.get_account(&issuer).await.unwrap().unwrap();
+    let gas = gas_cost(&create) + gas_cost(&mint(holder, 600));
+    assert_eq!(issuer_account.balance_x, 1_000_000 - gas as u128);
+}
+
+#[tokio::test]
+async fn asset_create_rejects_bad_metadata() {
+    let ctx = bootstrap_state();
+    let (sk, _) = FIXTURE.funded(&ctx, 1).await;
+
+    for (symbol, decimals) in [("", 6), ("NOT-ALNUM", 6), ("THIRTEENCHARS", 6), ("OK", 19)] {
+        let create = TxPayload::AssetCreate {
+            symbol: symbol.into(),
+            decimals,
+            max_supply: None,
+        };
+        assert!(apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, create), 1)
+            .await
+            .is_err());
+    }
+    assert!(ctx.state.get_chain_state().await.unwrap().assets.is_empty());
+}
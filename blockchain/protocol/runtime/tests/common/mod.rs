@@ -0,0 +1,81 @@
+//! Funded accounts and signed txs shared by the runtime integration tests.
+//! Each test file picks the balance and gas settings it runs with.
+
+#![allow(dead_code)]
+
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, sign_bytes, tx_signing_bytes, Address, ExecutionContext, Tx, TxPayload,
+};
+use state::{Account, InMemoryStateStore, StateStore};
+
+pub struct Fixture {
+    /// Balance `funded` accounts start with.
+    pub balance: u128,
+    pub gas_limit: u64,
+    /// Pay a base fee of up to 1 with no tip instead of a gas price of 1.
+    pub dynamic_fee: bool,
+}
+
+impl Fixture {
+    pub const fn legacy(balance: u128, gas_limit: u64) -> Self {
+        Self {
+            balance,
+            gas_limit,
+            dynamic_fee: false,
+        }
+    }
+
+    pub const fn dynamic(balance: u128, gas_limit: u64) -> Self {
+        Self {
+            balance,
+            gas_limit,
+            dynamic_fee: true,
+        }
+    }
+
+    /// Key derived from `seed` and the address it controls, funded with
+    /// `balance`.
+    pub async fn funded(
+        &self,
+        ctx: &ExecutionContext<InMemoryStateStore>,
+        seed: u8,
+    ) -> (SigningKey, Address) {
+        let sk = SigningKey::from_bytes(&[seed; 32]);
+        let address = address_from_pubkey(&sk.verifying_key().to_bytes());
+        ctx.state
+            .put_account(Account {
+                address,
+                nonce: 0,
+                balance_x: self.balance,
+                code_hash: None,
+                storage_root: None,
+                assets: Default::default(),
+            })
+            .await
+            .unwrap();
+        (sk, address)
+    }
+
+    pub fn signed_tx(&self, sk: &SigningKey, nonce: u64, payload: TxPayload) -> Tx {
+        let (max_fee, max_priority_fee, gas_price) = if self.dynamic_fee {
+            (Some(1), Some(0), None)
+        } else {
+            (None, None, Some(1))
+        };
+        let mut tx = Tx {
+            chain_id: "kova-devnet".into(),
+            nonce,
+            gas_limit: self.gas_limit,
+            max_fee,
+            max_priority_fee,
+            gas_price,
+            payload,
+            public_key: sk.verifying_key().to_bytes().to_vec(),
+            signature: vec![],
+        };
+        let msg = tx_signing_bytes(&tx).unwrap();
+        tx.signature = sign_bytes(sk, &msg);
+        tx
+    }
+}
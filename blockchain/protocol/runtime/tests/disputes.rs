@@ -0,0 +1,306 @@
+mod common;
+
+use ed25519_dalek::SigningKey;
+use runtime::{
+    apply_block, apply_tx, batch_calls, bootstrap_state, call_proof, calls_root, Address, Block,
+    BlockHeader, DomainCall, DomainState, ExecutionContext, StepWitness, Tx, TxPayload,
+};
+use state::{InMemoryStateStore, StateStore};
+use uuid::Uuid;
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::dynamic(10_000_000, 300_000);
+
+fn block(height: u64) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp: 0,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions: vec![],
+        da_blobs: vec![],
+    }
+}
+
+fn calls(domain_id: Uuid) -> Vec<DomainCall> {
+    (0..4)
+        .map(|i| DomainCall {
+            domain_id,
+            payload: serde_json::json!({ "step": i }),
+            raw: vec![],
+            max_gas: None,
+        })
+        .collect()
+}
+
+async fn balance(ctx: &ExecutionContext<InMemoryStateStore>, address: &Address) -> u128 {
+    ctx.state
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_x
+}
+
+/// An optimistic domain with a four-call claim from `sequencer` under
+/// dispute by `challenger`, opened at height 2.
+async fn disputed_claim(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    sequencer: &SigningKey,
+    challenger: &SigningKey,
+) -> Uuid {
+    let domain_id = Uuid::new_v4();
+    let create = TxPayload::DomainCreate {
+        domain_id,
+        params: serde_json::json!({
+            "kind": "wasm",
+            "challenge_window_blocks": 10,
+            "response_blocks": 2,
+            "claim_bond": 1_000,
+        }),
+    };
+    apply_tx(ctx, &FIXTURE.signed_tx(sequencer, 0, create), 0)
+        .await
+        .unwrap();
+    let claim = TxPayload::RollupBatchClaim {
+        domain_id,
+        blob_id: "blob-1".into(),
+        state_root: [5u8; 32],
+        calls_root: calls_root(&calls(domain_id)),
+        steps: 4,
+    };
+    apply_tx(ctx, &FIXTURE.signed_tx(sequencer, 1, claim), 1)
+        .await
+        .unwrap();
+
+    let challenge = |root: [u8; 32]| TxPayload::FraudChallenge {
+        domain_id,
+        claimed_root: root,
+        witness: serde_json::json!({}),
+    };
+    let err = apply_tx(
+        ctx,
+        &FIXTURE.signed_tx(sequencer, 2, challenge([7u8; 32])),
+        2,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("own claim"));
+    apply_tx(
+        ctx,
+        &FIXTURE.signed_tx(challenger, 0, challenge([7u8; 32])),
+        2,
+    )
+    .await
+    .unwrap();
+    domain_id
+}
+
+#[tokio::test]
+async fn unanswered_bisection_proves_fraud_and_rolls_back_the_root() {
+    let ctx = bootstrap_state();
+    let (sequencer, _) = FIXTURE.funded(&ctx, 1).await;
+    let (challenger, challenger_addr) = FIXTURE.funded(&ctx, 2).await;
+    let domain_id = disputed_claim(&ctx, &sequencer, &challenger).await;
+    let bisect = |root: [u8; 32]| TxPayload::FraudBisect {
+        domain_id,
+        midpoint_root: root,
+    };
+    let respond = |agree| TxPayload::FraudRespond { domain_id, agree };
+
+    // Only the side to move may act.
+    assert!(
+        apply_tx(&ctx, &FIXTURE.signed_tx(&challenger, 1, respond(true)), 3)
+            .await
+            .is_err()
+    );
+    let moves = [
+        (&sequencer, 2, bisect([6u8; 32])),
+        (&challenger, 1, respond(false)),
+        (&sequencer, 3, bisect([8u8; 32])),
+        (&challenger, 2, respond(true)),
+    ];
+    for (height, (sk, nonce, payload)) in (3..).zip(moves) {
+        apply_tx(&ctx, &FIXTURE.signed_tx(sk, nonce, payload), height)
+            .await
+            .unwrap();
+    }
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let dispute = chain.optimistic_claims[&domain_id].dispute.clone().unwrap();
+    assert_eq!((dispute.agreed_step, dispute.disputed_step), (1, 2));
+    assert_eq!(dispute.agreed_root, [8u8; 32]);
+
+    let err = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&sequencer, 4, bisect([9u8; 32])),
+        7,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("one call"));
+    let defend = TxPayload::FraudDefend {
+        domain_id,
+        witness: StepWitness {
+            pre_state: DomainState::default(),
+            call: calls(domain_id)[1].clone(),
+            call_proof: call_proof(&calls(domain_id), 1).unwrap(),
+        },
+    };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&sequencer, 4, defend), 7)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("agreed root"));
+
+    // The sequencer misses its deadline.
+    let before = balance(&ctx, &challenger_addr).await;
+    let result = apply_block(&ctx, &block(8)).await.unwrap();
+    let resolved = result
+        .events
+        .iter()
+        .find(|e| e.kind == "fraud_dispute_resolved")
+        .unwrap();
+    assert_eq!(resolved.attribute("outcome"), Some("fraud_proven"));
+    assert_eq!(balance(&ctx, &challenger_addr).await, before + 2_000);
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(!chain.optimistic_claims.contains_key(&domain_id));
+    let root = &chain.domain_roots[&domain_id];
+    assert_eq!(root.state_root, DomainState::default().root());
+    assert_eq!(root.batch_height, 0);
+}
+
+#[tokio::test]
+async fn silent_challengers_forfeit_and_claims_finalize() {
+    let ctx = bootstrap_state();
+    let (sequencer, sequencer_addr) = FIXTURE.funded(&ctx, 1).await;
+    let (challenger, _) = FIXTURE.funded(&ctx, 2).await;
+    let domain_id = disputed_claim(&ctx, &sequencer, &challenger).await;
+    let bisect = TxPayload::FraudBisect {
+        domain_id,
+        midpoint_root: [6u8; 32],
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sequencer, 2, bisect), 3)
+        .await
+        .unwrap();
+
+    let before = balance(&ctx, &sequencer_addr).await;
+    let result = apply_block(&ctx, &block(5)).await.unwrap();
+    assert!(result
+        .events
+        .iter()
+        .any(|e| e.attribute("outcome") == Some("defended")));
+    assert_eq!(balance(&ctx, &sequencer_addr).await, before + 1_000);
+
+    // The root stands and the bond comes back once the window closes.
+    apply_block(&ctx, &block(10)).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(chain.optimistic_claims.contains_key(&domain_id));
+    assert!(!chain.finalized_domain_roots.contains_key(&domain_id));
+    let result = apply_block(&ctx, &block(11)).await.unwrap();
+    assert!(result
+        .events
+        .iter()
+        .any(|e| e.kind == "optimistic_claim_final"));
+    assert_eq!(balance(&ctx, &sequencer_addr).await, before + 2_000);
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(chain.optimistic_claims.is_empty());
+    assert_eq!(chain.domain_roots[&domain_id].state_root, [5u8; 32]);
+    assert_eq!(chain.finalized_domain_roots[&domain_id], [5u8; 32]);
+}
+
+#[tokio::test]
+async fn withdrawals_only_prove_against_claims_past_their_window() {
+    let ctx = bootstrap_state();
+    let (sequencer, sequencer_addr) = FIXTURE.funded(&ctx, 1).await;
+    let domain_id = Uuid::new_v4();
+    let create = TxPayload::DomainCreate {
+        domain_id,
+        params: serde_json::json!({
+            "kind": "wasm",
+            "challenge_window_blocks": 10,
+            "claim_bond": 1_000,
+        }),
+    };
+    let deposit = TxPayload::RollupBridgeDeposit {
+        domain_id,
+        amount: 500,
+    };
+    // The claimed root carries a burn the domain never executed.
+    let burn = runtime::BridgeBurn {
+        owner: sequencer_addr,
+        amount: 500,
+    };
+    let mut forged = DomainState::default();
+    forged.bridge_burns.insert(0, burn.clone());
+    let claim = TxPayload::RollupBatchClaim {
+        domain_id,
+        blob_id: "blob-1".into(),
+        state_root: forged.root(),
+        calls_root: calls_root(&calls(domain_id)),
+        steps: 4,
+    };
+    for (nonce, payload) in [create, deposit, claim].into_iter().enumerate() {
+        apply_tx(
+            &ctx,
+            &FIXTURE.signed_tx(&sequencer, nonce as u64, payload),
+            1,
+        )
+        .await
+        .unwrap();
+    }
+    let withdraw = FIXTURE.signed_tx(
+        &sequencer,
+        3,
+        TxPayload::RollupBridgeWithdraw {
+            domain_id,
+            burn_nonce: 0,
+            amount: 500,
+            proof: forged.prove(DomainState::burn_leaf(0, &burn)).unwrap(),
+        },
+    );
+    let err = apply_tx(&ctx, &withdraw, 2).await.unwrap_err();
+    assert!(err.to_string().contains("no verified root"), "{err}");
+
+    // Unchallenged, the claim is final once its window closes.
+    apply_block(&ctx, &block(11)).await.unwrap();
+    apply_tx(&ctx, &withdraw, 12).await.unwrap();
+}
+
+#[test]
+fn batch_blobs_decode_to_the_calls_a_claim_commits_to() {
+    let domain_id = Uuid::new_v4();
+    let sk = SigningKey::from_bytes(&[3u8; 32]);
+    let mut txs: Vec<Tx> = calls(domain_id)
+        .into_iter()
+        .enumerate()
+        .map(|(i, call)| FIXTURE.signed_tx(&sk, i as u64, TxPayload::DomainExecute(call)))
+        .collect();
+    let elsewhere = DomainCall {
+        domain_id: Uuid::new_v4(),
+        ..calls(domain_id)[0].clone()
+    };
+    txs.insert(
+        1,
+        FIXTURE.signed_tx(&sk, 9, TxPayload::DomainExecute(elsewhere)),
+    );
+
+    let blob = zk_program_rollup::encode_batch(&txs).unwrap();
+    let json = serde_json::to_vec(&txs).unwrap();
+    for bytes in [&blob, &json] {
+        let decoded = batch_calls(&domain_id, bytes).unwrap();
+        assert_eq!(calls_root(&decoded), calls_root(&calls(domain_id)));
+    }
+    assert!(batch_calls(&domain_id, &blob[..blob.len() / 2]).is_err());
+}
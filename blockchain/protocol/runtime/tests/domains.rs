@@ -1,7 +1,11 @@
 use runtime::{
-    address_from_pubkey, apply_tx, bootstrap_state, tx_signing_bytes, DomainCall, Tx, TxPayload,
+    address_from_pubkey, apply_block, apply_tx, bootstrap_state, tx_signing_bytes, Block,
+    BlockHeader, CrossDomainMessage, DeliveryStatus, DomainCall, DomainProof,
+    DomainRootCommitment, DomainState, DomainStatus, MessageAck, Tx, TxPayload,
+    BRIDGE_LIMITS_PROPOSAL, DOMAIN_APPROVAL_PROPOSAL,
 };
 use ed25519_dalek::SigningKey;
+use state::{Account, InMemoryStateStore, StateStore};
 use uuid::Uuid;
 
 fn signer() -> SigningKey {
@@ -26,10 +30,61 @@ fn build_tx(payload: TxPayload, sk: &SigningKey, nonce: u64) -> Tx {
     tx
 }
 
+async fn funded_ctx(sk: &SigningKey) -> runtime::ExecutionContext<InMemoryStateStore> {
+    let ctx = bootstrap_state();
+    ctx.state
+        .put_account(Account {
+            address: address_from_pubkey(&sk.verifying_key().to_bytes()),
+            nonce: 0,
+            balance_x: 10_000_000,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await
+        .unwrap();
+    ctx
+}
+
+fn block(height: u64, transactions: Vec<Tx>) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp: 0,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions,
+        da_blobs: vec![],
+    }
+}
+
+/// Relays `message` with its proof against its source domain's state.
+fn relay(
+    ctx: &runtime::ExecutionContext<InMemoryStateStore>,
+    message: CrossDomainMessage,
+) -> TxPayload {
+    let state = ctx.domains.domain_state(&message.from);
+    let proof = DomainState::message_leaf(&message)
+        .and_then(|leaf| state.prove(leaf))
+        .expect("message is in its source's outbox");
+    TxPayload::CrossDomainRelay { message, proof }
+}
+
 #[tokio::test]
 async fn domain_execute_and_cross_domain_flow() {
     let sk = signer();
-    let mut ctx = bootstrap_state();
+    let ctx = funded_ctx(&sk).await;
 
     // Register a wasm domain entry via DomainCreate
     let domain_id = Uuid::new_v4();
@@ -52,12 +107,17 @@ async fn domain_execute_and_cross_domain_flow() {
     let call = DomainCall {
         domain_id,
         payload: wasm_payload,
-        raw: None,
+        raw: vec![],
         max_gas: Some(50_000),
     };
     let exec_tx = build_tx(TxPayload::DomainExecute(call), &sk, 1);
     let result = apply_tx(&ctx, &exec_tx, 1).await.unwrap();
-    assert!(result.events.contains(&"domain_execute".into()));
+    let executed = result.events.last().unwrap();
+    assert_eq!(executed.kind, "domain_execute");
+    assert_eq!(
+        executed.attribute("domain_id"),
+        Some(domain_id.to_string().as_str())
+    );
 
     // Cross-domain send/relay roundtrip
     let dest_domain = Uuid::new_v4();
@@ -85,9 +145,15 @@ async fn domain_execute_and_cross_domain_flow() {
 
     let msg = ctx.domains.outbox(&domain_id).last().cloned();
     assert!(msg.is_some());
+    let state = ctx.domains.domain_state(&domain_id);
+    let leaf = DomainState::message_leaf(msg.as_ref().unwrap()).unwrap();
+    let proof = state.prove(leaf).expect("outbox message is a state leaf");
+    assert!(proof.verify(&ctx.domains.state_root(&domain_id)));
+    assert!(!proof.verify(&[0u8; 32]));
     let relay_tx = build_tx(
         TxPayload::CrossDomainRelay {
             message: msg.unwrap(),
+            proof,
         },
         &sk,
         4,
@@ -111,3 +177,1146 @@ async fn domain_execute_and_cross_domain_flow() {
     let account = chain.accounts.get(&sender).unwrap();
     assert!(account.nonce >= 6);
 }
+
+#[tokio::test]
+async fn inbox_messages_are_processed_in_nonce_order() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let source = Uuid::new_v4();
+    let dest = Uuid::new_v4();
+    for (nonce, domain_id) in [(0, source), (1, dest)] {
+        let tx = build_tx(
+            TxPayload::DomainCreate {
+                domain_id,
+                params: serde_json::json!({"kind": "wasm"}),
+            },
+            &sk,
+            nonce,
+        );
+        apply_tx(&ctx, &tx, nonce).await.unwrap();
+    }
+
+    let payloads = [
+        serde_json::json!({
+            "action": "deploy",
+            "module_id": "relayed",
+            "code_b64": base64::encode("00"),
+        }),
+        serde_json::json!({"action": "invoke", "module_id": "missing"}),
+    ];
+    for (i, payload) in payloads.into_iter().enumerate() {
+        let nonce = 2 + i as u64;
+        let tx = build_tx(
+            TxPayload::CrossDomainSend {
+                from_domain: source,
+                to_domain: dest,
+                payload,
+                fee: 1,
+            },
+            &sk,
+            nonce,
+        );
+        apply_tx(&ctx, &tx, nonce).await.unwrap();
+    }
+    let outbox = ctx.domains.outbox(&source);
+    assert_eq!(outbox.len(), 2);
+
+    // Relays must arrive in nonce order; processing keeps that order.
+    for (i, message) in outbox.iter().enumerate() {
+        let nonce = 4 + i as u64;
+        let tx = build_tx(relay(&ctx, message.clone()), &sk, nonce);
+        apply_tx(&ctx, &tx, nonce).await.unwrap();
+    }
+    let process = build_tx(
+        TxPayload::DomainInboxProcess {
+            domain_id: dest,
+            max_messages: None,
+        },
+        &sk,
+        6,
+    );
+    let result = apply_tx(&ctx, &process, 6).await.unwrap();
+    let processed = result.events.last().unwrap();
+    assert_eq!(processed.kind, "domain_inbox_process");
+    assert_eq!(
+        processed.attribute("domain_id"),
+        Some(dest.to_string().as_str())
+    );
+
+    let receipts = ctx.domains.inbox_receipts(&dest);
+    let nonces: Vec<u64> = receipts.iter().map(|r| r.nonce).collect();
+    assert_eq!(nonces, vec![0, 1]);
+    assert!(receipts[0].success);
+    assert!(receipts[0].events.contains(&"wasm_deploy:relayed".into()));
+    assert!(!receipts[1].success);
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(
+        chain.domain_roots.get(&dest).unwrap().state_root,
+        ctx.domains.state_root(&dest)
+    );
+}
+
+/// Creates two domains and sends two messages from the first to the second.
+async fn sent_messages(
+    ctx: &runtime::ExecutionContext<InMemoryStateStore>,
+    sk: &SigningKey,
+) -> (Uuid, Vec<CrossDomainMessage>) {
+    let source = Uuid::new_v4();
+    let dest = Uuid::new_v4();
+    for (nonce, domain_id) in [(0, source), (1, dest)] {
+        let tx = build_tx(
+            TxPayload::DomainCreate {
+                domain_id,
+                params: serde_json::json!({"kind": "wasm"}),
+            },
+            sk,
+            nonce,
+        );
+        apply_tx(ctx, &tx, nonce).await.unwrap();
+    }
+    for nonce in 2..4 {
+        let tx = build_tx(
+            TxPayload::CrossDomainSend {
+                from_domain: source,
+                to_domain: dest,
+                payload: serde_json::json!({"seq": nonce}),
+                fee: 1,
+            },
+            sk,
+            nonce,
+        );
+        apply_tx(ctx, &tx, nonce).await.unwrap();
+    }
+    (dest, ctx.domains.outbox(&source))
+}
+
+#[tokio::test]
+async fn relays_are_accepted_once_and_in_nonce_order() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let (dest, outbox) = sent_messages(&ctx, &sk).await;
+
+    let out_of_order = build_tx(relay(&ctx, outbox[1].clone()), &sk, 4);
+    let err = apply_tx(&ctx, &out_of_order, 4).await.unwrap_err();
+    assert!(err.to_string().contains("out of order"), "{err}");
+    assert!(ctx.domains.domain_state(&dest).inbox.is_empty());
+
+    for (i, message) in outbox.iter().enumerate() {
+        let nonce = 4 + i as u64;
+        let tx = build_tx(relay(&ctx, message.clone()), &sk, nonce);
+        apply_tx(&ctx, &tx, nonce).await.unwrap();
+    }
+    let replay = build_tx(relay(&ctx, outbox[0].clone()), &sk, 6);
+    let err = apply_tx(&ctx, &replay, 6).await.unwrap_err();
+    assert!(err.to_string().contains("already relayed"), "{err}");
+    let inbox = ctx.domains.domain_state(&dest).inbox;
+    assert_eq!(inbox.iter().map(|m| m.nonce).collect::<Vec<_>>(), [0, 1]);
+}
+
+#[tokio::test]
+async fn relays_must_prove_the_committed_outbox() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let (_, outbox) = sent_messages(&ctx, &sk).await;
+
+    let TxPayload::CrossDomainRelay { mut message, proof } = relay(&ctx, outbox[0].clone()) else {
+        unreachable!()
+    };
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(proof.verify(&chain.domain_roots[&message.from].state_root));
+
+    message.fee = 1_000;
+    let forged = build_tx(TxPayload::CrossDomainRelay { message, proof }, &sk, 4);
+    let err = apply_tx(&ctx, &forged, 4).await.unwrap_err();
+    assert!(err.to_string().contains("committed outbox"), "{err}");
+}
+
+#[tokio::test]
+async fn bridge_deposit_mints_in_domain_and_withdraw_burns() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let sender = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let domain_id = Uuid::new_v4();
+    let create = build_tx(
+        TxPayload::DomainCreate {
+            domain_id,
+            params: serde_json::json!({"kind": "wasm"}),
+        },
+        &sk,
+        0,
+    );
+    apply_tx(&ctx, &create, 0).await.unwrap();
+
+    let deposit = build_tx(
+        TxPayload::RollupBridgeDeposit {
+            domain_id,
+            amount: 500,
+        },
+        &sk,
+        1,
+    );
+    apply_tx(&ctx, &deposit, 1).await.unwrap();
+    // Credited only once the domain consumes its inbox.
+    assert_eq!(ctx.domains.token_balance(&domain_id, &sender), 0);
+    let process = build_tx(
+        TxPayload::DomainInboxProcess {
+            domain_id,
+            max_messages: None,
+        },
+        &sk,
+        2,
+    );
+    apply_tx(&ctx, &process, 2).await.unwrap();
+    assert_eq!(ctx.domains.token_balance(&domain_id, &sender), 500);
+
+    // Relayers cannot forge bridge mints.
+    let forged = runtime::CrossDomainMessage {
+        from: runtime::L1_BRIDGE_ID,
+        to: domain_id,
+        nonce: 1,
+        fee: 0,
+        payload: serde_json::to_value(runtime::BridgeMessage::Mint {
+            recipient: sender,
+            amount: 1_000_000,
+        })
+        .unwrap(),
+        timeout_height: 0,
+        ack: None,
+    };
+    let proof = DomainProof {
+        leaf: DomainState::message_leaf(&forged).unwrap(),
+        index: 0,
+        path: vec![],
+    };
+    let relay = build_tx(
+        TxPayload::CrossDomainRelay {
+            message: forged,
+            proof,
+        },
+        &sk,
+        3,
+    );
+    assert!(apply_tx(&ctx, &relay, 3).await.is_err());
+
+    let burn = build_tx(
+        TxPayload::RollupBridgeBurn {
+            domain_id,
+            amount: 200,
+        },
+        &sk,
+        3,
+    );
+    apply_tx(&ctx, &burn, 3).await.unwrap();
+    let burned = runtime::BridgeBurn {
+        owner: sender,
+        amount: 200,
+    };
+    let proof = ctx
+        .domains
+        .domain_state(&domain_id)
+        .prove(DomainState::burn_leaf(0, &burned))
+        .unwrap();
+    let withdraw = |amount: u128, nonce: u64| {
+        build_tx(
+            TxPayload::RollupBridgeWithdraw {
+                domain_id,
+                burn_nonce: 0,
+                amount,
+                proof: proof.clone(),
+            },
+            &sk,
+            nonce,
+        )
+    };
+    // The proof only covers the amount actually burned.
+    let err = apply_tx(&ctx, &withdraw(300, 4), 4).await.unwrap_err();
+    assert!(err.to_string().contains("not for the sender's burn"), "{err}");
+
+    let before = ctx.state.get_account(&sender).await.unwrap().unwrap().balance_x;
+    let outcome = apply_tx(&ctx, &withdraw(200, 4), 4).await.unwrap();
+    let after = ctx.state.get_account(&sender).await.unwrap().unwrap().balance_x;
+    assert_eq!(after, before + 200 - outcome.gas_used as u128);
+    let err = apply_tx(&ctx, &withdraw(200, 5), 5).await.unwrap_err();
+    assert!(err.to_string().contains("already withdrawn"), "{err}");
+
+    let overdraw = build_tx(
+        TxPayload::RollupBridgeBurn {
+            domain_id,
+            amount: 301,
+        },
+        &sk,
+        5,
+    );
+    assert!(apply_tx(&ctx, &overdraw, 5).await.is_err());
+
+    // Domain supply reconciles with the escrow.
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.bridge_escrows[&domain_id].balance, 300);
+    assert_eq!(ctx.domains.token_balance(&domain_id, &sender), 300);
+    assert_eq!(ctx.domains.token_supply(&domain_id), 500 - 200);
+}
+
+/// Creates a domain, bridges `deposit` into it and burns `burn` of it back,
+/// using the four sender nonces from `nonce`. Returns the withdrawal of the
+/// burn.
+async fn bridge_round_trip(
+    ctx: &runtime::ExecutionContext<InMemoryStateStore>,
+    sk: &SigningKey,
+    domain_id: Uuid,
+    deposit: u128,
+    burn: u128,
+    nonce: u64,
+) -> TxPayload {
+    let sender = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let payloads = [
+        TxPayload::DomainCreate {
+            domain_id,
+            params: serde_json::json!({"kind": "wasm"}),
+        },
+        TxPayload::RollupBridgeDeposit {
+            domain_id,
+            amount: deposit,
+        },
+        TxPayload::DomainInboxProcess {
+            domain_id,
+            max_messages: None,
+        },
+        TxPayload::RollupBridgeBurn {
+            domain_id,
+            amount: burn,
+        },
+    ];
+    for (i, payload) in payloads.into_iter().enumerate() {
+        let n = nonce + i as u64;
+        apply_tx(ctx, &build_tx(payload, sk, n), n).await.unwrap();
+    }
+    let burn_nonce = ctx.domains.domain_state(&domain_id).next_burn_nonce - 1;
+    let proof = ctx
+        .domains
+        .domain_state(&domain_id)
+        .prove(DomainState::burn_leaf(
+            burn_nonce,
+            &runtime::BridgeBurn {
+                owner: sender,
+                amount: burn,
+            },
+        ))
+        .unwrap();
+    TxPayload::RollupBridgeWithdraw {
+        domain_id,
+        burn_nonce,
+        amount: burn,
+        proof,
+    }
+}
+
+async fn set_escrow_balance(
+    ctx: &runtime::ExecutionContext<InMemoryStateStore>,
+    domain_id: Uuid,
+    balance: u128,
+) {
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    chain.bridge_escrows.get_mut(&domain_id).unwrap().balance = balance;
+    ctx.state.put_chain_state(chain).await.unwrap();
+}
+
+async fn escrow_balance(
+    ctx: &runtime::ExecutionContext<InMemoryStateStore>,
+    domain_id: Uuid,
+) -> u128 {
+    ctx.state.get_chain_state().await.unwrap().bridge_escrows[&domain_id].balance
+}
+
+#[tokio::test]
+async fn withdrawals_fail_when_the_domain_escrow_is_short() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let domain_id = Uuid::new_v4();
+    let withdraw = bridge_round_trip(&ctx, &sk, domain_id, 500, 200, 0).await;
+    set_escrow_balance(&ctx, domain_id, 150).await;
+
+    let err = apply_tx(&ctx, &build_tx(withdraw, &sk, 4), 4)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("cannot cover"), "{err}");
+    assert_eq!(escrow_balance(&ctx, domain_id).await, 150);
+}
+
+#[tokio::test]
+async fn withdrawals_cannot_draw_on_another_domains_escrow() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let short = Uuid::new_v4();
+    let funded = Uuid::new_v4();
+    let withdraw_short = bridge_round_trip(&ctx, &sk, short, 200, 200, 0).await;
+    let withdraw_funded = bridge_round_trip(&ctx, &sk, funded, 500, 100, 4).await;
+    set_escrow_balance(&ctx, short, 50).await;
+
+    let err = apply_tx(&ctx, &build_tx(withdraw_short, &sk, 8), 8)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("cannot cover"), "{err}");
+    assert_eq!(escrow_balance(&ctx, funded).await, 500);
+
+    apply_tx(&ctx, &build_tx(withdraw_funded, &sk, 8), 8)
+        .await
+        .unwrap();
+    assert_eq!(escrow_balance(&ctx, funded).await, 400);
+    assert_eq!(escrow_balance(&ctx, short).await, 50);
+}
+
+/// Executes a governance proposal as if it passed.
+async fn execute_proposal(
+    ctx: &runtime::ExecutionContext<InMemoryStateStore>,
+    sk: &SigningKey,
+    nonce: u64,
+    kind: &str,
+    update: serde_json::Value,
+) {
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    let id = Uuid::new_v4();
+    chain.proposals.insert(
+        id,
+        state::Proposal {
+            id,
+            payload: update.clone(),
+            kind: kind.into(),
+            status: state::ProposalStatus::Queued,
+            proposer: address_from_pubkey(&sk.verifying_key().to_bytes()),
+            start: 0,
+            end: 0,
+            eta: Some(0),
+            snapshot_total_stake: 0,
+            for_votes: 0,
+            against_votes: 0,
+            abstain_votes: 0,
+            votes: Vec::new(),
+            execution: update,
+            voter_weights: Default::default(),
+            approvals: Vec::new(),
+            deposit: 0,
+        },
+    );
+    ctx.state.put_chain_state(chain).await.unwrap();
+    let execute = build_tx(TxPayload::GovernanceExecute { proposal_id: id }, sk, nonce);
+    apply_tx(ctx, &execute, 0).await.unwrap();
+}
+
+#[tokio::test]
+async fn bridge_limits_cap_outflows_and_pause_halts_the_bridge() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let sender = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let domain_id = Uuid::new_v4();
+    let setup = [
+        TxPayload::DomainCreate {
+            domain_id,
+            params: serde_json::json!({"kind": "wasm"}),
+        },
+        TxPayload::RollupBridgeDeposit {
+            domain_id,
+            amount: 1_000,
+        },
+        TxPayload::DomainInboxProcess {
+            domain_id,
+            max_messages: None,
+        },
+    ];
+    for (nonce, payload) in setup.into_iter().enumerate() {
+        apply_tx(&ctx, &build_tx(payload, &sk, nonce as u64), 0)
+            .await
+            .unwrap();
+    }
+    let limits = serde_json::json!({
+        "domain_id": domain_id,
+        "max_withdrawal": 300,
+        "epoch_outflow_cap": 500,
+    });
+    execute_proposal(&ctx, &sk, 3, BRIDGE_LIMITS_PROPOSAL, limits).await;
+    let burns = [400u128, 300, 250];
+    for (i, amount) in burns.into_iter().enumerate() {
+        let burn = TxPayload::RollupBridgeBurn { domain_id, amount };
+        apply_tx(&ctx, &build_tx(burn, &sk, 4 + i as u64), 0)
+            .await
+            .unwrap();
+    }
+    let state = ctx.domains.domain_state(&domain_id);
+    let withdraw = |burn_nonce: u64, nonce: u64| {
+        let amount = burns[burn_nonce as usize];
+        let burn = runtime::BridgeBurn {
+            owner: sender,
+            amount,
+        };
+        let payload = TxPayload::RollupBridgeWithdraw {
+            domain_id,
+            burn_nonce,
+            amount,
+            proof: state.prove(DomainState::burn_leaf(burn_nonce, &burn)).unwrap(),
+        };
+        build_tx(payload, &sk, nonce)
+    };
+
+    let err = apply_tx(&ctx, &withdraw(0, 7), 0).await.unwrap_err();
+    assert!(err.to_string().contains("per tx"), "{err}");
+    apply_tx(&ctx, &withdraw(1, 7), 0).await.unwrap();
+    let err = apply_tx(&ctx, &withdraw(2, 8), 0).await.unwrap_err();
+    assert!(err.to_string().contains("per epoch"), "{err}");
+    // The cap resets with the epoch.
+    let next_epoch = ctx.epoch_length_blocks;
+    apply_tx(&ctx, &withdraw(2, 8), next_epoch).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.bridge_escrows[&domain_id].balance, 1_000 - 300 - 250);
+
+    let pause = serde_json::json!({"domain_id": domain_id, "paused": true});
+    execute_proposal(&ctx, &sk, 9, BRIDGE_LIMITS_PROPOSAL, pause).await;
+    let deposit = TxPayload::RollupBridgeDeposit {
+        domain_id,
+        amount: 10,
+    };
+    let err = apply_tx(&ctx, &build_tx(deposit, &sk, 10), next_epoch)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("paused"), "{err}");
+    let limits = &ctx.state.get_chain_state().await.unwrap().bridge_escrows[&domain_id].limits;
+    assert_eq!(limits.max_withdrawal, 300);
+    assert!(limits.paused);
+}
+
+#[tokio::test]
+async fn domains_lock_a_bond_and_follow_their_lifecycle() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let owner = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let other = SigningKey::from_bytes(&[4u8; 32]);
+    ctx.state
+        .put_account(Account {
+            address: address_from_pubkey(&other.verifying_key().to_bytes()),
+            nonce: 0,
+            balance_x: 1_000_000,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await
+        .unwrap();
+    let balance = || async {
+        ctx.state
+            .get_account(&owner)
+            .await
+            .unwrap()
+            .unwrap()
+            .balance_x
+    };
+    let domain_id = Uuid::new_v4();
+    let create =
+        |domain_id, params: serde_json::Value| TxPayload::DomainCreate { domain_id, params };
+    let bond = ctx
+        .state
+        .get_chain_state()
+        .await
+        .unwrap()
+        .governance_params
+        .domain_registration_bond;
+    let before = balance().await;
+    let outcome = apply_tx(
+        &ctx,
+        &build_tx(create(domain_id, serde_json::json!({"kind": "wasm"})), &sk, 0),
+        0,
+    )
+    .await
+    .unwrap();
+    assert_eq!(balance().await, before - bond - outcome.gas_used as u128);
+    let err = apply_tx(
+        &ctx,
+        &build_tx(create(domain_id, serde_json::json!({"kind": "wasm"})), &other, 0),
+        0,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("already registered"), "{err}");
+
+    let err = apply_tx(&ctx, &build_tx(TxPayload::DomainPause { domain_id }, &other, 0), 0)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("only the domain owner"), "{err}");
+    apply_tx(&ctx, &build_tx(TxPayload::DomainPause { domain_id }, &sk, 1), 0)
+        .await
+        .unwrap();
+    let call = TxPayload::DomainExecute(DomainCall {
+        domain_id,
+        payload: serde_json::json!({"action": "noop"}),
+        raw: vec![],
+        max_gas: None,
+    });
+    let err = apply_tx(&ctx, &build_tx(call, &sk, 2), 0)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Paused"), "{err}");
+    let deposit = TxPayload::RollupBridgeDeposit {
+        domain_id,
+        amount: 10,
+    };
+    assert!(apply_tx(&ctx, &build_tx(deposit, &sk, 2), 0).await.is_err());
+    apply_tx(&ctx, &build_tx(TxPayload::DomainResume { domain_id }, &sk, 2), 0)
+        .await
+        .unwrap();
+
+    let before = balance().await;
+    let outcome = apply_tx(&ctx, &build_tx(TxPayload::DomainRetire { domain_id }, &sk, 3), 0)
+        .await
+        .unwrap();
+    assert_eq!(balance().await, before + bond - outcome.gas_used as u128);
+    assert!(apply_tx(&ctx, &build_tx(TxPayload::DomainResume { domain_id }, &sk, 4), 0)
+        .await
+        .is_err());
+
+    // With approval required, shared-security domains wait for governance.
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    chain.governance_params.shared_security_approval = true;
+    ctx.state.put_chain_state(chain).await.unwrap();
+    let (shared, own) = (Uuid::new_v4(), Uuid::new_v4());
+    let params = serde_json::json!({"kind": "wasm", "security_model": "own"});
+    apply_tx(&ctx, &build_tx(create(own, params), &sk, 4), 0)
+        .await
+        .unwrap();
+    apply_tx(
+        &ctx,
+        &build_tx(create(shared, serde_json::json!({"kind": "wasm"})), &sk, 5),
+        0,
+    )
+    .await
+    .unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.domains[&own].status, DomainStatus::Active);
+    assert_eq!(chain.domains[&shared].status, DomainStatus::Pending);
+    let approval = serde_json::json!({ "domain_id": shared });
+    execute_proposal(&ctx, &sk, 6, DOMAIN_APPROVAL_PROPOSAL, approval).await;
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.domains[&shared].status, DomainStatus::Active);
+}
+
+#[tokio::test]
+async fn batch_commits_record_only_proven_roots() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let domain_id = Uuid::new_v4();
+    let create_tx = build_tx(
+        TxPayload::DomainCreate {
+            domain_id,
+            params: serde_json::json!({"kind": "wasm"}),
+        },
+        &sk,
+        0,
+    );
+    apply_tx(&ctx, &create_tx, 0).await.unwrap();
+
+    let input = zk_program_rollup::RollupProofInput {
+        domain_id,
+        blob_id: "blob-1".into(),
+        da_root: [2u8; 32],
+        state_root: [3u8; 32],
+        batch_bytes: b"[]".to_vec(),
+    };
+    let commit = |state_root: [u8; 32]| TxPayload::RollupBatchCommit {
+        domain_id,
+        blob_id: input.blob_id.clone(),
+        state_root,
+        da_root: input.da_root,
+        proof: zk_program_rollup::stub_batch_proof(&input).unwrap(),
+    };
+
+    let err = apply_tx(&ctx, &build_tx(commit([9u8; 32]), &sk, 1), 1)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("claimed roots"));
+    let mut other_domain = input.clone();
+    other_domain.domain_id = Uuid::new_v4();
+    let misdirected = TxPayload::RollupBatchCommit {
+        domain_id,
+        blob_id: input.blob_id.clone(),
+        state_root: input.state_root,
+        da_root: input.da_root,
+        proof: zk_program_rollup::stub_batch_proof(&other_domain).unwrap(),
+    };
+    let err = apply_tx(&ctx, &build_tx(misdirected, &sk, 1), 1)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("another domain"));
+
+    for (nonce, height) in [(1, 5), (2, 6)] {
+        let proven = build_tx(commit(input.state_root), &sk, nonce);
+        apply_tx(&ctx, &proven, height).await.unwrap();
+    }
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let root = &chain.domain_roots[&domain_id];
+    assert_eq!(root.state_root, [3u8; 32]);
+    assert_eq!(root.da_root, [2u8; 32]);
+    assert_eq!(root.last_verified_epoch, 6);
+    assert_eq!(root.batch_height, 2);
+}
+
+#[tokio::test]
+async fn validity_domains_exit_against_the_last_proven_root() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let sender = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let domain_id = Uuid::new_v4();
+    let txs = [
+        TxPayload::DomainCreate {
+            domain_id,
+            params: serde_json::json!({"kind": "wasm", "proof_mode": "validity"}),
+        },
+        TxPayload::RollupBridgeDeposit {
+            domain_id,
+            amount: 500,
+        },
+        TxPayload::DomainInboxProcess {
+            domain_id,
+            max_messages: None,
+        },
+    ];
+    for (nonce, payload) in txs.into_iter().enumerate() {
+        let tx = build_tx(payload, &sk, nonce as u64);
+        apply_tx(&ctx, &tx, nonce as u64).await.unwrap();
+    }
+    // Unproven execution does not move the settled root.
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(!chain.domain_roots.contains_key(&domain_id));
+
+    let state = ctx.domains.domain_state(&domain_id);
+    let proof = state
+        .prove(DomainState::balance_leaf(&sender, 500))
+        .unwrap();
+    let forced = |balance: u128| TxPayload::ForcedWithdraw {
+        domain_id,
+        balance,
+        proof: proof.clone(),
+    };
+    let err = apply_tx(&ctx, &build_tx(forced(500), &sk, 3), 3)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("no proven root"));
+
+    let input = zk_program_rollup::RollupProofInput {
+        domain_id,
+        blob_id: "blob-1".into(),
+        da_root: [2u8; 32],
+        state_root: state.root(),
+        batch_bytes: b"[]".to_vec(),
+    };
+    let commit = TxPayload::RollupBatchCommit {
+        domain_id,
+        blob_id: input.blob_id.clone(),
+        state_root: input.state_root,
+        da_root: input.da_root,
+        proof: zk_program_rollup::stub_batch_proof(&input).unwrap(),
+    };
+    apply_tx(&ctx, &build_tx(commit, &sk, 3), 4).await.unwrap();
+
+    let challenge = TxPayload::FraudChallenge {
+        domain_id,
+        claimed_root: [7u8; 32],
+        witness: serde_json::json!({}),
+    };
+    assert!(apply_tx(&ctx, &build_tx(challenge, &sk, 4), 5)
+        .await
+        .is_err());
+    assert!(apply_tx(&ctx, &build_tx(forced(600), &sk, 4), 5)
+        .await
+        .is_err());
+
+    let before = ctx
+        .state
+        .get_account(&sender)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_x;
+    let outcome = apply_tx(&ctx, &build_tx(forced(500), &sk, 4), 5)
+        .await
+        .unwrap();
+    let after = ctx
+        .state
+        .get_account(&sender)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_x;
+    assert_eq!(after, before + 500 - outcome.gas_used as u128);
+    assert_eq!(ctx.domains.token_balance(&domain_id, &sender), 0);
+
+    // The proof still matches the root, but the balance is gone.
+    assert!(apply_tx(&ctx, &build_tx(forced(500), &sk, 5), 6)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn blocks_list_the_domain_roots_they_moved_in_id_order() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let mut ids = [Uuid::new_v4(), Uuid::new_v4()];
+    for (nonce, domain_id) in ids.iter().enumerate() {
+        let create = build_tx(
+            TxPayload::DomainCreate {
+                domain_id: *domain_id,
+                params: serde_json::json!({"kind": "wasm"}),
+            },
+            &sk,
+            nonce as u64,
+        );
+        apply_tx(&ctx, &create, 0).await.unwrap();
+    }
+    ids.sort();
+
+    let commit = |domain_id: Uuid, nonce: u64| {
+        let input = zk_program_rollup::RollupProofInput {
+            domain_id,
+            blob_id: format!("blob-{nonce}"),
+            da_root: [nonce as u8; 32],
+            state_root: [3u8; 32],
+            batch_bytes: b"[]".to_vec(),
+        };
+        let payload = TxPayload::RollupBatchCommit {
+            domain_id,
+            blob_id: input.blob_id.clone(),
+            state_root: input.state_root,
+            da_root: input.da_root,
+            proof: zk_program_rollup::stub_batch_proof(&input).unwrap(),
+        };
+        build_tx(payload, &sk, nonce)
+    };
+    let txs = vec![commit(ids[1], 2), commit(ids[0], 3)];
+    let result = apply_block(&ctx, &block(1, txs)).await.unwrap();
+    assert!(result.receipts.iter().all(|r| r.succeeded()));
+    assert_eq!(
+        result.domain_roots,
+        vec![
+            DomainRootCommitment {
+                domain_id: ids[0],
+                state_root: [3u8; 32],
+                da_root: [3u8; 32],
+                batch_height: 1,
+            },
+            DomainRootCommitment {
+                domain_id: ids[1],
+                state_root: [3u8; 32],
+                da_root: [2u8; 32],
+                batch_height: 1,
+            },
+        ]
+    );
+
+    let result = apply_block(&ctx, &block(2, vec![])).await.unwrap();
+    assert!(result.domain_roots.is_empty());
+}
+
+#[tokio::test]
+async fn failed_blocks_leave_domain_state_untouched() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let domain_id = Uuid::new_v4();
+    let create = build_tx(
+        TxPayload::DomainCreate {
+            domain_id,
+            params: serde_json::json!({"kind": "wasm"}),
+        },
+        &sk,
+        0,
+    );
+    apply_tx(&ctx, &create, 0).await.unwrap();
+    let before = ctx.domains.domain_state(&domain_id);
+
+    let deposit = build_tx(
+        TxPayload::RollupBridgeDeposit {
+            domain_id,
+            amount: 500,
+        },
+        &sk,
+        1,
+    );
+    let mut forged = build_tx(
+        TxPayload::Transfer {
+            to: [9u8; 32],
+            amount: 1,
+        },
+        &sk,
+        2,
+    );
+    forged.signature[0] ^= 1;
+    assert!(apply_block(&ctx, &block(1, vec![deposit.clone(), forged]))
+        .await
+        .is_err());
+    let after = ctx.domains.domain_state(&domain_id);
+    assert!(after.inbox.is_empty());
+    assert_eq!(after.root(), before.root());
+
+    // The same deposit goes through once it lands in a good block.
+    apply_block(&ctx, &block(1, vec![deposit])).await.unwrap();
+    assert_eq!(ctx.domains.domain_state(&domain_id).inbox.len(), 1);
+}
+
+#[tokio::test]
+async fn timed_out_messages_are_acked_and_refunded_once() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let sender = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let source = Uuid::new_v4();
+    let dest = Uuid::new_v4();
+    let apply = |payload: TxPayload, nonce: u64, height: u64| {
+        let tx = build_tx(payload, &sk, nonce);
+        let ctx = &ctx;
+        async move { apply_tx(ctx, &tx, height).await }
+    };
+    let create = |domain_id, params| TxPayload::DomainCreate { domain_id, params };
+    apply(create(source, serde_json::json!({"kind": "wasm"})), 0, 0)
+        .await
+        .unwrap();
+    let params = serde_json::json!({"kind": "wasm", "message_timeout_blocks": 5});
+    apply(create(dest, params), 1, 0).await.unwrap();
+    let send = TxPayload::CrossDomainSend {
+        from_domain: source,
+        to_domain: dest,
+        payload: serde_json::json!({"action": "invoke", "module_id": "m"}),
+        fee: 7,
+    };
+    apply(send, 2, 10).await.unwrap();
+    let msg = ctx.domains.outbox(&source)[0].clone();
+    assert_eq!(msg.timeout_height, 15);
+
+    let refund = || TxPayload::CrossDomainRefund {
+        from_domain: source,
+        to_domain: dest,
+        nonce: 0,
+    };
+    let err = apply(refund(), 3, 15).await.unwrap_err();
+    assert!(err.to_string().contains("not timed out"), "{err}");
+
+    apply(relay(&ctx, msg), 3, 16).await.unwrap();
+    let process = |domain_id| TxPayload::DomainInboxProcess {
+        domain_id,
+        max_messages: None,
+    };
+    apply(process(dest), 4, 16).await.unwrap();
+    let receipts = ctx.domains.inbox_receipts(&dest);
+    assert_eq!(receipts[0].error.as_deref(), Some("message timed out"));
+    let ack = ctx.domains.outbox(&dest)[0].clone();
+    assert_eq!(
+        ack.ack,
+        Some(MessageAck {
+            nonce: 0,
+            status: DeliveryStatus::TimedOut,
+        })
+    );
+    // The destination consumed it, so the refund waits for the ack.
+    let err = apply(refund(), 5, 17).await.unwrap_err();
+    assert!(
+        err.to_string().contains("ack is still to be relayed"),
+        "{err}"
+    );
+
+    apply(relay(&ctx, ack), 5, 17).await.unwrap();
+    apply(process(source), 6, 17).await.unwrap();
+    assert!(ctx.domains.domain_state(&source).unacked[&dest][&0].timed_out);
+
+    let before = ctx
+        .state
+        .get_account(&sender)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_x;
+    let outcome = apply(refund(), 7, 18).await.unwrap();
+    let after = ctx
+        .state
+        .get_account(&sender)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_x;
+    assert_eq!(after, before + 7 - outcome.gas_used as u128);
+    assert!(ctx.domains.domain_state(&source).unacked.is_empty());
+    assert!(apply(refund(), 8, 19).await.is_err());
+}
+
+#[tokio::test]
+async fn domain_calls_deliver_pending_messages_first() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let source = Uuid::new_v4();
+    let dest = Uuid::new_v4();
+    for (nonce, domain_id) in [(0, source), (1, dest)] {
+        let create = TxPayload::DomainCreate {
+            domain_id,
+            params: serde_json::json!({"kind": "wasm"}),
+        };
+        apply_tx(&ctx, &build_tx(create, &sk, nonce), 0)
+            .await
+            .unwrap();
+    }
+    // An empty module.
+    let module = base64::encode(b"\0asm\x01\0\0\0");
+    let send = TxPayload::CrossDomainSend {
+        from_domain: source,
+        to_domain: dest,
+        payload: serde_json::json!({"action": "deploy", "module_id": "a", "code_b64": module}),
+        fee: 0,
+    };
+    apply_tx(&ctx, &build_tx(send, &sk, 2), 1).await.unwrap();
+    let message = ctx.domains.outbox(&source)[0].clone();
+    apply_tx(&ctx, &build_tx(relay(&ctx, message), &sk, 3), 1)
+        .await
+        .unwrap();
+
+    let call = DomainCall {
+        domain_id: dest,
+        payload: serde_json::json!({"action": "deploy", "module_id": "b", "code_b64": module}),
+        raw: vec![],
+        max_gas: Some(50_000),
+    };
+    let outcome = apply_tx(&ctx, &build_tx(TxPayload::DomainExecute(call), &sk, 4), 2)
+        .await
+        .unwrap();
+    assert_eq!(outcome.events[0].kind, "inbox_message");
+    assert_eq!(outcome.events[0].attribute("status"), Some("ok"));
+    let ack = ctx.domains.outbox(&dest)[0].ack.clone();
+    assert_eq!(
+        ack,
+        Some(MessageAck {
+            nonce: 0,
+            status: DeliveryStatus::Delivered,
+        })
+    );
+}
+
+#[tokio::test]
+async fn domain_gas_is_prepaid_charged_at_the_domain_price_and_refunded() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let owner = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let domain_id = Uuid::new_v4();
+    let create = TxPayload::DomainCreate {
+        domain_id,
+        params: serde_json::json!({"kind": "wasm", "gas_price": 3}),
+    };
+    apply_tx(&ctx, &build_tx(create, &sk, 0), 0).await.unwrap();
+    // An empty module.
+    let module = base64::encode(b"\0asm\x01\0\0\0");
+    let deploy = |max_gas| {
+        TxPayload::DomainExecute(DomainCall {
+            domain_id,
+            payload: serde_json::json!({"action": "deploy", "module_id": "m", "code_b64": module}),
+            raw: vec![],
+            max_gas: Some(max_gas),
+        })
+    };
+
+    // The whole of `max_gas` has to be covered up front.
+    let err = apply_tx(&ctx, &build_tx(deploy(5_000_000), &sk, 1), 1)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("insufficient funds"), "{err}");
+
+    let before = ctx.state.get_account(&owner).await.unwrap().unwrap().balance_x;
+    let pools = ctx.state.get_chain_state().await.unwrap().fee_pools;
+    let outcome = apply_tx(&ctx, &build_tx(deploy(50_000), &sk, 1), 1)
+        .await
+        .unwrap();
+    let executed = outcome.events.last().unwrap();
+    // 10_000 per deploy and 10 per byte of code.
+    assert_eq!(executed.attribute("domain_gas_used"), Some("10080"));
+    assert_eq!(executed.attribute("domain_fee"), Some("30240"));
+    assert_eq!(executed.attribute("refund"), Some("119760"));
+
+    // Half to the domain's sequencers, 30% to DA and the rest as L1 rent.
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.sequencer_fees[&domain_id], 15_120);
+    assert_eq!(chain.fee_pools.da - pools.da, 9_072);
+    let l1_fee = (chain.fee_pools.l1_gas - pools.l1_gas - 6_048)
+        + (chain.fee_pools.treasury - pools.treasury);
+    let after = ctx.state.get_account(&owner).await.unwrap().unwrap().balance_x;
+    assert_eq!(before - after, l1_fee + 30_240);
+}
+
+#[tokio::test]
+async fn domain_snapshots_replay_calls_without_touching_live_state() {
+    let sk = signer();
+    let ctx = funded_ctx(&sk).await;
+    let domain_id = Uuid::new_v4();
+    let create = TxPayload::DomainCreate {
+        domain_id,
+        params: serde_json::json!({"kind": "wasm"}),
+    };
+    apply_block(&ctx, &block(1, vec![build_tx(create, &sk, 0)]))
+        .await
+        .unwrap();
+    let deposit = TxPayload::RollupBridgeDeposit {
+        domain_id,
+        amount: 500,
+    };
+    apply_block(&ctx, &block(2, vec![build_tx(deposit, &sk, 1)]))
+        .await
+        .unwrap();
+    apply_block(&ctx, &block(3, vec![])).await.unwrap();
+
+    let err = ctx.domains.snapshot(&domain_id, 0).unwrap_err();
+    assert!(err.to_string().contains("no snapshot"), "{err}");
+    assert!(ctx.domains.snapshot(&domain_id, 1).unwrap().inbox.is_empty());
+    // Block 3 changed nothing, so it reads the state block 2 left.
+    let at_3 = ctx.domains.snapshot(&domain_id, 3).unwrap();
+    assert_eq!(at_3.inbox.len(), 1);
+    assert_eq!(at_3.root(), ctx.domains.state_root(&domain_id));
+
+    let deploy = |domain_id| DomainCall {
+        domain_id,
+        payload: serde_json::json!({
+            "action": "deploy",
+            "module_id": "m",
+            "code_b64": base64::encode(b"\0asm\x01\0\0\0"),
+        }),
+        raw: vec![],
+        max_gas: Some(50_000),
+    };
+    let live = ctx.domains.state_root(&domain_id);
+    let err = ctx
+        .domains
+        .replay(&domain_id, at_3.clone(), &[deploy(Uuid::new_v4())], &ctx, 4)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("call is for domain"), "{err}");
+    let calls = [deploy(domain_id), deploy(domain_id)];
+    let steps = ctx
+        .domains
+        .replay(&domain_id, at_3.clone(), &calls, &ctx, 4)
+        .await
+        .unwrap();
+    assert!(steps.iter().all(|step| step.error.is_none()));
+    assert_ne!(steps[0].state_root, at_3.root());
+    // Replays are deterministic and leave the live state alone.
+    let again = ctx
+        .domains
+        .replay(&domain_id, at_3, &calls[..1], &ctx, 4)
+        .await
+        .unwrap();
+    assert_eq!(again[0].state_root, steps[0].state_root);
+    assert_eq!(ctx.domains.state_root(&domain_id), live);
+}
+
+#[test]
+fn state_leaves_of_one_kind_do_not_prove_another() {
+    let owner = [b'a'; 32];
+    let mut state = DomainState::default();
+    state.kv.insert(
+        String::from_utf8(owner.to_vec()).unwrap(),
+        500u128.to_le_bytes().to_vec(),
+    );
+    assert!(state
+        .prove(DomainState::balance_leaf(&owner, 500))
+        .is_none());
+
+    state.token.balances.insert(owner, 500);
+    assert!(state
+        .prove(DomainState::balance_leaf(&owner, 500))
+        .is_some());
+}
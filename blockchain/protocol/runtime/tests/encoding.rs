@@ -0,0 +1,129 @@
+//! Golden vectors for the canonical encoding. These bytes are what tx
+//! hashes, block hashes and signatures cover: a change to the encoding rules
+//! needs a new `ENCODING_VERSION`, and a change to the types moves these
+//! vectors too.
+
+use runtime::{
+    decode, encode, hash_block, tx_hash, vote_signing_bytes, Block, BlockDACommitment, BlockHeader,
+    Tx, TxPayload, ENCODING_VERSION,
+};
+
+fn transfer() -> Tx {
+    Tx {
+        chain_id: "kova".into(),
+        nonce: 1,
+        gas_limit: 21_000,
+        max_fee: Some(3),
+        max_priority_fee: None,
+        gas_price: None,
+        payload: TxPayload::Transfer {
+            to: [2u8; 32],
+            amount: 500,
+        },
+        public_key: vec![0xaa; 2],
+        signature: vec![0xbb; 3],
+    }
+}
+
+fn header() -> BlockHeader {
+    BlockHeader {
+        parent_hash: [1u8; 32],
+        height: 7,
+        timestamp: 1_700_000_000_000,
+        proposer_id: [3u8; 32],
+        state_root: [4u8; 32],
+        l1_tx_root: [5u8; 32],
+        da_commitment: Some(BlockDACommitment {
+            root: [6u8; 32],
+            total_shards: 6,
+            data_shards: 4,
+            parity_shards: 2,
+            shard_size: 1024,
+        }),
+        domain_roots: vec![],
+        gas_used: 21_000,
+        gas_limit: 30_000_000,
+        base_fee: 1,
+        snapshot_root: None,
+        protocol_version: 0,
+        consensus_metadata: serde_json::json!({"view": 9}),
+    }
+}
+
+#[test]
+fn tx_encoding_matches_golden_vector() {
+    let encoded = encode(&transfer()).unwrap();
+    assert_eq!(hex::encode(&encoded), TX_VECTOR);
+    assert_eq!(encoded[0], ENCODING_VERSION);
+    assert_eq!(hex::encode(tx_hash(&transfer())), TX_HASH);
+
+    let decoded: Tx = decode(&encoded).unwrap();
+    assert_eq!(encode(&decoded).unwrap(), encoded);
+}
+
+#[test]
+fn header_and_block_hashes_match_golden_vectors() {
+    let encoded = encode(&header()).unwrap();
+    assert_eq!(hex::encode(blake3::hash(&encoded).as_bytes()), HEADER_HASH);
+    let decoded: BlockHeader = decode(&encoded).unwrap();
+    assert_eq!(decoded.consensus_metadata["view"], 9);
+
+    // A block's encoding starts with its header's.
+    let block = Block {
+        header: header(),
+        transactions: vec![transfer()],
+        da_blobs: vec![],
+    };
+    assert!(encode(&block).unwrap().starts_with(&encoded));
+    assert_eq!(hex::encode(hash_block(&block)), BLOCK_HASH);
+}
+
+#[test]
+fn vote_payload_matches_golden_vector() {
+    let msg = vote_signing_bytes("kova", &[7u8; 32], 300).unwrap();
+    assert_eq!(hex::encode(msg), VOTE_VECTOR);
+}
+
+#[test]
+fn malformed_encodings_are_rejected() {
+    let encoded = encode(&transfer()).unwrap();
+    let mut future = encoded.clone();
+    future[0] = ENCODING_VERSION + 1;
+    assert!(decode::<Tx>(&future)
+        .unwrap_err()
+        .to_string()
+        .contains("unsupported encoding version"));
+
+    let mut trailing = encoded.clone();
+    trailing.push(0);
+    assert!(decode::<Tx>(&trailing)
+        .unwrap_err()
+        .to_string()
+        .contains("trailing"));
+
+    assert!(decode::<Tx>(&encoded[..encoded.len() - 1]).is_err());
+    assert!(decode::<Tx>(&[]).is_err());
+
+    // `max_fee` is `Some`: its tag follows the chain id, nonce and gas limit.
+    let tag = 1 + 1 + 4 + 8 + 8;
+    assert_eq!(encoded[tag], 1);
+    let mut bad_tag = encoded.clone();
+    bad_tag[tag] = 2;
+    assert!(decode::<Tx>(&bad_tag)
+        .unwrap_err()
+        .to_string()
+        .contains("invalid option tag"));
+}
+
+const TX_VECTOR: &str = concat!(
+    "01046b6f76610100000000000000085200000000000001030000000000000000000000000000000000000202",
+    "020202020202020202020202020202020202020202020202020202020202f401000000000000000000000000",
+    "000002aaaa03bbbbbb",
+);
+const TX_HASH: &str = "7a3db8d81c322843844736c904f33be7b4b0b2244fb543717e35bbbdde4caa17";
+const HEADER_HASH: &str = "182da3f3b37753582cfc76628e65a864aa1e93e530833207021fbf113758b943";
+const BLOCK_HASH: &str = "0803b2bbb8b6038a10f44985acd2fb62121e5ead341b84c8e030cc70bea3c012";
+const VOTE_VECTOR: &str = concat!(
+    "6b6f76612f766f74652f763100040000006b6f766101070707070707070707070707070707070707",
+    "07070707070707070707070707072c01000000000000",
+);
@@ -0,0 +1,193 @@
+use ed25519_dalek::SigningKey;
+use revm::primitives::{Address, U256};
+use runtime::{
+    address_from_pubkey, apply_tx, bootstrap_state, sign_bytes, tx_signing_bytes, DomainCall,
+    EvmAdapter, ExecutionContext, Tx, TxPayload,
+};
+use state::{Account, InMemoryStateStore, StateStore};
+use uuid::Uuid;
+
+/// Stores the first calldata word in slot 0 and emits an empty `LOG0`.
+const RUNTIME: &str = "60003560005560006000a000";
+/// Copies `RUNTIME`, which follows it, into memory and returns it.
+const INIT: &str = "600c600c600039600c6000f3";
+
+fn signer() -> SigningKey {
+    SigningKey::from_bytes(&[8u8; 32])
+}
+
+async fn evm_domain(id: Uuid) -> anyhow::Result<ExecutionContext<InMemoryStateStore>> {
+    let ctx = bootstrap_state();
+    ctx.state
+        .put_account(Account {
+            address: address_from_pubkey(&signer().verifying_key().to_bytes()),
+            nonce: 0,
+            balance_x: 10_000_000,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await?;
+    apply(
+        &ctx,
+        0,
+        TxPayload::DomainCreate {
+            domain_id: id,
+            params: serde_json::json!({"kind": "evm"}),
+        },
+    )
+    .await?;
+    Ok(ctx)
+}
+
+async fn apply(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    nonce: u64,
+    payload: TxPayload,
+) -> anyhow::Result<()> {
+    let mut tx = Tx {
+        chain_id: "kova-devnet".into(),
+        nonce,
+        gas_limit: 300_000,
+        max_fee: Some(1),
+        max_priority_fee: Some(0),
+        gas_price: None,
+        payload,
+        public_key: signer().verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    };
+    tx.signature = sign_bytes(&signer(), &tx_signing_bytes(&tx)?);
+    apply_tx(ctx, &tx, nonce).await.map(|_| ())
+}
+
+fn evm_call(id: Uuid, payload: serde_json::Value) -> TxPayload {
+    TxPayload::DomainExecute(DomainCall {
+        domain_id: id,
+        payload,
+        raw: vec![],
+        max_gas: Some(200_000),
+    })
+}
+
+#[tokio::test]
+async fn contracts_deploy_run_and_keep_storage_in_domain_state() -> anyhow::Result<()> {
+    let id = Uuid::new_v4();
+    let from = Address::with_last_byte(1);
+    let contract = from.create(0);
+    let mut roots = Vec::new();
+    for _ in 0..2 {
+        let ctx = evm_domain(id).await?;
+        let deploy = serde_json::json!({
+            "from": from.to_string(),
+            "input": format!("0x{INIT}{RUNTIME}"),
+        });
+        apply(&ctx, 1, evm_call(id, deploy)).await?;
+        let receipt = ctx.domains.last_trace(&id).unwrap();
+        assert_eq!(
+            receipt.events[0],
+            format!("evm_create:{}", hex::encode(contract))
+        );
+        assert!(receipt.gas_used > 0 && receipt.gas_used < 200_000);
+
+        let word = format!("{:064x}", 7);
+        let call = serde_json::json!({
+            "from": from.to_string(),
+            "to": contract.to_string(),
+            "input": word,
+        });
+        apply(&ctx, 2, evm_call(id, call)).await?;
+        let receipt = ctx.domains.last_trace(&id).unwrap();
+        assert_eq!(receipt.events[0], "evm_call:success");
+        assert_eq!(
+            receipt.events[1],
+            format!("evm_log:{}::", hex::encode(contract))
+        );
+
+        let state = ctx.domains.domain_state(&id);
+        assert_eq!(
+            EvmAdapter::storage(&state, &contract, &U256::ZERO),
+            U256::from(7)
+        );
+        let sender = EvmAdapter::account(&state, &from).unwrap();
+        assert_eq!(sender.nonce, 2);
+        let code = EvmAdapter::account(&state, &contract)
+            .unwrap()
+            .code
+            .unwrap();
+        assert_eq!(hex::encode(code.original_bytes()), RUNTIME);
+        assert_eq!(receipt.state_root, state.root());
+        roots.push(receipt.state_root);
+    }
+    // Every node executing the same calls reaches the same root.
+    assert_eq!(roots[0], roots[1]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_call_with_a_stale_nonce_is_rejected() -> anyhow::Result<()> {
+    let id = Uuid::new_v4();
+    let ctx = evm_domain(id).await?;
+    let from = Address::with_last_byte(1);
+    let deploy = serde_json::json!({
+        "from": from.to_string(),
+        "input": format!("{INIT}{RUNTIME}"),
+        "nonce": 0,
+    });
+    apply(&ctx, 1, evm_call(id, deploy.clone())).await?;
+    let err = apply(&ctx, 2, evm_call(id, deploy)).await.unwrap_err();
+    assert!(
+        format!("{err:#}").contains("evm execution failed"),
+        "{err:#}"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn simulated_calls_leave_domain_state_untouched() -> anyhow::Result<()> {
+    let id = Uuid::new_v4();
+    let ctx = evm_domain(id).await?;
+    let from = Address::with_last_byte(1);
+    let deploy = serde_json::json!({
+        "from": from.to_string(),
+        "input": format!("{INIT}{RUNTIME}"),
+    });
+    apply(&ctx, 1, evm_call(id, deploy)).await?;
+    let state = ctx.domains.domain_state(&id);
+    let call = serde_json::json!({
+        "from": from.to_string(),
+        "to": from.create(0).to_string(),
+        "input": format!("{:064x}", 7),
+        // A stale nonce doesn't stop a simulation.
+        "nonce": 0,
+    });
+    let result = EvmAdapter::new(id).simulate(&state, &call, 2)?;
+    assert_eq!(result.status, "success");
+    assert!(result.gas_used > 0);
+    assert_eq!(ctx.domains.domain_state(&id).root(), state.root());
+
+    let view = EvmAdapter::account_view(&state, &from.create(0).to_string())?;
+    assert_eq!(view.code, format!("0x{RUNTIME}"));
+    assert_eq!(
+        EvmAdapter::account_view(&state, &from.to_string())?.nonce,
+        1
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn raw_calls_must_be_signed_ethereum_txs() -> anyhow::Result<()> {
+    let id = Uuid::new_v4();
+    let ctx = evm_domain(id).await?;
+    let call = TxPayload::DomainExecute(DomainCall {
+        domain_id: id,
+        payload: serde_json::Value::Null,
+        raw: vec![0x02, 0xc0],
+        max_gas: Some(200_000),
+    });
+    let err = apply(&ctx, 1, call).await.unwrap_err();
+    assert!(
+        format!("{err:#}").contains("invalid signed evm tx"),
+        "{err:#}"
+    );
+    Ok(())
+}
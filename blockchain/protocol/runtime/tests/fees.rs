@@ -0,0 +1,104 @@
+use runtime::{
+    bootstrap_state, estimate_gas, suggest_fees, Block, BlockHeader, FeeTier, Tx, TxPayload,
+};
+use state::{Account, StateStore};
+
+fn block(base_fee: u128, tips: &[u128]) -> Block {
+    let transactions = tips
+        .iter()
+        .map(|tip| Tx {
+            chain_id: "kova-devnet".into(),
+            nonce: 0,
+            gas_limit: 21_000,
+            max_fee: Some(base_fee + tip),
+            max_priority_fee: Some(*tip),
+            gas_price: None,
+            payload: TxPayload::Transfer {
+                to: [2u8; 32],
+                amount: 1,
+            },
+            public_key: vec![],
+            signature: vec![],
+        })
+        .collect();
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height: 0,
+            timestamp: 0,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions,
+        da_blobs: vec![],
+    }
+}
+
+#[tokio::test]
+async fn estimate_simulates_without_touching_state() {
+    let ctx = bootstrap_state();
+    let sender = [7u8; 32];
+    ctx.state
+        .put_account(Account {
+            address: sender,
+            nonce: 4,
+            balance_x: 1_000_000,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await
+        .unwrap();
+
+    let transfer = TxPayload::Transfer {
+        to: [2u8; 32],
+        amount: 10,
+    };
+    let base = ctx.state.get_chain_state().await.unwrap();
+    let estimate = estimate_gas(&ctx, base.clone(), sender, transfer, 1)
+        .await
+        .unwrap();
+    assert_eq!(estimate.gas_used, 21_000);
+    assert_eq!(estimate.gas_limit, 25_200);
+
+    let account = ctx.state.get_account(&sender).await.unwrap().unwrap();
+    assert_eq!((account.nonce, account.balance_x), (4, 1_000_000));
+    assert!(ctx.state.get_account(&[2u8; 32]).await.unwrap().is_none());
+
+    let broke = TxPayload::Transfer {
+        to: [2u8; 32],
+        amount: 10_000_000,
+    };
+    assert!(estimate_gas(&ctx, base, sender, broke, 1).await.is_err());
+}
+
+#[test]
+fn suggestions_follow_recent_tips() {
+    let empty = suggest_fees(&[], 3);
+    assert_eq!(empty.base_fee, 3);
+    assert_eq!(
+        empty.fast,
+        FeeTier {
+            max_priority_fee: 0,
+            max_fee: 6,
+        }
+    );
+
+    let blocks = [block(1, &[0, 10, 20]), block(2, &[30, 40])];
+    let suggestion = suggest_fees(&blocks, 1);
+    assert_eq!(suggestion.base_fee, 2);
+    assert_eq!(suggestion.sampled_blocks, 2);
+    assert_eq!(suggestion.slow.max_priority_fee, 10);
+    assert_eq!(suggestion.standard.max_priority_fee, 20);
+    assert_eq!(suggestion.fast.max_priority_fee, 30);
+    assert_eq!(suggestion.fast.max_fee, 34);
+}
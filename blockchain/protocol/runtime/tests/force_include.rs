@@ -0,0 +1,172 @@
+mod common;
+
+use ed25519_dalek::SigningKey;
+use runtime::{
+    apply_block, apply_tx, batch_calls, bootstrap_state, calls_root, tx_hash, Block, BlockHeader,
+    DomainCall, ExecutionContext, Tx, TxPayload,
+};
+use state::{InMemoryStateStore, StateStore};
+use uuid::Uuid;
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::dynamic(10_000_000, 300_000);
+
+fn block(height: u64) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp: 0,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions: vec![],
+        da_blobs: vec![],
+    }
+}
+
+fn call(sk: &SigningKey, domain_id: Uuid, step: u64) -> Tx {
+    let call = DomainCall {
+        domain_id,
+        payload: serde_json::json!({ "step": step }),
+        raw: vec![],
+        max_gas: None,
+    };
+    FIXTURE.signed_tx(sk, step, TxPayload::DomainExecute(call))
+}
+
+/// Claims the batch `blob` carries, committing to its calls.
+fn claim(domain_id: Uuid, blob_id: &str, blob: &[u8]) -> TxPayload {
+    TxPayload::RollupBatchClaim {
+        domain_id,
+        blob_id: blob_id.into(),
+        state_root: [5u8; 32],
+        calls_root: calls_root(&batch_calls(&domain_id, blob).unwrap()),
+        steps: 1,
+    }
+}
+
+async fn bond(ctx: &ExecutionContext<InMemoryStateStore>, domain_id: &Uuid) -> u128 {
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    chain.sequencer_bonds[domain_id][0].bond
+}
+
+#[tokio::test]
+async fn queued_txs_must_be_carried_by_a_batch_or_l1_applies_them() {
+    let ctx = bootstrap_state();
+    let (sequencer, _) = FIXTURE.funded(&ctx, 1).await;
+    let (user, _) = FIXTURE.funded(&ctx, 2).await;
+    let domain_id = Uuid::new_v4();
+    let create = TxPayload::DomainCreate {
+        domain_id,
+        params: serde_json::json!({
+            "kind": "wasm",
+            "min_sequencer_bond": 1_000,
+            "missed_round_slash_bps": 0,
+            "force_include_blocks": 3,
+            "force_include_slash_bps": 2_000,
+        }),
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sequencer, 0, create), 0)
+        .await
+        .unwrap();
+    let register = TxPayload::SequencerRegister {
+        domain_id,
+        bond: 50_000,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sequencer, 1, register), 0)
+        .await
+        .unwrap();
+
+    let force = |tx_bytes: Vec<u8>| TxPayload::ForceInclude {
+        domain_id,
+        tx_bytes,
+    };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&user, 0, force(vec![1, 2, 3])), 1)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not an encoded tx"));
+    let elsewhere = serde_json::to_vec(&call(&user, Uuid::new_v4(), 7)).unwrap();
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&user, 0, force(elsewhere)), 1)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("call on the domain"));
+
+    // The sequencer carries the first queued tx in its next batch.
+    let carried = call(&user, domain_id, 0);
+    let bytes = serde_json::to_vec(&carried).unwrap();
+    apply_tx(&ctx, &FIXTURE.signed_tx(&user, 0, force(bytes.clone())), 1)
+        .await
+        .unwrap();
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&user, 1, force(bytes)), 1)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("already queued"));
+    let blob = zk_program_rollup::encode_batch(std::slice::from_ref(&carried)).unwrap();
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&sequencer, 2, claim(domain_id, "b1", &blob)),
+        2,
+    )
+    .await
+    .unwrap();
+    let prove = |tx: &Tx, batch_height| TxPayload::ForceIncludeProve {
+        domain_id,
+        tx_hash: tx_hash(tx),
+        batch_height,
+        blob: blob.clone(),
+    };
+    let outcome = apply_tx(&ctx, &FIXTURE.signed_tx(&user, 1, prove(&carried, 1)), 3)
+        .await
+        .unwrap();
+    assert!(outcome
+        .events
+        .iter()
+        .any(|e| e.kind == "force_include_proven"));
+
+    // The second is left out until its deadline passes.
+    let ignored = call(&user, domain_id, 1);
+    let bytes = serde_json::to_vec(&ignored).unwrap();
+    apply_tx(&ctx, &FIXTURE.signed_tx(&user, 2, force(bytes)), 10)
+        .await
+        .unwrap();
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&user, 3, prove(&ignored, 1)), 11)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("before the tx was queued"));
+    let result = apply_block(&ctx, &block(13)).await.unwrap();
+    assert!(!result
+        .events
+        .iter()
+        .any(|e| e.kind.starts_with("force_include")));
+    let before = bond(&ctx, &domain_id).await;
+    let result = apply_block(&ctx, &block(14)).await.unwrap();
+    let forced = result
+        .events
+        .iter()
+        .find(|e| e.kind == "force_include_applied" || e.kind == "force_include_failed")
+        .unwrap();
+    assert_eq!(
+        forced.attribute("tx_hash"),
+        Some(hex::encode(tx_hash(&ignored)).as_str())
+    );
+    let slashed = result
+        .events
+        .iter()
+        .find(|e| e.kind == "sequencer_slashed")
+        .unwrap();
+    assert_eq!(slashed.attribute("reason"), Some("force_include_missed"));
+    assert_eq!(bond(&ctx, &domain_id).await, before - before / 5);
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(chain.forced_inclusions.is_empty());
+}
@@ -0,0 +1,98 @@
+use runtime::{
+    devnet_genesis, fork_genesis, from_genesis, ForkOptions, ForkPatch, GenesisConfig,
+    GenesisValidator,
+};
+use state::{Account, ChainState, StateStore, Unbonding, Validator, ValidatorStatus};
+use uuid::Uuid;
+
+fn account(address: [u8; 32], balance_x: u128) -> Account {
+    Account {
+        address,
+        nonce: 3,
+        balance_x,
+        code_hash: None,
+        storage_root: None,
+        assets: Default::default(),
+    }
+}
+
+fn source_state() -> ChainState {
+    let mut chain = ChainState::default();
+    chain.accounts.insert([1u8; 32], account([1u8; 32], 1_000));
+    chain.accounts.insert([2u8; 32], account([2u8; 32], 500));
+    let validator_id = Uuid::new_v4();
+    chain.validators.insert(
+        validator_id,
+        Validator {
+            owner: [1u8; 32],
+            id: validator_id,
+            pubkey: vec![1u8; 32],
+            stake: 10_000,
+            status: ValidatorStatus::Active,
+            commission_rate: 5,
+            bls_pubkey: vec![],
+            description: Default::default(),
+            commission_changed_epoch: None,
+        },
+    );
+    chain.delegate([2u8; 32], validator_id, 4_000).unwrap();
+    chain.pending_unbonds.push(Unbonding {
+        owner: [3u8; 32],
+        validator_id: Some(validator_id),
+        amount: 700,
+        release_height: 99,
+    });
+    chain
+}
+
+fn template() -> GenesisConfig {
+    let mut genesis = devnet_genesis();
+    genesis.chain_id = "kova-mainnet".into();
+    genesis
+}
+
+fn options() -> ForkOptions {
+    ForkOptions {
+        chain_id: "kova-shadow".into(),
+        validators: vec![GenesisValidator {
+            pubkey: vec![9u8; 32],
+            stake: 1_000,
+            commission_rate: 0,
+            bls_pubkey: vec![],
+            bls_proof_of_possession: vec![],
+        }],
+        allowlist: None,
+        patch: ForkPatch::default(),
+    }
+}
+
+#[tokio::test]
+async fn fork_returns_stake_to_owners_and_replaces_validators() {
+    let genesis = fork_genesis(&source_state(), template(), options()).unwrap();
+    assert_eq!(genesis.chain_id, "kova-shadow");
+    assert_eq!(
+        genesis.initial_accounts,
+        vec![([1u8; 32], 7_000), ([2u8; 32], 4_500), ([3u8; 32], 700)]
+    );
+
+    let ctx = from_genesis(genesis).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.validators.len(), 1);
+    assert_eq!(chain.validators.values().next().unwrap().pubkey, vec![9u8; 32]);
+}
+
+#[test]
+fn allowlist_and_patch_are_applied() {
+    let mut opts = options();
+    opts.allowlist = Some([[1u8; 32], [2u8; 32]].into_iter().collect());
+    opts.patch = ForkPatch {
+        set_balances: vec![([4u8; 32], 42)],
+        remove: vec![[2u8; 32]],
+    };
+    let genesis = fork_genesis(&source_state(), template(), opts).unwrap();
+    assert_eq!(genesis.initial_accounts, vec![([1u8; 32], 7_000), ([4u8; 32], 42)]);
+
+    let mut same_chain = options();
+    same_chain.chain_id = "kova-mainnet".into();
+    assert!(fork_genesis(&source_state(), template(), same_chain).is_err());
+}
@@ -1,7 +1,7 @@
 use proptest::prelude::*;
 use runtime::{
-    address_from_pubkey, sign_bytes, tx_signing_bytes, CrossDomainMessage, DomainCall, Tx,
-    TxPayload,
+    address_from_pubkey, decode, encode, sign_bytes, tx_hash, tx_signing_bytes,
+    CrossDomainMessage, DomainCall, DomainProof, Tx, TxPayload,
 };
 use uuid::Uuid;
 
@@ -47,15 +47,23 @@ fn arb_payload() -> impl Strategy<Value = TxPayload> {
             arb_uuid(),
             any::<u64>(),
             any::<u128>(),
-            arb_json()
+            arb_json(),
+            arb_address()
         )
-            .prop_map(|(from, to, nonce, fee, payload)| TxPayload::CrossDomainRelay {
+            .prop_map(|(from, to, nonce, fee, payload, leaf)| TxPayload::CrossDomainRelay {
                 message: CrossDomainMessage {
                     from,
                     to,
                     nonce,
                     fee,
                     payload,
+                    timeout_height: 0,
+                    ack: None,
+                },
+                proof: DomainProof {
+                    leaf,
+                    index: 0,
+                    path: vec![],
                 },
             }),
         (arb_uuid(), arb_json()).prop_map(|(domain_id, params)| TxPayload::DomainCreate { domain_id, params }),
@@ -104,6 +112,15 @@ proptest! {
         prop_assert_eq!(orig_signing, decoded_signing);
 
         let addr = address_from_pubkey(&decoded.public_key);
-        prop_assert_eq!(addr, runtime::verify_tx_signature(&decoded).unwrap());
+        prop_assert_eq!(addr, runtime::verify_tx_signature(&decoded, false).unwrap());
+    }
+
+    #[test]
+    fn canonical_roundtrip_reencodes_to_the_same_bytes(tx in arb_signed_tx()) {
+        let encoded = encode(&tx).unwrap();
+        let decoded: Tx = decode(&encoded).unwrap();
+        prop_assert_eq!(encode(&decoded).unwrap(), encoded.clone());
+        prop_assert_eq!(tx_hash(&decoded), tx_hash(&tx));
+        prop_assert!(decode::<Tx>(&encoded[..encoded.len() - 1]).is_err());
     }
 }
@@ -0,0 +1,224 @@
+//! Golden tests: fixed scenarios are replayed from genesis and every state
+//! root, block hash and tx hash is checked against the fixtures in
+//! `tests/golden/`. A mismatch means a change altered what nodes hash or how
+//! they execute, and so breaks consensus with nodes running the old code.
+//!
+//! If the change is intended, regenerate the fixtures and commit them with it:
+//!
+//! ```text
+//! UPDATE_GOLDEN=1 cargo test -p runtime --test golden
+//! ```
+
+use std::path::PathBuf;
+
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, apply_block, devnet_genesis, from_genesis, hash_block, sign_bytes,
+    tx_hash, tx_root, tx_signing_bytes, Address, Block, BlockHeader, GenesisConfig,
+    GenesisValidator, Tx, TxPayload,
+};
+use serde::{Deserialize, Serialize};
+use state::StateStore;
+
+const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Golden {
+    genesis_state_root: String,
+    blocks: Vec<GoldenBlock>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct GoldenBlock {
+    height: u64,
+    hash: String,
+    state_root: String,
+    tx_hashes: Vec<String>,
+}
+
+fn key(seed: u8) -> SigningKey {
+    SigningKey::from_bytes(&[seed; 32])
+}
+
+fn address(seed: u8) -> Address {
+    address_from_pubkey(&key(seed).verifying_key().to_bytes())
+}
+
+fn tx(seed: u8, nonce: u64, payload: TxPayload) -> Tx {
+    let mut tx = Tx {
+        chain_id: "kova-devnet".into(),
+        nonce,
+        gas_limit: 100_000,
+        max_fee: None,
+        max_priority_fee: None,
+        gas_price: Some(2),
+        payload,
+        public_key: key(seed).verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    };
+    tx.signature = sign_bytes(&key(seed), &tx_signing_bytes(&tx).unwrap());
+    tx
+}
+
+fn genesis() -> GenesisConfig {
+    let mut genesis = devnet_genesis();
+    genesis.initial_accounts = vec![(address(1), 5_000_000), (address(2), 1_000_000)];
+    genesis.initial_validators = vec![GenesisValidator {
+        pubkey: key(9).verifying_key().to_bytes().to_vec(),
+        stake: 10_000_000,
+        commission_rate: 5,
+        bls_pubkey: vec![],
+        bls_proof_of_possession: vec![],
+    }];
+    genesis
+}
+
+fn transfer(to: u8, amount: u128) -> TxPayload {
+    TxPayload::Transfer {
+        to: address(to),
+        amount,
+    }
+}
+
+/// Transfers, an EIP-1559 priced tx, and a failed tx that is still
+/// included and charged.
+fn transfers() -> (GenesisConfig, Vec<Vec<Tx>>) {
+    let mut dynamic = tx(2, 1, transfer(3, 7));
+    dynamic.gas_price = None;
+    dynamic.max_fee = Some(5);
+    dynamic.max_priority_fee = Some(1);
+    dynamic.signature = sign_bytes(&key(2), &tx_signing_bytes(&dynamic).unwrap());
+    let blocks = vec![
+        vec![tx(1, 0, transfer(2, 250_000)), tx(2, 0, transfer(3, 1_000))],
+        vec![dynamic, tx(1, 1, transfer(3, 50_000_000))],
+        vec![],
+        vec![tx(1, 2, transfer(1, 1))],
+    ];
+    (genesis(), blocks)
+}
+
+/// Staking through an epoch boundary so the exit queue and unbonding run.
+fn staking() -> (GenesisConfig, Vec<Vec<Tx>>) {
+    let mut genesis = genesis();
+    genesis.epoch_length_blocks = 4;
+    genesis.unbonding_delay_blocks = 2;
+    let blocks = vec![
+        vec![tx(1, 0, TxPayload::Stake { amount: 2_000_000 })],
+        vec![tx(
+            2,
+            0,
+            TxPayload::Delegate {
+                validator: address(1),
+                amount: 300_000,
+            },
+        )],
+        vec![tx(1, 1, TxPayload::Unstake { amount: 500_000 })],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+        vec![],
+    ];
+    (genesis, blocks)
+}
+
+/// Applies `blocks` from `genesis`, chaining and sealing headers the way the
+/// node does.
+async fn replay(genesis: GenesisConfig, blocks: Vec<Vec<Tx>>) -> Golden {
+    let ctx = from_genesis(genesis).await.unwrap();
+    let genesis_state_root = hex::encode(ctx.state.commit().await.unwrap());
+    let proposer = address(9);
+    let mut parent_hash = [0u8; 32];
+    let mut golden = Vec::new();
+    for (height, transactions) in blocks.into_iter().enumerate() {
+        let height = height as u64;
+        let mut block = Block {
+            header: BlockHeader {
+                parent_hash,
+                height,
+                timestamp: 1_700_000_000_000 + height * 1_000,
+                proposer_id: proposer,
+                state_root: [0u8; 32],
+                l1_tx_root: tx_root(&transactions),
+                da_commitment: None,
+                domain_roots: vec![],
+                gas_used: 0,
+                gas_limit: ctx.max_gas_per_block,
+                base_fee: ctx.base_fee,
+                snapshot_root: None,
+                protocol_version: 0,
+                consensus_metadata: serde_json::json!({ "view": height }),
+            },
+            transactions,
+            da_blobs: vec![],
+        };
+        let result = apply_block(&ctx, &block).await.unwrap();
+        block.header.state_root = result.state_root;
+        block.header.gas_used = result.gas_used;
+        parent_hash = hash_block(&block);
+        golden.push(GoldenBlock {
+            height,
+            hash: hex::encode(parent_hash),
+            state_root: hex::encode(result.state_root),
+            tx_hashes: block
+                .transactions
+                .iter()
+                .map(|tx| hex::encode(tx_hash(tx)))
+                .collect(),
+        });
+    }
+    Golden {
+        genesis_state_root,
+        blocks: golden,
+    }
+}
+
+async fn check(name: &str, scenario: fn() -> (GenesisConfig, Vec<Vec<Tx>>)) {
+    let (genesis, blocks) = scenario();
+    let actual = replay(genesis, blocks).await;
+    let (genesis, blocks) = scenario();
+    assert_eq!(
+        actual,
+        replay(genesis, blocks).await,
+        "{name}: replaying the same scenario gave different hashes"
+    );
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.json"));
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        let json = serde_json::to_string_pretty(&actual).unwrap();
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, json + "\n").unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("{}: {err}; run with {UPDATE_ENV}=1", path.display()));
+    let expected: Golden = serde_json::from_str(&expected).unwrap();
+    assert_eq!(
+        expected.genesis_state_root, actual.genesis_state_root,
+        "{name}: genesis state root changed; if intended, rerun with {UPDATE_ENV}=1"
+    );
+    assert_eq!(
+        expected.blocks.len(),
+        actual.blocks.len(),
+        "{name}: block count"
+    );
+    for (expected, actual) in expected.blocks.iter().zip(&actual.blocks) {
+        assert_eq!(
+            expected, actual,
+            "{name}: block {} changed; if intended, rerun with {UPDATE_ENV}=1",
+            expected.height
+        );
+    }
+}
+
+#[tokio::test]
+async fn transfers_match_golden() {
+    check("transfers", transfers).await;
+}
+
+#[tokio::test]
+async fn staking_matches_golden() {
+    check("staking", staking).await;
+}
@@ -0,0 +1,375 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use runtime::{
+    apply_block, apply_tx, bootstrap_state, Address, Block, BlockHeader, ExecutionContext,
+    ManualClock, Tx, TxPayload, FEE_SPLIT_PROPOSAL, PARAM_CHANGE_PROPOSAL, TREASURY_SPEND_PROPOSAL,
+};
+use serde_json::json;
+use state::{InMemoryStateStore, Proposal, ProposalStatus, StateStore};
+use uuid::Uuid;
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::legacy(1_000_000, 50_000);
+
+fn block_at(height: u64, timestamp: u64, transactions: Vec<Tx>) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions,
+        da_blobs: vec![],
+    }
+}
+
+/// Inserts a proposal that passed and whose timelock is over.
+async fn queue(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    proposer: Address,
+    kind: &str,
+    execution: serde_json::Value,
+) -> Uuid {
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    let id = Uuid::new_v4();
+    chain.proposals.insert(
+        id,
+        Proposal {
+            id,
+            payload: execution.clone(),
+            kind: kind.into(),
+            status: ProposalStatus::Queued,
+            proposer,
+            start: 0,
+            end: 0,
+            eta: Some(0),
+            snapshot_total_stake: 0,
+            for_votes: 0,
+            against_votes: 0,
+            abstain_votes: 0,
+            votes: Vec::new(),
+            execution,
+            voter_weights: HashMap::new(),
+            approvals: Vec::new(),
+            deposit: 0,
+        },
+    );
+    ctx.state.put_chain_state(chain).await.unwrap();
+    id
+}
+
+#[tokio::test]
+async fn executed_param_changes_update_chain_parameters() {
+    let ctx = bootstrap_state();
+    let (sk, sender) = FIXTURE.funded(&ctx, 1).await;
+
+    let slash = json!({ "param": "slash_penalty_bps", "value": 250 });
+    let quorum = json!({ "param": "quorum_bps", "value": 3_000 });
+    let slash_id = queue(&ctx, sender, PARAM_CHANGE_PROPOSAL, slash).await;
+    let quorum_id = queue(&ctx, sender, PARAM_CHANGE_PROPOSAL, quorum).await;
+    let execute = TxPayload::GovernanceExecute {
+        proposal_id: slash_id,
+    };
+    let outcome = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, execute), 0)
+        .await
+        .unwrap();
+    let event = outcome
+        .events
+        .iter()
+        .find(|e| e.kind == PARAM_CHANGE_PROPOSAL)
+        .unwrap();
+    assert_eq!(event.attributes["param"], "slash_penalty_bps");
+    assert_eq!(event.attributes["value"], "250");
+    let execute = TxPayload::GovernanceExecute {
+        proposal_id: quorum_id,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, execute), 0)
+        .await
+        .unwrap();
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.param_overrides.slash_penalty_bps, Some(250));
+    assert_eq!(chain.governance_params.quorum_bps, 3_000);
+    assert_eq!(chain.proposals[&slash_id].status, ProposalStatus::Executed);
+
+    // Invalid payloads are refused at submission.
+    let bad = TxPayload::GovernanceProposal {
+        payload: json!({ "param": "quorum_bps", "value": 20_000 }),
+        kind: Some(PARAM_CHANGE_PROPOSAL.into()),
+    };
+    assert!(apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 2, bad), 0)
+        .await
+        .is_err());
+    let unknown = TxPayload::GovernanceProposal {
+        payload: json!({ "param": "block_reward", "value": 1 }),
+        kind: Some(PARAM_CHANGE_PROPOSAL.into()),
+    };
+    assert!(apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 2, unknown), 0)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn fee_split_update_routes_later_gas_fees() {
+    let ctx = bootstrap_state();
+    let (sk, sender) = FIXTURE.funded(&ctx, 2).await;
+    let split = json!({
+        "l1_gas_burn_pct": 100,
+        "l1_gas_validators_pct": 0,
+        "da_validators_pct": 70,
+        "da_nodes_pct": 20,
+        "da_treasury_pct": 10,
+        "l2_sequencer_pct": 50,
+        "l2_da_costs_pct": 30,
+        "l2_l1_rent_pct": 20,
+    });
+    let mut unbalanced = split.clone();
+    unbalanced["l1_gas_burn_pct"] = json!(90);
+    let bad = TxPayload::GovernanceProposal {
+        payload: unbalanced,
+        kind: Some(FEE_SPLIT_PROPOSAL.into()),
+    };
+    assert!(apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, bad), 0)
+        .await
+        .is_err());
+
+    let id = queue(&ctx, sender, FEE_SPLIT_PROPOSAL, split).await;
+    let execute = TxPayload::GovernanceExecute { proposal_id: id };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, execute), 0)
+        .await
+        .unwrap();
+
+    let before = ctx.state.get_chain_state().await.unwrap().fee_pools;
+    let transfer = TxPayload::Transfer {
+        to: [9u8; 32],
+        amount: 10,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, transfer), 0)
+        .await
+        .unwrap();
+    let after = ctx.state.get_chain_state().await.unwrap().fee_pools;
+    assert_eq!(after.l1_gas, before.l1_gas);
+    assert!(after.treasury > before.treasury);
+}
+
+#[tokio::test]
+async fn treasury_spend_pays_recipient_from_treasury() {
+    let ctx = bootstrap_state();
+    let (sk, sender) = FIXTURE.funded(&ctx, 3).await;
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    chain.fee_pools.treasury = 1_000;
+    ctx.state.put_chain_state(chain).await.unwrap();
+
+    let recipient = [5u8; 32];
+    let spend = json!({ "recipient": hex::encode(recipient), "amount": 400 });
+    let id = queue(&ctx, sender, TREASURY_SPEND_PROPOSAL, spend).await;
+    let execute = TxPayload::GovernanceExecute { proposal_id: id };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, execute), 0)
+        .await
+        .unwrap();
+    let account = ctx.state.get_account(&recipient).await.unwrap().unwrap();
+    assert_eq!(account.balance_x, 400);
+
+    let treasury = ctx
+        .state
+        .get_chain_state()
+        .await
+        .unwrap()
+        .fee_pools
+        .treasury;
+    let overspend = json!({ "recipient": hex::encode(recipient), "amount": treasury + 1 });
+    let id = queue(&ctx, sender, TREASURY_SPEND_PROPOSAL, overspend).await;
+    let execute = TxPayload::GovernanceExecute { proposal_id: id };
+    assert!(apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, execute), 0)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn cancelled_proposals_refund_their_deposit() {
+    let ctx = bootstrap_state();
+    let (sk, sender) = FIXTURE.funded(&ctx, 4).await;
+    let (other, _) = FIXTURE.funded(&ctx, 5).await;
+    let deposit = ctx
+        .state
+        .get_chain_state()
+        .await
+        .unwrap()
+        .governance_params
+        .min_deposit;
+
+    let propose = TxPayload::GovernanceProposal {
+        payload: json!({ "title": "signal" }),
+        kind: None,
+    };
+    let outcome = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, propose), 0)
+        .await
+        .unwrap();
+    let proposal_id: Uuid = outcome.events[0].attributes["proposal_id"].parse().unwrap();
+    let balance = ctx
+        .state
+        .get_account(&sender)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_x;
+    assert_eq!(balance, 1_000_000 - deposit - outcome.gas_used as u128);
+
+    let cancel = TxPayload::GovernanceCancel { proposal_id };
+    assert!(
+        apply_tx(&ctx, &FIXTURE.signed_tx(&other, 0, cancel.clone()), 0)
+            .await
+            .is_err()
+    );
+    let outcome = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, cancel), 0)
+        .await
+        .unwrap();
+    let balance = balance - outcome.gas_used as u128;
+
+    let result = apply_block(&ctx, &block_at(1, 1_000, vec![]))
+        .await
+        .unwrap();
+    assert!(result
+        .events
+        .iter()
+        .any(|e| e.kind == "gov_deposit_refunded"));
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(
+        chain.proposals[&proposal_id].status,
+        ProposalStatus::Cancelled
+    );
+    assert_eq!(chain.proposals[&proposal_id].deposit, 0);
+    let account = ctx.state.get_account(&sender).await.unwrap().unwrap();
+    assert_eq!(account.balance_x, balance + deposit);
+}
+
+#[tokio::test]
+async fn unsupported_proposals_burn_and_stale_queued_ones_expire() {
+    let ctx = bootstrap_state();
+    let (sk, sender) = FIXTURE.funded(&ctx, 6).await;
+    let propose = TxPayload::GovernanceProposal {
+        payload: json!({ "title": "spam" }),
+        kind: None,
+    };
+    let outcome = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, propose), 0)
+        .await
+        .unwrap();
+    let spam: Uuid = outcome.events[0].attributes["proposal_id"].parse().unwrap();
+    let stale = queue(&ctx, sender, "general", json!({})).await;
+
+    // Voting closes with no votes, and the queued proposal's grace is over.
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    chain.proposals.get_mut(&spam).unwrap().end = 0;
+    chain.proposals.get_mut(&spam).unwrap().snapshot_total_stake = 100;
+    chain.governance_params.queued_grace_ms = 0;
+    chain.total_supply = 2_000_000;
+    let supply = chain.total_supply;
+    let deposit = chain.proposals[&spam].deposit;
+    ctx.state.put_chain_state(chain).await.unwrap();
+    let balance = ctx
+        .state
+        .get_account(&sender)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_x;
+
+    let result = apply_block(&ctx, &block_at(1, 1_000, vec![]))
+        .await
+        .unwrap();
+    assert!(result.events.iter().any(|e| e.kind == "gov_deposit_burned"));
+    assert!(result.events.iter().any(|e| e.kind == "gov_expired"));
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.proposals[&spam].status, ProposalStatus::Defeated);
+    assert_eq!(chain.proposals[&stale].status, ProposalStatus::Expired);
+    assert_eq!(chain.total_supply, supply - deposit);
+    let account = ctx.state.get_account(&sender).await.unwrap().unwrap();
+    assert_eq!(account.balance_x, balance);
+}
+
+#[tokio::test]
+async fn treasury_spends_are_capped_per_period() {
+    let ctx = bootstrap_state();
+    let (sk, sender) = FIXTURE.funded(&ctx, 7).await;
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    chain.fee_pools.treasury = 10_000;
+    chain.governance_params.treasury_spend_cap = 500;
+    chain.governance_params.treasury_spend_period_blocks = 100;
+    ctx.state.put_chain_state(chain).await.unwrap();
+
+    let recipient = hex::encode([6u8; 32]);
+    let spend = |amount: u128| {
+        let payload = json!({ "recipient": recipient, "amount": amount });
+        queue(&ctx, sender, TREASURY_SPEND_PROPOSAL, payload)
+    };
+    let first = spend(400).await;
+    let second = spend(200).await;
+    let third = spend(200).await;
+    let execute = |nonce, proposal_id| {
+        FIXTURE.signed_tx(&sk, nonce, TxPayload::GovernanceExecute { proposal_id })
+    };
+    apply_tx(&ctx, &execute(0, first), 10).await.unwrap();
+    let err = apply_tx(&ctx, &execute(1, second), 20).await.unwrap_err();
+    assert!(err.to_string().contains("cap"));
+    // A new period starts with a fresh allowance. Outside block inclusion
+    // the failed execute isn't rolled back, so its nonce is used up.
+    apply_tx(&ctx, &execute(2, third), 100).await.unwrap();
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.treasury_period.period, 1);
+    assert_eq!(chain.treasury_period.spent, 200);
+    let account = ctx.state.get_account(&[6u8; 32]).await.unwrap().unwrap();
+    assert_eq!(account.balance_x, 600);
+}
+
+#[tokio::test]
+async fn governance_deadlines_follow_block_time() {
+    let ctx = bootstrap_state();
+    let (sk, _) = FIXTURE.funded(&ctx, 8).await;
+    let propose = TxPayload::GovernanceProposal {
+        payload: json!({ "title": "timed" }),
+        kind: None,
+    };
+    let result = apply_block(
+        &ctx,
+        &block_at(0, 5_000, vec![FIXTURE.signed_tx(&sk, 0, propose)]),
+    )
+    .await
+    .unwrap();
+    let proposal_id: Uuid = result.events[0].attributes["proposal_id"].parse().unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let proposal = &chain.proposals[&proposal_id];
+    assert_eq!(proposal.start, 5_000);
+    assert_eq!(
+        proposal.end,
+        5_000 + chain.governance_params.voting_period_ms
+    );
+
+    // A pinned clock ignores block timestamps.
+    let clock = Arc::new(ManualClock::new(proposal.end));
+    let ctx = ctx.with_clock(clock.clone());
+    let cancel = TxPayload::GovernanceCancel { proposal_id };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, cancel.clone()), 1)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "voting has ended");
+    clock.set(proposal.end - 1);
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, cancel), 1)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,126 @@
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, apply_block, bootstrap_state, select_block_txs, sign_bytes,
+    tx_signing_bytes, Block, BlockHeader, ExecutionContext, Tx, TxPayload,
+};
+use state::{Account, InMemoryStateStore, StateStore};
+
+const FUNDS: u128 = 1_000_000;
+
+fn user() -> SigningKey {
+    SigningKey::from_bytes(&[6u8; 32])
+}
+
+fn transfer(nonce: u64, amount: u128) -> Tx {
+    let mut tx = Tx {
+        chain_id: "kova-devnet".into(),
+        nonce,
+        gas_limit: 21_000,
+        max_fee: None,
+        max_priority_fee: None,
+        gas_price: Some(1),
+        payload: TxPayload::Transfer {
+            to: [2u8; 32],
+            amount,
+        },
+        public_key: user().verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    };
+    tx.signature = sign_bytes(&user(), &tx_signing_bytes(&tx).unwrap());
+    tx
+}
+
+fn block(transactions: Vec<Tx>) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height: 1,
+            timestamp: 0,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 0,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions,
+        da_blobs: vec![],
+    }
+}
+
+async fn funded() -> ExecutionContext<InMemoryStateStore> {
+    let ctx = bootstrap_state();
+    let address = address_from_pubkey(&user().verifying_key().to_bytes());
+    ctx.state
+        .put_account(Account {
+            address,
+            nonce: 0,
+            balance_x: FUNDS,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await
+        .unwrap();
+    ctx
+}
+
+#[tokio::test]
+async fn failed_tx_is_charged_and_block_continues() {
+    let sender = address_from_pubkey(&user().verifying_key().to_bytes());
+    let txs = vec![transfer(0, 10), transfer(1, FUNDS * 2), transfer(2, 10)];
+
+    let mut roots = Vec::new();
+    for _ in 0..2 {
+        let ctx = funded().await;
+        let result = apply_block(&ctx, &block(txs.clone())).await.unwrap();
+        assert_eq!(result.receipts.len(), 3);
+        assert!(result.receipts[0].succeeded());
+        assert!(!result.receipts[1].succeeded());
+        assert!(result.receipts[2].succeeded());
+        assert_eq!(result.gas_used, 3 * 21_000);
+
+        let account = ctx.state.get_account(&sender).await.unwrap().unwrap();
+        assert_eq!(account.nonce, 3);
+        assert_eq!(account.balance_x, FUNDS - 20 - 3 * 21_000);
+        let recipient = ctx.state.get_account(&[2u8; 32]).await.unwrap().unwrap();
+        assert_eq!(recipient.balance_x, 20);
+        roots.push(result.state_root);
+    }
+    assert_eq!(roots[0], roots[1]);
+}
+
+#[tokio::test]
+async fn unadmissible_txs_reject_the_block_and_are_filtered_at_proposal() {
+    let ctx = funded().await;
+    let mut forged = transfer(1, 10);
+    forged.signature[0] ^= 1;
+    assert!(apply_block(&ctx, &block(vec![transfer(1, 10)]))
+        .await
+        .is_err());
+    assert!(apply_block(&ctx, &block(vec![forged.clone()]))
+        .await
+        .is_err());
+
+    let ctx = funded().await;
+    let candidates = vec![
+        transfer(1, 10),
+        forged,
+        transfer(0, FUNDS * 2),
+        transfer(0, 10),
+    ];
+    let selection = select_block_txs(&ctx, candidates, 1).await.unwrap();
+    let nonces: Vec<u64> = selection.included.iter().map(|tx| tx.nonce).collect();
+    assert_eq!(nonces, vec![0, 1]);
+    assert_eq!(selection.rejected.len(), 2);
+    assert!(selection.deferred.is_empty());
+
+    let result = apply_block(&ctx, &block(selection.included)).await.unwrap();
+    assert!(!result.receipts[0].succeeded());
+    assert!(result.receipts[1].succeeded());
+}
@@ -0,0 +1,177 @@
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, apply_tx, bootstrap_state, client_domain_id, header_signing_bytes,
+    packet_leaf, sign_bytes, tx_signing_bytes, Address, CrossDomainPacket, DomainProof,
+    ExecutionContext, HeaderSignature, LightClientHeader, Tx, TxPayload, ValidatorSet,
+};
+use state::{Account, InMemoryStateStore, StateStore};
+use uuid::Uuid;
+
+fn signer() -> SigningKey {
+    SigningKey::from_bytes(&[12u8; 32])
+}
+
+fn sender() -> Address {
+    address_from_pubkey(&signer().verifying_key().to_bytes())
+}
+
+async fn apply(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    nonce: u64,
+    payload: TxPayload,
+) -> anyhow::Result<()> {
+    let mut tx = Tx {
+        chain_id: "kova-devnet".into(),
+        nonce,
+        gas_limit: 300_000,
+        max_fee: Some(1),
+        max_priority_fee: Some(0),
+        gas_price: None,
+        payload,
+        public_key: signer().verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    };
+    tx.signature = sign_bytes(&signer(), &tx_signing_bytes(&tx)?);
+    apply_tx(ctx, &tx, nonce).await.map(|_| ())
+}
+
+fn validator_set(keys: &[SigningKey], threshold: u32) -> ValidatorSet {
+    ValidatorSet {
+        validators: keys
+            .iter()
+            .map(|k| k.verifying_key().to_bytes().to_vec())
+            .collect(),
+        threshold,
+    }
+}
+
+fn sign(keys: &[SigningKey], header: &LightClientHeader) -> Vec<HeaderSignature> {
+    let msg = header_signing_bytes("ext-1", header);
+    keys.iter()
+        .map(|k| HeaderSignature {
+            public_key: k.verifying_key().to_bytes().to_vec(),
+            signature: sign_bytes(k, &msg),
+        })
+        .collect()
+}
+
+fn packet(to: Uuid, sequence: u64) -> CrossDomainPacket {
+    CrossDomainPacket {
+        src_domain: "ext-1".into(),
+        dst_domain: to.to_string(),
+        sequence,
+        payload: serde_json::json!({"action": "invoke", "module_id": "m"}),
+        timeout_height: 0,
+    }
+}
+
+/// Proof of a packet that is the only commitment under its header's root.
+fn sole_leaf(packet: &CrossDomainPacket) -> DomainProof {
+    DomainProof {
+        leaf: packet_leaf(packet),
+        index: 0,
+        path: vec![],
+    }
+}
+
+#[tokio::test]
+async fn light_clients_follow_signed_headers_and_deliver_proven_packets() -> anyhow::Result<()> {
+    let ctx = bootstrap_state();
+    ctx.state
+        .put_account(Account {
+            address: sender(),
+            nonce: 0,
+            balance_x: 10_000_000,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await?;
+    let dest = Uuid::new_v4();
+    apply(
+        &ctx,
+        0,
+        TxPayload::DomainCreate {
+            domain_id: dest,
+            params: serde_json::json!({"kind": "wasm"}),
+        },
+    )
+    .await?;
+
+    let keys: Vec<SigningKey> = (1..=3u8).map(|i| SigningKey::from_bytes(&[i; 32])).collect();
+    let set = validator_set(&keys, 2);
+    let first = packet(dest, 0);
+    let genesis = LightClientHeader {
+        state_root: packet_leaf(&first),
+        validator_set_hash: set.hash(),
+        height: 100,
+    };
+    let create = TxPayload::LightClientCreate {
+        client_id: "ext".into(),
+        chain_id: "ext-1".into(),
+        header: genesis,
+        validators: set.clone(),
+    };
+    apply(&ctx, 1, create).await?;
+
+    let deliver = |height, packet: CrossDomainPacket, proof| TxPayload::LightClientPacket {
+        client_id: "ext".into(),
+        height,
+        packet,
+        proof,
+    };
+    let tampered = CrossDomainPacket {
+        payload: serde_json::json!({"action": "drain"}),
+        ..first.clone()
+    };
+    let err = apply(&ctx, 2, deliver(100, tampered, sole_leaf(&first)))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not committed"), "{err}");
+    apply(&ctx, 2, deliver(100, first.clone(), sole_leaf(&first))).await?;
+    let err = apply(&ctx, 3, deliver(100, first.clone(), sole_leaf(&first)))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("already relayed"), "{err}");
+
+    // The next header rotates to a new set; one signature is not enough.
+    let next_keys: Vec<SigningKey> = (4..=5u8).map(|i| SigningKey::from_bytes(&[i; 32])).collect();
+    let next_set = validator_set(&next_keys, 2);
+    let second = packet(dest, 1);
+    let header = LightClientHeader {
+        state_root: packet_leaf(&second),
+        validator_set_hash: next_set.hash(),
+        height: 101,
+    };
+    let update = |signatures, next_validators| TxPayload::LightClientUpdate {
+        client_id: "ext".into(),
+        header: header.clone(),
+        signatures,
+        next_validators,
+    };
+    let err = apply(&ctx, 3, update(sign(&keys[..1], &header), Some(next_set.clone())))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("1 of 2"), "{err}");
+    let err = apply(&ctx, 3, update(sign(&next_keys, &header), Some(next_set.clone())))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("0 of 2"), "{err}");
+    let err = apply(&ctx, 3, update(sign(&keys[1..], &header), None))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("rotates the validator set"), "{err}");
+    apply(&ctx, 3, update(sign(&keys[1..], &header), Some(next_set))).await?;
+
+    apply(&ctx, 4, deliver(101, second.clone(), sole_leaf(&second))).await?;
+    let chain = ctx.state.get_chain_state().await?;
+    let client = &chain.light_clients["ext"];
+    assert_eq!(client.latest_height(), 101);
+    assert_eq!(client.validators, validator_set(&next_keys, 2).validators);
+
+    let inbox = ctx.domains.domain_state(&dest).inbox;
+    assert_eq!(inbox.len(), 2);
+    assert!(inbox.iter().all(|m| m.from == client_domain_id("ext")));
+    assert_eq!(inbox.iter().map(|m| m.nonce).collect::<Vec<_>>(), vec![0, 1]);
+    Ok(())
+}
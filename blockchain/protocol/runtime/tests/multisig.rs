@@ -0,0 +1,138 @@
+mod common;
+
+use ed25519_dalek::SigningKey;
+use runtime::{apply_tx, bootstrap_state, MultisigCall, TxPayload};
+use state::{multisig_address, StateStore};
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::legacy(1_000_000, 100_000);
+
+#[tokio::test]
+async fn two_of_three_multisig_spends_after_threshold() {
+    let ctx = bootstrap_state();
+    let (a_sk, a) = FIXTURE.funded(&ctx, 1).await;
+    let (b_sk, b) = FIXTURE.funded(&ctx, 2).await;
+    let (c_sk, c) = FIXTURE.funded(&ctx, 3).await;
+    let recipient = [9u8; 32];
+
+    let create = TxPayload::MultisigCreate {
+        signers: vec![a, b, c],
+        threshold: 2,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&a_sk, 0, create), 1)
+        .await
+        .unwrap();
+    let multisig = multisig_address(&a, 0);
+    let fund = TxPayload::Transfer {
+        to: multisig,
+        amount: 5_000,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&c_sk, 0, fund), 1)
+        .await
+        .unwrap();
+
+    let submit = TxPayload::MultisigSubmit {
+        multisig,
+        call: MultisigCall::Transfer {
+            to: recipient,
+            amount: 4_000,
+        },
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&a_sk, 1, submit), 2)
+        .await
+        .unwrap();
+
+    let execute = TxPayload::MultisigExecute {
+        multisig,
+        proposal_id: 0,
+    };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&a_sk, 2, execute.clone()), 2)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("1 of 2 approvals"));
+
+    let approve = TxPayload::MultisigApprove {
+        multisig,
+        proposal_id: 0,
+    };
+    let outsider = SigningKey::from_bytes(&[7u8; 32]);
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&outsider, 0, approve.clone()), 2)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not a multisig signer"));
+    apply_tx(&ctx, &FIXTURE.signed_tx(&b_sk, 0, approve), 2)
+        .await
+        .unwrap();
+
+    let outcome = apply_tx(&ctx, &FIXTURE.signed_tx(&a_sk, 2, execute.clone()), 3)
+        .await
+        .unwrap();
+    assert_eq!(outcome.events[0].kind, "multisig_execute");
+    assert_eq!(outcome.events[0].attribute("call"), Some("transfer"));
+
+    let multisig_account = ctx.state.get_account(&multisig).await.unwrap().unwrap();
+    assert_eq!(multisig_account.balance_x, 1_000);
+    let recipient_account = ctx.state.get_account(&recipient).await.unwrap().unwrap();
+    assert_eq!(recipient_account.balance_x, 4_000);
+
+    // Executed calls can't be replayed.
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&b_sk, 1, execute), 3)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("proposal not found"));
+}
+
+#[tokio::test]
+async fn changing_signers_drops_pending_calls() {
+    let ctx = bootstrap_state();
+    let (a_sk, a) = FIXTURE.funded(&ctx, 1).await;
+    let (_, b) = FIXTURE.funded(&ctx, 2).await;
+
+    let create = TxPayload::MultisigCreate {
+        signers: vec![a, b],
+        threshold: 1,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&a_sk, 0, create), 1)
+        .await
+        .unwrap();
+    let multisig = multisig_address(&a, 0);
+
+    let transfer = TxPayload::MultisigSubmit {
+        multisig,
+        call: MultisigCall::Transfer { to: b, amount: 0 },
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&a_sk, 1, transfer), 1)
+        .await
+        .unwrap();
+    let rotate = TxPayload::MultisigSubmit {
+        multisig,
+        call: MultisigCall::SetSigners {
+            signers: vec![a],
+            threshold: 1,
+        },
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&a_sk, 2, rotate), 1)
+        .await
+        .unwrap();
+    let execute = TxPayload::MultisigExecute {
+        multisig,
+        proposal_id: 1,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&a_sk, 3, execute), 1)
+        .await
+        .unwrap();
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let ms = &chain.multisigs[&multisig];
+    assert_eq!(ms.signers, vec![a]);
+    assert!(ms.pending.is_empty());
+
+    let invalid = TxPayload::MultisigCreate {
+        signers: vec![a, a],
+        threshold: 1,
+    };
+    assert!(apply_tx(&ctx, &FIXTURE.signed_tx(&a_sk, 4, invalid), 1)
+        .await
+        .is_err());
+}
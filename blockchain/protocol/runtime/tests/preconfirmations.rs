@@ -0,0 +1,220 @@
+mod common;
+
+use ed25519_dalek::SigningKey;
+use runtime::{
+    apply_block, apply_tx, batch_calls, bootstrap_state, calls_root, tx_hash, Address, Block,
+    BlockHeader, DomainCall, ExecutionContext, Preconfirmation, Tx, TxPayload,
+};
+use state::{InMemoryStateStore, StateStore};
+use uuid::Uuid;
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::dynamic(10_000_000, 300_000);
+
+fn block(height: u64) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp: 0,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions: vec![],
+        da_blobs: vec![],
+    }
+}
+
+fn call(sk: &SigningKey, domain_id: Uuid, step: u64) -> Tx {
+    let call = DomainCall {
+        domain_id,
+        payload: serde_json::json!({ "step": step }),
+        raw: vec![],
+        max_gas: None,
+    };
+    FIXTURE.signed_tx(sk, step, TxPayload::DomainExecute(call))
+}
+
+/// Claims the batch `blob` carries, committing to its calls.
+fn claim(domain_id: Uuid, blob_id: &str, blob: &[u8]) -> TxPayload {
+    TxPayload::RollupBatchClaim {
+        domain_id,
+        blob_id: blob_id.into(),
+        state_root: [5u8; 32],
+        calls_root: calls_root(&batch_calls(&domain_id, blob).unwrap()),
+        steps: 1,
+    }
+}
+
+async fn balance(ctx: &ExecutionContext<InMemoryStateStore>, address: &Address) -> u128 {
+    ctx.state
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_x
+}
+
+async fn bond(ctx: &ExecutionContext<InMemoryStateStore>, domain_id: &Uuid) -> u128 {
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    chain.sequencer_bonds[domain_id][0].bond
+}
+
+#[tokio::test]
+async fn unkept_preconfirmations_slash_the_sequencer_and_kept_ones_can_be_proven() {
+    let ctx = bootstrap_state();
+    let (sequencer, sequencer_addr) = FIXTURE.funded(&ctx, 1).await;
+    let (challenger, challenger_addr) = FIXTURE.funded(&ctx, 2).await;
+    let (user, _) = FIXTURE.funded(&ctx, 3).await;
+    let domain_id = Uuid::new_v4();
+    let create = TxPayload::DomainCreate {
+        domain_id,
+        params: serde_json::json!({
+            "kind": "wasm",
+            "challenge_window_blocks": 5,
+            "response_blocks": 2,
+            "claim_bond": 1_000,
+            "min_sequencer_bond": 1_000,
+            "missed_round_slash_bps": 0,
+        }),
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sequencer, 0, create), 0)
+        .await
+        .unwrap();
+    let register = TxPayload::SequencerRegister {
+        domain_id,
+        bond: 50_000,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sequencer, 1, register), 0)
+        .await
+        .unwrap();
+
+    // Both txs are promised for batch 1; only the first makes it.
+    let kept = call(&user, domain_id, 0);
+    let dropped = call(&user, domain_id, 1);
+    let promise =
+        |tx: &Tx| Preconfirmation::sign(&sequencer, "kova-devnet", domain_id, tx_hash(tx), 0, 1);
+    let (kept_promise, dropped_promise) = (promise(&kept), promise(&dropped));
+    assert_eq!(kept_promise.verify().unwrap(), sequencer_addr);
+
+    let first = zk_program_rollup::encode_batch(std::slice::from_ref(&kept)).unwrap();
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&sequencer, 2, claim(domain_id, "first", &first)),
+        1,
+    )
+    .await
+    .unwrap();
+    let challenge = |preconfirmation: &Preconfirmation| TxPayload::PreconfirmationChallenge {
+        preconfirmation: preconfirmation.clone(),
+    };
+    let err = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&challenger, 0, challenge(&dropped_promise)),
+        2,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("has not expired"));
+
+    apply_block(&ctx, &block(7)).await.unwrap();
+    let later = zk_program_rollup::encode_batch(&[call(&user, domain_id, 2)]).unwrap();
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&sequencer, 3, claim(domain_id, "later", &later)),
+        8,
+    )
+    .await
+    .unwrap();
+
+    let mut forged = dropped_promise.clone();
+    forged.expiry = 0;
+    let err = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&challenger, 0, challenge(&forged)),
+        9,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("signature"));
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&challenger, 0, challenge(&kept_promise)),
+        9,
+    )
+    .await
+    .unwrap();
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&challenger, 1, challenge(&dropped_promise)),
+        9,
+    )
+    .await
+    .unwrap();
+    let err = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&challenger, 2, challenge(&dropped_promise)),
+        9,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("already challenged"));
+
+    // Anyone holding the batch can answer for the sequencer.
+    let fulfill = |tx: &Tx, batch_height, blob: &[u8]| TxPayload::PreconfirmationFulfill {
+        tx_hash: tx_hash(tx),
+        batch_height,
+        blob: blob.to_vec(),
+    };
+    let err = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&user, 0, fulfill(&dropped, 1, &first)),
+        10,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("does not carry the tx"));
+    let err = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&user, 0, fulfill(&kept, 1, &later)),
+        10,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("does not carry the tx"));
+    let held = balance(&ctx, &sequencer_addr).await;
+    let outcome = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&user, 0, fulfill(&kept, 1, &first)),
+        10,
+    )
+    .await
+    .unwrap();
+    assert!(outcome.events.iter().any(|e| e.kind == "preconf_fulfilled"));
+    assert_eq!(balance(&ctx, &sequencer_addr).await, held + 1_000);
+
+    // The dropped promise goes unanswered past its deadline.
+    let before = bond(&ctx, &domain_id).await;
+    let refundable = balance(&ctx, &challenger_addr).await;
+    let result = apply_block(&ctx, &block(12)).await.unwrap();
+    let slashed = result
+        .events
+        .iter()
+        .find(|e| e.kind == "sequencer_slashed")
+        .unwrap();
+    assert_eq!(slashed.attribute("reason"), Some("preconf_violation"));
+    assert_eq!(bond(&ctx, &domain_id).await, before - before / 10);
+    assert_eq!(balance(&ctx, &challenger_addr).await, refundable + 1_000);
+    let result = apply_block(&ctx, &block(13)).await.unwrap();
+    assert!(!result.events.iter().any(|e| e.kind == "sequencer_slashed"));
+}
@@ -0,0 +1,265 @@
+mod common;
+
+use std::collections::HashMap;
+
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, apply_tx, bootstrap_state, Address, ExecutionContext, Hash, TxPayload,
+    PRIVACY_POOL_PROPOSAL, VERIFICATION_KEY_PROPOSAL,
+};
+use serde_json::json;
+use state::{InMemoryStateStore, Proposal, ProposalStatus, StateStore};
+use uuid::Uuid;
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::legacy(1_000_000, 200_000);
+
+/// Executes a `kind` proposal that already passed.
+async fn execute(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    sk: &SigningKey,
+    nonce: u64,
+    kind: &str,
+    execution: serde_json::Value,
+) -> anyhow::Result<()> {
+    let proposer = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let mut chain = ctx.state.get_chain_state().await?;
+    let id = Uuid::new_v4();
+    chain.proposals.insert(
+        id,
+        Proposal {
+            id,
+            payload: execution.clone(),
+            kind: kind.into(),
+            status: ProposalStatus::Queued,
+            proposer,
+            start: 0,
+            end: 0,
+            eta: Some(0),
+            snapshot_total_stake: 0,
+            for_votes: 0,
+            against_votes: 0,
+            abstain_votes: 0,
+            votes: Vec::new(),
+            execution,
+            voter_weights: HashMap::new(),
+            approvals: Vec::new(),
+            deposit: 0,
+        },
+    );
+    ctx.state.put_chain_state(chain).await?;
+    let execute = TxPayload::GovernanceExecute { proposal_id: id };
+    apply_tx(ctx, &FIXTURE.signed_tx(sk, nonce, execute), 0).await?;
+    Ok(())
+}
+
+/// Registers `pool` through a governance proposal that already passed.
+async fn register_pool(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    sk: &SigningKey,
+    nonce: u64,
+    pool: &str,
+    denomination: u128,
+) {
+    let execution = json!({ "pool": pool, "denomination": denomination });
+    execute(ctx, sk, nonce, PRIVACY_POOL_PROPOSAL, execution)
+        .await
+        .unwrap();
+}
+
+fn deposit(pool: Option<&str>, commitment: Hash, amount: u128) -> TxPayload {
+    TxPayload::PrivacyDeposit {
+        commitment,
+        amount,
+        pool: pool.map(Into::into),
+        memo: None,
+    }
+}
+
+async fn withdraw(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    pool: Option<&str>,
+    nullifier: Hash,
+    recipient: Address,
+    amount: u128,
+    commitment: Hash,
+) -> TxPayload {
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let merkle_root = chain.privacy_pools[pool.unwrap_or("shielded")].merkle_root;
+    let input = zk_program_privacy::PrivacyWithdrawInput {
+        nullifier,
+        merkle_root,
+        recipient,
+        amount,
+        commitment,
+        fee: 0,
+        relayer: None,
+        relayer_fee: 0,
+    };
+    TxPayload::PrivacyWithdraw {
+        nullifier,
+        recipient,
+        amount,
+        merkle_root,
+        commitment,
+        proof: zk_program_privacy::stub_withdraw_proof(&input).unwrap(),
+        pool: pool.map(Into::into),
+        relayer: None,
+        relayer_fee: 0,
+    }
+}
+
+#[tokio::test]
+async fn registered_pools_enforce_their_denomination() {
+    let ctx = bootstrap_state();
+    let (sk, _) = FIXTURE.funded(&ctx, 1).await;
+    register_pool(&ctx, &sk, 0, "notes-1k", 1_000).await;
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.privacy_pools["notes-1k"].denomination, Some(1_000));
+
+    let odd_amount = deposit(Some("notes-1k"), [1u8; 32], 500);
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, odd_amount), 1)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("only takes notes of 1000"));
+    let unregistered = deposit(Some("missing"), [1u8; 32], 1_000);
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, unregistered), 1)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not registered"));
+    let exact = deposit(Some("notes-1k"), [1u8; 32], 1_000);
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, exact), 1)
+        .await
+        .unwrap();
+
+    // The default pool still takes any amount and keeps its own tree.
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&sk, 2, deposit(None, [1u8; 32], 7)),
+        2,
+    )
+    .await
+    .unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let registered = chain.privacy_pools["notes-1k"].stats("notes-1k");
+    let default = chain.privacy_pools["shielded"].stats("shielded");
+    assert_eq!(registered.total_shielded, 1_000);
+    assert_eq!(default.total_shielded, 7);
+    assert_eq!(registered.merkle_root, default.merkle_root);
+
+    // Malformed pool ids are refused at submission.
+    let proposal = TxPayload::GovernanceProposal {
+        payload: json!({ "pool": "bad pool!", "denomination": 1 }),
+        kind: Some(PRIVACY_POOL_PROPOSAL.into()),
+    };
+    assert!(apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 3, proposal), 3)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn nullifier_sets_are_per_pool() {
+    let ctx = bootstrap_state();
+    let (sk, _) = FIXTURE.funded(&ctx, 1).await;
+    register_pool(&ctx, &sk, 0, "notes-1k", 1_000).await;
+
+    let nullifier = [6u8; 32];
+    let recipient = [9u8; 32];
+    let small = zk_program_privacy::note_commitment(&nullifier, &recipient, 10, &[7u8; 32]);
+    let large = zk_program_privacy::note_commitment(&nullifier, &recipient, 1_000, &[7u8; 32]);
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&sk, 1, deposit(None, small, 10)),
+        1,
+    )
+    .await
+    .unwrap();
+    let large_deposit = deposit(Some("notes-1k"), large, 1_000);
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 2, large_deposit), 1)
+        .await
+        .unwrap();
+
+    let spend_small = withdraw(&ctx, None, nullifier, recipient, 10, small).await;
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 3, spend_small.clone()), 2)
+        .await
+        .unwrap();
+    assert!(apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 4, spend_small), 2)
+        .await
+        .is_err());
+
+    // The same nullifier is still unspent in the other pool.
+    let spend_large = withdraw(&ctx, Some("notes-1k"), nullifier, recipient, 1_000, large).await;
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 4, spend_large), 3)
+        .await
+        .unwrap();
+    let account = ctx.state.get_account(&recipient).await.unwrap().unwrap();
+    assert_eq!(account.balance_x, 1_010);
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let stats = chain.privacy_pools["notes-1k"].stats("notes-1k");
+    assert_eq!((stats.deposits, stats.withdrawals), (1, 1));
+    assert_eq!(stats.total_shielded, 0);
+}
+
+#[tokio::test]
+async fn withdrawals_need_the_registered_verification_key() {
+    let ctx = bootstrap_state();
+    let (sk, _) = FIXTURE.funded(&ctx, 1).await;
+    let v1 = json!({
+        "program": "PrivacyWithdraw",
+        "version": "1",
+        "verification_key": hex::encode([0xaa; 32]),
+        "activate": true,
+    });
+    execute(&ctx, &sk, 0, VERIFICATION_KEY_PROPOSAL, v1)
+        .await
+        .unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let registered = chain.verification_keys.active_key("privacy_withdraw");
+    assert_eq!(registered, Some(&[0xaa; 32][..]));
+
+    let nullifier = [6u8; 32];
+    let recipient = [9u8; 32];
+    let commitment = zk_program_privacy::note_commitment(&nullifier, &recipient, 10, &[7u8; 32]);
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&sk, 1, deposit(None, commitment, 10)),
+        1,
+    )
+    .await
+    .unwrap();
+    let with_key = |key: Option<Vec<u8>>, mut payload: TxPayload| {
+        if let TxPayload::PrivacyWithdraw { proof, .. } = &mut payload {
+            proof.verification_key = key;
+        }
+        payload
+    };
+    let spend = withdraw(&ctx, None, nullifier, recipient, 10, commitment).await;
+
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 2, spend.clone()), 2)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("no verification key"));
+    let wrong_key = with_key(Some(vec![0xbb; 32]), spend.clone());
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 2, wrong_key), 2)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not the registered"));
+    let right_key = with_key(Some(vec![0xaa; 32]), spend);
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 2, right_key), 2)
+        .await
+        .unwrap();
+
+    // A registered version keeps its key; upgrades register a new version.
+    let rekey = json!({
+        "program": "PrivacyWithdraw",
+        "version": "1",
+        "verification_key": hex::encode([0xbb; 32]),
+        "activate": true,
+    });
+    assert!(execute(&ctx, &sk, 3, VERIFICATION_KEY_PROPOSAL, rekey)
+        .await
+        .is_err());
+}
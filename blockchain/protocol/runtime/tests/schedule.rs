@@ -0,0 +1,144 @@
+mod common;
+
+use runtime::{
+    apply_block, apply_tx, bootstrap_state, gas_cost, Block, BlockHeader, Tx, TxPayload,
+};
+use state::StateStore;
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::legacy(1_000_000, 100_000);
+
+fn block(height: u64, transactions: Vec<Tx>) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp: 0,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions,
+        da_blobs: vec![],
+    }
+}
+
+#[tokio::test]
+async fn scheduled_call_runs_at_its_height_and_pays_its_own_gas() {
+    let ctx = bootstrap_state();
+    let (sk, sender) = FIXTURE.funded(&ctx, 1).await;
+    let recipient = [9u8; 32];
+    let inner = TxPayload::Transfer {
+        to: recipient,
+        amount: 1_000,
+    };
+    let schedule = TxPayload::Schedule {
+        execute_at_height: 3,
+        inner: Box::new(inner.clone()),
+    };
+    apply_block(
+        &ctx,
+        &block(1, vec![FIXTURE.signed_tx(&sk, 0, schedule.clone())]),
+    )
+    .await
+    .unwrap();
+
+    let result = apply_block(&ctx, &block(2, vec![])).await.unwrap();
+    assert!(result.events.iter().all(|e| e.kind != "scheduled_call"));
+    assert!(ctx.state.get_account(&recipient).await.unwrap().is_none());
+
+    let result = apply_block(&ctx, &block(3, vec![])).await.unwrap();
+    let ran = result
+        .events
+        .iter()
+        .find(|e| e.kind == "scheduled_call")
+        .unwrap();
+    assert_eq!(ran.attribute("schedule_id"), Some("0"));
+    assert_eq!(ran.attribute("success"), Some("true"));
+    assert!(result.events.iter().any(|e| e.kind == "transfer"));
+
+    let recipient_account = ctx.state.get_account(&recipient).await.unwrap().unwrap();
+    assert_eq!(recipient_account.balance_x, 1_000);
+    let sender_account = ctx.state.get_account(&sender).await.unwrap().unwrap();
+    let gas = (gas_cost(&schedule) + gas_cost(&inner)) as u128;
+    assert_eq!(sender_account.balance_x, 1_000_000 - 1_000 - gas);
+    // The scheduled call took the sender's next nonce.
+    assert_eq!(sender_account.nonce, 2);
+    assert!(ctx
+        .state
+        .get_chain_state()
+        .await
+        .unwrap()
+        .schedule
+        .calls
+        .is_empty());
+}
+
+#[tokio::test]
+async fn cancelled_calls_never_run() {
+    let ctx = bootstrap_state();
+    let (sk, _) = FIXTURE.funded(&ctx, 1).await;
+    let (other_sk, _) = FIXTURE.funded(&ctx, 2).await;
+    let schedule = TxPayload::Schedule {
+        execute_at_height: 5,
+        inner: Box::new(TxPayload::Transfer {
+            to: [9u8; 32],
+            amount: 1,
+        }),
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, schedule), 1)
+        .await
+        .unwrap();
+
+    let cancel = TxPayload::ScheduleCancel { schedule_id: 0 };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&other_sk, 0, cancel.clone()), 2)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("only the owner"));
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, cancel), 2)
+        .await
+        .unwrap();
+
+    let result = apply_block(&ctx, &block(5, vec![])).await.unwrap();
+    assert!(result.events.iter().all(|e| e.kind != "scheduled_call"));
+    assert!(ctx.state.get_account(&[9u8; 32]).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn schedule_rejects_past_heights_and_nesting() {
+    let ctx = bootstrap_state();
+    let (sk, _) = FIXTURE.funded(&ctx, 1).await;
+    let transfer = TxPayload::Transfer {
+        to: [9u8; 32],
+        amount: 1,
+    };
+
+    let past = TxPayload::Schedule {
+        execute_at_height: 4,
+        inner: Box::new(transfer.clone()),
+    };
+    assert!(apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, past), 4)
+        .await
+        .is_err());
+
+    let nested = TxPayload::Schedule {
+        execute_at_height: 10,
+        inner: Box::new(TxPayload::Schedule {
+            execute_at_height: 20,
+            inner: Box::new(transfer),
+        }),
+    };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, nested), 4)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("nested"));
+}
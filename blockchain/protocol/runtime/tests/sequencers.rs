@@ -0,0 +1,351 @@
+mod common;
+
+use ed25519_dalek::SigningKey;
+use runtime::{
+    apply_block, apply_tx, bootstrap_state, failover_pick, rotation_seed, stake_weighted_pick,
+    Address, Block, BlockHeader, ExecutionContext, TxPayload,
+};
+use state::{InMemoryStateStore, StateStore};
+use uuid::Uuid;
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::dynamic(10_000_000, 300_000);
+
+fn block(height: u64) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp: 0,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions: vec![],
+        da_blobs: vec![],
+    }
+}
+
+fn claim(domain_id: Uuid, blob: &str) -> TxPayload {
+    TxPayload::RollupBatchClaim {
+        domain_id,
+        blob_id: blob.into(),
+        state_root: [5u8; 32],
+        calls_root: [6u8; 32],
+        steps: 4,
+    }
+}
+
+async fn create_domain(ctx: &ExecutionContext<InMemoryStateStore>, creator: &SigningKey) -> Uuid {
+    let domain_id = Uuid::new_v4();
+    let create = TxPayload::DomainCreate {
+        domain_id,
+        params: serde_json::json!({
+            "kind": "wasm",
+            "challenge_window_blocks": 5,
+            "response_blocks": 2,
+            "claim_bond": 1_000,
+            "min_sequencer_bond": 1_000,
+            "rotation_blocks": 10,
+        }),
+    };
+    apply_tx(ctx, &FIXTURE.signed_tx(creator, 0, create), 0)
+        .await
+        .unwrap();
+    domain_id
+}
+
+async fn bonds(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    domain_id: &Uuid,
+) -> Vec<(Address, u128)> {
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    chain
+        .sequencer_bonds
+        .get(domain_id)
+        .into_iter()
+        .flatten()
+        .map(|b| (b.sequencer, b.bond))
+        .collect()
+}
+
+async fn balance(ctx: &ExecutionContext<InMemoryStateStore>, address: &Address) -> u128 {
+    ctx.state
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap()
+        .balance_x
+}
+
+/// The runtime's draw for `round` over the domain's current bonds.
+async fn leader(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    domain_id: &Uuid,
+    round: u64,
+) -> Address {
+    // Bonds slashed below the domain's minimum are not drawn.
+    let bonds: Vec<_> = bonds(ctx, domain_id)
+        .await
+        .into_iter()
+        .filter(|(_, bond)| *bond >= 1_000)
+        .collect();
+    let stakes: Vec<u128> = bonds.iter().map(|(_, bond)| *bond).collect();
+    let index = stake_weighted_pick(&stakes, &rotation_seed(domain_id, round)).unwrap();
+    bonds[index].0
+}
+
+#[tokio::test]
+async fn rounds_rotate_between_bonded_sequencers_and_missed_rounds_are_slashed() {
+    let ctx = bootstrap_state();
+    let (a, a_addr) = FIXTURE.funded(&ctx, 1).await;
+    let (b, b_addr) = FIXTURE.funded(&ctx, 2).await;
+    let domain_id = create_domain(&ctx, &a).await;
+
+    // Anyone may post until a sequencer bonds.
+    apply_tx(&ctx, &FIXTURE.signed_tx(&b, 0, claim(domain_id, "open")), 0)
+        .await
+        .unwrap();
+    apply_block(&ctx, &block(5)).await.unwrap();
+
+    let register = |bond| TxPayload::SequencerRegister { domain_id, bond };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&a, 1, register(999)), 5)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("minimum"));
+    apply_tx(&ctx, &FIXTURE.signed_tx(&a, 1, register(3_000)), 5)
+        .await
+        .unwrap();
+    apply_tx(&ctx, &FIXTURE.signed_tx(&b, 1, register(1_000)), 5)
+        .await
+        .unwrap();
+
+    let keys = [(&a, a_addr), (&b, b_addr)];
+    let mut nonces = [2u64, 2];
+    let round_leader = leader(&ctx, &domain_id, 0).await;
+    let (l, other) = if round_leader == a_addr {
+        (0, 1)
+    } else {
+        (1, 0)
+    };
+    let err = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(keys[other].0, nonces[other], claim(domain_id, "x")),
+        6,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("not the domain's sequencer"));
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(keys[l].0, nonces[l], claim(domain_id, "r0")),
+        6,
+    )
+    .await
+    .unwrap();
+    nonces[l] += 1;
+
+    // Round 0 was posted; round 1's leader never posts.
+    let result = apply_block(&ctx, &block(10)).await.unwrap();
+    assert!(!result.events.iter().any(|e| e.kind == "sequencer_slashed"));
+    let missed = leader(&ctx, &domain_id, 1).await;
+    let before = bonds(&ctx, &domain_id).await;
+    let result = apply_block(&ctx, &block(20)).await.unwrap();
+    let slashed = result
+        .events
+        .iter()
+        .find(|e| e.kind == "sequencer_slashed")
+        .unwrap();
+    assert_eq!(slashed.attribute("reason"), Some("missed_round"));
+    let after = bonds(&ctx, &domain_id).await;
+    for ((addr, bond), (_, bond_after)) in before.iter().zip(&after) {
+        let expected = if *addr == missed {
+            bond - bond / 20
+        } else {
+            *bond
+        };
+        assert_eq!(*bond_after, expected);
+    }
+
+    // An exiting sequencer keeps its bond at stake until the window passes
+    // and it has no round left to post for.
+    let exiting = if leader(&ctx, &domain_id, 2).await == a_addr {
+        1
+    } else {
+        0
+    };
+    let (sk, addr) = keys[exiting];
+    let exit = TxPayload::SequencerExit { domain_id };
+    apply_tx(&ctx, &FIXTURE.signed_tx(sk, nonces[exiting], exit), 21)
+        .await
+        .unwrap();
+    let bond = after.iter().find(|(s, _)| *s == addr).unwrap().1;
+    let held = balance(&ctx, &addr).await;
+    apply_block(&ctx, &block(25)).await.unwrap();
+    assert_eq!(bonds(&ctx, &domain_id).await.len(), 2);
+    apply_block(&ctx, &block(26)).await.unwrap();
+    assert!(bonds(&ctx, &domain_id)
+        .await
+        .iter()
+        .all(|(s, _)| *s != addr));
+    assert_eq!(balance(&ctx, &addr).await, held + bond);
+}
+
+#[tokio::test]
+async fn a_leader_missing_its_slot_hands_the_round_to_the_next_draw() {
+    let ctx = bootstrap_state();
+    let (a, a_addr) = FIXTURE.funded(&ctx, 1).await;
+    let (b, _) = FIXTURE.funded(&ctx, 2).await;
+    let domain_id = Uuid::new_v4();
+    let create = TxPayload::DomainCreate {
+        domain_id,
+        params: serde_json::json!({
+            "kind": "wasm",
+            "challenge_window_blocks": 5,
+            "response_blocks": 2,
+            "claim_bond": 1_000,
+            "min_sequencer_bond": 1_000,
+            "rotation_blocks": 10,
+            "failover_blocks": 4,
+        }),
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&a, 0, create), 0)
+        .await
+        .unwrap();
+    let register = |bond| TxPayload::SequencerRegister { domain_id, bond };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&a, 1, register(3_000)), 0)
+        .await
+        .unwrap();
+    apply_tx(&ctx, &FIXTURE.signed_tx(&b, 0, register(2_000)), 0)
+        .await
+        .unwrap();
+    apply_block(&ctx, &block(1)).await.unwrap();
+
+    let bonded = bonds(&ctx, &domain_id).await;
+    let stakes: Vec<u128> = bonded.iter().map(|(_, bond)| *bond).collect();
+    let first = failover_pick(&stakes, &domain_id, 0, 0).unwrap();
+    let backup = failover_pick(&stakes, &domain_id, 0, 1).unwrap();
+    assert_ne!(first, backup);
+    assert_eq!(
+        Some(first),
+        stake_weighted_pick(&stakes, &rotation_seed(&domain_id, 0))
+    );
+    let (first_addr, backup_addr) = (bonded[first].0, bonded[backup].0);
+    let (backup_key, backup_nonce) = if backup_addr == a_addr {
+        (&a, 2)
+    } else {
+        (&b, 1)
+    };
+    let err = apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(backup_key, backup_nonce, claim(domain_id, "early")),
+        2,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("not the domain's sequencer"));
+
+    let result = apply_block(&ctx, &block(4)).await.unwrap();
+    let slashed = result
+        .events
+        .iter()
+        .find(|e| e.kind == "sequencer_slashed")
+        .unwrap();
+    assert_eq!(slashed.attribute("reason"), Some("missed_slot"));
+    assert_eq!(
+        slashed.attribute("sequencer"),
+        Some(hex::encode(first_addr).as_str())
+    );
+    let failover = result
+        .events
+        .iter()
+        .find(|e| e.kind == "sequencer_failover")
+        .unwrap();
+    assert_eq!(
+        failover.attribute("leader"),
+        Some(hex::encode(backup_addr).as_str())
+    );
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(backup_key, backup_nonce, claim(domain_id, "taken_over")),
+        5,
+    )
+    .await
+    .unwrap();
+
+    // A posted round does not fail over again.
+    let result = apply_block(&ctx, &block(8)).await.unwrap();
+    assert!(!result.events.iter().any(|e| e.kind == "sequencer_slashed"));
+}
+
+#[tokio::test]
+async fn proven_fraud_slashes_the_sequencer_bond() {
+    let ctx = bootstrap_state();
+    let (sequencer, _) = FIXTURE.funded(&ctx, 1).await;
+    let (challenger, challenger_addr) = FIXTURE.funded(&ctx, 2).await;
+    let domain_id = create_domain(&ctx, &sequencer).await;
+    let register = TxPayload::SequencerRegister {
+        domain_id,
+        bond: 5_000,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sequencer, 1, register), 0)
+        .await
+        .unwrap();
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&sequencer, 2, claim(domain_id, "b")),
+        1,
+    )
+    .await
+    .unwrap();
+    let challenge = TxPayload::FraudChallenge {
+        domain_id,
+        claimed_root: [7u8; 32],
+        witness: serde_json::json!({}),
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&challenger, 0, challenge), 2)
+        .await
+        .unwrap();
+
+    let treasury = ctx
+        .state
+        .get_chain_state()
+        .await
+        .unwrap()
+        .fee_pools
+        .treasury;
+    let result = apply_block(&ctx, &block(4)).await.unwrap();
+    let slashed = result
+        .events
+        .iter()
+        .find(|e| e.kind == "sequencer_slashed")
+        .unwrap();
+    assert_eq!(slashed.attribute("reason"), Some("fraud"));
+    assert_eq!(slashed.attribute("amount"), Some("5000"));
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(!chain.sequencer_bonds.contains_key(&domain_id));
+    assert!(chain.fee_pools.treasury >= treasury + 5_000);
+
+    // With no bond left the domain is open again.
+    apply_tx(
+        &ctx,
+        &FIXTURE.signed_tx(&challenger, 1, claim(domain_id, "c")),
+        5,
+    )
+    .await
+    .unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(
+        chain.optimistic_claims[&domain_id].sequencer,
+        challenger_addr
+    );
+}
@@ -0,0 +1,99 @@
+use ed25519_dalek::SigningKey;
+use runtime::{
+    legacy_signatures_accepted, sign_bytes, sign_in_domain, tx_signing_bytes, verify_in_domain,
+    verify_signature_bytes, verify_tx_signature, vote_messages, SigningDomain, Tx, TxPayload,
+    TAGGED_SIGNATURES_VERSION,
+};
+
+fn transfer(sk: &SigningKey) -> Tx {
+    Tx {
+        chain_id: "kova-devnet".into(),
+        nonce: 0,
+        gas_limit: 21_000,
+        max_fee: Some(1),
+        max_priority_fee: Some(0),
+        gas_price: None,
+        payload: TxPayload::Transfer {
+            to: [2u8; 32],
+            amount: 10,
+        },
+        public_key: sk.verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    }
+}
+
+#[test]
+fn signatures_are_bound_to_domain_and_chain() {
+    let sk = SigningKey::from_bytes(&[4u8; 32]);
+    let pk = sk.verifying_key().to_bytes();
+    let block_id = [7u8; 32];
+
+    let sig = sign_in_domain(&sk, SigningDomain::Proposal, "kova-devnet", &block_id);
+    let verify = |domain, chain_id| verify_in_domain(&pk, &sig, domain, chain_id, &block_id, false);
+    assert!(verify(SigningDomain::Proposal, "kova-devnet").is_ok());
+    assert!(verify(SigningDomain::Vote, "kova-devnet").is_err());
+    assert!(verify(SigningDomain::Proposal, "kova-testnet").is_err());
+
+    let mut tx = transfer(&sk);
+    tx.signature = sign_bytes(&sk, &tx_signing_bytes(&tx).unwrap());
+    assert!(verify_tx_signature(&tx, false).is_ok());
+    tx.chain_id = "kova-testnet".into();
+    assert!(verify_tx_signature(&tx, false).is_err());
+}
+
+#[test]
+fn untagged_signatures_verify_only_during_transition() {
+    let sk = SigningKey::from_bytes(&[5u8; 32]);
+    let block_id = [8u8; 32];
+    let legacy = sign_bytes(&sk, &block_id);
+    let pk = sk.verifying_key().to_bytes();
+
+    let verify = |accept_legacy| {
+        verify_in_domain(
+            &pk,
+            &legacy,
+            SigningDomain::Proposal,
+            "kova-devnet",
+            &block_id,
+            accept_legacy,
+        )
+    };
+    assert!(legacy_signatures_accepted(0));
+    assert!(verify(legacy_signatures_accepted(0)).is_ok());
+    assert!(!legacy_signatures_accepted(TAGGED_SIGNATURES_VERSION));
+    assert!(verify(legacy_signatures_accepted(TAGGED_SIGNATURES_VERSION)).is_err());
+}
+
+#[test]
+fn pre_canonical_signatures_verify_during_transition() {
+    let sk = SigningKey::from_bytes(&[6u8; 32]);
+    let pk = sk.verifying_key().to_bytes();
+
+    // Legacy clients signed the bincode encoding, untagged.
+    let mut tx = transfer(&sk);
+    let signable = bincode::serialize(&(
+        &tx.chain_id,
+        tx.nonce,
+        tx.gas_limit,
+        tx.max_fee,
+        tx.max_priority_fee,
+        tx.gas_price,
+        &tx.payload,
+        &tx.public_key,
+    ))
+    .unwrap();
+    tx.signature = sign_bytes(&sk, &signable);
+    assert!(verify_tx_signature(&tx, true).is_ok());
+    assert!(verify_tx_signature(&tx, false).is_err());
+
+    let block_id = [9u8; 32];
+    let vote = sign_bytes(&sk, &bincode::serialize(&(&block_id, 3u64)).unwrap());
+    let verifies = |accept_legacy| {
+        vote_messages("kova-devnet", &block_id, 3, accept_legacy)
+            .unwrap()
+            .iter()
+            .any(|msg| verify_signature_bytes(&pk, &vote, msg).is_ok())
+    };
+    assert!(verifies(true));
+    assert!(!verifies(false));
+}
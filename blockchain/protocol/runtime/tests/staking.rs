@@ -1,9 +1,10 @@
 use ed25519_dalek::SigningKey;
+use runtime::bls::BlsSecretKey;
 use runtime::{
-    address_from_pubkey, apply_block, bootstrap_state, sign_bytes, tx_signing_bytes, Block,
-    BlockHeader, Tx, TxPayload,
+    address_from_pubkey, apply_block, apply_tx, bootstrap_state, sign_bytes, tx_signing_bytes,
+    vote_signing_bytes, Block, BlockHeader, DoubleSignEvidence, LivenessReport, Tx, TxPayload,
 };
-use state::{Account, StateStore};
+use state::{Account, StateStore, ValidatorStatus};
 
 #[tokio::test]
 async fn stake_creates_validator_and_updates_balance() {
@@ -20,6 +21,7 @@ async fn stake_creates_validator_and_updates_balance() {
             balance_x: 1_000_000,
             code_hash: None,
             storage_root: None,
+            assets: Default::default(),
         })
         .await
         .unwrap();
@@ -51,6 +53,8 @@ async fn stake_creates_validator_and_updates_balance() {
             gas_used: 0,
             gas_limit: 30_000_000,
             base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
             consensus_metadata: serde_json::json!({}),
         },
         transactions: vec![tx],
@@ -66,3 +70,467 @@ async fn stake_creates_validator_and_updates_balance() {
     assert_eq!(validator.owner, owner);
     assert!(validator.stake >= 100_000);
 }
+
+fn signed_tx(sk: &SigningKey, nonce: u64, payload: TxPayload) -> Tx {
+    let mut tx = Tx {
+        chain_id: "kova-devnet".into(),
+        nonce,
+        gas_limit: 50_000,
+        max_fee: None,
+        max_priority_fee: None,
+        gas_price: Some(1),
+        payload,
+        public_key: sk.verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    };
+    let msg = tx_signing_bytes(&tx).unwrap();
+    tx.signature = sign_bytes(sk, &msg);
+    tx
+}
+
+#[tokio::test]
+async fn validator_registers_bls_key_with_proof_of_possession() {
+    let ctx = bootstrap_state();
+    let sk = SigningKey::from_bytes(&[8u8; 32]);
+    let owner = address_from_pubkey(&sk.verifying_key().to_bytes());
+    ctx.state
+        .put_account(Account {
+            address: owner,
+            nonce: 0,
+            balance_x: 1_000_000,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await
+        .unwrap();
+    apply_tx(&ctx, &signed_tx(&sk, 0, TxPayload::Stake { amount: 100_000 }), 0)
+        .await
+        .unwrap();
+
+    let bls = BlsSecretKey::from_seed(&[3u8; 32]).unwrap();
+    let other = BlsSecretKey::from_seed(&[4u8; 32]).unwrap();
+    let bad = TxPayload::RegisterBlsKey {
+        bls_pubkey: bls.public_key(),
+        proof_of_possession: other.proof_of_possession(),
+    };
+    assert!(apply_tx(&ctx, &signed_tx(&sk, 1, bad), 1).await.is_err());
+
+    let good = TxPayload::RegisterBlsKey {
+        bls_pubkey: bls.public_key(),
+        proof_of_possession: bls.proof_of_possession(),
+    };
+    apply_tx(&ctx, &signed_tx(&sk, 1, good), 1).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let validator = chain.validators.values().find(|v| v.owner == owner).unwrap();
+    assert_eq!(validator.bls_pubkey, bls.public_key());
+}
+
+#[tokio::test]
+async fn double_vote_evidence_slashes_and_jails_once() {
+    let ctx = bootstrap_state();
+    let offender = SigningKey::from_bytes(&[9u8; 32]);
+    let reporter = SigningKey::from_bytes(&[10u8; 32]);
+    for sk in [&offender, &reporter] {
+        ctx.state
+            .put_account(Account {
+                address: address_from_pubkey(&sk.verifying_key().to_bytes()),
+                nonce: 0,
+                balance_x: 1_000_000,
+                code_hash: None,
+                storage_root: None,
+                assets: Default::default(),
+            })
+            .await
+            .unwrap();
+    }
+    apply_tx(&ctx, &signed_tx(&offender, 0, TxPayload::Stake { amount: 100_000 }), 0)
+        .await
+        .unwrap();
+
+    let vote = |block_id: [u8; 32]| {
+        let msg = vote_signing_bytes("kova-devnet", &block_id, 4).unwrap();
+        (block_id, sign_bytes(&offender, &msg))
+    };
+    let evidence = DoubleSignEvidence::Vote {
+        public_key: offender.verifying_key().to_bytes().to_vec(),
+        view: 4,
+        first: vote([1u8; 32]),
+        second: vote([2u8; 32]),
+    };
+    let mut forged = evidence.clone();
+    if let DoubleSignEvidence::Vote { second, .. } = &mut forged {
+        second.0 = [3u8; 32];
+    }
+    let submit = |nonce, evidence| {
+        signed_tx(&reporter, nonce, TxPayload::SubmitEvidence { evidence })
+    };
+    assert!(apply_tx(&ctx, &submit(0, forged), 1).await.is_err());
+
+    apply_tx(&ctx, &submit(0, evidence.clone()), 1).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let owner = address_from_pubkey(&offender.verifying_key().to_bytes());
+    let validator = chain.validators.values().find(|v| v.owner == owner).unwrap();
+    assert!(validator.stake < 100_000);
+    assert!(matches!(validator.status, ValidatorStatus::Jailed));
+
+    assert!(apply_tx(&ctx, &submit(1, evidence), 2).await.is_err());
+}
+
+fn empty_block(height: u64, proposer: [u8; 32]) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp: 0,
+            proposer_id: proposer,
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions: vec![],
+        da_blobs: vec![],
+    }
+}
+
+#[tokio::test]
+async fn exits_are_rate_limited_per_epoch_in_queue_order() {
+    let mut ctx = bootstrap_state();
+    ctx.epoch_length_blocks = 2;
+    ctx.exit_churn_bps = 1_000;
+    let first = SigningKey::from_bytes(&[11u8; 32]);
+    let second = SigningKey::from_bytes(&[12u8; 32]);
+    for sk in [&first, &second] {
+        ctx.state
+            .put_account(Account {
+                address: address_from_pubkey(&sk.verifying_key().to_bytes()),
+                nonce: 0,
+                balance_x: 1_000_000,
+                code_hash: None,
+                storage_root: None,
+                assets: Default::default(),
+            })
+            .await
+            .unwrap();
+        apply_tx(&ctx, &signed_tx(sk, 0, TxPayload::Stake { amount: 100_000 }), 0)
+            .await
+            .unwrap();
+    }
+    let stake_of = |chain: &state::ChainState, sk: &SigningKey| {
+        let owner = address_from_pubkey(&sk.verifying_key().to_bytes());
+        chain.validators.values().find(|v| v.owner == owner).unwrap().stake
+    };
+
+    // 10% of 200_000 bonded stake may leave per epoch.
+    apply_tx(&ctx, &signed_tx(&first, 1, TxPayload::Unstake { amount: 30_000 }), 1)
+        .await
+        .unwrap();
+    apply_tx(&ctx, &signed_tx(&second, 1, TxPayload::Unstake { amount: 5_000 }), 1)
+        .await
+        .unwrap();
+    let over = signed_tx(&first, 2, TxPayload::Unstake { amount: 80_000 });
+    assert!(apply_tx(&ctx, &over, 1).await.is_err());
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(stake_of(&chain, &first), 100_000);
+    assert_eq!(chain.exit_queue.len(), 2);
+
+    let proposer = address_from_pubkey(&first.verifying_key().to_bytes());
+    apply_block(&ctx, &empty_block(2, proposer)).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(stake_of(&chain, &first), 80_000);
+    assert_eq!(stake_of(&chain, &second), 100_000);
+
+    // Odd heights are not epoch boundaries.
+    apply_block(&ctx, &empty_block(3, proposer)).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(stake_of(&chain, &first), 80_000);
+
+    apply_block(&ctx, &empty_block(4, proposer)).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(stake_of(&chain, &first), 70_000);
+    assert_eq!(stake_of(&chain, &second), 95_000);
+    assert!(chain.exit_queue.is_empty());
+}
+
+#[tokio::test]
+async fn delegator_rewards_accrue_lazily_until_claimed() {
+    let mut ctx = bootstrap_state();
+    // One block per year mints the whole annual inflation at once.
+    ctx.block_time_ms = 365 * 24 * 60 * 60 * 1_000;
+    ctx.reward_params.treasury_pct = 0;
+    ctx.reward_params.proposer_bonus_pct = 0;
+    let validator = SigningKey::from_bytes(&[13u8; 32]);
+    let small = SigningKey::from_bytes(&[14u8; 32]);
+    let large = SigningKey::from_bytes(&[15u8; 32]);
+    for sk in [&validator, &small, &large] {
+        ctx.state
+            .put_account(Account {
+                address: address_from_pubkey(&sk.verifying_key().to_bytes()),
+                nonce: 0,
+                balance_x: 1_000_000,
+                code_hash: None,
+                storage_root: None,
+                assets: Default::default(),
+            })
+            .await
+            .unwrap();
+    }
+    let owner = address_from_pubkey(&validator.verifying_key().to_bytes());
+    let small_addr = address_from_pubkey(&small.verifying_key().to_bytes());
+    let large_addr = address_from_pubkey(&large.verifying_key().to_bytes());
+    apply_tx(&ctx, &signed_tx(&validator, 0, TxPayload::Stake { amount: 100_000 }), 0)
+        .await
+        .unwrap();
+    let delegate = |amount| TxPayload::Delegate {
+        validator: owner,
+        amount,
+    };
+    apply_tx(&ctx, &signed_tx(&small, 0, delegate(100_000)), 0)
+        .await
+        .unwrap();
+    apply_tx(&ctx, &signed_tx(&large, 0, delegate(300_000)), 0)
+        .await
+        .unwrap();
+
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    chain.total_supply = 10_000_000;
+    ctx.state.put_chain_state(chain).await.unwrap();
+    let balance = |chain: &state::ChainState, address| chain.accounts[&address].balance_x;
+    let before = ctx.state.get_chain_state().await.unwrap();
+
+    // 15% of supply, four fifths of it earned by delegated stake.
+    apply_block(&ctx, &empty_block(1, owner)).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let validator_id = chain.validators.values().find(|v| v.owner == owner).unwrap().id;
+    assert_eq!(chain.delegated_stake(&validator_id), 400_000);
+    assert_eq!(chain.delegation_rewards(&small_addr, &validator_id), 300_000);
+    assert_eq!(chain.delegation_rewards(&large_addr, &validator_id), 900_000);
+    assert_eq!(balance(&chain, small_addr), balance(&before, small_addr));
+    assert_eq!(balance(&chain, owner), balance(&before, owner) + 300_000);
+
+    let claim = signed_tx(&small, 1, TxPayload::ClaimRewards { validator: owner });
+    let outcome = apply_tx(&ctx, &claim, 2).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(outcome.error.is_none());
+    assert_eq!(chain.delegation_rewards(&small_addr, &validator_id), 0);
+    // Less 50_000 gas at a price of 1.
+    assert_eq!(
+        balance(&chain, small_addr),
+        balance(&before, small_addr) + 300_000 - 50_000
+    );
+
+    // Leaving pays out what accrued and drops the position.
+    let undelegate = TxPayload::Undelegate {
+        validator: owner,
+        amount: 300_000,
+    };
+    apply_tx(&ctx, &signed_tx(&large, 1, undelegate), 2)
+        .await
+        .unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(!chain.delegations.contains_key(&(large_addr, validator_id)));
+    assert_eq!(chain.delegated_stake(&validator_id), 100_000);
+    assert_eq!(
+        balance(&chain, large_addr),
+        balance(&before, large_addr) + 900_000 - 60_000
+    );
+}
+
+#[tokio::test]
+async fn commission_moves_once_per_epoch_within_the_limit() {
+    let mut ctx = bootstrap_state();
+    ctx.epoch_length_blocks = 10;
+    ctx.max_commission_change_per_epoch = 2;
+    let sk = SigningKey::from_bytes(&[16u8; 32]);
+    let owner = address_from_pubkey(&sk.verifying_key().to_bytes());
+    ctx.state
+        .put_account(Account {
+            address: owner,
+            nonce: 0,
+            balance_x: 1_000_000,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await
+        .unwrap();
+    apply_tx(&ctx, &signed_tx(&sk, 0, TxPayload::Stake { amount: 100_000 }), 0)
+        .await
+        .unwrap();
+    let edit = |commission_rate, moniker: Option<&str>| TxPayload::ValidatorEdit {
+        commission_rate,
+        moniker: moniker.map(String::from),
+        website: None,
+        details: Some("runs on solar".into()),
+    };
+
+    let too_far = signed_tx(&sk, 1, edit(Some(3), None));
+    assert!(apply_tx(&ctx, &too_far, 1).await.is_err());
+    let long_name = "x".repeat(71);
+    let too_long = signed_tx(&sk, 1, edit(None, Some(&long_name)));
+    assert!(apply_tx(&ctx, &too_long, 1).await.is_err());
+
+    apply_tx(&ctx, &signed_tx(&sk, 1, edit(Some(2), Some("solar"))), 1)
+        .await
+        .unwrap();
+    // The profile may change again, the commission only next epoch.
+    apply_tx(&ctx, &signed_tx(&sk, 2, edit(None, Some("sunny"))), 5)
+        .await
+        .unwrap();
+    let again = signed_tx(&sk, 3, edit(Some(4), None));
+    assert!(apply_tx(&ctx, &again, 9).await.is_err());
+    apply_tx(&ctx, &again, 10).await.unwrap();
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let validator = chain.validators.values().find(|v| v.owner == owner).unwrap();
+    assert_eq!(validator.commission_rate, 4);
+    assert_eq!(validator.description.moniker, "sunny");
+    assert_eq!(validator.description.details, "runs on solar");
+}
+
+#[tokio::test]
+async fn validators_below_uptime_are_jailed_until_they_unjail() {
+    let mut ctx = bootstrap_state();
+    ctx.liveness_params.window = 4;
+    ctx.liveness_params.min_uptime_bps = 5_000;
+    ctx.liveness_params.downtime_penalty_bps = 100;
+    ctx.liveness_params.jail_cooldown_blocks = 10;
+    // Every block is an epoch boundary, so new validators join the active set
+    // with the first one.
+    ctx.epoch_length_blocks = 1;
+    let online = SigningKey::from_bytes(&[17u8; 32]);
+    let offline = SigningKey::from_bytes(&[18u8; 32]);
+    for sk in [&online, &offline] {
+        ctx.state
+            .put_account(Account {
+                address: address_from_pubkey(&sk.verifying_key().to_bytes()),
+                nonce: 0,
+                balance_x: 1_000_000,
+                code_hash: None,
+                storage_root: None,
+                assets: Default::default(),
+            })
+            .await
+            .unwrap();
+        apply_tx(&ctx, &signed_tx(sk, 0, TxPayload::Stake { amount: 100_000 }), 0)
+            .await
+            .unwrap();
+    }
+    let owner = |sk: &SigningKey| address_from_pubkey(&sk.verifying_key().to_bytes());
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let id_of =
+        |sk: &SigningKey| chain.validators.values().find(|v| v.owner == owner(sk)).unwrap().id;
+    let (online_id, offline_id) = (id_of(&online), id_of(&offline));
+    let reported = |height: u64, qc_view: u64| {
+        let mut block = empty_block(height, owner(&online));
+        let report = LivenessReport {
+            qc_view,
+            signers: vec![online_id],
+            missed_proposals: vec![],
+        };
+        block.header.consensus_metadata = serde_json::json!({ "liveness": report });
+        block
+    };
+
+    for height in 1..4 {
+        apply_block(&ctx, &reported(height, height)).await.unwrap();
+    }
+    // A QC reported again does not count twice.
+    apply_block(&ctx, &reported(4, 3)).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(matches!(chain.validators[&offline_id].status, ValidatorStatus::Active));
+
+    let result = apply_block(&ctx, &reported(5, 5)).await.unwrap();
+    assert!(result.events.iter().any(|e| e.kind == "validator_jailed"));
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    let jailed = &chain.validators[&offline_id];
+    assert!(matches!(jailed.status, ValidatorStatus::Jailed));
+    assert_eq!(jailed.stake, 99_000);
+    assert_eq!(chain.liveness[&offline_id].jailed_until, Some(15));
+    assert!(matches!(chain.validators[&online_id].status, ValidatorStatus::Active));
+
+    // Topping up stake does not lift the jail, and neither does an early unjail.
+    apply_tx(&ctx, &signed_tx(&offline, 1, TxPayload::Stake { amount: 1_000 }), 6)
+        .await
+        .unwrap();
+    let early = signed_tx(&offline, 2, TxPayload::Unjail);
+    assert!(apply_tx(&ctx, &early, 14).await.is_err());
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(matches!(chain.validators[&offline_id].status, ValidatorStatus::Jailed));
+
+    apply_tx(&ctx, &early, 15).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(matches!(chain.validators[&offline_id].status, ValidatorStatus::Candidate));
+    assert_eq!(chain.liveness[&offline_id].jailed_until, None);
+
+    apply_block(&ctx, &empty_block(16, owner(&online))).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(matches!(chain.validators[&offline_id].status, ValidatorStatus::Active));
+}
+
+#[tokio::test]
+async fn active_set_is_chosen_by_stake_at_epoch_boundaries() {
+    let mut ctx = bootstrap_state();
+    ctx.epoch_length_blocks = 10;
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    chain.staking_params.min_validator_stake = 50_000;
+    chain.staking_params.max_active_validators = 2;
+    ctx.state.put_chain_state(chain).await.unwrap();
+
+    let keys: Vec<SigningKey> = (20u8..24).map(|i| SigningKey::from_bytes(&[i; 32])).collect();
+    let stakes = [100_000, 200_000, 150_000, 40_000];
+    for (sk, amount) in keys.iter().zip(stakes) {
+        ctx.state
+            .put_account(Account {
+                address: address_from_pubkey(&sk.verifying_key().to_bytes()),
+                nonce: 0,
+                balance_x: 1_000_000,
+                code_hash: None,
+                storage_root: None,
+                assets: Default::default(),
+            })
+            .await
+            .unwrap();
+        apply_tx(&ctx, &signed_tx(sk, 0, TxPayload::Stake { amount }), 1)
+            .await
+            .unwrap();
+    }
+    let statuses = |chain: &state::ChainState| {
+        keys.iter()
+            .map(|sk| {
+                let owner = address_from_pubkey(&sk.verifying_key().to_bytes());
+                let v = chain.validators.values().find(|v| v.owner == owner).unwrap();
+                matches!(v.status, ValidatorStatus::Active)
+            })
+            .collect::<Vec<_>>()
+    };
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(statuses(&chain), [false; 4]);
+
+    let proposer = address_from_pubkey(&keys[0].verifying_key().to_bytes());
+    let result = apply_block(&ctx, &empty_block(10, proposer)).await.unwrap();
+    let promoted = result.events.iter().filter(|e| e.kind == "validator_promoted");
+    assert_eq!(promoted.count(), 2);
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(statuses(&chain), [false, true, true, false]);
+
+    // Outranking an active validator takes effect at the next boundary.
+    let top_up = TxPayload::Stake { amount: 200_000 };
+    apply_tx(&ctx, &signed_tx(&keys[0], 1, top_up), 11).await.unwrap();
+    apply_block(&ctx, &empty_block(15, proposer)).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(statuses(&chain), [false, true, true, false]);
+
+    let result = apply_block(&ctx, &empty_block(20, proposer)).await.unwrap();
+    assert!(result.events.iter().any(|e| e.kind == "validator_demoted"));
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(statuses(&chain), [true, true, false, false]);
+}
@@ -21,6 +21,7 @@ async fn transfer_moves_balance() {
             balance_x: 1_000_000,
             code_hash: None,
             storage_root: None,
+            assets: Default::default(),
         })
         .await
         .unwrap();
@@ -52,6 +53,8 @@ async fn transfer_moves_balance() {
             gas_used: 0,
             gas_limit: 30_000_000,
             base_fee: 0,
+            snapshot_root: None,
+            protocol_version: 0,
             consensus_metadata: serde_json::json!({}),
         },
         transactions: vec![tx],
@@ -60,4 +63,10 @@ async fn transfer_moves_balance() {
 
     let result = apply_block(&ctx, &block).await.unwrap();
     assert_ne!(result.state_root, [0u8; 32]);
+
+    let event = &result.receipts[0].events[0];
+    assert_eq!(event.kind, "transfer");
+    assert_eq!(event.attribute("sender"), Some(hex::encode(from).as_str()));
+    assert_eq!(event.attribute("recipient"), Some(hex::encode(to).as_str()));
+    assert_eq!(event.attribute("amount"), Some("10"));
 }
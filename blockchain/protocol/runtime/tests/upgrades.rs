@@ -0,0 +1,149 @@
+mod common;
+
+use std::collections::HashMap;
+
+use runtime::{
+    apply_block, apply_tx, bootstrap_state, protocol_version_at, Address, Block, BlockHeader,
+    ExecutionContext, ProtocolUpgrade, TxPayload, UnsupportedProtocolVersion,
+    PROTOCOL_UPGRADE_PROPOSAL, PROTOCOL_VERSION,
+};
+use serde_json::json;
+use state::{InMemoryStateStore, Proposal, ProposalStatus, StateStore};
+use uuid::Uuid;
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::legacy(1_000_000, 50_000);
+
+fn block_at(height: u64, protocol_version: u32) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp: height * 1_000,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions: vec![],
+        da_blobs: vec![],
+    }
+}
+
+/// Inserts a protocol upgrade proposal that passed and whose timelock is over.
+async fn queue(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    proposer: Address,
+    execution: serde_json::Value,
+) -> Uuid {
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    let id = Uuid::new_v4();
+    chain.proposals.insert(
+        id,
+        Proposal {
+            id,
+            payload: execution.clone(),
+            kind: PROTOCOL_UPGRADE_PROPOSAL.into(),
+            status: ProposalStatus::Queued,
+            proposer,
+            start: 0,
+            end: 0,
+            eta: Some(0),
+            snapshot_total_stake: 0,
+            for_votes: 0,
+            against_votes: 0,
+            abstain_votes: 0,
+            votes: Vec::new(),
+            execution,
+            voter_weights: HashMap::new(),
+            approvals: Vec::new(),
+            deposit: 0,
+        },
+    );
+    ctx.state.put_chain_state(chain).await.unwrap();
+    id
+}
+
+#[tokio::test]
+async fn system_upgrade_opens_a_proposal_instead_of_scheduling() {
+    let ctx = bootstrap_state();
+    let (sk, _) = FIXTURE.funded(&ctx, 1).await;
+    let upgrade = TxPayload::SystemUpgrade {
+        name: "v1".into(),
+        version: PROTOCOL_VERSION + 1,
+        activation_height: 10,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, upgrade), 0)
+        .await
+        .unwrap();
+
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert!(chain.pending_upgrade.is_none());
+    let proposal = chain.proposals.values().next().unwrap();
+    assert_eq!(proposal.kind, PROTOCOL_UPGRADE_PROPOSAL);
+    assert_eq!(proposal.status, ProposalStatus::Active);
+    assert_eq!(proposal.execution["activation_height"], 10);
+}
+
+#[tokio::test]
+async fn unsupported_versions_halt_at_their_activation_height() {
+    let ctx = bootstrap_state();
+    let (sk, sender) = FIXTURE.funded(&ctx, 2).await;
+    let next = PROTOCOL_VERSION + 1;
+    let execution = json!({ "name": "v1", "version": next, "activation_height": 5 });
+    let id = queue(&ctx, sender, execution).await;
+    let execute = TxPayload::GovernanceExecute { proposal_id: id };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, execute), 0)
+        .await
+        .unwrap();
+
+    let pending = ctx.state.get_pending_upgrade().await.unwrap().unwrap();
+    assert_eq!(pending.version, next);
+    let current = ctx.state.get_protocol_version().await.unwrap();
+    assert_eq!(protocol_version_at(&ctx.state, 4).await.unwrap(), current);
+    assert_eq!(protocol_version_at(&ctx.state, 5).await.unwrap(), next);
+
+    // Blocks before the activation height run as before, and must say so.
+    apply_block(&ctx, &block_at(4, current)).await.unwrap();
+    assert!(apply_block(&ctx, &block_at(4, next)).await.is_err());
+
+    let root = ctx.state.commit().await.unwrap();
+    let err = apply_block(&ctx, &block_at(5, next)).await.unwrap_err();
+    let unsupported = err.downcast_ref::<UnsupportedProtocolVersion>().unwrap();
+    assert_eq!(unsupported.version, next);
+    assert_eq!(unsupported.height, 5);
+    // Nothing from the refused block is written, the upgrade included.
+    assert_eq!(ctx.state.commit().await.unwrap(), root);
+    assert!(ctx.state.get_pending_upgrade().await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn due_upgrades_activate_before_the_block_runs() {
+    let ctx = bootstrap_state();
+    let mut chain = ctx.state.get_chain_state().await.unwrap();
+    chain.pending_upgrade = Some(ProtocolUpgrade {
+        name: "noop".into(),
+        version: PROTOCOL_VERSION,
+        activation_height: 3,
+    });
+    ctx.state.put_chain_state(chain).await.unwrap();
+
+    let result = apply_block(&ctx, &block_at(3, PROTOCOL_VERSION))
+        .await
+        .unwrap();
+    let event = result
+        .events
+        .iter()
+        .find(|e| e.kind == "protocol_upgraded")
+        .unwrap();
+    assert_eq!(event.attributes["name"], "noop");
+    assert!(ctx.state.get_pending_upgrade().await.unwrap().is_none());
+}
@@ -0,0 +1,159 @@
+mod common;
+
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, apply_tx, bootstrap_state, devnet_genesis, from_genesis, include_tx,
+    TxPayload, VestingSchedule,
+};
+use state::{StateStore, MIN_VESTING_AMOUNT};
+
+use common::Fixture;
+
+const FIXTURE: Fixture = Fixture::legacy(1_000_000, 100_000);
+
+#[tokio::test]
+async fn vested_funds_unlock_linearly_after_the_cliff() {
+    let ctx = bootstrap_state();
+    let (funder_sk, _) = FIXTURE.funded(&ctx, 1).await;
+    let (sk, beneficiary) = FIXTURE.funded(&ctx, 2).await;
+
+    let create = TxPayload::VestingCreate {
+        beneficiary,
+        amount: 100_000,
+        cliff_height: 20,
+        end_height: 30,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&funder_sk, 0, create), 10)
+        .await
+        .unwrap();
+    let account = ctx.state.get_account(&beneficiary).await.unwrap().unwrap();
+    assert_eq!(account.balance_x, 1_100_000);
+
+    // Spending 1_000_000 plus 21_000 gas leaves 79_000: short of the full
+    // 100_000 locked before the cliff, enough for the half still locked at it.
+    let transfer = TxPayload::Transfer {
+        to: [9u8; 32],
+        amount: 1_000_000,
+    };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, transfer.clone()), 19)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("still vesting"));
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, transfer), 20)
+        .await
+        .unwrap();
+
+    // Draining the remaining 79_000 has to wait until everything vested.
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.vesting[&beneficiary][0].locked(20), 50_000);
+    assert_eq!(chain.vesting[&beneficiary][0].locked(30), 0);
+    let drain = TxPayload::Transfer {
+        to: [9u8; 32],
+        amount: 79_000 - 21_000,
+    };
+    assert!(
+        apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, drain.clone()), 29)
+            .await
+            .is_err()
+    );
+    apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 1, drain), 30)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn vesting_create_rejects_bad_schedules() {
+    let ctx = bootstrap_state();
+    let (sk, _) = FIXTURE.funded(&ctx, 1).await;
+    let cliff_after_end = TxPayload::VestingCreate {
+        beneficiary: [9u8; 32],
+        amount: 1_000,
+        cliff_height: 20,
+        end_height: 15,
+    };
+    assert!(
+        apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, cliff_after_end), 10)
+            .await
+            .is_err()
+    );
+    let cliff_in_past = TxPayload::VestingCreate {
+        beneficiary: [9u8; 32],
+        amount: 1_000,
+        cliff_height: 5,
+        end_height: 15,
+    };
+    assert!(
+        apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, cliff_in_past), 10)
+            .await
+            .is_err()
+    );
+    let dust = TxPayload::VestingCreate {
+        beneficiary: [9u8; 32],
+        amount: MIN_VESTING_AMOUNT - 1,
+        cliff_height: 20,
+        end_height: 30,
+    };
+    let err = apply_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, dust), 10)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("at least"), "{err}");
+}
+
+#[tokio::test]
+async fn unvested_funds_cannot_pay_for_gas() {
+    let ctx = bootstrap_state();
+    let (funder_sk, _) = FIXTURE.funded(&ctx, 1).await;
+    let sk = SigningKey::from_bytes(&[3u8; 32]);
+    let beneficiary = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let create = TxPayload::VestingCreate {
+        beneficiary,
+        amount: 100_000,
+        cliff_height: 20,
+        end_height: 30,
+    };
+    apply_tx(&ctx, &FIXTURE.signed_tx(&funder_sk, 0, create), 10)
+        .await
+        .unwrap();
+
+    // All the beneficiary holds is locked, so not even a failing tx's gas
+    // can be charged.
+    let overdraft = TxPayload::Transfer {
+        to: [9u8; 32],
+        amount: 1_000_000,
+    };
+    let err = include_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, overdraft.clone()), 11)
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("insufficient funds for gas"),
+        "{err}"
+    );
+
+    let outcome = include_tx(&ctx, &FIXTURE.signed_tx(&sk, 0, overdraft), 30)
+        .await
+        .unwrap();
+    assert!(!outcome.succeeded());
+    let account = ctx.state.get_account(&beneficiary).await.unwrap().unwrap();
+    assert_eq!(account.balance_x, 100_000 - outcome.gas_used as u128);
+}
+
+#[tokio::test]
+async fn genesis_vesting_must_be_backed_by_the_initial_balance() {
+    let beneficiary = [4u8; 32];
+    let schedule = VestingSchedule {
+        beneficiary,
+        total: 600,
+        start_height: 0,
+        cliff_height: 0,
+        end_height: 100,
+    };
+    let mut genesis = devnet_genesis();
+    genesis.initial_accounts = vec![(beneficiary, 1_000)];
+    genesis.vesting = vec![schedule.clone(), schedule.clone()];
+    assert!(from_genesis(genesis.clone()).await.is_err());
+
+    genesis.vesting = vec![schedule];
+    let ctx = from_genesis(genesis).await.unwrap();
+    let chain = ctx.state.get_chain_state().await.unwrap();
+    assert_eq!(chain.vesting[&beneficiary][0].locked(50), 300);
+}
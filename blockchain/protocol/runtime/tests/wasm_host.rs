@@ -0,0 +1,123 @@
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, apply_tx, bootstrap_state, sign_bytes, tx_signing_bytes, Address,
+    DomainCall, ExecutionContext, Tx, TxPayload,
+};
+use state::{Account, InMemoryStateStore, StateStore};
+use uuid::Uuid;
+
+/// Stores its caller under "owner" and emits its input; `oob` reads past
+/// the end of memory.
+const CONTRACT: &str = r#"
+(module
+  (import "kova" "storage_set" (func $set (param i32 i32 i32 i32)))
+  (import "kova" "emit_event" (func $emit (param i32 i32)))
+  (import "kova" "caller" (func $caller (param i32)))
+  (import "kova" "input" (func $input (param i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "owner")
+  (func (export "run") (local $n i32)
+    (call $caller (i32.const 64))
+    (call $set (i32.const 0) (i32.const 5) (i32.const 64) (i32.const 32))
+    (local.set $n (call $input (i32.const 128) (i32.const 64)))
+    (call $emit (i32.const 128) (local.get $n)))
+  (func (export "oob")
+    (call $emit (i32.const 65530) (i32.const 100))))
+"#;
+
+fn signer() -> SigningKey {
+    SigningKey::from_bytes(&[8u8; 32])
+}
+
+fn sender() -> Address {
+    address_from_pubkey(&signer().verifying_key().to_bytes())
+}
+
+async fn apply(
+    ctx: &ExecutionContext<InMemoryStateStore>,
+    nonce: u64,
+    payload: TxPayload,
+) -> anyhow::Result<()> {
+    let mut tx = Tx {
+        chain_id: "kova-devnet".into(),
+        nonce,
+        gas_limit: 300_000,
+        max_fee: Some(1),
+        max_priority_fee: Some(0),
+        gas_price: None,
+        payload,
+        public_key: signer().verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    };
+    tx.signature = sign_bytes(&signer(), &tx_signing_bytes(&tx)?);
+    apply_tx(ctx, &tx, nonce).await.map(|_| ())
+}
+
+fn call(id: Uuid, payload: serde_json::Value) -> TxPayload {
+    TxPayload::DomainExecute(DomainCall {
+        domain_id: id,
+        payload,
+        raw: vec![],
+        max_gas: Some(1_000_000),
+    })
+}
+
+#[tokio::test]
+async fn contracts_use_storage_events_and_the_caller_through_host_calls() -> anyhow::Result<()> {
+    let ctx = bootstrap_state();
+    ctx.state
+        .put_account(Account {
+            address: sender(),
+            nonce: 0,
+            balance_x: 10_000_000,
+            code_hash: None,
+            storage_root: None,
+            assets: Default::default(),
+        })
+        .await?;
+    let id = Uuid::new_v4();
+    apply(
+        &ctx,
+        0,
+        TxPayload::DomainCreate {
+            domain_id: id,
+            params: serde_json::json!({"kind": "wasm"}),
+        },
+    )
+    .await?;
+    let deploy = serde_json::json!({
+        "action": "deploy",
+        "module_id": "c",
+        "code_b64": base64::encode(wat::parse_str(CONTRACT)?),
+    });
+    apply(&ctx, 1, call(id, deploy)).await?;
+
+    let run = serde_json::json!({
+        "action": "invoke",
+        "module_id": "c",
+        "entry": "run",
+        "input_b64": base64::encode(b"hi"),
+    });
+    apply(&ctx, 2, call(id, run)).await?;
+    let receipt = ctx.domains.last_trace(&id).unwrap();
+    assert_eq!(
+        receipt.events,
+        vec![
+            "wasm_invoke:c".to_string(),
+            format!("wasm_event:c:{}", hex::encode(b"hi")),
+        ]
+    );
+    // The storage write alone costs more than the instructions.
+    assert!(receipt.gas_used > 5_000, "{}", receipt.gas_used);
+    let state = ctx.domains.domain_state(&id);
+    let key = format!("wasm:storage:c:{}", hex::encode(b"owner"));
+    assert_eq!(state.kv.get(&key), Some(&sender().to_vec()));
+
+    let oob = serde_json::json!({"action": "invoke", "module_id": "c", "entry": "oob"});
+    let err = apply(&ctx, 3, call(id, oob)).await.unwrap_err();
+    assert!(
+        format!("{err:#}").contains("wasm memory access out of bounds"),
+        "{err:#}"
+    );
+    Ok(())
+}
@@ -0,0 +1,227 @@
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, apply_tx, bootstrap_state, sign_bytes, tx_signing_bytes, DomainCall,
+    ExecutionContext, ExecutionOutcome, Tx, TxPayload,
+};
+use state::{Account, InMemoryStateStore, StateStore};
+use uuid::Uuid;
+
+const HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+/// `(module (func (export "f") <body>))`, hand-assembled.
+fn exported_func(body: &[u8]) -> Vec<u8> {
+    let mut module = HEADER.to_vec();
+    module.extend([0x01, 0x04, 0x01, 0x60, 0x00, 0x00]);
+    module.extend([0x03, 0x02, 0x01, 0x00]);
+    module.extend([0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x00]);
+    let size = body.len() as u8 + 1;
+    module.extend([0x0a, size + 2, 0x01, size, 0x00]);
+    module.extend(body);
+    module
+}
+
+/// `(memory 2)`
+fn two_page_memory() -> Vec<u8> {
+    let mut module = HEADER.to_vec();
+    module.extend([0x05, 0x03, 0x01, 0x00, 0x02]);
+    module
+}
+
+fn padded(len: u8) -> Vec<u8> {
+    let mut module = HEADER.to_vec();
+    module.extend([0x00, len, 0x01, b'x']);
+    module.extend(vec![0u8; len as usize - 2]);
+    module
+}
+
+fn signer() -> SigningKey {
+    SigningKey::from_bytes(&[8u8; 32])
+}
+
+struct Domain {
+    ctx: ExecutionContext<InMemoryStateStore>,
+    id: Uuid,
+    nonce: u64,
+}
+
+impl Domain {
+    async fn create(params: serde_json::Value) -> anyhow::Result<Self> {
+        let ctx = bootstrap_state();
+        ctx.state
+            .put_account(Account {
+                address: address_from_pubkey(&signer().verifying_key().to_bytes()),
+                nonce: 0,
+                balance_x: 10_000_000,
+                code_hash: None,
+                storage_root: None,
+                assets: Default::default(),
+            })
+            .await?;
+        let mut domain = Self {
+            ctx,
+            id: Uuid::new_v4(),
+            nonce: 0,
+        };
+        let id = domain.id;
+        domain
+            .apply(TxPayload::DomainCreate {
+                domain_id: id,
+                params,
+            })
+            .await?;
+        Ok(domain)
+    }
+
+    async fn apply(&mut self, payload: TxPayload) -> anyhow::Result<ExecutionOutcome> {
+        let mut tx = Tx {
+            chain_id: "kova-devnet".into(),
+            nonce: self.nonce,
+            gas_limit: 300_000,
+            max_fee: Some(1),
+            max_priority_fee: Some(0),
+            gas_price: None,
+            payload,
+            public_key: signer().verifying_key().to_bytes().to_vec(),
+            signature: vec![],
+        };
+        tx.signature = sign_bytes(&signer(), &tx_signing_bytes(&tx)?);
+        let outcome = apply_tx(&self.ctx, &tx, self.nonce).await?;
+        self.nonce += 1;
+        Ok(outcome)
+    }
+
+    async fn call(&mut self, payload: serde_json::Value, max_gas: u64) -> anyhow::Result<()> {
+        let call = DomainCall {
+            domain_id: self.id,
+            payload,
+            raw: vec![],
+            max_gas: Some(max_gas),
+        };
+        self.apply(TxPayload::DomainExecute(call)).await.map(|_| ())
+    }
+
+    async fn deploy(&mut self, module_id: &str, code: &[u8]) -> anyhow::Result<()> {
+        let payload = serde_json::json!({
+            "action": "deploy",
+            "module_id": module_id,
+            "code_b64": base64::encode(code),
+        });
+        self.call(payload, 50_000).await
+    }
+
+    /// Runs the module's `f` and returns the error it fails with.
+    async fn invoke_err(&mut self, module_id: &str, max_gas: u64) -> String {
+        let payload = serde_json::json!({
+            "action": "invoke",
+            "module_id": module_id,
+            "entry": "f",
+        });
+        let err = self.call(payload, max_gas).await.unwrap_err();
+        format!("{err:#}")
+    }
+}
+
+#[tokio::test]
+async fn risk_params_bound_memory_stack_size_and_time() -> anyhow::Result<()> {
+    let mut domain = Domain::create(serde_json::json!({
+        "kind": "wasm",
+        "wasm_max_memory_bytes": 65_536,
+        "wasm_max_stack_bytes": 65_536,
+        "wasm_max_module_bytes": 96,
+        "wasm_max_execution_ms": 50,
+    }))
+    .await?;
+
+    let err = domain
+        .deploy("memory", &two_page_memory())
+        .await
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("wasm memory limit exceeded"));
+    let grow = wat::parse_str(
+        r#"(module (memory 1) (func (export "f") (drop (memory.grow (i32.const 1)))))"#,
+    )?;
+    domain.deploy("grow", &grow).await?;
+    let err = domain.invoke_err("grow", 1_000_000).await;
+    assert!(err.contains("wasm memory limit exceeded"), "{err}");
+
+    // (func $f (call $f))
+    domain
+        .deploy("recurse", &exported_func(&[0x10, 0x00, 0x0b]))
+        .await?;
+    let err = domain.invoke_err("recurse", 1_000_000_000).await;
+    assert!(err.contains("wasm call depth limit exceeded"), "{err}");
+    let err = domain.invoke_err("recurse", 100).await;
+    assert!(err.contains("wasm out of gas"), "{err}");
+
+    // (func $f (loop (br 0)))
+    let spin = exported_func(&[0x03, 0x40, 0x0c, 0x00, 0x0b, 0x0b]);
+    domain.deploy("spin", &spin).await?;
+    let err = domain.invoke_err("spin", 1_000_000_000_000).await;
+    assert!(err.contains("wasm execution time limit exceeded"), "{err}");
+
+    let err = domain.deploy("large", &padded(100)).await.unwrap_err();
+    assert!(format!("{err:#}").contains("exceeds size limit"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_limits_are_rejected_and_updates_apply() -> anyhow::Result<()> {
+    let bad = Domain::create(serde_json::json!({"kind": "wasm", "wasm_max_memory_bytes": 0})).await;
+    assert!(bad.is_err());
+
+    let mut domain = Domain::create(serde_json::json!({"kind": "wasm"})).await?;
+    domain.deploy("memory", &two_page_memory()).await?;
+    let payload = serde_json::json!({"action": "invoke", "module_id": "memory"});
+    domain.call(payload.clone(), 1_000_000).await?;
+
+    let id = domain.id;
+    domain
+        .apply(TxPayload::DomainConfigUpdate {
+            domain_id: id,
+            params: serde_json::json!({"kind": "wasm", "wasm_max_memory_bytes": 65_536}),
+        })
+        .await?;
+    let err = domain.call(payload, 1_000_000).await.unwrap_err();
+    assert!(format!("{err:#}").contains("wasm memory limit exceeded"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn modules_outside_the_deploy_policy_are_rejected() -> anyhow::Result<()> {
+    let mut domain =
+        Domain::create(serde_json::json!({"kind": "wasm", "wasm_max_functions": 2})).await?;
+    let rejected = [
+        (
+            r#"(module (func (export "f") (result f32) (f32.const 1)))"#,
+            "unsupported",
+        ),
+        (
+            "(module (memory 1) (func (memory.fill (i32.const 0) (i32.const 0) (i32.const 9))))",
+            "unsupported",
+        ),
+        (
+            r#"(module (import "env" "abort" (func)))"#,
+            "imports unknown env::abort",
+        ),
+        (
+            r#"(module (import "kova" "memory" (memory 1)))"#,
+            "imports unknown kova::memory",
+        ),
+        ("(module (func) (func) (func))", "too many functions"),
+        (
+            "(module (table 20000 funcref))",
+            "wasm table limit exceeded",
+        ),
+    ];
+    for (module, why) in rejected {
+        let err = domain
+            .deploy("bad", &wat::parse_str(module)?)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains(why), "{module}: {err:#}");
+    }
+    domain
+        .deploy("ok", &wat::parse_str("(module (func) (func))")?)
+        .await?;
+    Ok(())
+}
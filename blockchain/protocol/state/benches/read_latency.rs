@@ -0,0 +1,133 @@
+//! Load test for RPC-style reads while blocks are being written: full-state
+//! reads through the store lock versus loads of a published `StateView`.
+//! Run with `cargo bench -p state --bench read_latency`.
+
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use state::{Account, ChainState, InMemoryStateStore, StateStore, StateView};
+
+const ACCOUNTS: u32 = 50_000;
+const READERS: usize = 4;
+const READS_PER_READER: usize = 500;
+const WRITES_PER_BLOCK: u32 = 200;
+
+#[derive(Clone, Copy)]
+enum ReadPath {
+    Store,
+    View,
+}
+
+fn account(i: u32) -> Account {
+    let mut address = [0u8; 32];
+    address[..4].copy_from_slice(&i.to_le_bytes());
+    Account {
+        address,
+        nonce: i as u64,
+        balance_x: i as u128 * 1_000,
+        code_hash: None,
+        storage_root: None,
+        assets: Default::default(),
+    }
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+}
+
+/// Executes blocks until stopped: account writes, then a view publish, as the
+/// node does after every block.
+fn writer(store: InMemoryStateStore, view: StateView, stop: Arc<AtomicBool>) {
+    let rt = runtime();
+    let mut round = 0u32;
+    while !stop.load(Ordering::Relaxed) {
+        rt.block_on(async {
+            for i in 0..WRITES_PER_BLOCK {
+                let mut acct = account((round * WRITES_PER_BLOCK + i) % ACCOUNTS);
+                acct.nonce += round as u64;
+                store.put_account(acct).await.unwrap();
+            }
+            view.publish(store.get_chain_state().await.unwrap());
+        });
+        round += 1;
+    }
+}
+
+fn reader(store: InMemoryStateStore, view: StateView, path: ReadPath) -> Vec<Duration> {
+    let rt = runtime();
+    let probe = account(ACCOUNTS / 2).address;
+    (0..READS_PER_READER)
+        .map(|_| {
+            let start = Instant::now();
+            match path {
+                ReadPath::Store => {
+                    let chain = rt.block_on(store.get_chain_state()).unwrap();
+                    black_box(chain.accounts.get(&probe).map(|a| a.balance_x));
+                }
+                ReadPath::View => {
+                    let chain = view.load();
+                    black_box(chain.accounts.get(&probe).map(|a| a.balance_x));
+                }
+            }
+            start.elapsed()
+        })
+        .collect()
+}
+
+fn run(path: ReadPath) -> Vec<Duration> {
+    let mut chain = ChainState::default();
+    for i in 0..ACCOUNTS {
+        let acct = account(i);
+        chain.accounts.insert(acct.address, acct);
+    }
+    let store = InMemoryStateStore::new();
+    runtime()
+        .block_on(store.put_chain_state(chain.clone()))
+        .unwrap();
+    let view = StateView::new(chain);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let writer = {
+        let (store, view, stop) = (store.clone(), view.clone(), stop.clone());
+        thread::spawn(move || writer(store, view, stop))
+    };
+    let readers: Vec<_> = (0..READERS)
+        .map(|_| {
+            let (store, view) = (store.clone(), view.clone());
+            thread::spawn(move || reader(store, view, path))
+        })
+        .collect();
+    let mut latencies: Vec<Duration> = readers
+        .into_iter()
+        .flat_map(|r| r.join().unwrap())
+        .collect();
+    stop.store(true, Ordering::Relaxed);
+    writer.join().unwrap();
+    latencies.sort_unstable();
+    latencies
+}
+
+fn percentile(sorted: &[Duration], pct: usize) -> Duration {
+    sorted[(sorted.len() - 1) * pct / 100]
+}
+
+fn main() {
+    for (name, path) in [
+        ("store.get_chain_state", ReadPath::Store),
+        ("view.load", ReadPath::View),
+    ] {
+        let latencies = run(path);
+        println!(
+            "{name:<24} reads={} p50={:?} p99={:?} max={:?}",
+            latencies.len(),
+            percentile(&latencies, 50),
+            percentile(&latencies, 99),
+            latencies.last().unwrap()
+        );
+    }
+}
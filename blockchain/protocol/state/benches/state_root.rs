@@ -0,0 +1,49 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use state::{Account, ChainState};
+
+fn state_with_accounts(count: u32) -> ChainState {
+    let mut state = ChainState::default();
+    for i in 0..count {
+        let mut address = [0u8; 32];
+        address[..4].copy_from_slice(&i.to_le_bytes());
+        state.accounts.insert(
+            address,
+            Account {
+                address,
+                nonce: i as u64,
+                balance_x: i as u128 * 1_000,
+                code_hash: None,
+                storage_root: None,
+                assets: Default::default(),
+            },
+        );
+    }
+    state
+}
+
+fn bench_state_root(c: &mut Criterion) {
+    let mut group = c.benchmark_group("state_root");
+    group.sample_size(20);
+    for count in [1_000u32, 10_000, 100_000] {
+        let state = state_with_accounts(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &state, |b, state| {
+            b.iter(|| state.state_root())
+        });
+    }
+    group.finish();
+}
+
+fn bench_snapshot(c: &mut Criterion) {
+    let state = state_with_accounts(10_000);
+    c.bench_function("snapshot_10000_accounts", |b| {
+        b.iter(|| {
+            state
+                .snapshot(1, state::DEFAULT_SNAPSHOT_CHUNK_SIZE)
+                .unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, bench_state_root, bench_snapshot);
+criterion_main!(benches);
@@ -0,0 +1,229 @@
+//! Archive mode: a state root for every height plus the diffs needed to
+//! rebuild state at any of them. A full checkpoint is kept every
+//! `checkpoint_interval` heights, so a lookup replays at most that many diffs.
+
+use std::collections::{BTreeMap, HashMap};
+use std::ops::{Bound, RangeInclusive};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{fold_hashes, Account, Address, ChainState, Hash, Validator};
+
+pub const DEFAULT_ARCHIVE_CHECKPOINT_INTERVAL: u64 = 256;
+
+/// Changes since the previous height. Accounts and validators are diffed per
+/// key; the other sections are carried whole when any of them changed.
+#[derive(Debug, Clone, Default)]
+struct StateDiff {
+    accounts: HashMap<Address, Option<Account>>,
+    validators: HashMap<Uuid, Option<Validator>>,
+    rest: Option<ChainState>,
+}
+
+impl StateDiff {
+    fn apply(&self, state: &mut ChainState) {
+        if let Some(rest) = &self.rest {
+            let accounts = std::mem::take(&mut state.accounts);
+            let validators = std::mem::take(&mut state.validators);
+            *state = ChainState {
+                accounts,
+                validators,
+                ..rest.clone()
+            };
+        }
+        for (address, account) in &self.accounts {
+            match account {
+                Some(account) => state.accounts.insert(*address, account.clone()),
+                None => state.accounts.remove(address),
+            };
+        }
+        for (id, validator) in &self.validators {
+            match validator {
+                Some(validator) => state.validators.insert(*id, validator.clone()),
+                None => state.validators.remove(id),
+            };
+        }
+    }
+}
+
+/// The most recently recorded state, kept whole so the next height can be
+/// diffed against it.
+struct Tip {
+    height: u64,
+    state: Arc<ChainState>,
+    sections: BTreeMap<&'static str, Hash>,
+}
+
+#[derive(Default)]
+struct ArchiveInner {
+    roots: BTreeMap<u64, Hash>,
+    checkpoints: BTreeMap<u64, Arc<ChainState>>,
+    diffs: BTreeMap<u64, StateDiff>,
+    tip: Option<Tip>,
+}
+
+impl ArchiveInner {
+    fn truncate(&mut self, from: u64) {
+        self.roots.split_off(&from);
+        self.checkpoints.split_off(&from);
+        self.diffs.split_off(&from);
+        self.tip = None;
+    }
+
+    /// Checkpoint at or below `height` and the diffs leading up to it.
+    fn path(
+        &self,
+        height: u64,
+    ) -> Option<(&ChainState, impl DoubleEndedIterator<Item = &StateDiff>)> {
+        if !self.roots.contains_key(&height) {
+            return None;
+        }
+        let (&base, checkpoint) = self.checkpoints.range(..=height).next_back()?;
+        let diffs = self
+            .diffs
+            .range((Bound::Excluded(base), Bound::Included(height)))
+            .map(|(_, diff)| diff);
+        Some((checkpoint.as_ref(), diffs))
+    }
+}
+
+#[derive(Clone)]
+pub struct StateArchive {
+    checkpoint_interval: u64,
+    inner: Arc<Mutex<ArchiveInner>>,
+}
+
+impl Default for StateArchive {
+    fn default() -> Self {
+        Self::new(DEFAULT_ARCHIVE_CHECKPOINT_INTERVAL)
+    }
+}
+
+impl StateArchive {
+    pub fn new(checkpoint_interval: u64) -> Self {
+        Self {
+            checkpoint_interval: checkpoint_interval.max(1),
+            inner: Arc::new(Mutex::new(ArchiveInner::default())),
+        }
+    }
+
+    /// Records `state` as of `height` and returns its root. Recording a height
+    /// that is already archived drops it and everything after it, as after a
+    /// reorg or a resync.
+    pub fn record(&self, height: u64, state: &ChainState) -> Hash {
+        let sections = state.leaf_sections();
+        let section_roots: BTreeMap<&'static str, Hash> = sections
+            .iter()
+            .map(|(name, leaves)| (*name, fold_hashes(leaves.clone())))
+            .collect();
+        let root = fold_hashes(sections.into_iter().flat_map(|(_, l)| l).collect());
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.roots.range(height..).next().is_some() {
+            inner.truncate(height);
+        }
+        let state = Arc::new(state.clone());
+        match inner.tip.take() {
+            Some(tip)
+                if tip.height + 1 == height && !height.is_multiple_of(self.checkpoint_interval) =>
+            {
+                let diff = diff_against(&tip, &state, &section_roots);
+                inner.diffs.insert(height, diff);
+            }
+            _ => {
+                inner.checkpoints.insert(height, state.clone());
+            }
+        }
+        inner.roots.insert(height, root);
+        inner.tip = Some(Tip {
+            height,
+            state,
+            sections: section_roots,
+        });
+        root
+    }
+
+    pub fn state_root(&self, height: u64) -> Option<Hash> {
+        self.inner.lock().unwrap().roots.get(&height).copied()
+    }
+
+    /// Lowest and highest archived heights.
+    pub fn heights(&self) -> Option<RangeInclusive<u64>> {
+        let inner = self.inner.lock().unwrap();
+        let first = *inner.roots.keys().next()?;
+        let last = *inner.roots.keys().next_back()?;
+        Some(first..=last)
+    }
+
+    pub fn state_at(&self, height: u64) -> Option<ChainState> {
+        let inner = self.inner.lock().unwrap();
+        let (checkpoint, diffs) = inner.path(height)?;
+        let mut state = checkpoint.clone();
+        for diff in diffs {
+            diff.apply(&mut state);
+        }
+        Some(state)
+    }
+
+    /// The account as of `height`, found by walking diffs back to the nearest
+    /// checkpoint instead of rebuilding the whole state. `None` when the
+    /// height isn't archived.
+    pub fn account_at(&self, height: u64, address: &Address) -> Option<Option<Account>> {
+        let inner = self.inner.lock().unwrap();
+        let (checkpoint, diffs) = inner.path(height)?;
+        let found = diffs
+            .rev()
+            .find_map(|diff| diff.accounts.get(address).cloned())
+            .unwrap_or_else(|| checkpoint.accounts.get(address).cloned());
+        Some(found)
+    }
+}
+
+fn diff_against(
+    prev: &Tip,
+    state: &ChainState,
+    sections: &BTreeMap<&'static str, Hash>,
+) -> StateDiff {
+    let section_changed = |name: &str| prev.sections.get(name) != sections.get(name);
+    let rest_changed = sections
+        .keys()
+        .any(|name| !matches!(*name, "accounts" | "validators") && section_changed(name));
+    StateDiff {
+        accounts: if section_changed("accounts") {
+            changed(&prev.state.accounts, &state.accounts)
+        } else {
+            HashMap::new()
+        },
+        validators: if section_changed("validators") {
+            changed(&prev.state.validators, &state.validators)
+        } else {
+            HashMap::new()
+        },
+        rest: rest_changed.then(|| ChainState {
+            accounts: HashMap::new(),
+            validators: HashMap::new(),
+            ..state.clone()
+        }),
+    }
+}
+
+fn changed<K, V>(prev: &HashMap<K, V>, next: &HashMap<K, V>) -> HashMap<K, Option<V>>
+where
+    K: Eq + std::hash::Hash + Clone,
+    V: Clone + Serialize,
+{
+    let same = |a: &V, b: &V| bincode::serialize(a).ok() == bincode::serialize(b).ok();
+    let mut out: HashMap<K, Option<V>> = next
+        .iter()
+        .filter(|(key, value)| prev.get(*key).is_none_or(|old| !same(old, value)))
+        .map(|(key, value)| (key.clone(), Some(value.clone())))
+        .collect();
+    out.extend(
+        prev.keys()
+            .filter(|key| !next.contains_key(*key))
+            .map(|key| (key.clone(), None)),
+    );
+    out
+}
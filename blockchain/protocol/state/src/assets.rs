@@ -0,0 +1,59 @@
+//! User-issued assets. Balances live on each holder's `Account`; the registry
+//! in `ChainState` tracks issuer and supply. Gas is always paid in the native
+//! asset (`balance_x`).
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Account, Address};
+
+pub const MAX_ASSET_SYMBOL_LEN: usize = 12;
+pub const MAX_ASSET_DECIMALS: u8 = 18;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Asset {
+    pub id: Uuid,
+    pub symbol: String,
+    pub decimals: u8,
+    /// The only account allowed to mint.
+    pub issuer: Address,
+    pub supply: u128,
+    /// `None` for an uncapped asset.
+    pub max_supply: Option<u128>,
+    pub created_height: u64,
+}
+
+/// Deterministic id of the asset `issuer` creates with `nonce`.
+pub fn asset_id(issuer: &Address, nonce: u64) -> Uuid {
+    let mut seed = issuer.to_vec();
+    seed.extend_from_slice(&nonce.to_le_bytes());
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, &seed)
+}
+
+impl Account {
+    pub fn asset_balance(&self, asset_id: &Uuid) -> u128 {
+        self.assets.get(asset_id).copied().unwrap_or(0)
+    }
+
+    pub fn credit_asset(&mut self, asset_id: Uuid, amount: u128) -> anyhow::Result<()> {
+        let balance = self.assets.entry(asset_id).or_insert(0);
+        *balance = balance
+            .checked_add(amount)
+            .ok_or_else(|| anyhow::anyhow!("asset balance overflow"))?;
+        Ok(())
+    }
+
+    /// Zero balances are dropped so empty holdings don't linger in state.
+    pub fn debit_asset(&mut self, asset_id: &Uuid, amount: u128) -> anyhow::Result<()> {
+        let balance = self.asset_balance(asset_id);
+        let remaining = balance
+            .checked_sub(amount)
+            .ok_or_else(|| anyhow::anyhow!("insufficient asset balance"))?;
+        if remaining == 0 {
+            self.assets.remove(asset_id);
+        } else {
+            self.assets.insert(*asset_id, remaining);
+        }
+        Ok(())
+    }
+}
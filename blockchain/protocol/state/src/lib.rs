@@ -1,10 +1,59 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
+mod archive;
+mod assets;
+mod merkle;
+mod multisig;
+mod overlay;
+mod params;
+mod proposals;
+mod pruning;
+mod schedule;
+pub mod sections;
+mod snapshot;
+mod staking;
+mod vesting;
+mod view;
+
+pub use archive::{StateArchive, DEFAULT_ARCHIVE_CHECKPOINT_INTERVAL};
+pub use assets::{asset_id, Asset, MAX_ASSET_DECIMALS, MAX_ASSET_SYMBOL_LEN};
+pub use merkle::{
+    merkle_path, CommitmentTree, MerklePath, COMMITMENT_TREE_DEPTH, ROOT_HISTORY_SIZE,
+};
+pub use multisig::{
+    multisig_address, validate_signers, Multisig, MultisigCall, MultisigProposal,
+    MAX_MULTISIG_SIGNERS,
+};
+pub use overlay::{Checkpoint, StateOverlay};
+pub use params::{FeeSplit, ParamOverrides, RewardParams};
+pub use proposals::{
+    ProposalIndex, ProposalPage, ProposalQuery, ProposalSummary, SortOrder,
+    DEFAULT_PROPOSAL_PAGE_SIZE, MAX_PROPOSAL_PAGE_SIZE,
+};
+pub use pruning::{PruneReport, RetentionParams};
+pub use sections::{MapSection, Section};
+pub use schedule::{
+    Schedule, ScheduledCall, MAX_SCHEDULED_PER_ACCOUNT, MAX_SCHEDULED_PER_BLOCK,
+    MAX_SCHEDULE_DELAY_BLOCKS,
+};
+pub use snapshot::{
+    SnapshotManifest, SnapshotStore, StateSnapshot, DEFAULT_SNAPSHOT_CHUNK_SIZE,
+    DEFAULT_SNAPSHOT_RETENTION,
+};
+pub use staking::{
+    delegate_to, mul_div, slash_positions, undelegate_from, DelegationPosition, StakingParams,
+    ValidatorRewards, REWARD_INDEX_SCALE,
+};
+pub use vesting::{
+    locked_balance, VestingSchedule, MAX_VESTING_SCHEDULES_PER_ACCOUNT, MIN_VESTING_AMOUNT,
+};
+pub use view::StateView;
+
 fn hash_leaf(bytes: &[u8]) -> Hash {
     *blake3::hash(bytes).as_bytes()
 }
@@ -31,6 +80,9 @@ pub struct Account {
     pub balance_x: u128,
     pub code_hash: Option<Hash>,
     pub storage_root: Option<Hash>,
+    /// Balances of user-issued assets, by asset id.
+    #[serde(default)]
+    pub assets: BTreeMap<Uuid, u128>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +90,9 @@ pub enum ValidatorStatus {
     Active,
     Jailed,
     Exited,
+    /// Bonded but outside the active set, below the minimum stake or ranked
+    /// past `max_active_validators`.
+    Candidate,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,13 +103,33 @@ pub struct Validator {
     pub stake: u128,
     pub status: ValidatorStatus,
     pub commission_rate: u8,
+    /// Compressed BLS12-381 public key used for aggregated consensus votes.
+    #[serde(default)]
+    pub bls_pubkey: Vec<u8>,
+    #[serde(default)]
+    pub description: ValidatorDescription,
+    /// Epoch of the last commission change; the rate moves at most once per
+    /// epoch.
+    #[serde(default)]
+    pub commission_changed_epoch: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Delegation {
-    pub delegator: Address,
-    pub validator_id: Uuid,
-    pub stake: u128,
+/// Public profile a validator sets with `ValidatorEdit`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorDescription {
+    pub moniker: String,
+    pub website: String,
+    pub details: String,
+}
+
+/// A validator's uptime over the current liveness window.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidatorLiveness {
+    /// Votes and proposals expected of the validator this window.
+    pub expected: u64,
+    pub missed: u64,
+    /// Height from which a validator jailed for downtime may unjail.
+    pub jailed_until: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +140,16 @@ pub struct Unbonding {
     pub release_height: u64,
 }
 
+/// Stake reduction waiting for churn capacity at an epoch boundary. Entries
+/// are served in queue order, possibly across several epochs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingExit {
+    pub owner: Address,
+    pub validator_id: Uuid,
+    pub amount: u128,
+    pub requested_height: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DomainType {
     EvmSharedSecurity,
@@ -74,12 +159,35 @@ pub enum DomainType {
     Custom,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SecurityModel {
     SharedSecurity,
     OwnSecurity,
 }
 
+/// Where a domain is in its lifecycle. Only active domains execute calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DomainStatus {
+    /// Waiting for governance to approve it.
+    Pending,
+    #[default]
+    Active,
+    Paused,
+    /// Closed for good; its registration bond was returned.
+    Retired,
+}
+
+/// How a domain's settled root advances: optimistically, open to fraud
+/// challenges, or only with a verified validity proof.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofMode {
+    #[default]
+    Optimistic,
+    Validity,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DomainEntry {
     pub domain_id: Uuid,
@@ -88,6 +196,16 @@ pub struct DomainEntry {
     pub sequencer_binding: Option<Uuid>,
     pub bridge_contracts: Vec<String>,
     pub risk_params: serde_json::Value,
+    #[serde(default)]
+    pub proof_mode: ProofMode,
+    #[serde(default)]
+    pub status: DomainStatus,
+    /// Account that registered the domain and may pause, resume or retire it.
+    #[serde(default)]
+    pub owner: Address,
+    /// Registration bond locked from `owner` until the domain is retired.
+    #[serde(default)]
+    pub bond: u128,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -104,6 +222,180 @@ pub struct DomainRoot {
     pub da_root: Hash,
     pub last_verified_epoch: u64,
     pub proof_meta: serde_json::Value,
+    /// Proven batches committed for the domain.
+    #[serde(default)]
+    pub batch_height: u64,
+}
+
+/// Root a sequencer committed for an optimistic domain without a proof. It
+/// stands unless a dispute opened before `challenge_until` shows it wrong.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimisticClaim {
+    pub domain_id: Uuid,
+    pub sequencer: Address,
+    /// Returned once the claim is final, paid to the challenger if it is
+    /// proven fraudulent.
+    pub bond: u128,
+    /// Root the claim builds on, restored if it is proven fraudulent.
+    pub prev_root: Hash,
+    pub state_root: Hash,
+    /// Merkle root over the claim's domain calls in execution order.
+    pub calls_root: Hash,
+    pub steps: u64,
+    pub claimed_at: u64,
+    pub challenge_until: u64,
+    pub dispute: Option<FraudDispute>,
+}
+
+/// Bisection over a claim's calls. Both sides agree on the root after
+/// `agreed_step` calls and disagree on the root after `disputed_step`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FraudDispute {
+    pub challenger: Address,
+    pub bond: u128,
+    /// Root the challenger says the claim should have committed.
+    pub challenger_root: Hash,
+    pub agreed_step: u64,
+    pub agreed_root: Hash,
+    pub disputed_step: u64,
+    pub disputed_root: Hash,
+    /// The sequencer's root after `midpoint()` calls, awaiting the
+    /// challenger's answer.
+    pub midpoint_root: Option<Hash>,
+    /// Height by which the side to move must act or lose.
+    pub deadline: u64,
+}
+
+impl FraudDispute {
+    pub fn midpoint(&self) -> u64 {
+        self.agreed_step + (self.disputed_step - self.agreed_step) / 2
+    }
+
+    /// Whether the sequencer moves next; otherwise the challenger does.
+    pub fn sequencer_to_move(&self) -> bool {
+        self.midpoint_root.is_none()
+    }
+}
+
+/// Stake a sequencer locked to post batches for a domain. It weights the
+/// sequencer's turns as leader and is slashed for fraud or missed rounds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequencerBond {
+    pub domain_id: Uuid,
+    pub sequencer: Address,
+    pub bond: u128,
+    pub registered_at: u64,
+    /// Height of the `SequencerExit`; the bond is released once the domain's
+    /// challenge window has passed since.
+    pub exiting_since: Option<u64>,
+}
+
+/// Leader of a domain's current rotation round and whether it has posted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequencerRound {
+    pub round: u64,
+    pub leader: Address,
+    pub posted: bool,
+    /// Failovers so far this round; each hands the round to a sequencer
+    /// not yet drawn for it.
+    #[serde(default)]
+    pub attempt: u32,
+}
+
+/// Batch a domain advanced by, kept so a pre-confirmation challenge can be
+/// answered with the batch's blob.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRecord {
+    pub batch_height: u64,
+    pub block_height: u64,
+    /// `calls_root` of an optimistic claim, or the proven batch commitment.
+    pub commitment: Hash,
+    pub optimistic: bool,
+}
+
+/// Domain tx queued on L1 for the sequencer to include. Past `deadline` L1
+/// applies it itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForcedInclusion {
+    pub domain_id: Uuid,
+    pub tx_hash: Hash,
+    pub tx_bytes: Vec<u8>,
+    pub sender: Address,
+    /// Domain batch height when queued; a later batch must carry the tx.
+    pub queued_head: u64,
+    pub deadline: u64,
+}
+
+/// External chain header a light client accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LightClientHeader {
+    pub state_root: Hash,
+    /// Hash of the validator set that signs the next header.
+    pub validator_set_hash: Hash,
+    pub height: u64,
+}
+
+/// Light client of an external chain, following its headers as its
+/// validators sign them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LightClient {
+    pub client_id: String,
+    /// The external chain's id, which its packets name as their source.
+    pub chain_id: String,
+    /// Ed25519 keys of the validators trusted to sign the next header.
+    pub validators: Vec<Vec<u8>>,
+    /// Signatures from `validators` a header needs.
+    pub threshold: u32,
+    /// Most recent accepted headers by height.
+    pub headers: BTreeMap<u64, LightClientHeader>,
+}
+
+impl LightClient {
+    pub fn latest_height(&self) -> u64 {
+        self.headers.keys().next_back().copied().unwrap_or(0)
+    }
+}
+
+/// Governance limits on a domain's bridge; zero caps are unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeLimits {
+    /// Most a single withdrawal may take out of the escrow.
+    pub max_withdrawal: u128,
+    /// Most that may leave the escrow per epoch.
+    pub epoch_outflow_cap: u128,
+    /// Halts deposits, burns and withdrawals.
+    pub paused: bool,
+}
+
+/// L1 funds a domain's bridge holds for its users, and the domain-side
+/// burns already paid out against them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BridgeEscrow {
+    pub balance: u128,
+    /// Burn nonces whose withdrawal was paid.
+    pub withdrawn: BTreeSet<u64>,
+    #[serde(default)]
+    pub limits: BridgeLimits,
+    /// Epoch `epoch_outflow` was counted in.
+    #[serde(default)]
+    pub outflow_epoch: u64,
+    #[serde(default)]
+    pub epoch_outflow: u128,
+}
+
+/// Challenge of a sequencer's pre-confirmation, open until `deadline` and
+/// kept once resolved so the promise cannot be challenged twice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreconfDispute {
+    pub domain_id: Uuid,
+    pub tx_hash: Hash,
+    pub sequencer: Address,
+    pub challenger: Address,
+    pub bond: u128,
+    pub domain_head: u64,
+    pub expiry: u64,
+    pub deadline: u64,
+    pub resolved: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -150,6 +442,10 @@ pub struct Proposal {
     pub execution: serde_json::Value,
     pub voter_weights: HashMap<Address, u128>,
     pub approvals: Vec<Address>,
+    /// Held from the proposer until the proposal is settled; zero once
+    /// refunded or burned.
+    #[serde(default)]
+    pub deposit: u128,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -160,6 +456,27 @@ pub struct GovernanceParams {
     pub approval_threshold_bps: u16,
     pub multisig_signers: Vec<Address>,
     pub multisig_threshold: u8,
+    /// Taken from the proposer on submission.
+    pub min_deposit: u128,
+    /// A defeated proposal's deposit is burned when fewer than this share of
+    /// the snapshot stake voted for it; otherwise it is refunded.
+    pub deposit_burn_threshold_bps: u16,
+    /// How long a queued proposal may wait past its eta before it expires.
+    pub queued_grace_ms: u64,
+    /// Most the treasury may pay out per period of this many blocks.
+    pub treasury_spend_cap: u128,
+    pub treasury_spend_period_blocks: u64,
+    /// Locked from whoever registers a domain.
+    #[serde(default = "default_domain_registration_bond")]
+    pub domain_registration_bond: u128,
+    /// Whether shared-security domains stay pending until governance
+    /// approves them.
+    #[serde(default)]
+    pub shared_security_approval: bool,
+}
+
+fn default_domain_registration_bond() -> u128 {
+    100_000
 }
 
 impl Default for GovernanceParams {
@@ -171,11 +488,18 @@ impl Default for GovernanceParams {
             approval_threshold_bps: 5_000,      // 50%
             multisig_signers: Vec::new(),
             multisig_threshold: 1,
+            min_deposit: 1_000,
+            deposit_burn_threshold_bps: 1_000,  // 10%
+            queued_grace_ms: 24 * 60 * 60 * 1000, // 1 day
+            treasury_spend_cap: 1_000_000,
+            treasury_spend_period_blocks: 43_200,
+            domain_registration_bond: default_domain_registration_bond(),
+            shared_security_approval: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct FeePools {
     pub l1_gas: u128,
     pub da: u128,
@@ -183,32 +507,156 @@ pub struct FeePools {
     pub treasury: u128,
 }
 
+/// Treasury spent so far in the current spending period.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TreasuryPeriod {
+    pub period: u64,
+    pub spent: u128,
+}
+
+/// Pool that takes deposits of any amount; payloads naming no pool use it.
+pub const DEFAULT_PRIVACY_POOL: &str = "shielded";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrivacyPool {
+    /// Current root of `tree`.
     pub merkle_root: Hash,
     pub parameters: serde_json::Value,
     pub nullifiers: Vec<Hash>,
+    /// Commitments in insertion order, i.e. by leaf index.
     pub commitments: Vec<Hash>,
+    #[serde(default)]
+    pub tree: CommitmentTree,
     pub total_shielded: u128,
+    /// Protocol fee on withdrawals, set by governance.
+    #[serde(default)]
+    pub withdraw_fee_bps: u16,
+    /// Share of the withdraw fee paid to whoever submits the withdrawal; the
+    /// rest goes to the treasury.
+    #[serde(default)]
+    pub relayer_fee_share_bps: u16,
+    /// The only amount deposits and withdrawals may move, so every note in
+    /// the pool looks alike. `None` accepts any amount.
+    #[serde(default)]
+    pub denomination: Option<u128>,
+}
+
+/// What a pool's anonymity set looks like from outside.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PrivacyPoolStats {
+    pub pool: String,
+    pub denomination: Option<u128>,
+    pub deposits: u64,
+    pub withdrawals: u64,
+    pub total_shielded: u128,
+    pub merkle_root: Hash,
+    pub withdraw_fee_bps: u16,
 }
 
 impl Default for PrivacyPool {
     fn default() -> Self {
+        let tree = CommitmentTree::default();
         Self {
-            merkle_root: [0u8; 32],
+            merkle_root: tree.root(),
             parameters: serde_json::json!({}),
             nullifiers: Vec::new(),
             commitments: Vec::new(),
+            tree,
             total_shielded: 0,
+            withdraw_fee_bps: 0,
+            relayer_fee_share_bps: 0,
+            denomination: None,
+        }
+    }
+}
+
+impl PrivacyPool {
+    /// Membership path of `commitment` against the current root.
+    pub fn witness(&self, commitment: &Hash) -> Option<MerklePath> {
+        let index = self.commitments.iter().position(|c| c == commitment)?;
+        merkle_path(&self.commitments, index)
+    }
+
+    pub fn stats(&self, pool: &str) -> PrivacyPoolStats {
+        PrivacyPoolStats {
+            pool: pool.to_string(),
+            denomination: self.denomination,
+            deposits: self.tree.len(),
+            withdrawals: self.nullifiers.len() as u64,
+            total_shielded: self.total_shielded,
+            merkle_root: self.merkle_root,
+            withdraw_fee_bps: self.withdraw_fee_bps,
         }
     }
 }
 
+/// Verification keys registered for one zk program, by version.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProgramKeys {
+    pub versions: BTreeMap<String, Vec<u8>>,
+    /// Version proofs are checked against.
+    pub active: Option<String>,
+}
+
+/// Keys proofs must carry, set through governance. A program without an
+/// active key is verified by the backend alone.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VerificationKeyRegistry {
+    /// By program name, e.g. `privacy_withdraw`.
+    pub programs: BTreeMap<String, ProgramKeys>,
+}
+
+impl VerificationKeyRegistry {
+    pub fn active_key(&self, program: &str) -> Option<&[u8]> {
+        let keys = self.programs.get(program)?;
+        let version = keys.active.as_ref()?;
+        keys.versions.get(version).map(Vec::as_slice)
+    }
+
+    /// Adds `version` of `program`, making it the active one when `activate`
+    /// is set. A version's key never changes once registered; registering
+    /// the same key again only activates it.
+    pub fn register(
+        &mut self,
+        program: &str,
+        version: &str,
+        key: Vec<u8>,
+        activate: bool,
+    ) -> anyhow::Result<()> {
+        let keys = self.programs.entry(program.to_string()).or_default();
+        match keys.versions.get(version) {
+            Some(existing) if *existing != key => {
+                anyhow::bail!("{program} version {version} is registered with another key")
+            }
+            Some(_) => {}
+            None => {
+                keys.versions.insert(version.to_string(), key);
+            }
+        }
+        if activate {
+            keys.active = Some(version.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Protocol version governance scheduled to take effect at a height.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolUpgrade {
+    /// Label for operators; execution doesn't read it.
+    pub name: String,
+    pub version: u32,
+    pub activation_height: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ChainState {
     pub accounts: HashMap<Address, Account>,
     pub validators: HashMap<Uuid, Validator>,
-    pub delegations: Vec<Delegation>,
+    pub delegations: HashMap<(Address, Uuid), DelegationPosition>,
+    /// Delegated stake and reward index per validator id.
+    #[serde(default)]
+    pub validator_rewards: HashMap<Uuid, ValidatorRewards>,
     pub domains: HashMap<Uuid, DomainEntry>,
     pub da_commitments: Vec<DACommitment>,
     pub domain_roots: HashMap<Uuid, DomainRoot>,
@@ -219,108 +667,317 @@ pub struct ChainState {
     pub total_supply: u128,
     pub last_reward_height: u64,
     pub pending_unbonds: Vec<Unbonding>,
+    /// Ids of double-sign evidence already slashed, to reject replays.
+    #[serde(default)]
+    pub processed_evidence: BTreeSet<Hash>,
+    #[serde(default)]
+    pub exit_queue: Vec<PendingExit>,
+    #[serde(default)]
+    pub staking_params: StakingParams,
+    #[serde(default)]
+    pub param_overrides: ParamOverrides,
+    #[serde(default)]
+    pub treasury_period: TreasuryPeriod,
+    #[serde(default)]
+    pub assets: HashMap<Uuid, Asset>,
+    #[serde(default)]
+    pub multisigs: HashMap<Address, Multisig>,
+    #[serde(default)]
+    pub schedule: Schedule,
+    /// Vesting schedules by beneficiary.
+    #[serde(default)]
+    pub vesting: HashMap<Address, Vec<VestingSchedule>>,
+    #[serde(default)]
+    pub liveness: HashMap<Uuid, ValidatorLiveness>,
+    /// Highest consensus view counted towards liveness, so a QC or timeout
+    /// reported twice counts once.
+    #[serde(default)]
+    pub liveness_view: Option<u64>,
+    #[serde(default)]
+    pub verification_keys: VerificationKeyRegistry,
+    /// Unfinalized optimistic roots by domain.
+    #[serde(default)]
+    pub optimistic_claims: HashMap<Uuid, OptimisticClaim>,
+    /// Latest root of each optimistic domain that can no longer be
+    /// challenged: one the L1 computed itself, or a claim whose window
+    /// closed undisputed. Bridge withdrawals prove against it.
+    #[serde(default)]
+    pub finalized_domain_roots: HashMap<Uuid, Hash>,
+    /// Bonded sequencers by domain, ordered by address.
+    #[serde(default)]
+    pub sequencer_bonds: HashMap<Uuid, Vec<SequencerBond>>,
+    #[serde(default)]
+    pub sequencer_rounds: HashMap<Uuid, SequencerRound>,
+    /// Latest batches by domain, oldest first.
+    #[serde(default)]
+    pub batch_records: HashMap<Uuid, Vec<BatchRecord>>,
+    /// Pre-confirmation challenges by tx hash.
+    #[serde(default)]
+    pub preconf_disputes: HashMap<Hash, PreconfDispute>,
+    /// Force-included txs by domain, in queue order.
+    #[serde(default)]
+    pub forced_inclusions: HashMap<Uuid, Vec<ForcedInclusion>>,
+    /// Light clients of external chains by client id.
+    #[serde(default)]
+    pub light_clients: HashMap<String, LightClient>,
+    /// Bridge escrow by domain.
+    #[serde(default)]
+    pub bridge_escrows: HashMap<Uuid, BridgeEscrow>,
+    /// Domain gas fees owed to each domain's sequencers.
+    #[serde(default)]
+    pub sequencer_fees: HashMap<Uuid, u128>,
+    #[serde(default)]
+    pub retention: RetentionParams,
+    /// Leaf hashes of everything pruned from the state, in pruning order.
+    #[serde(default)]
+    pub pruned: CommitmentTree,
+    /// Protocol version blocks are executed under.
+    #[serde(default)]
+    pub protocol_version: u32,
+    /// Upgrade waiting for its activation height.
+    #[serde(default)]
+    pub pending_upgrade: Option<ProtocolUpgrade>,
+}
+
+fn serialized_leaves<'a, T: Serialize + 'a>(items: impl IntoIterator<Item = &'a T>) -> Vec<Hash> {
+    items
+        .into_iter()
+        .filter_map(|item| bincode::serialize(item).ok())
+        .map(|bytes| hash_leaf(&bytes))
+        .collect()
 }
 
 impl ChainState {
+    /// Leaf hashes grouped by the part of the state they commit to.
+    fn leaf_sections(&self) -> Vec<(&'static str, Vec<Hash>)> {
+        vec![
+            ("accounts", serialized_leaves(self.accounts.values())),
+            ("validators", serialized_leaves(self.validators.values())),
+            ("delegations", serialized_leaves(self.delegations.values())),
+            (
+                "validator_rewards",
+                serialized_leaves(&self.validator_rewards.iter().collect::<Vec<_>>()),
+            ),
+            ("domains", serialized_leaves(self.domains.values())),
+            ("da_commitments", serialized_leaves(&self.da_commitments)),
+            ("domain_roots", serialized_leaves(self.domain_roots.values())),
+            ("proposals", serialized_leaves(self.proposals.values())),
+            ("fee_pools", serialized_leaves([&self.fee_pools])),
+            ("privacy_pools", serialized_leaves(self.privacy_pools.values())),
+            ("governance_params", serialized_leaves([&self.governance_params])),
+            ("total_supply", serialized_leaves([&self.total_supply])),
+            ("last_reward_height", serialized_leaves([&self.last_reward_height])),
+            ("pending_unbonds", serialized_leaves(&self.pending_unbonds)),
+            (
+                "processed_evidence",
+                self.processed_evidence.iter().map(|id| hash_leaf(id)).collect(),
+            ),
+            ("exit_queue", serialized_leaves(&self.exit_queue)),
+            ("staking_params", serialized_leaves([&self.staking_params])),
+            ("param_overrides", serialized_leaves([&self.param_overrides])),
+            ("treasury_period", serialized_leaves([&self.treasury_period])),
+            ("assets", serialized_leaves(self.assets.values())),
+            ("multisigs", serialized_leaves(self.multisigs.values())),
+            ("schedule", serialized_leaves([&self.schedule])),
+            ("vesting", serialized_leaves(self.vesting.values().flatten())),
+            ("liveness", serialized_leaves(&self.liveness.iter().collect::<Vec<_>>())),
+            ("liveness_view", serialized_leaves(self.liveness_view.iter())),
+            (
+                "verification_keys",
+                serialized_leaves(&self.verification_keys.programs.iter().collect::<Vec<_>>()),
+            ),
+            ("optimistic_claims", serialized_leaves(self.optimistic_claims.values())),
+            (
+                "finalized_domain_roots",
+                serialized_leaves(&self.finalized_domain_roots.iter().collect::<Vec<_>>()),
+            ),
+            (
+                "sequencer_bonds",
+                serialized_leaves(self.sequencer_bonds.values().flatten()),
+            ),
+            (
+                "sequencer_rounds",
+                serialized_leaves(&self.sequencer_rounds.iter().collect::<Vec<_>>()),
+            ),
+            (
+                "batch_records",
+                serialized_leaves(&self.batch_records.iter().collect::<Vec<_>>()),
+            ),
+            ("preconf_disputes", serialized_leaves(self.preconf_disputes.values())),
+            (
+                "forced_inclusions",
+                serialized_leaves(self.forced_inclusions.values().flatten()),
+            ),
+            ("light_clients", serialized_leaves(self.light_clients.values())),
+            (
+                "bridge_escrows",
+                serialized_leaves(&self.bridge_escrows.iter().collect::<Vec<_>>()),
+            ),
+            (
+                "sequencer_fees",
+                serialized_leaves(&self.sequencer_fees.iter().collect::<Vec<_>>()),
+            ),
+            ("retention", serialized_leaves([&self.retention])),
+            ("pruned", serialized_leaves([&self.pruned])),
+            ("protocol_version", serialized_leaves([&self.protocol_version])),
+            ("pending_upgrade", serialized_leaves(self.pending_upgrade.iter())),
+        ]
+    }
+
     pub fn state_root(&self) -> Hash {
-        let mut leaves = Vec::new();
+        let leaves = self
+            .leaf_sections()
+            .into_iter()
+            .flat_map(|(_, leaves)| leaves)
+            .collect();
+        fold_hashes(leaves)
+    }
 
-        for account in self.accounts.values() {
-            if let Ok(bytes) = bincode::serialize(account) {
-                leaves.push(hash_leaf(&bytes));
-            }
-        }
+    /// Per-section roots, so nodes that disagree on `state_root` can tell
+    /// which part of the state diverged.
+    pub fn component_roots(&self) -> BTreeMap<String, Hash> {
+        self.leaf_sections()
+            .into_iter()
+            .map(|(name, leaves)| (name.to_string(), fold_hashes(leaves)))
+            .collect()
+    }
+}
 
-        for validator in self.validators.values() {
-            if let Ok(bytes) = bincode::serialize(validator) {
-                leaves.push(hash_leaf(&bytes));
-            }
-        }
+#[async_trait]
+pub trait StateStore: Send + Sync {
+    async fn get_account(&self, address: &Address) -> anyhow::Result<Option<Account>>;
+    async fn put_account(&self, account: Account) -> anyhow::Result<()>;
+    async fn get_validator(&self, id: &Uuid) -> anyhow::Result<Option<Validator>>;
+    async fn put_validator(&self, validator: Validator) -> anyhow::Result<()>;
+    async fn get_chain_state(&self) -> anyhow::Result<ChainState>;
+    async fn put_chain_state(&self, state: ChainState) -> anyhow::Result<()>;
 
-        for delegation in &self.delegations {
-            if let Ok(bytes) = bincode::serialize(delegation) {
-                leaves.push(hash_leaf(&bytes));
-            }
-        }
+    // Granular access to parts of the chain state. The defaults go through
+    // the whole state; stores holding it in memory answer without a copy.
 
-        for domain in self.domains.values() {
-            if let Ok(bytes) = bincode::serialize(domain) {
-                leaves.push(hash_leaf(&bytes));
-            }
-        }
+    async fn get_proposal(&self, id: &Uuid) -> anyhow::Result<Option<Proposal>> {
+        Ok(self.get_chain_state().await?.proposals.remove(id))
+    }
 
-        for da in &self.da_commitments {
-            if let Ok(bytes) = bincode::serialize(da) {
-                leaves.push(hash_leaf(&bytes));
-            }
-        }
+    async fn put_proposal(&self, proposal: Proposal) -> anyhow::Result<()> {
+        let mut chain = self.get_chain_state().await?;
+        chain.proposals.insert(proposal.id, proposal);
+        self.put_chain_state(chain).await
+    }
 
-        for root in self.domain_roots.values() {
-            if let Ok(bytes) = bincode::serialize(root) {
-                leaves.push(hash_leaf(&bytes));
-            }
-        }
+    async fn get_fee_pools(&self) -> anyhow::Result<FeePools> {
+        Ok(self.get_chain_state().await?.fee_pools)
+    }
 
-        for proposal in self.proposals.values() {
-            if let Ok(bytes) = bincode::serialize(proposal) {
-                leaves.push(hash_leaf(&bytes));
-            }
-        }
+    async fn put_fee_pools(&self, pools: FeePools) -> anyhow::Result<()> {
+        let mut chain = self.get_chain_state().await?;
+        chain.fee_pools = pools;
+        self.put_chain_state(chain).await
+    }
 
-        if let Ok(bytes) = bincode::serialize(&self.fee_pools) {
-            leaves.push(hash_leaf(&bytes));
-        }
+    async fn get_vesting(&self, address: &Address) -> anyhow::Result<Vec<VestingSchedule>> {
+        Ok(self.get_chain_state().await?.vesting.remove(address).unwrap_or_default())
+    }
 
-        for pool in self.privacy_pools.values() {
-            if let Ok(bytes) = bincode::serialize(pool) {
-                leaves.push(hash_leaf(&bytes));
-            }
-        }
+    /// One field of the chain state, named by its `sections` marker.
+    async fn get_section<T: Section>(&self) -> anyhow::Result<T::Value> {
+        Ok(T::get(&self.get_chain_state().await?).clone())
+    }
 
-        if let Ok(bytes) = bincode::serialize(&self.governance_params) {
-            leaves.push(hash_leaf(&bytes));
-        }
+    async fn put_section<T: Section>(&self, value: T::Value) -> anyhow::Result<()> {
+        let mut chain = self.get_chain_state().await?;
+        *T::get_mut(&mut chain) = value;
+        self.put_chain_state(chain).await
+    }
 
-        if let Ok(bytes) = bincode::serialize(&self.total_supply) {
-            leaves.push(hash_leaf(&bytes));
-        }
+    async fn get_entry<T: MapSection>(&self, key: &T::Key) -> anyhow::Result<Option<T::Entry>> {
+        Ok(T::entries(T::get(&self.get_chain_state().await?))
+            .get(key)
+            .cloned())
+    }
 
-        if let Ok(bytes) = bincode::serialize(&self.last_reward_height) {
-            leaves.push(hash_leaf(&bytes));
-        }
+    async fn put_entry<T: MapSection>(&self, key: T::Key, entry: T::Entry) -> anyhow::Result<()> {
+        let mut chain = self.get_chain_state().await?;
+        T::entries_mut(T::get_mut(&mut chain)).insert(key, entry);
+        self.put_chain_state(chain).await
+    }
 
-        for unbond in &self.pending_unbonds {
-            if let Ok(bytes) = bincode::serialize(unbond) {
-                leaves.push(hash_leaf(&bytes));
-            }
-        }
+    async fn remove_entry<T: MapSection>(&self, key: &T::Key) -> anyhow::Result<()> {
+        let mut chain = self.get_chain_state().await?;
+        T::entries_mut(T::get_mut(&mut chain)).remove(key);
+        self.put_chain_state(chain).await
+    }
 
-        fold_hashes(leaves)
+    async fn get_param_overrides(&self) -> anyhow::Result<ParamOverrides> {
+        Ok(self.get_chain_state().await?.param_overrides)
+    }
+
+    async fn get_governance_params(&self) -> anyhow::Result<GovernanceParams> {
+        Ok(self.get_chain_state().await?.governance_params)
+    }
+
+    async fn get_protocol_version(&self) -> anyhow::Result<u32> {
+        Ok(self.get_chain_state().await?.protocol_version)
+    }
+
+    async fn get_pending_upgrade(&self) -> anyhow::Result<Option<ProtocolUpgrade>> {
+        Ok(self.get_chain_state().await?.pending_upgrade)
+    }
+
+    /// Brings `chain`, read earlier, up to date with accounts written
+    /// through `put_account` since.
+    async fn sync_accounts(&self, chain: &mut ChainState) -> anyhow::Result<()> {
+        chain.accounts = self.get_chain_state().await?.accounts;
+        Ok(())
+    }
+
+    /// Marks a point `rollback` returns to. The default copies the whole
+    /// state; `StateOverlay` keeps only what changed.
+    async fn checkpoint(&self) -> anyhow::Result<Checkpoint> {
+        Ok(Checkpoint::full(self.get_chain_state().await?))
+    }
+
+    async fn rollback(&self, checkpoint: Checkpoint) -> anyhow::Result<()> {
+        self.put_chain_state(checkpoint.into_full()?).await
     }
-}
 
-#[async_trait]
-pub trait StateStore: Send + Sync {
-    async fn get_account(&self, address: &Address) -> anyhow::Result<Option<Account>>;
-    async fn put_account(&self, account: Account) -> anyhow::Result<()>;
-    async fn get_validator(&self, id: &Uuid) -> anyhow::Result<Option<Validator>>;
-    async fn put_validator(&self, validator: Validator) -> anyhow::Result<()>;
-    async fn get_chain_state(&self) -> anyhow::Result<ChainState>;
-    async fn put_chain_state(&self, state: ChainState) -> anyhow::Result<()>;
     async fn commit(&self) -> anyhow::Result<Hash>;
+    async fn list_proposals(&self, query: &ProposalQuery) -> anyhow::Result<ProposalPage>;
+    async fn proposal_summary(&self) -> anyhow::Result<ProposalSummary>;
+    /// Records the current state as of `height`. A no-op unless the store
+    /// archives history.
+    async fn archive(&self, height: u64) -> anyhow::Result<()>;
+    /// `None` when `height` isn't archived.
+    async fn state_root_at(&self, height: u64) -> anyhow::Result<Option<Hash>>;
+    async fn get_chain_state_at(&self, height: u64) -> anyhow::Result<Option<ChainState>>;
+    async fn get_account_at(
+        &self,
+        address: &Address,
+        height: u64,
+    ) -> anyhow::Result<Option<Account>>;
 }
 
 #[derive(Clone, Default)]
 pub struct InMemoryStateStore {
     inner: Arc<Mutex<ChainState>>,
+    proposals: Arc<Mutex<ProposalIndex>>,
+    archive: Option<StateArchive>,
 }
 
 impl InMemoryStateStore {
     pub fn new() -> Self {
         Self {
             inner: Arc::new(Mutex::new(ChainState::default())),
+            proposals: Arc::new(Mutex::new(ProposalIndex::default())),
+            archive: None,
         }
     }
+
+    /// Keeps history in `archive` from the next `StateStore::archive` call on.
+    pub fn with_archive(mut self, archive: StateArchive) -> Self {
+        self.archive = Some(archive);
+        self
+    }
 }
 
 #[async_trait]
@@ -347,6 +1004,79 @@ impl StateStore for InMemoryStateStore {
         Ok(())
     }
 
+    async fn get_proposal(&self, id: &Uuid) -> anyhow::Result<Option<Proposal>> {
+        let guard = self.inner.lock().unwrap();
+        Ok(guard.proposals.get(id).cloned())
+    }
+
+    async fn put_proposal(&self, proposal: Proposal) -> anyhow::Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        self.proposals.lock().unwrap().upsert(&proposal);
+        guard.proposals.insert(proposal.id, proposal);
+        Ok(())
+    }
+
+    async fn get_fee_pools(&self) -> anyhow::Result<FeePools> {
+        Ok(self.inner.lock().unwrap().fee_pools.clone())
+    }
+
+    async fn put_fee_pools(&self, pools: FeePools) -> anyhow::Result<()> {
+        self.inner.lock().unwrap().fee_pools = pools;
+        Ok(())
+    }
+
+    async fn get_vesting(&self, address: &Address) -> anyhow::Result<Vec<VestingSchedule>> {
+        let guard = self.inner.lock().unwrap();
+        Ok(guard.vesting.get(address).cloned().unwrap_or_default())
+    }
+
+    async fn get_section<T: Section>(&self) -> anyhow::Result<T::Value> {
+        Ok(T::get(&self.inner.lock().unwrap()).clone())
+    }
+
+    async fn put_section<T: Section>(&self, value: T::Value) -> anyhow::Result<()> {
+        *T::get_mut(&mut self.inner.lock().unwrap()) = value;
+        Ok(())
+    }
+
+    async fn get_entry<T: MapSection>(&self, key: &T::Key) -> anyhow::Result<Option<T::Entry>> {
+        let guard = self.inner.lock().unwrap();
+        Ok(T::entries(T::get(&guard)).get(key).cloned())
+    }
+
+    async fn put_entry<T: MapSection>(&self, key: T::Key, entry: T::Entry) -> anyhow::Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        T::entries_mut(T::get_mut(&mut guard)).insert(key, entry);
+        Ok(())
+    }
+
+    async fn remove_entry<T: MapSection>(&self, key: &T::Key) -> anyhow::Result<()> {
+        let mut guard = self.inner.lock().unwrap();
+        T::entries_mut(T::get_mut(&mut guard)).remove(key);
+        Ok(())
+    }
+
+    async fn get_param_overrides(&self) -> anyhow::Result<ParamOverrides> {
+        Ok(self.inner.lock().unwrap().param_overrides.clone())
+    }
+
+    async fn get_governance_params(&self) -> anyhow::Result<GovernanceParams> {
+        Ok(self.inner.lock().unwrap().governance_params.clone())
+    }
+
+    async fn get_protocol_version(&self) -> anyhow::Result<u32> {
+        Ok(self.inner.lock().unwrap().protocol_version)
+    }
+
+    async fn get_pending_upgrade(&self) -> anyhow::Result<Option<ProtocolUpgrade>> {
+        Ok(self.inner.lock().unwrap().pending_upgrade.clone())
+    }
+
+    async fn sync_accounts(&self, chain: &mut ChainState) -> anyhow::Result<()> {
+        chain.accounts = self.inner.lock().unwrap().accounts.clone();
+        Ok(())
+    }
+
     async fn get_chain_state(&self) -> anyhow::Result<ChainState> {
         let guard = self.inner.lock().unwrap();
         Ok(guard.clone())
@@ -354,6 +1084,7 @@ impl StateStore for InMemoryStateStore {
 
     async fn put_chain_state(&self, state: ChainState) -> anyhow::Result<()> {
         let mut guard = self.inner.lock().unwrap();
+        self.proposals.lock().unwrap().rebuild(&state);
         *guard = state;
         Ok(())
     }
@@ -362,6 +1093,54 @@ impl StateStore for InMemoryStateStore {
         let guard = self.inner.lock().unwrap();
         Ok(guard.state_root())
     }
+
+    async fn list_proposals(&self, query: &ProposalQuery) -> anyhow::Result<ProposalPage> {
+        let (offset, limit) = query.bounds();
+        let (total, ids) = self.proposals.lock().unwrap().query(query);
+        let guard = self.inner.lock().unwrap();
+        let items = ids
+            .iter()
+            .filter_map(|id| guard.proposals.get(id).cloned())
+            .collect();
+        Ok(ProposalPage {
+            total,
+            offset,
+            limit,
+            items,
+        })
+    }
+
+    async fn proposal_summary(&self) -> anyhow::Result<ProposalSummary> {
+        Ok(self.proposals.lock().unwrap().summary())
+    }
+
+    async fn archive(&self, height: u64) -> anyhow::Result<()> {
+        if let Some(archive) = &self.archive {
+            let guard = self.inner.lock().unwrap();
+            archive.record(height, &guard);
+        }
+        Ok(())
+    }
+
+    async fn state_root_at(&self, height: u64) -> anyhow::Result<Option<Hash>> {
+        Ok(self.archive.as_ref().and_then(|a| a.state_root(height)))
+    }
+
+    async fn get_chain_state_at(&self, height: u64) -> anyhow::Result<Option<ChainState>> {
+        Ok(self.archive.as_ref().and_then(|a| a.state_at(height)))
+    }
+
+    async fn get_account_at(
+        &self,
+        address: &Address,
+        height: u64,
+    ) -> anyhow::Result<Option<Account>> {
+        Ok(self
+            .archive
+            .as_ref()
+            .and_then(|a| a.account_at(height, address))
+            .flatten())
+    }
 }
 
 #[derive(Default, Clone)]
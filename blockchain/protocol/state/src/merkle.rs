@@ -0,0 +1,162 @@
+//! Fixed-depth incremental Merkle tree over privacy pool commitments.
+//! Inserting a leaf only touches its path: the tree keeps the rightmost
+//! filled node at each level, and empty subtrees hash to precomputed zeros.
+//! The last `ROOT_HISTORY_SIZE` roots stay valid, so a withdrawal proven
+//! against a slightly older root still lands after later deposits.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Hash;
+
+pub const COMMITMENT_TREE_DEPTH: usize = 20;
+pub const ROOT_HISTORY_SIZE: usize = 32;
+
+fn hash_pair(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(left);
+    hasher.update(right);
+    *hasher.finalize().as_bytes()
+}
+
+/// Root of an empty subtree at each level; the last entry is the root of the
+/// empty tree.
+fn zero_hashes() -> Vec<Hash> {
+    let mut zeros = vec![[0u8; 32]];
+    for level in 0..COMMITMENT_TREE_DEPTH {
+        zeros.push(hash_pair(&zeros[level], &zeros[level]));
+    }
+    zeros
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CommitmentTree {
+    next_index: u64,
+    /// Rightmost node filled at each level; the left sibling on the path of
+    /// the next leaf whenever that path turns right.
+    filled_subtrees: Vec<Hash>,
+    /// Recent roots, newest last.
+    roots: VecDeque<Hash>,
+}
+
+impl Default for CommitmentTree {
+    fn default() -> Self {
+        let zeros = zero_hashes();
+        Self {
+            next_index: 0,
+            filled_subtrees: zeros[..COMMITMENT_TREE_DEPTH].to_vec(),
+            roots: VecDeque::from([zeros[COMMITMENT_TREE_DEPTH]]),
+        }
+    }
+}
+
+impl CommitmentTree {
+    pub fn from_leaves(leaves: &[Hash]) -> anyhow::Result<Self> {
+        let mut tree = Self::default();
+        for leaf in leaves {
+            tree.insert(*leaf)?;
+        }
+        Ok(tree)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.next_index
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next_index == 0
+    }
+
+    /// Appends `leaf` and returns its index.
+    pub fn insert(&mut self, leaf: Hash) -> anyhow::Result<u64> {
+        if self.next_index >= 1 << COMMITMENT_TREE_DEPTH {
+            anyhow::bail!("commitment tree is full");
+        }
+        let zeros = zero_hashes();
+        let index = self.next_index;
+        let mut node = leaf;
+        let mut position = index;
+        for (level, zero) in zeros.iter().enumerate().take(COMMITMENT_TREE_DEPTH) {
+            node = if position & 1 == 0 {
+                self.filled_subtrees[level] = node;
+                hash_pair(&node, zero)
+            } else {
+                hash_pair(&self.filled_subtrees[level], &node)
+            };
+            position >>= 1;
+        }
+        self.next_index += 1;
+        self.roots.push_back(node);
+        if self.roots.len() > ROOT_HISTORY_SIZE {
+            self.roots.pop_front();
+        }
+        Ok(index)
+    }
+
+    pub fn root(&self) -> Hash {
+        self.roots
+            .back()
+            .copied()
+            .unwrap_or(zero_hashes()[COMMITMENT_TREE_DEPTH])
+    }
+
+    /// Whether `root` is the current root or one of the recent ones.
+    pub fn is_known_root(&self, root: &Hash) -> bool {
+        self.roots.contains(root)
+    }
+}
+
+/// Siblings from a leaf up to the root, for proving membership.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MerklePath {
+    pub leaf_index: u64,
+    pub siblings: Vec<Hash>,
+    pub root: Hash,
+}
+
+impl MerklePath {
+    /// Root reached by hashing `leaf` up along the path.
+    pub fn compute_root(&self, leaf: &Hash) -> Hash {
+        let mut node = *leaf;
+        let mut position = self.leaf_index;
+        for sibling in &self.siblings {
+            node = if position & 1 == 0 {
+                hash_pair(&node, sibling)
+            } else {
+                hash_pair(sibling, &node)
+            };
+            position >>= 1;
+        }
+        node
+    }
+
+    pub fn verify(&self, leaf: &Hash) -> bool {
+        self.siblings.len() == COMMITMENT_TREE_DEPTH && self.compute_root(leaf) == self.root
+    }
+}
+
+/// Path of the leaf at `index` in the tree holding `leaves`, in insertion
+/// order.
+pub fn merkle_path(leaves: &[Hash], index: usize) -> Option<MerklePath> {
+    if index >= leaves.len() {
+        return None;
+    }
+    let zeros = zero_hashes();
+    let mut layer = leaves.to_vec();
+    let mut position = index;
+    let mut siblings = Vec::with_capacity(COMMITMENT_TREE_DEPTH);
+    for zero in zeros.iter().take(COMMITMENT_TREE_DEPTH) {
+        siblings.push(layer.get(position ^ 1).copied().unwrap_or(*zero));
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(zero)))
+            .collect();
+        position >>= 1;
+    }
+    Some(MerklePath {
+        leaf_index: index as u64,
+        siblings,
+        root: layer[0],
+    })
+}
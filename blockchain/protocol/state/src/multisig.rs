@@ -0,0 +1,79 @@
+//! Native multisig accounts. A multisig owns an ordinary `Account` at a
+//! derived address nobody holds a key for; its funds move only through calls
+//! approved by `threshold` of its signers.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Address;
+
+pub const MAX_MULTISIG_SIGNERS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MultisigCall {
+    Transfer {
+        to: Address,
+        amount: u128,
+    },
+    AssetTransfer {
+        asset_id: Uuid,
+        to: Address,
+        amount: u128,
+    },
+    /// Replaces the signer set. Pending calls are dropped, since their
+    /// approvals were given under the old set.
+    SetSigners {
+        signers: Vec<Address>,
+        threshold: u16,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultisigProposal {
+    pub call: MultisigCall,
+    pub proposer: Address,
+    pub approvals: BTreeSet<Address>,
+    pub submitted_height: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Multisig {
+    pub address: Address,
+    pub signers: Vec<Address>,
+    pub threshold: u16,
+    pub created_height: u64,
+    /// Calls awaiting approval, by id. Executed calls are removed.
+    pub pending: BTreeMap<u64, MultisigProposal>,
+    pub next_proposal_id: u64,
+}
+
+impl Multisig {
+    pub fn is_signer(&self, address: &Address) -> bool {
+        self.signers.contains(address)
+    }
+}
+
+/// Address of the multisig `creator` registers with `nonce`.
+pub fn multisig_address(creator: &Address, nonce: u64) -> Address {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(b"kova-multisig");
+    hasher.update(creator);
+    hasher.update(&nonce.to_le_bytes());
+    *hasher.finalize().as_bytes()
+}
+
+pub fn validate_signers(signers: &[Address], threshold: u16) -> anyhow::Result<()> {
+    if signers.is_empty() || signers.len() > MAX_MULTISIG_SIGNERS {
+        anyhow::bail!("a multisig needs 1 to {} signers", MAX_MULTISIG_SIGNERS);
+    }
+    if signers.iter().collect::<BTreeSet<_>>().len() != signers.len() {
+        anyhow::bail!("duplicate multisig signer");
+    }
+    if threshold == 0 || threshold as usize > signers.len() {
+        anyhow::bail!("threshold must be between 1 and the number of signers");
+    }
+    Ok(())
+}
@@ -0,0 +1,437 @@
+//! Copy-on-write working set over a `StateStore`. The base chain state is
+//! read once and shared; accounts, proposals and chain state sections are
+//! then read and written one entry at a time in memory, and the result is
+//! written back to the base in a single `put_chain_state` on `flush`.
+//! Whole-state reads still work but copy the state, as they would from the
+//! base.
+
+use async_trait::async_trait;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use crate::sections::{self, MapSection, Section};
+use crate::{
+    Account, Address, ChainState, FeePools, GovernanceParams, Hash, ParamOverrides, Proposal,
+    ProposalIndex, ProposalPage, ProposalQuery, ProposalSummary, ProtocolUpgrade, StateStore,
+    Validator, VestingSchedule,
+};
+
+/// Point a store can be rolled back to, from `StateStore::checkpoint`.
+pub struct Checkpoint(CheckpointInner);
+
+enum CheckpointInner {
+    Full(Box<ChainState>),
+    Overlay(Box<Layer>),
+}
+
+impl Checkpoint {
+    pub(crate) fn full(chain: ChainState) -> Self {
+        Self(CheckpointInner::Full(Box::new(chain)))
+    }
+
+    /// The whole state the checkpoint holds, when it is a copy of it.
+    pub(crate) fn into_full(self) -> anyhow::Result<ChainState> {
+        match self.0 {
+            CheckpointInner::Full(chain) => Ok(*chain),
+            CheckpointInner::Overlay(_) => anyhow::bail!("checkpoint belongs to a state overlay"),
+        }
+    }
+}
+
+/// Writes to one section of the state, kept by the section's marker type.
+trait SectionWrites: Send + Sync {
+    fn apply(self: Box<Self>, chain: &mut ChainState);
+    /// Applies the writes to a copy of the section, passed as `&mut T::Value`.
+    fn merge_into(&self, value: &mut dyn Any);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn box_clone(&self) -> Box<dyn SectionWrites>;
+}
+
+impl Clone for Box<dyn SectionWrites> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// A section replaced whole.
+struct Replaced<T: Section>(T::Value);
+
+impl<T: Section> SectionWrites for Replaced<T> {
+    fn apply(self: Box<Self>, chain: &mut ChainState) {
+        *T::get_mut(chain) = self.0;
+    }
+
+    fn merge_into(&self, value: &mut dyn Any) {
+        if let Some(value) = value.downcast_mut::<T::Value>() {
+            *value = self.0.clone();
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn box_clone(&self) -> Box<dyn SectionWrites> {
+        Box::new(Self(self.0.clone()))
+    }
+}
+
+/// Entries of a map section written since it was last replaced; `None`
+/// removes the entry.
+struct Entries<T: MapSection>(HashMap<T::Key, Option<T::Entry>>);
+
+impl<T: MapSection> SectionWrites for Entries<T> {
+    fn apply(self: Box<Self>, chain: &mut ChainState) {
+        let entries = T::entries_mut(T::get_mut(chain));
+        for (key, entry) in self.0 {
+            match entry {
+                Some(entry) => entries.insert(key, entry),
+                None => entries.remove(&key),
+            };
+        }
+    }
+
+    fn merge_into(&self, value: &mut dyn Any) {
+        let Some(value) = value.downcast_mut::<T::Value>() else {
+            return;
+        };
+        let entries = T::entries_mut(value);
+        for (key, entry) in &self.0 {
+            match entry {
+                Some(entry) => entries.insert(key.clone(), entry.clone()),
+                None => entries.remove(key),
+            };
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn box_clone(&self) -> Box<dyn SectionWrites> {
+        Box::new(Self(self.0.clone()))
+    }
+}
+
+/// Entries written since the base state was last replaced. Cloning it is
+/// cheap next to cloning the state, which is what makes checkpoints cheap.
+#[derive(Clone, Default)]
+struct Layer {
+    chain: Arc<ChainState>,
+    accounts: HashMap<Address, Account>,
+    proposals: HashMap<Uuid, Proposal>,
+    /// Sections replaced whole, by marker type.
+    replaced: HashMap<TypeId, Box<dyn SectionWrites>>,
+    /// Entries written to map sections since, by marker type.
+    entries: HashMap<TypeId, Box<dyn SectionWrites>>,
+}
+
+impl Layer {
+    fn new(chain: ChainState) -> Self {
+        Self {
+            chain: Arc::new(chain),
+            ..Self::default()
+        }
+    }
+
+    fn replaced<T: Section>(&self) -> Option<&T::Value> {
+        let writes = self.replaced.get(&TypeId::of::<T>())?;
+        writes.as_any().downcast_ref::<Replaced<T>>().map(|r| &r.0)
+    }
+
+    fn entries<T: MapSection>(&self) -> Option<&Entries<T>> {
+        let writes = self.entries.get(&TypeId::of::<T>())?;
+        writes.as_any().downcast_ref::<Entries<T>>()
+    }
+
+    fn entries_mut<T: MapSection>(&mut self) -> &mut HashMap<T::Key, Option<T::Entry>> {
+        let writes = self
+            .entries
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Entries::<T>(HashMap::new())));
+        &mut writes
+            .as_any_mut()
+            .downcast_mut::<Entries<T>>()
+            .expect("entries are kept by their section's type")
+            .0
+    }
+
+    /// The section as written so far. Map sections come back with their
+    /// entry writes merged in.
+    fn section<T: Section>(&self) -> T::Value {
+        let mut value = self
+            .replaced::<T>()
+            .unwrap_or_else(|| T::get(&self.chain))
+            .clone();
+        if let Some(writes) = self.entries.get(&TypeId::of::<T>()) {
+            writes.merge_into(&mut value);
+        }
+        value
+    }
+
+    fn put_section<T: Section>(&mut self, value: T::Value) {
+        let id = TypeId::of::<T>();
+        self.entries.remove(&id);
+        self.replaced.insert(id, Box::new(Replaced::<T>(value)));
+    }
+
+    fn entry<T: MapSection>(&self, key: &T::Key) -> Option<T::Entry> {
+        if let Some(written) = self.entries::<T>().and_then(|e| e.0.get(key)) {
+            return written.clone();
+        }
+        let section = self.replaced::<T>().unwrap_or_else(|| T::get(&self.chain));
+        T::entries(section).get(key).cloned()
+    }
+
+    /// Index over the base proposals with the ones written since.
+    fn proposal_index(&self) -> ProposalIndex {
+        let mut index = ProposalIndex::default();
+        for proposal in self.chain.proposals.values().chain(self.proposals.values()) {
+            index.upsert(proposal);
+        }
+        index
+    }
+
+    fn proposal(&self, id: &Uuid) -> Option<&Proposal> {
+        self.proposals
+            .get(id)
+            .or_else(|| self.chain.proposals.get(id))
+    }
+
+    fn into_chain_state(self) -> ChainState {
+        let mut chain = Arc::try_unwrap(self.chain).unwrap_or_else(|shared| (*shared).clone());
+        chain.accounts.extend(self.accounts);
+        chain.proposals.extend(self.proposals);
+        // Entry writes are newer than any replacement of their section.
+        for writes in self
+            .replaced
+            .into_values()
+            .chain(self.entries.into_values())
+        {
+            writes.apply(&mut chain);
+        }
+        chain
+    }
+}
+
+pub struct StateOverlay<S> {
+    base: S,
+    layer: Mutex<Layer>,
+}
+
+impl<S: StateStore> StateOverlay<S> {
+    /// Reads the base chain state; everything after is served from memory
+    /// until `flush`.
+    pub async fn new(base: S) -> anyhow::Result<Self> {
+        let chain = base.get_chain_state().await?;
+        Ok(Self {
+            base,
+            layer: Mutex::new(Layer::new(chain)),
+        })
+    }
+
+    /// Writes the working set to the base store.
+    pub async fn flush(self) -> anyhow::Result<()> {
+        let chain = self.layer.into_inner().unwrap().into_chain_state();
+        self.base.put_chain_state(chain).await
+    }
+}
+
+#[async_trait]
+impl<S: StateStore> StateStore for StateOverlay<S> {
+    async fn get_account(&self, address: &Address) -> anyhow::Result<Option<Account>> {
+        let layer = self.layer.lock().unwrap();
+        Ok(layer
+            .accounts
+            .get(address)
+            .or_else(|| layer.chain.accounts.get(address))
+            .cloned())
+    }
+
+    async fn put_account(&self, account: Account) -> anyhow::Result<()> {
+        self.layer
+            .lock()
+            .unwrap()
+            .accounts
+            .insert(account.address, account);
+        Ok(())
+    }
+
+    async fn get_validator(&self, id: &Uuid) -> anyhow::Result<Option<Validator>> {
+        Ok(self.layer.lock().unwrap().entry::<sections::Validators>(id))
+    }
+
+    async fn put_validator(&self, validator: Validator) -> anyhow::Result<()> {
+        self.layer
+            .lock()
+            .unwrap()
+            .entries_mut::<sections::Validators>()
+            .insert(validator.id, Some(validator));
+        Ok(())
+    }
+
+    async fn get_proposal(&self, id: &Uuid) -> anyhow::Result<Option<Proposal>> {
+        Ok(self.layer.lock().unwrap().proposal(id).cloned())
+    }
+
+    async fn put_proposal(&self, proposal: Proposal) -> anyhow::Result<()> {
+        self.layer
+            .lock()
+            .unwrap()
+            .proposals
+            .insert(proposal.id, proposal);
+        Ok(())
+    }
+
+    async fn get_fee_pools(&self) -> anyhow::Result<FeePools> {
+        self.get_section::<sections::FeePools>().await
+    }
+
+    async fn put_fee_pools(&self, pools: FeePools) -> anyhow::Result<()> {
+        self.put_section::<sections::FeePools>(pools).await
+    }
+
+    async fn get_vesting(&self, address: &Address) -> anyhow::Result<Vec<VestingSchedule>> {
+        Ok(self
+            .get_entry::<sections::Vesting>(address)
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn get_section<T: Section>(&self) -> anyhow::Result<T::Value> {
+        Ok(self.layer.lock().unwrap().section::<T>())
+    }
+
+    async fn put_section<T: Section>(&self, value: T::Value) -> anyhow::Result<()> {
+        self.layer.lock().unwrap().put_section::<T>(value);
+        Ok(())
+    }
+
+    async fn get_entry<T: MapSection>(&self, key: &T::Key) -> anyhow::Result<Option<T::Entry>> {
+        Ok(self.layer.lock().unwrap().entry::<T>(key))
+    }
+
+    async fn put_entry<T: MapSection>(&self, key: T::Key, entry: T::Entry) -> anyhow::Result<()> {
+        self.layer
+            .lock()
+            .unwrap()
+            .entries_mut::<T>()
+            .insert(key, Some(entry));
+        Ok(())
+    }
+
+    async fn remove_entry<T: MapSection>(&self, key: &T::Key) -> anyhow::Result<()> {
+        self.layer
+            .lock()
+            .unwrap()
+            .entries_mut::<T>()
+            .insert(key.clone(), None);
+        Ok(())
+    }
+
+    async fn get_param_overrides(&self) -> anyhow::Result<ParamOverrides> {
+        self.get_section::<sections::ParamOverrides>().await
+    }
+
+    async fn get_governance_params(&self) -> anyhow::Result<GovernanceParams> {
+        self.get_section::<sections::GovernanceParams>().await
+    }
+
+    async fn get_protocol_version(&self) -> anyhow::Result<u32> {
+        self.get_section::<sections::ProtocolVersion>().await
+    }
+
+    async fn get_pending_upgrade(&self) -> anyhow::Result<Option<ProtocolUpgrade>> {
+        self.get_section::<sections::PendingUpgrade>().await
+    }
+
+    async fn get_chain_state(&self) -> anyhow::Result<ChainState> {
+        Ok(self.layer.lock().unwrap().clone().into_chain_state())
+    }
+
+    async fn put_chain_state(&self, state: ChainState) -> anyhow::Result<()> {
+        *self.layer.lock().unwrap() = Layer::new(state);
+        Ok(())
+    }
+
+    async fn sync_accounts(&self, chain: &mut ChainState) -> anyhow::Result<()> {
+        let layer = self.layer.lock().unwrap();
+        chain.accounts.extend(
+            layer
+                .accounts
+                .iter()
+                .map(|(address, account)| (*address, account.clone())),
+        );
+        Ok(())
+    }
+
+    async fn checkpoint(&self) -> anyhow::Result<Checkpoint> {
+        Ok(Checkpoint(CheckpointInner::Overlay(Box::new(
+            self.layer.lock().unwrap().clone(),
+        ))))
+    }
+
+    async fn rollback(&self, checkpoint: Checkpoint) -> anyhow::Result<()> {
+        let layer = match checkpoint.0 {
+            CheckpointInner::Overlay(layer) => *layer,
+            CheckpointInner::Full(chain) => Layer::new(*chain),
+        };
+        *self.layer.lock().unwrap() = layer;
+        Ok(())
+    }
+
+    async fn commit(&self) -> anyhow::Result<Hash> {
+        Ok(self.get_chain_state().await?.state_root())
+    }
+
+    async fn list_proposals(&self, query: &ProposalQuery) -> anyhow::Result<ProposalPage> {
+        let (offset, limit) = query.bounds();
+        let layer = self.layer.lock().unwrap();
+        let (total, ids) = layer.proposal_index().query(query);
+        let items = ids
+            .iter()
+            .filter_map(|id| layer.proposal(id).cloned())
+            .collect();
+        Ok(ProposalPage {
+            total,
+            offset,
+            limit,
+            items,
+        })
+    }
+
+    async fn proposal_summary(&self) -> anyhow::Result<ProposalSummary> {
+        Ok(self.layer.lock().unwrap().proposal_index().summary())
+    }
+
+    async fn archive(&self, height: u64) -> anyhow::Result<()> {
+        self.base.archive(height).await
+    }
+
+    async fn state_root_at(&self, height: u64) -> anyhow::Result<Option<Hash>> {
+        self.base.state_root_at(height).await
+    }
+
+    async fn get_chain_state_at(&self, height: u64) -> anyhow::Result<Option<ChainState>> {
+        self.base.get_chain_state_at(height).await
+    }
+
+    async fn get_account_at(
+        &self,
+        address: &Address,
+        height: u64,
+    ) -> anyhow::Result<Option<Account>> {
+        self.base.get_account_at(address, height).await
+    }
+}
@@ -0,0 +1,48 @@
+//! Chain parameters fixed at genesis that governance may later replace.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeSplit {
+    pub l1_gas_burn_pct: u8,
+    pub l1_gas_validators_pct: u8,
+    pub da_validators_pct: u8,
+    pub da_nodes_pct: u8,
+    pub da_treasury_pct: u8,
+    pub l2_sequencer_pct: u8,
+    pub l2_da_costs_pct: u8,
+    pub l2_l1_rent_pct: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardParams {
+    pub base_inflation_bps: u16,
+    pub max_inflation_bps: u16,
+    pub target_stake_bps: u16,
+    pub treasury_pct: u8,
+    pub proposer_bonus_pct: u8,
+}
+
+impl Default for RewardParams {
+    fn default() -> Self {
+        Self {
+            base_inflation_bps: 500, // 5% when at target or above
+            max_inflation_bps: 1500, // 15% when below target stake
+            target_stake_bps: 6_700, // 67% staked target
+            treasury_pct: 10,
+            proposer_bonus_pct: 5,
+        }
+    }
+}
+
+/// Values set by executed governance proposals. `None` keeps the genesis
+/// value from the execution context.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParamOverrides {
+    pub fee_split: Option<FeeSplit>,
+    pub reward_params: Option<RewardParams>,
+    pub slash_penalty_bps: Option<u16>,
+    pub unbonding_delay_blocks: Option<u64>,
+    pub exit_churn_bps: Option<u16>,
+    pub max_commission_change_per_epoch: Option<u8>,
+}
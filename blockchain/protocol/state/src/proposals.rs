@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{ChainState, Proposal, ProposalStatus};
+
+pub const DEFAULT_PROPOSAL_PAGE_SIZE: usize = 50;
+pub const MAX_PROPOSAL_PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProposalQuery {
+    pub status: Option<ProposalStatus>,
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+impl ProposalQuery {
+    /// Effective `(offset, limit)` with the page size clamped.
+    pub fn bounds(&self) -> (usize, usize) {
+        let limit = self
+            .limit
+            .unwrap_or(DEFAULT_PROPOSAL_PAGE_SIZE)
+            .min(MAX_PROPOSAL_PAGE_SIZE);
+        (self.offset.unwrap_or(0), limit)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposalPage {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub items: Vec<Proposal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProposalSummary {
+    pub total: usize,
+    pub by_status: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone)]
+struct IndexEntry {
+    end: u64,
+    status: ProposalStatus,
+    kind: String,
+}
+
+/// Secondary index over governance proposals ordered by voting end time.
+/// Only metadata is kept here; proposal bodies are fetched by id for the
+/// requested page.
+#[derive(Debug, Clone, Default)]
+pub struct ProposalIndex {
+    by_end: BTreeSet<(u64, Uuid)>,
+    entries: HashMap<Uuid, IndexEntry>,
+}
+
+impl ProposalIndex {
+    pub fn rebuild(&mut self, state: &ChainState) {
+        self.by_end.clear();
+        self.entries.clear();
+        for proposal in state.proposals.values() {
+            self.upsert(proposal);
+        }
+    }
+
+    pub fn upsert(&mut self, proposal: &Proposal) {
+        if let Some(prev) = self.entries.get(&proposal.id) {
+            self.by_end.remove(&(prev.end, proposal.id));
+        }
+        self.by_end.insert((proposal.end, proposal.id));
+        self.entries.insert(
+            proposal.id,
+            IndexEntry {
+                end: proposal.end,
+                status: proposal.status.clone(),
+                kind: proposal.kind.clone(),
+            },
+        );
+    }
+
+    /// Returns the ids matching `query` for the requested page, along with
+    /// the total number of matches.
+    pub fn query(&self, query: &ProposalQuery) -> (usize, Vec<Uuid>) {
+        let (offset, limit) = query.bounds();
+        let matches = |id: &Uuid| {
+            let Some(entry) = self.entries.get(id) else {
+                return false;
+            };
+            query.status.as_ref().is_none_or(|s| &entry.status == s)
+                && query.kind.as_ref().is_none_or(|k| &entry.kind == k)
+        };
+        let ids: Box<dyn Iterator<Item = &(u64, Uuid)>> = match query.order {
+            SortOrder::Asc => Box::new(self.by_end.iter()),
+            SortOrder::Desc => Box::new(self.by_end.iter().rev()),
+        };
+        let mut total = 0;
+        let mut page = Vec::new();
+        for (_, id) in ids.filter(|(_, id)| matches(id)) {
+            if total >= offset && page.len() < limit {
+                page.push(*id);
+            }
+            total += 1;
+        }
+        (total, page)
+    }
+
+    pub fn summary(&self) -> ProposalSummary {
+        let mut by_status = BTreeMap::new();
+        for entry in self.entries.values() {
+            *by_status.entry(format!("{:?}", entry.status)).or_insert(0) += 1;
+        }
+        ProposalSummary {
+            total: self.entries.len(),
+            by_status,
+        }
+    }
+}
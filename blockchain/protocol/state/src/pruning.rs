@@ -0,0 +1,127 @@
+//! Retention limits for state that otherwise grows with chain history.
+//! Everything pruned is appended, as its state leaf hash, to
+//! `ChainState::pruned`, so a proof that an item was once part of the state
+//! can still be checked against the committed root. Nullifiers and note
+//! commitments are never pruned: spends are checked against the former and
+//! witnesses are built from the latter.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{hash_leaf, ChainState, Hash, ProposalStatus, Unbonding};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionParams {
+    /// Epochs a settled proposal is kept after voting ends, or after its
+    /// eta once queued.
+    pub proposal_retention_epochs: u64,
+    /// DA commitments kept; the oldest are pruned first.
+    pub max_da_commitments: usize,
+}
+
+impl Default for RetentionParams {
+    fn default() -> Self {
+        Self {
+            proposal_retention_epochs: 30,
+            max_da_commitments: 10_000,
+        }
+    }
+}
+
+/// What a pruning pass removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    pub proposals: usize,
+    pub da_commitments: usize,
+    pub delegations: usize,
+    pub unbonds: usize,
+}
+
+impl PruneReport {
+    pub fn total(&self) -> usize {
+        self.proposals + self.da_commitments + self.delegations + self.unbonds
+    }
+}
+
+/// Leaf hash a pruned item is accumulated under, the same one it had in
+/// the state root.
+fn pruned_leaf<T: Serialize>(item: &T) -> anyhow::Result<Hash> {
+    Ok(hash_leaf(&bincode::serialize(item)?))
+}
+
+impl ChainState {
+    /// Applies `self.retention` as of `now_ms`, with `epoch_ms` the length of
+    /// an epoch in milliseconds. Settled proposals past their retention and
+    /// the oldest DA commitments over the cap are dropped, emptied
+    /// delegation positions are removed and unbonds releasing together are
+    /// merged. Items stop being pruned once the accumulator is full.
+    pub fn prune(&mut self, now_ms: u64, epoch_ms: u64) -> anyhow::Result<PruneReport> {
+        let mut report = PruneReport::default();
+        let keep_ms = self.retention.proposal_retention_epochs.saturating_mul(epoch_ms);
+        let mut expired: Vec<_> = self
+            .proposals
+            .values()
+            .filter(|p| {
+                matches!(
+                    p.status,
+                    ProposalStatus::Executed
+                        | ProposalStatus::Defeated
+                        | ProposalStatus::Cancelled
+                        | ProposalStatus::Expired
+                ) && p.deposit == 0
+                    && now_ms >= p.eta.unwrap_or(p.end).saturating_add(keep_ms)
+            })
+            .map(|p| (p.end, p.id))
+            .collect();
+        expired.sort();
+        for (_, id) in expired {
+            let leaf = pruned_leaf(&self.proposals[&id])?;
+            if self.pruned.insert(leaf).is_err() {
+                return Ok(report);
+            }
+            self.proposals.remove(&id);
+            report.proposals += 1;
+        }
+
+        let excess = self
+            .da_commitments
+            .len()
+            .saturating_sub(self.retention.max_da_commitments);
+        for commitment in &self.da_commitments[..excess] {
+            if self.pruned.insert(pruned_leaf(commitment)?).is_err() {
+                break;
+            }
+            report.da_commitments += 1;
+        }
+        self.da_commitments.drain(..report.da_commitments);
+
+        let mut empty: Vec<_> = self
+            .delegations
+            .iter()
+            .filter(|(_, p)| p.stake == 0 && p.pending_rewards == 0)
+            .map(|(key, _)| *key)
+            .collect();
+        empty.sort();
+        for key in empty {
+            let leaf = pruned_leaf(&self.delegations[&key])?;
+            if self.pruned.insert(leaf).is_err() {
+                return Ok(report);
+            }
+            self.delegations.remove(&key);
+            report.delegations += 1;
+        }
+
+        // Merging changes no balances, so nothing is accumulated for it.
+        let before = self.pending_unbonds.len();
+        let mut merged: BTreeMap<_, Unbonding> = BTreeMap::new();
+        for unbond in self.pending_unbonds.drain(..).filter(|u| u.amount > 0) {
+            merged
+                .entry((unbond.release_height, unbond.owner, unbond.validator_id))
+                .and_modify(|u| u.amount = u.amount.saturating_add(unbond.amount))
+                .or_insert(unbond);
+        }
+        self.pending_unbonds = merged.into_values().collect();
+        report.unbonds = before - self.pending_unbonds.len();
+        Ok(report)
+    }
+}
@@ -0,0 +1,48 @@
+//! Calls scheduled by an account to run at a later height. `apply_block`
+//! runs them after the block's txs, as txs from their owner.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Address;
+
+pub const MAX_SCHEDULED_PER_ACCOUNT: usize = 16;
+/// Due calls beyond this many roll over to the next block.
+pub const MAX_SCHEDULED_PER_BLOCK: usize = 64;
+pub const MAX_SCHEDULE_DELAY_BLOCKS: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledCall {
+    pub owner: Address,
+    /// The owner's key, for payloads that depend on it such as `Stake`.
+    pub public_key: Vec<u8>,
+    pub execute_at_height: u64,
+    /// The bincode-encoded payload.
+    pub payload: Vec<u8>,
+    pub scheduled_height: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Schedule {
+    pub next_id: u64,
+    pub calls: BTreeMap<u64, ScheduledCall>,
+}
+
+impl Schedule {
+    pub fn pending_for(&self, owner: &Address) -> usize {
+        self.calls.values().filter(|c| c.owner == *owner).count()
+    }
+
+    /// Ids of calls due at `height`, earliest target first.
+    pub fn due(&self, height: u64, limit: usize) -> Vec<u64> {
+        let mut due: Vec<(u64, u64)> = self
+            .calls
+            .iter()
+            .filter(|(_, c)| c.execute_at_height <= height)
+            .map(|(id, c)| (c.execute_at_height, *id))
+            .collect();
+        due.sort_unstable();
+        due.into_iter().take(limit).map(|(_, id)| id).collect()
+    }
+}
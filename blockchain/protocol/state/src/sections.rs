@@ -0,0 +1,109 @@
+//! Fields of `ChainState` a `StateStore` reads and writes on their own, so a
+//! tx touching a few entries does not copy the whole state. Accounts and
+//! proposals have their own `StateStore` methods and no section here.
+
+use std::collections::{BTreeSet, HashMap};
+
+use uuid::Uuid;
+
+use crate::{Address, ChainState, Hash};
+
+/// A field of `ChainState`, named by a marker type.
+pub trait Section: 'static {
+    type Value: Clone + Send + Sync + 'static;
+
+    fn get(chain: &ChainState) -> &Self::Value;
+    fn get_mut(chain: &mut ChainState) -> &mut Self::Value;
+}
+
+/// A section that is a map, so its entries can also be read and written one
+/// at a time.
+pub trait MapSection: Section {
+    type Key: Eq + std::hash::Hash + Clone + Send + Sync + 'static;
+    type Entry: Clone + Send + Sync + 'static;
+
+    fn entries(value: &Self::Value) -> &HashMap<Self::Key, Self::Entry>;
+    fn entries_mut(value: &mut Self::Value) -> &mut HashMap<Self::Key, Self::Entry>;
+}
+
+macro_rules! sections {
+    ($($name:ident => $field:ident: $value:ty;)*) => {$(
+        #[doc = concat!("`ChainState::", stringify!($field), "`.")]
+        pub struct $name;
+
+        impl Section for $name {
+            type Value = $value;
+
+            fn get(chain: &ChainState) -> &$value {
+                &chain.$field
+            }
+
+            fn get_mut(chain: &mut ChainState) -> &mut $value {
+                &mut chain.$field
+            }
+        }
+    )*};
+}
+
+macro_rules! map_sections {
+    ($($name:ident => $field:ident: $key:ty => $entry:ty;)*) => {
+        sections! { $($name => $field: HashMap<$key, $entry>;)* }
+
+        $(impl MapSection for $name {
+            type Key = $key;
+            type Entry = $entry;
+
+            fn entries(value: &Self::Value) -> &HashMap<$key, $entry> {
+                value
+            }
+
+            fn entries_mut(value: &mut Self::Value) -> &mut HashMap<$key, $entry> {
+                value
+            }
+        })*
+    };
+}
+
+map_sections! {
+    Validators => validators: Uuid => crate::Validator;
+    Delegations => delegations: (Address, Uuid) => crate::DelegationPosition;
+    ValidatorRewards => validator_rewards: Uuid => crate::ValidatorRewards;
+    Domains => domains: Uuid => crate::DomainEntry;
+    DomainRoots => domain_roots: Uuid => crate::DomainRoot;
+    PrivacyPools => privacy_pools: String => crate::PrivacyPool;
+    Assets => assets: Uuid => crate::Asset;
+    Multisigs => multisigs: Address => crate::Multisig;
+    Vesting => vesting: Address => Vec<crate::VestingSchedule>;
+    Liveness => liveness: Uuid => crate::ValidatorLiveness;
+    OptimisticClaims => optimistic_claims: Uuid => crate::OptimisticClaim;
+    FinalizedDomainRoots => finalized_domain_roots: Uuid => Hash;
+    SequencerBonds => sequencer_bonds: Uuid => Vec<crate::SequencerBond>;
+    SequencerRounds => sequencer_rounds: Uuid => crate::SequencerRound;
+    BatchRecords => batch_records: Uuid => Vec<crate::BatchRecord>;
+    PreconfDisputes => preconf_disputes: Hash => crate::PreconfDispute;
+    ForcedInclusions => forced_inclusions: Uuid => Vec<crate::ForcedInclusion>;
+    LightClients => light_clients: String => crate::LightClient;
+    BridgeEscrows => bridge_escrows: Uuid => crate::BridgeEscrow;
+    SequencerFees => sequencer_fees: Uuid => u128;
+}
+
+sections! {
+    DaCommitments => da_commitments: Vec<crate::DACommitment>;
+    FeePools => fee_pools: crate::FeePools;
+    GovernanceParams => governance_params: crate::GovernanceParams;
+    TotalSupply => total_supply: u128;
+    LastRewardHeight => last_reward_height: u64;
+    PendingUnbonds => pending_unbonds: Vec<crate::Unbonding>;
+    ProcessedEvidence => processed_evidence: BTreeSet<Hash>;
+    ExitQueue => exit_queue: Vec<crate::PendingExit>;
+    StakingParams => staking_params: crate::StakingParams;
+    ParamOverrides => param_overrides: crate::ParamOverrides;
+    TreasuryPeriod => treasury_period: crate::TreasuryPeriod;
+    Schedule => schedule: crate::Schedule;
+    LivenessView => liveness_view: Option<u64>;
+    VerificationKeys => verification_keys: crate::VerificationKeyRegistry;
+    Retention => retention: crate::RetentionParams;
+    Pruned => pruned: crate::CommitmentTree;
+    ProtocolVersion => protocol_version: u32;
+    PendingUpgrade => pending_upgrade: Option<crate::ProtocolUpgrade>;
+}
@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    Account, Address, Asset, BatchRecord, BridgeEscrow, ChainState, CommitmentTree, DACommitment,
+    DelegationPosition, DomainEntry, DomainRoot, FeePools, ForcedInclusion, GovernanceParams, Hash,
+    LightClient, Multisig, OptimisticClaim, ParamOverrides, PendingExit, PreconfDispute,
+    PrivacyPool, Proposal, ProtocolUpgrade, RetentionParams, Schedule, SequencerBond, SequencerRound, StakingParams, TreasuryPeriod,
+    Unbonding, Validator, ValidatorLiveness, ValidatorRewards, VerificationKeyRegistry,
+    VestingSchedule,
+};
+
+pub const DEFAULT_SNAPSHOT_CHUNK_SIZE: usize = 256 * 1024;
+pub const DEFAULT_SNAPSHOT_RETENTION: usize = 4;
+
+/// Order-stable view of `ChainState`. Maps are flattened into key-sorted
+/// vectors so two nodes holding the same state produce identical chunks.
+#[derive(Serialize, Deserialize)]
+struct CanonicalState {
+    accounts: Vec<(Address, Account)>,
+    validators: Vec<(Uuid, Validator)>,
+    delegations: Vec<((Address, Uuid), DelegationPosition)>,
+    validator_rewards: Vec<(Uuid, ValidatorRewards)>,
+    domains: Vec<(Uuid, DomainEntry)>,
+    da_commitments: Vec<DACommitment>,
+    domain_roots: Vec<(Uuid, DomainRoot)>,
+    proposals: Vec<(Uuid, Proposal)>,
+    fee_pools: FeePools,
+    privacy_pools: Vec<(String, PrivacyPool)>,
+    governance_params: GovernanceParams,
+    total_supply: u128,
+    last_reward_height: u64,
+    pending_unbonds: Vec<Unbonding>,
+    processed_evidence: BTreeSet<Hash>,
+    exit_queue: Vec<PendingExit>,
+    staking_params: StakingParams,
+    param_overrides: ParamOverrides,
+    treasury_period: TreasuryPeriod,
+    assets: Vec<(Uuid, Asset)>,
+    multisigs: Vec<(Address, Multisig)>,
+    schedule: Schedule,
+    vesting: Vec<(Address, Vec<VestingSchedule>)>,
+    liveness: Vec<(Uuid, ValidatorLiveness)>,
+    liveness_view: Option<u64>,
+    verification_keys: VerificationKeyRegistry,
+    optimistic_claims: Vec<(Uuid, OptimisticClaim)>,
+    finalized_domain_roots: Vec<(Uuid, Hash)>,
+    sequencer_bonds: Vec<(Uuid, Vec<SequencerBond>)>,
+    sequencer_rounds: Vec<(Uuid, SequencerRound)>,
+    batch_records: Vec<(Uuid, Vec<BatchRecord>)>,
+    preconf_disputes: Vec<(Hash, PreconfDispute)>,
+    forced_inclusions: Vec<(Uuid, Vec<ForcedInclusion>)>,
+    light_clients: Vec<(String, LightClient)>,
+    bridge_escrows: Vec<(Uuid, BridgeEscrow)>,
+    sequencer_fees: Vec<(Uuid, u128)>,
+    retention: RetentionParams,
+    pruned: CommitmentTree,
+    protocol_version: u32,
+    pending_upgrade: Option<ProtocolUpgrade>,
+}
+
+fn sorted<K: Ord + Clone, V: Clone>(map: &std::collections::HashMap<K, V>) -> Vec<(K, V)> {
+    let mut out: Vec<(K, V)> = map.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    out.sort_by(|a, b| a.0.cmp(&b.0));
+    out
+}
+
+impl From<&ChainState> for CanonicalState {
+    fn from(state: &ChainState) -> Self {
+        Self {
+            accounts: sorted(&state.accounts),
+            validators: sorted(&state.validators),
+            delegations: sorted(&state.delegations),
+            validator_rewards: sorted(&state.validator_rewards),
+            domains: sorted(&state.domains),
+            da_commitments: state.da_commitments.clone(),
+            domain_roots: sorted(&state.domain_roots),
+            proposals: sorted(&state.proposals),
+            fee_pools: state.fee_pools.clone(),
+            privacy_pools: sorted(&state.privacy_pools),
+            governance_params: state.governance_params.clone(),
+            total_supply: state.total_supply,
+            last_reward_height: state.last_reward_height,
+            pending_unbonds: state.pending_unbonds.clone(),
+            processed_evidence: state.processed_evidence.clone(),
+            exit_queue: state.exit_queue.clone(),
+            staking_params: state.staking_params.clone(),
+            param_overrides: state.param_overrides.clone(),
+            treasury_period: state.treasury_period.clone(),
+            assets: sorted(&state.assets),
+            multisigs: sorted(&state.multisigs),
+            schedule: state.schedule.clone(),
+            vesting: sorted(&state.vesting),
+            liveness: sorted(&state.liveness),
+            liveness_view: state.liveness_view,
+            verification_keys: state.verification_keys.clone(),
+            optimistic_claims: sorted(&state.optimistic_claims),
+            finalized_domain_roots: sorted(&state.finalized_domain_roots),
+            sequencer_bonds: sorted(&state.sequencer_bonds),
+            sequencer_rounds: sorted(&state.sequencer_rounds),
+            batch_records: sorted(&state.batch_records),
+            preconf_disputes: sorted(&state.preconf_disputes),
+            forced_inclusions: sorted(&state.forced_inclusions),
+            light_clients: sorted(&state.light_clients),
+            bridge_escrows: sorted(&state.bridge_escrows),
+            sequencer_fees: sorted(&state.sequencer_fees),
+            retention: state.retention.clone(),
+            pruned: state.pruned.clone(),
+            protocol_version: state.protocol_version,
+            pending_upgrade: state.pending_upgrade.clone(),
+        }
+    }
+}
+
+impl From<CanonicalState> for ChainState {
+    fn from(c: CanonicalState) -> Self {
+        Self {
+            accounts: c.accounts.into_iter().collect(),
+            validators: c.validators.into_iter().collect(),
+            delegations: c.delegations.into_iter().collect(),
+            validator_rewards: c.validator_rewards.into_iter().collect(),
+            domains: c.domains.into_iter().collect(),
+            da_commitments: c.da_commitments,
+            domain_roots: c.domain_roots.into_iter().collect(),
+            proposals: c.proposals.into_iter().collect(),
+            fee_pools: c.fee_pools,
+            privacy_pools: c.privacy_pools.into_iter().collect(),
+            governance_params: c.governance_params,
+            total_supply: c.total_supply,
+            last_reward_height: c.last_reward_height,
+            pending_unbonds: c.pending_unbonds,
+            processed_evidence: c.processed_evidence,
+            exit_queue: c.exit_queue,
+            staking_params: c.staking_params,
+            param_overrides: c.param_overrides,
+            treasury_period: c.treasury_period,
+            assets: c.assets.into_iter().collect(),
+            multisigs: c.multisigs.into_iter().collect(),
+            schedule: c.schedule,
+            vesting: c.vesting.into_iter().collect(),
+            liveness: c.liveness.into_iter().collect(),
+            liveness_view: c.liveness_view,
+            verification_keys: c.verification_keys,
+            optimistic_claims: c.optimistic_claims.into_iter().collect(),
+            finalized_domain_roots: c.finalized_domain_roots.into_iter().collect(),
+            sequencer_bonds: c.sequencer_bonds.into_iter().collect(),
+            sequencer_rounds: c.sequencer_rounds.into_iter().collect(),
+            batch_records: c.batch_records.into_iter().collect(),
+            preconf_disputes: c.preconf_disputes.into_iter().collect(),
+            forced_inclusions: c.forced_inclusions.into_iter().collect(),
+            light_clients: c.light_clients.into_iter().collect(),
+            bridge_escrows: c.bridge_escrows.into_iter().collect(),
+            sequencer_fees: c.sequencer_fees.into_iter().collect(),
+            retention: c.retention,
+            pruned: c.pruned,
+            protocol_version: c.protocol_version,
+            pending_upgrade: c.pending_upgrade,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SnapshotManifest {
+    pub height: u64,
+    pub state_root: Hash,
+    pub chunk_size: u32,
+    pub total_bytes: u64,
+    pub chunk_hashes: Vec<Hash>,
+}
+
+impl SnapshotManifest {
+    /// Commitment placed in `BlockHeader::snapshot_root`.
+    pub fn hash(&self) -> Hash {
+        let bytes = bincode::serialize(self).unwrap_or_default();
+        *blake3::hash(&bytes).as_bytes()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_hashes.len()
+    }
+
+    pub fn verify_chunk(&self, index: usize, chunk: &[u8]) -> anyhow::Result<()> {
+        let expected = self
+            .chunk_hashes
+            .get(index)
+            .ok_or_else(|| anyhow::anyhow!("chunk index {} out of range", index))?;
+        if blake3::hash(chunk).as_bytes() != expected {
+            anyhow::bail!("chunk {} hash mismatch", index);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub manifest: SnapshotManifest,
+    pub chunks: Vec<Vec<u8>>,
+}
+
+impl ChainState {
+    pub fn snapshot(&self, height: u64, chunk_size: usize) -> anyhow::Result<StateSnapshot> {
+        if chunk_size == 0 {
+            anyhow::bail!("chunk size must be non-zero");
+        }
+        let bytes = bincode::serialize(&CanonicalState::from(self))?;
+        let chunks: Vec<Vec<u8>> = bytes.chunks(chunk_size).map(|c| c.to_vec()).collect();
+        let chunk_hashes = chunks.iter().map(|c| *blake3::hash(c).as_bytes()).collect();
+        Ok(StateSnapshot {
+            manifest: SnapshotManifest {
+                height,
+                state_root: self.state_root(),
+                chunk_size: chunk_size as u32,
+                total_bytes: bytes.len() as u64,
+                chunk_hashes,
+            },
+            chunks,
+        })
+    }
+
+    pub fn restore_snapshot(
+        manifest: &SnapshotManifest,
+        chunks: &[Vec<u8>],
+    ) -> anyhow::Result<ChainState> {
+        if chunks.len() != manifest.chunk_count() {
+            anyhow::bail!(
+                "expected {} chunks, got {}",
+                manifest.chunk_count(),
+                chunks.len()
+            );
+        }
+        let mut bytes = Vec::with_capacity(manifest.total_bytes as usize);
+        for (idx, chunk) in chunks.iter().enumerate() {
+            manifest.verify_chunk(idx, chunk)?;
+            bytes.extend_from_slice(chunk);
+        }
+        if bytes.len() as u64 != manifest.total_bytes {
+            anyhow::bail!("snapshot size mismatch");
+        }
+        let canonical: CanonicalState = bincode::deserialize(&bytes)?;
+        let state = ChainState::from(canonical);
+        if state.state_root() != manifest.state_root {
+            anyhow::bail!("restored state root does not match manifest");
+        }
+        Ok(state)
+    }
+}
+
+/// Recent snapshots served to syncing peers, keyed by height.
+#[derive(Clone)]
+pub struct SnapshotStore {
+    inner: Arc<Mutex<BTreeMap<u64, StateSnapshot>>>,
+    retention: usize,
+}
+
+impl Default for SnapshotStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_SNAPSHOT_RETENTION)
+    }
+}
+
+impl SnapshotStore {
+    pub fn new(retention: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(BTreeMap::new())),
+            retention: retention.max(1),
+        }
+    }
+
+    pub fn insert(&self, snapshot: StateSnapshot) {
+        let mut guard = self.inner.lock().unwrap();
+        guard.insert(snapshot.manifest.height, snapshot);
+        while guard.len() > self.retention {
+            let oldest = *guard.keys().next().unwrap();
+            guard.remove(&oldest);
+        }
+    }
+
+    pub fn latest_manifest(&self) -> Option<SnapshotManifest> {
+        let guard = self.inner.lock().unwrap();
+        guard.values().next_back().map(|s| s.manifest.clone())
+    }
+
+    pub fn manifest(&self, height: u64) -> Option<SnapshotManifest> {
+        let guard = self.inner.lock().unwrap();
+        guard.get(&height).map(|s| s.manifest.clone())
+    }
+
+    pub fn chunk(&self, height: u64, index: usize) -> Option<Vec<u8>> {
+        let guard = self.inner.lock().unwrap();
+        guard
+            .get(&height)
+            .and_then(|s| s.chunks.get(index).cloned())
+    }
+}
@@ -0,0 +1,287 @@
+//! Delegation positions with F1-style reward accounting. Each validator keeps
+//! a running index of delegator rewards per unit of delegated stake; a
+//! position remembers the index it was last settled at, so rewards accrue
+//! lazily and distributing them touches only the validator.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Address, ChainState};
+
+/// Fixed-point scale of reward indexes.
+pub const REWARD_INDEX_SCALE: u128 = 1_000_000_000_000_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelegationPosition {
+    pub delegator: Address,
+    pub validator_id: Uuid,
+    pub stake: u128,
+    /// The validator's `reward_index` when this position was last settled.
+    pub reward_index: u128,
+    /// Rewards settled into the position and not yet withdrawn.
+    pub pending_rewards: u128,
+}
+
+/// Active-set rules, applied at each epoch boundary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StakingParams {
+    /// Stake below which a validator stays a candidate.
+    pub min_validator_stake: u128,
+    pub max_active_validators: u32,
+}
+
+impl Default for StakingParams {
+    fn default() -> Self {
+        Self {
+            min_validator_stake: 10_000,
+            max_active_validators: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidatorRewards {
+    /// Total stake of the validator's delegation positions.
+    pub delegated_stake: u128,
+    /// Delegator rewards per unit of delegated stake, times
+    /// `REWARD_INDEX_SCALE`. Wraps on overflow; only differences are used.
+    pub reward_index: u128,
+}
+
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    let mask = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & mask);
+    let (b_hi, b_lo) = (b >> 64, b & mask);
+    let lo_lo = a_lo * b_lo;
+    let hi_lo = a_hi * b_lo;
+    let lo_hi = a_lo * b_hi;
+    let mid = (lo_lo >> 64) + (hi_lo & mask) + (lo_hi & mask);
+    let lo = (lo_lo & mask) | (mid << 64);
+    let hi = a_hi * b_hi + (hi_lo >> 64) + (lo_hi >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+/// `a * b / d` with a 256-bit intermediate product. Saturates when the
+/// quotient does not fit; `d` must be non-zero.
+pub fn mul_div(a: u128, b: u128, d: u128) -> u128 {
+    if let Some(product) = a.checked_mul(b) {
+        return product / d;
+    }
+    let (hi, lo) = widening_mul(a, b);
+    if hi >= d {
+        return u128::MAX;
+    }
+    let mut rem = hi;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || rem >= d {
+            rem = rem.wrapping_sub(d);
+            quotient |= 1;
+        }
+    }
+    quotient
+}
+
+fn accrued(position: &DelegationPosition, index: u128) -> u128 {
+    let delta = index.wrapping_sub(position.reward_index);
+    mul_div(position.stake, delta, REWARD_INDEX_SCALE)
+}
+
+impl DelegationPosition {
+    /// Moves rewards accrued up to `index`, the validator's reward index,
+    /// into `pending_rewards`. Must run before the stake changes.
+    pub fn settle(&mut self, index: u128) {
+        self.pending_rewards = self.pending_rewards.saturating_add(accrued(self, index));
+        self.reward_index = index;
+    }
+
+    /// Settles the position and takes its pending rewards.
+    pub fn withdraw_rewards(&mut self, index: u128) -> u128 {
+        self.settle(index);
+        std::mem::take(&mut self.pending_rewards)
+    }
+}
+
+/// Adds `amount` to `position`, opening it when there is none, and to the
+/// validator's delegated stake.
+pub fn delegate_to(
+    rewards: &mut ValidatorRewards,
+    position: Option<DelegationPosition>,
+    delegator: Address,
+    validator_id: Uuid,
+    amount: u128,
+) -> anyhow::Result<DelegationPosition> {
+    let mut position = position.unwrap_or(DelegationPosition {
+        delegator,
+        validator_id,
+        stake: 0,
+        reward_index: rewards.reward_index,
+        pending_rewards: 0,
+    });
+    position.settle(rewards.reward_index);
+    rewards.delegated_stake = rewards
+        .delegated_stake
+        .checked_add(amount)
+        .ok_or_else(|| anyhow::anyhow!("stake overflow"))?;
+    position.stake = position
+        .stake
+        .checked_add(amount)
+        .ok_or_else(|| anyhow::anyhow!("stake overflow"))?;
+    Ok(position)
+}
+
+/// Takes `amount` out of `position` and the validator's delegated stake.
+/// The position's rewards stay pending until withdrawn.
+pub fn undelegate_from(
+    rewards: Option<&mut ValidatorRewards>,
+    position: &mut DelegationPosition,
+    amount: u128,
+) -> anyhow::Result<()> {
+    position.settle(rewards.as_ref().map_or(0, |r| r.reward_index));
+    if position.stake < amount {
+        anyhow::bail!("undelegate amount exceeds delegation");
+    }
+    position.stake -= amount;
+    if let Some(rewards) = rewards {
+        rewards.delegated_stake = rewards.delegated_stake.saturating_sub(amount);
+    }
+    Ok(())
+}
+
+impl ChainState {
+    pub fn delegated_stake(&self, validator_id: &Uuid) -> u128 {
+        self.validator_rewards
+            .get(validator_id)
+            .map(|r| r.delegated_stake)
+            .unwrap_or(0)
+    }
+
+    fn reward_index(&self, validator_id: &Uuid) -> u128 {
+        self.validator_rewards
+            .get(validator_id)
+            .map(|r| r.reward_index)
+            .unwrap_or(0)
+    }
+
+    /// Rewards a withdrawal would pay out right now.
+    pub fn delegation_rewards(&self, delegator: &Address, validator_id: &Uuid) -> u128 {
+        let Some(position) = self.delegations.get(&(*delegator, *validator_id)) else {
+            return 0;
+        };
+        position
+            .pending_rewards
+            .saturating_add(accrued(position, self.reward_index(validator_id)))
+    }
+
+    pub fn delegate(
+        &mut self,
+        delegator: Address,
+        validator_id: Uuid,
+        amount: u128,
+    ) -> anyhow::Result<()> {
+        let key = (delegator, validator_id);
+        let position = self.delegations.get(&key).cloned();
+        let rewards = self.validator_rewards.entry(validator_id).or_default();
+        let position = delegate_to(rewards, position, delegator, validator_id, amount)?;
+        self.delegations.insert(key, position);
+        Ok(())
+    }
+
+    /// Takes `amount` out of the position. Its rewards stay pending until
+    /// withdrawn.
+    pub fn undelegate(
+        &mut self,
+        delegator: Address,
+        validator_id: Uuid,
+        amount: u128,
+    ) -> anyhow::Result<()> {
+        let Some(position) = self.delegations.get_mut(&(delegator, validator_id)) else {
+            anyhow::bail!("delegation not found");
+        };
+        undelegate_from(
+            self.validator_rewards.get_mut(&validator_id),
+            position,
+            amount,
+        )
+    }
+
+    /// Settles the position and pays out its pending rewards, dropping it
+    /// once nothing is left in it. Returns the amount to credit.
+    pub fn withdraw_delegation_rewards(
+        &mut self,
+        delegator: Address,
+        validator_id: Uuid,
+    ) -> anyhow::Result<u128> {
+        let index = self.reward_index(&validator_id);
+        let key = (delegator, validator_id);
+        let Some(position) = self.delegations.get_mut(&key) else {
+            anyhow::bail!("delegation not found");
+        };
+        let rewards = position.withdraw_rewards(index);
+        if position.stake == 0 {
+            self.delegations.remove(&key);
+        }
+        Ok(rewards)
+    }
+
+    /// Raises the validator's reward index by `amount` spread over its
+    /// delegated stake. Returns false, leaving the index alone, when nothing
+    /// is delegated.
+    pub fn distribute_delegator_rewards(&mut self, validator_id: &Uuid, amount: u128) -> bool {
+        let Some(rewards) = self.validator_rewards.get_mut(validator_id) else {
+            return false;
+        };
+        if rewards.delegated_stake == 0 {
+            return false;
+        }
+        let per_stake = mul_div(amount, REWARD_INDEX_SCALE, rewards.delegated_stake);
+        rewards.reward_index = rewards.reward_index.wrapping_add(per_stake);
+        true
+    }
+
+    /// Cuts each of the validator's positions by its share of `penalty`,
+    /// out of `stake_before` total stake. Rewards already earned are kept.
+    pub fn slash_delegations(&mut self, validator_id: &Uuid, penalty: u128, stake_before: u128) {
+        slash_positions(
+            &mut self.delegations,
+            self.validator_rewards.get_mut(validator_id),
+            validator_id,
+            penalty,
+            stake_before,
+        );
+    }
+}
+
+/// `ChainState::slash_delegations` over the delegations and the validator's
+/// rewards entry.
+pub fn slash_positions(
+    delegations: &mut HashMap<(Address, Uuid), DelegationPosition>,
+    rewards: Option<&mut ValidatorRewards>,
+    validator_id: &Uuid,
+    penalty: u128,
+    stake_before: u128,
+) {
+    if stake_before == 0 {
+        return;
+    }
+    let index = rewards.as_ref().map_or(0, |r| r.reward_index);
+    let mut slashed = 0u128;
+    delegations.retain(|(_, id), position| {
+        if id != validator_id {
+            return true;
+        }
+        position.settle(index);
+        let cut = mul_div(penalty, position.stake, stake_before).min(position.stake);
+        position.stake -= cut;
+        slashed = slashed.saturating_add(cut);
+        position.stake > 0 || position.pending_rewards > 0
+    });
+    if let Some(rewards) = rewards {
+        rewards.delegated_stake = rewards.delegated_stake.saturating_sub(slashed);
+    }
+}
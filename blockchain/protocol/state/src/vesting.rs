@@ -0,0 +1,59 @@
+//! Vesting schedules. Funds under a schedule sit in the beneficiary's
+//! balance but only the vested part may be spent: nothing before the cliff,
+//! then linearly from `start_height` until everything unlocks at
+//! `end_height`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{mul_div, Address};
+
+pub const MAX_VESTING_SCHEDULES_PER_ACCOUNT: usize = 8;
+/// Smallest schedule a `VestingCreate` may open, so filling someone's
+/// schedule slots costs the griefer real funds.
+pub const MIN_VESTING_AMOUNT: u128 = 100_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VestingSchedule {
+    pub beneficiary: Address,
+    pub total: u128,
+    pub start_height: u64,
+    /// Nothing vests before this height; at it, the linear share accrued
+    /// since `start_height` unlocks at once.
+    pub cliff_height: u64,
+    pub end_height: u64,
+}
+
+impl VestingSchedule {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.total == 0 {
+            anyhow::bail!("vesting amount must be positive");
+        }
+        if self.cliff_height < self.start_height || self.end_height < self.cliff_height {
+            anyhow::bail!("vesting needs start <= cliff <= end");
+        }
+        Ok(())
+    }
+
+    pub fn vested(&self, height: u64) -> u128 {
+        if height < self.cliff_height {
+            return 0;
+        }
+        if height >= self.end_height {
+            return self.total;
+        }
+        let elapsed = (height - self.start_height) as u128;
+        let duration = (self.end_height - self.start_height) as u128;
+        mul_div(self.total, elapsed, duration)
+    }
+
+    pub fn locked(&self, height: u64) -> u128 {
+        self.total - self.vested(height)
+    }
+}
+
+/// Balance still locked across `schedules` at `height`.
+pub fn locked_balance(schedules: &[VestingSchedule], height: u64) -> u128 {
+    schedules
+        .iter()
+        .fold(0u128, |acc, s| acc.saturating_add(s.locked(height)))
+}
@@ -0,0 +1,35 @@
+//! Read-optimized copy of chain state. Writers publish an immutable snapshot
+//! once per block and readers load the latest one without touching the
+//! store's lock, so queries never wait on block execution.
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::{Account, Address, ChainState};
+
+#[derive(Clone, Default)]
+pub struct StateView {
+    current: Arc<ArcSwap<ChainState>>,
+}
+
+impl StateView {
+    pub fn new(state: ChainState) -> Self {
+        Self {
+            current: Arc::new(ArcSwap::from_pointee(state)),
+        }
+    }
+
+    /// The latest published state. Holding it does not block publishing.
+    pub fn load(&self) -> Arc<ChainState> {
+        self.current.load_full()
+    }
+
+    pub fn publish(&self, state: ChainState) {
+        self.current.store(Arc::new(state));
+    }
+
+    pub fn account(&self, address: &Address) -> Option<Account> {
+        self.current.load().accounts.get(address).cloned()
+    }
+}
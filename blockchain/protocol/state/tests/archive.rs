@@ -0,0 +1,71 @@
+use state::{Account, ChainState, InMemoryStateStore, StateArchive, StateStore};
+
+fn account(i: u8, balance_x: u128) -> Account {
+    Account {
+        address: [i; 32],
+        nonce: 0,
+        balance_x,
+        code_hash: None,
+        storage_root: None,
+        assets: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn past_heights_rebuild_from_checkpoints_and_diffs() {
+    let store = InMemoryStateStore::new().with_archive(StateArchive::new(4));
+    let mut chain = ChainState::default();
+    chain.accounts.insert([1; 32], account(1, 100));
+    store.put_chain_state(chain).await.unwrap();
+    store.archive(0).await.unwrap();
+
+    let mut roots = vec![store.commit().await.unwrap()];
+    for height in 1..=9u8 {
+        store
+            .put_account(account(1, 100 + height as u128))
+            .await
+            .unwrap();
+        if height == 3 {
+            store.put_account(account(2, 7)).await.unwrap();
+        }
+        if height == 6 {
+            let mut chain = store.get_chain_state().await.unwrap();
+            chain.accounts.remove(&[2; 32]);
+            chain.total_supply = 6;
+            store.put_chain_state(chain).await.unwrap();
+        }
+        store.archive(height as u64).await.unwrap();
+        roots.push(store.commit().await.unwrap());
+    }
+
+    for (height, root) in roots.iter().enumerate() {
+        let height = height as u64;
+        assert_eq!(store.state_root_at(height).await.unwrap(), Some(*root));
+        let past = store.get_chain_state_at(height).await.unwrap().unwrap();
+        assert_eq!(past.state_root(), *root);
+    }
+    let at = |h| store.get_account_at(&[2; 32], h);
+    assert!(at(2).await.unwrap().is_none());
+    assert_eq!(at(5).await.unwrap().map(|a| a.balance_x), Some(7));
+    assert!(at(6).await.unwrap().is_none());
+    let balance = store.get_account_at(&[1; 32], 5).await.unwrap();
+    assert_eq!(balance.map(|a| a.balance_x), Some(105));
+    assert!(store.get_chain_state_at(10).await.unwrap().is_none());
+}
+
+#[test]
+fn rerecording_a_height_drops_later_history() {
+    let archive = StateArchive::new(16);
+    let mut chain = ChainState::default();
+    for height in 0..5 {
+        chain.total_supply = height;
+        archive.record(height as u64, &chain);
+    }
+    chain.total_supply = 99;
+    let root = archive.record(2, &chain);
+
+    assert_eq!(archive.heights(), Some(0..=2));
+    assert_eq!(archive.state_root(2), Some(root));
+    assert_eq!(archive.state_at(2).unwrap().total_supply, 99);
+    assert_eq!(archive.state_at(1).unwrap().total_supply, 1);
+}
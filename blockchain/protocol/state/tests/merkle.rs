@@ -0,0 +1,40 @@
+use state::{merkle_path, CommitmentTree, ROOT_HISTORY_SIZE};
+
+fn leaf(i: u8) -> [u8; 32] {
+    [i + 1; 32]
+}
+
+#[test]
+fn incremental_root_matches_paths_over_all_leaves() {
+    let mut tree = CommitmentTree::default();
+    let empty_root = tree.root();
+    let mut leaves = Vec::new();
+    for i in 0..7 {
+        assert_eq!(tree.insert(leaf(i)).unwrap(), i as u64);
+        leaves.push(leaf(i));
+    }
+    assert_ne!(tree.root(), empty_root);
+
+    for (index, leaf) in leaves.iter().enumerate() {
+        let path = merkle_path(&leaves, index).unwrap();
+        assert_eq!(path.root, tree.root());
+        assert!(path.verify(leaf));
+        assert!(!path.verify(&[0xff; 32]));
+    }
+    assert!(merkle_path(&leaves, leaves.len()).is_none());
+    assert_eq!(CommitmentTree::from_leaves(&leaves).unwrap(), tree);
+}
+
+#[test]
+fn old_roots_expire_after_the_history_window() {
+    let mut tree = CommitmentTree::default();
+    tree.insert(leaf(0)).unwrap();
+    let first = tree.root();
+    for i in 1..ROOT_HISTORY_SIZE as u8 {
+        tree.insert(leaf(i)).unwrap();
+    }
+    assert!(tree.is_known_root(&first));
+    tree.insert(leaf(ROOT_HISTORY_SIZE as u8)).unwrap();
+    assert!(!tree.is_known_root(&first));
+    assert!(tree.is_known_root(&tree.root()));
+}
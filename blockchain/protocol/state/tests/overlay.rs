@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+use state::{
+    sections, Account, ChainState, InMemoryStateStore, Proposal, ProposalQuery, ProposalStatus,
+    StateOverlay, StateStore,
+};
+use uuid::Uuid;
+
+fn account(i: u8, balance_x: u128) -> Account {
+    Account {
+        address: [i; 32],
+        nonce: 0,
+        balance_x,
+        code_hash: None,
+        storage_root: None,
+        assets: Default::default(),
+    }
+}
+
+fn proposal(status: ProposalStatus) -> Proposal {
+    Proposal {
+        id: Uuid::new_v4(),
+        payload: serde_json::json!({}),
+        kind: "general".into(),
+        status,
+        proposer: [0u8; 32],
+        start: 0,
+        end: 10,
+        eta: None,
+        snapshot_total_stake: 0,
+        for_votes: 0,
+        against_votes: 0,
+        abstain_votes: 0,
+        votes: vec![],
+        execution: serde_json::json!({}),
+        voter_weights: HashMap::new(),
+        approvals: vec![],
+        deposit: 0,
+    }
+}
+
+#[tokio::test]
+async fn overlay_writes_reach_the_base_only_on_flush() {
+    let base = InMemoryStateStore::new();
+    let mut chain = ChainState::default();
+    chain.accounts.insert([1; 32], account(1, 100));
+    base.put_chain_state(chain).await.unwrap();
+    let base_root = base.commit().await.unwrap();
+
+    let overlay = StateOverlay::new(base.clone()).await.unwrap();
+    assert_eq!(
+        overlay
+            .get_account(&[1; 32])
+            .await
+            .unwrap()
+            .unwrap()
+            .balance_x,
+        100
+    );
+    overlay.put_account(account(1, 60)).await.unwrap();
+    overlay.put_account(account(2, 40)).await.unwrap();
+    let mut pools = overlay.get_fee_pools().await.unwrap();
+    pools.treasury += 5;
+    overlay.put_fee_pools(pools).await.unwrap();
+
+    assert_eq!(
+        overlay
+            .get_account(&[1; 32])
+            .await
+            .unwrap()
+            .unwrap()
+            .balance_x,
+        60
+    );
+    assert_eq!(overlay.get_chain_state().await.unwrap().accounts.len(), 2);
+    assert_eq!(
+        base.get_account(&[1; 32]).await.unwrap().unwrap().balance_x,
+        100
+    );
+    assert!(base.get_account(&[2; 32]).await.unwrap().is_none());
+    assert_eq!(base.commit().await.unwrap(), base_root);
+
+    let overlay_root = overlay.commit().await.unwrap();
+    assert_ne!(overlay_root, base_root);
+    overlay.flush().await.unwrap();
+    assert_eq!(base.commit().await.unwrap(), overlay_root);
+    assert_eq!(
+        base.get_account(&[2; 32]).await.unwrap().unwrap().balance_x,
+        40
+    );
+    assert_eq!(base.get_fee_pools().await.unwrap().treasury, 5);
+}
+
+#[tokio::test]
+async fn rollback_restores_the_checkpointed_working_set() {
+    let base = InMemoryStateStore::new();
+    let overlay = StateOverlay::new(base.clone()).await.unwrap();
+    overlay.put_account(account(1, 10)).await.unwrap();
+    let root = overlay.commit().await.unwrap();
+
+    let checkpoint = overlay.checkpoint().await.unwrap();
+    overlay.put_account(account(1, 3)).await.unwrap();
+    overlay.put_account(account(2, 7)).await.unwrap();
+    let mut chain = overlay.get_chain_state().await.unwrap();
+    chain.total_supply = 17;
+    overlay.put_chain_state(chain).await.unwrap();
+    overlay.rollback(checkpoint).await.unwrap();
+
+    assert_eq!(
+        overlay
+            .get_account(&[1; 32])
+            .await
+            .unwrap()
+            .unwrap()
+            .balance_x,
+        10
+    );
+    assert!(overlay.get_account(&[2; 32]).await.unwrap().is_none());
+    assert_eq!(overlay.commit().await.unwrap(), root);
+
+    // A store's own checkpoints roll back the same way.
+    let checkpoint = base.checkpoint().await.unwrap();
+    base.put_account(account(3, 1)).await.unwrap();
+    base.rollback(checkpoint).await.unwrap();
+    assert!(base.get_account(&[3; 32]).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn proposal_listings_include_the_overlay_writes() {
+    let base = InMemoryStateStore::new();
+    let mut chain = ChainState::default();
+    let existing = proposal(ProposalStatus::Active);
+    chain.proposals.insert(existing.id, existing.clone());
+    base.put_chain_state(chain).await.unwrap();
+
+    let overlay = StateOverlay::new(base.clone()).await.unwrap();
+    overlay
+        .put_proposal(Proposal {
+            status: ProposalStatus::Executed,
+            ..existing
+        })
+        .await
+        .unwrap();
+    overlay
+        .put_proposal(proposal(ProposalStatus::Active))
+        .await
+        .unwrap();
+
+    let all = overlay
+        .list_proposals(&ProposalQuery::default())
+        .await
+        .unwrap();
+    assert_eq!(all.total, 2);
+    let active = overlay
+        .list_proposals(&ProposalQuery {
+            status: Some(ProposalStatus::Active),
+            ..ProposalQuery::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(active.total, 1);
+    assert_ne!(active.items[0].id, existing.id);
+    let summary = overlay.proposal_summary().await.unwrap();
+    assert_eq!(summary.total, 2);
+    assert_eq!(summary.by_status.get("Executed"), Some(&1));
+
+    assert_eq!(
+        base.list_proposals(&ProposalQuery::default())
+            .await
+            .unwrap()
+            .total,
+        1
+    );
+}
+
+#[tokio::test]
+async fn section_and_entry_writes_roll_back_and_flush() {
+    let base = InMemoryStateStore::new();
+    let mut chain = ChainState::default();
+    let kept = Uuid::new_v4();
+    let dropped = Uuid::new_v4();
+    chain.sequencer_fees.insert(kept, 1);
+    chain.sequencer_fees.insert(dropped, 2);
+    base.put_chain_state(chain).await.unwrap();
+
+    let overlay = StateOverlay::new(base.clone()).await.unwrap();
+    overlay
+        .put_section::<sections::TotalSupply>(100)
+        .await
+        .unwrap();
+    overlay
+        .put_entry::<sections::SequencerFees>(kept, 5)
+        .await
+        .unwrap();
+
+    let checkpoint = overlay.checkpoint().await.unwrap();
+    overlay
+        .put_section::<sections::TotalSupply>(7)
+        .await
+        .unwrap();
+    overlay
+        .remove_entry::<sections::SequencerFees>(&dropped)
+        .await
+        .unwrap();
+    overlay.rollback(checkpoint).await.unwrap();
+
+    assert_eq!(
+        overlay
+            .get_section::<sections::TotalSupply>()
+            .await
+            .unwrap(),
+        100
+    );
+    assert_eq!(
+        overlay
+            .get_entry::<sections::SequencerFees>(&dropped)
+            .await
+            .unwrap(),
+        Some(2)
+    );
+
+    overlay
+        .remove_entry::<sections::SequencerFees>(&dropped)
+        .await
+        .unwrap();
+    let root = overlay.commit().await.unwrap();
+    overlay.flush().await.unwrap();
+    assert_eq!(base.commit().await.unwrap(), root);
+    let chain = base.get_chain_state().await.unwrap();
+    assert_eq!(chain.total_supply, 100);
+    assert_eq!(chain.sequencer_fees.get(&kept), Some(&5));
+    assert!(!chain.sequencer_fees.contains_key(&dropped));
+}
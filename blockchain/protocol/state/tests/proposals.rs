@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use state::{
+    ChainState, InMemoryStateStore, Proposal, ProposalQuery, ProposalStatus, SortOrder, StateStore,
+};
+use uuid::Uuid;
+
+fn proposal(end: u64, status: ProposalStatus, kind: &str) -> Proposal {
+    Proposal {
+        id: Uuid::new_v4(),
+        payload: serde_json::json!({}),
+        kind: kind.into(),
+        status,
+        proposer: [0u8; 32],
+        start: 0,
+        end,
+        eta: None,
+        snapshot_total_stake: 0,
+        for_votes: 0,
+        against_votes: 0,
+        abstain_votes: 0,
+        votes: vec![],
+        execution: serde_json::json!({}),
+        voter_weights: HashMap::new(),
+        approvals: vec![],
+        deposit: 0,
+    }
+}
+
+async fn seeded_store() -> InMemoryStateStore {
+    let mut chain = ChainState::default();
+    for end in 1..=10u64 {
+        let status = if end % 2 == 0 {
+            ProposalStatus::Active
+        } else {
+            ProposalStatus::Executed
+        };
+        let kind = if end <= 3 { "param_change" } else { "upgrade" };
+        let p = proposal(end, status, kind);
+        chain.proposals.insert(p.id, p);
+    }
+    let store = InMemoryStateStore::new();
+    store.put_chain_state(chain).await.unwrap();
+    store
+}
+
+#[tokio::test]
+async fn lists_proposals_with_filters_and_pages() {
+    let store = seeded_store().await;
+
+    let page = store
+        .list_proposals(&ProposalQuery {
+            status: Some(ProposalStatus::Active),
+            limit: Some(2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.total, 5);
+    let ends: Vec<u64> = page.items.iter().map(|p| p.end).collect();
+    assert_eq!(ends, vec![10, 8]);
+
+    let page = store
+        .list_proposals(&ProposalQuery {
+            kind: Some("param_change".into()),
+            order: SortOrder::Asc,
+            offset: Some(1),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.total, 3);
+    let ends: Vec<u64> = page.items.iter().map(|p| p.end).collect();
+    assert_eq!(ends, vec![2, 3]);
+}
+
+#[tokio::test]
+async fn summary_counts_by_status() {
+    let store = seeded_store().await;
+    let summary = store.proposal_summary().await.unwrap();
+    assert_eq!(summary.total, 10);
+    assert_eq!(summary.by_status.get("Active"), Some(&5));
+    assert_eq!(summary.by_status.get("Executed"), Some(&5));
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use state::{
+    ChainState, CommitmentTree, DACommitment, DelegationPosition, Proposal, ProposalStatus,
+    RetentionParams, Unbonding,
+};
+use uuid::Uuid;
+
+fn proposal(end: u64, status: ProposalStatus, deposit: u128) -> Proposal {
+    Proposal {
+        id: Uuid::new_v4(),
+        payload: serde_json::json!({}),
+        kind: "param_change".into(),
+        status,
+        proposer: [0u8; 32],
+        start: 0,
+        end,
+        eta: None,
+        snapshot_total_stake: 0,
+        for_votes: 0,
+        against_votes: 0,
+        abstain_votes: 0,
+        votes: vec![],
+        execution: serde_json::json!({}),
+        voter_weights: HashMap::new(),
+        approvals: vec![],
+        deposit,
+    }
+}
+
+fn delegation(delegator: u8, stake: u128, pending_rewards: u128) -> DelegationPosition {
+    DelegationPosition {
+        delegator: [delegator; 32],
+        validator_id: Uuid::nil(),
+        stake,
+        reward_index: 0,
+        pending_rewards,
+    }
+}
+
+fn unbond(owner: u8, amount: u128, release_height: u64) -> Unbonding {
+    Unbonding {
+        owner: [owner; 32],
+        validator_id: None,
+        amount,
+        release_height,
+    }
+}
+
+#[test]
+fn pruning_drops_expired_history_into_the_accumulator() {
+    let mut chain = ChainState {
+        retention: RetentionParams {
+            proposal_retention_epochs: 2,
+            max_da_commitments: 3,
+        },
+        ..ChainState::default()
+    };
+    let old = proposal(1_000, ProposalStatus::Executed, 0);
+    let recent = proposal(9_500, ProposalStatus::Defeated, 0);
+    let unsettled = proposal(1_000, ProposalStatus::Defeated, 50);
+    let active = proposal(1_000, ProposalStatus::Active, 0);
+    for p in [&old, &recent, &unsettled, &active] {
+        chain.proposals.insert(p.id, p.clone());
+    }
+    chain.da_commitments = (1..=5)
+        .map(|h| DACommitment {
+            block_height: h,
+            da_root: [h as u8; 32],
+            blob_ids: vec![],
+        })
+        .collect();
+    for position in [delegation(1, 0, 0), delegation(2, 0, 7), delegation(3, 10, 0)] {
+        chain
+            .delegations
+            .insert((position.delegator, position.validator_id), position);
+    }
+    chain.pending_unbonds = vec![unbond(1, 5, 20), unbond(1, 6, 20), unbond(2, 0, 20), unbond(1, 1, 30)];
+    let root_before = chain.state_root();
+
+    // Two epochs of 4_000ms after the oldest proposals ended.
+    let report = chain.prune(9_000, 4_000).unwrap();
+    assert_eq!(report.proposals, 1);
+    assert_eq!(report.da_commitments, 2);
+    assert_eq!(report.delegations, 1);
+    assert_eq!(report.unbonds, 2);
+
+    assert!(!chain.proposals.contains_key(&old.id));
+    for kept in [&recent, &unsettled, &active] {
+        assert!(chain.proposals.contains_key(&kept.id));
+    }
+    let heights: Vec<u64> = chain.da_commitments.iter().map(|c| c.block_height).collect();
+    assert_eq!(heights, vec![3, 4, 5]);
+    assert!(!chain.delegations.contains_key(&([1u8; 32], Uuid::nil())));
+    assert_eq!(chain.delegations.len(), 2);
+    let unbonds: Vec<(u128, u64)> = chain
+        .pending_unbonds
+        .iter()
+        .map(|u| (u.amount, u.release_height))
+        .collect();
+    assert_eq!(unbonds, vec![(11, 20), (1, 30)]);
+
+    assert_eq!(chain.pruned.len(), 4);
+    assert_ne!(chain.pruned.root(), CommitmentTree::default().root());
+    assert_ne!(chain.state_root(), root_before);
+
+    // Nothing left past retention: a second pass is a no-op.
+    let pruned_root = chain.pruned.root();
+    assert_eq!(chain.prune(9_000, 4_000).unwrap().total(), 0);
+    assert_eq!(chain.pruned.root(), pruned_root);
+}
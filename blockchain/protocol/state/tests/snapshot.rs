@@ -0,0 +1,106 @@
+use state::{Account, ChainState, Multisig, MultisigCall, MultisigProposal, SnapshotStore};
+
+fn sample_state() -> ChainState {
+    let mut state = ChainState::default();
+    for i in 0..64u8 {
+        let address = [i; 32];
+        state.accounts.insert(
+            address,
+            Account {
+                address,
+                nonce: i as u64,
+                balance_x: 1_000 * i as u128,
+                code_hash: None,
+                storage_root: None,
+                assets: Default::default(),
+            },
+        );
+    }
+    state.total_supply = 42_000;
+    state
+}
+
+#[test]
+fn snapshot_roundtrip_restores_state_root() {
+    let state = sample_state();
+    let snap = state.snapshot(10, 128).unwrap();
+    assert!(snap.manifest.chunk_count() > 1);
+    let restored = ChainState::restore_snapshot(&snap.manifest, &snap.chunks).unwrap();
+    assert_eq!(restored.state_root(), state.state_root());
+    assert_eq!(restored.accounts.len(), 64);
+}
+
+#[test]
+fn snapshot_is_deterministic() {
+    let a = sample_state().snapshot(5, 256).unwrap();
+    let b = sample_state().snapshot(5, 256).unwrap();
+    assert_eq!(a.manifest.hash(), b.manifest.hash());
+}
+
+#[test]
+fn tampered_chunk_is_rejected() {
+    let snap = sample_state().snapshot(1, 128).unwrap();
+    let mut chunks = snap.chunks.clone();
+    chunks[0][0] ^= 0xff;
+    assert!(ChainState::restore_snapshot(&snap.manifest, &chunks).is_err());
+}
+
+#[test]
+fn store_keeps_most_recent_snapshots() {
+    let store = SnapshotStore::new(2);
+    let state = sample_state();
+    for height in [10, 20, 30] {
+        store.insert(state.snapshot(height, 512).unwrap());
+    }
+    assert!(store.manifest(10).is_none());
+    assert_eq!(store.latest_manifest().unwrap().height, 30);
+    assert!(store.chunk(20, 0).is_some());
+}
+
+#[test]
+fn component_roots_localize_divergence() {
+    let state = sample_state();
+    let mut diverged = state.clone();
+    diverged.total_supply += 1;
+
+    let ours = state.component_roots();
+    let theirs = diverged.component_roots();
+    let differing: Vec<&String> = ours
+        .keys()
+        .filter(|name| ours[*name] != theirs[*name])
+        .collect();
+    assert_eq!(differing, vec!["total_supply"]);
+    assert_ne!(state.state_root(), diverged.state_root());
+}
+
+#[test]
+fn snapshot_restores_pending_multisig_calls() {
+    let mut state = sample_state();
+    let address = [0xaa; 32];
+    let proposal = MultisigProposal {
+        call: MultisigCall::Transfer {
+            to: [1; 32],
+            amount: 5,
+        },
+        proposer: [1; 32],
+        approvals: [[1; 32]].into(),
+        submitted_height: 3,
+    };
+    state.multisigs.insert(
+        address,
+        Multisig {
+            address,
+            signers: vec![[1; 32], [2; 32]],
+            threshold: 2,
+            created_height: 1,
+            pending: [(0, proposal)].into(),
+            next_proposal_id: 1,
+        },
+    );
+    let snap = state.snapshot(10, 256).unwrap();
+    let restored = ChainState::restore_snapshot(&snap.manifest, &snap.chunks).unwrap();
+    assert_eq!(
+        restored.multisigs[&address].pending[&0].call,
+        state.multisigs[&address].pending[&0].call
+    );
+}
@@ -0,0 +1,339 @@
+//! `kova-cli airdrop`: sends one transfer per CSV row, in batches that fit a
+//! block, waits for each batch to land and writes a per-row report.
+
+use std::collections::HashMap;
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use clap::Args;
+use ed25519_dalek::SigningKey;
+use reqwest::blocking::Client;
+use runtime::{address_from_pubkey, sign_bytes, tx_hash, tx_signing_bytes, Address, Tx, TxPayload};
+use serde_json::json;
+
+use crate::{get_json, parse_address};
+
+const TRANSFER_GAS: u64 = 21_000;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const NOT_INCLUDED: &str = "not included before timeout";
+
+#[derive(Args, Debug)]
+pub struct AirdropArgs {
+    /// CSV with `address,amount` rows; a header row and `#` comments are allowed
+    #[arg(long)]
+    csv: String,
+    /// Output path for the per-row result report (CSV)
+    #[arg(long, default_value = "airdrop-report.csv")]
+    out: String,
+    /// Gas budget per batch; one batch is submitted and confirmed at a time
+    #[arg(long, default_value = "30000000")]
+    block_gas_limit: u64,
+    /// Maximum price per gas unit
+    #[arg(long, default_value = "1")]
+    max_fee: u128,
+    /// Rebroadcasts of an unconfirmed transfer before it is reported as failed
+    #[arg(long, default_value = "3")]
+    retries: u32,
+    /// Seconds to wait for a batch to be included before rebroadcasting
+    #[arg(long, default_value = "30")]
+    confirm_timeout_secs: u64,
+    /// Validate the CSV and print the plan without sending anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Recipient {
+    line: usize,
+    address: Address,
+    amount: u128,
+}
+
+#[derive(Debug)]
+enum Status {
+    Included,
+    Failed(String),
+}
+
+struct Outcome {
+    recipient: Recipient,
+    nonce: u64,
+    tx_hash: [u8; 32],
+    attempts: u32,
+    status: Status,
+}
+
+pub fn run(
+    client: &Client,
+    rpc: &str,
+    chain_id: &str,
+    sk: &SigningKey,
+    args: AirdropArgs,
+) -> anyhow::Result<()> {
+    let contents =
+        fs::read_to_string(&args.csv).with_context(|| format!("reading {}", args.csv))?;
+    let recipients = parse_recipients(&contents)?;
+    let rpc = rpc.trim_end_matches('/');
+    let sender = address_from_pubkey(&sk.verifying_key().to_bytes());
+    let per_batch = (args.block_gas_limit / TRANSFER_GAS).max(1) as usize;
+    let total: u128 = recipients.iter().map(|r| r.amount).sum();
+    let fees = recipients.len() as u128 * TRANSFER_GAS as u128 * args.max_fee;
+    let balance: Option<u128> = get_json(
+        client,
+        &format!("{rpc}/get_balance/{}", hex::encode(sender)),
+    )?;
+    let balance = balance.unwrap_or(0);
+    println!(
+        "{} recipients, {} total plus up to {} in fees, {} batches of up to {}",
+        recipients.len(),
+        total,
+        fees,
+        recipients.len().div_ceil(per_batch),
+        per_batch
+    );
+    if balance < total.saturating_add(fees) {
+        anyhow::bail!("sender balance {balance} cannot cover {total} plus {fees} in fees");
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+
+    let mut outcomes = Vec::with_capacity(recipients.len());
+    for (i, batch) in recipients.chunks(per_batch).enumerate() {
+        let nonce: Option<u64> =
+            get_json(client, &format!("{rpc}/get_nonce/{}", hex::encode(sender)))?;
+        let results = send_batch(client, rpc, chain_id, sk, nonce.unwrap_or(0), batch, &args)?;
+        let failed = results
+            .iter()
+            .filter(|o| matches!(o.status, Status::Failed(_)))
+            .count();
+        println!(
+            "batch {}: {} included, {} failed",
+            i + 1,
+            batch.len() - failed,
+            failed
+        );
+        outcomes.extend(results);
+    }
+
+    fs::write(&args.out, render_report(&outcomes))
+        .with_context(|| format!("writing {}", args.out))?;
+    let failed = outcomes
+        .iter()
+        .filter(|o| matches!(o.status, Status::Failed(_)))
+        .count();
+    println!("wrote {} ({} failed)", args.out, failed);
+    if failed > 0 {
+        anyhow::bail!("{failed} transfers were not included; see {}", args.out);
+    }
+    Ok(())
+}
+
+/// Validates every row up front so a bad file never sends a partial airdrop.
+fn parse_recipients(contents: &str) -> anyhow::Result<Vec<Recipient>> {
+    let mut recipients = Vec::new();
+    let mut seen = HashMap::new();
+    let mut errors = Vec::new();
+    let mut first_row = true;
+    for (idx, raw) in contents.lines().enumerate() {
+        let line = idx + 1;
+        let row = raw.trim();
+        if row.is_empty() || row.starts_with('#') {
+            continue;
+        }
+        let Some((addr, amount)) = row.split_once(',') else {
+            errors.push(format!("line {line}: expected `address,amount`"));
+            continue;
+        };
+        let (addr, amount) = (addr.trim(), amount.trim());
+        if std::mem::take(&mut first_row) && amount.parse::<u128>().is_err() {
+            // Header row.
+            continue;
+        }
+        let address = match parse_address(addr) {
+            Ok(address) => address,
+            Err(err) => {
+                errors.push(format!("line {line}: {err}"));
+                continue;
+            }
+        };
+        let amount = match amount.parse::<u128>() {
+            Ok(0) => {
+                errors.push(format!("line {line}: amount must be positive"));
+                continue;
+            }
+            Ok(amount) => amount,
+            Err(_) => {
+                errors.push(format!("line {line}: invalid amount {amount:?}"));
+                continue;
+            }
+        };
+        let first = *seen.entry(address).or_insert(line);
+        if first != line {
+            errors.push(format!("line {line}: duplicate of line {first}"));
+            continue;
+        }
+        recipients.push(Recipient {
+            line,
+            address,
+            amount,
+        });
+    }
+    if !errors.is_empty() {
+        anyhow::bail!("invalid airdrop file:\n{}", errors.join("\n"));
+    }
+    if recipients.is_empty() {
+        anyhow::bail!("airdrop file has no recipients");
+    }
+    Ok(recipients)
+}
+
+/// Signs the batch with consecutive nonces, submits it and rebroadcasts the
+/// same signed transfers until they are indexed or retries run out. Signed
+/// bytes never change, so a rebroadcast can't pay a recipient twice.
+fn send_batch(
+    client: &Client,
+    rpc: &str,
+    chain_id: &str,
+    sk: &SigningKey,
+    first_nonce: u64,
+    batch: &[Recipient],
+    args: &AirdropArgs,
+) -> anyhow::Result<Vec<Outcome>> {
+    let mut pending = Vec::with_capacity(batch.len());
+    for (offset, recipient) in batch.iter().enumerate() {
+        let nonce = first_nonce + offset as u64;
+        let tx = sign_transfer(chain_id, sk, recipient, nonce, args.max_fee)?;
+        let outcome = Outcome {
+            recipient: recipient.clone(),
+            nonce,
+            tx_hash: tx_hash(&tx),
+            attempts: 0,
+            status: Status::Failed(NOT_INCLUDED.into()),
+        };
+        pending.push((tx, outcome));
+    }
+
+    let mut done = Vec::with_capacity(batch.len());
+    for _ in 0..=args.retries {
+        for (tx, outcome) in pending.iter_mut() {
+            outcome.attempts += 1;
+            outcome.status = match submit(client, rpc, tx) {
+                Ok(()) => Status::Failed(NOT_INCLUDED.into()),
+                Err(err) => Status::Failed(format!("{err:#}")),
+            };
+        }
+        let deadline = Instant::now() + Duration::from_secs(args.confirm_timeout_secs);
+        while !pending.is_empty() && Instant::now() < deadline {
+            thread::sleep(POLL_INTERVAL);
+            let mut still_pending = Vec::with_capacity(pending.len());
+            for (tx, mut outcome) in pending {
+                if is_included(client, rpc, &outcome.tx_hash)? {
+                    outcome.status = Status::Included;
+                    done.push(outcome);
+                } else {
+                    still_pending.push((tx, outcome));
+                }
+            }
+            pending = still_pending;
+        }
+        if pending.is_empty() {
+            break;
+        }
+    }
+    done.extend(pending.into_iter().map(|(_, outcome)| outcome));
+    done.sort_by_key(|o| o.nonce);
+    Ok(done)
+}
+
+fn sign_transfer(
+    chain_id: &str,
+    sk: &SigningKey,
+    recipient: &Recipient,
+    nonce: u64,
+    max_fee: u128,
+) -> anyhow::Result<Tx> {
+    let mut tx = Tx {
+        chain_id: chain_id.to_string(),
+        nonce,
+        gas_limit: TRANSFER_GAS,
+        max_fee: Some(max_fee),
+        max_priority_fee: Some(0),
+        gas_price: None,
+        payload: TxPayload::Transfer {
+            to: recipient.address,
+            amount: recipient.amount,
+        },
+        public_key: sk.verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    };
+    tx.signature = sign_bytes(sk, &tx_signing_bytes(&tx)?);
+    Ok(tx)
+}
+
+fn submit(client: &Client, rpc: &str, tx: &Tx) -> anyhow::Result<()> {
+    let res = client
+        .post(format!("{rpc}/send_raw_tx"))
+        .json(&json!({ "tx": tx }))
+        .send()
+        .context("sending tx to node")?;
+    let body: serde_json::Value = res.error_for_status()?.json()?;
+    if body != json!("ok") {
+        anyhow::bail!("node rejected tx: {body}");
+    }
+    Ok(())
+}
+
+fn is_included(client: &Client, rpc: &str, hash: &[u8; 32]) -> anyhow::Result<bool> {
+    let tx: Option<serde_json::Value> =
+        get_json(client, &format!("{rpc}/get_tx/{}", hex::encode(hash)))?;
+    Ok(tx.is_some())
+}
+
+fn render_report(outcomes: &[Outcome]) -> String {
+    let mut out = String::from("line,address,amount,nonce,tx_hash,attempts,status,detail\n");
+    for o in outcomes {
+        let (status, detail) = match &o.status {
+            Status::Included => ("included", String::new()),
+            Status::Failed(reason) => ("failed", reason.replace(',', ";")),
+        };
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            o.recipient.line,
+            hex::encode(o.recipient.address),
+            o.recipient.amount,
+            o.nonce,
+            hex::encode(o.tx_hash),
+            o.attempts,
+            status,
+            detail
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rows_and_reports_every_bad_line() {
+        let a = hex::encode([1u8; 32]);
+        let b = hex::encode([2u8; 32]);
+        let csv = format!("address,amount\n# team\n{a},100\n\n0x{b}, 5\n");
+        let recipients = parse_recipients(&csv).unwrap();
+        assert_eq!(recipients.len(), 2);
+        assert_eq!(recipients[1].address, [2u8; 32]);
+        assert_eq!(recipients[1].line, 5);
+
+        let err = parse_recipients(&format!("{a},100\n{a},1\nzz,3\n{b},0\n{b}\n"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("line 2: duplicate of line 1"));
+        assert!(err.contains("line 3:"));
+        assert!(err.contains("line 4: amount must be positive"));
+        assert!(err.contains("line 5: expected `address,amount`"));
+    }
+}
@@ -0,0 +1,237 @@
+//! `kova-cli gov`: governance txs plus proposal status read from the node's
+//! `/governance` endpoints.
+
+use std::fmt::Write;
+
+use anyhow::Context;
+use clap::{Subcommand, ValueEnum};
+use ed25519_dalek::SigningKey;
+use reqwest::blocking::Client;
+use runtime::Tx;
+use sdk_rust::{
+    build_governance_cancel_signed, build_governance_execute_signed,
+    build_governance_proposal_signed, build_governance_vote_signed, VoteChoice,
+};
+use state::{Proposal, ProposalPage};
+use uuid::Uuid;
+
+use crate::{get_json, read_json};
+
+#[derive(Subcommand, Debug)]
+pub enum GovCommands {
+    /// Submit a proposal; voting opens immediately
+    Propose {
+        /// JSON payload file path
+        #[arg(long)]
+        payload_path: String,
+        /// Proposal kind, e.g. `privacy_fee` (chain default: general)
+        #[arg(long)]
+        kind: Option<String>,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Vote on an active proposal with the signer's stake
+    Vote {
+        proposal_id: Uuid,
+        #[arg(value_enum)]
+        support: Support,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Execute a queued proposal once its timelock has passed
+    Execute {
+        proposal_id: Uuid,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Withdraw your own proposal while voting is open; the deposit is
+    /// refunded
+    Cancel {
+        proposal_id: Uuid,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Show a proposal's status and tally
+    Show { proposal_id: Uuid },
+    /// List proposals, newest first
+    List {
+        /// Only proposals in this status, e.g. Active
+        #[arg(long)]
+        status: Option<String>,
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Support {
+    For,
+    Against,
+    Abstain,
+}
+
+impl From<Support> for VoteChoice {
+    fn from(support: Support) -> Self {
+        match support {
+            Support::For => VoteChoice::For,
+            Support::Against => VoteChoice::Against,
+            Support::Abstain => VoteChoice::Abstain,
+        }
+    }
+}
+
+impl GovCommands {
+    /// Read-only commands, which need no signing key.
+    pub fn is_query(&self) -> bool {
+        matches!(self, GovCommands::Show { .. } | GovCommands::List { .. })
+    }
+}
+
+pub fn build_tx(chain_id: &str, command: GovCommands, sk: &SigningKey) -> anyhow::Result<Tx> {
+    match command {
+        GovCommands::Propose {
+            payload_path,
+            kind,
+            nonce,
+        } => {
+            let payload: serde_json::Value = read_json(&payload_path)?;
+            build_governance_proposal_signed(chain_id, payload, kind, sk, nonce)
+        }
+        GovCommands::Vote {
+            proposal_id,
+            support,
+            nonce,
+        } => build_governance_vote_signed(chain_id, proposal_id, support.into(), sk, nonce),
+        GovCommands::Execute { proposal_id, nonce } => {
+            build_governance_execute_signed(chain_id, proposal_id, sk, nonce)
+        }
+        GovCommands::Cancel { proposal_id, nonce } => {
+            build_governance_cancel_signed(chain_id, proposal_id, sk, nonce)
+        }
+        GovCommands::Show { .. } | GovCommands::List { .. } => {
+            unreachable!("queries are handled by gov::query")
+        }
+    }
+}
+
+pub fn query(client: &Client, rpc: &str, command: GovCommands) -> anyhow::Result<()> {
+    let rpc = rpc.trim_end_matches('/');
+    match command {
+        GovCommands::Show { proposal_id } => {
+            let proposal: Option<Proposal> =
+                get_json(client, &format!("{rpc}/governance/proposal/{proposal_id}"))?;
+            let proposal = proposal.with_context(|| format!("proposal {proposal_id} not found"))?;
+            print!("{}", render_proposal(&proposal));
+        }
+        GovCommands::List { status, limit } => {
+            let mut url = format!("{rpc}/governance/proposals?limit={limit}");
+            if let Some(status) = status {
+                write!(url, "&status={status}")?;
+            }
+            let page: Option<ProposalPage> = get_json(client, &url)?;
+            let page = page.context("node could not list proposals")?;
+            println!("{} of {} proposals", page.items.len(), page.total);
+            for p in &page.items {
+                println!(
+                    "{}  {:<9}  {:<14}  for {} / against {} / abstain {}",
+                    p.id,
+                    format!("{:?}", p.status),
+                    p.kind,
+                    p.for_votes,
+                    p.against_votes,
+                    p.abstain_votes
+                );
+            }
+        }
+        _ => unreachable!("txs are built by gov::build_tx"),
+    }
+    Ok(())
+}
+
+fn share(votes: u128, total: u128) -> String {
+    if total == 0 {
+        return "-".into();
+    }
+    format!("{:.2}%", votes as f64 * 100.0 / total as f64)
+}
+
+fn render_proposal(p: &Proposal) -> String {
+    let total = p.snapshot_total_stake;
+    let turnout = p.for_votes + p.against_votes + p.abstain_votes;
+    let mut out = String::new();
+    let _ = writeln!(out, "proposal  {}", p.id);
+    let _ = writeln!(out, "kind      {}", p.kind);
+    let _ = writeln!(out, "status    {:?}", p.status);
+    let _ = writeln!(out, "proposer  {}", hex::encode(p.proposer));
+    let _ = writeln!(out, "voting    {} .. {}", p.start, p.end);
+    if let Some(eta) = p.eta {
+        let _ = writeln!(out, "eta       {eta}");
+    }
+    if p.deposit > 0 {
+        let _ = writeln!(out, "deposit   {}", p.deposit);
+    }
+    let _ = writeln!(
+        out,
+        "for       {} ({})",
+        p.for_votes,
+        share(p.for_votes, total)
+    );
+    let _ = writeln!(
+        out,
+        "against   {} ({})",
+        p.against_votes,
+        share(p.against_votes, total)
+    );
+    let _ = writeln!(
+        out,
+        "abstain   {} ({})",
+        p.abstain_votes,
+        share(p.abstain_votes, total)
+    );
+    let _ = writeln!(
+        out,
+        "turnout   {turnout} of {total} ({}), {} voters",
+        share(turnout, total),
+        p.votes.len()
+    );
+    let _ = writeln!(
+        out,
+        "payload   {}",
+        serde_json::to_string(&p.payload).unwrap_or_default()
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::ProposalStatus;
+
+    #[test]
+    fn renders_tally_as_share_of_snapshot_stake() {
+        let proposal = Proposal {
+            id: Uuid::nil(),
+            payload: serde_json::json!({ "param": "min_stake" }),
+            kind: "general".into(),
+            status: ProposalStatus::Active,
+            proposer: [1u8; 32],
+            start: 10,
+            end: 20,
+            eta: None,
+            snapshot_total_stake: 1_000,
+            for_votes: 600,
+            against_votes: 100,
+            abstain_votes: 0,
+            votes: vec![],
+            execution: serde_json::Value::Null,
+            voter_weights: Default::default(),
+            approvals: vec![],
+            deposit: 0,
+        };
+        let out = render_proposal(&proposal);
+        assert!(out.contains("status    Active\n"));
+        assert!(out.contains("for       600 (60.00%)\n"));
+        assert!(out.contains("turnout   700 of 1000 (70.00%), 0 voters\n"));
+        assert!(!out.contains("eta"));
+    }
+}
@@ -0,0 +1,290 @@
+//! `kova-cli keys`: named signing keys kept in a password-encrypted keystore
+//! file. Each key is sealed separately with AES-256-GCM under a key derived
+//! from the password with scrypt, so listing needs no password.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::Context;
+use clap::Subcommand;
+use ed25519_dalek::SigningKey;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use runtime::address_from_pubkey;
+use serde::{Deserialize, Serialize};
+
+const KEYSTORE_VERSION: u32 = 1;
+const PASSWORD_ENV: &str = "KOVA_KEYSTORE_PASSWORD";
+
+#[derive(Subcommand, Debug)]
+pub enum KeysCommands {
+    /// Generate a new key and store it under `name`
+    Generate { name: String },
+    /// Store an existing hex-encoded private key under `name`
+    Import {
+        name: String,
+        /// Hex private key; prompted for when omitted so it stays out of shell history
+        #[arg(long)]
+        sk: Option<String>,
+    },
+    /// List stored keys and their addresses
+    List,
+    /// Print the hex-encoded private key stored under `name`
+    Export { name: String },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct KdfParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+const DEFAULT_KDF: KdfParams = KdfParams {
+    log_n: 15,
+    r: 8,
+    p: 1,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeyEntry {
+    address: String,
+    kdf: KdfParams,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Keystore {
+    version: u32,
+    keys: BTreeMap<String, KeyEntry>,
+}
+
+impl Default for Keystore {
+    fn default() -> Self {
+        Self {
+            version: KEYSTORE_VERSION,
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl Keystore {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let keystore: Self = serde_json::from_str(&contents)
+            .with_context(|| format!("parsing keystore {}", path.display()))?;
+        if keystore.version != KEYSTORE_VERSION {
+            anyhow::bail!("unsupported keystore version {}", keystore.version);
+        }
+        Ok(keystore)
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::rename(&tmp, path).with_context(|| format!("writing {}", path.display()))
+    }
+
+    fn insert(
+        &mut self,
+        name: &str,
+        sk: &SigningKey,
+        password: &str,
+        kdf: KdfParams,
+    ) -> anyhow::Result<()> {
+        if self.keys.contains_key(name) {
+            anyhow::bail!("a key named {name} already exists");
+        }
+        self.keys.insert(name.to_string(), seal(sk, password, kdf)?);
+        Ok(())
+    }
+
+    fn signing_key(&self, name: &str, password: &str) -> anyhow::Result<SigningKey> {
+        let entry = self
+            .keys
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("no key named {name} in the keystore"))?;
+        open(entry, password)
+    }
+}
+
+fn derive_key(password: &str, salt: &[u8], kdf: KdfParams) -> anyhow::Result<[u8; 32]> {
+    let params = scrypt::Params::new(kdf.log_n, kdf.r, kdf.p, 32)
+        .map_err(|e| anyhow::anyhow!("invalid scrypt params: {e}"))?;
+    let mut key = [0u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+        .map_err(|e| anyhow::anyhow!("scrypt: {e}"))?;
+    Ok(key)
+}
+
+fn seal(sk: &SigningKey, password: &str, kdf: KdfParams) -> anyhow::Result<KeyEntry> {
+    let mut salt = [0u8; 32];
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+    let cipher = Aes256Gcm::new(&derive_key(password, &salt, kdf)?.into());
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), sk.to_bytes().as_slice())
+        .map_err(|_| anyhow::anyhow!("encrypting key"))?;
+    Ok(KeyEntry {
+        address: hex::encode(address_from_pubkey(&sk.verifying_key().to_bytes())),
+        kdf,
+        salt: hex::encode(salt),
+        nonce: hex::encode(nonce),
+        ciphertext: hex::encode(ciphertext),
+    })
+}
+
+fn open(entry: &KeyEntry, password: &str) -> anyhow::Result<SigningKey> {
+    let salt = hex::decode(&entry.salt).context("decoding keystore salt")?;
+    let nonce = hex::decode(&entry.nonce).context("decoding keystore nonce")?;
+    let ciphertext = hex::decode(&entry.ciphertext).context("decoding keystore ciphertext")?;
+    if nonce.len() != 12 {
+        anyhow::bail!("keystore nonce must be 12 bytes");
+    }
+    let cipher = Aes256Gcm::new(&derive_key(password, &salt, entry.kdf)?.into());
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow::anyhow!("wrong password or corrupted keystore entry"))?;
+    let bytes: [u8; 32] = plaintext
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("stored key must be 32 bytes"))?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// `--keystore` when given, `~/.kova/keystore.json` otherwise.
+pub fn keystore_path(keystore: Option<&str>) -> anyhow::Result<PathBuf> {
+    if let Some(path) = keystore {
+        return Ok(PathBuf::from(path));
+    }
+    let home = std::env::var_os("HOME")
+        .ok_or_else(|| anyhow::anyhow!("HOME is not set; pass --keystore"))?;
+    Ok(PathBuf::from(home).join(".kova").join("keystore.json"))
+}
+
+/// Reads the keystore password from `KOVA_KEYSTORE_PASSWORD`, or prompts for
+/// it (twice when `confirm` is set, as for a new key).
+fn password(confirm: bool) -> anyhow::Result<String> {
+    if let Ok(password) = std::env::var(PASSWORD_ENV) {
+        return Ok(password);
+    }
+    let password = rpassword::prompt_password("Keystore password: ")?;
+    if confirm && rpassword::prompt_password("Repeat password: ")? != password {
+        anyhow::bail!("passwords do not match");
+    }
+    Ok(password)
+}
+
+pub fn parse_signing_key(sk_hex: &str) -> anyhow::Result<SigningKey> {
+    let sk_bytes = hex::decode(sk_hex.trim().trim_start_matches("0x"))
+        .context("failed to decode secret key")?;
+    Ok(SigningKey::from_bytes(
+        sk_bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("secret key must be 32 bytes"))?,
+    ))
+}
+
+/// Decrypts the key stored under `name` for signing.
+pub fn load_signing_key(path: &Path, name: &str) -> anyhow::Result<SigningKey> {
+    let keystore = Keystore::load(path)?;
+    if !keystore.keys.contains_key(name) {
+        anyhow::bail!("no key named {name} in {}", path.display());
+    }
+    keystore.signing_key(name, &password(false)?)
+}
+
+pub fn run(path: &Path, command: KeysCommands) -> anyhow::Result<()> {
+    let mut keystore = Keystore::load(path)?;
+    match command {
+        KeysCommands::Generate { name } => {
+            let mut seed = [0u8; 32];
+            OsRng.fill_bytes(&mut seed);
+            let sk = SigningKey::from_bytes(&seed);
+            keystore.insert(&name, &sk, &password(true)?, DEFAULT_KDF)?;
+            keystore.save(path)?;
+            println!("{name}: {}", keystore.keys[&name].address);
+        }
+        KeysCommands::Import { name, sk } => {
+            let sk_hex = match sk {
+                Some(sk) => sk,
+                None => rpassword::prompt_password("Private key (hex): ")?,
+            };
+            let sk = parse_signing_key(&sk_hex)?;
+            keystore.insert(&name, &sk, &password(true)?, DEFAULT_KDF)?;
+            keystore.save(path)?;
+            println!("{name}: {}", keystore.keys[&name].address);
+        }
+        KeysCommands::List => {
+            for (name, entry) in &keystore.keys {
+                println!("{name}\t{}", entry.address);
+            }
+        }
+        KeysCommands::Export { name } => {
+            if !keystore.keys.contains_key(&name) {
+                anyhow::bail!("no key named {name} in {}", path.display());
+            }
+            let sk = keystore.signing_key(&name, &password(false)?)?;
+            println!("{}", hex::encode(sk.to_bytes()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters keep the tests fast; they are stored per entry.
+    const TEST_KDF: KdfParams = KdfParams {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+
+    #[test]
+    fn keys_round_trip_through_the_keystore_file() {
+        let dir = std::env::temp_dir().join(format!("kova-keys-{}", std::process::id()));
+        let path = dir.join("keystore.json");
+        let sk = SigningKey::from_bytes(&[5u8; 32]);
+
+        let mut keystore = Keystore::load(&path).unwrap();
+        keystore.insert("alice", &sk, "hunter2", TEST_KDF).unwrap();
+        assert!(keystore.insert("alice", &sk, "other", TEST_KDF).is_err());
+        keystore.save(&path).unwrap();
+
+        let keystore = Keystore::load(&path).unwrap();
+        let entry = &keystore.keys["alice"];
+        assert_eq!(
+            entry.address,
+            hex::encode(address_from_pubkey(&sk.verifying_key().to_bytes()))
+        );
+        assert!(!entry.ciphertext.contains(&hex::encode(sk.to_bytes())));
+        let opened = keystore.signing_key("alice", "hunter2").unwrap();
+        assert_eq!(opened.to_bytes(), sk.to_bytes());
+        let err = keystore.signing_key("alice", "hunter3").unwrap_err();
+        assert!(err.to_string().contains("wrong password"));
+        assert!(keystore.signing_key("bob", "hunter2").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
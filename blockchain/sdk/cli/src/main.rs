@@ -1,17 +1,34 @@
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 
 use anyhow::Context;
 use clap::{Parser, Subcommand};
 use ed25519_dalek::SigningKey;
 use reqwest::blocking::Client;
-use runtime::{CrossDomainMessage, DomainCall};
+use runtime::{
+    devnet_genesis, fork_genesis, Address, CrossDomainMessage, DomainCall, DomainProof,
+    ForkOptions, ForkPatch, GenesisConfig, GenesisValidator, Tx, TxPayload,
+};
 use sdk_rust::{
-    build_cross_domain_relay_signed, build_cross_domain_send_signed, build_domain_execute_signed,
-    build_transfer_signed,
+    build_asset_create_signed, build_asset_mint_signed, build_asset_transfer_signed,
+    build_claim_rewards_signed, build_cross_domain_relay_signed, build_cross_domain_send_signed,
+    build_delegate_signed, build_domain_execute_signed, build_multisig_approve_signed,
+    build_multisig_create_signed, build_multisig_execute_signed, build_multisig_submit_signed,
+    build_schedule_cancel_signed, build_schedule_signed, build_stake_signed, build_transfer_signed,
+    build_undelegate_signed, build_unjail_signed, build_unstake_signed,
+    build_validator_edit_signed, build_vesting_create_signed, MultisigCall,
 };
+use serde::Deserialize;
 use serde_json::json;
+use state::{ChainState, StateSnapshot, Validator};
 use uuid::Uuid;
 
+mod airdrop;
+mod gov;
+mod keys;
+mod query;
+mod watch;
+
 #[derive(Parser, Debug)]
 #[command(name = "kova-cli")]
 #[command(about = "Kova dev CLI for sending txs and domain calls", long_about = None)]
@@ -20,9 +37,17 @@ struct Cli {
     #[arg(long, env = "KOVA_RPC", default_value = "http://localhost:7000")]
     rpc: String,
 
-    /// Hex-encoded 32-byte ed25519 private key
+    /// Hex-encoded 32-byte ed25519 private key (tx commands need this or --from)
     #[arg(long, env = "KOVA_SK")]
-    sk: String,
+    sk: Option<String>,
+
+    /// Name of the keystore key to sign tx commands with
+    #[arg(long, env = "KOVA_FROM", conflicts_with = "sk")]
+    from: Option<String>,
+
+    /// Keystore file (default: ~/.kova/keystore.json)
+    #[arg(long, env = "KOVA_KEYSTORE", global = true)]
+    keystore: Option<String>,
 
     /// Chain id to tag txs
     #[arg(long, env = "KOVA_CHAIN_ID", default_value = "kova-devnet")]
@@ -68,28 +93,496 @@ enum Commands {
         #[arg(long, default_value = "0")]
         nonce: u64,
     },
-    /// Relay a cross-domain message (expects JSON)
+    /// Relay a cross-domain message (expects an outbox item JSON with the
+    /// message and its proof, as `/domain/:id/outbox` serves them)
     CrossRelay {
         #[arg(long)]
         message_path: String,
         #[arg(long, default_value = "0")]
         nonce: u64,
     },
+    /// Stake as a validator, registering the signer on first use
+    Stake {
+        #[arg(long)]
+        amount: u128,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Start unbonding self-stake
+    Unstake {
+        #[arg(long)]
+        amount: u128,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Delegate to a validator, identified by its owner address
+    Delegate {
+        #[arg(long)]
+        validator: String,
+        #[arg(long)]
+        amount: u128,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Start unbonding a delegation
+    Undelegate {
+        #[arg(long)]
+        validator: String,
+        #[arg(long)]
+        amount: u128,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Withdraw the rewards accrued by a delegation
+    ClaimRewards {
+        #[arg(long)]
+        validator: String,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Lock funds for a beneficiary, vesting linearly after a cliff
+    Vest {
+        #[arg(long)]
+        beneficiary: String,
+        #[arg(long)]
+        amount: u128,
+        #[arg(long)]
+        cliff_height: u64,
+        #[arg(long)]
+        end_height: u64,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Governance proposals and votes
+    Gov {
+        #[command(subcommand)]
+        command: gov::GovCommands,
+    },
+    /// Create, mint and transfer user-issued assets
+    Asset {
+        #[command(subcommand)]
+        command: AssetCommands,
+    },
+    /// Create multisig accounts and propose, approve and execute their calls
+    Multisig {
+        #[command(subcommand)]
+        command: MultisigCommands,
+    },
+    /// Schedule a payload to run from the signer at a later height
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommands,
+    },
+    /// Validator set queries and profile edits
+    Validator {
+        #[command(subcommand)]
+        command: ValidatorCommands,
+    },
+    /// Read-only lookups
+    Query {
+        #[command(subcommand)]
+        command: query::QueryCommands,
+    },
+    /// Follow the chain over the node's WebSocket feed
+    Watch {
+        #[command(subcommand)]
+        command: watch::WatchCommands,
+    },
+    /// Send transfers to every `address,amount` row of a CSV file
+    Airdrop(airdrop::AirdropArgs),
+    /// Manage keys in the encrypted keystore
+    Keys {
+        #[command(subcommand)]
+        command: keys::KeysCommands,
+    },
+    /// Genesis tooling
+    Genesis {
+        #[command(subcommand)]
+        command: GenesisCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ValidatorCommands {
+    /// List validators with their stake and status
+    List,
+    /// Change the signer's commission or public profile
+    Edit {
+        /// Percent of delegator rewards kept; may move a little each epoch
+        #[arg(long)]
+        commission_rate: Option<u8>,
+        #[arg(long)]
+        moniker: Option<String>,
+        #[arg(long)]
+        website: Option<String>,
+        #[arg(long)]
+        details: Option<String>,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Rejoin the active set once a downtime jail's cooldown has passed
+    Unjail {
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AssetCommands {
+    /// Register an asset issued by the signer
+    Create {
+        #[arg(long)]
+        symbol: String,
+        #[arg(long, default_value = "0")]
+        decimals: u8,
+        /// Omit for an uncapped asset
+        #[arg(long)]
+        max_supply: Option<u128>,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Issue new units; only the asset's issuer may mint
+    Mint {
+        #[arg(long)]
+        asset_id: Uuid,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: u128,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    Transfer {
+        #[arg(long)]
+        asset_id: Uuid,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        amount: u128,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum MultisigCommands {
+    /// Register a multisig; its address is derived from the signer and nonce
+    Create {
+        /// Comma-separated hex addresses
+        #[arg(long, value_delimiter = ',')]
+        signers: Vec<String>,
+        #[arg(long)]
+        threshold: u16,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Propose a call, counting as its first approval
+    Submit {
+        #[arg(long)]
+        multisig: String,
+        /// MultisigCall JSON, e.g. {"transfer":{"to":[..],"amount":10}}
+        #[arg(long)]
+        call: String,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    Approve {
+        #[arg(long)]
+        multisig: String,
+        #[arg(long)]
+        proposal_id: u64,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    /// Run a call that has reached the threshold
+    Execute {
+        #[arg(long)]
+        multisig: String,
+        #[arg(long)]
+        proposal_id: u64,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ScheduleCommands {
+    Submit {
+        #[arg(long)]
+        at_height: u64,
+        /// TxPayload JSON file path
+        #[arg(long)]
+        payload_path: String,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+    Cancel {
+        #[arg(long)]
+        schedule_id: u64,
+        #[arg(long, default_value = "0")]
+        nonce: u64,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GenesisCommands {
+    /// Build a genesis for a new network from an exported state snapshot
+    Fork {
+        /// StateSnapshot JSON (manifest + chunks)
+        #[arg(long)]
+        from_snapshot: String,
+        /// Chain id of the new network; must differ from the template's
+        #[arg(long)]
+        new_chain_id: String,
+        /// JSON array of GenesisValidator replacing the source validator set
+        #[arg(long)]
+        validators: String,
+        /// GenesisConfig JSON supplying non-state parameters (devnet defaults otherwise)
+        #[arg(long)]
+        template: Option<String>,
+        /// File with one hex address per line; only these accounts are kept
+        #[arg(long)]
+        allowlist: Option<String>,
+        /// JSON patch: {"set_balances": {"<hex address>": amount}, "remove": ["<hex address>"]}
+        #[arg(long)]
+        patch: Option<String>,
+        /// Output path for the generated GenesisConfig JSON
+        #[arg(long, default_value = "genesis.json")]
+        out: String,
+    },
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PatchFile {
+    #[serde(default)]
+    set_balances: BTreeMap<String, u128>,
+    #[serde(default)]
+    remove: Vec<String>,
+}
+
+/// One item of a domain's outbox page.
+#[derive(Debug, Deserialize)]
+struct RelayItem {
+    message: CrossDomainMessage,
+    proof: Option<DomainProof>,
+}
+
+fn parse_address(hex_str: &str) -> anyhow::Result<Address> {
+    let bytes = hex::decode(hex_str.trim().trim_start_matches("0x"))
+        .with_context(|| format!("decode address {hex_str}"))?;
+    bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("address {hex_str} must be 32 bytes"))
+}
+
+fn asset_tx(chain_id: &str, command: AssetCommands, sk: &SigningKey) -> anyhow::Result<Tx> {
+    match command {
+        AssetCommands::Create {
+            symbol,
+            decimals,
+            max_supply,
+            nonce,
+        } => build_asset_create_signed(chain_id, symbol, decimals, max_supply, sk, nonce),
+        AssetCommands::Mint {
+            asset_id,
+            to,
+            amount,
+            nonce,
+        } => build_asset_mint_signed(chain_id, asset_id, parse_address(&to)?, amount, sk, nonce),
+        AssetCommands::Transfer {
+            asset_id,
+            to,
+            amount,
+            nonce,
+        } => {
+            let to = parse_address(&to)?;
+            build_asset_transfer_signed(chain_id, asset_id, to, amount, sk, nonce)
+        }
+    }
+}
+
+fn multisig_tx(chain_id: &str, command: MultisigCommands, sk: &SigningKey) -> anyhow::Result<Tx> {
+    match command {
+        MultisigCommands::Create {
+            signers,
+            threshold,
+            nonce,
+        } => {
+            let signers = signers
+                .iter()
+                .map(|s| parse_address(s))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            build_multisig_create_signed(chain_id, signers, threshold, sk, nonce)
+        }
+        MultisigCommands::Submit {
+            multisig,
+            call,
+            nonce,
+        } => {
+            let call: MultisigCall = serde_json::from_str(&call).context("parsing --call")?;
+            build_multisig_submit_signed(chain_id, parse_address(&multisig)?, call, sk, nonce)
+        }
+        MultisigCommands::Approve {
+            multisig,
+            proposal_id,
+            nonce,
+        } => {
+            let multisig = parse_address(&multisig)?;
+            build_multisig_approve_signed(chain_id, multisig, proposal_id, sk, nonce)
+        }
+        MultisigCommands::Execute {
+            multisig,
+            proposal_id,
+            nonce,
+        } => {
+            let multisig = parse_address(&multisig)?;
+            build_multisig_execute_signed(chain_id, multisig, proposal_id, sk, nonce)
+        }
+    }
+}
+
+fn get_json<T: serde::de::DeserializeOwned>(client: &Client, url: &str) -> anyhow::Result<T> {
+    client
+        .get(url)
+        .send()
+        .with_context(|| format!("GET {url}"))?
+        .error_for_status()?
+        .json()
+        .with_context(|| format!("decoding response from {url}"))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> anyhow::Result<T> {
+    let bytes = fs::read_to_string(path).with_context(|| format!("reading {path}"))?;
+    serde_json::from_str(&bytes).with_context(|| format!("parsing json from {path}"))
+}
+
+fn genesis_command(command: GenesisCommands) -> anyhow::Result<()> {
+    match command {
+        GenesisCommands::Fork {
+            from_snapshot,
+            new_chain_id,
+            validators,
+            template,
+            allowlist,
+            patch,
+            out,
+        } => {
+            let snapshot: StateSnapshot = read_json(&from_snapshot)?;
+            let source = ChainState::restore_snapshot(&snapshot.manifest, &snapshot.chunks)
+                .context("restoring snapshot")?;
+            let template = match template {
+                Some(path) => read_json::<GenesisConfig>(&path)?,
+                None => devnet_genesis(),
+            };
+            let validators: Vec<GenesisValidator> = read_json(&validators)?;
+            let allowlist = match allowlist {
+                Some(path) => {
+                    let contents =
+                        fs::read_to_string(&path).with_context(|| format!("reading {path}"))?;
+                    let addresses = contents
+                        .lines()
+                        .map(str::trim)
+                        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                        .map(parse_address)
+                        .collect::<anyhow::Result<HashSet<Address>>>()?;
+                    Some(addresses)
+                }
+                None => None,
+            };
+            let patch_file: PatchFile = match patch {
+                Some(path) => read_json(&path)?,
+                None => PatchFile::default(),
+            };
+            let patch = ForkPatch {
+                set_balances: patch_file
+                    .set_balances
+                    .iter()
+                    .map(|(addr, balance)| Ok((parse_address(addr)?, *balance)))
+                    .collect::<anyhow::Result<_>>()?,
+                remove: patch_file
+                    .remove
+                    .iter()
+                    .map(|addr| parse_address(addr))
+                    .collect::<anyhow::Result<_>>()?,
+            };
+            let genesis = fork_genesis(
+                &source,
+                template,
+                ForkOptions {
+                    chain_id: new_chain_id,
+                    validators,
+                    allowlist,
+                    patch,
+                },
+            )?;
+            fs::write(&out, serde_json::to_string_pretty(&genesis)?)
+                .with_context(|| format!("writing {out}"))?;
+            println!(
+                "forked snapshot at height {} into {} ({} accounts, {} validators)",
+                snapshot.manifest.height,
+                out,
+                genesis.initial_accounts.len(),
+                genesis.initial_validators.len()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn validator_command(client: &Client, rpc: &str, command: ValidatorCommands) -> anyhow::Result<()> {
+    match command {
+        ValidatorCommands::List => {
+            let url = format!("{}/get_validators", rpc.trim_end_matches('/'));
+            let mut validators: Vec<Validator> = get_json(client, &url)?;
+            validators.sort_by_key(|v| std::cmp::Reverse(v.stake));
+            for v in &validators {
+                println!(
+                    "{}  {:<9}  stake {}  commission {}%  {}",
+                    hex::encode(v.owner),
+                    format!("{:?}", v.status),
+                    v.stake,
+                    v.commission_rate,
+                    v.description.moniker
+                );
+            }
+            Ok(())
+        }
+        ValidatorCommands::Edit { .. } | ValidatorCommands::Unjail { .. } => {
+            unreachable!("edits and unjails are signed txs")
+        }
+    }
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    let command = match cli.command {
+        Commands::Genesis { command } => return genesis_command(command),
+        Commands::Keys { command } => {
+            return keys::run(&keys::keystore_path(cli.keystore.as_deref())?, command)
+        }
+        Commands::Validator {
+            command: command @ ValidatorCommands::List,
+        } => return validator_command(&Client::new(), &cli.rpc, command),
+        Commands::Query { command } => return query::run(&Client::new(), &cli.rpc, command),
+        Commands::Watch { command } => return watch::run(&cli.rpc, command),
+        Commands::Gov { command } if command.is_query() => {
+            return gov::query(&Client::new(), &cli.rpc, command)
+        }
+        command => command,
+    };
     let client = Client::new();
-    let sk_bytes = hex::decode(cli.sk.trim_start_matches("0x"))
-        .context("failed to decode secret key")?;
-    let sk = SigningKey::from_bytes(
-        sk_bytes
-            .as_slice()
-            .try_into()
-            .map_err(|_| anyhow::anyhow!("secret key must be 32 bytes"))?,
-    );
-
-    let tx = match cli.command {
+    let sk = match (&cli.from, &cli.sk) {
+        (Some(name), _) => {
+            keys::load_signing_key(&keys::keystore_path(cli.keystore.as_deref())?, name)?
+        }
+        (None, Some(sk_hex)) => keys::parse_signing_key(sk_hex)?,
+        (None, None) => anyhow::bail!(
+            "--from <key-name> or --sk (KOVA_FROM / KOVA_SK) is required to sign transactions"
+        ),
+    };
+
+    let tx = match command {
         Commands::Transfer { to, amount, nonce } => {
             let mut dest = [0u8; 32];
             let decoded = hex::decode(to.trim_start_matches("0x"))
@@ -113,7 +606,7 @@ fn main() -> anyhow::Result<()> {
                 domain_id: Uuid::parse_str(&domain_id)
                     .context("invalid domain_id (uuid expected)")?,
                 payload,
-                raw: None,
+                raw: vec![],
                 max_gas: Some(gas_limit),
             };
             build_domain_execute_signed(&cli.chain_id, call, &sk, nonce, gas_limit)?
@@ -142,10 +635,103 @@ fn main() -> anyhow::Result<()> {
         Commands::CrossRelay { message_path, nonce } => {
             let bytes = fs::read_to_string(&message_path)
                 .with_context(|| format!("reading message at {message_path}"))?;
-            let msg: CrossDomainMessage = serde_json::from_str(&bytes)
+            let item: RelayItem = serde_json::from_str(&bytes)
                 .with_context(|| format!("parsing message json from {message_path}"))?;
-            build_cross_domain_relay_signed(&cli.chain_id, msg, &sk, nonce)?
+            let proof = item
+                .proof
+                .context("message has no proof; wait for its source root to be committed")?;
+            build_cross_domain_relay_signed(&cli.chain_id, item.message, proof, &sk, nonce)?
+        }
+        Commands::Stake { amount, nonce } => build_stake_signed(&cli.chain_id, amount, &sk, nonce)?,
+        Commands::Unstake { amount, nonce } => {
+            build_unstake_signed(&cli.chain_id, amount, &sk, nonce)?
+        }
+        Commands::Delegate {
+            validator,
+            amount,
+            nonce,
+        } => build_delegate_signed(
+            &cli.chain_id,
+            parse_address(&validator)?,
+            amount,
+            &sk,
+            nonce,
+        )?,
+        Commands::Undelegate {
+            validator,
+            amount,
+            nonce,
+        } => build_undelegate_signed(
+            &cli.chain_id,
+            parse_address(&validator)?,
+            amount,
+            &sk,
+            nonce,
+        )?,
+        Commands::ClaimRewards { validator, nonce } => {
+            build_claim_rewards_signed(&cli.chain_id, parse_address(&validator)?, &sk, nonce)?
+        }
+        Commands::Vest {
+            beneficiary,
+            amount,
+            cliff_height,
+            end_height,
+            nonce,
+        } => build_vesting_create_signed(
+            &cli.chain_id,
+            parse_address(&beneficiary)?,
+            amount,
+            cliff_height,
+            end_height,
+            &sk,
+            nonce,
+        )?,
+        Commands::Gov { command } => gov::build_tx(&cli.chain_id, command, &sk)?,
+        Commands::Validator {
+            command:
+                ValidatorCommands::Edit {
+                    commission_rate,
+                    moniker,
+                    website,
+                    details,
+                    nonce,
+                },
+        } => build_validator_edit_signed(
+            &cli.chain_id,
+            commission_rate,
+            moniker,
+            website,
+            details,
+            &sk,
+            nonce,
+        )?,
+        Commands::Validator {
+            command: ValidatorCommands::Unjail { nonce },
+        } => build_unjail_signed(&cli.chain_id, &sk, nonce)?,
+        Commands::Asset { command } => asset_tx(&cli.chain_id, command, &sk)?,
+        Commands::Multisig { command } => multisig_tx(&cli.chain_id, command, &sk)?,
+        Commands::Schedule {
+            command:
+                ScheduleCommands::Submit {
+                    at_height,
+                    payload_path,
+                    nonce,
+                },
+        } => {
+            let inner: TxPayload = read_json(&payload_path)?;
+            build_schedule_signed(&cli.chain_id, at_height, inner, &sk, nonce)?
+        }
+        Commands::Schedule {
+            command: ScheduleCommands::Cancel { schedule_id, nonce },
+        } => build_schedule_cancel_signed(&cli.chain_id, schedule_id, &sk, nonce)?,
+        Commands::Airdrop(args) => {
+            return airdrop::run(&client, &cli.rpc, &cli.chain_id, &sk, args);
         }
+        Commands::Genesis { .. }
+        | Commands::Keys { .. }
+        | Commands::Validator { .. }
+        | Commands::Query { .. }
+        | Commands::Watch { .. } => unreachable!("handled above"),
     };
 
     let payload = json!({ "tx": tx });
@@ -0,0 +1,104 @@
+//! `kova-cli query`: read-only lookups against the node RPC.
+
+use anyhow::Context;
+use clap::Subcommand;
+use reqwest::blocking::Client;
+use runtime::{address_from_pubkey, hash_block, Block, Tx};
+use sdk_rust::{TxReceipt, VestingStatus};
+use uuid::Uuid;
+
+use crate::{get_json, parse_address};
+
+#[derive(Subcommand, Debug)]
+pub enum QueryCommands {
+    /// Balance and next nonce of an account
+    Balance { address: String },
+    /// Vested and locked amounts of an account's vesting schedules
+    Vesting { address: String },
+    /// A tx and, once included, its receipt
+    Tx { hash: String },
+    /// Block header summary and tx hashes
+    Block { height: u64 },
+    /// Committed and live roots of a domain
+    Domain { domain_id: Uuid },
+}
+
+pub fn run(client: &Client, rpc: &str, command: QueryCommands) -> anyhow::Result<()> {
+    let rpc = rpc.trim_end_matches('/');
+    match command {
+        QueryCommands::Balance { address } => {
+            let address = hex::encode(parse_address(&address)?);
+            let balance: Option<u128> = get_json(client, &format!("{rpc}/get_balance/{address}"))?;
+            let nonce: Option<u64> = get_json(client, &format!("{rpc}/get_nonce/{address}"))?;
+            println!("address  {address}");
+            println!("balance  {}", balance.unwrap_or(0));
+            println!("nonce    {}", nonce.unwrap_or(0));
+        }
+        QueryCommands::Vesting { address } => {
+            let address = hex::encode(parse_address(&address)?);
+            let status: Option<VestingStatus> =
+                get_json(client, &format!("{rpc}/get_vesting/{address}"))?;
+            let status = status.with_context(|| format!("invalid address {address}"))?;
+            println!("height   {}", status.height);
+            println!("vested   {}", status.vested);
+            println!("locked   {}", status.locked);
+            for p in &status.schedules {
+                let s = &p.schedule;
+                println!(
+                    "  {} vested, {} locked (start {}, cliff {}, end {})",
+                    p.vested, p.locked, s.start_height, s.cliff_height, s.end_height
+                );
+            }
+        }
+        QueryCommands::Tx { hash } => {
+            let hash = hash.trim().trim_start_matches("0x").to_lowercase();
+            let tx: Option<Tx> = get_json(client, &format!("{rpc}/get_tx/{hash}"))?;
+            let tx = tx.with_context(|| format!("tx {hash} not found"))?;
+            let receipt: Option<TxReceipt> =
+                get_json(client, &format!("{rpc}/get_receipt/{hash}"))?;
+            println!("hash     {hash}");
+            println!(
+                "sender   {}",
+                hex::encode(address_from_pubkey(&tx.public_key))
+            );
+            println!("nonce    {}", tx.nonce);
+            match receipt {
+                Some(r) => {
+                    let status = if r.success { "success" } else { "failed" };
+                    println!("status   {status} at height {}", r.height);
+                    println!("gas      {} of {}", r.outcome.gas_used, tx.gas_limit);
+                    if let Some(error) = &r.outcome.error {
+                        println!("error    {error}");
+                    }
+                    for event in &r.outcome.events {
+                        println!("event    {} {:?}", event.kind, event.attributes);
+                    }
+                }
+                None => println!("status   pending"),
+            }
+            println!("payload  {}", serde_json::to_string(&tx.payload)?);
+        }
+        QueryCommands::Block { height } => {
+            let block: Option<Block> = get_json(client, &format!("{rpc}/get_block/{height}"))?;
+            let block = block.with_context(|| format!("no block at height {height}"))?;
+            let header = &block.header;
+            println!("height     {}", header.height);
+            println!("hash       {}", hex::encode(hash_block(&block)));
+            println!("parent     {}", hex::encode(header.parent_hash));
+            println!("proposer   {}", hex::encode(header.proposer_id));
+            println!("timestamp  {}", header.timestamp);
+            println!("state      {}", hex::encode(header.state_root));
+            println!("gas        {} of {}", header.gas_used, header.gas_limit);
+            println!("txs        {}", block.transactions.len());
+            for tx in &block.transactions {
+                println!("  {}", hex::encode(sdk_rust::tx_hash(tx)));
+            }
+        }
+        QueryCommands::Domain { domain_id } => {
+            let root: serde_json::Value =
+                get_json(client, &format!("{rpc}/domain/{domain_id}/root"))?;
+            println!("{}", serde_json::to_string_pretty(&root)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,148 @@
+//! `kova-cli watch`: tails the node's `/ws` feed.
+
+use anyhow::Context;
+use clap::Subcommand;
+use serde::Deserialize;
+use serde_json::json;
+use tungstenite::Message;
+
+#[derive(Subcommand, Debug)]
+pub enum WatchCommands {
+    /// Print one line per new block, and a line for each reorg
+    Blocks,
+}
+
+/// The `new_heads` events the node pushes, as far as they are printed.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "channel", rename_all = "snake_case")]
+enum HeadEvent {
+    NewHead {
+        height: u64,
+        hash: String,
+        proposer: String,
+        gas_used: u64,
+        tx_count: usize,
+        timestamp: u64,
+    },
+    Reorg {
+        height: u64,
+        old_head: String,
+        new_head: String,
+        orphaned: Vec<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct Push {
+    event: HeadEvent,
+}
+
+/// `ws://` URL of the node's subscription endpoint for an `http(s)://` RPC URL.
+fn ws_url(rpc: &str) -> String {
+    let rpc = rpc.trim_end_matches('/');
+    let rpc = match rpc.split_once("://") {
+        Some(("https", rest)) => format!("wss://{rest}"),
+        Some((_, rest)) => format!("ws://{rest}"),
+        None => format!("ws://{rpc}"),
+    };
+    format!("{rpc}/ws")
+}
+
+fn short(hash: &str) -> &str {
+    &hash[..hash.len().min(16)]
+}
+
+fn summary(event: &HeadEvent) -> String {
+    match event {
+        HeadEvent::NewHead {
+            height,
+            hash,
+            proposer,
+            gas_used,
+            tx_count,
+            timestamp,
+        } => format!(
+            "#{height} {} txs={tx_count} gas={gas_used} proposer={} ts={timestamp}",
+            short(hash),
+            short(proposer)
+        ),
+        HeadEvent::Reorg {
+            height,
+            old_head,
+            new_head,
+            orphaned,
+        } => format!(
+            "reorg from #{height}: {} -> {} ({} blocks orphaned)",
+            short(old_head),
+            short(new_head),
+            orphaned.len()
+        ),
+    }
+}
+
+pub fn run(rpc: &str, command: WatchCommands) -> anyhow::Result<()> {
+    match command {
+        WatchCommands::Blocks => {
+            let url = ws_url(rpc);
+            let (mut socket, _) =
+                tungstenite::connect(&url).with_context(|| format!("connecting to {url}"))?;
+            let subscribe = json!({ "method": "subscribe", "channel": "new_heads" });
+            socket.send(Message::Text(subscribe.to_string()))?;
+            loop {
+                let text = match socket.read().context("reading from node")? {
+                    Message::Text(text) => text,
+                    Message::Close(_) => return Ok(()),
+                    _ => continue,
+                };
+                let value: serde_json::Value =
+                    serde_json::from_str(&text).context("decoding node message")?;
+                if value.get("error").is_some() {
+                    // A slow reader gets `lagged` with a count of missed events.
+                    eprintln!("node error: {value}");
+                    continue;
+                }
+                if value.get("subscribed").is_some() {
+                    continue;
+                }
+                let push: Push =
+                    serde_json::from_value(value).context("decoding new_heads event")?;
+                println!("{}", summary(&push.event));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_ws_url_and_summarizes_heads() {
+        assert_eq!(ws_url("http://localhost:7000/"), "ws://localhost:7000/ws");
+        assert_eq!(ws_url("https://rpc.kova.dev"), "wss://rpc.kova.dev/ws");
+
+        let push: Push = serde_json::from_value(json!({
+            "subscription": 1,
+            "event": {
+                "channel": "new_head",
+                "height": 12,
+                "hash": "ab".repeat(32),
+                "parent_hash": "00".repeat(32),
+                "proposer": "cd".repeat(32),
+                "state_root": "00".repeat(32),
+                "gas_used": 42000,
+                "tx_count": 2,
+                "timestamp": 1700
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            summary(&push.event),
+            format!(
+                "#12 {} txs=2 gas=42000 proposer={} ts=1700",
+                "ab".repeat(8),
+                "cd".repeat(8)
+            )
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Writing Kova WASM contracts. A contract is a `cdylib` built for
+//! `wasm32-unknown-unknown` that exports its memory and functions taking no
+//! arguments; they read their input, storage and context through this
+//! crate, which wraps the runtime's `kova` host functions. Built for any
+//! other target the host functions are backed by the in-memory [`testing`]
+//! host, so contracts can be unit tested natively.
+//!
+//! Domains reject modules using floats or post-MVP proposals other than
+//! sign extension and multi-value, so build contracts with
+//! `-C target-cpu=mvp` and keep floats out of them.
+
+#![cfg_attr(target_arch = "wasm32", no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+/// A Kova account address.
+pub type Address = [u8; 32];
+
+#[cfg(target_arch = "wasm32")]
+mod host {
+    #[link(wasm_import_module = "kova")]
+    extern "C" {
+        pub fn storage_get(key_ptr: *const u8, key_len: u32, out_ptr: *mut u8, out_cap: u32)
+            -> i32;
+        pub fn storage_set(key_ptr: *const u8, key_len: u32, value_ptr: *const u8, value_len: u32);
+        pub fn emit_event(ptr: *const u8, len: u32);
+        pub fn caller(out_ptr: *mut u8);
+        pub fn block_height() -> u64;
+        pub fn transfer(to_ptr: *const u8, amount: u64) -> i32;
+        pub fn input(out_ptr: *mut u8, out_cap: u32) -> i32;
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+use testing as host;
+
+/// Reads a value the host copies out, retrying with a large enough buffer
+/// when the first read was short. `None` if the host has no value.
+fn read(copy: impl Fn(*mut u8, u32) -> i32) -> Option<Vec<u8>> {
+    let mut buf = Vec::with_capacity(64);
+    let len = usize::try_from(copy(buf.as_mut_ptr(), buf.capacity() as u32)).ok()?;
+    if len > buf.capacity() {
+        buf.reserve_exact(len);
+        copy(buf.as_mut_ptr(), len as u32);
+    }
+    // SAFETY: the host wrote `len` bytes, which the buffer has room for.
+    unsafe { buf.set_len(len) };
+    Some(buf)
+}
+
+/// The contract's value under `key`.
+pub fn get(key: &[u8]) -> Option<Vec<u8>> {
+    read(|ptr, cap| unsafe { host::storage_get(key.as_ptr(), key.len() as u32, ptr, cap) })
+}
+
+/// Stores `value` under `key`; an empty value removes the key.
+pub fn set(key: &[u8], value: &[u8]) {
+    unsafe {
+        host::storage_set(
+            key.as_ptr(),
+            key.len() as u32,
+            value.as_ptr(),
+            value.len() as u32,
+        )
+    }
+}
+
+pub fn remove(key: &[u8]) {
+    set(key, &[]);
+}
+
+/// Emits an event, which the receipt records as
+/// `wasm_event:<module id>:<hex data>`.
+pub fn emit(data: &[u8]) {
+    unsafe { host::emit_event(data.as_ptr(), data.len() as u32) }
+}
+
+/// The Kova account that submitted the call; zero for relayed messages.
+pub fn caller() -> Address {
+    let mut address = [0u8; 32];
+    unsafe { host::caller(address.as_mut_ptr()) };
+    address
+}
+
+pub fn block_height() -> u64 {
+    unsafe { host::block_height() }
+}
+
+/// Pays `amount` domain tokens from the contract's address to `to`; false
+/// if the contract can't cover it.
+pub fn transfer(to: &Address, amount: u64) -> bool {
+    unsafe { host::transfer(to.as_ptr(), amount) == 0 }
+}
+
+/// The call's input bytes.
+pub fn input() -> Vec<u8> {
+    read(|ptr, cap| unsafe { host::input(ptr, cap) }).unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod testing {
+    //! In-memory stand-in for the runtime's host functions, one per thread.
+
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use super::Address;
+
+    #[derive(Debug, Clone, Default)]
+    pub struct Host {
+        pub storage: HashMap<Vec<u8>, Vec<u8>>,
+        pub events: Vec<Vec<u8>>,
+        pub caller: Address,
+        pub block_height: u64,
+        pub input: Vec<u8>,
+        /// The contract's domain token balance.
+        pub balance: u64,
+        pub transfers: Vec<(Address, u64)>,
+    }
+
+    thread_local! {
+        static HOST: RefCell<Host> = RefCell::new(Host::default());
+    }
+
+    /// Runs `f` with this thread's host, to set up a call or inspect what
+    /// it did.
+    pub fn with_host<R>(f: impl FnOnce(&mut Host) -> R) -> R {
+        HOST.with(|host| f(&mut host.borrow_mut()))
+    }
+
+    unsafe fn bytes<'a>(ptr: *const u8, len: u32) -> &'a [u8] {
+        std::slice::from_raw_parts(ptr, len as usize)
+    }
+
+    unsafe fn copy_out(value: &[u8], out_ptr: *mut u8, out_cap: u32) -> i32 {
+        let n = value.len().min(out_cap as usize);
+        std::ptr::copy_nonoverlapping(value.as_ptr(), out_ptr, n);
+        value.len() as i32
+    }
+
+    pub(crate) unsafe fn storage_get(
+        key_ptr: *const u8,
+        key_len: u32,
+        out_ptr: *mut u8,
+        out_cap: u32,
+    ) -> i32 {
+        let key = bytes(key_ptr, key_len);
+        with_host(|host| match host.storage.get(key) {
+            Some(value) => copy_out(value, out_ptr, out_cap),
+            None => -1,
+        })
+    }
+
+    pub(crate) unsafe fn storage_set(
+        key_ptr: *const u8,
+        key_len: u32,
+        value_ptr: *const u8,
+        value_len: u32,
+    ) {
+        let key = bytes(key_ptr, key_len).to_vec();
+        let value = bytes(value_ptr, value_len).to_vec();
+        with_host(|host| {
+            if value.is_empty() {
+                host.storage.remove(&key);
+            } else {
+                host.storage.insert(key, value);
+            }
+        })
+    }
+
+    pub(crate) unsafe fn emit_event(ptr: *const u8, len: u32) {
+        let data = bytes(ptr, len).to_vec();
+        with_host(|host| host.events.push(data))
+    }
+
+    pub(crate) unsafe fn caller(out_ptr: *mut u8) {
+        let address = with_host(|host| host.caller);
+        copy_out(&address, out_ptr, 32);
+    }
+
+    pub(crate) unsafe fn block_height() -> u64 {
+        with_host(|host| host.block_height)
+    }
+
+    pub(crate) unsafe fn transfer(to_ptr: *const u8, amount: u64) -> i32 {
+        let to: Address = bytes(to_ptr, 32).try_into().unwrap();
+        with_host(|host| {
+            if host.balance < amount {
+                return 1;
+            }
+            host.balance -= amount;
+            host.transfers.push((to, amount));
+            0
+        })
+    }
+
+    pub(crate) unsafe fn input(out_ptr: *mut u8, out_cap: u32) -> i32 {
+        with_host(|host| copy_out(&host.input, out_ptr, out_cap))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::with_host;
+    use super::*;
+
+    #[test]
+    fn storage_round_trips_values_longer_than_the_first_read() {
+        let long = vec![7u8; 200];
+        set(b"k", &long);
+        assert_eq!(get(b"k"), Some(long));
+        remove(b"k");
+        assert_eq!(get(b"k"), None);
+    }
+
+    #[test]
+    fn calls_see_their_context_and_record_effects() {
+        with_host(|host| {
+            host.caller = [1u8; 32];
+            host.block_height = 9;
+            host.input = b"hello".to_vec();
+            host.balance = 5;
+        });
+        assert_eq!(caller(), [1u8; 32]);
+        assert_eq!(block_height(), 9);
+        assert_eq!(input(), b"hello");
+        emit(b"paid");
+        assert!(transfer(&[2u8; 32], 5));
+        assert!(!transfer(&[2u8; 32], 1));
+        with_host(|host| {
+            assert_eq!(host.events, vec![b"paid".to_vec()]);
+            assert_eq!(host.transfers, vec![([2u8; 32], 5)]);
+        });
+    }
+}
@@ -0,0 +1,217 @@
+//! Async client for the node's HTTP RPC.
+
+use std::time::Duration;
+
+use runtime::{Address, Block, ExecutionOutcome, Hash, Tx};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use state::{MerklePath, PrivacyPool, PrivacyPoolStats, Validator, VestingSchedule};
+use tokio::time::{sleep, Instant};
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("cannot reach node: {0}")]
+    Connection(#[from] reqwest::Error),
+    /// The node answered but refused the request.
+    #[error("node rejected request: {0}")]
+    Rejected(String),
+    #[error("tx {} not included within {timeout:?}", hex::encode(.tx_hash))]
+    Timeout { tx_hash: Hash, timeout: Duration },
+    #[error("unexpected response from node: {0}")]
+    InvalidResponse(String),
+}
+
+/// Outcome of an included tx. Failed txs are included and charged too, so
+/// check `success`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxReceipt {
+    pub height: u64,
+    pub success: bool,
+    #[serde(flatten)]
+    pub outcome: ExecutionOutcome,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeStatus {
+    /// Height of the next block.
+    pub height: u64,
+    pub mempool_len: usize,
+    pub view: u64,
+}
+
+/// An account's vesting as of `height`, the next block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VestingStatus {
+    pub height: u64,
+    pub vested: u128,
+    pub locked: u128,
+    pub schedules: Vec<VestingProgress>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VestingProgress {
+    pub schedule: VestingSchedule,
+    pub vested: u128,
+    pub locked: u128,
+}
+
+/// Hash the node indexes a tx under.
+pub fn tx_hash(tx: &Tx) -> Hash {
+    runtime::tx_hash(tx)
+}
+
+#[derive(Clone)]
+pub struct KovaClient {
+    http: reqwest::Client,
+    endpoint: String,
+    pub(crate) poll_interval: Duration,
+}
+
+impl KovaClient {
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self::with_http(endpoint, reqwest::Client::new())
+    }
+
+    /// Uses `http` for requests, e.g. one configured with timeouts or a proxy.
+    pub fn with_http(endpoint: impl Into<String>, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// How often `wait_for_inclusion` asks for the receipt.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Submits a signed tx to the mempool and returns its hash.
+    pub async fn send_tx(&self, tx: &Tx) -> Result<Hash, ClientError> {
+        let res = self
+            .http
+            .post(self.url("send_raw_tx"))
+            .json(&serde_json::json!({ "tx": tx }))
+            .send()
+            .await?;
+        let status: String = decode(res).await?;
+        if status != "ok" {
+            return Err(ClientError::Rejected(status));
+        }
+        Ok(tx_hash(tx))
+    }
+
+    /// Balance at the tip; 0 for accounts the chain hasn't seen.
+    pub async fn get_balance(&self, address: &Address) -> Result<u128, ClientError> {
+        let path = format!("get_balance/{}", hex::encode(address));
+        Ok(self.get::<Option<u128>>(&path).await?.unwrap_or(0))
+    }
+
+    /// Nonce the account's next tx must carry.
+    pub async fn get_nonce(&self, address: &Address) -> Result<u64, ClientError> {
+        let path = format!("get_nonce/{}", hex::encode(address));
+        Ok(self.get::<Option<u64>>(&path).await?.unwrap_or(0))
+    }
+
+    /// Vesting progress as of the next block; accounts without schedules
+    /// report nothing locked.
+    pub async fn get_vesting(&self, address: &Address) -> Result<VestingStatus, ClientError> {
+        let path = format!("get_vesting/{}", hex::encode(address));
+        self.get::<Option<VestingStatus>>(&path)
+            .await?
+            .ok_or_else(|| ClientError::InvalidResponse("address rejected".into()))
+    }
+
+    /// Membership path of a shielded note's commitment against the current
+    /// root of `pool` (the default pool when `None`); `None` if the
+    /// commitment isn't in the pool.
+    pub async fn get_privacy_witness(
+        &self,
+        pool: Option<&str>,
+        commitment: &Hash,
+    ) -> Result<Option<MerklePath>, ClientError> {
+        let mut path = format!("privacy/witness/{}", hex::encode(commitment));
+        if let Some(pool) = pool {
+            path.push_str(&format!("?pool={pool}"));
+        }
+        self.get(&path).await
+    }
+
+    /// Full state of `pool` (the default pool when `None`), commitments and
+    /// spent nullifiers included.
+    pub async fn get_privacy_pool(
+        &self,
+        pool: Option<&str>,
+    ) -> Result<Option<PrivacyPool>, ClientError> {
+        match pool {
+            Some(pool) => self.get(&format!("privacy/pool?pool={pool}")).await,
+            None => self.get("privacy/pool").await,
+        }
+    }
+
+    /// Deposit and withdrawal counts of every privacy pool, by pool id.
+    pub async fn get_privacy_pools(&self) -> Result<Vec<PrivacyPoolStats>, ClientError> {
+        self.get("privacy/pools").await
+    }
+
+    pub async fn get_block(&self, height: u64) -> Result<Option<Block>, ClientError> {
+        self.get(&format!("get_block/{height}")).await
+    }
+
+    /// `None` until the tx is included.
+    pub async fn get_receipt(&self, tx_hash: &Hash) -> Result<Option<TxReceipt>, ClientError> {
+        self.get(&format!("get_receipt/{}", hex::encode(tx_hash)))
+            .await
+    }
+
+    pub async fn get_validators(&self) -> Result<Vec<Validator>, ClientError> {
+        self.get("get_validators").await
+    }
+
+    pub async fn status(&self) -> Result<NodeStatus, ClientError> {
+        self.get("status").await
+    }
+
+    /// Polls for the receipt of `tx_hash` until it shows up or `timeout`
+    /// passes. Inclusion is not success: the receipt may record a failure.
+    pub async fn wait_for_inclusion(
+        &self,
+        tx_hash: &Hash,
+        timeout: Duration,
+    ) -> Result<TxReceipt, ClientError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(receipt) = self.get_receipt(tx_hash).await? {
+                return Ok(receipt);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(ClientError::Timeout {
+                    tx_hash: *tx_hash,
+                    timeout,
+                });
+            }
+            sleep(self.poll_interval.min(deadline - now)).await;
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{path}", self.endpoint)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        decode(self.http.get(self.url(path)).send().await?).await
+    }
+}
+
+async fn decode<T: DeserializeOwned>(res: reqwest::Response) -> Result<T, ClientError> {
+    let status = res.status();
+    let body = res.text().await?;
+    if !status.is_success() {
+        return Err(ClientError::Rejected(format!("{status}: {body}")));
+    }
+    serde_json::from_str(&body).map_err(|e| ClientError::InvalidResponse(e.to_string()))
+}
@@ -0,0 +1,97 @@
+//! SLIP-0010 ed25519 key derivation for exchange deposit sub-accounts.
+//!
+//! Every customer gets a hardened path `m/44'/<coin>'/<account>'/0'/<customer>'`
+//! under a single seed, so one backup covers all deposit addresses.
+
+use ed25519_dalek::SigningKey;
+use hmac::{Hmac, Mac};
+use runtime::address_from_pubkey;
+use sha2::Sha512;
+
+pub const KOVA_COIN_TYPE: u32 = 7777;
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+type HmacSha512 = Hmac<Sha512>;
+
+fn hmac_sha512(key: &[u8], parts: &[&[u8]]) -> [u8; 64] {
+    let mut mac = HmacSha512::new_from_slice(key).expect("hmac accepts any key length");
+    for part in parts {
+        mac.update(part);
+    }
+    let mut out = [0u8; 64];
+    out.copy_from_slice(&mac.finalize().into_bytes());
+    out
+}
+
+#[derive(Clone)]
+pub struct ExtendedKey {
+    key: [u8; 32],
+    chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    pub fn from_seed(seed: &[u8]) -> anyhow::Result<Self> {
+        if !(16..=64).contains(&seed.len()) {
+            anyhow::bail!("seed must be between 16 and 64 bytes");
+        }
+        Ok(Self::split(hmac_sha512(b"ed25519 seed", &[seed])))
+    }
+
+    fn split(bytes: [u8; 64]) -> Self {
+        let mut key = [0u8; 32];
+        let mut chain_code = [0u8; 32];
+        key.copy_from_slice(&bytes[..32]);
+        chain_code.copy_from_slice(&bytes[32..]);
+        Self { key, chain_code }
+    }
+
+    /// Derives a hardened child; ed25519 has no non-hardened derivation, so
+    /// the hardened bit is always set.
+    pub fn derive_child(&self, index: u32) -> Self {
+        let index = (index | HARDENED_OFFSET).to_be_bytes();
+        Self::split(hmac_sha512(&self.chain_code, &[&[0u8], &self.key, &index]))
+    }
+
+    pub fn derive_path(&self, path: &[u32]) -> Self {
+        path.iter()
+            .fold(self.clone(), |key, idx| key.derive_child(*idx))
+    }
+
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.key
+    }
+
+    pub fn chain_code(&self) -> [u8; 32] {
+        self.chain_code
+    }
+
+    pub fn signing_key(&self) -> SigningKey {
+        SigningKey::from_bytes(&self.key)
+    }
+}
+
+pub fn deposit_path(account: u32, customer: u32) -> [u32; 5] {
+    [44, KOVA_COIN_TYPE, account, 0, customer]
+}
+
+/// Derives per-customer deposit keys under one exchange account.
+#[derive(Clone)]
+pub struct DepositKeyring {
+    account_key: ExtendedKey,
+}
+
+impl DepositKeyring {
+    pub fn new(seed: &[u8], account: u32) -> anyhow::Result<Self> {
+        let account_key =
+            ExtendedKey::from_seed(seed)?.derive_path(&[44, KOVA_COIN_TYPE, account, 0]);
+        Ok(Self { account_key })
+    }
+
+    pub fn signing_key(&self, customer: u32) -> SigningKey {
+        self.account_key.derive_child(customer).signing_key()
+    }
+
+    pub fn address(&self, customer: u32) -> [u8; 32] {
+        address_from_pubkey(&self.signing_key(customer).verifying_key().to_bytes())
+    }
+}
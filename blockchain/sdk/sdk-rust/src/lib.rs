@@ -1,24 +1,69 @@
-use ed25519_dalek::SigningKey;
 use serde_json;
 use uuid;
 use runtime::{
-    sign_bytes, tx_signing_bytes, CrossDomainMessage, DomainCall, Tx, TxPayload,
+    gas_cost, Address, CrossDomainMessage, CrossDomainPacket, DomainCall, DomainProof,
+    FeeSuggestion, GasEstimate, Hash, HeaderSignature, LightClientHeader, Tx, TxPayload,
+    ValidatorSet,
 };
 
+pub mod client;
+pub mod hd;
+pub mod notes;
+pub mod signer;
+pub mod sweep;
+pub mod wallet;
+
+pub use client::{
+    tx_hash, ClientError, KovaClient, NodeStatus, TxReceipt, VestingProgress, VestingStatus,
+};
+pub use hd::{deposit_path, DepositKeyring, ExtendedKey, KOVA_COIN_TYPE};
+pub use notes::{NoteScanner, OwnedNote};
+pub use runtime::Preconfirmation;
+pub use signer::{sign_tx, LedgerSigner, LedgerTransport, RemoteSigner, Signer};
+pub use sweep::{SweepBuilder, SweepInput};
+pub use wallet::Wallet;
+pub use state::{MerklePath, MultisigCall, PrivacyPoolStats, VestingSchedule, VoteChoice};
+pub use zk_core::ProofArtifact;
+pub use zk_program_privacy::{EncryptedNote, NotePlaintext, PrivacyWithdrawInput, ViewingKey};
+
 pub async fn send_raw_tx(endpoint: &str, tx: &Tx) -> anyhow::Result<()> {
-    let _ = (endpoint, tx);
-    // Placeholder: serialize and POST to node RPC.
+    KovaClient::new(endpoint).send_tx(tx).await?;
     Ok(())
 }
 
-pub fn build_transfer_signed(
+/// Simulates `payload` from `from` on the node; use `gas_limit` from the
+/// result when building the transaction.
+pub async fn estimate_gas(
+    endpoint: &str,
+    from: &Address,
+    payload: &TxPayload,
+) -> anyhow::Result<GasEstimate> {
+    let url = format!("{}/estimate_gas", endpoint.trim_end_matches('/'));
+    let res = reqwest::Client::new()
+        .post(url)
+        .json(&serde_json::json!({ "from": hex::encode(from), "payload": payload }))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        let status = res.status();
+        anyhow::bail!("estimate_gas failed ({status}): {}", res.text().await?);
+    }
+    Ok(res.json().await?)
+}
+
+/// Fee tiers derived from tips paid in the node's recent blocks.
+pub async fn fee_suggestion(endpoint: &str) -> anyhow::Result<FeeSuggestion> {
+    let url = format!("{}/fee_suggestion", endpoint.trim_end_matches('/'));
+    Ok(reqwest::get(url).await?.error_for_status()?.json().await?)
+}
+
+pub fn build_transfer_signed<S: Signer + ?Sized>(
     chain_id: &str,
     to: [u8; 32],
     amount: u128,
-    signing_key: &SigningKey,
+    signer: &S,
     nonce: u64,
 ) -> anyhow::Result<Tx> {
-    let public_key = signing_key.verifying_key().to_bytes().to_vec();
     let mut tx = Tx {
         chain_id: chain_id.to_string(),
         nonce,
@@ -27,22 +72,20 @@ pub fn build_transfer_signed(
         max_priority_fee: Some(0),
         gas_price: None,
         payload: TxPayload::Transfer { to, amount },
-        public_key: public_key.clone(),
+        public_key: vec![],
         signature: vec![],
     };
-    let bytes = tx_signing_bytes(&tx)?;
-    tx.signature = sign_bytes(signing_key, &bytes);
+    sign_tx(&mut tx, signer)?;
     Ok(tx)
 }
 
-pub fn build_domain_execute_signed(
+pub fn build_domain_execute_signed<S: Signer + ?Sized>(
     chain_id: &str,
     call: DomainCall,
-    signing_key: &SigningKey,
+    signer: &S,
     nonce: u64,
     gas_limit: u64,
 ) -> anyhow::Result<Tx> {
-    let public_key = signing_key.verifying_key().to_bytes().to_vec();
     let mut tx = Tx {
         chain_id: chain_id.to_string(),
         nonce,
@@ -51,24 +94,22 @@ pub fn build_domain_execute_signed(
         max_priority_fee: Some(0),
         gas_price: None,
         payload: TxPayload::DomainExecute(call),
-        public_key: public_key.clone(),
+        public_key: vec![],
         signature: vec![],
     };
-    let bytes = tx_signing_bytes(&tx)?;
-    tx.signature = sign_bytes(signing_key, &bytes);
+    sign_tx(&mut tx, signer)?;
     Ok(tx)
 }
 
-pub fn build_cross_domain_send_signed(
+pub fn build_cross_domain_send_signed<S: Signer + ?Sized>(
     chain_id: &str,
     from_domain: uuid::Uuid,
     to_domain: uuid::Uuid,
     payload: serde_json::Value,
     fee: u128,
-    signing_key: &SigningKey,
+    signer: &S,
     nonce: u64,
 ) -> anyhow::Result<Tx> {
-    let public_key = signing_key.verifying_key().to_bytes().to_vec();
     let mut tx = Tx {
         chain_id: chain_id.to_string(),
         nonce,
@@ -82,21 +123,22 @@ pub fn build_cross_domain_send_signed(
             payload,
             fee,
         },
-        public_key: public_key.clone(),
+        public_key: vec![],
         signature: vec![],
     };
-    let bytes = tx_signing_bytes(&tx)?;
-    tx.signature = sign_bytes(signing_key, &bytes);
+    sign_tx(&mut tx, signer)?;
     Ok(tx)
 }
 
-pub fn build_cross_domain_relay_signed(
+/// Relays `message` with `proof` of it against its source domain's
+/// committed root, as the node's outbox pages serve them.
+pub fn build_cross_domain_relay_signed<S: Signer + ?Sized>(
     chain_id: &str,
     message: CrossDomainMessage,
-    signing_key: &SigningKey,
+    proof: DomainProof,
+    signer: &S,
     nonce: u64,
 ) -> anyhow::Result<Tx> {
-    let public_key = signing_key.verifying_key().to_bytes().to_vec();
     let mut tx = Tx {
         chain_id: chain_id.to_string(),
         nonce,
@@ -104,12 +146,611 @@ pub fn build_cross_domain_relay_signed(
         max_fee: Some(1),
         max_priority_fee: Some(0),
         gas_price: None,
-        payload: TxPayload::CrossDomainRelay { message },
-        public_key: public_key.clone(),
+        payload: TxPayload::CrossDomainRelay { message, proof },
+        public_key: vec![],
+        signature: vec![],
+    };
+    sign_tx(&mut tx, signer)?;
+    Ok(tx)
+}
+
+/// Refunds the fee of a message `from_domain` sent to `to_domain` that
+/// timed out undelivered; the fee goes to whoever paid it, not the signer.
+pub fn build_cross_domain_refund_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    from_domain: uuid::Uuid,
+    to_domain: uuid::Uuid,
+    nonce: u64,
+    signer: &S,
+    tx_nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::CrossDomainRefund {
+        from_domain,
+        to_domain,
+        nonce,
+    };
+    build_signed(chain_id, payload, signer, tx_nonce)
+}
+
+/// Signs `payload` with a gas limit of exactly what the runtime charges.
+fn build_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    payload: TxPayload,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let mut tx = Tx {
+        chain_id: chain_id.to_string(),
+        nonce,
+        gas_limit: gas_cost(&payload),
+        max_fee: Some(1),
+        max_priority_fee: Some(0),
+        gas_price: None,
+        payload,
+        public_key: vec![],
         signature: vec![],
     };
-    let bytes = tx_signing_bytes(&tx)?;
-    tx.signature = sign_bytes(signing_key, &bytes);
+    sign_tx(&mut tx, signer)?;
     Ok(tx)
 }
 
+/// Stakes `amount` as the signer's validator, creating it on first use.
+pub fn build_stake_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    amount: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    build_signed(chain_id, TxPayload::Stake { amount }, signer, nonce)
+}
+
+pub fn build_unstake_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    amount: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    build_signed(chain_id, TxPayload::Unstake { amount }, signer, nonce)
+}
+
+/// `validator` is the validator's owner address.
+pub fn build_delegate_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    validator: Address,
+    amount: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::Delegate { validator, amount };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_undelegate_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    validator: Address,
+    amount: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::Undelegate { validator, amount };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Withdraws the rewards accrued by the signer's delegation to `validator`.
+pub fn build_claim_rewards_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    validator: Address,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    build_signed(chain_id, TxPayload::ClaimRewards { validator }, signer, nonce)
+}
+
+/// Edits the signer's validator; `None` fields are left unchanged.
+pub fn build_validator_edit_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    commission_rate: Option<u8>,
+    moniker: Option<String>,
+    website: Option<String>,
+    details: Option<String>,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::ValidatorEdit {
+        commission_rate,
+        moniker,
+        website,
+        details,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Returns the signer's validator to the active set after a downtime jail.
+pub fn build_unjail_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    build_signed(chain_id, TxPayload::Unjail, signer, nonce)
+}
+
+/// A `penalty_bps` of 0 applies the chain's default penalty.
+pub fn build_slash_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    validator: Address,
+    penalty_bps: u16,
+    reason: Option<String>,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::Slash {
+        validator,
+        penalty_bps,
+        reason,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Shields `amount` under `commitment`, e.g. from
+/// `zk_program_privacy::note_commitment`. `pool` defaults to
+/// `state::DEFAULT_PRIVACY_POOL`; fixed-denomination pools take only their
+/// denomination.
+pub fn build_privacy_deposit_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    pool: Option<String>,
+    commitment: Hash,
+    amount: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::PrivacyDeposit {
+        commitment,
+        amount,
+        pool,
+        memo: None,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Shields `note` and attaches it sealed to `viewing_public_key`, so the
+/// owner's wallet finds it when scanning with a `NoteScanner`.
+pub fn build_privacy_note_deposit_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    pool: Option<String>,
+    note: &NotePlaintext,
+    viewing_public_key: &Hash,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::PrivacyDeposit {
+        commitment: note.commitment(),
+        amount: note.amount,
+        pool,
+        memo: Some(zk_program_privacy::encrypt_note(viewing_public_key, note)?),
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Withdraws the note described by `input` with `proof` attached. The proof
+/// must commit to `input`, fees and relayer included, or the chain rejects
+/// it. With `input.relayer` set, `signer` can be any relayer's key: the
+/// relayer pays gas and is repaid `input.relayer_fee` from the note.
+pub fn build_privacy_withdraw_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    pool: Option<String>,
+    input: &PrivacyWithdrawInput,
+    proof: ProofArtifact,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    if proof.program_id != zk_program_privacy::program_id() {
+        anyhow::bail!("proof is not for the privacy withdraw program");
+    }
+    let expected = bincode::serialize(&zk_program_privacy::commitments(input))?;
+    let actual = proof.commitments.as_ref().map(bincode::serialize).transpose()?;
+    if actual.as_ref() != Some(&expected) {
+        anyhow::bail!("proof does not commit to this withdrawal");
+    }
+    let payload = TxPayload::PrivacyWithdraw {
+        nullifier: input.nullifier,
+        recipient: input.recipient,
+        amount: input.amount,
+        merkle_root: input.merkle_root,
+        commitment: input.commitment,
+        proof,
+        pool,
+        relayer: input.relayer,
+        relayer_fee: input.relayer_fee,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// `kind` defaults to `general` on chain.
+pub fn build_governance_proposal_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    payload: serde_json::Value,
+    kind: Option<String>,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::GovernanceProposal { payload, kind };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_governance_vote_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    proposal_id: uuid::Uuid,
+    support: VoteChoice,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::GovernanceVote {
+        proposal_id,
+        support,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_governance_execute_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    proposal_id: uuid::Uuid,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::GovernanceExecute { proposal_id };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Only the proposer may cancel, and only while voting is open.
+pub fn build_governance_cancel_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    proposal_id: uuid::Uuid,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::GovernanceCancel { proposal_id };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// The new asset's id is `state::asset_id(signer address, nonce)`.
+pub fn build_asset_create_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    symbol: String,
+    decimals: u8,
+    max_supply: Option<u128>,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::AssetCreate {
+        symbol,
+        decimals,
+        max_supply,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Only the asset's issuer may mint.
+pub fn build_asset_mint_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    asset_id: uuid::Uuid,
+    to: Address,
+    amount: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::AssetMint {
+        asset_id,
+        to,
+        amount,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_asset_transfer_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    asset_id: uuid::Uuid,
+    to: Address,
+    amount: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::AssetTransfer {
+        asset_id,
+        to,
+        amount,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// The multisig's address is `state::multisig_address(signer address, nonce)`.
+pub fn build_multisig_create_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    signers: Vec<Address>,
+    threshold: u16,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::MultisigCreate { signers, threshold };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_multisig_submit_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    multisig: Address,
+    call: MultisigCall,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::MultisigSubmit { multisig, call };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_multisig_approve_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    multisig: Address,
+    proposal_id: u64,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::MultisigApprove {
+        multisig,
+        proposal_id,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_multisig_execute_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    multisig: Address,
+    proposal_id: u64,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::MultisigExecute {
+        multisig,
+        proposal_id,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Runs `inner` from the signer at `execute_at_height`; its gas is charged
+/// then, at that block's base fee.
+pub fn build_schedule_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    execute_at_height: u64,
+    inner: TxPayload,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::Schedule {
+        execute_at_height,
+        inner: Box::new(inner),
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_schedule_cancel_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    schedule_id: u64,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::ScheduleCancel { schedule_id };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_vesting_create_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    beneficiary: Address,
+    amount: u128,
+    cliff_height: u64,
+    end_height: u64,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::VestingCreate {
+        beneficiary,
+        amount,
+        cliff_height,
+        end_height,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_rollup_bridge_deposit_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    domain_id: uuid::Uuid,
+    amount: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::RollupBridgeDeposit { domain_id, amount };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_rollup_bridge_burn_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    domain_id: uuid::Uuid,
+    amount: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::RollupBridgeBurn { domain_id, amount };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Withdraws burn `burn_nonce` with `proof` of it under the domain's
+/// committed root.
+pub fn build_rollup_bridge_withdraw_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    domain_id: uuid::Uuid,
+    burn_nonce: u64,
+    amount: u128,
+    proof: DomainProof,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::RollupBridgeWithdraw {
+        domain_id,
+        burn_nonce,
+        amount,
+        proof,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Exits a validity domain with `proof` of the signer's `balance` under the
+/// domain's last proven root.
+pub fn build_forced_withdraw_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    domain_id: uuid::Uuid,
+    balance: u128,
+    proof: DomainProof,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::ForcedWithdraw {
+        domain_id,
+        balance,
+        proof,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_sequencer_register_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    domain_id: uuid::Uuid,
+    bond: u128,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::SequencerRegister { domain_id, bond };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_sequencer_exit_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    domain_id: uuid::Uuid,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::SequencerExit { domain_id };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_preconfirmation_challenge_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    preconfirmation: Preconfirmation,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::PreconfirmationChallenge { preconfirmation };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Queues `forced`, a signed call on `domain_id`, for the domain's batches
+/// to carry.
+pub fn build_force_include_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    domain_id: uuid::Uuid,
+    forced: &Tx,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::ForceInclude {
+        domain_id,
+        tx_bytes: serde_json::to_vec(forced)?,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_force_include_prove_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    domain_id: uuid::Uuid,
+    tx_hash: Hash,
+    batch_height: u64,
+    blob: Vec<u8>,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::ForceIncludeProve {
+        domain_id,
+        tx_hash,
+        batch_height,
+        blob,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Answers a challenge of the sequencer's pre-confirmation of `tx_hash`
+/// with the batch blob carrying it.
+pub fn build_preconfirmation_fulfill_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    tx_hash: Hash,
+    batch_height: u64,
+    blob: Vec<u8>,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::PreconfirmationFulfill {
+        tx_hash,
+        batch_height,
+        blob,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_light_client_create_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    client_id: String,
+    external_chain_id: String,
+    header: LightClientHeader,
+    validators: ValidatorSet,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::LightClientCreate {
+        client_id,
+        chain_id: external_chain_id,
+        header,
+        validators,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+pub fn build_light_client_update_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    client_id: String,
+    header: LightClientHeader,
+    signatures: Vec<HeaderSignature>,
+    next_validators: Option<ValidatorSet>,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::LightClientUpdate {
+        client_id,
+        header,
+        signatures,
+        next_validators,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
+
+/// Delivers `packet`, proven against the client's header at `height`.
+pub fn build_light_client_packet_signed<S: Signer + ?Sized>(
+    chain_id: &str,
+    client_id: String,
+    height: u64,
+    packet: CrossDomainPacket,
+    proof: DomainProof,
+    signer: &S,
+    nonce: u64,
+) -> anyhow::Result<Tx> {
+    let payload = TxPayload::LightClientPacket {
+        client_id,
+        height,
+        packet,
+        proof,
+    };
+    build_signed(chain_id, payload, signer, nonce)
+}
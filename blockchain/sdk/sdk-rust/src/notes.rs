@@ -0,0 +1,103 @@
+//! Rebuilds a shielded balance from a viewing key by scanning blocks for
+//! deposits whose memo it can open.
+
+use std::collections::HashMap;
+
+use runtime::{Block, Hash, TxPayload};
+use state::{PrivacyPool, DEFAULT_PRIVACY_POOL};
+use zk_program_privacy::{NotePlaintext, ViewingKey};
+
+use crate::client::{ClientError, KovaClient};
+
+/// A note the viewing key opened, and where it was deposited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedNote {
+    pub pool: String,
+    pub height: u64,
+    pub commitment: Hash,
+    pub note: NotePlaintext,
+}
+
+pub struct NoteScanner {
+    viewing_key: ViewingKey,
+    notes: Vec<OwnedNote>,
+}
+
+impl NoteScanner {
+    pub fn new(viewing_key: ViewingKey) -> Self {
+        Self {
+            viewing_key,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Records the notes in `block` sealed to this key; returns how many.
+    pub fn scan_block(&mut self, block: &Block) -> usize {
+        let before = self.notes.len();
+        for tx in &block.transactions {
+            let TxPayload::PrivacyDeposit {
+                commitment,
+                pool,
+                memo: Some(memo),
+                ..
+            } = &tx.payload
+            else {
+                continue;
+            };
+            if self.notes.iter().any(|n| n.commitment == *commitment) {
+                continue;
+            }
+            if let Some(note) = self.viewing_key.decrypt(memo, commitment) {
+                self.notes.push(OwnedNote {
+                    pool: pool.as_deref().unwrap_or(DEFAULT_PRIVACY_POOL).to_string(),
+                    height: block.header.height,
+                    commitment: *commitment,
+                    note,
+                });
+            }
+        }
+        self.notes.len() - before
+    }
+
+    /// Fetches and scans blocks `from..=to`, skipping heights the node does
+    /// not have.
+    pub async fn sync(
+        &mut self,
+        client: &KovaClient,
+        from: u64,
+        to: u64,
+    ) -> Result<usize, ClientError> {
+        let mut found = 0;
+        for height in from..=to {
+            if let Some(block) = client.get_block(height).await? {
+                found += self.scan_block(&block);
+            }
+        }
+        Ok(found)
+    }
+
+    pub fn notes(&self) -> &[OwnedNote] {
+        &self.notes
+    }
+
+    /// Notes that made it into their pool and whose nullifier is not spent
+    /// there. A deposit that failed on chain never reaches the pool, so it is
+    /// left out even though its memo was found.
+    pub fn spendable<'a>(&'a self, pools: &HashMap<String, PrivacyPool>) -> Vec<&'a OwnedNote> {
+        self.notes
+            .iter()
+            .filter(|owned| {
+                pools.get(&owned.pool).is_some_and(|pool| {
+                    pool.commitments.contains(&owned.commitment)
+                        && !pool.nullifiers.contains(&owned.note.nullifier)
+                })
+            })
+            .collect()
+    }
+
+    pub fn balance(&self, pools: &HashMap<String, PrivacyPool>) -> u128 {
+        self.spendable(pools)
+            .iter()
+            .fold(0u128, |acc, owned| acc.saturating_add(owned.note.amount))
+    }
+}
@@ -0,0 +1,253 @@
+//! Where tx signatures come from. Builders and `Wallet` take any `Signer`, so
+//! a key can live in memory, behind a remote signing service or on a Ledger.
+
+use std::time::Duration;
+
+use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+use runtime::{address_from_pubkey, sign_bytes, tx_signing_bytes, Address, Tx};
+use serde::Deserialize;
+
+use crate::hd::{HARDENED_OFFSET, KOVA_COIN_TYPE};
+
+/// An ed25519 key that can sign tx bytes. The public key is known up front;
+/// signing may fail, e.g. when a device is unplugged or the user declines.
+pub trait Signer {
+    fn public_key(&self) -> [u8; 32];
+
+    fn sign(&self, message: &[u8]) -> anyhow::Result<Vec<u8>>;
+
+    fn address(&self) -> Address {
+        address_from_pubkey(&self.public_key())
+    }
+}
+
+impl Signer for SigningKey {
+    fn public_key(&self) -> [u8; 32] {
+        self.verifying_key().to_bytes()
+    }
+
+    fn sign(&self, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+        Ok(sign_bytes(self, message))
+    }
+}
+
+impl<S: Signer + ?Sized> Signer for &S {
+    fn public_key(&self) -> [u8; 32] {
+        (**self).public_key()
+    }
+
+    fn sign(&self, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+        (**self).sign(message)
+    }
+}
+
+impl<S: Signer + ?Sized> Signer for Box<S> {
+    fn public_key(&self) -> [u8; 32] {
+        (**self).public_key()
+    }
+
+    fn sign(&self, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+        (**self).sign(message)
+    }
+}
+
+/// Sets `tx.public_key` to the signer's and signs the result.
+pub fn sign_tx<S: Signer + ?Sized>(tx: &mut Tx, signer: &S) -> anyhow::Result<()> {
+    tx.public_key = signer.public_key().to_vec();
+    tx.signature = signer.sign(&tx_signing_bytes(tx)?)?;
+    Ok(())
+}
+
+/// Rejects signatures that don't verify, so a misbehaving external signer is
+/// caught before the tx reaches a node.
+fn checked(public_key: &[u8; 32], message: &[u8], signature: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let key = VerifyingKey::from_bytes(public_key)?;
+    let sig = Signature::from_slice(&signature)?;
+    key.verify_strict(message, &sig)
+        .map_err(|_| anyhow::anyhow!("signer returned an invalid signature"))?;
+    Ok(signature)
+}
+
+/// Signs through an HTTP signing service holding the key:
+/// `GET {url}/public_key` answers `{"public_key": "<hex>"}` and
+/// `POST {url}/sign` with `{"message": "<hex>"}` answers
+/// `{"signature": "<hex>"}`.
+///
+/// Requests are blocking and run on their own thread, so signing is safe,
+/// if not free, from async code.
+pub struct RemoteSigner {
+    url: String,
+    auth_token: Option<String>,
+    timeout: Duration,
+    public_key: [u8; 32],
+}
+
+#[derive(Deserialize)]
+struct PublicKeyResponse {
+    public_key: String,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    signature: String,
+}
+
+impl RemoteSigner {
+    /// Fetches the service's public key.
+    pub fn connect(url: impl Into<String>, auth_token: Option<String>) -> anyhow::Result<Self> {
+        let mut signer = Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            auth_token,
+            timeout: Duration::from_secs(30),
+            public_key: [0u8; 32],
+        };
+        let res: PublicKeyResponse = signer.request("public_key", None)?;
+        signer.public_key = hex::decode(&res.public_key)?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("remote public key must be 32 bytes"))?;
+        Ok(signer)
+    }
+
+    /// How long one signing request may take, e.g. while an operator
+    /// approves it.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn request<T: serde::de::DeserializeOwned + Send>(
+        &self,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> anyhow::Result<T> {
+        // The blocking client must not be created or dropped on an async
+        // runtime thread.
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let http = reqwest::blocking::Client::builder()
+                        .timeout(self.timeout)
+                        .build()?;
+                    let url = format!("{}/{path}", self.url);
+                    let mut req = match &body {
+                        Some(body) => http.post(&url).json(body),
+                        None => http.get(&url),
+                    };
+                    if let Some(token) = &self.auth_token {
+                        req = req.bearer_auth(token);
+                    }
+                    let res = req.send()?;
+                    let status = res.status();
+                    if !status.is_success() {
+                        anyhow::bail!("signer refused ({status}): {}", res.text()?);
+                    }
+                    Ok(res.json()?)
+                })
+                .join()
+                .map_err(|_| anyhow::anyhow!("signer request panicked"))?
+        })
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+
+    fn sign(&self, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let body = serde_json::json!({ "message": hex::encode(message) });
+        let res: SignResponse = self.request("sign", Some(body))?;
+        checked(&self.public_key, message, hex::decode(&res.signature)?)
+    }
+}
+
+/// Carries APDUs to a Ledger device, e.g. over USB HID with the
+/// `ledger-transport-hid` crate. Returns the response with its status word.
+pub trait LedgerTransport {
+    fn exchange(&self, apdu: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+const LEDGER_CLA: u8 = 0xe0;
+const INS_GET_PUBLIC_KEY: u8 = 0x02;
+const INS_SIGN: u8 = 0x03;
+const P1_FIRST: u8 = 0x00;
+const P1_MORE: u8 = 0x01;
+const P2_LAST: u8 = 0x00;
+const P2_MORE: u8 = 0x80;
+const SW_OK: u16 = 0x9000;
+const SW_DENIED: u16 = 0x6985;
+const MAX_APDU_DATA: usize = 255;
+
+/// Signs on a Ledger running the Kova app, with the key at
+/// `m/44'/<coin>'/<account>'/0'/<index>'`. The device shows the tx and
+/// signs only once the user approves it.
+pub struct LedgerSigner<T> {
+    transport: T,
+    path: Vec<u32>,
+    public_key: [u8; 32],
+}
+
+impl<T: LedgerTransport> LedgerSigner<T> {
+    /// Reads the public key for the path from the device.
+    pub fn new(transport: T, account: u32, index: u32) -> anyhow::Result<Self> {
+        let mut signer = Self {
+            transport,
+            path: vec![44, KOVA_COIN_TYPE, account, 0, index],
+            public_key: [0u8; 32],
+        };
+        let res = signer.exchange(INS_GET_PUBLIC_KEY, P1_FIRST, P2_LAST, &signer.path_bytes())?;
+        signer.public_key = res
+            .get(..32)
+            .and_then(|pk| pk.try_into().ok())
+            .ok_or_else(|| anyhow::anyhow!("device returned a short public key"))?;
+        Ok(signer)
+    }
+
+    fn path_bytes(&self) -> Vec<u8> {
+        let mut out = vec![self.path.len() as u8];
+        for index in &self.path {
+            out.extend_from_slice(&(index | HARDENED_OFFSET).to_be_bytes());
+        }
+        out
+    }
+
+    fn exchange(&self, ins: u8, p1: u8, p2: u8, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut apdu = vec![LEDGER_CLA, ins, p1, p2, data.len() as u8];
+        apdu.extend_from_slice(data);
+        let mut res = self.transport.exchange(&apdu)?;
+        if res.len() < 2 {
+            anyhow::bail!("device returned no status word");
+        }
+        let sw = res.split_off(res.len() - 2);
+        match u16::from_be_bytes([sw[0], sw[1]]) {
+            SW_OK => Ok(res),
+            SW_DENIED => anyhow::bail!("signing was rejected on the device"),
+            sw => anyhow::bail!("device returned status {sw:#06x}"),
+        }
+    }
+}
+
+impl<T: LedgerTransport> Signer for LedgerSigner<T> {
+    fn public_key(&self) -> [u8; 32] {
+        self.public_key
+    }
+
+    /// Sends the path, then the message in chunks that fit one APDU; the
+    /// signature comes back with the last chunk.
+    fn sign(&self, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut payload = self.path_bytes();
+        payload.extend_from_slice(message);
+        let chunks: Vec<&[u8]> = payload.chunks(MAX_APDU_DATA).collect();
+        let mut res = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let p1 = if i == 0 { P1_FIRST } else { P1_MORE };
+            let p2 = if i + 1 == chunks.len() {
+                P2_LAST
+            } else {
+                P2_MORE
+            };
+            res = self.exchange(INS_SIGN, p1, p2, chunk)?;
+        }
+        checked(&self.public_key, message, res)
+    }
+}
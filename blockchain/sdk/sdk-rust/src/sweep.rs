@@ -0,0 +1,94 @@
+//! Consolidates deposit sub-account balances into a hot wallet.
+
+use ed25519_dalek::SigningKey;
+use runtime::{Tx, TxPayload};
+
+use crate::signer::{sign_tx, Signer};
+
+const TRANSFER_GAS: u64 = 21_000;
+
+pub struct SweepInput<S = SigningKey> {
+    pub signing_key: S,
+    pub balance: u128,
+    pub nonce: u64,
+}
+
+pub struct SweepBuilder {
+    chain_id: String,
+    hot_wallet: [u8; 32],
+    max_fee: u128,
+    min_amount: u128,
+    block_gas_limit: u64,
+}
+
+impl SweepBuilder {
+    pub fn new(chain_id: impl Into<String>, hot_wallet: [u8; 32]) -> Self {
+        Self {
+            chain_id: chain_id.into(),
+            hot_wallet,
+            max_fee: 1,
+            min_amount: 1,
+            block_gas_limit: 30_000_000,
+        }
+    }
+
+    /// Maximum price per gas unit; the swept amount reserves the worst case.
+    pub fn max_fee(mut self, max_fee: u128) -> Self {
+        self.max_fee = max_fee;
+        self
+    }
+
+    /// Sub-accounts that would move less than this after fees are skipped.
+    pub fn min_amount(mut self, min_amount: u128) -> Self {
+        self.min_amount = min_amount;
+        self
+    }
+
+    pub fn block_gas_limit(mut self, limit: u64) -> Self {
+        self.block_gas_limit = limit;
+        self
+    }
+
+    pub fn fee_per_transfer(&self) -> u128 {
+        TRANSFER_GAS as u128 * self.max_fee
+    }
+
+    /// Signs one transfer per sub-account holding enough to cover the fee.
+    pub fn build<S: Signer>(&self, inputs: &[SweepInput<S>]) -> anyhow::Result<Vec<Tx>> {
+        let fee = self.fee_per_transfer();
+        let mut txs = Vec::new();
+        for input in inputs {
+            let amount = input.balance.saturating_sub(fee);
+            if amount < self.min_amount.max(1) {
+                continue;
+            }
+            let mut tx = Tx {
+                chain_id: self.chain_id.clone(),
+                nonce: input.nonce,
+                gas_limit: TRANSFER_GAS,
+                max_fee: Some(self.max_fee),
+                max_priority_fee: Some(0),
+                gas_price: None,
+                payload: TxPayload::Transfer {
+                    to: self.hot_wallet,
+                    amount,
+                },
+                public_key: vec![],
+                signature: vec![],
+            };
+            sign_tx(&mut tx, &input.signing_key)?;
+            txs.push(tx);
+        }
+        Ok(txs)
+    }
+
+    /// Same as `build`, split into groups that each fit in one block.
+    pub fn build_batches<S: Signer>(
+        &self,
+        inputs: &[SweepInput<S>],
+    ) -> anyhow::Result<Vec<Vec<Tx>>> {
+        let per_block = (self.block_gas_limit / TRANSFER_GAS).max(1) as usize;
+        let txs = self.build(inputs)?;
+        Ok(txs.chunks(per_block).map(|c| c.to_vec()).collect())
+    }
+}
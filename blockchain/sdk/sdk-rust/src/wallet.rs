@@ -0,0 +1,185 @@
+//! Signs and submits txs for one key, keeping track of its nonce.
+
+use std::time::Duration;
+
+use ed25519_dalek::SigningKey;
+use runtime::{Address, Tx, TxPayload};
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Instant};
+
+use crate::client::{tx_hash, ClientError, KovaClient, TxReceipt};
+use crate::signer::{sign_tx, Signer};
+
+const DEFAULT_MAX_RESUBMITS: u32 = 3;
+
+pub struct Wallet<S = SigningKey> {
+    client: KovaClient,
+    chain_id: String,
+    signer: S,
+    address: Address,
+    max_fee: u128,
+    max_priority_fee: u128,
+    rebroadcast_after: Option<u64>,
+    max_resubmits: u32,
+    /// Nonce for the next tx; fetched from the node when unknown.
+    next_nonce: Mutex<Option<u64>>,
+}
+
+impl<S: Signer> Wallet<S> {
+    pub fn new(client: KovaClient, chain_id: impl Into<String>, signer: S) -> Self {
+        let address = signer.address();
+        Self {
+            client,
+            chain_id: chain_id.into(),
+            signer,
+            address,
+            max_fee: 1,
+            max_priority_fee: 0,
+            rebroadcast_after: None,
+            max_resubmits: DEFAULT_MAX_RESUBMITS,
+            next_nonce: Mutex::new(None),
+        }
+    }
+
+    pub fn max_fee(mut self, max_fee: u128) -> Self {
+        self.max_fee = max_fee;
+        self
+    }
+
+    pub fn max_priority_fee(mut self, max_priority_fee: u128) -> Self {
+        self.max_priority_fee = max_priority_fee;
+        self
+    }
+
+    /// Makes `send_and_wait` submit a tx again if it isn't included within
+    /// `blocks` blocks, e.g. because a node dropped it from its mempool.
+    pub fn rebroadcast_after(mut self, blocks: u64) -> Self {
+        self.rebroadcast_after = Some(blocks);
+        self
+    }
+
+    /// How many times `send_and_wait` re-signs a tx whose nonce was taken by
+    /// another tx from the same account.
+    pub fn max_resubmits(mut self, max_resubmits: u32) -> Self {
+        self.max_resubmits = max_resubmits;
+        self
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    pub fn client(&self) -> &KovaClient {
+        &self.client
+    }
+
+    /// Reserves the next nonce, fetching it from the node the first time.
+    pub async fn next_nonce(&self) -> anyhow::Result<u64> {
+        let mut next = self.next_nonce.lock().await;
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => self.client.get_nonce(&self.address).await?,
+        };
+        *next = Some(nonce + 1);
+        Ok(nonce)
+    }
+
+    /// Drops the cached nonce in favour of the node's. Txs still in the
+    /// mempool aren't counted by the node, so only call this once they are
+    /// included or gone.
+    pub async fn refresh_nonce(&self) -> anyhow::Result<u64> {
+        let mut next = self.next_nonce.lock().await;
+        let nonce = self.client.get_nonce(&self.address).await?;
+        *next = Some(nonce);
+        Ok(nonce)
+    }
+
+    pub fn sign(&self, payload: TxPayload, gas_limit: u64, nonce: u64) -> anyhow::Result<Tx> {
+        let mut tx = Tx {
+            chain_id: self.chain_id.clone(),
+            nonce,
+            gas_limit,
+            max_fee: Some(self.max_fee),
+            max_priority_fee: Some(self.max_priority_fee),
+            gas_price: None,
+            payload,
+            public_key: vec![],
+            signature: vec![],
+        };
+        sign_tx(&mut tx, &self.signer)?;
+        Ok(tx)
+    }
+
+    /// Signs `payload` with the next nonce and submits it. If the node
+    /// refuses it, the cached nonce is dropped so the next tx refetches it.
+    pub async fn send(&self, payload: TxPayload, gas_limit: u64) -> anyhow::Result<Tx> {
+        let tx = self.sign(payload, gas_limit, self.next_nonce().await?)?;
+        if let Err(err) = self.client.send_tx(&tx).await {
+            *self.next_nonce.lock().await = None;
+            return Err(err.into());
+        }
+        Ok(tx)
+    }
+
+    /// Sends `payload` and waits up to `timeout` for it to be included.
+    ///
+    /// Nodes drop txs whose nonce another tx from the account already used,
+    /// so when the account's nonce moves past the tx without a receipt for
+    /// it, the payload is re-signed with a fresh nonce and sent again.
+    pub async fn send_and_wait(
+        &self,
+        payload: TxPayload,
+        gas_limit: u64,
+        timeout: Duration,
+    ) -> anyhow::Result<TxReceipt> {
+        let deadline = Instant::now() + timeout;
+        let mut tx = self.send(payload.clone(), gas_limit).await?;
+        let mut sent_at = self.client.status().await?.height;
+        let mut resubmits = 0;
+        loop {
+            // The nonce is read before the receipt so that a tx included in
+            // between is seen as included rather than as displaced.
+            let chain_nonce = self.client.get_nonce(&self.address).await?;
+            let hash = tx_hash(&tx);
+            if let Some(receipt) = self.client.get_receipt(&hash).await? {
+                return Ok(receipt);
+            }
+            let height = self.client.status().await?.height;
+            if chain_nonce > tx.nonce {
+                if resubmits == self.max_resubmits {
+                    anyhow::bail!(
+                        "nonce {} was used by another tx; gave up after {resubmits} resubmits",
+                        tx.nonce
+                    );
+                }
+                resubmits += 1;
+                self.raise_nonce(chain_nonce).await;
+                tx = self.send(payload.clone(), gas_limit).await?;
+                sent_at = height;
+            } else if self
+                .rebroadcast_after
+                .is_some_and(|blocks| height >= sent_at.saturating_add(blocks))
+            {
+                self.client.send_tx(&tx).await?;
+                sent_at = height;
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(ClientError::Timeout {
+                    tx_hash: hash,
+                    timeout,
+                }
+                .into());
+            }
+            sleep(self.client.poll_interval.min(deadline - now)).await;
+        }
+    }
+
+    /// Moves the cached nonce up to `nonce` without going back on nonces
+    /// already handed out.
+    async fn raise_nonce(&self, nonce: u64) {
+        let mut next = self.next_nonce.lock().await;
+        *next = Some(next.map_or(nonce, |n| n.max(nonce)));
+    }
+}
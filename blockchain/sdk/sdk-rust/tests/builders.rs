@@ -0,0 +1,87 @@
+use ed25519_dalek::SigningKey;
+use kova_sdk::{
+    build_delegate_signed, build_governance_vote_signed, build_privacy_withdraw_signed,
+    build_rollup_bridge_deposit_signed, build_slash_signed, build_stake_signed,
+    PrivacyWithdrawInput, VoteChoice,
+};
+use runtime::{address_from_pubkey, gas_cost, verify_tx_signature, Tx, TxPayload};
+
+fn key() -> SigningKey {
+    SigningKey::from_bytes(&[4u8; 32])
+}
+
+fn withdraw_input() -> PrivacyWithdrawInput {
+    PrivacyWithdrawInput {
+        nullifier: [1u8; 32],
+        merkle_root: [2u8; 32],
+        recipient: [3u8; 32],
+        amount: 500,
+        commitment: [4u8; 32],
+        fee: 5,
+        relayer: Some([6u8; 32]),
+        relayer_fee: 20,
+    }
+}
+
+fn assert_signed(tx: &Tx, nonce: u64) {
+    let signer = address_from_pubkey(&key().verifying_key().to_bytes());
+    assert_eq!(verify_tx_signature(tx, false).unwrap(), signer);
+    assert_eq!(tx.nonce, nonce);
+    assert_eq!(tx.gas_limit, gas_cost(&tx.payload));
+}
+
+#[test]
+fn builders_sign_with_the_runtime_gas_cost() {
+    let domain = uuid::Uuid::new_v4();
+    let txs = [
+        build_stake_signed("kova-devnet", 1_000, &key(), 0).unwrap(),
+        build_delegate_signed("kova-devnet", [9u8; 32], 50, &key(), 1).unwrap(),
+        build_slash_signed("kova-devnet", [9u8; 32], 0, None, &key(), 2).unwrap(),
+        build_governance_vote_signed("kova-devnet", domain, VoteChoice::For, &key(), 3).unwrap(),
+        build_rollup_bridge_deposit_signed("kova-devnet", domain, 10, &key(), 4).unwrap(),
+    ];
+    for (nonce, tx) in txs.iter().enumerate() {
+        assert_signed(tx, nonce as u64);
+    }
+    assert!(matches!(
+        txs[4].payload,
+        TxPayload::RollupBridgeDeposit { domain_id, amount: 10 } if domain_id == domain
+    ));
+}
+
+#[test]
+fn privacy_withdraw_requires_a_matching_proof() {
+    let input = withdraw_input();
+    let proof = zk_program_privacy::stub_withdraw_proof(&input).unwrap();
+    let tx = build_privacy_withdraw_signed("kova-devnet", None, &input, proof, &key(), 0).unwrap();
+    assert_signed(&tx, 0);
+    let TxPayload::PrivacyWithdraw {
+        nullifier,
+        amount,
+        relayer,
+        relayer_fee,
+        ..
+    } = tx.payload
+    else {
+        panic!("expected a privacy withdraw");
+    };
+    assert_eq!((nullifier, amount), (input.nullifier, input.amount));
+    assert_eq!((relayer, relayer_fee), (input.relayer, input.relayer_fee));
+
+    let other = PrivacyWithdrawInput {
+        fee: 0,
+        ..withdraw_input()
+    };
+    let proof = zk_program_privacy::stub_withdraw_proof(&other).unwrap();
+    let err =
+        build_privacy_withdraw_signed("kova-devnet", None, &input, proof, &key(), 0).unwrap_err();
+    assert!(err.to_string().contains("does not commit"));
+
+    // Swapping in another relayer breaks the proof too.
+    let other = PrivacyWithdrawInput {
+        relayer: Some([7u8; 32]),
+        ..withdraw_input()
+    };
+    let proof = zk_program_privacy::stub_withdraw_proof(&other).unwrap();
+    assert!(build_privacy_withdraw_signed("kova-devnet", None, &input, proof, &key(), 0).is_err());
+}
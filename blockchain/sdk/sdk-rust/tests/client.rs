@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::Path;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use ed25519_dalek::SigningKey;
+use kova_sdk::{build_transfer_signed, tx_hash, ClientError, KovaClient};
+
+/// Serves a node stub that includes every tx after two receipt lookups.
+async fn stub_node() -> String {
+    let lookups = Arc::new(AtomicUsize::new(0));
+    let app = Router::new()
+        .route(
+            "/send_raw_tx",
+            post(|Json(body): Json<serde_json::Value>| async move {
+                if body["tx"]["signature"]
+                    .as_array()
+                    .is_some_and(|s| s.is_empty())
+                {
+                    Json("invalid signature")
+                } else {
+                    Json("ok")
+                }
+            }),
+        )
+        .route(
+            "/get_balance/:address",
+            get(|Path(address): Path<String>| async move {
+                Json((address == hex::encode([7u8; 32])).then_some(1_000_u128))
+            }),
+        )
+        .route(
+            "/get_receipt/:hash",
+            get(move || {
+                let lookups = lookups.clone();
+                async move {
+                    if lookups.fetch_add(1, Ordering::SeqCst) < 2 {
+                        return Json(serde_json::Value::Null);
+                    }
+                    Json(serde_json::json!({
+                        "height": 4,
+                        "success": true,
+                        "gas_used": 21000,
+                        "events": [{ "kind": "transfer", "attributes": {} }],
+                        "error": null
+                    }))
+                }
+            }),
+        )
+        .route("/get_validators", get(|| async { "not json" }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}/")
+}
+
+#[tokio::test]
+async fn client_sends_and_waits_for_inclusion() {
+    let client = KovaClient::new(stub_node().await).poll_interval(Duration::from_millis(10));
+    let key = SigningKey::from_bytes(&[1u8; 32]);
+    let mut tx = build_transfer_signed("kova-devnet", [2u8; 32], 5, &key, 0).unwrap();
+
+    let hash = client.send_tx(&tx).await.unwrap();
+    assert_eq!(hash, tx_hash(&tx));
+    let receipt = client
+        .wait_for_inclusion(&hash, Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(receipt.height, 4);
+    assert!(receipt.success);
+    assert_eq!(receipt.outcome.events[0].kind, "transfer");
+
+    assert_eq!(client.get_balance(&[7u8; 32]).await.unwrap(), 1_000);
+    assert_eq!(client.get_balance(&[8u8; 32]).await.unwrap(), 0);
+
+    tx.signature.clear();
+    let err = client.send_tx(&tx).await.unwrap_err();
+    assert!(matches!(err, ClientError::Rejected(ref msg) if msg == "invalid signature"));
+    let err = client.get_validators().await.unwrap_err();
+    assert!(matches!(err, ClientError::InvalidResponse(_)));
+    let err = client.get_block(1).await.unwrap_err();
+    assert!(matches!(err, ClientError::Rejected(_)));
+}
+
+#[tokio::test]
+async fn client_errors_are_typed() {
+    let unreachable = KovaClient::new("http://127.0.0.1:1");
+    let err = unreachable.get_nonce(&[0u8; 32]).await.unwrap_err();
+    assert!(matches!(err, ClientError::Connection(_)));
+
+    let client = KovaClient::new(stub_node().await).poll_interval(Duration::from_millis(10));
+    let err = client
+        .wait_for_inclusion(&[0u8; 32], Duration::from_millis(5))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Timeout { .. }));
+}
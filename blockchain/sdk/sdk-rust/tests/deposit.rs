@@ -0,0 +1,60 @@
+use kova_sdk::{DepositKeyring, ExtendedKey, SweepBuilder, SweepInput};
+use runtime::TxPayload;
+
+#[test]
+fn slip10_ed25519_test_vector() {
+    let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+    let master = ExtendedKey::from_seed(&seed).unwrap();
+    assert_eq!(
+        hex::encode(master.secret_bytes()),
+        "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+    );
+    assert_eq!(
+        hex::encode(master.chain_code()),
+        "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb"
+    );
+    let child = master.derive_path(&[0]);
+    assert_eq!(
+        hex::encode(child.secret_bytes()),
+        "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+    );
+}
+
+#[test]
+fn deposit_addresses_are_deterministic_and_distinct() {
+    let keyring = DepositKeyring::new(&[9u8; 32], 0).unwrap();
+    let again = DepositKeyring::new(&[9u8; 32], 0).unwrap();
+    assert_eq!(keyring.address(17), again.address(17));
+    assert_ne!(keyring.address(17), keyring.address(18));
+    let other_account = DepositKeyring::new(&[9u8; 32], 1).unwrap();
+    assert_ne!(keyring.address(17), other_account.address(17));
+}
+
+#[test]
+fn sweep_skips_dust_and_batches_by_block_gas() {
+    let keyring = DepositKeyring::new(&[9u8; 32], 0).unwrap();
+    let hot = [0xAA; 32];
+    let builder = SweepBuilder::new("kova-devnet", hot).block_gas_limit(42_000);
+    let fee = builder.fee_per_transfer();
+    let inputs: Vec<SweepInput> = (0..5)
+        .map(|i| SweepInput {
+            signing_key: keyring.signing_key(i),
+            balance: if i == 2 { fee } else { fee + 1_000 },
+            nonce: 0,
+        })
+        .collect();
+    let batches = builder.build_batches(&inputs).unwrap();
+    assert_eq!(
+        batches.iter().map(|b| b.len()).collect::<Vec<_>>(),
+        vec![2, 2]
+    );
+    for tx in batches.iter().flatten() {
+        match &tx.payload {
+            TxPayload::Transfer { to, amount } => {
+                assert_eq!(*to, hot);
+                assert_eq!(*amount, 1_000);
+            }
+            other => panic!("unexpected payload {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use ed25519_dalek::SigningKey;
+use kova_sdk::{
+    build_privacy_deposit_signed, build_privacy_note_deposit_signed, NotePlaintext, NoteScanner,
+    ViewingKey,
+};
+use runtime::{Block, BlockHeader, Tx, TxPayload};
+use state::PrivacyPool;
+
+fn key() -> SigningKey {
+    SigningKey::from_bytes(&[4u8; 32])
+}
+
+fn note(seed: u8, amount: u128) -> NotePlaintext {
+    NotePlaintext {
+        nullifier: [seed; 32],
+        recipient: [9u8; 32],
+        amount,
+        salt: [seed.wrapping_add(100); 32],
+        memo: b"rent".to_vec(),
+    }
+}
+
+fn block(height: u64, transactions: Vec<Tx>) -> Block {
+    Block {
+        header: BlockHeader {
+            parent_hash: [0u8; 32],
+            height,
+            timestamp: 0,
+            proposer_id: [0u8; 32],
+            state_root: [0u8; 32],
+            l1_tx_root: [0u8; 32],
+            da_commitment: None,
+            domain_roots: vec![],
+            gas_used: 0,
+            gas_limit: 30_000_000,
+            base_fee: 1,
+            snapshot_root: None,
+            protocol_version: 0,
+            consensus_metadata: serde_json::json!({}),
+        },
+        transactions,
+        da_blobs: vec![],
+    }
+}
+
+#[test]
+fn scanner_finds_only_notes_sealed_to_its_key() {
+    let mine = ViewingKey::from_spend_key(&[1u8; 32]);
+    let theirs = ViewingKey::from_spend_key(&[2u8; 32]);
+    let txs = vec![
+        build_privacy_note_deposit_signed(
+            "kova-devnet",
+            None,
+            &note(1, 100),
+            &mine.public_key(),
+            &key(),
+            0,
+        )
+        .unwrap(),
+        build_privacy_note_deposit_signed(
+            "kova-devnet",
+            None,
+            &note(2, 200),
+            &theirs.public_key(),
+            &key(),
+            1,
+        )
+        .unwrap(),
+        build_privacy_deposit_signed(
+            "kova-devnet",
+            None,
+            note(3, 300).commitment(),
+            300,
+            &key(),
+            2,
+        )
+        .unwrap(),
+    ];
+
+    let mut scanner = NoteScanner::new(mine.clone());
+    assert_eq!(scanner.scan_block(&block(5, txs.clone())), 1);
+    // Rescanning the same block adds nothing.
+    assert_eq!(scanner.scan_block(&block(5, txs.clone())), 0);
+    let found = &scanner.notes()[0];
+    assert_eq!(found.pool, "shielded");
+    assert_eq!(found.height, 5);
+    assert_eq!(found.note, note(1, 100));
+
+    // A memo is only accepted for the deposit it describes.
+    let TxPayload::PrivacyDeposit {
+        memo: Some(memo), ..
+    } = &txs[0].payload
+    else {
+        panic!("expected a deposit with a memo");
+    };
+    assert!(mine.decrypt(memo, &note(1, 100).commitment()).is_some());
+    assert!(mine.decrypt(memo, &[0u8; 32]).is_none());
+    assert!(theirs.decrypt(memo, &note(1, 100).commitment()).is_none());
+}
+
+#[test]
+fn balance_counts_landed_unspent_notes() {
+    let viewing_key = ViewingKey::from_spend_key(&[1u8; 32]);
+    let deposits: Vec<Tx> = (1..=3)
+        .map(|i| {
+            build_privacy_note_deposit_signed(
+                "kova-devnet",
+                None,
+                &note(i, 100 * i as u128),
+                &viewing_key.public_key(),
+                &key(),
+                i as u64,
+            )
+            .unwrap()
+        })
+        .collect();
+    let mut scanner = NoteScanner::new(viewing_key);
+    assert_eq!(scanner.scan_block(&block(1, deposits)), 3);
+
+    // Note 3's deposit failed on chain and note 2 was withdrawn.
+    let pool = PrivacyPool {
+        commitments: vec![note(1, 100).commitment(), note(2, 200).commitment()],
+        nullifiers: vec![note(2, 200).nullifier],
+        ..PrivacyPool::default()
+    };
+    let pools = HashMap::from([("shielded".to_string(), pool)]);
+    let spendable = scanner.spendable(&pools);
+    assert_eq!(spendable.len(), 1);
+    assert_eq!(spendable[0].note.amount, 100);
+    assert_eq!(scanner.balance(&pools), 100);
+}
@@ -0,0 +1,127 @@
+use std::sync::Mutex;
+
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use ed25519_dalek::SigningKey;
+use kova_sdk::{
+    build_governance_proposal_signed, build_transfer_signed, LedgerSigner, LedgerTransport,
+    RemoteSigner, Signer, Wallet,
+};
+use runtime::{address_from_pubkey, sign_bytes, verify_tx_signature};
+use serde_json::{json, Value};
+
+fn key() -> SigningKey {
+    SigningKey::from_bytes(&[6u8; 32])
+}
+
+/// A signing service for `key()`; with `tamper` it signs something else.
+async fn signing_service(tamper: bool) -> String {
+    let app = Router::new()
+        .route(
+            "/public_key",
+            get(|| async {
+                Json(json!({ "public_key": hex::encode(key().verifying_key().to_bytes()) }))
+            }),
+        )
+        .route(
+            "/sign",
+            post(move |Json(body): Json<Value>| async move {
+                let mut message = hex::decode(body["message"].as_str().unwrap()).unwrap();
+                if tamper {
+                    message.push(0);
+                }
+                Json(json!({ "signature": hex::encode(sign_bytes(&key(), &message)) }))
+            }),
+        );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}/")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn remote_signer_signs_txs_and_rejects_bad_signatures() {
+    let signer = RemoteSigner::connect(signing_service(false).await, None).unwrap();
+    assert_eq!(signer.address(), key().address());
+    let tx = build_transfer_signed("kova-devnet", [2u8; 32], 5, &signer, 0).unwrap();
+    assert_eq!(verify_tx_signature(&tx, false).unwrap(), key().address());
+
+    let wallet = Wallet::new(
+        kova_sdk::KovaClient::new("http://127.0.0.1:1"),
+        "kova-devnet",
+        signer,
+    );
+    assert_eq!(wallet.address(), key().address());
+
+    let tampering = RemoteSigner::connect(signing_service(true).await, None).unwrap();
+    let err = build_transfer_signed("kova-devnet", [2u8; 32], 5, &tampering, 0).unwrap_err();
+    assert!(err.to_string().contains("invalid signature"));
+}
+
+/// Emulates the Kova Ledger app: collects chunked sign requests and signs
+/// once the last chunk arrives, unless the user rejects.
+struct FakeLedger {
+    reject: bool,
+    pending: Mutex<Vec<u8>>,
+}
+
+impl LedgerTransport for FakeLedger {
+    fn exchange(&self, apdu: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let (ins, p2, data) = (apdu[1], apdu[3], &apdu[5..]);
+        assert_eq!(apdu[4] as usize, data.len());
+        let mut out = match ins {
+            0x02 => {
+                assert_eq!(data.len(), 1 + 5 * 4);
+                key().verifying_key().to_bytes().to_vec()
+            }
+            0x03 => {
+                let mut pending = self.pending.lock().unwrap();
+                pending.extend_from_slice(data);
+                if p2 == 0x80 {
+                    Vec::new()
+                } else if self.reject {
+                    return Ok(vec![0x69, 0x85]);
+                } else {
+                    let message = pending.split_off(1 + 5 * 4);
+                    pending.clear();
+                    sign_bytes(&key(), &message)
+                }
+            }
+            _ => return Ok(vec![0x6d, 0x00]),
+        };
+        out.extend_from_slice(&[0x90, 0x00]);
+        Ok(out)
+    }
+}
+
+fn ledger(reject: bool) -> LedgerSigner<FakeLedger> {
+    let device = FakeLedger {
+        reject,
+        pending: Mutex::new(Vec::new()),
+    };
+    LedgerSigner::new(device, 0, 0).unwrap()
+}
+
+#[test]
+fn ledger_signer_chunks_large_txs() {
+    let signer = ledger(false);
+    let payload = json!({ "memo": "x".repeat(600) });
+    let tx = build_governance_proposal_signed("kova-devnet", payload, None, &signer, 0).unwrap();
+    let public_key = key().verifying_key().to_bytes();
+    assert_eq!(
+        verify_tx_signature(&tx, false).unwrap(),
+        address_from_pubkey(&public_key)
+    );
+
+    let err = build_transfer_signed("kova-devnet", [2u8; 32], 5, &ledger(true), 0).unwrap_err();
+    assert!(err.to_string().contains("rejected on the device"));
+}
+
+#[test]
+fn builders_accept_trait_objects() {
+    let signers: Vec<Box<dyn Signer>> = vec![Box::new(key()), Box::new(ledger(false))];
+    for signer in &signers {
+        let tx = build_transfer_signed("kova-devnet", [2u8; 32], 5, signer.as_ref(), 0).unwrap();
+        assert_eq!(verify_tx_signature(&tx, false).unwrap(), key().address());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{Path, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use ed25519_dalek::SigningKey;
+use kova_sdk::{tx_hash, KovaClient, Wallet};
+use runtime::{Tx, TxPayload};
+use serde_json::{json, Value};
+
+/// A chain for one account that includes a tx when its nonce is next. The
+/// first submission can be lost, or have its nonce taken by a foreign tx.
+#[derive(Default)]
+struct Chain {
+    nonce: u64,
+    height: u64,
+    receipts: HashMap<String, Value>,
+    submissions: Vec<Tx>,
+    lose_first: bool,
+    race_first: bool,
+}
+
+type Shared = Arc<Mutex<Chain>>;
+
+async fn send(State(chain): State<Shared>, Json(body): Json<Value>) -> Json<&'static str> {
+    let tx: Tx = serde_json::from_value(body["tx"].clone()).unwrap();
+    let mut chain = chain.lock().unwrap();
+    chain.submissions.push(tx.clone());
+    if chain.submissions.len() == 1 && chain.race_first {
+        chain.nonce = tx.nonce + 1;
+    } else if !(chain.submissions.len() == 1 && chain.lose_first) && tx.nonce == chain.nonce {
+        chain.nonce += 1;
+        let receipt = json!({
+            "height": chain.height,
+            "success": true,
+            "gas_used": 21000,
+            "events": [],
+            "error": null
+        });
+        chain.receipts.insert(hex::encode(tx_hash(&tx)), receipt);
+    }
+    Json("ok")
+}
+
+async fn stub_node(chain: Chain) -> (String, Shared) {
+    let chain = Arc::new(Mutex::new(chain));
+    let app =
+        Router::new()
+            .route("/send_raw_tx", post(send))
+            .route(
+                "/get_nonce/:address",
+                get(|State(chain): State<Shared>| async move {
+                    Json(Some(chain.lock().unwrap().nonce))
+                }),
+            )
+            .route(
+                "/get_receipt/:hash",
+                get(
+                    |State(chain): State<Shared>, Path(hash): Path<String>| async move {
+                        Json(chain.lock().unwrap().receipts.get(&hash).cloned())
+                    },
+                ),
+            )
+            .route(
+                // Every status call is a new block.
+                "/status",
+                get(|State(chain): State<Shared>| async move {
+                    let mut chain = chain.lock().unwrap();
+                    chain.height += 1;
+                    Json(json!({ "height": chain.height, "mempool_len": 0, "view": 0 }))
+                }),
+            )
+            .with_state(chain.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (format!("http://{addr}"), chain)
+}
+
+fn wallet(endpoint: String) -> Wallet {
+    let client = KovaClient::new(endpoint).poll_interval(Duration::from_millis(5));
+    Wallet::new(client, "kova-devnet", SigningKey::from_bytes(&[1u8; 32]))
+}
+
+fn transfer() -> TxPayload {
+    TxPayload::Transfer {
+        to: [2u8; 32],
+        amount: 5,
+    }
+}
+
+#[tokio::test]
+async fn nonces_are_cached_and_refreshed() {
+    let (endpoint, chain) = stub_node(Chain {
+        nonce: 7,
+        ..Chain::default()
+    })
+    .await;
+    let wallet = wallet(endpoint);
+    assert_eq!(wallet.send(transfer(), 21_000).await.unwrap().nonce, 7);
+    assert_eq!(wallet.send(transfer(), 21_000).await.unwrap().nonce, 8);
+    chain.lock().unwrap().nonce = 20;
+    assert_eq!(wallet.next_nonce().await.unwrap(), 9);
+    assert_eq!(wallet.refresh_nonce().await.unwrap(), 20);
+    assert_eq!(wallet.next_nonce().await.unwrap(), 20);
+}
+
+#[tokio::test]
+async fn displaced_tx_is_resigned_with_a_fresh_nonce() {
+    let (endpoint, chain) = stub_node(Chain {
+        race_first: true,
+        ..Chain::default()
+    })
+    .await;
+    let receipt = wallet(endpoint)
+        .send_and_wait(transfer(), 21_000, Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert!(receipt.success);
+    let nonces: Vec<u64> = chain
+        .lock()
+        .unwrap()
+        .submissions
+        .iter()
+        .map(|tx| tx.nonce)
+        .collect();
+    assert_eq!(nonces, vec![0, 1]);
+}
+
+#[tokio::test]
+async fn lost_tx_is_rebroadcast() {
+    let (endpoint, chain) = stub_node(Chain {
+        lose_first: true,
+        ..Chain::default()
+    })
+    .await;
+    let err = wallet(endpoint.clone())
+        .send_and_wait(transfer(), 21_000, Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not included"));
+
+    // The first submission is lost again.
+    chain.lock().unwrap().submissions.clear();
+    wallet(endpoint)
+        .rebroadcast_after(2)
+        .send_and_wait(transfer(), 21_000, Duration::from_secs(5))
+        .await
+        .unwrap();
+    let submissions = &chain.lock().unwrap().submissions;
+    assert_eq!(submissions.len(), 2);
+    assert_eq!(tx_hash(&submissions[0]), tx_hash(&submissions[1]));
+}
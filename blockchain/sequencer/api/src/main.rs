@@ -1,23 +1,47 @@
-use axum::{extract::Query, routing::get, routing::post, Json, Router};
-use runtime::Tx;
+use axum::{
+    extract::{Path, Query},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+    routing::post,
+    Json, Router,
+};
+use futures::{stream, Stream, StreamExt};
+use runtime::{Preconfirmation, Tx};
 use serde::{Deserialize, Serialize};
 use sequencer_core::{
-    BatchStatus, RotationPolicy, Sequencer, SequencedBatch, SequencerInfo, SequencerSet,
+    BatchEvent, BatchEventLog, BatchStage, BatchStatus, BatchStore, Mempool, MempoolConfig,
+    RotationPolicy, Sequencer, SequencedBatch, SequencerInfo, SequencerSet,
 };
+use std::convert::Infallible;
 use std::sync::Arc;
 use std::env;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::RwLock;
 use tracing::info;
-use zk_core::ZkBackend;
+use metrics::Metrics;
+use zk_core::{ProgramId, ZkBackend};
+use zk_risczero::{Risc0Backend, Risc0Config, Risc0Program};
 use zk_sp1::{Sp1Backend, Sp1Config, Sp1Program};
 use zk_program_rollup;
 use zk_program_privacy;
 use std::fs;
 
-#[derive(Clone)]
 struct ApiState<S: Sequencer> {
     sequencer: Arc<RwLock<S>>,
     sequencer_set: Option<Arc<SequencerSet>>,
+    events: BatchEventLog,
+}
+
+// Manual impl: deriving would needlessly require `S: Clone`.
+impl<S: Sequencer> Clone for ApiState<S> {
+    fn clone(&self) -> Self {
+        Self {
+            sequencer: self.sequencer.clone(),
+            sequencer_set: self.sequencer_set.clone(),
+            events: self.events.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,10 +50,41 @@ struct SubmitRequest {
     tx: Tx,
 }
 
-async fn submit_tx<S: Sequencer>(state: Arc<ApiState<S>>, Json(req): Json<SubmitRequest>) -> Json<&'static str> {
-    let mut seq = state.sequencer.write().await;
-    seq.submit_tx(&req.domain_id, req.tx).await.unwrap();
-    Json("ok")
+#[derive(Debug, Serialize)]
+struct SubmitResponse {
+    status: &'static str,
+    preconfirmation: Option<Preconfirmation>,
+}
+
+async fn submit_tx<S: Sequencer>(
+    state: Arc<ApiState<S>>,
+    Json(req): Json<SubmitRequest>,
+) -> Result<Json<SubmitResponse>, (StatusCode, String)> {
+    let seq = state.sequencer.read().await;
+    let preconfirmation = seq
+        .submit_tx(&req.domain_id, req.tx)
+        .await
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))?;
+    Ok(Json(SubmitResponse {
+        status: "ok",
+        preconfirmation,
+    }))
+}
+
+async fn preconfirmation<S: Sequencer>(
+    state: Arc<ApiState<S>>,
+    Path(tx_hash): Path<String>,
+) -> Result<Json<Preconfirmation>, StatusCode> {
+    let tx_hash: runtime::Hash = hex::decode(tx_hash.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let seq = state.sequencer.read().await;
+    match seq.preconfirmation(&tx_hash).await {
+        Ok(Some(preconf)) => Ok(Json(preconf)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -64,21 +119,138 @@ struct BatchQuery {
     batch_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct BatchEventsQuery {
+    domain_id: Option<String>,
+    cursor: Option<u64>,
+}
+
+/// Server-sent stream of batch lifecycle events. Each event id is its cursor,
+/// so clients resume with `Last-Event-ID` or `?cursor=` after a disconnect.
+async fn batch_events(
+    events: BatchEventLog,
+    headers: HeaderMap,
+    Query(q): Query<BatchEventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let cursor = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .or(q.cursor)
+        .unwrap_or(0);
+    let (backlog, live) = events.resume(cursor, q.domain_id.as_deref());
+    let state = (backlog.into_iter(), live, cursor, q.domain_id);
+    let stream = stream::unfold(state, |(mut backlog, mut live, mut last, domain)| async move {
+        if let Some(event) = backlog.next() {
+            last = event.cursor;
+            return Some((event, (backlog, live, last, domain)));
+        }
+        loop {
+            match live.recv().await {
+                Ok(event) => {
+                    if event.cursor <= last
+                        || domain.as_deref().is_some_and(|d| d != event.domain_id)
+                    {
+                        continue;
+                    }
+                    last = event.cursor;
+                    return Some((event, (backlog, live, last, domain)));
+                }
+                // A lagging client is disconnected and replays from its cursor.
+                Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .map(|event| Ok(sse_event(&event)));
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn sse_event(event: &BatchEvent) -> Event {
+    let stage = serde_json::to_value(event.stage)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    Event::default()
+        .id(event.cursor.to_string())
+        .event(stage)
+        .json_data(event)
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchStageRequest {
+    domain_id: String,
+    batch_id: String,
+    stage: BatchStage,
+}
+
+async fn record_batch_stage<S: Sequencer>(
+    state: ApiState<S>,
+    Json(req): Json<BatchStageRequest>,
+) -> Result<Json<BatchEvent>, (StatusCode, String)> {
+    let seq = state.sequencer.read().await;
+    seq.record_batch_stage(&req.domain_id, &req.batch_id, req.stage)
+        .await
+        .map(Json)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))
+}
+
 #[derive(Debug, Serialize)]
 struct ActiveSequencerResponse {
     active: Option<SequencerInfo>,
 }
 
+#[derive(Debug, Deserialize)]
+struct BuildBatchRequest {
+    domain_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncNonceRequest {
+    domain_id: String,
+    /// Hex address of the sender.
+    sender: String,
+    nonce: u64,
+}
+
 #[derive(Debug, Deserialize)]
 struct ForceIncludeRequest {
-    blob_id: String,
+    domain_id: String,
+    tx: Tx,
 }
 
 fn app<S: Sequencer + 'static>(state: ApiState<S>) -> Router {
     Router::new()
-        .route("/v1/submit_tx", post(move |body| submit_tx(Arc::new(state.clone()), body)))
-        .route("/v1/domain_head", get(move |q| domain_head(Arc::new(state.clone()), q)))
-        .route("/v1/batch_status", get(move |q| batch_status(Arc::new(state.clone()), q)))
+        .route("/v1/submit_tx", post({
+            let state = state.clone();
+            move |body| submit_tx(Arc::new(state.clone()), body)
+        }))
+        .route("/v1/domain_head", get({
+            let state = state.clone();
+            move |q| domain_head(Arc::new(state.clone()), q)
+        }))
+        .route("/v1/batch_status", get({
+            let state = state.clone();
+            move |q| batch_status(Arc::new(state.clone()), q)
+        }))
+        .route("/v1/preconfirmation/:tx_hash", get({
+            let state = state.clone();
+            move |path| preconfirmation(Arc::new(state.clone()), path)
+        }))
+        .route(
+            "/v1/batch_events",
+            get({
+                let events = state.events.clone();
+                move |headers, q| batch_events(events.clone(), headers, q)
+            }),
+        )
+        .route(
+            "/v1/batch_stage",
+            post({
+                let state = state.clone();
+                move |body| record_batch_stage(state.clone(), body)
+            }),
+        )
         .route(
             "/v1/active_sequencer",
             get({
@@ -102,23 +274,99 @@ fn app<S: Sequencer + 'static>(state: ApiState<S>) -> Router {
                 move |Json(body): Json<ForceIncludeRequest>| {
                     let state = state.clone();
                     async move {
-                        if let Some(set) = state.sequencer_set.as_ref() {
-                            set.enqueue_force_include(body.blob_id);
-                            Json("queued")
-                        } else {
-                            Json("no sequencer set configured")
-                        }
+                        let seq = state.sequencer.read().await;
+                        seq.force_include(&body.domain_id, body.tx)
+                            .await
+                            .map(|()| Json("queued"))
+                            .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))
+                    }
+                }
+            }),
+        )
+        .route(
+            "/v1/build_batch",
+            post({
+                let state = state.clone();
+                move |Json(body): Json<BuildBatchRequest>| {
+                    let state = state.clone();
+                    async move {
+                        let seq = state.sequencer.read().await;
+                        seq.build_batch(&body.domain_id)
+                            .await
+                            .map(Json)
+                            .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+                    }
+                }
+            }),
+        )
+        .route(
+            "/v1/sync_nonce",
+            post({
+                let state = state.clone();
+                move |Json(body): Json<SyncNonceRequest>| {
+                    let state = state.clone();
+                    async move {
+                        let sender: runtime::Address =
+                            hex::decode(body.sender.trim_start_matches("0x"))
+                                .ok()
+                                .and_then(|bytes| bytes.try_into().ok())
+                                .ok_or((StatusCode::BAD_REQUEST, "invalid sender".to_string()))?;
+                        let seq = state.sequencer.read().await;
+                        seq.sync_nonce(&body.domain_id, sender, body.nonce)
+                            .await
+                            .map(|()| Json("ok"))
+                            .map_err(|err| (StatusCode::BAD_REQUEST, err.to_string()))
                     }
                 }
             }),
         )
 }
 
+/// `MEMPOOL_POLICY` picks batch ordering: `fifo` (default), `priority_fee`
+/// or `fair_hash`.
+fn mempool_config_from_env() -> MempoolConfig {
+    let mut config = MempoolConfig::default();
+    if let Ok(policy) = env::var("MEMPOOL_POLICY") {
+        match policy.parse() {
+            Ok(policy) => config.policy = policy,
+            Err(err) => tracing::warn!("{err}; using fifo"),
+        }
+    }
+    let var = |name: &str| env::var(name).ok().and_then(|v| v.parse().ok());
+    if let Some(max) = var("MEMPOOL_MAX_TXS") {
+        config.max_domain_txs = max;
+    }
+    if let Some(max) = var("MEMPOOL_MAX_SENDER_TXS") {
+        config.max_sender_txs = max;
+    }
+    if let Some(rate) = var("MEMPOOL_SENDER_RATE") {
+        config.sender_rate = rate;
+    }
+    if let Some(max) = var("MAX_BATCH_TXS") {
+        config.max_batch_txs = max;
+    }
+    config
+}
+
+/// `ZK_BACKEND` picks the prover when `ENABLE_ZK` is set: `sp1` (default)
+/// or `risc0`.
 fn init_zk_backend() -> Option<Arc<dyn ZkBackend>> {
     let enabled = env::var("ENABLE_ZK").unwrap_or_else(|_| "0".into());
     if enabled != "1" && enabled.to_lowercase() != "true" {
         return None;
     }
+    let backend = env::var("ZK_BACKEND").unwrap_or_else(|_| "sp1".into());
+    match backend.to_lowercase().as_str() {
+        "sp1" => Some(init_sp1_backend()),
+        "risc0" => init_risc0_backend(),
+        other => {
+            tracing::warn!("unknown ZK_BACKEND {other}; expected sp1 or risc0");
+            None
+        }
+    }
+}
+
+fn init_sp1_backend() -> Arc<dyn ZkBackend> {
     let rollup_elf = load_elf("ZK_SP1_ROLLUP_ELF", "zk/artifacts/rollup.elf");
     let privacy_elf = load_elf("ZK_SP1_PRIVACY_ELF", "zk/artifacts/privacy.elf");
     let programs = vec![
@@ -139,7 +387,53 @@ fn init_zk_backend() -> Option<Arc<dyn ZkBackend>> {
         programs,
         verify_only: false,
     });
-    Some(Arc::new(backend))
+    Arc::new(backend)
+}
+
+/// RISC Zero programs are registered by the image ID in
+/// `ZK_RISC0_<PROGRAM>_IMAGE_ID`; programs without one are left out.
+fn init_risc0_backend() -> Option<Arc<dyn ZkBackend>> {
+    let programs = [
+        (zk_program_rollup::program_id(), "rollup_batch", "ROLLUP"),
+        (zk_program_privacy::program_id(), "privacy_withdraw", "PRIVACY"),
+    ]
+    .into_iter()
+    .filter_map(|(id, name, key)| risc0_program(id, name, key))
+    .collect();
+    match Risc0Backend::new(Risc0Config {
+        programs,
+        verify_only: false,
+    }) {
+        Ok(backend) => Some(Arc::new(backend)),
+        Err(err) => {
+            tracing::warn!("RISC Zero backend disabled: {err}");
+            None
+        }
+    }
+}
+
+fn risc0_program(id: ProgramId, name: &str, key: &str) -> Option<Risc0Program> {
+    let image_key = format!("ZK_RISC0_{key}_IMAGE_ID");
+    let Ok(image_hex) = env::var(&image_key) else {
+        tracing::warn!("{image_key} not set; {name} not registered with RISC Zero");
+        return None;
+    };
+    let Some(image_id) = hex::decode(image_hex.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+    else {
+        tracing::warn!("{image_key} must be 32 hex-encoded bytes");
+        return None;
+    };
+    let elf_key = format!("ZK_RISC0_{key}_ELF");
+    let default_path = format!("zk/artifacts/risc0/{}.elf", key.to_lowercase());
+    Some(Risc0Program {
+        id,
+        image_id,
+        elf: load_elf(&elf_key, &default_path).unwrap_or_default(),
+        name: name.into(),
+        version: "0.1.0".into(),
+    })
 }
 
 fn load_elf(env_key: &str, default_path: &str) -> Option<Vec<u8>> {
@@ -153,17 +447,38 @@ fn load_elf(env_key: &str, default_path: &str) -> Option<Vec<u8>> {
     }
 }
 
+/// `DATA_DIR/sequencer`; batches and pending txs stay in memory when
+/// `DATA_DIR` is unset.
+fn open_store_from_env() -> Option<BatchStore> {
+    let dir = std::path::PathBuf::from(env::var("DATA_DIR").ok()?).join("sequencer");
+    Some(BatchStore::open(&dir).expect("opening batch store"))
+}
+
 fn build_sequencer_set_from_env() -> Option<Arc<SequencerSet>> {
     let members = env::var("SEQUENCERS").ok()?;
+    // `id:stake` entries; the stake should mirror the member's bond on chain.
     let roster: Vec<SequencerInfo> = members
         .split(',')
-        .map(|id| SequencerInfo {
-            id: id.trim().to_string(),
-            stake: 1,
-            endpoint: format!("http://{}", id.trim()),
+        .filter_map(|entry| {
+            let (id, stake) = entry.trim().split_once(':').unwrap_or((entry.trim(), "1"));
+            match stake.parse() {
+                Ok(stake) => Some(SequencerInfo {
+                    id: id.to_string(),
+                    stake,
+                    endpoint: format!("http://{}", id),
+                }),
+                Err(_) => {
+                    tracing::warn!("ignoring sequencer {id}: invalid stake {stake}");
+                    None
+                }
+            }
         })
         .collect();
-    Some(Arc::new(SequencerSet::new(roster, RotationPolicy::RoundRobin)))
+    let mut set = SequencerSet::new(roster, RotationPolicy::StakeWeighted);
+    if let Some(domain_id) = env::var("SEQUENCER_DOMAIN").ok().and_then(|d| d.parse().ok()) {
+        set = set.for_domain(domain_id);
+    }
+    Some(Arc::new(set))
 }
 
 #[tokio::main]
@@ -171,18 +486,48 @@ async fn main() {
     tracing_subscriber::fmt().with_env_filter("info").init();
     let zk_backend = init_zk_backend();
     let sequencer_set = build_sequencer_set_from_env();
+    let metrics = Metrics::new(&[("service", "sequencer")]);
     let sequencer = sequencer_core::InMemorySequencer {
-        pending: std::sync::Arc::new(std::sync::Mutex::new(vec![])),
+        mempool: Mempool::new(mempool_config_from_env()),
+        forced: std::sync::Arc::new(std::sync::Mutex::new(vec![])),
         da: da::InMemoryDA::new(),
         batches: std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
         heads: std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
         zk: zk_backend,
+        events: BatchEventLog::default(),
+        metrics: Some(metrics.clone()),
+        fee_split: runtime::FeeSplit {
+            l1_gas_burn_pct: 30,
+            l1_gas_validators_pct: 70,
+            da_validators_pct: 70,
+            da_nodes_pct: 20,
+            da_treasury_pct: 10,
+            l2_sequencer_pct: 50,
+            l2_da_costs_pct: 30,
+            l2_l1_rent_pct: 20,
+        },
+        da_byte_price: env::var("DA_BYTE_PRICE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1),
+        preconf_key: Arc::new(sequencer_core::load_sequencer_key()),
+        preconf_batches: env::var("PRECONF_BATCHES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(4),
+        preconfirmations: std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
+        store: open_store_from_env(),
     };
+    let reposted = sequencer.recover().await.expect("recovering sequencer state");
+    if reposted > 0 {
+        info!("re-posted {reposted} unposted batches to DA");
+    }
     let state = ApiState {
+        events: sequencer.events.clone(),
         sequencer: Arc::new(RwLock::new(sequencer)),
         sequencer_set,
     };
-    let router = app(state);
+    let router = app(state).merge(metrics::router(metrics));
     info!("sequencer api listening on 0.0.0.0:7545");
     axum::Server::bind(&"0.0.0.0:7545".parse().unwrap())
         .serve(router.into_make_service())
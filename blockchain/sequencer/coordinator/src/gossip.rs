@@ -0,0 +1,119 @@
+//! Gossipsub channel a domain's sequencers share over QUIC. Messages with a
+//! bad signature are rejected at validation so they are not relayed; who may
+//! send is checked against the bonds by the coordinator.
+
+use anyhow::Context;
+use futures::StreamExt;
+use libp2p::{
+    gossipsub::{self, IdentTopic, MessageAcceptance, MessageAuthenticity},
+    identity,
+    multiaddr::Protocol,
+    Multiaddr, PeerId, SwarmBuilder, SwarmEvent,
+};
+use sequencer_core::SequencerGossip;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+fn topic(domain_id: &Uuid) -> IdentTopic {
+    IdentTopic::new(format!("kova/sequencers/{domain_id}/1.0"))
+}
+
+pub fn start_gossip(
+    keypair: identity::Keypair,
+    listen_addr: Multiaddr,
+    bootstrap: Vec<Multiaddr>,
+    domain_id: Uuid,
+) -> anyhow::Result<(
+    mpsc::Sender<SequencerGossip>,
+    mpsc::Receiver<SequencerGossip>,
+)> {
+    let peer_id = PeerId::from(keypair.public());
+    info!("sequencer gossip peer id {}", peer_id);
+
+    let transport = libp2p::quic::tokio::Transport::new(libp2p::quic::Config::new(&keypair));
+    let mut gossipsub = gossipsub::Behaviour::new(
+        MessageAuthenticity::Signed(keypair.clone()),
+        gossipsub::ConfigBuilder::default()
+            .validation_mode(gossipsub::ValidationMode::Strict)
+            .validate_messages()
+            .build()
+            .context("building gossipsub config")?,
+    )?;
+    let topic = topic(&domain_id);
+    gossipsub.subscribe(&topic)?;
+
+    let mut swarm = SwarmBuilder::with_tokio_executor(transport, gossipsub, peer_id).build();
+    swarm.listen_on(listen_addr)?;
+    for addr in bootstrap {
+        if swarm.dial(addr.clone()).is_ok() {
+            info!("dialing sequencer peer {}", addr);
+        }
+    }
+
+    let (publish_tx, mut publish_rx) = mpsc::channel::<SequencerGossip>(64);
+    let (inbound_tx, inbound_rx) = mpsc::channel::<SequencerGossip>(256);
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                maybe_msg = publish_rx.recv() => {
+                    let Some(msg) = maybe_msg else { break };
+                    match serde_json::to_vec(&msg) {
+                        Ok(bytes) => {
+                            if let Err(err) = swarm.behaviour_mut().publish(topic.clone(), bytes) {
+                                debug!("failed to publish sequencer gossip: {err}");
+                            }
+                        }
+                        Err(err) => warn!("serialize sequencer gossip failed: {err}"),
+                    }
+                }
+                event = swarm.select_next_some() => {
+                    match event {
+                        SwarmEvent::Behaviour(gossipsub::Event::Message { propagation_source, message_id, message }) => {
+                            let decoded = serde_json::from_slice::<SequencerGossip>(&message.data)
+                                .ok()
+                                .filter(|gossip| gossip.domain_id == domain_id && gossip.verify().is_ok());
+                            let acceptance = if decoded.is_some() {
+                                MessageAcceptance::Accept
+                            } else {
+                                MessageAcceptance::Reject
+                            };
+                            swarm.behaviour_mut().report_message_validation_result(
+                                &message_id,
+                                &propagation_source,
+                                acceptance,
+                            );
+                            if let Some(gossip) = decoded {
+                                if inbound_tx.send(gossip).await.is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                        SwarmEvent::NewListenAddr { address, .. } => {
+                            info!("sequencer gossip listening on {address}");
+                        }
+                        SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+                            warn!("dial error {:?}: {error}", peer_id);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    });
+
+    Ok((publish_tx, inbound_rx))
+}
+
+pub fn parse_multiaddr_list(addrs: &str) -> Vec<Multiaddr> {
+    addrs
+        .split(',')
+        .filter_map(|s| s.trim().parse::<Multiaddr>().ok())
+        .map(|mut addr| {
+            if !addr.iter().any(|p| matches!(p, Protocol::QuicV1)) {
+                addr.push(Protocol::QuicV1);
+            }
+            addr
+        })
+        .collect()
+}
@@ -1,40 +1,109 @@
-use sequencer_core::{RotationPolicy, SequencerInfo, SequencerSet, Sequencer, SequencedBatch};
-use tokio::time::{sleep, Duration};
-use tracing::info;
+//! Runs beside a sequencer's API. Follows the domain's bonded sequencers on
+//! L1, gossips heartbeats with the other sequencers, and has the local
+//! sequencer build and announce batches while it holds the round, taking
+//! over when the leader misses its slot.
+
+use libp2p::identity;
+use sequencer_core::{Coordinator, DomainSequencers, SequencedBatch};
+use serde_json::json;
+use tokio::time::{interval, Duration};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+mod gossip;
+
+/// Blocks a sequencer may stay silent before it is reported as down.
+const LIVENESS_BLOCKS: u64 = 10;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().with_env_filter("info").init();
-    let members = std::env::var("SEQUENCERS").unwrap_or_else(|_| "sequencer-0".into());
-    let roster: Vec<SequencerInfo> = members
-        .split(',')
-        .enumerate()
-        .map(|(idx, id)| SequencerInfo {
-            id: id.trim().to_string(),
-            stake: 1,
-            endpoint: format!("http://{}", id.trim()),
-        })
-        .collect();
-    let set = SequencerSet::new(roster, RotationPolicy::RoundRobin);
-    let member_count = set.member_count();
-    info!(
-        "sequencer coordinator starting with {} configured members",
-        member_count
-    );
-
-    let mut round: u64 = 0;
+    let domain_id: Uuid = std::env::var("SEQUENCER_DOMAIN")
+        .map_err(|_| anyhow::anyhow!("SEQUENCER_DOMAIN must be set"))?
+        .parse()?;
+    let chain_id = std::env::var("CHAIN_ID").unwrap_or_else(|_| "kova-devnet".into());
+    let node_rpc = std::env::var("NODE_RPC").unwrap_or_else(|_| "http://localhost:8545".into());
+    let sequencer_api =
+        std::env::var("SEQUENCER_API").unwrap_or_else(|_| "http://localhost:7545".into());
+    let tick_ms = std::env::var("COORDINATOR_TICK_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1_000);
+
+    let signing_key = sequencer_core::load_sequencer_key();
+    let keypair = identity::Keypair::ed25519_from_bytes(signing_key.to_bytes().to_vec())?;
+    let listen = std::env::var("P2P_LISTEN")
+        .unwrap_or_else(|_| "/ip4/0.0.0.0/udp/9100/quic-v1".into())
+        .parse()?;
+    let bootstrap =
+        gossip::parse_multiaddr_list(&std::env::var("P2P_BOOTSTRAP").unwrap_or_default());
+    let (publish, mut inbound) = gossip::start_gossip(keypair, listen, bootstrap, domain_id)?;
+
+    let mut coordinator = Coordinator::new(&chain_id, domain_id, signing_key);
+    let http = reqwest::Client::new();
+    info!("sequencer coordinator starting for domain {domain_id}");
+
+    let mut ticker = interval(Duration::from_millis(tick_ms));
+    let mut last_round = None;
     loop {
-        if let Some(active) = set.active_leader(round) {
-            info!("round {} active sequencer: {}", round, active.id);
-        } else {
-            info!("round {} no active sequencer configured", round);
-        }
-        if let Some(force_blob) = set.pop_force_include() {
-            info!("force-include requested for blob {}", force_blob);
+        tokio::select! {
+            _ = ticker.tick() => {
+                let url = format!("{node_rpc}/domain/{domain_id}/sequencers");
+                match fetch::<DomainSequencers>(http.get(&url)).await {
+                    Ok(chain) => coordinator.sync(&chain),
+                    Err(err) => {
+                        warn!("reading the sequencer set failed: {err}");
+                        continue;
+                    }
+                }
+                let round = coordinator.round();
+                if last_round != Some(round) {
+                    match coordinator.leader() {
+                        Some(leader) if round.1 > 0 => {
+                            info!("round {} failed over to {} (attempt {})", round.0, leader.id, round.1)
+                        }
+                        Some(leader) => info!("round {} leader: {}", round.0, leader.id),
+                        None => info!("round {} has no bonded sequencer", round.0),
+                    }
+                    last_round = Some(round);
+                }
+                if let Some(leader) = coordinator.leader() {
+                    let silent = !coordinator.live(LIVENESS_BLOCKS).iter().any(|a| hex::encode(a) == leader.id);
+                    if silent && !coordinator.is_leader() {
+                        debug!("no heartbeat from leader {} in {LIVENESS_BLOCKS} blocks", leader.id);
+                    }
+                }
+
+                let head_url = format!("{sequencer_api}/v1/domain_head?domain_id={domain_id}");
+                let head = fetch::<u64>(http.get(&head_url)).await.unwrap_or(0);
+                let _ = publish.try_send(coordinator.heartbeat(head));
+                if !coordinator.is_leader() {
+                    continue;
+                }
+                let build = http
+                    .post(format!("{sequencer_api}/v1/build_batch"))
+                    .json(&json!({ "domain_id": domain_id.to_string() }));
+                match fetch::<SequencedBatch>(build).await {
+                    Ok(batch) if batch.da_blob.is_some() => {
+                        let blob_id = batch.da_blob.map(|b| b.id);
+                        info!("built batch {} as leader of round {}", batch.batch_id, round.0);
+                        let _ = publish.try_send(coordinator.announce(batch.batch_id, blob_id, head + 1));
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!("building a batch failed: {err}"),
+                }
+            }
+            Some(msg) = inbound.recv() => {
+                if let Err(err) = coordinator.observe(&msg) {
+                    debug!("ignoring sequencer gossip: {err}");
+                }
+            }
         }
-        round = round.saturating_add(1);
-        sleep(Duration::from_secs(5)).await;
     }
-    Ok(())
 }
 
+async fn fetch<T: serde::de::DeserializeOwned>(
+    request: reqwest::RequestBuilder,
+) -> anyhow::Result<T> {
+    Ok(request.send().await?.error_for_status()?.json().await?)
+}
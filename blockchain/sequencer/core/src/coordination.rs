@@ -0,0 +1,273 @@
+//! Leader coordination between a domain's sequencers. Each round's leader
+//! is drawn from the bonds on chain exactly as the runtime draws it, and a
+//! leader that has not announced a batch `failover_blocks` into its slot
+//! hands the round to the next draw, as L1 does. Sequencers gossip signed
+//! heartbeats and batch announcements so each can tell who is live and
+//! whether the round's batch is out.
+
+use ed25519_dalek::SigningKey;
+use runtime::{
+    address_from_pubkey, sign_in_domain, signing_message, verify_signature_bytes, Address,
+    SigningDomain,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{RotationPolicy, SequencerInfo, SequencerSet};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GossipBody {
+    Heartbeat {
+        head: u64,
+    },
+    BatchAnnounce {
+        batch_id: String,
+        blob_id: Option<String>,
+        head: u64,
+    },
+}
+
+/// A message one sequencer gossips to the others, signed with the key its
+/// bond is held by.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SequencerGossip {
+    pub chain_id: String,
+    pub domain_id: Uuid,
+    pub round: u64,
+    pub attempt: u32,
+    pub body: GossipBody,
+    pub sequencer_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SequencerGossip {
+    pub fn sign(
+        signing_key: &SigningKey,
+        chain_id: &str,
+        domain_id: Uuid,
+        round: u64,
+        attempt: u32,
+        body: GossipBody,
+    ) -> Self {
+        let mut gossip = Self {
+            chain_id: chain_id.to_string(),
+            domain_id,
+            round,
+            attempt,
+            body,
+            sequencer_key: signing_key.verifying_key().to_bytes().to_vec(),
+            signature: Vec::new(),
+        };
+        gossip.signature = sign_in_domain(
+            signing_key,
+            SigningDomain::SequencerGossip,
+            chain_id,
+            &gossip.signing_bytes(),
+        );
+        gossip
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&(self.domain_id, self.round, self.attempt, &self.body))
+            .unwrap_or_default()
+    }
+
+    /// Checks the signature and returns the sender's address.
+    pub fn verify(&self) -> anyhow::Result<Address> {
+        let msg = signing_message(
+            SigningDomain::SequencerGossip,
+            &self.chain_id,
+            &self.signing_bytes(),
+        );
+        verify_signature_bytes(&self.sequencer_key, &self.signature, &msg)?;
+        Ok(address_from_pubkey(&self.sequencer_key))
+    }
+}
+
+/// Round a domain's sequencers are in, as L1 reports it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainRound {
+    pub round: u64,
+    pub leader: Address,
+    pub posted: bool,
+    #[serde(default)]
+    pub attempt: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainBond {
+    pub sequencer: Address,
+    pub bond: u128,
+    pub exiting_since: Option<u64>,
+}
+
+/// A domain's bonded sequencers and rotation settings as L1 reports them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainSequencers {
+    pub height: u64,
+    pub rotation_blocks: u64,
+    pub failover_blocks: u64,
+    pub min_bond: u128,
+    pub round: Option<ChainRound>,
+    pub bonds: Vec<ChainBond>,
+}
+
+/// One sequencer's view of who leads its domain.
+pub struct Coordinator {
+    chain_id: String,
+    domain_id: Uuid,
+    signing_key: SigningKey,
+    local: Address,
+    set: SequencerSet,
+    rotation_blocks: u64,
+    failover_blocks: u64,
+    height: u64,
+    round: u64,
+    attempt: u32,
+    /// Whether the round's leader has put out its batch.
+    posted: bool,
+    /// Height at which each sequencer was last heard from.
+    last_seen: HashMap<Address, u64>,
+}
+
+impl Coordinator {
+    pub fn new(chain_id: &str, domain_id: Uuid, signing_key: SigningKey) -> Self {
+        let local = address_from_pubkey(&signing_key.verifying_key().to_bytes());
+        Self {
+            chain_id: chain_id.to_string(),
+            domain_id,
+            signing_key,
+            local,
+            set: SequencerSet::new(Vec::new(), RotationPolicy::StakeWeighted).for_domain(domain_id),
+            rotation_blocks: 1,
+            failover_blocks: 0,
+            height: 0,
+            round: 0,
+            attempt: 0,
+            posted: false,
+            last_seen: HashMap::new(),
+        }
+    }
+
+    /// Follows the bonds, rotation settings and height reported by L1.
+    pub fn sync(&mut self, chain: &DomainSequencers) {
+        let members = chain
+            .bonds
+            .iter()
+            .filter(|b| b.exiting_since.is_none() && b.bond >= chain.min_bond)
+            .map(|b| SequencerInfo {
+                id: hex::encode(b.sequencer),
+                stake: b.bond,
+                endpoint: String::new(),
+            })
+            .collect();
+        self.set.set_members(members);
+        self.rotation_blocks = chain.rotation_blocks.max(1);
+        self.failover_blocks = chain.failover_blocks;
+        self.advance(chain.height);
+        if let Some(round) = &chain.round {
+            if round.round == self.round && round.posted {
+                self.posted = true;
+            }
+        }
+    }
+
+    /// Moves to the round and slot containing `height`. A posted round
+    /// keeps its leader until it ends.
+    pub fn advance(&mut self, height: u64) {
+        self.height = height;
+        let round = height / self.rotation_blocks;
+        if round != self.round {
+            self.round = round;
+            self.posted = false;
+            self.attempt = 0;
+        }
+        if !self.posted && self.failover_blocks > 0 {
+            let slot = ((height % self.rotation_blocks) / self.failover_blocks) as u32;
+            self.attempt = self.attempt.max(slot);
+        }
+    }
+
+    pub fn round(&self) -> (u64, u32) {
+        (self.round, self.attempt)
+    }
+
+    pub fn leader(&self) -> Option<SequencerInfo> {
+        self.set.leader(self.round, self.attempt)
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.leader()
+            .is_some_and(|leader| leader.id == hex::encode(self.local))
+    }
+
+    /// Whether the round's batch is out, so the leader is done.
+    pub fn posted(&self) -> bool {
+        self.posted
+    }
+
+    /// Takes in a gossiped message, returning its sender. Only bonded
+    /// sequencers of this domain are heard.
+    pub fn observe(&mut self, gossip: &SequencerGossip) -> anyhow::Result<Address> {
+        if gossip.chain_id != self.chain_id || gossip.domain_id != self.domain_id {
+            anyhow::bail!("gossip is for another domain");
+        }
+        let sender = gossip.verify()?;
+        let id = hex::encode(sender);
+        if !self.set.members().iter().any(|m| m.id == id) {
+            anyhow::bail!("gossip sender is not a bonded sequencer");
+        }
+        self.last_seen.insert(sender, self.height);
+        let leads = gossip.round == self.round
+            && gossip.attempt == self.attempt
+            && self.leader().is_some_and(|leader| leader.id == id);
+        if leads && matches!(gossip.body, GossipBody::BatchAnnounce { .. }) {
+            self.posted = true;
+        }
+        Ok(sender)
+    }
+
+    /// Sequencers heard from within the last `blocks` blocks.
+    pub fn live(&self, blocks: u64) -> Vec<Address> {
+        let mut live: Vec<Address> = self
+            .last_seen
+            .iter()
+            .filter(|(_, seen)| self.height.saturating_sub(**seen) <= blocks)
+            .map(|(address, _)| *address)
+            .collect();
+        live.sort();
+        live
+    }
+
+    pub fn heartbeat(&self, head: u64) -> SequencerGossip {
+        self.gossip(GossipBody::Heartbeat { head })
+    }
+
+    /// Announces the batch this sequencer built as the round's leader.
+    pub fn announce(
+        &mut self,
+        batch_id: String,
+        blob_id: Option<String>,
+        head: u64,
+    ) -> SequencerGossip {
+        self.posted = true;
+        self.gossip(GossipBody::BatchAnnounce {
+            batch_id,
+            blob_id,
+            head,
+        })
+    }
+
+    fn gossip(&self, body: GossipBody) -> SequencerGossip {
+        SequencerGossip::sign(
+            &self.signing_key,
+            &self.chain_id,
+            self.domain_id,
+            self.round,
+            self.attempt,
+            body,
+        )
+    }
+}
@@ -0,0 +1,45 @@
+use runtime::{FeeSplit, Tx};
+use serde::{Deserialize, Serialize};
+
+/// What a batch's txs offer in fees, split by the L2 shares of the fee
+/// split, against what posting its blob to DA costs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchCosts {
+    pub tx_count: usize,
+    /// Size of the posted, compressed blob.
+    pub blob_bytes: usize,
+    /// Gas limit times max fee, summed over the batch.
+    pub fees: u128,
+    pub sequencer_share: u128,
+    pub da_share: u128,
+    pub l1_rent_share: u128,
+    /// `blob_bytes` at the DA byte price.
+    pub da_cost: u128,
+}
+
+impl BatchCosts {
+    pub fn new(txs: &[Tx], blob_bytes: usize, split: &FeeSplit, da_byte_price: u128) -> Self {
+        let fees = txs
+            .iter()
+            .map(|tx| {
+                let price = tx.max_fee.or(tx.gas_price).unwrap_or(0);
+                (tx.gas_limit as u128).saturating_mul(price)
+            })
+            .fold(0u128, u128::saturating_add);
+        let share = |pct: u8| fees.saturating_mul(pct as u128) / 100;
+        Self {
+            tx_count: txs.len(),
+            blob_bytes,
+            fees,
+            sequencer_share: share(split.l2_sequencer_pct),
+            da_share: share(split.l2_da_costs_pct),
+            l1_rent_share: share(split.l2_l1_rent_pct),
+            da_cost: (blob_bytes as u128).saturating_mul(da_byte_price),
+        }
+    }
+
+    /// DA cost the batch's DA share of fees does not cover.
+    pub fn da_shortfall(&self) -> u128 {
+        self.da_cost.saturating_sub(self.da_share)
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+pub const DEFAULT_EVENT_RETENTION: usize = 4_096;
+
+/// Lifecycle stages of a batch, in the order they are reached.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStage {
+    Built,
+    BlobPosted,
+    ProofGenerated,
+    L1Committed,
+    Finalized,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchEvent {
+    /// Monotonic position in the event log; pass it back to resume a stream.
+    pub cursor: u64,
+    pub domain_id: String,
+    pub batch_id: String,
+    pub stage: BatchStage,
+    pub blob_id: Option<String>,
+    pub timestamp_ms: u64,
+}
+
+struct EventLogInner {
+    events: VecDeque<BatchEvent>,
+    next_cursor: u64,
+}
+
+impl EventLogInner {
+    fn after(&self, cursor: u64, domain_id: Option<&str>) -> Vec<BatchEvent> {
+        self.events
+            .iter()
+            .filter(|e| e.cursor > cursor)
+            .filter(|e| domain_id.is_none() || domain_id == Some(e.domain_id.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Bounded history of batch lifecycle events plus a live broadcast feed.
+#[derive(Clone)]
+pub struct BatchEventLog {
+    inner: Arc<Mutex<EventLogInner>>,
+    sender: broadcast::Sender<BatchEvent>,
+    retention: usize,
+}
+
+impl Default for BatchEventLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_RETENTION)
+    }
+}
+
+impl BatchEventLog {
+    pub fn new(retention: usize) -> Self {
+        let (sender, _) = broadcast::channel(retention.max(1));
+        Self {
+            inner: Arc::new(Mutex::new(EventLogInner {
+                events: VecDeque::new(),
+                next_cursor: 1,
+            })),
+            sender,
+            retention: retention.max(1),
+        }
+    }
+
+    pub fn publish(
+        &self,
+        domain_id: &str,
+        batch_id: &str,
+        stage: BatchStage,
+        blob_id: Option<String>,
+    ) -> BatchEvent {
+        let mut inner = self.inner.lock().unwrap();
+        let event = BatchEvent {
+            cursor: inner.next_cursor,
+            domain_id: domain_id.to_string(),
+            batch_id: batch_id.to_string(),
+            stage,
+            blob_id,
+            timestamp_ms: now_millis(),
+        };
+        inner.next_cursor += 1;
+        inner.events.push_back(event.clone());
+        while inner.events.len() > self.retention {
+            inner.events.pop_front();
+        }
+        // Sending under the lock keeps live delivery in cursor order.
+        let _ = self.sender.send(event.clone());
+        event
+    }
+
+    /// Retained events after `cursor`, optionally limited to one domain.
+    pub fn since(&self, cursor: u64, domain_id: Option<&str>) -> Vec<BatchEvent> {
+        let inner = self.inner.lock().unwrap();
+        inner.after(cursor, domain_id)
+    }
+
+    /// Latest stage reached by a batch, if it is still retained.
+    pub fn stage_of(&self, domain_id: &str, batch_id: &str) -> Option<BatchStage> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .events
+            .iter()
+            .filter(|e| e.domain_id == domain_id && e.batch_id == batch_id)
+            .map(|e| e.stage)
+            .max()
+    }
+
+    /// Replay after `cursor` and subscribe atomically, so no event is missed
+    /// or delivered twice between the two.
+    pub fn resume(
+        &self,
+        cursor: u64,
+        domain_id: Option<&str>,
+    ) -> (Vec<BatchEvent>, broadcast::Receiver<BatchEvent>) {
+        let inner = self.inner.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        (inner.after(cursor, domain_id), receiver)
+    }
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
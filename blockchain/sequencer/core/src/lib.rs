@@ -1,14 +1,30 @@
 use async_trait::async_trait;
 use da::{BlobRef, DAProvider, InMemoryDA};
-use runtime::Tx;
+use ed25519_dalek::SigningKey;
+use runtime::{tx_hash, Address, FeeSplit, Hash, Preconfirmation, Tx};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tracing::{info, warn};
 use uuid::Uuid;
 use blake3;
+use metrics::Metrics;
 use zk_core::{ProgramId, ProofArtifact, ProofRequest, ZkBackend};
-use zk_program_rollup::{commitments as rollup_commitments, encode_input as encode_rollup_input, RollupProofInput};
+use zk_program_rollup::{commitments as rollup_commitments, encode_batch, encode_input as encode_rollup_input, RollupProofInput};
+
+mod coordination;
+mod costs;
+mod events;
+mod mempool;
+mod store;
+pub use coordination::{
+    ChainBond, ChainRound, Coordinator, DomainSequencers, GossipBody, SequencerGossip,
+};
+pub use costs::BatchCosts;
+pub use events::{BatchEvent, BatchEventLog, BatchStage, DEFAULT_EVENT_RETENTION};
+pub use mempool::{Mempool, MempoolConfig, OrderingPolicy};
+pub use store::{BatchStore, StoredState};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SequencedBatch {
@@ -17,6 +33,8 @@ pub struct SequencedBatch {
     pub txs: Vec<Tx>,
     pub da_blob: Option<BlobRef>,
     pub proof: Option<ProofArtifact>,
+    #[serde(default)]
+    pub costs: BatchCosts,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +42,8 @@ pub struct BatchStatus {
     pub batch_id: String,
     pub posted: bool,
     pub blob_ref: Option<BlobRef>,
+    #[serde(default)]
+    pub costs: BatchCosts,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,11 +56,14 @@ pub struct SequencerInfo {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RotationPolicy {
     RoundRobin,
+    /// Leader drawn each round with odds proportional to stake, as the
+    /// runtime draws it for the domain's bonded sequencers.
+    StakeWeighted,
 }
 
 impl Default for RotationPolicy {
     fn default() -> Self {
-        RotationPolicy::RoundRobin
+        RotationPolicy::StakeWeighted
     }
 }
 
@@ -54,7 +77,7 @@ pub struct SlashEvent {
 pub struct SequencerSet {
     members: Arc<Mutex<Vec<SequencerInfo>>>,
     policy: RotationPolicy,
-    force_include: Arc<Mutex<VecDeque<String>>>,
+    domain_id: Uuid,
     pub slashing_events: Arc<Mutex<Vec<SlashEvent>>>,
 }
 
@@ -63,18 +86,39 @@ impl SequencerSet {
         Self {
             members: Arc::new(Mutex::new(members)),
             policy,
-            force_include: Arc::new(Mutex::new(VecDeque::new())),
+            domain_id: Uuid::nil(),
             slashing_events: Arc::new(Mutex::new(vec![])),
         }
     }
 
+    /// Seeds stake-weighted draws with `domain_id`. Members must be ordered
+    /// by address to match the runtime's draw.
+    pub fn for_domain(mut self, domain_id: Uuid) -> Self {
+        self.domain_id = domain_id;
+        self
+    }
+
     pub fn active_leader(&self, round: u64) -> Option<SequencerInfo> {
+        self.leader(round, 0)
+    }
+
+    /// Leader of `round` after `attempt` failovers, drawn as the runtime
+    /// draws it when the round's leader misses its slot.
+    pub fn leader(&self, round: u64, attempt: u32) -> Option<SequencerInfo> {
         let members = self.members.lock().unwrap();
         if members.is_empty() {
             return None;
         }
         match self.policy {
-            RotationPolicy::RoundRobin => members.get((round as usize) % members.len()).cloned(),
+            RotationPolicy::RoundRobin => {
+                let index = round.wrapping_add(attempt as u64) as usize % members.len();
+                members.get(index).cloned()
+            }
+            RotationPolicy::StakeWeighted => {
+                let stakes: Vec<u128> = members.iter().map(|m| m.stake).collect();
+                runtime::failover_pick(&stakes, &self.domain_id, round, attempt)
+                    .and_then(|i| members.get(i).cloned())
+            }
         }
     }
 
@@ -82,12 +126,23 @@ impl SequencerSet {
         self.members.lock().unwrap().len()
     }
 
-    pub fn enqueue_force_include(&self, blob_id: String) {
-        self.force_include.lock().unwrap().push_back(blob_id);
+    pub fn members(&self) -> Vec<SequencerInfo> {
+        self.members.lock().unwrap().clone()
     }
 
-    pub fn pop_force_include(&self) -> Option<String> {
-        self.force_include.lock().unwrap().pop_front()
+    /// Replaces the roster, i.e. with the bonds read back from chain.
+    pub fn set_members(&self, members: Vec<SequencerInfo>) {
+        *self.members.lock().unwrap() = members;
+    }
+
+    /// Follows a bond change on chain; a stake of zero removes the member.
+    pub fn set_stake(&self, sequencer_id: &str, stake: u128) {
+        let mut members = self.members.lock().unwrap();
+        if stake == 0 {
+            members.retain(|m| m.id != sequencer_id);
+        } else if let Some(member) = members.iter_mut().find(|m| m.id == sequencer_id) {
+            member.stake = stake;
+        }
     }
 
     pub fn slash(&self, sequencer_id: &str, reason: &str) -> SlashEvent {
@@ -100,108 +155,319 @@ impl SequencerSet {
     }
 }
 
+/// Key a sequencer signs pre-confirmations and gossip with; its address
+/// must hold the domain's bond. `SEQUENCER_KEY` is a hex-encoded 32-byte
+/// seed; without it the key is derived from `SEQUENCER_ID`, as nodes derive
+/// theirs from the node id.
+pub fn load_sequencer_key() -> SigningKey {
+    if let Ok(seed) = std::env::var("SEQUENCER_KEY") {
+        match hex::decode(seed.trim()).ok().and_then(|b| <[u8; 32]>::try_from(b).ok()) {
+            Some(seed) => return SigningKey::from_bytes(&seed),
+            None => warn!("SEQUENCER_KEY must be 32 hex-encoded bytes; deriving key"),
+        }
+    }
+    let id = std::env::var("SEQUENCER_ID").unwrap_or_else(|_| "sequencer-0".into());
+    SigningKey::from_bytes(blake3::hash(id.as_bytes()).as_bytes())
+}
+
 #[async_trait]
 pub trait Sequencer: Send + Sync {
-    async fn submit_tx(&self, domain_id: &str, tx: Tx) -> anyhow::Result<()>;
+    /// Admits `tx` to the domain's mempool and, for a domain with a UUID
+    /// id, returns the signed promise to include it.
+    async fn submit_tx(&self, domain_id: &str, tx: Tx) -> anyhow::Result<Option<Preconfirmation>>;
+    async fn preconfirmation(&self, tx_hash: &Hash) -> anyhow::Result<Option<Preconfirmation>>;
+    /// Puts a tx force-included on L1 at the front of the queue, so the
+    /// next batch carries it before its deadline.
+    async fn force_include(&self, domain_id: &str, tx: Tx) -> anyhow::Result<()>;
+    /// Follows `sender`'s next nonce in the domain's state, which the
+    /// mempool checks submitted txs against.
+    async fn sync_nonce(&self, domain_id: &str, sender: Address, nonce: u64) -> anyhow::Result<()>;
     async fn build_batch(&self, domain_id: &str) -> anyhow::Result<SequencedBatch>;
     async fn domain_head(&self, domain_id: &str) -> anyhow::Result<u64>;
     async fn batch_status(&self, domain_id: &str, batch_id: &str) -> anyhow::Result<Option<BatchStatus>>;
+    /// Record a stage reached outside the sequencer, i.e. L1 commitment or
+    /// finalization reported by the settlement submitter.
+    async fn record_batch_stage(
+        &self,
+        domain_id: &str,
+        batch_id: &str,
+        stage: BatchStage,
+    ) -> anyhow::Result<BatchEvent>;
 }
 
 pub struct InMemorySequencer {
-    pub pending: Arc<Mutex<Vec<(String, Tx)>>>,
+    pub mempool: Mempool,
+    /// Txs force-included on L1, batched ahead of the mempool.
+    pub forced: Arc<Mutex<Vec<(String, Tx)>>>,
     pub da: InMemoryDA,
     pub batches: Arc<Mutex<HashMap<String, Vec<SequencedBatch>>>>,
     pub heads: Arc<Mutex<HashMap<String, u64>>>,
     pub zk: Option<Arc<dyn ZkBackend>>,
+    pub events: BatchEventLog,
+    pub metrics: Option<Metrics>,
+    /// Splits the fees a batch's txs offer; the DA share is meant to cover
+    /// posting the blob.
+    pub fee_split: FeeSplit,
+    /// Price of one posted blob byte.
+    pub da_byte_price: u128,
+    /// Signs pre-confirmations; its address must hold the domain's bond.
+    pub preconf_key: Arc<SigningKey>,
+    /// Batches within which a pre-confirmed tx is promised to land.
+    pub preconf_batches: u64,
+    /// Pre-confirmations handed out, kept until they expire.
+    pub preconfirmations: Arc<Mutex<HashMap<Hash, Preconfirmation>>>,
+    /// Keeps pending txs, batches and heads across restarts; without one
+    /// they live in memory only.
+    pub store: Option<BatchStore>,
+}
+
+impl InMemorySequencer {
+    fn record_pending(&self, depth: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.mempool_depth.set(depth as i64);
+        }
+    }
+
+    /// Loads what the store kept before a restart and re-posts the batches
+    /// that were built but never reached DA, returning how many were.
+    pub async fn recover(&self) -> anyhow::Result<usize> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+        let stored = store.load()?;
+        let mut stale = Vec::new();
+        for (domain_id, tx) in stored.pending {
+            let hash = tx_hash(&tx);
+            if let Err(err) = self.mempool.restore(&domain_id, tx) {
+                warn!("dropping stored tx for domain {domain_id}: {err}");
+                stale.push(hash);
+            }
+        }
+        store.remove_pending(&stale)?;
+        self.record_pending(self.mempool.len());
+        *self.forced.lock().unwrap() = stored.forced;
+        *self.heads.lock().unwrap() = stored.heads;
+        let unposted: Vec<SequencedBatch> = stored
+            .batches
+            .values()
+            .flatten()
+            .filter(|b| b.da_blob.is_none() && !b.txs.is_empty())
+            .cloned()
+            .collect();
+        *self.batches.lock().unwrap() = stored.batches;
+        for batch in &unposted {
+            info!("re-posting batch {} to DA", batch.batch_id);
+            self.post_batch(batch.clone()).await?;
+        }
+        Ok(unposted.len())
+    }
+
+    /// Posts a built batch's txs to DA and proves the blob, then records the
+    /// batch as posted and advances the domain's head.
+    async fn post_batch(&self, mut batch: SequencedBatch) -> anyhow::Result<SequencedBatch> {
+        let domain_id = batch.domain_id.clone();
+        let batch_bytes = encode_batch(&batch.txs)?;
+        let blob = self.da.submit_blob(&domain_id, &batch_bytes).await?;
+        batch.proof = self.prove(&domain_id, &blob, batch_bytes).await?;
+        batch.da_blob = Some(blob);
+        let index = {
+            let mut batches = self.batches.lock().unwrap();
+            let list = batches.entry(domain_id.clone()).or_default();
+            let index = list
+                .iter()
+                .position(|b| b.batch_id == batch.batch_id)
+                .ok_or_else(|| anyhow::anyhow!("unknown batch {}", batch.batch_id))?;
+            list[index] = batch.clone();
+            index as u64
+        };
+        // Only posted batches count, as only they advance the domain's batch
+        // height on L1 that pre-confirmations are judged against.
+        let head = {
+            let mut heads = self.heads.lock().unwrap();
+            let height = heads.entry(domain_id.clone()).or_insert(0);
+            *height += 1;
+            *height
+        };
+        if let Ok(domain_uuid) = Uuid::parse_str(&domain_id) {
+            self.preconfirmations
+                .lock()
+                .unwrap()
+                .retain(|_, p| p.domain_id != domain_uuid || p.expiry > head);
+        }
+        if let Some(store) = &self.store {
+            let forced = self.forced.lock().unwrap().clone();
+            store.put_batch(index, &batch, &[], &forced, head)?;
+        }
+        let blob_id = batch.da_blob.as_ref().map(|b| b.id.clone());
+        let batch_id = batch.batch_id.as_str();
+        self.events.publish(&domain_id, batch_id, BatchStage::BlobPosted, blob_id.clone());
+        if batch.proof.is_some() {
+            self.events.publish(&domain_id, batch_id, BatchStage::ProofGenerated, blob_id);
+        }
+        Ok(batch)
+    }
+
+    async fn prove(
+        &self,
+        domain_id: &str,
+        blob_ref: &BlobRef,
+        batch_bytes: Vec<u8>,
+    ) -> anyhow::Result<Option<ProofArtifact>> {
+        let (Some(zk), Ok(domain_uuid)) = (self.zk.clone(), Uuid::parse_str(domain_id)) else {
+            return Ok(None);
+        };
+        let input = RollupProofInput {
+            domain_id: domain_uuid,
+            blob_id: blob_ref.id.clone(),
+            da_root: blob_ref.commitment.root,
+            state_root: [0u8; 32],
+            batch_bytes,
+        };
+        let witness = encode_rollup_input(&input)?;
+        let commitments = rollup_commitments(&input);
+        let started = Instant::now();
+        let proved = zk
+            .prove(ProofRequest {
+                program_id: ProgramId::Rollup,
+                witness,
+                commitments: Some(commitments),
+            })
+            .await;
+        if let (Some(metrics), Ok(_)) = (&self.metrics, &proved) {
+            metrics.zk_proof_seconds.observe(started.elapsed().as_secs_f64());
+        }
+        match proved {
+            Ok(artifact) => {
+                if let Err(err) = zk.verify(&artifact).await {
+                    warn!("rollup proof verification failed: {err}");
+                    Ok(None)
+                } else {
+                    Ok(Some(artifact))
+                }
+            }
+            Err(err) => {
+                warn!("rollup proof generation failed: {err}");
+                Ok(None)
+            }
+        }
+    }
 }
 
 #[async_trait]
 impl Sequencer for InMemorySequencer {
-    async fn submit_tx(&self, domain_id: &str, tx: Tx) -> anyhow::Result<()> {
+    async fn submit_tx(&self, domain_id: &str, tx: Tx) -> anyhow::Result<Option<Preconfirmation>> {
+        let chain_id = tx.chain_id.clone();
+        let hash = self.mempool.insert(domain_id, tx.clone())?;
+        if let Some(store) = &self.store {
+            store.put_pending(domain_id, &hash, &tx)?;
+        }
         info!("queued tx for domain {}", domain_id);
-        self.pending
-            .lock()
-            .unwrap()
-            .push((domain_id.to_string(), tx));
+        self.record_pending(self.mempool.len());
+        let preconf = Uuid::parse_str(domain_id).ok().map(|domain_uuid| {
+            let head = *self.heads.lock().unwrap().get(domain_id).unwrap_or(&0);
+            Preconfirmation::sign(
+                &self.preconf_key,
+                &chain_id,
+                domain_uuid,
+                hash,
+                head,
+                self.preconf_batches,
+            )
+        });
+        if let Some(preconf) = &preconf {
+            self.preconfirmations
+                .lock()
+                .unwrap()
+                .insert(preconf.tx_hash, preconf.clone());
+        }
+        Ok(preconf)
+    }
+
+    async fn preconfirmation(&self, tx_hash: &Hash) -> anyhow::Result<Option<Preconfirmation>> {
+        Ok(self.preconfirmations.lock().unwrap().get(tx_hash).cloned())
+    }
+
+    async fn force_include(&self, domain_id: &str, tx: Tx) -> anyhow::Result<()> {
+        info!("force-included tx for domain {}", domain_id);
+        let mut forced = self.forced.lock().unwrap();
+        forced.push((domain_id.to_string(), tx));
+        if let Some(store) = &self.store {
+            store.put_forced(&forced)?;
+        }
+        Ok(())
+    }
+
+    async fn sync_nonce(&self, domain_id: &str, sender: Address, nonce: u64) -> anyhow::Result<()> {
+        let dropped = self.mempool.set_nonce(domain_id, sender, nonce);
+        if let Some(store) = &self.store {
+            store.remove_pending(&dropped)?;
+        }
+        self.record_pending(self.mempool.len());
         Ok(())
     }
 
     async fn build_batch(&self, domain_id: &str) -> anyhow::Result<SequencedBatch> {
-        let mut pending = self.pending.lock().unwrap();
         let mut txs = Vec::new();
-        let mut remaining = Vec::new();
-        for (d, tx) in pending.drain(..) {
-            if d == domain_id {
-                txs.push(tx);
-            } else {
-                remaining.push((d, tx));
-            }
-        }
-        *pending = remaining;
-        let blob = if !txs.is_empty() {
-            let bytes = serde_json::to_vec(&txs)?;
-            Some(self.da.submit_blob(domain_id, &bytes).await?)
-        } else {
-            None
-        };
-        let proof = if let (Some(zk), Some(blob_ref)) = (self.zk.clone(), blob.clone()) {
-            match Uuid::parse_str(domain_id) {
-                Ok(domain_uuid) => {
-                    let da_root = blob_ref.commitment.root;
-                    let input = RollupProofInput {
-                        domain_id: domain_uuid,
-                        blob_id: blob_ref.id.clone(),
-                        da_root,
-                        state_root: [0u8; 32],
-                        batch_bytes: serde_json::to_vec(&txs)?,
-                    };
-                    let witness = encode_rollup_input(&input)?;
-                    let commitments = rollup_commitments(&input);
-                    match zk
-                        .prove(ProofRequest {
-                            program_id: ProgramId::Rollup,
-                            witness,
-                            commitments: Some(commitments),
-                        })
-                        .await
-                    {
-                        Ok(artifact) => {
-                            if let Err(err) = zk.verify(&artifact).await {
-                                warn!("rollup proof verification failed: {err}");
-                                None
-                            } else {
-                                Some(artifact)
-                            }
-                        }
-                        Err(err) => {
-                            warn!("rollup proof generation failed: {err}");
-                            None
-                        }
-                    }
+        let mut dropped = Vec::new();
+        let forced = {
+            let mut forced = self.forced.lock().unwrap();
+            forced.retain(|(d, tx)| {
+                let take = d == domain_id;
+                if take {
+                    // Pending txs the forced one displaces are dropped.
+                    let sender = runtime::address_from_pubkey(&tx.public_key);
+                    dropped.extend(self.mempool.set_nonce(domain_id, sender, tx.nonce + 1));
+                    txs.push(tx.clone());
                 }
-                Err(_) => None,
-            }
+                !take
+            });
+            forced.clone()
+        };
+        let head = *self.heads.lock().unwrap().get(domain_id).unwrap_or(&0);
+        txs.extend(self.mempool.take_batch(domain_id, head));
+        self.record_pending(self.mempool.len());
+        let blob_bytes = if txs.is_empty() {
+            0
         } else {
-            None
+            encode_batch(&txs)?.len()
         };
-        let mut batches = self.batches.lock().unwrap();
-        let next_id = batches
-            .get(domain_id)
-            .map(|v| v.len() as u64)
-            .unwrap_or(0);
-        let batch = SequencedBatch {
-            domain_id: domain_id.to_string(),
-            batch_id: format!("{}-{}", domain_id, next_id),
-            txs,
-            da_blob: blob.clone(),
-            proof,
+        let costs = BatchCosts::new(&txs, blob_bytes, &self.fee_split, self.da_byte_price);
+        if let Some(metrics) = &self.metrics {
+            metrics.da_batch_bytes.inc_by(costs.blob_bytes as u64);
+        }
+        if costs.da_shortfall() > 0 {
+            warn!(
+                "batch for domain {} underpays DA by {} ({} bytes)",
+                domain_id,
+                costs.da_shortfall(),
+                costs.blob_bytes
+            );
+        }
+        let (index, batch) = {
+            let mut batches = self.batches.lock().unwrap();
+            let list = batches.entry(domain_id.to_string()).or_default();
+            let batch = SequencedBatch {
+                domain_id: domain_id.to_string(),
+                batch_id: format!("{}-{}", domain_id, list.len()),
+                txs,
+                da_blob: None,
+                proof: None,
+                costs,
+            };
+            list.push(batch.clone());
+            (list.len() as u64 - 1, batch)
         };
-        batches.entry(domain_id.to_string()).or_default().push(batch.clone());
-        let mut heads = self.heads.lock().unwrap();
-        let height = heads.entry(domain_id.to_string()).or_insert(0);
-        *height += 1;
-        Ok(batch)
+        // The batch is on disk before its blob is posted, so a crash while
+        // posting leaves it for `recover` to re-post.
+        if let Some(store) = &self.store {
+            let taken: Vec<Hash> = batch.txs.iter().map(tx_hash).chain(dropped).collect();
+            store.put_batch(index, &batch, &taken, &forced, head)?;
+        }
+        self.events.publish(domain_id, &batch.batch_id, BatchStage::Built, None);
+        if batch.txs.is_empty() {
+            return Ok(batch);
+        }
+        self.post_batch(batch).await
     }
 
     async fn domain_head(&self, domain_id: &str) -> anyhow::Result<u64> {
@@ -221,8 +487,45 @@ impl Sequencer for InMemorySequencer {
                 batch_id: b.batch_id.clone(),
                 posted: b.da_blob.is_some(),
                 blob_ref: b.da_blob.clone(),
+                costs: b.costs.clone(),
             });
         Ok(status)
     }
+
+    async fn record_batch_stage(
+        &self,
+        domain_id: &str,
+        batch_id: &str,
+        stage: BatchStage,
+    ) -> anyhow::Result<BatchEvent> {
+        let blob_id = {
+            let batches = self.batches.lock().unwrap();
+            let Some(batch) = batches
+                .get(domain_id)
+                .and_then(|list| list.iter().find(|b| b.batch_id == batch_id))
+            else {
+                anyhow::bail!("unknown batch {batch_id}");
+            };
+            batch.da_blob.as_ref().map(|b| b.id.clone())
+        };
+        let current = self.events.stage_of(domain_id, batch_id);
+        match stage {
+            BatchStage::L1Committed => {
+                if blob_id.is_none() {
+                    anyhow::bail!("batch has no posted blob to commit");
+                }
+                if current >= Some(BatchStage::L1Committed) {
+                    anyhow::bail!("batch already committed");
+                }
+            }
+            BatchStage::Finalized => {
+                if current != Some(BatchStage::L1Committed) {
+                    anyhow::bail!("batch must be committed before finalization");
+                }
+            }
+            _ => anyhow::bail!("stage {stage:?} is recorded by the sequencer itself"),
+        }
+        Ok(self.events.publish(domain_id, batch_id, stage, blob_id))
+    }
 }
 
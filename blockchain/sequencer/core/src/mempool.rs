@@ -0,0 +1,284 @@
+use runtime::{address_from_pubkey, tx_hash, Address, Hash, Tx};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Order in which a batch takes pending txs. Every policy keeps each
+/// sender's txs in nonce order.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderingPolicy {
+    /// Arrival order.
+    #[default]
+    Fifo,
+    /// Highest priority fee first, arrival order among equal fees.
+    PriorityFee,
+    /// Order fixed by each tx's hash keyed with the domain head, so neither
+    /// arrival time nor the sequencer's choice decides it.
+    FairHash,
+}
+
+impl std::str::FromStr for OrderingPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "fifo" => Ok(Self::Fifo),
+            "priority_fee" | "priority" => Ok(Self::PriorityFee),
+            "fair_hash" | "fair" => Ok(Self::FairHash),
+            other => anyhow::bail!("unknown ordering policy {other}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MempoolConfig {
+    pub policy: OrderingPolicy,
+    /// Pending txs a domain holds at once.
+    pub max_domain_txs: usize,
+    /// Largest JSON-encoded tx accepted.
+    pub max_tx_bytes: usize,
+    /// Pending txs one sender may hold in a domain.
+    pub max_sender_txs: usize,
+    /// Txs one sender may submit to a domain per `rate_window`.
+    pub sender_rate: usize,
+    pub rate_window: Duration,
+    /// Txs a batch takes at most.
+    pub max_batch_txs: usize,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            policy: OrderingPolicy::Fifo,
+            max_domain_txs: 10_000,
+            max_tx_bytes: 128 * 1024,
+            max_sender_txs: 64,
+            sender_rate: 32,
+            rate_window: Duration::from_secs(1),
+            max_batch_txs: 1_024,
+        }
+    }
+}
+
+struct PooledTx {
+    arrival: u64,
+    hash: Hash,
+    tx: Tx,
+}
+
+#[derive(Default)]
+struct DomainPool {
+    /// Each sender's pending txs, in nonce order with no gaps.
+    senders: HashMap<Address, VecDeque<PooledTx>>,
+    /// Next nonce of each sender in domain state, as last synced or
+    /// advanced by taken batches.
+    nonces: HashMap<Address, u64>,
+    submissions: HashMap<Address, VecDeque<Instant>>,
+    len: usize,
+}
+
+impl DomainPool {
+    fn next_nonce(&self, sender: &Address) -> u64 {
+        let base = self.nonces.get(sender).copied().unwrap_or(0);
+        base + self.senders.get(sender).map_or(0, |txs| txs.len() as u64)
+    }
+}
+
+#[derive(Default)]
+struct MempoolInner {
+    domains: HashMap<String, DomainPool>,
+    next_arrival: u64,
+}
+
+/// Per-domain pool of txs waiting for a batch.
+#[derive(Clone, Default)]
+pub struct Mempool {
+    config: MempoolConfig,
+    inner: Arc<Mutex<MempoolInner>>,
+}
+
+impl Mempool {
+    pub fn new(config: MempoolConfig) -> Self {
+        Self {
+            config,
+            inner: Arc::default(),
+        }
+    }
+
+    pub fn config(&self) -> &MempoolConfig {
+        &self.config
+    }
+
+    /// Admits `tx` to `domain_id`'s pool. Its nonce must be the sender's
+    /// next after domain state and the sender's pending txs.
+    pub fn insert(&self, domain_id: &str, tx: Tx) -> anyhow::Result<Hash> {
+        let size = serde_json::to_vec(&tx)?.len();
+        if size > self.config.max_tx_bytes {
+            anyhow::bail!(
+                "tx is {size} bytes, over the {} byte limit",
+                self.config.max_tx_bytes
+            );
+        }
+        let sender = address_from_pubkey(&tx.public_key);
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        let arrival = inner.next_arrival;
+        let pool = inner.domains.entry(domain_id.to_string()).or_default();
+        if pool.len >= self.config.max_domain_txs {
+            anyhow::bail!("mempool for domain {domain_id} is full");
+        }
+        let pending = pool.senders.get(&sender).map_or(0, |txs| txs.len());
+        if pending >= self.config.max_sender_txs {
+            anyhow::bail!("sender has {pending} txs pending, the most allowed");
+        }
+        let submissions = pool.submissions.entry(sender).or_default();
+        while submissions
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= self.config.rate_window)
+        {
+            submissions.pop_front();
+        }
+        if submissions.len() >= self.config.sender_rate {
+            anyhow::bail!("sender is over its rate limit");
+        }
+        let expected = pool.next_nonce(&sender);
+        if tx.nonce < expected {
+            anyhow::bail!("nonce {} is too low, expected {expected}", tx.nonce);
+        }
+        if tx.nonce > expected {
+            anyhow::bail!("nonce {} leaves a gap, expected {expected}", tx.nonce);
+        }
+        let hash = tx_hash(&tx);
+        pool.submissions.entry(sender).or_default().push_back(now);
+        pool.senders
+            .entry(sender)
+            .or_default()
+            .push_back(PooledTx { arrival, hash, tx });
+        pool.len += 1;
+        inner.next_arrival += 1;
+        Ok(hash)
+    }
+
+    /// Puts back a tx admitted before a restart, skipping the spam limits it
+    /// already passed. A sender's first restored tx resumes its nonce.
+    pub fn restore(&self, domain_id: &str, tx: Tx) -> anyhow::Result<Hash> {
+        let sender = address_from_pubkey(&tx.public_key);
+        let mut inner = self.inner.lock().unwrap();
+        let arrival = inner.next_arrival;
+        let pool = inner.domains.entry(domain_id.to_string()).or_default();
+        if !pool.senders.contains_key(&sender) {
+            let nonce = pool.nonces.entry(sender).or_insert(tx.nonce);
+            *nonce = (*nonce).max(tx.nonce);
+        }
+        let expected = pool.next_nonce(&sender);
+        if tx.nonce != expected {
+            anyhow::bail!("restored nonce {} does not follow {expected}", tx.nonce);
+        }
+        let hash = tx_hash(&tx);
+        pool.senders
+            .entry(sender)
+            .or_default()
+            .push_back(PooledTx { arrival, hash, tx });
+        pool.len += 1;
+        inner.next_arrival += 1;
+        Ok(hash)
+    }
+
+    /// Follows `sender`'s nonce in `domain_id`'s state, dropping pending
+    /// txs it has moved past. Returns the hashes of the dropped txs.
+    pub fn set_nonce(&self, domain_id: &str, sender: Address, nonce: u64) -> Vec<Hash> {
+        let mut inner = self.inner.lock().unwrap();
+        let pool = inner.domains.entry(domain_id.to_string()).or_default();
+        pool.nonces.insert(sender, nonce);
+        let mut dropped = Vec::new();
+        if let Some(txs) = pool.senders.get_mut(&sender) {
+            // Txs past a jump in the nonce can no longer follow it.
+            let keep = txs.iter().any(|p| p.tx.nonce == nonce);
+            while txs.front().is_some_and(|p| !keep || p.tx.nonce < nonce) {
+                dropped.extend(txs.pop_front().map(|p| p.hash));
+            }
+            pool.len -= dropped.len();
+            if txs.is_empty() {
+                pool.senders.remove(&sender);
+            }
+        }
+        dropped
+    }
+
+    /// Takes up to `max_batch_txs` of `domain_id`'s txs in policy order,
+    /// advancing their senders' nonces. `head` keys fair ordering.
+    pub fn take_batch(&self, domain_id: &str, head: u64) -> Vec<Tx> {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(pool) = inner.domains.get_mut(domain_id) else {
+            return Vec::new();
+        };
+        let seed = blake3::hash(&[domain_id.as_bytes(), &head.to_le_bytes()].concat());
+        let window = self.config.rate_window;
+        pool.submissions
+            .retain(|_, at| at.back().is_some_and(|t| t.elapsed() < window));
+        let rank = |pooled: &PooledTx| -> (Reverse<u128>, Hash, u64) {
+            match self.config.policy {
+                OrderingPolicy::Fifo => (Reverse(0), [0u8; 32], pooled.arrival),
+                OrderingPolicy::PriorityFee => {
+                    let tip = pooled
+                        .tx
+                        .max_priority_fee
+                        .or(pooled.tx.gas_price)
+                        .unwrap_or(0);
+                    (Reverse(tip), [0u8; 32], pooled.arrival)
+                }
+                OrderingPolicy::FairHash => {
+                    let mut hasher = blake3::Hasher::new_keyed(seed.as_bytes());
+                    hasher.update(&pooled.hash);
+                    (Reverse(0), *hasher.finalize().as_bytes(), pooled.arrival)
+                }
+            }
+        };
+        // Only a sender's lowest pending nonce competes, so its txs stay in
+        // order whatever the policy.
+        let mut heads: BinaryHeap<Reverse<(_, Address)>> = pool
+            .senders
+            .iter()
+            .filter_map(|(sender, txs)| txs.front().map(|p| Reverse((rank(p), *sender))))
+            .collect();
+        let mut taken = Vec::new();
+        while taken.len() < self.config.max_batch_txs {
+            let Some(Reverse((_, sender))) = heads.pop() else {
+                break;
+            };
+            let Some(txs) = pool.senders.get_mut(&sender) else {
+                continue;
+            };
+            let Some(pooled) = txs.pop_front() else {
+                continue;
+            };
+            if let Some(next) = txs.front() {
+                heads.push(Reverse((rank(next), sender)));
+            } else {
+                pool.senders.remove(&sender);
+            }
+            pool.nonces.insert(sender, pooled.tx.nonce + 1);
+            pool.len -= 1;
+            taken.push(pooled.tx);
+        }
+        taken
+    }
+
+    /// Pending txs across all domains.
+    pub fn len(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner.domains.values().map(|pool| pool.len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn domain_len(&self, domain_id: &str) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner.domains.get(domain_id).map_or(0, |pool| pool.len)
+    }
+}
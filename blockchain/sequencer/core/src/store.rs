@@ -0,0 +1,164 @@
+//! On-disk record of a sequencer's work, kept in sled so a restart resumes
+//! where the sequencer stopped: pending and force-included txs, every batch
+//! with its blob ref and proof, and each domain's head. A batch is written
+//! before its blob is posted, and in the same transaction that takes its
+//! txs out of the pending set, so a crash in between leaves an unposted
+//! batch to re-post rather than lost txs.
+
+use anyhow::Context;
+use runtime::{Hash, Tx};
+use serde::{Deserialize, Serialize};
+use sled::transaction::TransactionError;
+use sled::Transactional;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::SequencedBatch;
+
+const FORCED_KEY: &[u8] = b"forced";
+
+#[derive(Serialize, Deserialize)]
+struct PendingRecord {
+    domain_id: String,
+    tx: Tx,
+}
+
+/// Everything a store holds, as loaded on startup.
+#[derive(Default)]
+pub struct StoredState {
+    /// Pending txs by domain, in the order they were admitted.
+    pub pending: Vec<(String, Tx)>,
+    pub forced: Vec<(String, Tx)>,
+    pub batches: HashMap<String, Vec<SequencedBatch>>,
+    pub heads: HashMap<String, u64>,
+}
+
+#[derive(Clone)]
+pub struct BatchStore {
+    db: sled::Db,
+    /// Pending txs keyed by admission order, with a hash index to remove
+    /// them by once batched.
+    pending: sled::Tree,
+    pending_index: sled::Tree,
+    batches: sled::Tree,
+    heads: sled::Tree,
+    meta: sled::Tree,
+}
+
+fn batch_key(domain_id: &str, index: u64) -> Vec<u8> {
+    [domain_id.as_bytes(), &[0], &index.to_be_bytes()].concat()
+}
+
+fn tx_error(err: TransactionError<String>) -> anyhow::Error {
+    match err {
+        TransactionError::Abort(msg) => anyhow::anyhow!(msg),
+        TransactionError::Storage(err) => err.into(),
+    }
+}
+
+impl BatchStore {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let db = sled::open(path)
+            .with_context(|| format!("opening batch store at {}", path.display()))?;
+        Ok(Self {
+            pending: db.open_tree("pending")?,
+            pending_index: db.open_tree("pending_index")?,
+            batches: db.open_tree("batches")?,
+            heads: db.open_tree("heads")?,
+            meta: db.open_tree("meta")?,
+            db,
+        })
+    }
+
+    /// Records a tx the mempool admitted.
+    pub fn put_pending(&self, domain_id: &str, hash: &Hash, tx: &Tx) -> anyhow::Result<()> {
+        let seq = self.db.generate_id()?.to_be_bytes();
+        let record = serde_json::to_vec(&PendingRecord {
+            domain_id: domain_id.to_string(),
+            tx: tx.clone(),
+        })?;
+        self.pending.insert(seq, record)?;
+        self.pending_index.insert(hash, &seq)?;
+        Ok(())
+    }
+
+    /// Forgets pending txs the mempool dropped.
+    pub fn remove_pending(&self, hashes: &[Hash]) -> anyhow::Result<()> {
+        for hash in hashes {
+            if let Some(seq) = self.pending_index.remove(hash)? {
+                self.pending.remove(seq)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn put_forced(&self, forced: &[(String, Tx)]) -> anyhow::Result<()> {
+        self.meta.insert(FORCED_KEY, serde_json::to_vec(forced)?)?;
+        Ok(())
+    }
+
+    /// Writes a batch, taking its txs out of the pending set, and the
+    /// domain's head, all at once, then flushes to disk.
+    pub fn put_batch(
+        &self,
+        index: u64,
+        batch: &SequencedBatch,
+        taken: &[Hash],
+        forced: &[(String, Tx)],
+        head: u64,
+    ) -> anyhow::Result<()> {
+        let record = serde_json::to_vec(batch)?;
+        let forced = serde_json::to_vec(forced)?;
+        let key = batch_key(&batch.domain_id, index);
+        (
+            &self.pending,
+            &self.pending_index,
+            &self.batches,
+            &self.heads,
+            &self.meta,
+        )
+            .transaction(|(pending, pending_index, batches, heads, meta)| {
+                for hash in taken {
+                    if let Some(seq) = pending_index.remove(hash.as_slice())? {
+                        pending.remove(seq)?;
+                    }
+                }
+                batches.insert(key.as_slice(), record.as_slice())?;
+                heads.insert(batch.domain_id.as_bytes(), &head.to_be_bytes())?;
+                meta.insert(FORCED_KEY, forced.as_slice())?;
+                Ok(())
+            })
+            .map_err(tx_error)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn load(&self) -> anyhow::Result<StoredState> {
+        let mut state = StoredState::default();
+        for entry in self.pending.iter() {
+            let (_, value) = entry?;
+            let record: PendingRecord = serde_json::from_slice(&value)?;
+            state.pending.push((record.domain_id, record.tx));
+        }
+        if let Some(forced) = self.meta.get(FORCED_KEY)? {
+            state.forced = serde_json::from_slice(&forced)?;
+        }
+        // Keys order each domain's batches by index.
+        for entry in self.batches.iter() {
+            let (_, value) = entry?;
+            let batch: SequencedBatch = serde_json::from_slice(&value)?;
+            state
+                .batches
+                .entry(batch.domain_id.clone())
+                .or_default()
+                .push(batch);
+        }
+        for entry in self.heads.iter() {
+            let (key, value) = entry?;
+            let domain_id = String::from_utf8(key.to_vec())?;
+            let head = <[u8; 8]>::try_from(value.as_ref()).context("corrupt head record")?;
+            state.heads.insert(domain_id, u64::from_be_bytes(head));
+        }
+        Ok(state)
+    }
+}
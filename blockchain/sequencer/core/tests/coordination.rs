@@ -0,0 +1,125 @@
+use ed25519_dalek::SigningKey;
+use runtime::{address_from_pubkey, failover_pick, Address};
+use sequencer_core::{ChainBond, Coordinator, DomainSequencers, GossipBody, SequencerGossip};
+use uuid::Uuid;
+
+const CHAIN: &str = "kova-devnet";
+
+fn key(seed: u8) -> SigningKey {
+    SigningKey::from_bytes(&[seed; 32])
+}
+
+fn address(seed: u8) -> Address {
+    address_from_pubkey(&key(seed).verifying_key().to_bytes())
+}
+
+fn chain(height: u64, seeds: &[u8]) -> DomainSequencers {
+    let mut bonds: Vec<ChainBond> = seeds
+        .iter()
+        .map(|seed| ChainBond {
+            sequencer: address(*seed),
+            bond: 1_000 * *seed as u128,
+            exiting_since: None,
+        })
+        .collect();
+    bonds.sort_by_key(|b| b.sequencer);
+    DomainSequencers {
+        height,
+        rotation_blocks: 10,
+        failover_blocks: 4,
+        min_bond: 1_000,
+        round: None,
+        bonds,
+    }
+}
+
+fn coordinators(domain: Uuid, height: u64) -> Vec<(u8, Coordinator)> {
+    (1..=3)
+        .map(|seed| {
+            let mut c = Coordinator::new(CHAIN, domain, key(seed));
+            c.sync(&chain(height, &[1, 2, 3]));
+            (seed, c)
+        })
+        .collect()
+}
+
+#[test]
+fn every_sequencer_agrees_on_the_leader_the_runtime_draws() {
+    let domain = Uuid::from_u128(7);
+    let set = coordinators(domain, 20);
+    let view = chain(20, &[1, 2, 3]);
+    let stakes: Vec<u128> = view.bonds.iter().map(|b| b.bond).collect();
+    let pick = failover_pick(&stakes, &domain, 2, 0).unwrap();
+    let expected = hex::encode(view.bonds[pick].sequencer);
+
+    let leaders: Vec<_> = set.iter().filter(|(_, c)| c.is_leader()).collect();
+    assert_eq!(leaders.len(), 1);
+    for (_, c) in &set {
+        assert_eq!(c.round(), (2, 0));
+        assert_eq!(c.leader().unwrap().id, expected);
+    }
+}
+
+#[test]
+fn a_silent_leader_loses_its_slot_but_an_announced_batch_keeps_it() {
+    let domain = Uuid::from_u128(7);
+    let mut set = coordinators(domain, 20);
+    let first = set[0].1.leader().unwrap().id;
+
+    // No batch four blocks in: the round moves to another sequencer.
+    for (_, c) in set.iter_mut() {
+        c.advance(24);
+        assert_eq!(c.round(), (2, 1));
+    }
+    let second = set[0].1.leader().unwrap().id;
+    assert_ne!(first, second);
+
+    // The new leader announces and everyone holds the round with it.
+    let leader = set
+        .iter()
+        .position(|(_, c)| c.is_leader())
+        .expect("one sequencer leads");
+    let announce = set[leader].1.announce("batch-1".into(), None, 5);
+    for (i, (_, c)) in set.iter_mut().enumerate() {
+        if i != leader {
+            c.observe(&announce).unwrap();
+        }
+        assert!(c.posted());
+        c.advance(28);
+        assert_eq!(c.round(), (2, 1));
+        assert_eq!(c.leader().unwrap().id, second);
+    }
+
+    // The next round starts over from its first draw.
+    set[0].1.advance(30);
+    assert_eq!(set[0].1.round(), (3, 0));
+    assert!(!set[0].1.posted());
+}
+
+#[test]
+fn gossip_is_only_heard_from_bonded_sequencers_of_the_domain() {
+    let domain = Uuid::from_u128(7);
+    let mut set = coordinators(domain, 20);
+    let heartbeat = set[1].1.heartbeat(3);
+    assert_eq!(heartbeat.verify().unwrap(), address(2));
+    assert_eq!(set[0].1.observe(&heartbeat).unwrap(), address(2));
+    assert_eq!(set[0].1.live(10), vec![address(2)]);
+
+    let mut forged = heartbeat.clone();
+    forged.body = GossipBody::Heartbeat { head: 99 };
+    assert!(set[0].1.observe(&forged).is_err());
+
+    let outsider = SequencerGossip::sign(
+        &key(9),
+        CHAIN,
+        domain,
+        2,
+        0,
+        GossipBody::Heartbeat { head: 3 },
+    );
+    let err = set[0].1.observe(&outsider).unwrap_err();
+    assert!(err.to_string().contains("not a bonded"));
+
+    let elsewhere = Coordinator::new(CHAIN, Uuid::from_u128(8), key(2)).heartbeat(3);
+    assert!(set[0].1.observe(&elsewhere).is_err());
+}
@@ -0,0 +1,28 @@
+use sequencer_core::{BatchEventLog, BatchStage};
+
+#[tokio::test]
+async fn resume_replays_after_cursor_then_streams_live() {
+    let log = BatchEventLog::new(16);
+    log.publish("a", "a-0", BatchStage::Built, None);
+    let posted = log.publish("a", "a-0", BatchStage::BlobPosted, Some("blob".into()));
+    log.publish("b", "b-0", BatchStage::Built, None);
+
+    let (backlog, mut live) = log.resume(posted.cursor - 1, Some("a"));
+    assert_eq!(backlog.len(), 1);
+    assert_eq!(backlog[0].stage, BatchStage::BlobPosted);
+
+    let committed = log.publish("a", "a-0", BatchStage::L1Committed, Some("blob".into()));
+    assert_eq!(live.recv().await.unwrap().cursor, committed.cursor);
+    assert_eq!(log.stage_of("a", "a-0"), Some(BatchStage::L1Committed));
+}
+
+#[test]
+fn history_is_bounded_by_retention() {
+    let log = BatchEventLog::new(2);
+    for i in 0..5 {
+        log.publish("a", &format!("a-{i}"), BatchStage::Built, None);
+    }
+    let retained = log.since(0, None);
+    assert_eq!(retained.len(), 2);
+    assert_eq!(retained[0].cursor, 4);
+}
@@ -0,0 +1,128 @@
+use ed25519_dalek::SigningKey;
+use runtime::{address_from_pubkey, Tx, TxPayload};
+use sequencer_core::{Mempool, MempoolConfig, OrderingPolicy};
+use std::time::Duration;
+
+fn tx(seed: u8, nonce: u64, tip: u128) -> Tx {
+    let sk = SigningKey::from_bytes(&[seed; 32]);
+    Tx {
+        chain_id: "kova-devnet".into(),
+        nonce,
+        gas_limit: 21_000,
+        max_fee: Some(10),
+        max_priority_fee: Some(tip),
+        gas_price: None,
+        payload: TxPayload::Transfer {
+            to: [9u8; 32],
+            amount: 1,
+        },
+        public_key: sk.verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    }
+}
+
+fn pool(policy: OrderingPolicy) -> Mempool {
+    Mempool::new(MempoolConfig {
+        policy,
+        ..MempoolConfig::default()
+    })
+}
+
+fn order(txs: &[Tx]) -> Vec<(u8, u64)> {
+    txs.iter().map(|tx| (tx.public_key[0], tx.nonce)).collect()
+}
+
+#[test]
+fn policies_order_batches_but_keep_each_senders_nonces_in_order() {
+    let key_byte = |seed: u8| {
+        SigningKey::from_bytes(&[seed; 32])
+            .verifying_key()
+            .to_bytes()[0]
+    };
+    let (a, b) = (key_byte(1), key_byte(2));
+
+    let fifo = pool(OrderingPolicy::Fifo);
+    for tx in [tx(1, 0, 1), tx(2, 0, 5), tx(1, 1, 9)] {
+        fifo.insert("d", tx).unwrap();
+    }
+    assert_eq!(
+        order(&fifo.take_batch("d", 0)),
+        vec![(a, 0), (b, 0), (a, 1)]
+    );
+    assert!(fifo.is_empty());
+
+    // The tip on a's second tx cannot lift it above a's first.
+    let priority = pool(OrderingPolicy::PriorityFee);
+    for tx in [tx(1, 0, 1), tx(2, 0, 5), tx(1, 1, 9), tx(2, 1, 0)] {
+        priority.insert("d", tx).unwrap();
+    }
+    assert_eq!(
+        order(&priority.take_batch("d", 0)),
+        vec![(b, 0), (a, 0), (a, 1), (b, 1)]
+    );
+
+    // Fair ordering does not depend on arrival.
+    let (forward, backward) = (
+        pool(OrderingPolicy::FairHash),
+        pool(OrderingPolicy::FairHash),
+    );
+    let txs: Vec<Tx> = (1..=8).map(|seed| tx(seed, 0, 0)).collect();
+    for tx in &txs {
+        forward.insert("d", tx.clone()).unwrap();
+    }
+    for tx in txs.iter().rev() {
+        backward.insert("d", tx.clone()).unwrap();
+    }
+    assert_eq!(
+        order(&forward.take_batch("d", 3)),
+        order(&backward.take_batch("d", 3))
+    );
+}
+
+#[test]
+fn nonces_follow_domain_state_and_batches() {
+    let mempool = Mempool::default();
+    let sender = address_from_pubkey(&tx(1, 0, 0).public_key);
+    mempool.set_nonce("d", sender, 4);
+    let err = mempool.insert("d", tx(1, 3, 0)).unwrap_err();
+    assert!(err.to_string().contains("too low"));
+    let err = mempool.insert("d", tx(1, 5, 0)).unwrap_err();
+    assert!(err.to_string().contains("gap"));
+    mempool.insert("d", tx(1, 4, 0)).unwrap();
+    mempool.insert("d", tx(1, 5, 0)).unwrap();
+    // Another domain tracks the sender separately.
+    mempool.insert("e", tx(1, 0, 0)).unwrap();
+    assert_eq!(mempool.len(), 3);
+
+    // Domain state moving past a pending tx drops it.
+    mempool.set_nonce("d", sender, 5);
+    assert_eq!(mempool.domain_len("d"), 1);
+    assert_eq!(mempool.take_batch("d", 0)[0].nonce, 5);
+    mempool.insert("d", tx(1, 6, 0)).unwrap();
+}
+
+#[test]
+fn caps_and_rate_limits_reject_spam() {
+    let mempool = Mempool::new(MempoolConfig {
+        max_domain_txs: 3,
+        max_sender_txs: 2,
+        sender_rate: 3,
+        rate_window: Duration::from_secs(3_600),
+        max_batch_txs: 2,
+        ..MempoolConfig::default()
+    });
+    mempool.insert("d", tx(1, 0, 0)).unwrap();
+    mempool.insert("d", tx(1, 1, 0)).unwrap();
+    let err = mempool.insert("d", tx(1, 2, 0)).unwrap_err();
+    assert!(err.to_string().contains("pending"));
+    mempool.insert("d", tx(2, 0, 0)).unwrap();
+    let err = mempool.insert("d", tx(3, 0, 0)).unwrap_err();
+    assert!(err.to_string().contains("full"));
+
+    assert_eq!(mempool.take_batch("d", 0).len(), 2);
+    mempool.insert("d", tx(1, 2, 0)).unwrap();
+    assert_eq!(mempool.take_batch("d", 0).len(), 2);
+    // Batching frees room but not the sender's rate.
+    let err = mempool.insert("d", tx(1, 3, 0)).unwrap_err();
+    assert!(err.to_string().contains("rate limit"));
+}
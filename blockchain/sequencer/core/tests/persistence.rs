@@ -0,0 +1,143 @@
+use da::InMemoryDA;
+use ed25519_dalek::SigningKey;
+use runtime::{FeeSplit, Tx, TxPayload};
+use sequencer_core::{BatchEventLog, BatchStore, InMemorySequencer, Mempool, Sequencer};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const DOMAIN: &str = "00000000-0000-0000-0000-000000000007";
+
+fn tx(seed: u8, nonce: u64) -> Tx {
+    let sk = SigningKey::from_bytes(&[seed; 32]);
+    Tx {
+        chain_id: "kova-devnet".into(),
+        nonce,
+        gas_limit: 21_000,
+        max_fee: Some(10),
+        max_priority_fee: Some(1),
+        gas_price: None,
+        payload: TxPayload::Transfer {
+            to: [9u8; 32],
+            amount: 1,
+        },
+        public_key: sk.verifying_key().to_bytes().to_vec(),
+        signature: vec![],
+    }
+}
+
+/// A sequencer as it comes up after a restart, with the store at `dir`.
+async fn start(dir: &Path) -> (InMemorySequencer, usize) {
+    let sequencer = InMemorySequencer {
+        mempool: Mempool::default(),
+        forced: Arc::new(Mutex::new(vec![])),
+        da: InMemoryDA::new(),
+        batches: Arc::new(Mutex::new(HashMap::new())),
+        heads: Arc::new(Mutex::new(HashMap::new())),
+        zk: None,
+        events: BatchEventLog::default(),
+        metrics: None,
+        fee_split: FeeSplit {
+            l1_gas_burn_pct: 30,
+            l1_gas_validators_pct: 70,
+            da_validators_pct: 70,
+            da_nodes_pct: 20,
+            da_treasury_pct: 10,
+            l2_sequencer_pct: 50,
+            l2_da_costs_pct: 30,
+            l2_l1_rent_pct: 20,
+        },
+        da_byte_price: 1,
+        preconf_key: Arc::new(SigningKey::from_bytes(&[1u8; 32])),
+        preconf_batches: 4,
+        preconfirmations: Arc::new(Mutex::new(HashMap::new())),
+        store: Some(BatchStore::open(dir).unwrap()),
+    };
+    let reposted = sequencer.recover().await.unwrap();
+    (sequencer, reposted)
+}
+
+#[tokio::test]
+async fn pending_txs_batches_and_heads_survive_a_restart() {
+    let dir = std::env::temp_dir().join(format!("kova-sequencer-{}", uuid::Uuid::new_v4()));
+    {
+        let (sequencer, _) = start(&dir).await;
+        sequencer.submit_tx(DOMAIN, tx(1, 0)).await.unwrap();
+        sequencer.submit_tx(DOMAIN, tx(2, 0)).await.unwrap();
+        let batch = sequencer.build_batch(DOMAIN).await.unwrap();
+        assert!(batch.da_blob.is_some());
+        sequencer.submit_tx(DOMAIN, tx(1, 1)).await.unwrap();
+        sequencer.submit_tx(DOMAIN, tx(1, 2)).await.unwrap();
+        sequencer.force_include(DOMAIN, tx(3, 0)).await.unwrap();
+    }
+
+    let (sequencer, reposted) = start(&dir).await;
+    assert_eq!(reposted, 0);
+    assert_eq!(sequencer.domain_head(DOMAIN).await.unwrap(), 1);
+    let first = sequencer
+        .batch_status(DOMAIN, &format!("{DOMAIN}-0"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(first.posted);
+    assert_eq!(sequencer.mempool.domain_len(DOMAIN), 2);
+    // The restored sender's next nonce carries on from its pending txs.
+    let err = sequencer.submit_tx(DOMAIN, tx(1, 1)).await.unwrap_err();
+    assert!(err.to_string().contains("too low"));
+    sequencer.submit_tx(DOMAIN, tx(1, 3)).await.unwrap();
+
+    let batch = sequencer.build_batch(DOMAIN).await.unwrap();
+    assert_eq!(batch.batch_id, format!("{DOMAIN}-1"));
+    let nonces: Vec<(u8, u64)> = batch
+        .txs
+        .iter()
+        .map(|t| (t.public_key[0], t.nonce))
+        .collect();
+    let forced = tx(3, 0).public_key[0];
+    let sender = tx(1, 0).public_key[0];
+    assert_eq!(
+        nonces,
+        vec![(forced, 0), (sender, 1), (sender, 2), (sender, 3)]
+    );
+    drop(sequencer);
+
+    // Nothing batched comes back as pending.
+    let (sequencer, _) = start(&dir).await;
+    assert!(sequencer.mempool.is_empty());
+    assert_eq!(sequencer.domain_head(DOMAIN).await.unwrap(), 2);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn batches_that_never_reached_da_are_reposted_on_startup() {
+    let dir = std::env::temp_dir().join(format!("kova-sequencer-{}", uuid::Uuid::new_v4()));
+    {
+        let (sequencer, _) = start(&dir).await;
+        sequencer.submit_tx(DOMAIN, tx(1, 0)).await.unwrap();
+        let mut batch = sequencer.build_batch(DOMAIN).await.unwrap();
+        // As if the sequencer crashed between writing the batch and posting
+        // its blob.
+        batch.da_blob = None;
+        sequencer
+            .store
+            .as_ref()
+            .unwrap()
+            .put_batch(0, &batch, &[], &[], 0)
+            .unwrap();
+    }
+
+    let (sequencer, reposted) = start(&dir).await;
+    assert_eq!(reposted, 1);
+    let status = sequencer
+        .batch_status(DOMAIN, &format!("{DOMAIN}-0"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(status.posted);
+    assert_eq!(sequencer.domain_head(DOMAIN).await.unwrap(), 1);
+    drop(sequencer);
+
+    let (_, reposted) = start(&dir).await;
+    assert_eq!(reposted, 0);
+    let _ = std::fs::remove_dir_all(dir);
+}
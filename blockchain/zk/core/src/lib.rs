@@ -15,6 +15,17 @@ pub enum ProgramId {
     Custom(String),
 }
 
+impl std::fmt::Display for ProgramId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramId::Block => f.write_str("block"),
+            ProgramId::Rollup => f.write_str("rollup"),
+            ProgramId::PrivacyWithdraw => f.write_str("privacy_withdraw"),
+            ProgramId::Custom(name) => write!(f, "custom:{name}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Commitments {
     pub state_root: Option<Hash>,
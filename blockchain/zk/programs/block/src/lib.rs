@@ -1,6 +1,6 @@
 use anyhow::Result;
 use blake3::Hasher;
-use runtime::{Block, Hash};
+use runtime::{Block, Event, Hash};
 use serde::{Deserialize, Serialize};
 use zk_core::{Commitments, ProgramId};
 
@@ -18,7 +18,7 @@ pub struct BlockProgramOutput {
     pub gas_used: u64,
 }
 
-pub fn encode_witness(block: &Block, post_state_root: Hash, events: &[String], gas_used: u64) -> Result<Vec<u8>> {
+pub fn encode_witness(block: &Block, post_state_root: Hash, events: &[Event], gas_used: u64) -> Result<Vec<u8>> {
     let events_root = hash_events(events);
     let witness = BlockProgramWitness {
         block: block.clone(),
@@ -54,10 +54,12 @@ pub fn decode_output(bytes: &[u8]) -> Result<BlockProgramOutput> {
     })
 }
 
-pub fn hash_events(events: &[String]) -> Hash {
+pub fn hash_events(events: &[Event]) -> Hash {
     let mut hasher = Hasher::new();
     for e in events {
-        hasher.update(e.as_bytes());
+        // Attributes are a sorted map, so the encoding is canonical.
+        let bytes = bincode::serialize(e).expect("event encoding is infallible");
+        hasher.update(&bytes);
     }
     *hasher.finalize().as_bytes()
 }
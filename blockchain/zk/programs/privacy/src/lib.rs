@@ -3,6 +3,13 @@ use blake3::Hasher;
 use serde::{Deserialize, Serialize};
 use zk_core::{stub_proof, Commitments, ProgramId, ProofArtifact};
 
+mod memo;
+
+pub use memo::{
+    encrypt_note, EncryptedNote, NotePlaintext, ViewingKey, MAX_NOTE_CIPHERTEXT_LEN,
+    MAX_NOTE_MEMO_LEN,
+};
+
 pub type Hash = [u8; 32];
 
 /// Note commitment inputs for a shielded note.
@@ -22,6 +29,15 @@ pub struct PrivacyWithdrawInput {
     pub recipient: Hash,
     pub amount: u128,
     pub commitment: Hash,
+    /// Protocol fee withheld from `amount`; public so the proof binds it.
+    pub fee: u128,
+    /// Third party paid `relayer_fee` out of `amount` for submitting the
+    /// withdrawal, so the recipient never has to pay gas from a linked
+    /// account. Bound by the proof so a copied tx cannot redirect it.
+    #[serde(default)]
+    pub relayer: Option<Hash>,
+    #[serde(default)]
+    pub relayer_fee: u128,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -30,6 +46,9 @@ pub struct PrivacyWithdrawOutput {
     pub recipient: Hash,
     pub amount: u128,
     pub commitment: Hash,
+    pub fee: u128,
+    pub relayer: Option<Hash>,
+    pub relayer_fee: u128,
 }
 
 /// Deterministically encode witness for the privacy withdraw circuit.
@@ -38,16 +57,42 @@ pub fn encode_input(input: &PrivacyWithdrawInput) -> Result<Vec<u8>> {
 }
 
 /// Commitments attached to the circuit; state root mirrors Merkle root to
-/// anchor against the on-chain pool.
+/// anchor against the on-chain pool, events root binds the public inputs.
 pub fn commitments(input: &PrivacyWithdrawInput) -> Commitments {
     Commitments {
         state_root: Some(input.merkle_root),
         da_root: None,
-        events_root: Some(hash_bytes(&input.nullifier)),
+        events_root: Some(public_inputs_hash(input)),
         domain_root: Some(hash_bytes(&input.commitment)),
     }
 }
 
+/// Hash of the values a withdrawal reveals: nullifier, recipient, amount,
+/// fee, and the relayer with its fee.
+pub fn public_inputs_hash(input: &PrivacyWithdrawInput) -> Hash {
+    let mut h = Hasher::new();
+    h.update(&input.nullifier);
+    h.update(&input.recipient);
+    h.update(&input.amount.to_le_bytes());
+    h.update(&input.fee.to_le_bytes());
+    match &input.relayer {
+        Some(relayer) => {
+            h.update(&[1]);
+            h.update(relayer);
+        }
+        None => {
+            h.update(&[0]);
+        }
+    }
+    h.update(&input.relayer_fee.to_le_bytes());
+    *h.finalize().as_bytes()
+}
+
+/// Fee charged on a withdrawal of `amount` from a pool with `fee_bps`.
+pub fn withdraw_fee(amount: u128, fee_bps: u16) -> u128 {
+    amount.saturating_mul(fee_bps as u128) / 10_000
+}
+
 /// Decode public outputs emitted by a real prover path. In stub mode these
 /// bytes will be a deterministic hash; callers should gate on backend id when
 /// using this helper.
@@ -0,0 +1,125 @@
+//! Encrypted note memos and viewing keys.
+//!
+//! A deposit can carry its note encrypted to the owner's viewing public key
+//! (x25519 with a per-note ephemeral key, AES-256-GCM under a key derived
+//! from the shared secret). Whoever holds the viewing key can scan blocks,
+//! decrypt the notes sent to it and rebuild the shielded balance, while the
+//! spend key it was derived from never leaves the wallet.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::{note_commitment, Hash};
+
+/// Upper bound on a memo ciphertext, free-text memo included.
+pub const MAX_NOTE_CIPHERTEXT_LEN: usize = 512;
+/// Longest free-text memo a note may carry.
+pub const MAX_NOTE_MEMO_LEN: usize = 256;
+
+const VIEWING_KEY_CONTEXT: &str = "kova privacy viewing key v1";
+const EPHEMERAL_KEY_CONTEXT: &str = "kova privacy note ephemeral key v1";
+const NOTE_KEY_CONTEXT: &str = "kova privacy note encryption v1";
+
+/// Everything needed to recognise and later spend a note.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NotePlaintext {
+    pub nullifier: Hash,
+    pub recipient: Hash,
+    pub amount: u128,
+    pub salt: Hash,
+    #[serde(default)]
+    pub memo: Vec<u8>,
+}
+
+impl NotePlaintext {
+    pub fn commitment(&self) -> Hash {
+        note_commitment(&self.nullifier, &self.recipient, self.amount, &self.salt)
+    }
+}
+
+/// A note sealed to a viewing public key, as carried by a deposit.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EncryptedNote {
+    pub ephemeral_key: Hash,
+    pub ciphertext: Vec<u8>,
+}
+
+#[derive(Clone)]
+pub struct ViewingKey {
+    secret: StaticSecret,
+}
+
+impl ViewingKey {
+    /// Derives the viewing key belonging to a spend key, so one backup
+    /// covers both.
+    pub fn from_spend_key(spend_key: &[u8; 32]) -> Self {
+        Self::from_bytes(blake3::derive_key(VIEWING_KEY_CONTEXT, spend_key))
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self {
+            secret: StaticSecret::from(bytes),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.secret.to_bytes()
+    }
+
+    /// What senders encrypt notes to.
+    pub fn public_key(&self) -> Hash {
+        PublicKey::from(&self.secret).to_bytes()
+    }
+
+    /// Opens `note` if it was sealed to this key. Notes whose plaintext does
+    /// not hash to `commitment` are rejected, so a memo cannot claim a
+    /// deposit it does not describe.
+    pub fn decrypt(&self, note: &EncryptedNote, commitment: &Hash) -> Option<NotePlaintext> {
+        let ephemeral = PublicKey::from(note.ephemeral_key);
+        let shared = self.secret.diffie_hellman(&ephemeral);
+        if !shared.was_contributory() {
+            return None;
+        }
+        let key = note_key(shared.as_bytes(), &note.ephemeral_key, &self.public_key());
+        let plaintext = Aes256Gcm::new(&key.into())
+            .decrypt(Nonce::from_slice(&[0u8; 12]), note.ciphertext.as_slice())
+            .ok()?;
+        let decoded: NotePlaintext = bincode::deserialize(&plaintext).ok()?;
+        (decoded.commitment() == *commitment).then_some(decoded)
+    }
+}
+
+/// Seals `note` to `viewing_public_key`. The ephemeral key is derived from
+/// the note itself, whose salt is secret, so every note gets its own key and
+/// the fixed AES-GCM nonce is never reused under one key.
+pub fn encrypt_note(viewing_public_key: &Hash, note: &NotePlaintext) -> Result<EncryptedNote> {
+    if note.memo.len() > MAX_NOTE_MEMO_LEN {
+        anyhow::bail!("note memo longer than {MAX_NOTE_MEMO_LEN} bytes");
+    }
+    let plaintext = bincode::serialize(note)?;
+    let ephemeral = StaticSecret::from(blake3::derive_key(EPHEMERAL_KEY_CONTEXT, &plaintext));
+    let ephemeral_key = PublicKey::from(&ephemeral).to_bytes();
+    let shared = ephemeral.diffie_hellman(&PublicKey::from(*viewing_public_key));
+    if !shared.was_contributory() {
+        anyhow::bail!("invalid viewing public key");
+    }
+    let key = note_key(shared.as_bytes(), &ephemeral_key, viewing_public_key);
+    let ciphertext = Aes256Gcm::new(&key.into())
+        .encrypt(Nonce::from_slice(&[0u8; 12]), plaintext.as_slice())
+        .map_err(|_| anyhow::anyhow!("encrypting note"))?;
+    Ok(EncryptedNote {
+        ephemeral_key,
+        ciphertext,
+    })
+}
+
+fn note_key(shared: &[u8; 32], ephemeral_key: &Hash, viewing_public_key: &Hash) -> [u8; 32] {
+    let mut material = Vec::with_capacity(96);
+    material.extend_from_slice(shared);
+    material.extend_from_slice(ephemeral_key);
+    material.extend_from_slice(viewing_public_key);
+    blake3::derive_key(NOTE_KEY_CONTEXT, &material)
+}
@@ -1,9 +1,8 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use blake3::Hasher;
-use runtime::Hash;
 use uuid::Uuid;
-use serde::{Deserialize, Serialize};
-use zk_core::{Commitments, ProgramId};
+use serde::{de::DeserializeOwned, de::IgnoredAny, Deserialize, Serialize};
+use zk_core::{stub_proof, Commitments, Hash, ProgramId, ProofArtifact};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RollupProofInput {
@@ -14,6 +13,13 @@ pub struct RollupProofInput {
     pub batch_bytes: Vec<u8>,
 }
 
+impl RollupProofInput {
+    /// The batch's txs, decoded as the prover replays them.
+    pub fn txs<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        decode_batch(&self.batch_bytes)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RollupProofOutput {
     pub domain_id: Uuid,
@@ -22,6 +28,35 @@ pub struct RollupProofOutput {
     pub batch_commitment: Hash,
 }
 
+/// Leading byte of a MessagePack batch compressed with zstd. Batches posted
+/// before compression are a JSON array and start with `[`.
+pub const BATCH_FORMAT_V1: u8 = 1;
+/// Most bytes a batch may decompress to.
+pub const MAX_BATCH_BYTES: usize = 16 * 1024 * 1024;
+const BATCH_ZSTD_LEVEL: i32 = 3;
+
+/// Encodes `txs` as a batch blob.
+pub fn encode_batch<T: Serialize>(txs: &[T]) -> Result<Vec<u8>> {
+    let raw = rmp_serde::to_vec_named(txs)?;
+    if raw.len() > MAX_BATCH_BYTES {
+        bail!("batch exceeds {MAX_BATCH_BYTES} bytes");
+    }
+    let mut out = vec![BATCH_FORMAT_V1];
+    out.extend(zstd::bulk::compress(&raw, BATCH_ZSTD_LEVEL)?);
+    Ok(out)
+}
+
+pub fn decode_batch<T: DeserializeOwned>(bytes: &[u8]) -> Result<Vec<T>> {
+    match bytes.split_first() {
+        Some((&BATCH_FORMAT_V1, compressed)) => {
+            let raw = zstd::bulk::decompress(compressed, MAX_BATCH_BYTES)?;
+            Ok(rmp_serde::from_slice(&raw)?)
+        }
+        Some((b'[', _)) => Ok(serde_json::from_slice(bytes)?),
+        _ => bail!("unknown batch format"),
+    }
+}
+
 pub fn encode_input(input: &RollupProofInput) -> Result<Vec<u8>> {
     Ok(bincode::serialize(input)?)
 }
@@ -31,8 +66,40 @@ pub fn commitments(input: &RollupProofInput) -> Commitments {
         state_root: Some(input.state_root),
         da_root: Some(input.da_root),
         events_root: Some(hash_blob(&input.batch_bytes)),
-        domain_root: Some(hash_blob(&input.domain_id.as_bytes())),
+        domain_root: Some(hash_blob(input.domain_id.as_bytes())),
+    }
+}
+
+/// Checks that `artifact` commits to `state_root` and `da_root` for
+/// `domain_id` and returns the batch commitment it carries. The batch bytes
+/// stay in DA, so the batch commitment is taken from the proof.
+pub fn check_claim(
+    artifact: &ProofArtifact,
+    domain_id: Uuid,
+    state_root: Hash,
+    da_root: Hash,
+) -> Result<Hash> {
+    if artifact.program_id != program_id() {
+        bail!("invalid proof program id");
     }
+    let Some(c) = &artifact.commitments else {
+        bail!("rollup proof has no commitments");
+    };
+    if c.state_root != Some(state_root) || c.da_root != Some(da_root) {
+        bail!("proof does not commit to the claimed roots");
+    }
+    if c.domain_root != Some(hash_blob(domain_id.as_bytes())) {
+        bail!("proof is for another domain");
+    }
+    c.events_root
+        .ok_or_else(|| anyhow::anyhow!("rollup proof has no batch commitment"))
+}
+
+/// Convenience to build a stub artifact for environments without a prover.
+pub fn stub_batch_proof(input: &RollupProofInput) -> Result<ProofArtifact> {
+    input.txs::<IgnoredAny>()?;
+    let witness = encode_input(input)?;
+    Ok(stub_proof(program_id(), witness, Some(commitments(input))))
 }
 
 pub fn decode_output(bytes: &[u8]) -> Result<RollupProofOutput> {
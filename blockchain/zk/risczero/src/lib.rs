@@ -0,0 +1,196 @@
+//! RISC Zero backend. Programs are registered by image ID, the digest of the
+//! guest ELF that receipts are verified against, so a verify-only node needs
+//! the image IDs but not the ELFs. Real proving needs the `risc0` feature and
+//! the RISC Zero toolchain; without it the backend produces and checks stub
+//! proofs like the SP1 backend does.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use zk_core::{
+    Hash, ProgramDescriptor, ProgramId, ProgramRegistry, ProofArtifact, ProofRequest, ZkBackend,
+    ZkError, ZkResult,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Risc0Program {
+    pub id: ProgramId,
+    pub image_id: Hash,
+    /// Guest ELF; only needed to prove.
+    #[serde(default)]
+    pub elf: Vec<u8>,
+    pub name: String,
+    pub version: String,
+}
+
+impl Risc0Program {
+    /// Registers `elf` under the image ID computed from it.
+    #[cfg(feature = "risc0")]
+    pub fn from_elf(id: ProgramId, name: &str, version: &str, elf: Vec<u8>) -> ZkResult<Self> {
+        let digest = risc0_zkvm::compute_image_id(&elf)
+            .map_err(|e| ZkError::Other(format!("image id: {e}")))?;
+        let image_id: Hash = digest
+            .as_bytes()
+            .try_into()
+            .map_err(|_| ZkError::Other("image id must be 32 bytes".into()))?;
+        Ok(Self {
+            id,
+            image_id,
+            elf,
+            name: name.into(),
+            version: version.into(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Risc0Config {
+    pub programs: Vec<Risc0Program>,
+    pub verify_only: bool,
+}
+
+pub struct Risc0Backend {
+    programs: HashMap<ProgramId, Risc0Program>,
+    by_image: HashMap<Hash, ProgramId>,
+    registry: ProgramRegistry,
+    verify_only: bool,
+}
+
+impl Risc0Backend {
+    pub fn new(cfg: Risc0Config) -> ZkResult<Self> {
+        let mut registry = ProgramRegistry::new();
+        let mut programs = HashMap::new();
+        let mut by_image = HashMap::new();
+        for p in cfg.programs {
+            if let Some(other) = by_image.insert(p.image_id, p.id.clone()) {
+                return Err(ZkError::Other(format!(
+                    "image id {} registered for both {other:?} and {:?}",
+                    hex::encode(p.image_id),
+                    p.id
+                )));
+            }
+            registry.register(ProgramDescriptor {
+                id: p.id.clone(),
+                name: p.name.clone(),
+                description: format!("RISC Zero image {}", hex::encode(p.image_id)),
+                version: p.version.clone(),
+            });
+            programs.insert(p.id.clone(), p);
+        }
+        Ok(Self {
+            programs,
+            by_image,
+            registry,
+            verify_only: cfg.verify_only,
+        })
+    }
+
+    pub fn image_id(&self, id: &ProgramId) -> Option<Hash> {
+        self.programs.get(id).map(|p| p.image_id)
+    }
+
+    /// Program registered under `image_id`.
+    pub fn program_for_image(&self, image_id: &Hash) -> Option<&ProgramId> {
+        self.by_image.get(image_id)
+    }
+
+    fn ensure_program(&self, id: &ProgramId) -> ZkResult<&Risc0Program> {
+        self.programs
+            .get(id)
+            .ok_or_else(|| ZkError::UnknownProgram(id.clone()))
+    }
+}
+
+#[async_trait]
+impl ZkBackend for Risc0Backend {
+    fn backend_id(&self) -> &'static str {
+        "risc0"
+    }
+
+    fn registry(&self) -> &ProgramRegistry {
+        &self.registry
+    }
+
+    async fn prove(&self, request: ProofRequest) -> ZkResult<ProofArtifact> {
+        let program = self.ensure_program(&request.program_id)?;
+        if self.verify_only {
+            return Err(ZkError::BackendUnavailable(
+                "backend configured as verify-only".into(),
+            ));
+        }
+        if program.elf.is_empty() {
+            return Err(ZkError::BackendUnavailable(
+                "RISC Zero guest ELF not provided; set program elf bytes".into(),
+            ));
+        }
+
+        #[cfg(feature = "risc0")]
+        {
+            use risc0_zkvm::{default_prover, ExecutorEnv};
+
+            let env = ExecutorEnv::builder()
+                .write_slice(&request.witness)
+                .build()
+                .map_err(|e| ZkError::Other(format!("executor env: {e}")))?;
+            let receipt = default_prover()
+                .prove(env, &program.elf)
+                .map_err(|e| ZkError::Other(format!("prove failed: {e}")))?
+                .receipt;
+            Ok(ProofArtifact {
+                backend: self.backend_id().into(),
+                program_id: request.program_id,
+                public_outputs: receipt.journal.bytes.clone(),
+                proof: bincode::serialize(&receipt)
+                    .map_err(|e| ZkError::Other(format!("serialize receipt: {e}")))?,
+                commitments: request.commitments,
+                verification_key: Some(program.image_id.to_vec()),
+            })
+        }
+        #[cfg(not(feature = "risc0"))]
+        {
+            let mut artifact =
+                zk_core::stub_proof(request.program_id, request.witness, request.commitments);
+            artifact.verification_key = Some(program.image_id.to_vec());
+            Ok(artifact)
+        }
+    }
+
+    async fn verify(&self, artifact: &ProofArtifact) -> ZkResult<()> {
+        // The image ID comes from the registry; one carried by the artifact
+        // only has to agree with it.
+        let program = self.ensure_program(&artifact.program_id)?;
+        if let Some(vk) = &artifact.verification_key {
+            if vk.as_slice() != program.image_id.as_slice() {
+                return Err(ZkError::ProofRejected(
+                    "proof is for a different image id".into(),
+                ));
+            }
+        }
+
+        #[cfg(feature = "risc0")]
+        {
+            use risc0_zkvm::{sha::Digest, Receipt};
+
+            let receipt: Receipt = bincode::deserialize(&artifact.proof)
+                .map_err(|e| ZkError::ProofRejected(format!("receipt decode error: {e}")))?;
+            if receipt.journal.bytes != artifact.public_outputs {
+                return Err(ZkError::ProofRejected(
+                    "journal does not match public outputs".into(),
+                ));
+            }
+            receipt
+                .verify(Digest::from(program.image_id))
+                .map_err(|e| ZkError::ProofRejected(format!("verify failed: {e}")))
+        }
+        #[cfg(not(feature = "risc0"))]
+        {
+            // `stub_proof` uses the same digest as proof and output.
+            if artifact.proof.len() != 32 || artifact.proof != artifact.public_outputs {
+                return Err(ZkError::ProofRejected(
+                    "stub proof does not match its outputs".into(),
+                ));
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use zk_core::{ProgramId, ProofRequest, ZkBackend, ZkError};
+use zk_risczero::{Risc0Backend, Risc0Config, Risc0Program};
+
+fn program(id: ProgramId, image_id: u8) -> Risc0Program {
+    Risc0Program {
+        id,
+        image_id: [image_id; 32],
+        elf: vec![0x7f, b'E', b'L', b'F'],
+        name: "test".into(),
+        version: "0.1.0".into(),
+    }
+}
+
+#[tokio::test]
+async fn proofs_are_bound_to_the_registered_image() {
+    let backend = Risc0Backend::new(Risc0Config {
+        programs: vec![
+            program(ProgramId::PrivacyWithdraw, 1),
+            program(ProgramId::Rollup, 2),
+        ],
+        verify_only: false,
+    })
+    .unwrap();
+    assert_eq!(
+        backend.program_for_image(&[2u8; 32]),
+        Some(&ProgramId::Rollup)
+    );
+
+    let mut artifact = backend
+        .prove(ProofRequest {
+            program_id: ProgramId::PrivacyWithdraw,
+            witness: vec![1, 2, 3],
+            commitments: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(artifact.verification_key, Some(vec![1u8; 32]));
+    backend.verify(&artifact).await.unwrap();
+
+    artifact.verification_key = Some(vec![2u8; 32]);
+    assert!(matches!(
+        backend.verify(&artifact).await,
+        Err(ZkError::ProofRejected(_))
+    ));
+    artifact.program_id = ProgramId::Block;
+    assert!(matches!(
+        backend.verify(&artifact).await,
+        Err(ZkError::UnknownProgram(_))
+    ));
+}
+
+#[tokio::test]
+async fn image_ids_are_unique_and_verify_only_cannot_prove() {
+    let duplicate = Risc0Config {
+        programs: vec![
+            program(ProgramId::PrivacyWithdraw, 1),
+            program(ProgramId::Rollup, 1),
+        ],
+        verify_only: false,
+    };
+    assert!(Risc0Backend::new(duplicate).is_err());
+
+    let backend = Risc0Backend::new(Risc0Config {
+        programs: vec![program(ProgramId::Rollup, 2)],
+        verify_only: true,
+    })
+    .unwrap();
+    let err = backend
+        .prove(ProofRequest {
+            program_id: ProgramId::Rollup,
+            witness: vec![],
+            commitments: None,
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ZkError::BackendUnavailable(_)));
+}